@@ -17,6 +17,9 @@ if ! id hey &>/dev/null; then
     useradd -m -G wheel,video,audio,input,seat -s /bin/bash hey
 fi
 
+# The switch-user login screen runs as greetd's own account
+usermod -aG seat,video,input greeter
+
 # Set passwords safely using standard chpasswd
 echo 'hey:hey' | chpasswd
 echo 'root:heyos' | chpasswd
@@ -25,6 +28,8 @@ echo 'root:heyos' | chpasswd
 systemctl enable NetworkManager.service 2>/dev/null || true
 systemctl enable vmtoolsd.service 2>/dev/null || true
 systemctl enable seatd.service 2>/dev/null || true
+systemctl enable bluetooth.service 2>/dev/null || true
+systemctl enable power-profiles-daemon.service 2>/dev/null || true
 # Set multi-user target as default (CLI boot)
 systemctl set-default multi-user.target
 
@@ -111,6 +111,8 @@ if [[ "$SCRIPT_DIR" == /mnt/* ]]; then
         --exclude='.git/' \
         --exclude='heydm/target/' \
         --exclude='heygreeter/target/' \
+        --exclude='heywelcome/target/' \
+        --exclude='heycommon/target/' \
         "$SCRIPT_DIR/" "$NATIVE_BUILD_DIR/"
 
     log_ok "Project synced to $NATIVE_BUILD_DIR"
@@ -205,19 +207,27 @@ build_rust() {
 
 log_step "Step 2 & 3: Compiling Rust components (Parallel: ${PARALLEL_JOBS} jobs/task)"
 
+# Shared library crate the components depend on by path (../heycommon)
+mkdir -p "${BUILD_TMP}/heycommon"
+rsync -a --delete --exclude='target/' "${SCRIPT_DIR}/heycommon/" "${BUILD_TMP}/heycommon/"
+
 # Launch builds in background if both are needed
 if ! $GREETER_ONLY && ! $HEYDM_ONLY; then
     build_rust "heyDM" "heydm" "heydm" true &
     PID_HEYDM=$!
     build_rust "hey-greeter" "heygreeter" "hey-greeter" true &
     PID_GREETER=$!
+    build_rust "hey-welcome" "heywelcome" "hey-welcome" true &
+    PID_WELCOME=$!
     
     wait "$PID_HEYDM" || { log_err "heyDM build failed"; exit 1; }
     wait "$PID_GREETER" || { log_err "hey-greeter build failed"; exit 1; }
+    wait "$PID_WELCOME" || { log_err "hey-welcome build failed"; exit 1; }
 else
     # Sequential build if only one requested (use all cores)
     if ! $GREETER_ONLY; then
         build_rust "heyDM" "heydm" "heydm" false
+        build_rust "hey-welcome" "heywelcome" "hey-welcome" false
     fi
     if ! $HEYDM_ONLY; then
         build_rust "hey-greeter" "heygreeter" "hey-greeter" false
@@ -243,21 +253,25 @@ write_greetd_config() {
 [terminal]
 vt = 1
 [default_session]
-command = "env WLR_RENDERER=pixman WLR_NO_HARDWARE_CURSORS=1 $cmd"
+command = "$cmd"
 user = "hey"
 EOF
 }
 
 if $GREETER_ONLY; then
     cp "${BUILD_TMP}/heygreeter/target/release/hey-greeter" "${AIROOTFS}/usr/bin/hey-greeter"
-    write_greetd_config "cage -s -- /usr/bin/hey-greeter"
+    write_greetd_config "/usr/bin/hey-greeter --launch"
 elif $HEYDM_ONLY; then
     cp "${BUILD_TMP}/heydm/target/release/heydm" "${AIROOTFS}/usr/bin/heydm"
-    write_greetd_config "cage -s -- /usr/bin/heydm"
+    ln -sf heydm "${AIROOTFS}/usr/bin/heyctl"
+    cp "${BUILD_TMP}/heywelcome/target/release/hey-welcome" "${AIROOTFS}/usr/bin/hey-welcome"
+    write_greetd_config "env WLR_RENDERER=pixman WLR_NO_HARDWARE_CURSORS=1 cage -s -- /usr/bin/heydm"
 else
     cp "${BUILD_TMP}/heydm/target/release/heydm" "${AIROOTFS}/usr/bin/heydm"
+    ln -sf heydm "${AIROOTFS}/usr/bin/heyctl"
     cp "${BUILD_TMP}/heygreeter/target/release/hey-greeter" "${AIROOTFS}/usr/bin/hey-greeter"
-    write_greetd_config "cage -s -- /usr/bin/hey-greeter"
+    cp "${BUILD_TMP}/heywelcome/target/release/hey-welcome" "${AIROOTFS}/usr/bin/hey-welcome"
+    write_greetd_config "/usr/bin/hey-greeter --launch"
 fi
 
 log_ok "Binaries deployed"
@@ -270,6 +284,7 @@ log_step "Step 5: Permissions & File Normalization"
 
 chmod 755 "${AIROOTFS}/usr/bin/heydm" 2>/dev/null || true
 chmod 755 "${AIROOTFS}/usr/bin/hey-greeter" 2>/dev/null || true
+chmod 755 "${AIROOTFS}/usr/bin/hey-welcome" 2>/dev/null || true
 chmod 755 "${AIROOTFS}/usr/local/bin/hey-install"
 chmod 755 "${AIROOTFS}/root/customize_airootfs.sh"
 chmod 440 "${AIROOTFS}/etc/sudoers.d/00-heyos" 2>/dev/null || true
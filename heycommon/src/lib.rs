@@ -0,0 +1,4 @@
+//! Code shared by the heyOS desktop components (heyDM, hey-greeter,
+//! hey-welcome).
+
+pub mod pam;
@@ -0,0 +1,41 @@
+//! Password checks through PAM.
+//!
+//! Only the auth and account stacks of the service run: no PAM session is
+//! opened, so callers that merely verify a password (lock screens) should
+//! use a service with just those lines, such as /etc/pam.d/heydm-lock.
+//! Calls block (PAM may sleep after a failure), so keep them off UI
+//! threads.
+
+use std::fmt;
+
+/// Why a password check did not succeed
+#[derive(Debug)]
+pub enum Error {
+    /// The PAM service could not be started (missing or broken config)
+    Init(String),
+    /// The password was wrong or the account is not allowed in
+    Denied(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Init(reason) => write!(f, "PAM initialization failed: {reason}"),
+            Self::Denied(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Verify `user`'s password with the PAM `service` (auth, then account)
+pub fn authenticate(service: &str, user: &str, password: &str) -> Result<(), Error> {
+    let mut authenticator = ::pam::Authenticator::with_password(service)
+        .map_err(|e| Error::Init(format!("{e:?}")))?;
+    authenticator
+        .get_handler()
+        .set_credentials(user, password);
+    authenticator
+        .authenticate()
+        .map_err(|e| Error::Denied(format!("{e:?}")))
+}
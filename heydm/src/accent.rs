@@ -0,0 +1,118 @@
+// =============================================================================
+// heyDM — Accent Color Extraction
+//
+// Picks a dominant, usable accent color from a (downscaled) wallpaper:
+//   1. k-means clusters the pixels in RGB space
+//   2. Clusters are scored by size and saturation; near-black, near-white
+//      and gray clusters only win when nothing colorful exists
+//   3. The winner is nudged into a saturation / lightness range that reads
+//      well on the dark desktop theme
+// =============================================================================
+
+/// Number of clusters
+const K: usize = 5;
+
+/// k-means refinement rounds (the sample is small, this converges quickly)
+const ITERATIONS: usize = 8;
+
+/// Dominant accent color of the given pixels as RGB in 0.0..=1.0
+pub fn dominant_color(pixels: &[[u8; 3]]) -> Option<[f32; 3]> {
+    if pixels.is_empty() {
+        return None;
+    }
+    let points: Vec<[f32; 3]> = pixels
+        .iter()
+        .map(|p| [p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0])
+        .collect();
+
+    // Deterministic seeds spread over the image
+    let k = K.min(points.len());
+    let mut centroids: Vec<[f32; 3]> = (0..k).map(|i| points[i * points.len() / k]).collect();
+    let mut counts = vec![0usize; k];
+
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![[0.0f32; 3]; k];
+        counts.iter_mut().for_each(|c| *c = 0);
+
+        for point in &points {
+            let cluster = nearest(&centroids, point);
+            counts[cluster] += 1;
+            for (sum, value) in sums[cluster].iter_mut().zip(point) {
+                *sum += value;
+            }
+        }
+        for (i, centroid) in centroids.iter_mut().enumerate() {
+            if counts[i] > 0 {
+                for (value, sum) in centroid.iter_mut().zip(sums[i]) {
+                    *value = sum / counts[i] as f32;
+                }
+            }
+        }
+    }
+
+    let score = |i: usize| {
+        let (_, s, l) = to_hsl(centroids[i]);
+        let usable = s >= 0.15 && (0.12..=0.92).contains(&l);
+        let weight = counts[i] as f32 * (0.3 + s);
+        (usable, weight)
+    };
+    let best = (0..k)
+        .filter(|i| counts[*i] > 0)
+        .max_by(|a, b| {
+            let (usable_a, weight_a) = score(*a);
+            let (usable_b, weight_b) = score(*b);
+            usable_a.cmp(&usable_b).then(weight_a.total_cmp(&weight_b))
+        })?;
+
+    let (h, s, l) = to_hsl(centroids[best]);
+    Some(from_hsl(h, s.max(0.45), l.clamp(0.45, 0.65)))
+}
+
+/// Index of the centroid closest to `point`
+fn nearest(centroids: &[[f32; 3]], point: &[f32; 3]) -> usize {
+    let distance = |c: &[f32; 3]| (0..3).map(|i| (c[i] - point[i]).powi(2)).sum::<f32>();
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+/// RGB → (hue 0..1, saturation, lightness)
+fn to_hsl([r, g, b]: [f32; 3]) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d <= f32::EPSILON {
+        return (0.0, 0.0, l);
+    }
+
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s.min(1.0), l)
+}
+
+/// (hue 0..1, saturation, lightness) → RGB
+fn from_hsl(h: f32, s: f32, l: f32) -> [f32; 3] {
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h6 = h * 6.0;
+    let x = c * (1.0 - (h6.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h6 as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = l - c / 2.0;
+    [r + m, g + m, b + m]
+}
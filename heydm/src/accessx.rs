@@ -0,0 +1,229 @@
+// =============================================================================
+// heyDM — Keyboard Accessibility
+//
+// Key filters for users who find the keyboard hard to operate, named after
+// X11's AccessX:
+//   - Sticky keys: a modifier pressed and released on its own stays down
+//     for the next key, so Super then D opens the launcher. Tapping it
+//     again locks it until a third tap; a click also uses it up.
+//   - Slow keys: a key only counts once it has been held this long, so
+//     brushing against keys types nothing
+//   - Bounce keys: a key pressed again this soon after its release is
+//     ignored, for tremors that hit a key twice
+//
+//     [accessibility]
+//     sticky_keys = true
+//     slow_keys = 300              # milliseconds, 0 = off
+//     bounce_keys = 0              # milliseconds, 0 = off
+//
+// The filters sit in front of seat0's keyboard (see InputHandler::key), so
+// compositor bindings and clients see the keys they let through.
+// =============================================================================
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Longest slow keys or bounce keys delay
+pub const MAX_KEY_DELAY_MS: i64 = 2000;
+
+/// XKB keycodes (evdev + 8) of Ctrl, Shift, Alt and Super, left and right
+const MODIFIER_KEYCODES: &[u32] = &[37, 50, 62, 64, 105, 108, 133, 134];
+
+/// `[accessibility]` settings from the config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessibilityConfig {
+    pub sticky_keys: bool,
+    /// How long a key must be held to count; zero is off
+    pub slow_keys: Duration,
+    /// How soon after a release a press is ignored; zero is off
+    pub bounce_keys: Duration,
+}
+
+impl AccessibilityConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let delay = |value: &str| {
+            let ms: u64 = value
+                .parse()
+                .map_err(|_| format!("invalid {key} delay '{value}'"))?;
+            Ok::<_, String>(Duration::from_millis(ms.min(MAX_KEY_DELAY_MS as u64)))
+        };
+        match key {
+            "sticky_keys" => {
+                self.sticky_keys = match value {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("expected true or false, got '{value}'")),
+                }
+            }
+            "slow_keys" => self.slow_keys = delay(value)?,
+            "bounce_keys" => self.bounce_keys = delay(value)?,
+            _ => return Err(format!("unknown accessibility setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// What to do with a key press
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Press {
+    Forward,
+    Drop,
+    /// Slow keys: forward it if `KeyAccess::slow_key_held` says so after
+    /// this long
+    After(Duration, u64),
+}
+
+/// What to do with a key release
+#[derive(Debug, Default)]
+pub struct Release {
+    pub forward: bool,
+    /// Sticky modifiers to release after it
+    pub then: Vec<u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct KeyAccess {
+    config: AccessibilityConfig,
+    /// Keys whose press was dropped, so their release is too
+    dropped: HashSet<u32>,
+    /// When each key was last released, for bounce keys
+    released: HashMap<u32, Instant>,
+    /// Keys held but not yet long enough for slow keys
+    slow: HashMap<u32, u64>,
+    generation: u64,
+    /// Sticky modifiers held down for the next key
+    latched: HashSet<u32>,
+    /// Sticky modifiers held down until tapped again
+    locked: HashSet<u32>,
+    /// Sticky modifiers physically held while already down
+    held: HashSet<u32>,
+    /// The modifier pressed with no other key since
+    tap: Option<u32>,
+}
+
+impl KeyAccess {
+    pub fn new(config: &AccessibilityConfig) -> Self {
+        Self {
+            config: config.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Apply reloaded settings. Returns the modifiers to release when
+    /// sticky keys was switched off.
+    pub fn set_config(&mut self, config: &AccessibilityConfig) -> Vec<u32> {
+        self.config = config.clone();
+        if config.sticky_keys {
+            return Vec::new();
+        }
+        self.tap = None;
+        self.held.clear();
+        self.latched.drain().chain(self.locked.drain()).collect()
+    }
+
+    pub fn press(&mut self, code: u32) -> Press {
+        let bounce = self.config.bounce_keys;
+        let bounced = self
+            .released
+            .get(&code)
+            .is_some_and(|released| released.elapsed() < bounce);
+        if bounced {
+            self.dropped.insert(code);
+            return Press::Drop;
+        }
+        if !self.config.slow_keys.is_zero() {
+            self.generation += 1;
+            self.slow.insert(code, self.generation);
+            return Press::After(self.config.slow_keys, self.generation);
+        }
+        self.sticky_press(code)
+    }
+
+    /// Slow keys' delay for a press is over; true if the key is still down
+    /// and should be forwarded now
+    pub fn slow_key_held(&mut self, code: u32, generation: u64) -> bool {
+        if self.slow.get(&code) != Some(&generation) {
+            return false;
+        }
+        self.slow.remove(&code);
+        self.sticky_press(code) == Press::Forward
+    }
+
+    pub fn release(&mut self, code: u32) -> Release {
+        if !self.config.bounce_keys.is_zero() {
+            self.released.insert(code, Instant::now());
+        }
+        if self.dropped.remove(&code) || self.slow.remove(&code).is_some() {
+            return Release::default();
+        }
+        if !self.config.sticky_keys {
+            return Release {
+                forward: true,
+                then: Vec::new(),
+            };
+        }
+        if !MODIFIER_KEYCODES.contains(&code) {
+            return Release {
+                forward: true,
+                then: self.release_latched(),
+            };
+        }
+
+        let tapped = self.tap.take() == Some(code);
+        self.held.remove(&code);
+        let forward = if self.locked.contains(&code) {
+            if tapped {
+                info!("Sticky modifier released");
+                self.locked.remove(&code);
+            }
+            tapped
+        } else if self.latched.contains(&code) {
+            if tapped {
+                info!("Sticky modifier locked");
+                self.latched.remove(&code);
+                self.locked.insert(code);
+            }
+            false
+        } else if tapped {
+            self.latched.insert(code);
+            false
+        } else {
+            // Pressed with another key, or used up while held
+            true
+        };
+        Release {
+            forward,
+            then: Vec::new(),
+        }
+    }
+
+    /// Latched modifiers used up by a key or click, except those still
+    /// physically held (their own release lets go of them)
+    pub fn release_latched(&mut self) -> Vec<u32> {
+        let free = self
+            .latched
+            .iter()
+            .filter(|code| !self.held.contains(code))
+            .copied()
+            .collect();
+        self.latched.clear();
+        free
+    }
+
+    fn sticky_press(&mut self, code: u32) -> Press {
+        if !self.config.sticky_keys || !MODIFIER_KEYCODES.contains(&code) {
+            self.tap = None;
+            return Press::Forward;
+        }
+        self.tap = Some(code);
+        // Already down for sticky keys: this press only counts as a tap
+        if self.latched.contains(&code) || self.locked.contains(&code) {
+            self.held.insert(code);
+            return Press::Drop;
+        }
+        Press::Forward
+    }
+}
@@ -0,0 +1,110 @@
+// =============================================================================
+// heyDM — Application Directory Watcher
+//
+// Watches the XDG application directories (see launcher::application_dirs)
+// with inotify so the launcher picks up installed, updated and removed apps
+// while heyDM runs. Each changed .desktop file is re-parsed on its own; if
+// the kernel's event queue overflows, everything is rescanned. Icons of new
+// apps go to the icon loader like the ones found at startup.
+//
+// Directories that do not exist at startup are not watched.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use calloop::channel::Sender;
+use calloop::generic::Generic;
+use calloop::{Interest, LoopHandle, Mode, PostAction};
+use nix::errno::Errno;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
+use tracing::{debug, info, warn};
+
+use crate::icons::{self, LoadedIcon};
+use crate::state::HeyDM;
+
+/// Start watching; the launcher works without it, so failures are only
+/// logged
+pub fn watch(
+    loop_handle: &LoopHandle<'static, HeyDM>,
+    icon_theme: Option<String>,
+    icon_size: u32,
+    icon_results: Sender<LoadedIcon>,
+) {
+    let inotify = match Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC) {
+        Ok(inotify) => inotify,
+        Err(e) => {
+            warn!("Cannot watch application directories: {e}");
+            return;
+        }
+    };
+
+    let flags = AddWatchFlags::IN_CLOSE_WRITE
+        | AddWatchFlags::IN_CREATE
+        | AddWatchFlags::IN_DELETE
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_MOVED_TO;
+    let mut dirs: HashMap<WatchDescriptor, PathBuf> = HashMap::new();
+    for dir in crate::launcher::application_dirs() {
+        if !dir.is_dir() {
+            continue;
+        }
+        match inotify.add_watch(&dir, flags) {
+            Ok(wd) => {
+                dirs.insert(wd, dir);
+            }
+            Err(e) => warn!("Cannot watch {}: {e}", dir.display()),
+        }
+    }
+    if dirs.is_empty() {
+        return;
+    }
+    info!("Watching {} application directories", dirs.len());
+
+    let inserted = loop_handle.insert_source(
+        Generic::new(inotify, Interest::READ, Mode::Level),
+        move |_, inotify, state| {
+            let events = match inotify.as_ref().read_events() {
+                Ok(events) => events,
+                Err(Errno::EAGAIN) => return Ok(PostAction::Continue),
+                Err(e) => {
+                    warn!("Reading application directory events failed: {e}");
+                    return Ok(PostAction::Continue);
+                }
+            };
+
+            let overflow = events
+                .iter()
+                .any(|event| event.mask.contains(AddWatchFlags::IN_Q_OVERFLOW));
+            let mut missing_icons = if overflow {
+                state.launcher.rescan()
+            } else {
+                // A package manager touches a file several times in one batch
+                let mut changed: Vec<PathBuf> = events
+                    .iter()
+                    .filter_map(|event| Some(dirs.get(&event.wd)?.join(event.name.as_ref()?)))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+                    .collect();
+                changed.sort();
+                changed.dedup();
+                changed
+                    .iter()
+                    .filter_map(|path| {
+                        debug!("Application changed: {}", path.display());
+                        state.launcher.reload_desktop_file(path)
+                    })
+                    .collect()
+            };
+
+            missing_icons.sort();
+            missing_icons.dedup();
+            if !missing_icons.is_empty() {
+                icons::load_all(missing_icons, icon_theme.clone(), icon_size, icon_results.clone());
+            }
+            Ok(PostAction::Continue)
+        },
+    );
+    if let Err(e) = inserted {
+        warn!("Cannot watch application directories: {e}");
+    }
+}
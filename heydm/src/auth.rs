@@ -0,0 +1,33 @@
+// =============================================================================
+// heyDM — PAM Authentication
+//
+// The built-in lock screen verifies the session user's password through the
+// shared PAM wrapper (hey-common) with its own service, /etc/pam.d/heydm-lock,
+// which has only auth and account lines: the full `login` stack would also
+// run session checks such as pam_securetty, pam_nologin and pam_lastlog.
+// Calls block (PAM may sleep after a failure), so they must run off the
+// compositor thread.
+// =============================================================================
+
+use tracing::warn;
+
+/// PAM service used to verify passwords
+const PAM_SERVICE: &str = "heydm-lock";
+
+/// Verify a user's password against PAM
+pub fn authenticate(user: &str, password: &str) -> Result<(), String> {
+    hey_common::pam::authenticate(PAM_SERVICE, user, password).map_err(|e| {
+        warn!("PAM authentication failed for '{user}': {e}");
+        e.to_string()
+    })
+}
+
+/// Name of the user running the compositor
+pub fn current_user() -> String {
+    nix::unistd::User::from_uid(nix::unistd::getuid())
+        .ok()
+        .flatten()
+        .map(|user| user.name)
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default()
+}
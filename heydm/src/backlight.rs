@@ -0,0 +1,142 @@
+// =============================================================================
+// heyDM — Backlight
+//
+// Reads and changes the screen brightness of the first backlight device in
+// /sys/class/backlight (firmware interfaces preferred over platform and raw
+// ones, like systemd-backlight does). The XF86MonBrightnessUp / Down keys
+// change it in BRIGHTNESS_STEP steps (see input.rs); the panel shows the
+// level and an on-screen indicator appears on every change (see osd.rs).
+//
+// The brightness file is usually only writable by root, so when writing it
+// fails heyDM asks logind (Session.SetBrightness), which allows the active
+// session to set it. Both happen on the "heydm-backlight" thread; the new
+// level goes back through the panel's update channel (see poller.rs).
+// =============================================================================
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+use tracing::{debug, warn};
+
+use crate::poller::PanelUpdate;
+
+/// Percent added or removed per key press
+pub const BRIGHTNESS_STEP: i32 = 5;
+
+/// Label of the on-screen indicator
+pub const BRIGHTNESS_LABEL: &str = "Brightness";
+
+/// Lowest level reachable with the keys, so the screen never goes black
+const MIN_BRIGHTNESS: i32 = 1;
+
+const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+/// Handle to the backlight thread
+pub struct BacklightControl {
+    commands: Sender<i32>,
+}
+
+impl BacklightControl {
+    /// Start the backlight thread; the level after every change is sent to
+    /// `updates`
+    pub fn start(updates: calloop::channel::Sender<PanelUpdate>) -> Self {
+        let (commands, receiver) = mpsc::channel::<i32>();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-backlight".to_string())
+            .spawn(move || {
+                for delta in receiver {
+                    let Some(device) = device() else {
+                        debug!("No backlight device to change");
+                        continue;
+                    };
+                    if let Err(e) = change(&device, delta) {
+                        warn!("Changing the brightness failed: {e}");
+                    }
+                    if updates.send(PanelUpdate::Brightness(read())).is_err() {
+                        return;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start backlight thread: {e}");
+        }
+        Self { commands }
+    }
+
+    /// Without the backlight thread (greeter mode); changes are dropped
+    pub fn disabled() -> Self {
+        Self {
+            commands: mpsc::channel().0,
+        }
+    }
+
+    /// Raise (positive) or lower (negative) the brightness by `delta` percent
+    pub fn change(&self, delta: i32) {
+        let _ = self.commands.send(delta);
+    }
+}
+
+/// Current brightness in percent, -1 without a backlight device
+pub fn read() -> i32 {
+    device()
+        .and_then(|device| {
+            let (current, max) = levels(&device)?;
+            Some(((current as f64 / max as f64) * 100.0).round() as i32)
+        })
+        .unwrap_or(-1)
+}
+
+/// The backlight device to use, by interface type
+fn device() -> Option<PathBuf> {
+    let rank = |path: &Path| match fs::read_to_string(path.join("type")).as_deref().map(str::trim) {
+        Ok("firmware") => 0,
+        Ok("platform") => 1,
+        _ => 2,
+    };
+    let mut devices: Vec<PathBuf> = fs::read_dir(BACKLIGHT_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    devices.sort_by_key(|path| (rank(path), path.clone()));
+    devices.into_iter().next()
+}
+
+/// Current and maximum raw brightness
+fn levels(device: &Path) -> Option<(u32, u32)> {
+    let read = |name: &str| fs::read_to_string(device.join(name)).ok()?.trim().parse::<u32>().ok();
+    let max = read("max_brightness").filter(|max| *max > 0)?;
+    Some((read("brightness")?, max))
+}
+
+fn change(device: &Path, delta: i32) -> Result<(), String> {
+    let (current, max) = levels(device).ok_or("cannot read the brightness")?;
+    let percent = ((current as f64 / max as f64) * 100.0).round() as i32;
+    let percent = (percent + delta).clamp(MIN_BRIGHTNESS, 100);
+    let value = ((percent as f64 / 100.0) * max as f64).round().max(1.0) as u32;
+
+    match fs::write(device.join("brightness"), value.to_string()) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            let name = device
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or("invalid backlight device name")?;
+            logind_set_brightness(name, value).map_err(|e| e.to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Set the brightness through logind, which allows it for the active session
+fn logind_set_brightness(name: &str, value: u32) -> zbus::Result<()> {
+    let connection = zbus::blocking::Connection::system()?;
+    let session = zbus::blocking::Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session",
+    )?;
+    session.call::<_, _, ()>("SetBrightness", &("backlight", name, value))
+}
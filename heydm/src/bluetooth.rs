@@ -0,0 +1,381 @@
+// =============================================================================
+// heyDM — Bluetooth
+//
+// Follows BlueZ over the system bus for the panel's Bluetooth module: whether
+// the adapter is powered and which devices are connected (highlighted in the
+// accent color, named in the tooltip). BlueZ signals every change, so
+// nothing is polled; without BlueZ or an adapter the module is hidden.
+//
+// Clicking the module opens a quick menu below it: the first row switches
+// the adapter on or off, the others list the paired devices, connected ones
+// first with their battery level when they report one (org.bluez.Battery1).
+// Clicking a device connects it, or disconnects it when it is connected.
+// Pairing new devices is left to a full settings app.
+//
+// As in network.rs, D-Bus calls happen on the "heydm-bluetooth" thread and
+// a helper thread turns BlueZ's signals into work for it. The state goes to
+// the panel's update channel (see poller.rs).
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use smithay::utils::{Physical, Rectangle, Size};
+use tracing::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+use crate::poller::PanelUpdate;
+use crate::render::bluetooth_rect;
+use crate::theme::UiMetrics;
+
+/// Device rows in the quick menu
+const MAX_DEVICES: usize = 8;
+
+const BLUEZ: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+/// A paired device, as listed in the quick menu
+#[derive(Debug, Clone, PartialEq)]
+pub struct BluetoothDevice {
+    pub name: String,
+    pub connected: bool,
+    /// Battery level in percent, for devices that report it
+    pub battery: Option<u8>,
+    path: String,
+}
+
+/// What the panel shows about Bluetooth
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BluetoothState {
+    /// Whether there is an adapter; the module is hidden without one
+    pub available: bool,
+    pub powered: bool,
+    /// Paired devices, connected ones first
+    pub devices: Vec<BluetoothDevice>,
+}
+
+impl BluetoothState {
+    pub fn connected(&self) -> impl Iterator<Item = &BluetoothDevice> {
+        self.devices.iter().filter(|device| device.connected)
+    }
+}
+
+/// From the compositor (and the signal thread) to the Bluetooth thread
+#[derive(Debug)]
+enum BluetoothCommand {
+    /// BlueZ changed something; re-read the state
+    Changed,
+    SetPowered(bool),
+    Connect(String),
+    Disconnect(String),
+}
+
+/// The Bluetooth quick menu, on the compositor side
+pub struct BluetoothMenu {
+    open: bool,
+    state: BluetoothState,
+    commands: Sender<BluetoothCommand>,
+    ui: UiMetrics,
+}
+
+#[allow(dead_code)]
+impl BluetoothMenu {
+    /// Start following BlueZ; the state goes to `updates`
+    pub fn start(updates: calloop::channel::Sender<PanelUpdate>, ui: UiMetrics) -> Self {
+        let (commands, receiver) = mpsc::channel::<BluetoothCommand>();
+        let worker_commands = commands.clone();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-bluetooth".to_string())
+            .spawn(move || match Client::connect(worker_commands, updates) {
+                Ok(client) => {
+                    info!("Following BlueZ");
+                    client.run(receiver);
+                }
+                Err(e) => info!("BlueZ unavailable ({e}), hiding the Bluetooth module"),
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start Bluetooth thread: {e}");
+        }
+        Self {
+            open: false,
+            state: BluetoothState::default(),
+            commands,
+            ui,
+        }
+    }
+
+    /// Without following BlueZ (greeter mode)
+    pub fn disabled(ui: UiMetrics) -> Self {
+        Self {
+            open: false,
+            state: BluetoothState::default(),
+            commands: mpsc::channel().0,
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    /// A new state from the Bluetooth thread
+    pub fn apply(&mut self, state: BluetoothState) {
+        if !state.available {
+            self.open = false;
+        }
+        self.state = state;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn state(&self) -> &BluetoothState {
+        &self.state
+    }
+
+    /// Devices shown in the menu, below the power row
+    pub fn devices(&self) -> &[BluetoothDevice] {
+        &self.state.devices[..self.state.devices.len().min(MAX_DEVICES)]
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open && self.state.available;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Where the menu is drawn: below the Bluetooth module, inside the
+    /// output
+    pub fn rect(&self, output_size: Size<i32, Physical>) -> Rectangle<i32, Physical> {
+        let module = bluetooth_rect(output_size, &self.ui);
+        let w = self.ui.px(260);
+        let rows = 1 + self.devices().len() as i32;
+        let h = self.row_height() * rows + self.ui.px(8);
+        let x = (module.loc.x + module.size.w - w).clamp(0, (output_size.w - w).max(0));
+        Rectangle::new((x, self.ui.panel_bottom() + self.ui.px(4)).into(), (w, h).into())
+    }
+
+    pub fn row_height(&self) -> i32 {
+        self.ui.px(28)
+    }
+
+    /// Handle a button press while the menu is open; returns true if it
+    /// was consumed. Any click closes the menu.
+    pub fn handle_press(&mut self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        if !self.open {
+            return false;
+        }
+        let point = (pos.0 as i32, pos.1 as i32);
+        let area = self.rect(output_size);
+        if area.contains(point) {
+            let row = (point.1 - area.loc.y - self.ui.px(4)) / self.row_height();
+            match row {
+                0 => {
+                    let powered = !self.state.powered;
+                    info!("Switching Bluetooth {}", if powered { "on" } else { "off" });
+                    self.send(BluetoothCommand::SetPowered(powered));
+                }
+                row => {
+                    let device = usize::try_from(row - 1).ok().and_then(|row| self.devices().get(row));
+                    if let Some(device) = device {
+                        let command = if device.connected {
+                            info!("Disconnecting Bluetooth device {}", device.name);
+                            BluetoothCommand::Disconnect(device.path.clone())
+                        } else {
+                            info!("Connecting Bluetooth device {}", device.name);
+                            BluetoothCommand::Connect(device.path.clone())
+                        };
+                        self.send(command);
+                    }
+                }
+            }
+            self.close();
+            return true;
+        }
+        self.close();
+        // A click on the module itself just closes the menu
+        bluetooth_rect(output_size, &self.ui).contains(point)
+    }
+
+    fn send(&self, command: BluetoothCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+/// The BlueZ connection on the Bluetooth thread
+struct Client {
+    connection: Connection,
+    updates: calloop::channel::Sender<PanelUpdate>,
+    /// The adapter used, e.g. /org/bluez/hci0
+    adapter: Option<String>,
+    last: Option<BluetoothState>,
+}
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+impl Client {
+    fn connect(
+        commands: Sender<BluetoothCommand>,
+        updates: calloop::channel::Sender<PanelUpdate>,
+    ) -> zbus::Result<Self> {
+        let connection = Connection::system()?;
+        let mut client = Self {
+            connection,
+            updates,
+            adapter: None,
+            last: None,
+        };
+        // Fails right away when BlueZ is not running
+        client.managed_objects()?;
+        watch_signals(&client.connection, commands);
+        client.refresh();
+        Ok(client)
+    }
+
+    fn run(mut self, commands: Receiver<BluetoothCommand>) {
+        while let Ok(command) = commands.recv() {
+            // BlueZ sends bursts of signals; read the state once per burst
+            for command in std::iter::once(command).chain(commands.try_iter()) {
+                let result = match command {
+                    BluetoothCommand::Changed => Ok(()),
+                    BluetoothCommand::SetPowered(powered) => self.set_powered(powered),
+                    BluetoothCommand::Connect(path) => {
+                        self.device(&path).and_then(|device| device.call::<_, _, ()>("Connect", &()))
+                    }
+                    BluetoothCommand::Disconnect(path) => self
+                        .device(&path)
+                        .and_then(|device| device.call::<_, _, ()>("Disconnect", &())),
+                };
+                if let Err(e) = result {
+                    warn!("Bluetooth: {e}");
+                }
+            }
+            if !self.refresh() {
+                return;
+            }
+        }
+    }
+
+    /// Send the state if it changed. Returns false once the compositor is
+    /// gone.
+    fn refresh(&mut self) -> bool {
+        let state = self.read_state().unwrap_or_else(|e| {
+            debug!("Reading the Bluetooth state failed: {e}");
+            BluetoothState::default()
+        });
+        if self.last.as_ref() == Some(&state) {
+            return true;
+        }
+        self.last = Some(state.clone());
+        self.updates.send(PanelUpdate::Bluetooth(state)).is_ok()
+    }
+
+    fn managed_objects(&self) -> zbus::Result<ManagedObjects> {
+        let manager = Proxy::new(&self.connection, BLUEZ, "/", "org.freedesktop.DBus.ObjectManager")?;
+        manager.call("GetManagedObjects", &())
+    }
+
+    /// The first adapter and its paired devices
+    fn read_state(&mut self) -> zbus::Result<BluetoothState> {
+        let objects = self.managed_objects()?;
+        let mut adapters: Vec<_> = objects
+            .iter()
+            .filter_map(|(path, interfaces)| Some((path.as_str(), interfaces.get(ADAPTER_INTERFACE)?)))
+            .collect();
+        adapters.sort_by_key(|(path, _)| *path);
+        let Some((adapter, properties)) = adapters.first() else {
+            self.adapter = None;
+            return Ok(BluetoothState::default());
+        };
+        self.adapter = Some(adapter.to_string());
+        let powered = bool_property(properties, "Powered");
+
+        let mut devices: Vec<BluetoothDevice> = objects
+            .iter()
+            .filter(|(path, _)| path.as_str().starts_with(&format!("{adapter}/")))
+            .filter_map(|(path, interfaces)| {
+                let device = interfaces.get(DEVICE_INTERFACE)?;
+                if !bool_property(device, "Paired") {
+                    return None;
+                }
+                let name = ["Alias", "Name"]
+                    .iter()
+                    .find_map(|key| String::try_from(device.get(*key)?.try_clone().ok()?).ok())
+                    .unwrap_or_else(|| path.to_string());
+                let battery = interfaces
+                    .get(BATTERY_INTERFACE)
+                    .and_then(|battery| u8::try_from(battery.get("Percentage")?).ok());
+                Some(BluetoothDevice {
+                    name,
+                    connected: bool_property(device, "Connected"),
+                    battery,
+                    path: path.to_string(),
+                })
+            })
+            .collect();
+        devices.sort_by(|a, b| b.connected.cmp(&a.connected).then_with(|| a.name.cmp(&b.name)));
+        Ok(BluetoothState {
+            available: true,
+            powered,
+            devices,
+        })
+    }
+
+    fn set_powered(&self, powered: bool) -> zbus::Result<()> {
+        let Some(adapter) = &self.adapter else {
+            return Ok(());
+        };
+        let adapter = Proxy::new(&self.connection, BLUEZ, adapter.as_str(), ADAPTER_INTERFACE)?;
+        adapter.set_property("Powered", powered)?;
+        Ok(())
+    }
+
+    fn device(&self, path: &str) -> zbus::Result<Proxy<'static>> {
+        Proxy::new(&self.connection, BLUEZ, path.to_string(), DEVICE_INTERFACE)
+    }
+}
+
+fn bool_property(properties: &HashMap<String, OwnedValue>, key: &str) -> bool {
+    properties
+        .get(key)
+        .and_then(|value| bool::try_from(value).ok())
+        .unwrap_or(false)
+}
+
+/// Forward every signal from BlueZ to the Bluetooth thread
+fn watch_signals(connection: &Connection, commands: Sender<BluetoothCommand>) {
+    let connection = connection.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-bluetooth-events".to_string())
+        .spawn(move || {
+            let rule = zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .sender(BLUEZ)
+                .map(|builder| builder.build());
+            let messages = rule.and_then(|rule| {
+                zbus::blocking::MessageIterator::for_match_rule(rule, &connection, None)
+            });
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Bluetooth: cannot follow BlueZ: {e}");
+                    return;
+                }
+            };
+            for _ in messages.flatten() {
+                if commands.send(BluetoothCommand::Changed).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start Bluetooth signal thread: {e}");
+    }
+}
@@ -0,0 +1,255 @@
+// =============================================================================
+// heyDM — Launcher Calculator
+//
+// Evaluates the expression after "=" in the launcher:
+//
+//     = 2^10 / (3 + 1)      → 256
+//     = sqrt(2) * pi        → 4.4428829382
+//
+// Supports + - * / % ^ (right-associative), parentheses, unary minus,
+// the constants pi and e, and the functions sqrt, abs, exp, ln, log (base
+// 10), sin, cos, tan (radians), floor, ceil and round. Nesting is limited to
+// MAX_DEPTH levels, so no input can overflow the stack.
+// =============================================================================
+
+/// Deepest nesting of parentheses, functions, signs and powers
+const MAX_DEPTH: usize = 256;
+
+/// Evaluate an expression and format the result for display
+pub fn evaluate(expr: &str) -> Result<String, String> {
+    let mut parser = Parser {
+        chars: expr.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+        depth: 0,
+    };
+    if parser.chars.is_empty() {
+        return Err("empty expression".to_string());
+    }
+    let value = parser.sum()?;
+    if let Some(c) = parser.peek() {
+        return Err(format!("unexpected '{c}'"));
+    }
+    if !value.is_finite() {
+        return Err("result is not a number".to_string());
+    }
+    Ok(format_number(value))
+}
+
+/// Integers without a fraction, everything else with up to 10 decimals
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let text = format!("{value:.10}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse a nested rule, refusing input nested deeper than MAX_DEPTH
+    fn nested(&mut self, rule: fn(&mut Self) -> Result<f64, String>) -> Result<f64, String> {
+        if self.depth == MAX_DEPTH {
+            return Err("expression nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let value = rule(self);
+        self.depth -= 1;
+        value
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// product := power (('*' | '/' | '%') power)*
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+        loop {
+            if self.eat('*') {
+                value *= self.power()?;
+            } else if self.eat('/') {
+                let divisor = self.power()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.power()?;
+                if divisor == 0.0 {
+                    return Err("division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// power := unary ('^' power)?
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.unary()?;
+        if self.eat('^') {
+            Ok(base.powf(self.nested(Self::power)?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    /// unary := '-' unary | '+' unary | atom
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            Ok(-self.nested(Self::unary)?)
+        } else if self.eat('+') {
+            self.nested(Self::unary)
+        } else {
+            self.atom()
+        }
+    }
+
+    /// atom := number | '(' sum ')' | constant | function '(' sum ')'
+    fn atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.nested(Self::sum)?;
+                if !self.eat(')') {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => self.name(),
+            Some(c) => Err(format!("unexpected '{c}'")),
+            None => Err("incomplete expression".to_string()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map_err(|_| format!("invalid number '{text}'"))
+    }
+
+    fn name(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+        match name.as_str() {
+            "pi" => return Ok(std::f64::consts::PI),
+            "e" => return Ok(std::f64::consts::E),
+            _ => {}
+        }
+
+        let function: fn(f64) -> f64 = match name.as_str() {
+            "sqrt" => f64::sqrt,
+            "abs" => f64::abs,
+            "exp" => f64::exp,
+            "ln" => f64::ln,
+            "log" => f64::log10,
+            "sin" => f64::sin,
+            "cos" => f64::cos,
+            "tan" => f64::tan,
+            "floor" => f64::floor,
+            "ceil" => f64::ceil,
+            "round" => f64::round,
+            _ => return Err(format!("unknown name '{name}'")),
+        };
+        if !self.eat('(') {
+            return Err(format!("expected '(' after {name}"));
+        }
+        let argument = self.nested(Self::sum)?;
+        if !self.eat(')') {
+            return Err("missing ')'".to_string());
+        }
+        Ok(function(argument))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+
+    fn eval(expr: &str) -> String {
+        evaluate(expr).unwrap_or_else(|e| panic!("{expr}: {e}"))
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(eval("1 + 2 * 3"), "7");
+        assert_eq!(eval("(1 + 2) * 3"), "9");
+        assert_eq!(eval("2^3^2"), "512");
+        assert_eq!(eval("2^10 / (3 + 1)"), "256");
+        assert_eq!(eval("10 - 4 - 3"), "3");
+        assert_eq!(eval("10 % 4"), "2");
+        assert_eq!(eval("--3"), "3");
+        assert_eq!(eval("-(2 + 3)"), "-5");
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_eq!(eval("sqrt(16) + abs(-2)"), "6");
+        assert_eq!(eval("round(pi * 100)"), "314");
+        assert_eq!(eval("sqrt(2) * pi"), "4.4428829382");
+        assert_eq!(eval("log(1000)"), "3");
+        assert_eq!(eval("floor(2.7) + ceil(2.2)"), "5");
+    }
+
+    #[test]
+    fn formats_fractions() {
+        assert_eq!(eval("1 / 3"), "0.3333333333");
+        assert_eq!(eval("0.5 + 0.25"), "0.75");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for expr in ["", "1 / 0", "5 % 0", "(1", "2 +", "1)", "foo(1)", "sqrt 4", "1..2", "sqrt(-1)", "#"] {
+            assert!(evaluate(expr).is_err(), "{expr} should fail");
+        }
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let deep = 100_000;
+        let parens = format!("{}1{}", "(".repeat(deep), ")".repeat(deep));
+        assert!(evaluate(&parens).is_err());
+        assert!(evaluate(&format!("{}1", "-".repeat(deep))).is_err());
+        assert!(evaluate(&format!("{}1", "2^".repeat(deep))).is_err());
+        assert!(evaluate(&format!("{}1", "sqrt(".repeat(deep))).is_err());
+
+        let shallow = format!("{}1{}", "(".repeat(50), ")".repeat(50));
+        assert_eq!(eval(&shallow), "1");
+    }
+}
@@ -0,0 +1,138 @@
+// =============================================================================
+// heyDM — Calendar Popup
+//
+// Clicking the panel clock opens a month calendar below it, weeks starting
+// on Monday, with today highlighted. While it is open:
+//
+//     Left / Right   previous / next month
+//     Escape         close
+//
+// A click outside the popup closes it too.
+// =============================================================================
+
+use chrono::{Datelike, Local, NaiveDate};
+use smithay::utils::{Physical, Rectangle, Size};
+
+use crate::theme::UiMetrics;
+
+/// Month calendar below the panel clock
+#[derive(Debug)]
+pub struct Calendar {
+    visible: bool,
+    /// Month shown, as (year, month 1..=12)
+    month: (i32, u32),
+    ui: UiMetrics,
+}
+
+/// One month laid out for drawing
+#[derive(Debug, Clone)]
+pub struct MonthView {
+    /// "October 2026"
+    pub title: String,
+    /// Column (0 = Monday) of the 1st
+    pub first_column: u32,
+    pub days: u32,
+    /// Day of the month that is today, if this is the current month
+    pub today: Option<u32>,
+}
+
+#[allow(dead_code)]
+impl Calendar {
+    pub fn new(ui: UiMetrics) -> Self {
+        Self {
+            visible: false,
+            month: (1970, 1),
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Open on the current month, or close
+    pub fn toggle(&mut self) {
+        if self.visible {
+            self.visible = false;
+            return;
+        }
+        let today = Local::now().date_naive();
+        self.month = (today.year(), today.month());
+        self.visible = true;
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    /// Handle a key while open; returns true if it was used
+    pub fn handle_key(&mut self, keysym: xkbcommon::xkb::Keysym) -> bool {
+        use xkbcommon::xkb::Keysym as K;
+        match keysym {
+            K::Escape => self.visible = false,
+            K::Left => self.step(-1),
+            K::Right => self.step(1),
+            _ => return false,
+        }
+        true
+    }
+
+    fn step(&mut self, months: i32) {
+        let index = self.month.0 * 12 + self.month.1 as i32 - 1 + months;
+        self.month = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+    }
+
+    /// The shown month
+    pub fn view(&self) -> MonthView {
+        let (year, month) = self.month;
+        let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default();
+        let next = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)
+        };
+        let days = next.map_or(31, |next| (next - first).num_days() as u32);
+        let today = Local::now().date_naive();
+        MonthView {
+            title: first.format("%B %Y").to_string(),
+            first_column: first.weekday().num_days_from_monday(),
+            days,
+            today: (today.year() == year && today.month() == month).then(|| today.day()),
+        }
+    }
+
+    /// Edge length of one day cell
+    pub fn cell_size(&self) -> i32 {
+        self.ui.px(36)
+    }
+
+    /// Height of the month title and weekday header above the grid
+    pub fn header_height(&self) -> i32 {
+        self.ui.px(64)
+    }
+
+    /// The popup, right-aligned below the clock
+    pub fn rect(&self, output_size: Size<i32, Physical>) -> Rectangle<i32, Physical> {
+        let pad = self.ui.px(12);
+        let w = self.cell_size() * 7 + pad * 2;
+        let h = self.header_height() + self.cell_size() * 6 + pad;
+        Rectangle::new(
+            (
+                output_size.w - self.ui.panel_margin() - w,
+                self.ui.panel_bottom() + self.ui.px(4),
+            )
+                .into(),
+            (w, h).into(),
+        )
+    }
+
+    /// Whether `pos` is inside the open popup
+    pub fn contains(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        self.visible && self.rect(output_size).contains((pos.0 as i32, pos.1 as i32))
+    }
+}
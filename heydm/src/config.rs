@@ -0,0 +1,524 @@
+// =============================================================================
+// heyDM — Configuration
+//
+// Loads the user's compositor configuration from
+// $XDG_CONFIG_HOME/heydm/config (falling back to ~/.config/heydm/config).
+//
+// The file is a simple INI-style format:
+//
+//     # Comments start with '#'
+//     [workspaces]
+//     1-5 = primary
+//     6-9 = secondary
+//     7   = HDMI-A-1
+//
+//     [input]
+//     tap = true
+//
+//     [tiling]
+//     layout = dwindle
+//
+//     [appearance]
+//     wallpaper = ~/Pictures/wall.png
+//
+//     [theme]
+//     scale = 2.0
+//
+//     [color]
+//     filter = grayscale
+//
+// Section headers may carry an argument, e.g. `[output "Dell U2720Q"]`.
+// A missing file is not an error: every setting has a sensible default.
+// Problems are reported as diagnostics (see schema.rs) and the offending
+// line is ignored.
+//
+// SIGHUP or `heyctl reload` reads the file again without restarting (see
+// HeyDM::reload_config). Keyboard, accessibility, input, tiling,
+// appearance, theme sizes, color and output settings apply right away, as does everything read on use
+// (power, compat, shell, presets, window rules); the theme font, [performance], [seats]
+// and [workspaces] take effect on the next start.
+// =============================================================================
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use tracing::{info, warn};
+
+use crate::accessx::AccessibilityConfig;
+use crate::devices::{DeviceSettings, InputConfig, KeyboardConfig};
+use crate::filters::ColorConfig;
+use crate::output::OutputConfig;
+use crate::power::PowerConfig;
+use crate::presets::LayoutPreset;
+use crate::protocol::CompatConfig;
+use crate::realtime::PerformanceConfig;
+use crate::schema::{self, Diagnostic};
+use crate::seats::SeatConfig;
+use crate::shell::ShellConfig;
+use crate::tearing::WindowRule;
+use crate::theme::ThemeConfig;
+use crate::tiling::TilingConfig;
+use crate::wallpaper::AppearanceConfig;
+use crate::workspace::{OutputTarget, WORKSPACE_COUNT};
+
+/// A single `key = value` line inside a section
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    /// 1-based line number in the source file
+    pub line: usize,
+    /// 1-based columns of the key and the value
+    pub column: usize,
+    pub value_column: usize,
+}
+
+/// A `[name arg]` section and its entries
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    /// Optional argument after the section name (quotes stripped)
+    pub arg: Option<String>,
+    pub entries: Vec<Entry>,
+    /// 1-based line number of the section header
+    pub line: usize,
+}
+
+/// The parsed, typed compositor configuration
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Workspace → output pinning (workspace numbers are 1-based)
+    pub workspace_outputs: BTreeMap<usize, OutputTarget>,
+    /// libinput device settings
+    pub input: InputConfig,
+    /// XKB keymap
+    pub keyboard: KeyboardConfig,
+    pub accessibility: AccessibilityConfig,
+    /// Automatic tiling defaults
+    pub tiling: TilingConfig,
+    /// Wallpaper and accent color
+    pub appearance: AppearanceConfig,
+    /// Font and scale of compositor-drawn UI
+    pub theme: ThemeConfig,
+    /// Color filter and night light
+    pub color: ColorConfig,
+    /// Workarounds for misbehaving clients
+    pub compat: CompatConfig,
+    /// Clients trusted with the hey_shell_v1 protocol
+    pub shell: ShellConfig,
+    /// Realtime scheduling of the compositor thread
+    pub performance: PerformanceConfig,
+    /// Low battery warnings
+    pub power: PowerConfig,
+    /// Additional seats by name
+    pub seats: BTreeMap<String, SeatConfig>,
+    /// Layout presets by name
+    pub presets: BTreeMap<String, LayoutPreset>,
+    /// Output settings by monitor identity or connector name
+    pub outputs: BTreeMap<String, OutputConfig>,
+    /// Tearing and adaptive sync by app id
+    pub window_rules: BTreeMap<String, WindowRule>,
+}
+
+impl Config {
+    /// Path of the user configuration file
+    pub fn path() -> PathBuf {
+        let base = std::env::var("XDG_CONFIG_HOME")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| {
+                let home = std::env::var("HOME").unwrap_or_default();
+                PathBuf::from(home).join(".config")
+            });
+        base.join("heydm").join("config")
+    }
+
+    /// Load the configuration from disk, falling back to defaults
+    pub fn load() -> Self {
+        let path = Self::path();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                info!("Loading configuration from {}", path.display());
+                let (config, diagnostics) = Self::parse(&content);
+                for diagnostic in diagnostics {
+                    warn!("{}:{diagnostic}", path.display());
+                }
+                config
+            }
+            Err(_) => {
+                info!("No configuration at {} — using defaults", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    /// Read the configuration again while running. Unlike `load`, a file
+    /// that exists but cannot be read is an error rather than the defaults.
+    pub fn reload() -> Result<(Self, Vec<Diagnostic>), String> {
+        let path = Self::path();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        info!("Reloading configuration from {}", path.display());
+        let (config, diagnostics) = Self::parse(&content);
+        for diagnostic in &diagnostics {
+            warn!("{}:{diagnostic}", path.display());
+        }
+        Ok((config, diagnostics))
+    }
+
+    /// Parse configuration text into a typed config plus any problems found
+    pub fn parse(content: &str) -> (Self, Vec<Diagnostic>) {
+        let mut config = Self::default();
+        let mut diagnostics = Vec::new();
+
+        for section in parse_sections(content, &mut diagnostics) {
+            if !validate_section(&section, &mut diagnostics) {
+                continue;
+            }
+            config.apply_section(&section, &mut diagnostics);
+        }
+
+        (config, diagnostics)
+    }
+
+    /// Apply one validated section on top of the current values
+    fn apply_section(&mut self, section: &Section, diagnostics: &mut Vec<Diagnostic>) {
+        match section.name.as_str() {
+            "workspaces" => self.apply_workspaces(section, diagnostics),
+            "input" => apply_device_settings(&mut self.input.defaults, section),
+            "tiling" => {
+                for entry in &section.entries {
+                    let _ = self.tiling.set(&entry.key, &entry.value);
+                }
+            }
+            "appearance" => {
+                for entry in &section.entries {
+                    let _ = self.appearance.set(&entry.key, &entry.value);
+                }
+            }
+            "theme" => {
+                for entry in &section.entries {
+                    let _ = self.theme.set(&entry.key, &entry.value);
+                }
+            }
+            "color" => {
+                for entry in &section.entries {
+                    let _ = self.color.set(&entry.key, &entry.value);
+                }
+            }
+            "accessibility" => {
+                for entry in &section.entries {
+                    let _ = self.accessibility.set(&entry.key, &entry.value);
+                }
+            }
+            "keyboard" => {
+                for entry in &section.entries {
+                    let _ = self.keyboard.set(&entry.key, &entry.value);
+                }
+            }
+            "shell" => {
+                for entry in &section.entries {
+                    let _ = self.shell.set(&entry.key, &entry.value);
+                }
+            }
+            "performance" => {
+                for entry in &section.entries {
+                    let _ = self.performance.set(&entry.key, &entry.value);
+                }
+            }
+            "power" => {
+                for entry in &section.entries {
+                    let _ = self.power.set(&entry.key, &entry.value);
+                }
+            }
+            "compat" => {
+                for entry in &section.entries {
+                    let _ = self.compat.set(&entry.key, &entry.value);
+                }
+            }
+            "preset" => {
+                let Some(name) = section.arg.clone() else { return };
+                let preset = self.presets.entry(name).or_default();
+                for entry in &section.entries {
+                    match entry.key.as_str() {
+                        // Checked by schema validation
+                        "workspace" | "key" => {
+                            let _ = preset.set(&entry.key, &entry.value);
+                        }
+                        app_id => {
+                            if let Err(message) = preset.add_window(app_id, &entry.value) {
+                                diagnostics.push(Diagnostic::warning(
+                                    entry.line,
+                                    entry.value_column,
+                                    message,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            "seat" => {
+                let Some(name) = section.arg.clone() else { return };
+                let seat = self.seats.entry(name).or_default();
+                for entry in &section.entries {
+                    let _ = seat.set(&entry.key, &entry.value);
+                }
+            }
+            "output" => {
+                let Some(name) = section.arg.clone() else { return };
+                let output = self.outputs.entry(name).or_default();
+                for entry in &section.entries {
+                    if let Err(message) = output.set(&entry.key, &entry.value) {
+                        diagnostics.push(Diagnostic::error(
+                            entry.line,
+                            entry.value_column,
+                            message,
+                        ));
+                    }
+                }
+            }
+            "window" => {
+                let Some(app_id) = section.arg.clone() else { return };
+                let rule = self.window_rules.entry(app_id).or_default();
+                for entry in &section.entries {
+                    let _ = rule.set(&entry.key, &entry.value);
+                }
+            }
+            "device" => {
+                let Some(name) = section.arg.clone() else { return };
+                let settings = self.input.devices.entry(name).or_default();
+                apply_device_settings(settings, section);
+            }
+            _ => {}
+        }
+    }
+
+    /// Change a single value at runtime (IPC `config set`), checked against
+    /// the schema like a line in the file
+    pub fn set_value(&mut self, section: &str, arg: Option<&str>, key: &str, value: &str) -> Result<(), String> {
+        let section = Section {
+            name: section.to_string(),
+            arg: arg.map(str::to_string),
+            entries: vec![Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+                line: 1,
+                column: 1,
+                value_column: 1,
+            }],
+            line: 1,
+        };
+        let mut diagnostics = Vec::new();
+        if validate_section(&section, &mut diagnostics) && diagnostics.is_empty() {
+            self.apply_section(&section, &mut diagnostics);
+        }
+        match diagnostics.first() {
+            Some(problem) => Err(problem.message.clone()),
+            None => Ok(()),
+        }
+    }
+
+    /// Apply a `[workspaces]` section: `<n>` or `<from>-<to>` = output
+    fn apply_workspaces(&mut self, section: &Section, diagnostics: &mut Vec<Diagnostic>) {
+        for entry in &section.entries {
+            let Some((from, to)) = parse_workspace_range(&entry.key) else {
+                diagnostics.push(Diagnostic::warning(
+                    entry.line,
+                    entry.column,
+                    format!(
+                        "invalid workspace '{}' (expected 1-{WORKSPACE_COUNT} or a range like 1-5)",
+                        entry.key
+                    ),
+                ));
+                continue;
+            };
+            if entry.value.is_empty() {
+                continue;
+            }
+
+            let target = OutputTarget::parse(&entry.value);
+            for ws in from..=to {
+                self.workspace_outputs.insert(ws, target.clone());
+            }
+        }
+    }
+}
+
+/// Apply an `[input]` or `[device]` section. Invalid values were already
+/// reported by schema validation and are skipped here.
+fn apply_device_settings(settings: &mut DeviceSettings, section: &Section) {
+    for entry in &section.entries {
+        let _ = settings.set(&entry.key, &entry.value);
+    }
+}
+
+/// Check a section against the schema: unknown sections and keys, header
+/// arguments and value types. Returns false if the section is unknown.
+fn validate_section(section: &Section, diagnostics: &mut Vec<Diagnostic>) -> bool {
+    let Some(schema) = schema::section(&section.name) else {
+        if section.line == 0 {
+            for entry in &section.entries {
+                diagnostics.push(Diagnostic::warning(
+                    entry.line,
+                    entry.column,
+                    format!("'{}' is outside of any [section]", entry.key),
+                ));
+            }
+            return false;
+        }
+        let names = schema::SCHEMA.iter().map(|s| s.name);
+        diagnostics.push(Diagnostic::warning(
+            section.line,
+            2,
+            schema::unknown("section", &section.name, names),
+        ));
+        return false;
+    };
+
+    match (schema.arg, &section.arg) {
+        (Some(arg), None) => diagnostics.push(Diagnostic::warning(
+            section.line,
+            2,
+            format!("[{}] needs an argument ({arg})", section.name),
+        )),
+        (None, Some(arg)) => diagnostics.push(Diagnostic::warning(
+            section.line,
+            2,
+            format!("[{}] takes no argument, ignoring '{arg}'", section.name),
+        )),
+        _ => {}
+    }
+
+    for entry in &section.entries {
+        if let Some(key) = schema.key(&entry.key) {
+            if let Err(message) = key.ty.check(&entry.value) {
+                diagnostics.push(Diagnostic::error(entry.line, entry.value_column, message));
+            }
+        } else if let Some(pattern) = &schema.pattern_keys {
+            // Key syntax is checked by the section's apply function
+            if let Err(message) = pattern.ty.check(&entry.value) {
+                diagnostics.push(Diagnostic::error(entry.line, entry.value_column, message));
+            }
+        } else {
+            let keys = schema.keys.iter().map(|k| k.name);
+            diagnostics.push(Diagnostic::warning(
+                entry.line,
+                entry.column,
+                schema::unknown("key", &entry.key, keys),
+            ));
+        }
+    }
+    true
+}
+
+/// Split configuration text into sections. Entries before the first header
+/// land in an implicit `[general]` section.
+pub fn parse_sections(content: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<Section> {
+    let mut sections = vec![Section {
+        name: "general".to_string(),
+        arg: None,
+        entries: Vec::new(),
+        line: 0,
+    }];
+
+    for (idx, raw) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = strip_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let indent = raw.len() - raw.trim_start().len();
+
+        if let Some(header) = line.strip_prefix('[') {
+            let Some(header) = header.strip_suffix(']') else {
+                diagnostics.push(Diagnostic::error(
+                    line_no,
+                    column(raw, indent + line.len()),
+                    "unterminated section header, expected ']'",
+                ));
+                continue;
+            };
+            let header = header.trim();
+            let (name, arg) = match header.split_once(char::is_whitespace) {
+                Some((name, arg)) => (name, Some(unquote(arg.trim()).to_string())),
+                None => (header, None),
+            };
+            sections.push(Section {
+                name: name.to_string(),
+                arg,
+                entries: Vec::new(),
+                line: line_no,
+            });
+            continue;
+        }
+
+        match line.split_once('=') {
+            Some((key, value)) => {
+                let value_start = indent + key.len() + 1;
+                let value_indent = value.len() - value.trim_start().len();
+                let section = sections.last_mut().expect("at least one section");
+                section.entries.push(Entry {
+                    key: key.trim().to_string(),
+                    value: unquote(value.trim()).to_string(),
+                    line: line_no,
+                    column: column(raw, indent),
+                    value_column: column(raw, value_start + value_indent),
+                });
+            }
+            None => diagnostics.push(Diagnostic::error(
+                line_no,
+                column(raw, indent),
+                format!("expected 'key = value', got '{line}'"),
+            )),
+        }
+    }
+
+    sections.retain(|s| s.line > 0 || !s.entries.is_empty());
+    sections
+}
+
+/// 1-based character column of a byte offset within a line
+fn column(line: &str, byte_offset: usize) -> usize {
+    line[..byte_offset.min(line.len())].chars().count() + 1
+}
+
+/// Remove a trailing `# comment` that is not inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (idx, ch) in line.char_indices() {
+        match ch {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..idx],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Strip one level of surrounding double quotes
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Parse `3` or `1-5` into an inclusive, validated workspace range
+fn parse_workspace_range(key: &str) -> Option<(usize, usize)> {
+    let (from, to) = match key.split_once('-') {
+        Some((from, to)) => (from.trim().parse().ok()?, to.trim().parse().ok()?),
+        None => {
+            let ws = key.trim().parse().ok()?;
+            (ws, ws)
+        }
+    };
+
+    let valid = |ws: usize| (1..=WORKSPACE_COUNT).contains(&ws);
+    (valid(from) && valid(to) && from <= to).then_some((from, to))
+}
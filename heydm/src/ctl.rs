@@ -0,0 +1,141 @@
+// =============================================================================
+// heyDM — heyctl
+//
+// Command-line client for the IPC socket (ipc.rs), for scripts and
+// debugging. heyctl is heydm itself, installed as a link and recognized by
+// its name; `heydm ctl` does the same:
+//
+//     heyctl list windows
+//     heyctl focus 12
+//     heyctl move 12 3
+//     heyctl workspace 2
+//     heyctl config set appearance wallpaper_mode fit
+//     heyctl subscribe window workspace
+//
+// The arguments form the command line sent to heyDM; the JSON reply is
+// printed pretty (or as sent with --raw). Subscriptions print one JSON line
+// per event until heyDM exits. The exit code is 0 on success, 1 when heyDM
+// reports a failure and 2 when it cannot be reached.
+// =============================================================================
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::ipc;
+
+/// How long to wait for heyDM's reply
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "\
+usage: heyctl [--raw] <command>
+
+commands:
+  list windows | workspaces | outputs
+  layout                     dump the window manager state (debugging)
+  focus <id>                 switch to a window, restoring it if minimized
+  close <id>                 ask a window to close
+  move <id> <workspace>      move a window to another workspace
+  workspace <n>              switch workspaces
+  kill <id> [signal]         signal the process owning a window
+  wallpaper <path> | none    change the wallpaper
+  wallpaper mode <mode>      fill, fit, center or tile
+  wallpaper output <connector> <path> | none
+  preset <name>              apply a layout preset
+  switch-user                lock and let another user log in
+  config set <section> <key> <value>
+                             change a setting until heyDM restarts
+  reload                     read the config file again
+  status                     the status bar object
+  subscribe events | status  follow events (or window, workspace, output)
+  latency [on | off | reset]
+  perf [on | off | reset | overlay]
+                             frame metrics; overlay toggles the FPS box";
+
+/// Whether heydm was started under the heyctl name
+pub fn invoked_as_heyctl() -> bool {
+    std::env::args_os()
+        .next()
+        .map(std::path::PathBuf::from)
+        .and_then(|path| path.file_name().map(|name| name == "heyctl"))
+        .unwrap_or(false)
+}
+
+/// Run heyctl with its arguments. Returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let raw = args.iter().any(|a| a == "--raw");
+    let words: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|a| *a != "--raw")
+        .collect();
+    if words.is_empty() || matches!(words[0], "-h" | "--help" | "help") {
+        println!("{USAGE}");
+        return if words.is_empty() { 2 } else { 0 };
+    }
+    let subscribe = words[0] == "subscribe";
+
+    let Some(path) = ipc::client_socket_path() else {
+        eprintln!("heyctl: neither HEYDM_SOCK nor WAYLAND_DISPLAY is set");
+        return 2;
+    };
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("heyctl: {}: {e}", path.display());
+            return 2;
+        }
+    };
+    let _ = stream.set_read_timeout(Some(REPLY_TIMEOUT));
+    if let Err(e) = writeln!(stream, "{}", words.join(" ")) {
+        eprintln!("heyctl: {e}");
+        return 2;
+    }
+
+    if subscribe {
+        return follow(stream);
+    }
+    let mut reply = String::new();
+    if let Err(e) = BufReader::new(stream).read_line(&mut reply) {
+        eprintln!("heyctl: no reply: {e}");
+        return 2;
+    }
+    let reply: Value = match serde_json::from_str(&reply) {
+        Ok(reply) => reply,
+        Err(e) => {
+            eprintln!("heyctl: invalid reply: {e}");
+            return 2;
+        }
+    };
+
+    if raw {
+        println!("{reply}");
+    } else {
+        println!("{reply:#}");
+    }
+    i32::from(reply["success"] == Value::Bool(false))
+}
+
+/// Print a subscription's lines as they arrive, until heyDM closes it
+fn follow(stream: UnixStream) -> i32 {
+    let _ = stream.set_read_timeout(None);
+    let mut stdout = std::io::stdout().lock();
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return 2;
+        };
+        if let Ok(reply) = serde_json::from_str::<Value>(&line) {
+            if reply["success"] == Value::Bool(false) {
+                eprintln!("heyctl: {}", reply["error"].as_str().unwrap_or("subscribe failed"));
+                return 1;
+            }
+        }
+        // Flush per line so pipes see events right away
+        if writeln!(stdout, "{line}").and_then(|()| stdout.flush()).is_err() {
+            return 0;
+        }
+    }
+    0
+}
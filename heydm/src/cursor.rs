@@ -0,0 +1,323 @@
+// =============================================================================
+// heyDM — Cursor Images
+//
+// Resolves what the pointer should look like:
+//   - Named cursors (the default arrow, text beam, resize arrows, ...) are
+//     loaded from the XCursor theme given by XCURSOR_THEME / XCURSOR_SIZE
+//   - Client cursor surfaces (wl_pointer.set_cursor) are copied out of their
+//     shm buffer on commit, together with the client's hotspot
+//   - Hidden cursors draw nothing
+// When no theme is installed the renderer falls back to a plain square.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use smithay::input::pointer::{CursorIcon, CursorImageStatus, CursorImageSurfaceData};
+use smithay::reexports::wayland_server::protocol::wl_shm;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::wayland::compositor::{with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::shm::{with_buffer_contents, BufferData};
+use tracing::{debug, info, warn};
+
+use crate::texture::{Image, PixelBuffer};
+
+/// Cursor size used when XCURSOR_SIZE is unset
+const DEFAULT_CURSOR_SIZE: u32 = 24;
+
+/// One image of a (possibly animated) cursor
+#[derive(Debug, Clone)]
+pub struct CursorFrame {
+    pub image: Image,
+    /// Hotspot offset from the image's top-left corner
+    pub hotspot: (i32, i32),
+    /// Display time in milliseconds (animated cursors only)
+    delay: u32,
+}
+
+/// Current cursor image selection and loaded theme images
+pub struct CursorManager {
+    theme: xcursor::CursorTheme,
+    size: u32,
+    /// Size from the environment; `size` is this times the output scale,
+    /// doubled with large cursors
+    base_size: u32,
+    large: bool,
+    scale: f64,
+    /// What the focused client asked for
+    status: CursorImageStatus,
+    /// Loaded theme cursors by name (None = not present in the theme)
+    named: HashMap<String, Option<Arc<Vec<CursorFrame>>>>,
+    /// Last image committed to the client's cursor surface
+    surface_frame: Option<CursorFrame>,
+    /// Animation clock for animated theme cursors
+    started: Instant,
+}
+
+#[allow(dead_code)]
+impl CursorManager {
+    /// Load the cursor theme from the environment
+    pub fn new() -> Self {
+        let theme_name = std::env::var("XCURSOR_THEME").unwrap_or_else(|_| "default".to_string());
+        let size = std::env::var("XCURSOR_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_CURSOR_SIZE);
+
+        let mut manager = Self {
+            theme: xcursor::CursorTheme::load(&theme_name),
+            size,
+            base_size: size,
+            large: false,
+            scale: 1.0,
+            status: CursorImageStatus::default_named(),
+            named: HashMap::new(),
+            surface_frame: None,
+            started: Instant::now(),
+        };
+
+        // The default arrow and the busy cursor are needed without a client asking
+        manager.load_icon(CursorIcon::Default);
+        manager.load_icon(CursorIcon::Progress);
+        if manager.frames_for(CursorIcon::Default).is_some() {
+            info!("Cursor theme '{theme_name}' loaded (size {size})");
+        } else {
+            warn!("Cursor theme '{theme_name}' has no default cursor — using fallback");
+        }
+        manager
+    }
+
+    /// Switch between the normal and a double-sized cursor (accessibility)
+    pub fn toggle_large(&mut self) {
+        self.large = !self.large;
+        self.resize();
+    }
+
+    /// Draw theme cursors for an output with this scale
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+        self.resize();
+    }
+
+    /// Load the theme cursors again at the size for `large` and `scale`
+    fn resize(&mut self) {
+        let factor = if self.large { 2.0 } else { 1.0 } * self.scale;
+        let size = (self.base_size as f64 * factor).round() as u32;
+        if size == self.size {
+            return;
+        }
+        self.size = size;
+        info!("Cursor size {}", self.size);
+        self.named.clear();
+        self.load_icon(CursorIcon::Default);
+        self.load_icon(CursorIcon::Progress);
+        if let CursorImageStatus::Named(icon) = self.status {
+            self.load_icon(icon);
+        }
+    }
+
+    /// Apply a cursor image request from the focused client
+    pub fn set_status(&mut self, status: CursorImageStatus) {
+        match &status {
+            CursorImageStatus::Named(icon) => self.load_icon(*icon),
+            CursorImageStatus::Surface(surface) => {
+                self.surface_frame = None;
+                self.surface_committed(surface);
+            }
+            CursorImageStatus::Hidden => {}
+        }
+        self.status = status;
+    }
+
+    /// Reset to the default arrow (e.g. when the pointer leaves all clients)
+    pub fn reset(&mut self) {
+        self.set_status(CursorImageStatus::default_named());
+    }
+
+    /// Whether `surface` is the client's current cursor surface
+    pub fn is_cursor_surface(&self, surface: &WlSurface) -> bool {
+        matches!(&self.status, CursorImageStatus::Surface(s) if s == surface)
+    }
+
+    /// Copy the newly committed buffer of the cursor surface
+    pub fn surface_committed(&mut self, surface: &WlSurface) {
+        let hotspot = with_states(surface, |states| {
+            states
+                .data_map
+                .get::<CursorImageSurfaceData>()
+                .map(|data| data.lock().unwrap().hotspot)
+        })
+        .map(|h| (h.x, h.y))
+        .unwrap_or((0, 0));
+
+        let buffer = with_states(surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            attributes.current().buffer.take()
+        });
+
+        match buffer {
+            Some(BufferAssignment::NewBuffer(buffer)) => {
+                let image = with_buffer_contents(&buffer, |ptr, len, data| {
+                    copy_shm_image(ptr, len, data)
+                });
+                buffer.release();
+
+                match image {
+                    Ok(Some(image)) => {
+                        self.surface_frame = Some(CursorFrame {
+                            image: Arc::new(image),
+                            hotspot,
+                            delay: 0,
+                        });
+                    }
+                    Ok(None) => debug!("Unsupported cursor buffer format"),
+                    Err(e) => debug!("Cursor buffer is not readable: {e:?}"),
+                }
+            }
+            Some(BufferAssignment::Removed) => self.surface_frame = None,
+            None => {
+                // Only the hotspot changed
+                if let Some(frame) = self.surface_frame.as_mut() {
+                    frame.hotspot = hotspot;
+                }
+            }
+        }
+    }
+
+    /// The image to draw this frame, or None to draw nothing
+    pub fn current_frame(&self, busy: bool) -> Option<CursorFrame> {
+        match &self.status {
+            CursorImageStatus::Hidden => None,
+            CursorImageStatus::Surface(_) => self
+                .surface_frame
+                .clone()
+                .or_else(|| self.animated_frame(CursorIcon::Default)),
+            CursorImageStatus::Named(icon) => {
+                // Apps that are still starting override the plain arrow
+                let icon = if busy && *icon == CursorIcon::Default {
+                    CursorIcon::Progress
+                } else {
+                    *icon
+                };
+                self.animated_frame(icon)
+                    .or_else(|| self.animated_frame(CursorIcon::Default))
+            }
+        }
+    }
+
+    /// The theme's default arrow, ignoring what clients asked for
+    pub fn default_frame(&self) -> Option<CursorFrame> {
+        self.animated_frame(CursorIcon::Default)
+    }
+
+    /// Whether the client hid the cursor
+    pub fn is_hidden(&self) -> bool {
+        matches!(self.status, CursorImageStatus::Hidden)
+    }
+
+    /// Whether the theme provides a named cursor (false = use the fallback)
+    pub fn has_theme_cursor(&self, icon: CursorIcon) -> bool {
+        self.frames_for(icon).is_some()
+    }
+
+    /// Pick the frame of an animated cursor for the current time
+    fn animated_frame(&self, icon: CursorIcon) -> Option<CursorFrame> {
+        let frames = self.frames_for(icon)?;
+        let total: u32 = frames.iter().map(|f| f.delay).sum();
+        if frames.len() == 1 || total == 0 {
+            return frames.first().cloned();
+        }
+
+        let mut t = (self.started.elapsed().as_millis() % total as u128) as u32;
+        for frame in frames.iter() {
+            if t < frame.delay {
+                return Some(frame.clone());
+            }
+            t -= frame.delay;
+        }
+        frames.last().cloned()
+    }
+
+    fn frames_for(&self, icon: CursorIcon) -> Option<&Arc<Vec<CursorFrame>>> {
+        self.named.get(icon.name()).and_then(Option::as_ref)
+    }
+
+    /// Load a named cursor from the theme (trying legacy X11 names too)
+    fn load_icon(&mut self, icon: CursorIcon) {
+        if self.named.contains_key(icon.name()) {
+            return;
+        }
+
+        let frames = std::iter::once(icon.name())
+            .chain(icon.alt_names().iter().copied())
+            .find_map(|name| self.load_xcursor(name));
+        self.named.insert(icon.name().to_string(), frames.map(Arc::new));
+    }
+
+    /// Load all frames of the best-sized image set of an XCursor file
+    fn load_xcursor(&self, name: &str) -> Option<Vec<CursorFrame>> {
+        let path = self.theme.load_icon(name)?;
+        let bytes = std::fs::read(path).ok()?;
+        let images = xcursor::parser::parse_xcursor(&bytes)?;
+
+        let nearest = images
+            .iter()
+            .min_by_key(|img| (img.size as i64 - self.size as i64).abs())?
+            .size;
+
+        let frames: Vec<CursorFrame> = images
+            .into_iter()
+            .filter(|img| img.size == nearest)
+            .map(|img| CursorFrame {
+                // XCursor pixels are premultiplied little-endian ARGB32,
+                // i.e. the same memory layout as our pixel buffers
+                image: Arc::new(PixelBuffer {
+                    width: img.width as i32,
+                    height: img.height as i32,
+                    pixels: img.pixels_rgba,
+                }),
+                hotspot: (img.xhot as i32, img.yhot as i32),
+                delay: img.delay,
+            })
+            .collect();
+
+        (!frames.is_empty()).then_some(frames)
+    }
+}
+
+/// Copy an ARGB/XRGB shm buffer into a pixel buffer (also used for input
+/// method popups, see ime.rs)
+pub fn copy_shm_image(ptr: *const u8, len: usize, data: BufferData) -> Option<PixelBuffer> {
+    let opaque = match data.format {
+        wl_shm::Format::Argb8888 => false,
+        wl_shm::Format::Xrgb8888 => true,
+        _ => return None,
+    };
+
+    let width = data.width.max(0) as usize;
+    let height = data.height.max(0) as usize;
+    let stride = data.stride.max(0) as usize;
+    let offset = data.offset.max(0) as usize;
+    if width == 0 || height == 0 || offset + stride * (height - 1) + width * 4 > len {
+        return None;
+    }
+
+    // SAFETY: smithay guarantees `ptr` is valid for `len` bytes during the
+    // callback, and the bounds were checked above.
+    let src = unsafe { std::slice::from_raw_parts(ptr, len) };
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for row in 0..height {
+        let start = offset + row * stride;
+        pixels.extend_from_slice(&src[start..start + width * 4]);
+    }
+    if opaque {
+        pixels.chunks_exact_mut(4).for_each(|px| px[3] = 0xff);
+    }
+
+    Some(PixelBuffer {
+        width: width as i32,
+        height: height as i32,
+        pixels,
+    })
+}
@@ -0,0 +1,222 @@
+// =============================================================================
+// heyDM — Input Device Configuration
+//
+// libinput settings from the config file, applied whenever the udev backend
+// sees a new device:
+//
+//     [input]                      # defaults for every device
+//     tap = true
+//     natural_scroll = false
+//     accel_profile = adaptive     # adaptive | flat
+//     accel_speed = 0.0            # -1.0 (slow) .. 1.0 (fast)
+//     scroll_method = two_finger   # two_finger | edge | on_button_down | none
+//
+//     [device "Logitech MX Master 3"]
+//     natural_scroll = true        # overrides [input] for this device only
+//
+//     [keyboard]                   # XKB keymap of seat0's keyboard
+//     layout = us,de
+//     options = grp:alt_shift_toggle
+//
+// Settings a device does not support are skipped; anything left unset keeps
+// the libinput default.
+// =============================================================================
+
+use std::collections::BTreeMap;
+
+use smithay::input::keyboard::XkbConfig;
+use smithay::reexports::input::{self as libinput, Device};
+use tracing::{debug, info, warn};
+
+/// Pointer acceleration curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccelProfile {
+    Adaptive,
+    Flat,
+}
+
+/// How a touchpad or mouse generates scroll events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollMethod {
+    TwoFinger,
+    Edge,
+    OnButtonDown,
+    None,
+}
+
+/// Accepted `accel_profile` values
+pub const ACCEL_PROFILES: &[&str] = &["adaptive", "flat"];
+
+/// Accepted `scroll_method` values
+pub const SCROLL_METHODS: &[&str] = &["two_finger", "edge", "on_button_down", "none"];
+
+/// `[keyboard]` settings; empty strings leave the XKB defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyboardConfig {
+    pub layout: String,
+    pub variant: String,
+    pub options: Option<String>,
+}
+
+impl KeyboardConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "layout" => self.layout = value.to_string(),
+            "variant" => self.variant = value.to_string(),
+            "options" => self.options = Some(value.to_string()),
+            _ => return Err(format!("unknown keyboard setting '{key}'")),
+        }
+        Ok(())
+    }
+
+    pub fn xkb(&self) -> XkbConfig<'_> {
+        XkbConfig {
+            layout: &self.layout,
+            variant: &self.variant,
+            options: self.options.clone(),
+            ..XkbConfig::default()
+        }
+    }
+}
+
+/// Settings for one device (or the defaults); None = libinput default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceSettings {
+    pub tap: Option<bool>,
+    pub natural_scroll: Option<bool>,
+    pub accel_profile: Option<AccelProfile>,
+    pub accel_speed: Option<f64>,
+    pub scroll_method: Option<ScrollMethod>,
+}
+
+impl DeviceSettings {
+    /// Set a key from the config file. Values are expected to have passed
+    /// schema validation; anything else is rejected.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "tap" => self.tap = Some(parse_bool(value)?),
+            "natural_scroll" => self.natural_scroll = Some(parse_bool(value)?),
+            "accel_profile" => {
+                self.accel_profile = Some(match value {
+                    "adaptive" => AccelProfile::Adaptive,
+                    "flat" => AccelProfile::Flat,
+                    _ => return Err(format!("invalid accel_profile '{value}'")),
+                })
+            }
+            "accel_speed" => {
+                let speed: f64 = value
+                    .parse()
+                    .map_err(|_| format!("invalid accel_speed '{value}'"))?;
+                self.accel_speed = Some(speed.clamp(-1.0, 1.0));
+            }
+            "scroll_method" => {
+                self.scroll_method = Some(match value {
+                    "two_finger" => ScrollMethod::TwoFinger,
+                    "edge" => ScrollMethod::Edge,
+                    "on_button_down" => ScrollMethod::OnButtonDown,
+                    "none" => ScrollMethod::None,
+                    _ => return Err(format!("invalid scroll_method '{value}'")),
+                })
+            }
+            _ => return Err(format!("unknown input setting '{key}'")),
+        }
+        Ok(())
+    }
+
+    /// Layer `other` on top of these settings
+    fn merged(&self, other: &DeviceSettings) -> DeviceSettings {
+        DeviceSettings {
+            tap: other.tap.or(self.tap),
+            natural_scroll: other.natural_scroll.or(self.natural_scroll),
+            accel_profile: other.accel_profile.or(self.accel_profile),
+            accel_speed: other.accel_speed.or(self.accel_speed),
+            scroll_method: other.scroll_method.or(self.scroll_method),
+        }
+    }
+}
+
+/// `[input]` defaults plus `[device "name"]` overrides
+#[derive(Debug, Clone, Default)]
+pub struct InputConfig {
+    pub defaults: DeviceSettings,
+    /// Per-device overrides keyed by libinput device name
+    pub devices: BTreeMap<String, DeviceSettings>,
+}
+
+impl InputConfig {
+    /// Effective settings for a device name
+    pub fn settings_for(&self, name: &str) -> DeviceSettings {
+        match self.devices.get(name) {
+            Some(overrides) => self.defaults.merged(overrides),
+            None => self.defaults.clone(),
+        }
+    }
+
+    /// Configure a newly added libinput device
+    pub fn apply(&self, device: &mut Device) {
+        let name = device.name().to_string();
+        let settings = self.settings_for(&name);
+        if self.devices.contains_key(&name) {
+            info!("Applying per-device input settings to '{name}'");
+        }
+
+        if let Some(tap) = settings.tap {
+            if device.config_tap_finger_count() > 0 {
+                report(&name, "tap", device.config_tap_set_enabled(tap));
+            }
+        }
+
+        if let Some(natural) = settings.natural_scroll {
+            if device.config_scroll_has_natural_scroll() {
+                report(
+                    &name,
+                    "natural_scroll",
+                    device.config_scroll_set_natural_scroll_enabled(natural),
+                );
+            }
+        }
+
+        if device.config_accel_is_available() {
+            if let Some(profile) = settings.accel_profile {
+                let profile = match profile {
+                    AccelProfile::Adaptive => libinput::AccelProfile::Adaptive,
+                    AccelProfile::Flat => libinput::AccelProfile::Flat,
+                };
+                if device.config_accel_profiles().contains(&profile) {
+                    report(&name, "accel_profile", device.config_accel_set_profile(profile));
+                }
+            }
+            if let Some(speed) = settings.accel_speed {
+                report(&name, "accel_speed", device.config_accel_set_speed(speed));
+            }
+        }
+
+        if let Some(method) = settings.scroll_method {
+            let method = match method {
+                ScrollMethod::TwoFinger => libinput::ScrollMethod::TwoFinger,
+                ScrollMethod::Edge => libinput::ScrollMethod::Edge,
+                ScrollMethod::OnButtonDown => libinput::ScrollMethod::OnButtonDown,
+                ScrollMethod::None => libinput::ScrollMethod::NoScroll,
+            };
+            if device.config_scroll_methods().contains(&method) {
+                report(&name, "scroll_method", device.config_scroll_set_method(method));
+            }
+        }
+    }
+}
+
+fn report(device: &str, setting: &str, result: Result<(), libinput::DeviceConfigError>) {
+    match result {
+        Ok(()) => debug!("{device}: {setting} applied"),
+        Err(e) => warn!("{device}: failed to apply {setting}: {e:?}"),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("expected true or false, got '{value}'")),
+    }
+}
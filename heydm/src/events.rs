@@ -0,0 +1,222 @@
+// =============================================================================
+// heyDM — IPC Event Stream
+//
+// Lets scripts react to the desktop instead of polling, like i3/sway IPC
+// events. Over the IPC socket (ipc.rs):
+//
+//     subscribe events                    → every event
+//     subscribe window workspace output   → only these kinds
+//
+// The reply is {"success": true}, then one JSON object per line for as
+// long as the connection stays open:
+//
+//     {"event": "window", "change": "new" | "close" | "focus" | "title" | "move",
+//      "window": {"id": 4, "title": "...", "app_id": "...", "workspace": 2} | null}
+//     {"event": "workspace", "change": "focus", "current": 3, "old": 1,
+//      "output": "eDP-1" | null}
+//     {"event": "output", "change": "added" | "removed", "name": "HDMI-A-1"}
+//
+// A window "focus" event with a null window means nothing is focused.
+// Events are found by comparing a small snapshot of the state after every
+// loop iteration (next to the status export, see status.rs), so nothing
+// that changes windows has to report it.
+// =============================================================================
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::state::HeyDM;
+
+/// Event kinds a subscriber can ask for
+pub const EVENT_KINDS: &[&str] = &["window", "workspace", "output"];
+
+/// What a window event reports about the window
+#[derive(Debug, Clone, PartialEq)]
+struct WindowInfo {
+    title: Option<String>,
+    app_id: Option<String>,
+    workspace: usize,
+}
+
+/// The parts of the state events are derived from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    windows: BTreeMap<u32, WindowInfo>,
+    focused: Option<u32>,
+    workspace: usize,
+    focused_output: Option<String>,
+    outputs: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn take(state: &HeyDM) -> Self {
+        let wm = &state.window_manager;
+        let windows = wm
+            .windows()
+            .iter()
+            .map(|window| {
+                let (title, app_id) = window.title_and_app_id();
+                (
+                    window.id(),
+                    WindowInfo {
+                        title,
+                        app_id,
+                        workspace: window.workspace(),
+                    },
+                )
+            })
+            .collect();
+        Self {
+            windows,
+            focused: wm.focused_window().map(|window| window.id()),
+            workspace: wm.workspaces().active_workspace(),
+            focused_output: wm.workspaces().focused_output().map(str::to_string),
+            outputs: wm.workspaces().outputs().to_vec(),
+        }
+    }
+
+    /// Events that lead from `old` to `self`, oldest change first
+    fn events_since(&self, old: &Snapshot) -> Vec<(&'static str, Value)> {
+        let mut events = Vec::new();
+        let window = |id: u32, info: &WindowInfo| {
+            json!({
+                "id": id,
+                "title": info.title,
+                "app_id": info.app_id,
+                "workspace": info.workspace,
+            })
+        };
+
+        for name in self.outputs.iter().filter(|name| !old.outputs.contains(name)) {
+            events.push(("output", json!({ "change": "added", "name": name })));
+        }
+        for name in old.outputs.iter().filter(|name| !self.outputs.contains(name)) {
+            events.push(("output", json!({ "change": "removed", "name": name })));
+        }
+
+        for (id, info) in &old.windows {
+            if !self.windows.contains_key(id) {
+                events.push(("window", json!({ "change": "close", "window": window(*id, info) })));
+            }
+        }
+        for (id, info) in &self.windows {
+            let change = match old.windows.get(id) {
+                None => "new",
+                Some(before) if before.workspace != info.workspace => "move",
+                Some(before) if before.title != info.title => "title",
+                Some(_) => continue,
+            };
+            events.push(("window", json!({ "change": change, "window": window(*id, info) })));
+        }
+
+        if self.workspace != old.workspace {
+            events.push((
+                "workspace",
+                json!({
+                    "change": "focus",
+                    "current": self.workspace,
+                    "old": old.workspace,
+                    "output": self.focused_output,
+                }),
+            ));
+        }
+        if self.focused != old.focused {
+            let focused = self
+                .focused
+                .and_then(|id| Some(window(id, self.windows.get(&id)?)));
+            events.push(("window", json!({ "change": "focus", "window": focused })));
+        }
+        events
+    }
+}
+
+/// An event subscription and the kinds it wants
+#[derive(Debug)]
+struct Subscriber {
+    stream: UnixStream,
+    kinds: Vec<&'static str>,
+}
+
+/// IPC connections subscribed to events
+#[derive(Debug, Default)]
+pub struct EventSubscribers {
+    subscribers: Vec<Subscriber>,
+    /// State the last events were computed against
+    last: Option<Snapshot>,
+}
+
+impl EventSubscribers {
+    /// Parse the kinds after `subscribe`; "events" means all of them
+    pub fn parse_kinds(words: &[&str]) -> Result<Vec<&'static str>, String> {
+        if words == ["events"] {
+            return Ok(EVENT_KINDS.to_vec());
+        }
+        words
+            .iter()
+            .map(|word| {
+                EVENT_KINDS
+                    .iter()
+                    .find(|kind| *kind == word)
+                    .copied()
+                    .ok_or_else(|| format!("unknown event '{word}' (expected {})", EVENT_KINDS.join(", ")))
+            })
+            .collect()
+    }
+
+    /// Add a subscriber, confirming the subscription on its stream.
+    /// Events start from `snapshot`.
+    pub fn add(&mut self, stream: UnixStream, kinds: Vec<&'static str>, snapshot: Snapshot) {
+        if stream.set_nonblocking(true).is_err() {
+            return;
+        }
+        let mut stream = stream;
+        if writeln!(stream, "{}", json!({ "success": true })).is_err() {
+            return;
+        }
+        if self.subscribers.is_empty() {
+            self.last = Some(snapshot);
+        }
+        self.subscribers.push(Subscriber { stream, kinds });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    /// Send the events since the last snapshot. Subscribers that went away
+    /// or stopped reading are dropped.
+    pub fn publish(&mut self, snapshot: Snapshot) {
+        let Some(last) = self.last.as_ref() else {
+            self.last = Some(snapshot);
+            return;
+        };
+        if *last == snapshot {
+            return;
+        }
+        let events: Vec<(&str, String)> = snapshot
+            .events_since(last)
+            .into_iter()
+            .map(|(kind, mut event)| {
+                event["event"] = json!(kind);
+                (kind, event.to_string())
+            })
+            .collect();
+        self.subscribers.retain_mut(|subscriber| {
+            for (kind, line) in &events {
+                if !subscriber.kinds.contains(kind) {
+                    continue;
+                }
+                if let Err(e) = writeln!(subscriber.stream, "{line}") {
+                    debug!("Dropping event subscriber: {e}");
+                    return false;
+                }
+            }
+            true
+        });
+        self.last = if self.subscribers.is_empty() { None } else { Some(snapshot) };
+    }
+}
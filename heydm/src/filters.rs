@@ -0,0 +1,491 @@
+// =============================================================================
+// heyDM — Color Filters and Night Light
+//
+// Whole-output color transformations:
+//   - Super+Alt+F cycles the filters: grayscale, inverted colors and
+//     corrections for protanopia and deuteranopia (daltonization)
+//   - Super+Alt+N switches night light, which warms the colors to a lower
+//     color temperature. By default it follows a schedule in local time,
+//     fading in and out over half an hour; the key overrides the schedule
+//     until its next change.
+//
+//     [color]
+//     filter = deuteranopia
+//     night_light = auto          # auto | on | off
+//     night_start = 20:00
+//     night_end = 07:00
+//     night_temperature = 4000
+//     latitude = 52.5              # optional: follow sunset and sunrise
+//     longitude = 13.4             #   here instead of start and end
+//
+// heyDM draws every pixel on screen itself, so a filter is a single color
+// matrix applied to everything it draws: solid colors as the scene is
+// replayed and images and text as they are uploaded (see texture.rs, which
+// uploads everything again when the matrix changes). Tools that set gamma
+// ramps through wlr-gamma-control (see gamma.rs) work as well.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Local, NaiveTime, Timelike};
+use tracing::info;
+
+pub const COLOR_FILTERS: &[&str] = &["none", "grayscale", "invert", "protanopia", "deuteranopia"];
+pub const NIGHT_LIGHT_MODES: &[&str] = &["auto", "on", "off"];
+
+/// Color temperature of unfiltered output
+const NEUTRAL_TEMPERATURE: u32 = 6500;
+/// How long night light takes to fade in or out on its schedule
+const NIGHT_FADE_MINUTES: f64 = 30.0;
+/// How often the schedule is checked
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Machado et al. (2009) simulations of full protanopia and deuteranopia
+const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFilter {
+    #[default]
+    None,
+    Grayscale,
+    Invert,
+    Protanopia,
+    Deuteranopia,
+}
+
+impl ColorFilter {
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "none" => Self::None,
+            "grayscale" => Self::Grayscale,
+            "invert" => Self::Invert,
+            "protanopia" => Self::Protanopia,
+            "deuteranopia" => Self::Deuteranopia,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Grayscale => "grayscale",
+            Self::Invert => "invert",
+            Self::Protanopia => "protanopia",
+            Self::Deuteranopia => "deuteranopia",
+        }
+    }
+
+    /// The next filter for Super+Alt+F
+    fn next(self) -> Self {
+        match self {
+            Self::None => Self::Grayscale,
+            Self::Grayscale => Self::Invert,
+            Self::Invert => Self::Protanopia,
+            Self::Protanopia => Self::Deuteranopia,
+            Self::Deuteranopia => Self::None,
+        }
+    }
+
+    fn matrix(self) -> ColorMatrix {
+        match self {
+            Self::None => ColorMatrix::IDENTITY,
+            Self::Grayscale => {
+                let luma = [0.2126, 0.7152, 0.0722, 0.0];
+                ColorMatrix([luma; 3])
+            }
+            Self::Invert => ColorMatrix([
+                [-1.0, 0.0, 0.0, 1.0],
+                [0.0, -1.0, 0.0, 1.0],
+                [0.0, 0.0, -1.0, 1.0],
+            ]),
+            Self::Protanopia => ColorMatrix::daltonize(PROTANOPIA),
+            Self::Deuteranopia => ColorMatrix::daltonize(DEUTERANOPIA),
+        }
+    }
+}
+
+/// When night light is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NightLightMode {
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+/// `[color]` settings from the config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorConfig {
+    pub filter: ColorFilter,
+    pub night_light: NightLightMode,
+    pub night_start: NaiveTime,
+    pub night_end: NaiveTime,
+    /// Color temperature at full night light, in kelvin
+    pub night_temperature: u32,
+    /// Where the user is, in degrees north
+    pub latitude: Option<f64>,
+    /// Where the user is, in degrees east
+    pub longitude: Option<f64>,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            filter: ColorFilter::None,
+            night_light: NightLightMode::Auto,
+            night_start: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+            night_end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            night_temperature: 4000,
+            latitude: None,
+            longitude: None,
+        }
+    }
+}
+
+impl ColorConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("expected HH:MM, got '{value}'"))
+        };
+        match key {
+            "filter" => {
+                self.filter = ColorFilter::parse(value).ok_or_else(|| format!("unknown color filter '{value}'"))?;
+            }
+            "night_light" => {
+                self.night_light = match value {
+                    "auto" => NightLightMode::Auto,
+                    "on" => NightLightMode::On,
+                    "off" => NightLightMode::Off,
+                    _ => return Err(format!("unknown night light mode '{value}'")),
+                };
+            }
+            "night_start" => self.night_start = time(value)?,
+            "night_end" => self.night_end = time(value)?,
+            "night_temperature" => {
+                let kelvin: u32 = value
+                    .parse()
+                    .map_err(|_| format!("invalid color temperature '{value}'"))?;
+                self.night_temperature = kelvin.clamp(1000, NEUTRAL_TEMPERATURE);
+            }
+            "latitude" | "longitude" => {
+                let degrees: f64 = value.parse().map_err(|_| format!("invalid {key} '{value}'"))?;
+                let limit = if key == "latitude" { 90.0 } else { 180.0 };
+                if !(-limit..=limit).contains(&degrees) {
+                    return Err(format!("{key} must be between -{limit} and {limit}"));
+                }
+                if key == "latitude" {
+                    self.latitude = Some(degrees);
+                } else {
+                    self.longitude = Some(degrees);
+                }
+            }
+            _ => return Err(format!("unknown color setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// An affine color transform: each output channel is a weighted sum of the
+/// input red, green and blue plus an offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorMatrix([[f32; 4]; 3]);
+
+impl ColorMatrix {
+    pub const IDENTITY: Self = Self([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+    ]);
+
+    /// Shift what a color vision deficiency cannot see into channels it
+    /// can: c + E·(c − S·c)
+    fn daltonize(simulation: [[f32; 3]; 3]) -> Self {
+        let error_shift = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+        let mut m = Self::IDENTITY;
+        for (row, shift) in error_shift.iter().enumerate() {
+            for col in 0..3 {
+                let lost: f32 = (0..3)
+                    .map(|k| {
+                        let identity = if k == col { 1.0 } else { 0.0 };
+                        shift[k] * (identity - simulation[k][col])
+                    })
+                    .sum();
+                m.0[row][col] += lost;
+            }
+        }
+        m
+    }
+
+    /// Scale the channels, for a color temperature
+    fn temperature(kelvin: u32) -> Self {
+        let [r, g, b] = temperature_rgb(kelvin);
+        let [nr, ng, nb] = temperature_rgb(NEUTRAL_TEMPERATURE);
+        Self([
+            [r / nr, 0.0, 0.0, 0.0],
+            [0.0, g / ng, 0.0, 0.0],
+            [0.0, 0.0, b / nb, 0.0],
+        ])
+    }
+
+    /// `self` applied after `first`
+    fn then(self, first: Self) -> Self {
+        let mut out = [[0.0; 4]; 3];
+        for (row, out_row) in out.iter_mut().enumerate() {
+            for col in 0..4 {
+                let mut sum: f32 = (0..3).map(|k| self.0[row][k] * first.0[k][col]).sum();
+                if col == 3 {
+                    sum += self.0[row][3];
+                }
+                out_row[col] = sum;
+            }
+        }
+        Self(out)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Transform a straight-alpha RGBA color
+    pub fn apply(&self, color: [f32; 4]) -> [f32; 4] {
+        let [r, g, b] = self.transform([color[0], color[1], color[2]], 1.0);
+        [r, g, b, color[3]]
+    }
+
+    /// Transform premultiplied BGRA pixels in place
+    pub fn apply_premultiplied_bgra(&self, pixels: &mut [u8]) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = pixel[3] as f32 / 255.0;
+            let rgb = [pixel[2], pixel[1], pixel[0]].map(|c| c as f32 / 255.0);
+            let [r, g, b] = self.transform(rgb, alpha);
+            let byte = |c: f32| (c.clamp(0.0, alpha) * 255.0).round() as u8;
+            pixel[0] = byte(b);
+            pixel[1] = byte(g);
+            pixel[2] = byte(r);
+        }
+    }
+
+    /// The offset is scaled by alpha so premultiplied colors stay so
+    fn transform(&self, rgb: [f32; 3], alpha: f32) -> [f32; 3] {
+        let m = &self.0;
+        let channel = |row: usize| {
+            let c = m[row][0] * rgb[0] + m[row][1] * rgb[1] + m[row][2] * rgb[2] + m[row][3] * alpha;
+            c.clamp(0.0, 1.0)
+        };
+        [channel(0), channel(1), channel(2)]
+    }
+}
+
+/// Approximate RGB of a black body at this temperature (Tanner Helland)
+fn temperature_rgb(kelvin: u32) -> [f32; 3] {
+    let t = kelvin as f64 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+    [red, green, blue].map(|c| (c.clamp(0.0, 255.0) / 255.0) as f32)
+}
+
+/// How far into the night `now` is: 0 by day, 1 at full night, in between
+/// while fading
+fn night_strength(now: NaiveTime, start: NaiveTime, end: NaiveTime) -> f64 {
+    let minutes = |time: NaiveTime| (time.hour() * 60 + time.minute()) as f64 + time.second() as f64 / 60.0;
+    let day = 24.0 * 60.0;
+    let length = (minutes(end) - minutes(start)).rem_euclid(day);
+    let since_start = (minutes(now) - minutes(start)).rem_euclid(day);
+    if since_start >= length {
+        return 0.0;
+    }
+    let until_end = length - since_start;
+    (since_start.min(until_end) / NIGHT_FADE_MINUTES).min(1.0)
+}
+
+/// Daylight at a place on one day
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Daylight {
+    /// Local times of sunrise and sunset
+    Sun { sunrise: NaiveTime, sunset: NaiveTime },
+    /// Midnight sun
+    AlwaysDay,
+    /// Polar night
+    AlwaysNight,
+}
+
+/// Sunrise and sunset by the NOAA solar calculator equations. `utc_offset`
+/// is the local time zone in minutes east of UTC.
+fn daylight(day_of_year: u32, latitude: f64, longitude: f64, utc_offset: f64) -> Daylight {
+    use std::f64::consts::PI;
+
+    let gamma = 2.0 * PI / 365.0 * (day_of_year as f64 - 1.0 + 0.5);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    // The sun's center 0.833° below the horizon, for refraction and its size
+    let latitude = latitude.to_radians();
+    let cos_hour_angle = 90.833_f64.to_radians().cos() / (latitude.cos() * declination.cos())
+        - latitude.tan() * declination.tan();
+    if cos_hour_angle > 1.0 {
+        return Daylight::AlwaysNight;
+    }
+    if cos_hour_angle < -1.0 {
+        return Daylight::AlwaysDay;
+    }
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+
+    let noon = 720.0 - 4.0 * longitude - equation_of_time + utc_offset;
+    let time = |minutes: f64| {
+        let seconds = (minutes * 60.0).rem_euclid(24.0 * 3600.0) as u32;
+        NaiveTime::from_num_seconds_from_midnight_opt(seconds, 0).unwrap_or_default()
+    };
+    Daylight::Sun {
+        sunrise: time(noon - 4.0 * hour_angle),
+        sunset: time(noon + 4.0 * hour_angle),
+    }
+}
+
+/// The active filter and night light, and the matrix they make
+#[derive(Debug)]
+pub struct ColorFilters {
+    config: ColorConfig,
+    filter: ColorFilter,
+    /// Night light switched by hand, and what the schedule said then
+    night_override: Option<(bool, bool)>,
+    temperature: u32,
+    matrix: ColorMatrix,
+    last_check: Option<Instant>,
+}
+
+impl ColorFilters {
+    pub fn new(config: &ColorConfig) -> Self {
+        let mut filters = Self {
+            config: config.clone(),
+            filter: config.filter,
+            night_override: None,
+            temperature: NEUTRAL_TEMPERATURE,
+            matrix: ColorMatrix::IDENTITY,
+            last_check: None,
+        };
+        filters.update();
+        filters
+    }
+
+    /// Apply reloaded settings; they replace any key toggles
+    pub fn set_config(&mut self, config: &ColorConfig) {
+        if *config == self.config {
+            return;
+        }
+        self.config = config.clone();
+        self.filter = config.filter;
+        self.night_override = None;
+        self.update();
+    }
+
+    /// Super+Alt+F
+    pub fn cycle_filter(&mut self) {
+        self.filter = self.filter.next();
+        info!("Color filter: {}", self.filter.name());
+        self.update();
+    }
+
+    /// Super+Alt+N: switch night light the other way until the schedule
+    /// changes
+    pub fn toggle_night_light(&mut self) {
+        let scheduled = self.scheduled_strength() > 0.0;
+        let on = self.temperature == NEUTRAL_TEMPERATURE;
+        self.night_override = Some((on, scheduled));
+        info!("Night light {}", if on { "on" } else { "off" });
+        self.update();
+    }
+
+    /// The matrix to draw the next frame with
+    pub fn matrix(&mut self) -> ColorMatrix {
+        let due = match self.last_check {
+            Some(checked) => checked.elapsed() >= SCHEDULE_INTERVAL,
+            None => true,
+        };
+        if due {
+            self.update();
+        }
+        self.matrix
+    }
+
+    /// Whether colors are changed at all
+    pub fn is_active(&self) -> bool {
+        !self.matrix.is_identity()
+    }
+
+    /// Night light strength by the `night_light` setting and the clock
+    fn scheduled_strength(&self) -> f64 {
+        match self.config.night_light {
+            NightLightMode::On => 1.0,
+            NightLightMode::Off => 0.0,
+            NightLightMode::Auto => {
+                let now = Local::now();
+                let (start, end) = match (self.config.latitude, self.config.longitude) {
+                    (Some(latitude), Some(longitude)) => {
+                        let offset = now.offset().local_minus_utc() as f64 / 60.0;
+                        match daylight(now.ordinal(), latitude, longitude, offset) {
+                            Daylight::Sun { sunrise, sunset } => (sunset, sunrise),
+                            Daylight::AlwaysDay => return 0.0,
+                            Daylight::AlwaysNight => return 1.0,
+                        }
+                    }
+                    _ => (self.config.night_start, self.config.night_end),
+                };
+                night_strength(now.time(), start, end)
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        self.last_check = Some(Instant::now());
+        let scheduled = self.scheduled_strength();
+        let strength = match self.night_override {
+            // The schedule moved on since the key was pressed
+            Some((_, then)) if then != (scheduled > 0.0) => {
+                self.night_override = None;
+                scheduled
+            }
+            Some((true, _)) => 1.0,
+            Some((false, _)) => 0.0,
+            None => scheduled,
+        };
+
+        // Whole 100 K steps, so a fade re-uploads textures a few dozen times
+        let range = (NEUTRAL_TEMPERATURE - self.config.night_temperature) as f64;
+        let temperature = NEUTRAL_TEMPERATURE - ((range * strength / 100.0).round() as u32 * 100);
+        self.temperature = temperature;
+        self.matrix = ColorMatrix::temperature(temperature).then(self.filter.matrix());
+    }
+}
@@ -0,0 +1,178 @@
+// =============================================================================
+// heyDM — Gamma Control
+//
+// Server side of wlr-gamma-control-unstable-v1, so night light tools such as
+// gammastep and wlsunset work. A client gets the gamma ramp size of an
+// output and hands over whole red, green and blue ramps; the DRM backend
+// loads them into the CRTC before its next frame. One client controls an
+// output at a time — a second one is told it failed — and the original
+// ramps come back when it lets go.
+//
+// Nested heyDM (winit) has no gamma ramps, so every control fails there.
+// Tools like these replace heyDM's own night light (see filters.rs); set
+// `night_light = off` in [color] when using one.
+// =============================================================================
+
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+
+use smithay::output::Output;
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use tracing::{info, warn};
+
+use crate::state::HeyDM;
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals, unused_imports)]
+#[allow(clippy::all)]
+pub mod protocol {
+    use smithay::reexports::wayland_server;
+    use smithay::reexports::wayland_server::protocol::*;
+
+    pub mod __interfaces {
+        use smithay::reexports::wayland_server::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/wlr-gamma-control-unstable-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("protocols/wlr-gamma-control-unstable-v1.xml");
+}
+
+use protocol::zwlr_gamma_control_manager_v1::{self, ZwlrGammaControlManagerV1};
+use protocol::zwlr_gamma_control_v1::{self, ZwlrGammaControlV1};
+
+/// Version of zwlr_gamma_control_manager_v1 heyDM implements
+const MANAGER_VERSION: u32 = 1;
+
+/// Gamma controls and the ramps waiting for the backend
+#[derive(Debug, Default)]
+pub struct GammaControls {
+    /// Entries per ramp of the driven output; 0 without gamma support
+    size: usize,
+    /// The control holding the driven output
+    owner: Option<ZwlrGammaControlV1>,
+    /// Ramps to load before the next frame: red, green and blue after each
+    /// other, or None to restore the original ones
+    pending: Option<Option<Vec<u16>>>,
+}
+
+impl GammaControls {
+    /// Create the zwlr_gamma_control_manager_v1 global
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<HeyDM, ZwlrGammaControlManagerV1, ()>(MANAGER_VERSION, ());
+        Self::default()
+    }
+
+    /// The backend drives an output with ramps of this size. The current
+    /// control, made for the previous output, stops working.
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+        if let Some(owner) = self.owner.take() {
+            owner.failed();
+        }
+        self.pending = None;
+    }
+
+    /// Ramps to load before the next frame, if they changed
+    pub fn take_pending(&mut self) -> Option<Option<Vec<u16>>> {
+        self.pending.take()
+    }
+
+    /// Read a client's ramps from the start of its file
+    fn read_ramps(&self, fd: std::os::fd::OwnedFd) -> Option<Vec<u16>> {
+        let mut bytes = vec![0u8; self.size * 3 * 2];
+        File::from(fd).read_exact_at(&mut bytes, 0).ok()?;
+        Some(
+            bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_ne_bytes([pair[0], pair[1]]))
+                .collect(),
+        )
+    }
+}
+
+impl GlobalDispatch<ZwlrGammaControlManagerV1, ()> for HeyDM {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwlrGammaControlManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for HeyDM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &ZwlrGammaControlManagerV1,
+        request: zwlr_gamma_control_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_gamma_control_manager_v1::Request::GetGammaControl { id, output } => {
+                let control = data_init.init(id, ());
+                let driven = Output::from_resource(&output)
+                    .is_some_and(|output| Some(&output) == state.output.as_ref());
+                let gamma = &mut state.gamma;
+                if !driven || gamma.size == 0 || gamma.owner.is_some() {
+                    control.failed();
+                    return;
+                }
+                control.gamma_size(gamma.size as u32);
+                gamma.owner = Some(control);
+            }
+            zwlr_gamma_control_manager_v1::Request::Destroy => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, ()> for HeyDM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &ZwlrGammaControlV1,
+        request: zwlr_gamma_control_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            zwlr_gamma_control_v1::Request::SetGamma { fd } => {
+                let gamma = &mut state.gamma;
+                if gamma.owner.as_ref() != Some(resource) {
+                    return;
+                }
+                match gamma.read_ramps(fd) {
+                    Some(ramps) => {
+                        info!("Gamma ramps set by a client");
+                        gamma.pending = Some(Some(ramps));
+                    }
+                    None => {
+                        warn!("Client sent gamma ramps of the wrong size");
+                        resource.post_error(
+                            zwlr_gamma_control_v1::Error::InvalidGamma,
+                            format!("expected {} ramp entries per channel", gamma.size),
+                        );
+                    }
+                }
+            }
+            zwlr_gamma_control_v1::Request::Destroy => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &ZwlrGammaControlV1, _data: &()) {
+        let gamma = &mut state.gamma;
+        if gamma.owner.as_ref() == Some(resource) {
+            gamma.owner = None;
+            gamma.pending = Some(None);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// =============================================================================
+// heyDM — Greeter Mode
+//
+// `heydm --greeter <cmd> [args...]` runs heyDM as a kiosk compositor for the
+// login screen (used by hey-greeter in place of cage):
+//   - <cmd> is started as the only client once the Wayland socket is up
+//   - every toplevel is shown fullscreen; the panel, launcher and workspace
+//     gestures are disabled
+//   - keybindings are off except accessibility ones (Super+Alt+C: large
+//     cursor), so the login screen cannot be escaped
+//   - none of the session's services run: nothing is started that talks to
+//     the session bus, the network or the power supply
+//   - heyDM exits when the client does
+// =============================================================================
+
+use std::ffi::OsStr;
+use std::process::{Command, ExitStatus};
+use std::sync::mpsc;
+
+use calloop::channel::Sender;
+use tracing::info;
+
+/// Start the greeter client; its exit status is sent to `exited` once it
+/// terminates. It is started on the thread that waits for it, which keeps
+/// it out of the main thread's reaping (see session::reap_children).
+pub fn spawn(
+    command: &[String],
+    wayland_display: &OsStr,
+    exited: Sender<std::io::Result<ExitStatus>>,
+) -> std::io::Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("no greeter command given"))?;
+
+    let mut command = Command::new(program);
+    command.args(args).env("WAYLAND_DISPLAY", wayland_display);
+    let (started, pid) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("heydm-greeter".to_string())
+        .spawn(move || match crate::launch::unblock_signals(&mut command).spawn() {
+            Ok(mut child) => {
+                let _ = started.send(Ok(child.id()));
+                let _ = exited.send(child.wait());
+            }
+            Err(e) => {
+                let _ = started.send(Err(e));
+            }
+        })?;
+    let pid = pid
+        .recv()
+        .map_err(|_| std::io::Error::other("greeter thread ended"))??;
+    info!("Greeter client '{program}' started (pid {pid})");
+    Ok(())
+}
@@ -0,0 +1,655 @@
+// =============================================================================
+// heyDM — Headless Backend
+//
+// Runs the compositor without a window or a GPU: frames are drawn with the
+// pixman software renderer into an offscreen image, and input comes from a
+// script instead of devices. Meant for trying out window management, the
+// launcher and the render paths from a terminal or a test script:
+//
+//     heydm --headless --size 1280x800 --script scripts/smoke.hey
+//
+// A script has one command per line (# starts a comment):
+//
+//     spawn foot                # start a client on heyDM's socket
+//     wait 500                  # keep the loop running for 500 ms
+//     key super+d               # press and release, modifiers first
+//     type firefox              # letters, digits and spaces
+//     move 640 400              # pointer to output coordinates
+//     click                     # left (default) or right
+//     screenshot /tmp/a.png     # the current frame as a PNG
+//     expect windows 1          # fail unless one window is mapped
+//     expect focused foot       # ... unless this app id has focus
+//     expect launcher open      # ... unless the launcher is (not) open
+//     exit                      # stop, like the end of the script
+//
+// Input goes through the same handler as libinput and winit events (see
+// input.rs), by way of a small InputBackend whose events the script makes.
+// scripts/smoke.hey is a sample that opens the launcher and a terminal.
+// heyDM exits with an error at the first failed `expect`, and logs the
+// frame metrics (see perf.rs, always on here) when it stops. Without
+// --script it serves clients (e.g. driven over IPC, see ipc.rs) until it is
+// killed.
+// =============================================================================
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use calloop::EventLoop;
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::input::{
+    AbsolutePositionEvent, ButtonState, Device, DeviceCapability, Event, InputBackend, InputEvent,
+    KeyState, KeyboardKeyEvent, Keycode, PointerButtonEvent, PointerMotionAbsoluteEvent,
+    UnusedEvent,
+};
+use smithay::backend::renderer::pixman::PixmanRenderer;
+use smithay::backend::renderer::{Bind, ExportMem, Frame, Renderer as _};
+use smithay::reexports::pixman;
+use smithay::reexports::wayland_server::Display;
+use smithay::utils::{Physical, Rectangle, Size, Transform};
+use tracing::{info, warn};
+
+use crate::input::InputHandler;
+use crate::render::{Renderer, Scene};
+use crate::state::HeyDM;
+use crate::texture::TextureCache;
+
+/// Output size without --size
+const DEFAULT_SIZE: (i32, i32) = (1280, 800);
+
+/// Time between frames
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Linux input event codes of the mouse buttons
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// xkb keycodes are evdev codes plus this
+const XKB_OFFSET: u32 = 8;
+
+/// `--headless` and its options
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub size: Size<i32, Physical>,
+    pub script: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    /// Options from the command line, None without --headless
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+        let value = |flag: &str| {
+            args.iter()
+                .position(|arg| arg == flag)
+                .and_then(|idx| args.get(idx + 1))
+        };
+        let size = match value("--size") {
+            Some(size) => {
+                let (w, h) = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .filter(|&(w, h): &(i32, i32)| w > 0 && h > 0)
+                    .ok_or_else(|| format!("invalid --size '{size}', expected WIDTHxHEIGHT"))?;
+                (w, h).into()
+            }
+            None => DEFAULT_SIZE.into(),
+        };
+        Ok(Some(Self {
+            size,
+            script: value("--script").map(PathBuf::from),
+        }))
+    }
+}
+
+/// One line of a script
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Spawn(String),
+    Wait(Duration),
+    Key(Vec<u32>),
+    Type(String),
+    Move(f64, f64),
+    Click(u32),
+    Screenshot(PathBuf),
+    Expect(Expectation),
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expectation {
+    Windows(usize),
+    Focused(String),
+    Launcher(bool),
+}
+
+/// Read a script; errors name the line
+fn parse_script(path: &Path) -> Result<Vec<Step>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut steps = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let step = parse_step(command, rest)
+            .map_err(|message| format!("{}:{}: {message}", path.display(), number + 1))?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+fn parse_step(command: &str, rest: &str) -> Result<Step, String> {
+    let number = |value: &str| value.parse::<f64>().map_err(|_| format!("invalid number '{value}'"));
+    Ok(match command {
+        "spawn" if !rest.is_empty() => Step::Spawn(rest.to_string()),
+        "wait" => Step::Wait(Duration::from_millis(number(rest)? as u64)),
+        "key" => Step::Key(
+            rest.split('+')
+                .map(|name| key_code(name).ok_or_else(|| format!("unknown key '{name}'")))
+                .collect::<Result<_, _>>()?,
+        ),
+        "type" => Step::Type(rest.to_string()),
+        "move" => match rest.split_whitespace().collect::<Vec<_>>()[..] {
+            [x, y] => Step::Move(number(x)?, number(y)?),
+            _ => return Err("expected: move <x> <y>".to_string()),
+        },
+        "click" => match rest {
+            "" | "left" => Step::Click(BTN_LEFT),
+            "right" => Step::Click(BTN_RIGHT),
+            _ => return Err(format!("unknown button '{rest}'")),
+        },
+        "screenshot" if !rest.is_empty() => Step::Screenshot(PathBuf::from(rest)),
+        "expect" => Step::Expect(match rest.split_once(' ') {
+            Some(("windows", count)) => {
+                Expectation::Windows(count.parse().map_err(|_| format!("invalid count '{count}'"))?)
+            }
+            Some(("focused", app_id)) => Expectation::Focused(app_id.to_string()),
+            Some(("launcher", "open")) => Expectation::Launcher(true),
+            Some(("launcher", "closed")) => Expectation::Launcher(false),
+            _ => return Err(format!("unknown expectation '{rest}'")),
+        }),
+        "exit" => Step::Exit,
+        _ => return Err(format!("unknown command '{command} {rest}'")),
+    })
+}
+
+/// evdev code of a key name, as used by `key`
+fn key_code(name: &str) -> Option<u32> {
+    const LETTERS: &[u32] = &[
+        30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47,
+        17, 45, 21, 44,
+    ];
+    let name = name.trim().to_ascii_lowercase();
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'a'..='z' => Some(LETTERS[(c as u8 - b'a') as usize]),
+            '1'..='9' => Some(2 + (c as u8 - b'1') as u32),
+            '0' => Some(11),
+            '`' => Some(41),
+            _ => None,
+        };
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        return match n {
+            1..=10 => Some(58 + n),
+            11 => Some(87),
+            12 => Some(88),
+            _ => None,
+        };
+    }
+    Some(match name.as_str() {
+        "super" | "logo" | "meta" => 125,
+        "ctrl" | "control" => 29,
+        "alt" => 56,
+        "shift" => 42,
+        "return" | "enter" => 28,
+        "escape" | "esc" => 1,
+        "tab" => 15,
+        "space" => 57,
+        "backspace" => 14,
+        "delete" => 111,
+        "up" => 103,
+        "down" => 108,
+        "left" => 105,
+        "right" => 106,
+        "home" => 102,
+        "end" => 107,
+        "grave" => 41,
+        _ => return None,
+    })
+}
+
+// ---- Synthetic input ----
+
+/// Input backend whose events come from the script
+#[derive(Debug)]
+pub struct ScriptInput;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScriptDevice;
+
+impl Device for ScriptDevice {
+    fn id(&self) -> String {
+        "heydm-script".to_string()
+    }
+    fn name(&self) -> String {
+        "heyDM script input".to_string()
+    }
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        matches!(capability, DeviceCapability::Keyboard | DeviceCapability::Pointer)
+    }
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        None
+    }
+    fn syspath(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+pub struct ScriptKey {
+    code: u32,
+    state: KeyState,
+    time: u64,
+}
+
+impl Event<ScriptInput> for ScriptKey {
+    fn time(&self) -> u64 {
+        self.time
+    }
+    fn device(&self) -> ScriptDevice {
+        ScriptDevice
+    }
+}
+
+impl KeyboardKeyEvent<ScriptInput> for ScriptKey {
+    fn key_code(&self) -> Keycode {
+        Keycode::new(self.code + XKB_OFFSET)
+    }
+    fn state(&self) -> KeyState {
+        self.state
+    }
+    fn count(&self) -> u32 {
+        u32::from(self.state == KeyState::Pressed)
+    }
+}
+
+/// Pointer position in output pixels
+pub struct ScriptMotion {
+    x: f64,
+    y: f64,
+    size: Size<i32, Physical>,
+    time: u64,
+}
+
+impl Event<ScriptInput> for ScriptMotion {
+    fn time(&self) -> u64 {
+        self.time
+    }
+    fn device(&self) -> ScriptDevice {
+        ScriptDevice
+    }
+}
+
+impl AbsolutePositionEvent<ScriptInput> for ScriptMotion {
+    fn x(&self) -> f64 {
+        self.x
+    }
+    fn y(&self) -> f64 {
+        self.y
+    }
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.x * f64::from(width) / f64::from(self.size.w)
+    }
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.y * f64::from(height) / f64::from(self.size.h)
+    }
+}
+
+impl PointerMotionAbsoluteEvent<ScriptInput> for ScriptMotion {}
+
+pub struct ScriptButton {
+    button: u32,
+    state: ButtonState,
+    time: u64,
+}
+
+impl Event<ScriptInput> for ScriptButton {
+    fn time(&self) -> u64 {
+        self.time
+    }
+    fn device(&self) -> ScriptDevice {
+        ScriptDevice
+    }
+}
+
+impl PointerButtonEvent<ScriptInput> for ScriptButton {
+    fn button_code(&self) -> u32 {
+        self.button
+    }
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+impl InputBackend for ScriptInput {
+    type Device = ScriptDevice;
+    type KeyboardKeyEvent = ScriptKey;
+    type PointerAxisEvent = UnusedEvent;
+    type PointerButtonEvent = ScriptButton;
+    type PointerMotionEvent = UnusedEvent;
+    type PointerMotionAbsoluteEvent = ScriptMotion;
+    type GestureSwipeBeginEvent = UnusedEvent;
+    type GestureSwipeUpdateEvent = UnusedEvent;
+    type GestureSwipeEndEvent = UnusedEvent;
+    type GesturePinchBeginEvent = UnusedEvent;
+    type GesturePinchUpdateEvent = UnusedEvent;
+    type GesturePinchEndEvent = UnusedEvent;
+    type GestureHoldBeginEvent = UnusedEvent;
+    type GestureHoldEndEvent = UnusedEvent;
+    type TouchDownEvent = UnusedEvent;
+    type TouchUpEvent = UnusedEvent;
+    type TouchMotionEvent = UnusedEvent;
+    type TouchCancelEvent = UnusedEvent;
+    type TouchFrameEvent = UnusedEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+    type SwitchToggleEvent = UnusedEvent;
+    type SpecialEvent = UnusedEvent;
+}
+
+/// Feeds script input to the input handler
+struct Driver {
+    started: Instant,
+}
+
+impl Driver {
+    fn time(&self) -> u64 {
+        self.started.elapsed().as_micros() as u64
+    }
+
+    fn send(&self, state: &mut HeyDM, event: InputEvent<ScriptInput>) {
+        crate::recovery::guard(state, "script input", |state| {
+            InputHandler::handle_input(state, event)
+        });
+    }
+
+    fn key(&self, state: &mut HeyDM, code: u32, key_state: KeyState) {
+        let time = self.time();
+        self.send(state, InputEvent::Keyboard {
+            event: ScriptKey { code, state: key_state, time },
+        });
+    }
+
+    /// Press the keys in order, then release them in reverse
+    fn chord(&self, state: &mut HeyDM, codes: &[u32]) {
+        for &code in codes {
+            self.key(state, code, KeyState::Pressed);
+        }
+        for &code in codes.iter().rev() {
+            self.key(state, code, KeyState::Released);
+        }
+    }
+
+    fn type_text(&self, state: &mut HeyDM, text: &str) {
+        let shift = key_code("shift").unwrap_or(42);
+        for c in text.chars() {
+            let name = if c == ' ' { "space".to_string() } else { c.to_string() };
+            let Some(code) = key_code(&name) else {
+                warn!("Script: cannot type '{c}'");
+                continue;
+            };
+            if c.is_ascii_uppercase() {
+                self.chord(state, &[shift, code]);
+            } else {
+                self.chord(state, &[code]);
+            }
+        }
+    }
+
+    fn move_to(&self, state: &mut HeyDM, x: f64, y: f64) {
+        let size = state.output_size;
+        let time = self.time();
+        self.send(state, InputEvent::PointerMotionAbsolute {
+            event: ScriptMotion { x, y, size, time },
+        });
+    }
+
+    fn click(&self, state: &mut HeyDM, button: u32) {
+        for button_state in [ButtonState::Pressed, ButtonState::Released] {
+            let time = self.time();
+            self.send(state, InputEvent::PointerButton {
+                event: ScriptButton { button, state: button_state, time },
+            });
+        }
+    }
+}
+
+fn check(state: &HeyDM, expectation: &Expectation) -> Result<(), String> {
+    match expectation {
+        Expectation::Windows(count) => {
+            let mapped = state.window_manager.windows().len();
+            if mapped != *count {
+                return Err(format!("expected {count} windows, found {mapped}"));
+            }
+        }
+        Expectation::Focused(app_id) => {
+            let focused = state
+                .window_manager
+                .focused_window()
+                .and_then(|window| window.title_and_app_id().1);
+            if focused.as_deref() != Some(app_id.as_str()) {
+                return Err(format!("expected {app_id} to have focus, found {focused:?}"));
+            }
+        }
+        Expectation::Launcher(open) => {
+            if state.launcher.is_visible() != *open {
+                return Err(format!(
+                    "expected the launcher to be {}",
+                    if *open { "open" } else { "closed" }
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Offscreen target and the software renderer drawing into it
+struct Offscreen {
+    renderer: PixmanRenderer,
+    image: pixman::Image<'static, 'static>,
+    textures: TextureCache<<PixmanRenderer as smithay::backend::renderer::Renderer>::TextureId>,
+    scene: Scene,
+}
+
+impl Offscreen {
+    fn new(size: Size<i32, Physical>) -> Result<Self, Box<dyn std::error::Error>> {
+        let image = pixman::Image::new(pixman::FormatCode::A8R8G8B8, size.w as usize, size.h as usize, true)
+            .map_err(|_| "cannot allocate the offscreen image")?;
+        Ok(Self {
+            renderer: PixmanRenderer::new()?,
+            image,
+            textures: TextureCache::new(),
+            scene: Scene::default(),
+        })
+    }
+
+    /// Draw a frame; with `save`, also write it out as a PNG
+    fn render(&mut self, state: &mut HeyDM, save: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        let size = state.output_size;
+        Renderer::build_scene(state, size, &mut self.scene);
+        self.textures
+            .prepare(&mut self.renderer, &state.text, &self.scene, state.color.matrix())?;
+
+        let mut target = self.renderer.bind(&mut self.image)?;
+        {
+            let mut frame = self.renderer.render(&mut target, size, Transform::Normal)?;
+            let view = state.zoom.frame(state.window_manager.cursor_position(), size);
+            Renderer::draw_scene(&mut frame, &self.scene, &self.textures, view)?;
+            let _ = frame.finish()?;
+        }
+        state.latency.frame_submitted(false);
+        state.perf.frame_submitted(started.elapsed(), size.w as u64 * size.h as u64);
+
+        if let Some(path) = save {
+            let region = Rectangle::from_size((size.w, size.h).into());
+            let mapping = self.renderer.copy_framebuffer(&target, region, Fourcc::Argb8888)?;
+            let pixels = self.renderer.map_texture(&mapping)?;
+            // Argb8888 is B, G, R, A in memory
+            let rgba: Vec<u8> = pixels
+                .chunks_exact(4)
+                .flat_map(|px| [px[2], px[1], px[0], px[3]])
+                .collect();
+            let image = image::RgbaImage::from_raw(size.w as u32, size.h as u32, rgba)
+                .ok_or("screenshot has the wrong size")?;
+            image.save(path)?;
+            info!("Screenshot saved to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Run heyDM headless until the script ends or the loop is stopped
+pub fn run(
+    event_loop: &mut EventLoop<HeyDM>,
+    display: &mut Display<HeyDM>,
+    state: &mut HeyDM,
+    socket_name: std::ffi::OsString,
+    options: HeadlessOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let steps = match &options.script {
+        Some(path) => parse_script(path)?,
+        None => Vec::new(),
+    };
+    std::env::set_var("WAYLAND_DISPLAY", &socket_name);
+
+    state.output_size = options.size;
+    let output = smithay::output::Output::new(
+        "HEADLESS-1".to_string(),
+        smithay::output::PhysicalProperties {
+            size: (0, 0).into(),
+            subpixel: smithay::output::Subpixel::Unknown,
+            make: "heyOS".into(),
+            model: "headless".into(),
+            serial_number: String::new(),
+        },
+    );
+    let mode = smithay::output::Mode {
+        size: options.size,
+        refresh: 60_000,
+    };
+    output.change_current_state(Some(mode), Some(Transform::Normal), None, Some((0, 0).into()));
+    output.set_preferred(mode);
+    output.create_global::<HeyDM>(&state.display_handle);
+    state.output = Some(output.clone());
+    state.apply_output_config();
+    state
+        .window_manager
+        .output_added(&output.name(), None, &state.output_size);
+    state.wallpaper.select_output(&output.name(), state.output_size);
+    state.perf.set_enabled(true);
+    state.latency.set_enabled(true);
+
+    info!(
+        "Headless backend started, output size: {}x{}",
+        options.size.w, options.size.h
+    );
+
+    let mut runner = Runner {
+        steps: steps.into_iter(),
+        scripted: options.script.is_some(),
+        waiting_until: None,
+        driver: Driver {
+            started: Instant::now(),
+        },
+        offscreen: Offscreen::new(options.size)?,
+    };
+    let mut result: Result<(), Box<dyn std::error::Error>> = Ok(());
+    event_loop.run(Some(FRAME), state, |state| {
+        let ticked = runner.tick(state).and_then(|running| {
+            crate::recovery::guard(state, "client dispatch", |state| display.dispatch_clients(state))
+                .transpose()?;
+            display.flush_clients()?;
+            Ok(running)
+        });
+        state.publish_status();
+        state.publish_events();
+        crate::shell::publish_workspaces(state);
+        match ticked {
+            Ok(true) => {}
+            Ok(false) => state.loop_signal.stop(),
+            Err(e) => {
+                result = Err(e);
+                state.loop_signal.stop();
+            }
+        }
+    })?;
+
+    let summary = state.perf.summary();
+    let ms = |stats: Option<crate::latency::LatencyStats>| stats.map_or(0.0, |s| s.p50.as_secs_f64() * 1000.0);
+    info!(
+        "Headless run: {} fps, frame {:.1} ms, render {:.1} ms (p50)",
+        summary.fps,
+        ms(summary.frame_time),
+        ms(summary.render_time)
+    );
+    result
+}
+
+/// Script progress and the offscreen output between loop iterations
+struct Runner {
+    steps: std::vec::IntoIter<Step>,
+    scripted: bool,
+    waiting_until: Option<Instant>,
+    driver: Driver,
+    offscreen: Offscreen,
+}
+
+impl Runner {
+    /// Run the script up to the next wait and draw a frame. Returns false
+    /// once the script is done.
+    fn tick(&mut self, state: &mut HeyDM) -> Result<bool, Box<dyn std::error::Error>> {
+        while self.waiting_until.is_none_or(|until| Instant::now() >= until) {
+            self.waiting_until = None;
+            let Some(step) = self.steps.next() else {
+                if self.scripted {
+                    info!("Script finished");
+                    return Ok(false);
+                }
+                break;
+            };
+            let driver = &self.driver;
+            match step {
+                Step::Spawn(command) => match shlex::split(&command) {
+                    Some(argv) if !argv.is_empty() => {
+                        let mut program = Command::new(&argv[0]);
+                        program.args(&argv[1..]);
+                        if let Err(e) = crate::launch::unblock_signals(&mut program).spawn() {
+                            warn!("Script: cannot start {command}: {e}");
+                        }
+                    }
+                    _ => warn!("Script: invalid command '{command}'"),
+                },
+                Step::Wait(duration) => self.waiting_until = Some(Instant::now() + duration),
+                Step::Key(codes) => driver.chord(state, &codes),
+                Step::Type(text) => driver.type_text(state, &text),
+                Step::Move(x, y) => driver.move_to(state, x, y),
+                Step::Click(button) => driver.click(state, button),
+                Step::Screenshot(path) => self.offscreen.render(state, Some(&path))?,
+                Step::Expect(expectation) => {
+                    check(state, &expectation).map_err(|message| format!("Script failed: {message}"))?;
+                }
+                Step::Exit => return Ok(false),
+            }
+        }
+        self.offscreen.render(state, None)?;
+        Ok(true)
+    }
+}
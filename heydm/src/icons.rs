@@ -0,0 +1,244 @@
+// =============================================================================
+// heyDM — Application Icons
+//
+// Resolves the Icon= names of .desktop files following the freedesktop icon
+// theme layout:
+//   - absolute paths are used as they are
+//   - otherwise the configured theme (`[appearance] icon_theme`), the themes
+//     it inherits from and finally hicolor are searched in
+//     $XDG_DATA_HOME/icons, ~/.icons and $XDG_DATA_DIRS/icons, preferring
+//     sizes close to ICON_SIZE and scalable SVGs over small bitmaps
+//   - /usr/share/pixmaps is the last resort
+//
+// PNG and SVG files are decoded and scaled to ICON_SIZE (times the UI scale)
+// on a worker thread; every icon comes back through a calloop channel as soon
+// as it is ready.
+// =============================================================================
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use calloop::channel::Sender;
+use image::imageops::FilterType;
+use resvg::tiny_skia;
+use tracing::{debug, info, warn};
+
+use crate::texture::{Image, PixelBuffer};
+
+/// Edge length of icons in the launcher grid at scale 1
+pub const ICON_SIZE: u32 = 40;
+
+/// Theme size directories in order of preference
+const SIZE_DIRS: &[&str] = &[
+    "48x48", "scalable", "64x64", "96x96", "128x128", "256x256", "512x512", "32x32", "24x24",
+];
+
+/// Extensions tried for every size directory
+const EXTENSIONS: &[&str] = &["png", "svg"];
+
+/// Theme contexts searched for application icons
+const APP_CONTEXTS: &[&str] = &["apps"];
+
+/// A decoded icon (None if it could not be found or decoded)
+#[derive(Debug)]
+pub struct LoadedIcon {
+    pub name: String,
+    pub image: Option<Image>,
+}
+
+/// Resolve and decode `names` at `size` pixels in the background, sending
+/// each result to `results`
+pub fn load_all(
+    names: Vec<String>,
+    theme: Option<String>,
+    size: u32,
+    results: Sender<LoadedIcon>,
+) {
+    let spawned = std::thread::Builder::new()
+        .name("heydm-icons".to_string())
+        .spawn(move || {
+            let bases = base_dirs();
+            let themes = theme_chain(theme.as_deref().unwrap_or("hicolor"), &bases);
+            let mut found = 0;
+            for name in names {
+                let image = lookup(&name, &themes, &bases, APP_CONTEXTS).and_then(|path| {
+                    decode(&path, size)
+                        .inspect_err(|e| debug!("Icon {}: {e}", path.display()))
+                        .ok()
+                });
+                found += usize::from(image.is_some());
+                if results.send(LoadedIcon { name, image }).is_err() {
+                    return;
+                }
+            }
+            info!("Loaded {found} application icons (themes: {})", themes.join(", "));
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start icon loader: {e}");
+    }
+}
+
+/// Resolve and decode a single icon on the calling thread, searching the
+/// given theme contexts ("status", "devices", ...). `extra_dir` is searched
+/// before the usual places, both as a theme base and for loose files.
+pub fn load_one(
+    name: &str,
+    theme: Option<&str>,
+    contexts: &[&str],
+    extra_dir: Option<&Path>,
+    size: u32,
+) -> Option<Image> {
+    let mut bases = base_dirs();
+    if let Some(dir) = extra_dir.filter(|dir| dir.is_dir()) {
+        let loose = EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{name}.{ext}")))
+            .find(|candidate| candidate.is_file());
+        if let Some(path) = loose {
+            return decode(&path, size).ok();
+        }
+        bases.insert(0, dir.to_path_buf());
+    }
+    let themes = theme_chain(theme.unwrap_or("hicolor"), &bases);
+    let path = lookup(name, &themes, &bases, contexts)?;
+    decode(&path, size)
+        .inspect_err(|e| debug!("Icon {}: {e}", path.display()))
+        .ok()
+}
+
+/// Directories that contain icon themes, most specific first
+fn base_dirs() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .map(|dir| dir.join("icons"))
+        .into_iter()
+        .chain(home.map(|home| home.join(".icons")))
+        .chain(data_dirs.split(':').map(|dir| Path::new(dir).join("icons")))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// The theme, everything it inherits from (depth first), then hicolor
+fn theme_chain(theme: &str, bases: &[PathBuf]) -> Vec<String> {
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue = vec![theme.to_string()];
+    while let Some(theme) = queue.pop() {
+        if !seen.insert(theme.clone()) {
+            continue;
+        }
+        let inherits = inherited_themes(&theme, bases);
+        chain.push(theme);
+        queue.extend(inherits.into_iter().rev());
+    }
+    if !seen.contains("hicolor") {
+        chain.push("hicolor".to_string());
+    }
+    chain
+}
+
+/// `Inherits=` of the first index.theme found for a theme
+fn inherited_themes(theme: &str, bases: &[PathBuf]) -> Vec<String> {
+    let Some(content) = bases
+        .iter()
+        .find_map(|base| fs::read_to_string(base.join(theme).join("index.theme")).ok())
+    else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Inherits="))
+        .map(|themes| {
+            themes
+                .split(',')
+                .map(str::trim)
+                .filter(|theme| !theme.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Find the file of an icon name in one of the theme `contexts`
+fn lookup(name: &str, themes: &[String], bases: &[PathBuf], contexts: &[&str]) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    for theme in themes {
+        for base in bases {
+            let dir = base.join(theme);
+            if !dir.is_dir() {
+                continue;
+            }
+            for size in SIZE_DIRS {
+                for context in contexts {
+                    for ext in EXTENSIONS {
+                        let candidate = dir.join(size).join(context).join(format!("{name}.{ext}"));
+                        if candidate.is_file() {
+                            return Some(candidate);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    EXTENSIONS
+        .iter()
+        .map(|ext| Path::new("/usr/share/pixmaps").join(format!("{name}.{ext}")))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Decode a PNG or SVG icon at `size` × `size`
+fn decode(path: &Path, size: u32) -> Result<Image, String> {
+    if path.extension().is_some_and(|ext| ext == "svg") {
+        return decode_svg(path, size);
+    }
+    let decoded = image::open(path).map_err(|e| e.to_string())?;
+    let scaled = decoded
+        .resize(size, size, FilterType::Triangle)
+        .to_rgba8();
+    Ok(Image::new(PixelBuffer::from_rgba(
+        scaled.width(),
+        scaled.height(),
+        scaled.as_raw(),
+    )))
+}
+
+/// Rasterize an SVG icon, fitted into `icon_size` × `icon_size`
+fn decode_svg(path: &Path, icon_size: u32) -> Result<Image, String> {
+    let data = fs::read(path).map_err(|e| e.to_string())?;
+    let tree = resvg::usvg::Tree::from_data(&data, &resvg::usvg::Options::default())
+        .map_err(|e| e.to_string())?;
+
+    let size = tree.size();
+    let scale = icon_size as f32 / size.width().max(size.height());
+    let width = ((size.width() * scale).round() as u32).max(1);
+    let height = ((size.height() * scale).round() as u32).max(1);
+    let mut pixmap =
+        tiny_skia::Pixmap::new(width, height).ok_or_else(|| "invalid icon size".to_string())?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    Ok(Image::new(PixelBuffer::from_premultiplied_rgba(
+        width,
+        height,
+        pixmap.data(),
+    )))
+}
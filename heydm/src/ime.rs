@@ -0,0 +1,139 @@
+// =============================================================================
+// heyDM — Input Method Popups
+//
+// Input methods (fcitx5, ibus) show their candidate list in a popup surface
+// (zwp_input_popup_surface_v2) attached to the text field being edited.
+// heyDM keeps those popups here:
+//   - each commit copies the popup's shm buffer, like client cursor images
+//     (see cursor.rs), and tells the input method it can draw the next one
+//   - the popup is placed right below the text cursor rectangle the text
+//     field reported (text-input-v3 set_cursor_rectangle), flipped above it
+//     when there is no room below and kept inside the window's output
+//   - the renderer draws it above the windows while its window is visible
+// =============================================================================
+
+use std::sync::Arc;
+
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Physical, Point, Size};
+use smithay::wayland::compositor::{with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::input_method::PopupSurface;
+use smithay::wayland::shm::with_buffer_contents;
+use tracing::debug;
+
+use crate::cursor::copy_shm_image;
+use crate::texture::Image;
+use crate::window::WindowManager;
+
+/// A candidate popup and its last committed image
+struct ImePopup {
+    surface: PopupSurface,
+    image: Option<Image>,
+}
+
+/// The input method's open popups
+#[derive(Default)]
+pub struct ImePopups {
+    popups: Vec<ImePopup>,
+}
+
+impl ImePopups {
+    /// Start tracking a new popup; it shows once it commits a buffer
+    pub fn add(&mut self, surface: PopupSurface) {
+        debug!("Input method popup created");
+        self.popups.retain(|popup| popup.surface.alive());
+        self.popups.push(ImePopup {
+            surface,
+            image: None,
+        });
+    }
+
+    /// The input method moved the popup (its text field's cursor moved)
+    pub fn repositioned(&mut self, surface: PopupSurface) {
+        if let Some(popup) = self.find(surface.wl_surface()) {
+            popup.surface = surface;
+        }
+    }
+
+    /// The popup was dismissed or destroyed
+    pub fn remove(&mut self, surface: &PopupSurface) {
+        let surface = surface.wl_surface();
+        self.popups
+            .retain(|popup| popup.surface.alive() && popup.surface.wl_surface() != surface);
+    }
+
+    /// Copy the newly committed buffer if `surface` is a popup. Returns
+    /// false for every other surface.
+    pub fn surface_committed(&mut self, surface: &WlSurface) -> bool {
+        let Some(popup) = self.find(surface) else {
+            return false;
+        };
+        let buffer = with_states(surface, |states| {
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            attributes.current().buffer.take()
+        });
+        match buffer {
+            Some(BufferAssignment::NewBuffer(buffer)) => {
+                let image = with_buffer_contents(&buffer, |ptr, len, data| {
+                    copy_shm_image(ptr, len, data)
+                });
+                buffer.release();
+                match image {
+                    Ok(Some(image)) => popup.image = Some(Arc::new(image)),
+                    Ok(None) => debug!("Unsupported input method popup buffer format"),
+                    Err(e) => debug!("Input method popup buffer is not readable: {e:?}"),
+                }
+            }
+            Some(BufferAssignment::Removed) => popup.image = None,
+            None => {}
+        }
+        true
+    }
+
+    /// Whether any popup would be drawn
+    pub fn is_visible(&self) -> bool {
+        self.popups.iter().any(|popup| popup.image.is_some() && popup.surface.alive())
+    }
+
+    /// Popups of visible windows with their top-left corner in output
+    /// pixels; `scale` is the output scale the clients use
+    pub fn placed<'a>(
+        &'a self,
+        windows: &'a WindowManager,
+        scale: f64,
+        output_size: Size<i32, Physical>,
+    ) -> impl Iterator<Item = (Point<i32, Physical>, &'a Image)> + 'a {
+        self.popups.iter().filter_map(move |popup| {
+            let image = popup.image.as_ref()?;
+            let parent = popup.surface.get_parent()?;
+            let window = windows
+                .window_for_surface(&parent.surface)
+                .filter(|window| windows.is_window_visible(window))?;
+
+            // The cursor rectangle is in the text field's surface coordinates
+            let origin = window.geometry().loc;
+            let cursor = popup.surface.text_input_rectangle();
+            let px = |value: i32| (value as f64 * scale).round() as i32;
+            let x = origin.x + px(cursor.loc.x);
+            let top = origin.y + px(cursor.loc.y);
+            let bottom = top + px(cursor.size.h);
+
+            let area = windows.output_area_of(window, &output_size);
+            let (right, lowest) = (area.loc.x + area.size.w, area.loc.y + area.size.h);
+            let y = if bottom + image.height > lowest && top - image.height >= area.loc.y {
+                top - image.height
+            } else {
+                bottom
+            };
+            let x = x.min(right - image.width).max(area.loc.x);
+            let y = y.min(lowest - image.height).max(area.loc.y);
+            Some((Point::from((x, y)), image))
+        })
+    }
+
+    fn find(&mut self, surface: &WlSurface) -> Option<&mut ImePopup> {
+        self.popups
+            .iter_mut()
+            .find(|popup| popup.surface.wl_surface() == surface)
+    }
+}
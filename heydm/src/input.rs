@@ -3,22 +3,49 @@
 //
 // Processes keyboard and pointer events from the backend (winit or libinput).
 // Routes input to the focused window, handles compositor keybindings
-// (e.g., Super+Enter to open terminal, Super+D for launcher), and manages
-// pointer-driven window interactions (move, resize, focus).
+// (e.g., Super+Enter to open terminal, Super+D for launcher, Super+M to
+// maximize, Super+N to minimize, Super+L to lock, Super+Ctrl+L to switch user,
+// Super+T for automatic tiling (Super+Shift+H/L resize the master area),
+// Super+Tab for the window switcher, Super+Shift+Escape for the task manager,
+// Super+arrows to snap windows to halves and quarters, Super+W for the window
+// overview, Super+` for the scratchpad terminal, Super+Ctrl+<digit> for layout
+// presets, Super+Alt+F/N for color filters and night light, Super+Alt+P for
+// the FPS overlay (see perf.rs), Ctrl+Alt+F<n> to switch VTs; keys pass the
+// sticky, slow and bounce keys filters first, see accessx.rs), and manages
+// pointer-driven window interactions (move with edge/corner snapping, resize,
+// focus; Super+drag grabs any window; Super+scroll zooms, see zoom.rs).
+// Touchpad gestures are forwarded to clients via pointer-gestures, except
+// three-finger horizontal swipes which switch workspaces.
 // =============================================================================
 
+use std::time::Duration;
+
+use calloop::timer::{TimeoutAction, Timer};
 use smithay::backend::input::{
-    AbsolutePositionEvent, Axis, ButtonState, Event, InputBackend, InputEvent,
+    AbsolutePositionEvent, Axis, ButtonState, Event, GestureBeginEvent, GestureEndEvent,
+    GesturePinchUpdateEvent as _, GestureSwipeUpdateEvent as _, InputBackend, InputEvent,
     KeyState, KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent,
     PointerMotionEvent,
 };
 use smithay::input::keyboard::{FilterResult, ModifiersState};
-use smithay::input::pointer::{AxisFrame, ButtonEvent, MotionEvent};
+use smithay::input::pointer::{
+    AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+    GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+    GestureSwipeUpdateEvent, MotionEvent,
+};
 use smithay::utils::SERIAL_COUNTER;
+use xkbcommon::xkb::Keycode;
 
 use tracing::info;
 
-use crate::state::HeyDM;
+use crate::accessx::Press;
+use crate::backlight::{BRIGHTNESS_LABEL, BRIGHTNESS_STEP};
+use crate::launcher::{AppCommand, LauncherAction};
+use crate::mpris::MediaControl;
+use crate::state::{HeyDM, TERMINAL};
+use crate::tiling::MASTER_RATIO_STEP;
+use crate::volume::VOLUME_STEP;
+use crate::window::SnapDirection;
 
 /// Modifier key state tracked for compositor keybindings
 #[derive(Debug, Default, Clone)]
@@ -30,11 +57,39 @@ pub struct ModifierState {
     pub logo: bool, // Super/Windows key
 }
 
+/// Linux input event codes of the primary and secondary mouse buttons
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+
+/// Fingers used by the compositor's workspace-switch swipe
+const WORKSPACE_SWIPE_FINGERS: u32 = 3;
+
+/// Horizontal distance a workspace swipe must travel to switch
+const WORKSPACE_SWIPE_THRESHOLD: f64 = 120.0;
+
+/// A swipe gesture the compositor kept for itself instead of forwarding
+#[derive(Debug, Default, Clone)]
+pub struct SwipeTracker {
+    /// Accumulated motion since the swipe began
+    dx: f64,
+    dy: f64,
+}
+
 pub struct InputHandler;
 
 impl InputHandler {
     /// Main input event dispatcher — routes backend input events
     pub fn handle_input<B: InputBackend>(state: &mut HeyDM, event: InputEvent<B>) {
+        if !matches!(
+            event,
+            InputEvent::DeviceAdded { .. } | InputEvent::DeviceRemoved { .. }
+        ) {
+            state.latency.input_received();
+        }
+        if crate::seats::handle_input(state, &event) {
+            return;
+        }
+
         match event {
             InputEvent::Keyboard { event } => {
                 Self::handle_keyboard::<B>(state, event);
@@ -46,60 +101,268 @@ impl InputHandler {
                 Self::handle_pointer_motion_absolute::<B>(state, event);
             }
             InputEvent::PointerButton { event } => {
+                let released = event.state() == ButtonState::Released;
                 Self::handle_pointer_button::<B>(state, event);
+                // A click uses up latched sticky modifiers
+                if released {
+                    let codes = state.access.release_latched();
+                    Self::release_keys(state, codes);
+                }
             }
             InputEvent::PointerAxis { event } => {
                 Self::handle_pointer_axis::<B>(state, event);
             }
+            InputEvent::GestureSwipeBegin { event } => {
+                Self::handle_swipe_begin::<B>(state, event);
+            }
+            InputEvent::GestureSwipeUpdate { event } => {
+                Self::handle_swipe_update::<B>(state, event);
+            }
+            InputEvent::GestureSwipeEnd { event } => {
+                Self::handle_swipe_end::<B>(state, event);
+            }
+            InputEvent::GesturePinchBegin { event } => {
+                Self::handle_pinch_begin::<B>(state, event);
+            }
+            InputEvent::GesturePinchUpdate { event } => {
+                Self::handle_pinch_update::<B>(state, event);
+            }
+            InputEvent::GesturePinchEnd { event } => {
+                Self::handle_pinch_end::<B>(state, event);
+            }
+            InputEvent::GestureHoldBegin { event } => {
+                Self::handle_hold_begin::<B>(state, event);
+            }
+            InputEvent::GestureHoldEnd { event } => {
+                Self::handle_hold_end::<B>(state, event);
+            }
             _ => {}
         }
     }
 
-    /// Handle keyboard key press/release events
+    /// Handle keyboard key press/release events, after the accessibility
+    /// filters (see accessx.rs)
     fn handle_keyboard<B: InputBackend>(state: &mut HeyDM, event: B::KeyboardKeyEvent) {
-        let serial = SERIAL_COUNTER.next_serial();
+        let code = event.key_code().raw();
         let time = event.time_msec();
-        let key_code = event.key_code();
-        let key_state = event.state();
+        match event.state() {
+            KeyState::Pressed => match state.access.press(code) {
+                Press::Forward => Self::key(state, code, KeyState::Pressed, time),
+                Press::Drop => {}
+                Press::After(delay, generation) => {
+                    let result = state.loop_handle.insert_source(
+                        Timer::from_duration(delay),
+                        move |_, _, state| {
+                            if state.access.slow_key_held(code, generation) {
+                                let time = Self::now_msec(state);
+                                Self::key(state, code, KeyState::Pressed, time);
+                            }
+                            TimeoutAction::Drop
+                        },
+                    );
+                    if let Err(e) = result {
+                        tracing::warn!("Failed to schedule a slow key: {e}");
+                    }
+                }
+            },
+            KeyState::Released => {
+                let release = state.access.release(code);
+                if release.forward {
+                    Self::key(state, code, KeyState::Released, time);
+                }
+                Self::release_keys(state, release.then);
+            }
+        }
+    }
+
+    /// Event time for keys heyDM sends itself
+    fn now_msec(state: &HeyDM) -> u32 {
+        Duration::from(state.clock.now()).as_millis() as u32
+    }
+
+    /// Release modifiers sticky keys held down
+    pub fn release_keys(state: &mut HeyDM, codes: Vec<u32>) {
+        for code in codes {
+            let time = Self::now_msec(state);
+            Self::key(state, code, KeyState::Released, time);
+        }
+    }
+
+    /// Feed one key to seat0's keyboard, running compositor bindings
+    fn key(state: &mut HeyDM, code: u32, key_state: KeyState, time: u32) {
+        let serial = SERIAL_COUNTER.next_serial();
+        let key_code = Keycode::new(code);
 
         let keyboard = state.seat.get_keyboard().unwrap();
 
-        keyboard.input::<(), _>(
+        // Actions run after the filter returns: the keyboard's internal state is
+        // locked while the filter runs, so actions must not touch keyboard focus
+        let action = keyboard.input::<Option<CompositorAction>, _>(
             state,
             key_code,
             key_state,
             serial,
             time,
             |state, modifiers, keysym| {
+                // VT switching works everywhere, even on the lock screen: the
+                // other VT has its own login
                 if key_state == KeyState::Pressed {
-                    if let Some(action) =
-                        Self::check_compositor_binding(modifiers, keysym.modified_sym())
-                    {
-                        Self::execute_action(state, action);
-                        return FilterResult::Intercept(());
+                    if let Some(vt) = Self::vt_number(keysym.modified_sym()) {
+                        return FilterResult::Intercept(Some(CompositorAction::SwitchVt(vt)));
+                    }
+                }
+
+                // The lock screen swallows every key
+                if state.lock.is_locked() {
+                    if key_state == KeyState::Pressed {
+                        state.lock.handle_key(keysym.modified_sym());
+                    }
+                    return FilterResult::Intercept(None);
+                }
+
+                // The window switcher keeps the keyboard until Super is released
+                if state.switcher.is_visible() {
+                    if !modifiers.logo {
+                        return FilterResult::Intercept(Some(CompositorAction::CommitSwitcher));
+                    }
+                    if key_state == KeyState::Pressed {
+                        use xkbcommon::xkb::Keysym as K;
+                        match keysym.modified_sym() {
+                            K::Tab | K::ISO_Left_Tab => state.switcher.step(modifiers.shift),
+                            K::Escape => state.switcher.hide(),
+                            _ => {}
+                        }
+                    }
+                    return FilterResult::Intercept(None);
+                }
+
+                // The overview takes the arrow keys, Enter and Escape; other
+                // keys are swallowed so they do not reach a window behind it
+                if state.overview.is_open() {
+                    if key_state == KeyState::Pressed {
+                        use xkbcommon::xkb::Keysym as K;
+                        match keysym.modified_sym() {
+                            K::Left => state.overview.step(-1, 0),
+                            K::Right => state.overview.step(1, 0),
+                            K::Up => state.overview.step(0, -1),
+                            K::Down => state.overview.step(0, 1),
+                            K::Return | K::KP_Enter => {
+                                return FilterResult::Intercept(Some(CompositorAction::CommitOverview));
+                            }
+                            K::Escape => state.overview.hide(),
+                            K::w | K::W if modifiers.logo => state.overview.hide(),
+                            _ => {}
+                        }
+                    }
+                    return FilterResult::Intercept(None);
+                }
+
+                // The calendar uses Escape and the arrow keys while open
+                if state.calendar.is_visible() && !modifiers.logo {
+                    if key_state == KeyState::Pressed && state.calendar.handle_key(keysym.modified_sym()) {
+                        return FilterResult::Intercept(None);
+                    }
+                }
+
+                // The launcher takes typing while open; Super bindings still work
+                if state.launcher.is_visible()
+                    && !modifiers.logo
+                    && !modifiers.ctrl
+                    && !modifiers.alt
+                {
+                    if key_state == KeyState::Pressed {
+                        let action = match state.launcher.handle_key(keysym.modified_sym()) {
+                            Some(LauncherAction::Launch(app)) => CompositorAction::Launch(app),
+                            Some(LauncherAction::Run(command)) => CompositorAction::RunCommand(command),
+                            Some(LauncherAction::Copy(text)) => CompositorAction::CopyText(text),
+                            None => return FilterResult::Intercept(None),
+                        };
+                        return FilterResult::Intercept(Some(action));
+                    }
+                    return FilterResult::Intercept(None);
+                }
+
+                // The task manager swallows every key while it is open
+                if state.task_manager.is_visible() {
+                    if key_state == KeyState::Pressed {
+                        state.task_manager.handle_key(keysym.modified_sym());
+                    }
+                    return FilterResult::Intercept(None);
+                }
+
+                if key_state == KeyState::Pressed {
+                    // Shift changes the modified sym of digit keys ('1' → '!'),
+                    // so workspace bindings match on the unmodified sym
+                    let raw_sym = keysym
+                        .raw_syms()
+                        .first()
+                        .copied()
+                        .unwrap_or_else(|| keysym.modified_sym());
+                    if let Some(action) = Self::check_compositor_binding(
+                        modifiers,
+                        keysym.modified_sym(),
+                        raw_sym,
+                    ) {
+                        // The login screen only keeps accessibility bindings
+                        if !state.greeter || action.is_accessibility() {
+                            return FilterResult::Intercept(Some(action));
+                        }
                     }
                 }
                 FilterResult::Forward
             },
         );
+
+        if let Some(action) = action.flatten() {
+            Self::execute_action(state, action);
+        }
     }
 
     /// Check if the current key combination matches a compositor keybinding
     fn check_compositor_binding(
         modifiers: &ModifiersState,
         keysym: xkbcommon::xkb::Keysym,
+        raw_sym: xkbcommon::xkb::Keysym,
     ) -> Option<CompositorAction> {
         use xkbcommon::xkb::Keysym as K;
 
         if modifiers.logo {
+            if let Some(ws) = Self::workspace_number(raw_sym) {
+                return Some(if modifiers.ctrl {
+                    CompositorAction::ApplyPreset(ws)
+                } else if modifiers.shift {
+                    CompositorAction::MoveToWorkspace(ws)
+                } else {
+                    CompositorAction::SwitchWorkspace(ws)
+                });
+            }
+
             match keysym {
+                K::c | K::C if modifiers.alt => Some(CompositorAction::ToggleLargeCursor),
+                K::f | K::F if modifiers.alt => Some(CompositorAction::CycleColorFilter),
+                K::n | K::N if modifiers.alt => Some(CompositorAction::ToggleNightLight),
+                K::p | K::P if modifiers.alt => Some(CompositorAction::TogglePerfOverlay),
                 K::Return => Some(CompositorAction::SpawnTerminal),
                 K::d | K::D => Some(CompositorAction::ToggleLauncher),
                 K::q | K::Q => Some(CompositorAction::CloseWindow),
+                K::l | K::L if modifiers.ctrl => Some(CompositorAction::SwitchUser),
+                K::l | K::L if modifiers.shift => Some(CompositorAction::GrowMaster),
+                K::l | K::L => Some(CompositorAction::LockScreen),
+                K::h | K::H if modifiers.shift => Some(CompositorAction::ShrinkMaster),
+                K::t | K::T if modifiers.shift => Some(CompositorAction::CycleLayout),
+                K::t | K::T => Some(CompositorAction::ToggleTiling),
                 K::f | K::F => Some(CompositorAction::ToggleFullscreen),
-                K::Left => Some(CompositorAction::TileLeft),
-                K::Right => Some(CompositorAction::TileRight),
-                K::Tab => Some(CompositorAction::CycleFocus),
+                K::m | K::M => Some(CompositorAction::ToggleMaximize),
+                K::n | K::N => Some(CompositorAction::Minimize),
+                K::Left => Some(CompositorAction::Snap(SnapDirection::Left)),
+                K::Right => Some(CompositorAction::Snap(SnapDirection::Right)),
+                K::Up => Some(CompositorAction::Snap(SnapDirection::Up)),
+                K::Down => Some(CompositorAction::Snap(SnapDirection::Down)),
+                K::Tab | K::ISO_Left_Tab => Some(CompositorAction::ShowSwitcher),
+                K::w | K::W => Some(CompositorAction::ShowOverview),
+                K::grave => Some(CompositorAction::ToggleScratchpad),
+                K::Escape if modifiers.shift => Some(CompositorAction::ToggleTaskManager),
+                K::Escape => Some(CompositorAction::LockScreen),
                 _ if modifiers.shift && (keysym == K::e || keysym == K::E) => {
                     Some(CompositorAction::ExitCompositor)
                 }
@@ -108,23 +371,66 @@ impl InputHandler {
         } else if modifiers.alt && keysym == xkbcommon::xkb::Keysym::F4 {
             Some(CompositorAction::CloseWindow)
         } else {
-            None
+            match keysym {
+                K::XF86_AudioRaiseVolume => Some(CompositorAction::ChangeVolume(VOLUME_STEP)),
+                K::XF86_AudioLowerVolume => Some(CompositorAction::ChangeVolume(-VOLUME_STEP)),
+                K::XF86_AudioMute => Some(CompositorAction::ToggleMute),
+                K::XF86_AudioPlay | K::XF86_AudioPause => Some(CompositorAction::Media(MediaControl::PlayPause)),
+                K::XF86_AudioNext => Some(CompositorAction::Media(MediaControl::Next)),
+                K::XF86_AudioPrev => Some(CompositorAction::Media(MediaControl::Previous)),
+                K::XF86_MonBrightnessUp => Some(CompositorAction::ChangeBrightness(BRIGHTNESS_STEP)),
+                K::XF86_MonBrightnessDown => Some(CompositorAction::ChangeBrightness(-BRIGHTNESS_STEP)),
+                _ => None,
+            }
         }
     }
 
+    /// The VT for the XF86_Switch_VT_<n> keysyms, which the keymap produces
+    /// for Ctrl+Alt+F<n>
+    fn vt_number(keysym: xkbcommon::xkb::Keysym) -> Option<i32> {
+        use xkbcommon::xkb::Keysym as K;
+
+        let first = K::XF86_Switch_VT_1.raw();
+        (first..=K::XF86_Switch_VT_12.raw())
+            .contains(&keysym.raw())
+            .then(|| (keysym.raw() - first) as i32 + 1)
+    }
+
+    /// Map the digit keys 1-9 to workspace numbers
+    fn workspace_number(keysym: xkbcommon::xkb::Keysym) -> Option<usize> {
+        use xkbcommon::xkb::Keysym as K;
+
+        let digits = [K::_1, K::_2, K::_3, K::_4, K::_5, K::_6, K::_7, K::_8, K::_9];
+        digits.iter().position(|d| *d == keysym).map(|idx| idx + 1)
+    }
+
     /// Execute a compositor action
     fn execute_action(state: &mut HeyDM, action: CompositorAction) {
         match action {
             CompositorAction::SpawnTerminal => {
-                info!("Action: Spawning terminal (alacritty)");
-                if let Err(e) = std::process::Command::new("alacritty").spawn() {
-                    tracing::warn!("Failed to spawn alacritty: {e}");
-                }
+                info!("Action: Spawning terminal ({TERMINAL})");
+                state.launch(TERMINAL);
             }
             CompositorAction::ToggleLauncher => {
                 info!("Action: Toggling application launcher");
                 state.launcher.toggle();
             }
+            CompositorAction::Launch(app) => {
+                info!("Action: Launching {}", app.name);
+                state.launch_app(&app);
+            }
+            CompositorAction::RunCommand(command) => {
+                info!("Action: Running {command}");
+                state.run_shell(&command);
+            }
+            CompositorAction::CopyText(text) => {
+                info!("Action: Copying '{text}' to the clipboard");
+                state.set_clipboard(text);
+            }
+            CompositorAction::ToggleTaskManager => {
+                info!("Action: Toggling task manager");
+                state.toggle_task_manager();
+            }
             CompositorAction::CloseWindow => {
                 info!("Action: Closing focused window");
                 state.window_manager.close_focused();
@@ -133,18 +439,109 @@ impl InputHandler {
                 info!("Action: Toggling fullscreen");
                 state.window_manager.toggle_fullscreen(&state.output_size);
             }
-            CompositorAction::TileLeft => {
-                info!("Action: Tiling window left");
-                state.window_manager.tile_left(&state.output_size);
+            CompositorAction::ToggleMaximize => {
+                info!("Action: Toggling maximize");
+                state.window_manager.toggle_maximized(&state.output_size);
+            }
+            CompositorAction::Minimize => {
+                info!("Action: Minimizing focused window");
+                state.window_manager.minimize_focused(&state.output_size);
+                state.refresh_keyboard_focus();
+            }
+            CompositorAction::Snap(direction) => {
+                info!("Action: Snapping window {direction:?}");
+                state
+                    .window_manager
+                    .snap_focused(direction, &state.output_size);
+            }
+            CompositorAction::ToggleTiling => {
+                info!("Action: Toggling automatic tiling");
+                state.window_manager.toggle_tiling(&state.output_size);
+            }
+            CompositorAction::CycleLayout => {
+                info!("Action: Switching tiling layout");
+                state.window_manager.cycle_layout(&state.output_size);
             }
-            CompositorAction::TileRight => {
-                info!("Action: Tiling window right");
-                state.window_manager.tile_right(&state.output_size);
+            CompositorAction::GrowMaster => {
+                state
+                    .window_manager
+                    .adjust_master_ratio(MASTER_RATIO_STEP, &state.output_size);
             }
-            CompositorAction::CycleFocus => {
-                info!("Action: Cycling window focus");
-                state.window_manager.cycle_focus();
+            CompositorAction::ShrinkMaster => {
+                state
+                    .window_manager
+                    .adjust_master_ratio(-MASTER_RATIO_STEP, &state.output_size);
+            }
+            CompositorAction::ShowSwitcher => {
+                info!("Action: Opening window switcher");
+                state.switcher.show(&state.window_manager);
+            }
+            CompositorAction::CommitSwitcher => {
+                if let Some(id) = state.switcher.commit() {
+                    info!("Action: Switching to window {id}");
+                    state.window_manager.focus_id(id);
+                    state.refresh_keyboard_focus();
+                }
+            }
+            CompositorAction::ShowOverview => {
+                info!("Action: Opening window overview");
+                state.overview.show(&state.window_manager);
+            }
+            CompositorAction::CommitOverview => {
+                if let Some(id) = state.overview.commit() {
+                    info!("Action: Switching to window {id}");
+                    state.window_manager.focus_id(id);
+                    state.refresh_keyboard_focus();
+                }
+            }
+            CompositorAction::SwitchWorkspace(ws) => {
+                info!("Action: Switching to workspace {ws}");
+                state.window_manager.switch_workspace(ws);
+                state.refresh_keyboard_focus();
+            }
+            CompositorAction::MoveToWorkspace(ws) => {
+                info!("Action: Moving window to workspace {ws}");
+                state
+                    .window_manager
+                    .move_focused_to_workspace(ws, &state.output_size);
+                state.refresh_keyboard_focus();
+            }
+            CompositorAction::ApplyPreset(key) => {
+                crate::presets::apply_key(state, key);
+            }
+            CompositorAction::LockScreen => {
+                info!("Action: Locking session");
+                state.lock_session();
+            }
+            CompositorAction::SwitchUser => crate::switchuser::switch_user(state),
+            CompositorAction::ToggleScratchpad => {
+                info!("Action: Toggling scratchpad");
+                crate::scratchpad::toggle(state);
+            }
+            CompositorAction::ToggleLargeCursor => {
+                info!("Action: Toggling large cursor");
+                state.cursor.toggle_large();
+            }
+            CompositorAction::CycleColorFilter => state.color.cycle_filter(),
+            CompositorAction::ToggleNightLight => state.color.toggle_night_light(),
+            CompositorAction::TogglePerfOverlay => {
+                state.perf.toggle_overlay();
+                if state.perf.is_enabled() {
+                    state.latency.set_enabled(true);
+                }
+            }
+            CompositorAction::ChangeVolume(delta) => state.volume.change(delta),
+            CompositorAction::ToggleMute => state.volume.toggle_mute(),
+            CompositorAction::Media(control) => state.media.control(control),
+            CompositorAction::ChangeBrightness(delta) => {
+                // Show the expected level right away; the real one follows
+                let current = state.panel.brightness();
+                if current >= 0 {
+                    state.osd.show(BRIGHTNESS_LABEL, current + delta);
+                }
+                state.backlight.change(delta);
             }
+            CompositorAction::SwitchVt(vt) => state.change_vt(vt),
             CompositorAction::ExitCompositor => {
                 info!("Action: Exiting compositor");
                 state.loop_signal.stop();
@@ -163,21 +560,30 @@ impl InputHandler {
             state.output_size,
         );
 
-        if state.window_manager.handle_pointer_motion(new_pos) {
+        if state.lock.is_locked()
+            || state
+                .window_manager
+                .handle_pointer_motion(new_pos, &state.output_size)
+        {
             return;
         }
+        state.update_tooltip(new_pos);
 
         if let Some((surface, surface_pos)) = state.window_manager.surface_under(new_pos) {
             let pointer = state.seat.get_pointer().unwrap();
+            let location = state.window_manager.client_point(new_pos);
             pointer.motion(
                 state,
-                Some((surface.clone(), surface_pos.into())),
+                Some((surface, surface_pos.into())),
                 &MotionEvent {
-                    location: new_pos.into(),
+                    location,
                     serial,
                     time: event.time_msec(),
                 },
             );
+        } else {
+            // Over compositor UI or the background
+            state.cursor.reset();
         }
     }
 
@@ -193,6 +599,12 @@ impl InputHandler {
         );
 
         state.window_manager.set_cursor_position(pos.0, pos.1);
+        if state.lock.is_locked()
+            || state.window_manager.handle_pointer_motion(pos, &output_size)
+        {
+            return;
+        }
+        state.update_tooltip(pos);
 
         let serial = SERIAL_COUNTER.next_serial();
 
@@ -200,15 +612,18 @@ impl InputHandler {
             state.window_manager.surface_under((pos.0, pos.1))
         {
             let pointer = state.seat.get_pointer().unwrap();
+            let location = state.window_manager.client_point(pos);
             pointer.motion(
                 state,
-                Some((surface.clone(), surface_pos.into())),
+                Some((surface, surface_pos.into())),
                 &MotionEvent {
-                    location: pos.into(),
+                    location,
                     serial,
                     time: event.time_msec(),
                 },
             );
+        } else {
+            state.cursor.reset();
         }
     }
 
@@ -218,25 +633,141 @@ impl InputHandler {
         let button = event.button_code();
         let button_state = event.state();
 
+        // The task manager is modal: clicks do not reach the windows below it
+        if state.lock.is_locked() || state.task_manager.is_visible() {
+            return;
+        }
+
         let cursor_pos = state.window_manager.cursor_position();
+        // Clicking a card in the overview picks its window, anywhere else
+        // closes it
+        if state.overview.is_open() {
+            if button_state == ButtonState::Pressed {
+                if state.overview.select_at(cursor_pos, state.output_size, &state.ui) {
+                    Self::execute_action(state, CompositorAction::CommitOverview);
+                } else {
+                    state.overview.hide();
+                }
+            }
+            return;
+        }
         if button_state == ButtonState::Pressed {
-            if cursor_pos.1 < 32.0 {
-                state.panel.handle_click(cursor_pos.0, cursor_pos.1);
+            state.tooltips.dismiss();
+
+            // The calendar closes on any click outside it; the clock toggles it
+            if state.calendar.is_visible() {
+                if state.calendar.contains(cursor_pos, state.output_size) {
+                    return;
+                }
+                state.calendar.hide();
+                if state.panel.is_over_clock(cursor_pos, state.output_size) {
+                    return;
+                }
+            }
+
+            // The tray menu takes the next click; tray icons live in the panel
+            if state.panel_visible()
+                && !state.launcher.is_visible()
+                && state.tray.handle_press(cursor_pos, button, state.output_size)
+            {
                 return;
             }
 
-            if state.launcher.is_visible() {
-                if let Some(app) = state.launcher.handle_click(cursor_pos.0, cursor_pos.1, state.output_size.w as u32, state.output_size.h as u32) {
-                    info!("Launching application: {}" , app);
-                    if let Err(e) = std::process::Command::new(&app).spawn() {
-                        tracing::warn!("Failed to launch {app}: {e}");
-                    }
-                    state.launcher.hide();
+            // The Wi-Fi menu closes on any click; clicking a network connects
+            if state.network_menu.handle_press(cursor_pos, state.output_size) {
+                return;
+            }
+            // So does the Bluetooth menu; clicking a device (dis)connects it
+            if state.bluetooth_menu.handle_press(cursor_pos, state.output_size) {
+                return;
+            }
+
+            // Notification toasts sit above windows (the launcher covers them)
+            if !state.greeter
+                && !state.launcher.is_visible()
+                && state.notifications.handle_click(cursor_pos, state.output_size)
+            {
+                return;
+            }
+
+            // Taskbar buttons restore, focus or minimize their window
+            let taskbar_hit = state.panel.taskbar_window_at(
+                &state.window_manager,
+                cursor_pos,
+                state.output_size,
+            );
+            if let Some(id) = taskbar_hit.filter(|_| state.panel_visible()) {
+                state.window_manager.taskbar_activate(id, &state.output_size);
+                state.refresh_keyboard_focus();
+                return;
+            }
+
+            if cursor_pos.1 < state.ui.panel_bottom() as f64 && state.panel_visible() {
+                if let Some(control) = state.panel.media_control_at(cursor_pos, state.output_size) {
+                    state.media.control(control);
                     return;
                 }
+                if state.panel.is_over_volume(cursor_pos, state.output_size) {
+                    state.volume.toggle_mute();
+                    return;
+                }
+                if state.panel.is_over_power_profile(cursor_pos, state.output_size) {
+                    state.power_profiles.cycle();
+                    return;
+                }
+                if state.panel.is_over_dnd(cursor_pos, state.output_size) {
+                    state.notifications.toggle_dnd();
+                    return;
+                }
+                if state.panel.is_over_bluetooth(cursor_pos, state.output_size) {
+                    state.bluetooth_menu.toggle();
+                    return;
+                }
+                if state.panel.is_over_network(cursor_pos, state.output_size) {
+                    state.network_menu.toggle();
+                    return;
+                }
+                if state.panel.is_over_clock(cursor_pos, state.output_size) {
+                    state.calendar.toggle();
+                    return;
+                }
+                state.panel.handle_click(cursor_pos.0, cursor_pos.1);
+                return;
+            }
+
+            // Launcher items act on release, so a long press can open the
+            // app's actions instead
+            if state.launcher.is_visible()
+                && state.launcher.handle_press(cursor_pos.0, cursor_pos.1, state.output_size.w as u32, state.output_size.h as u32)
+            {
+                return;
             }
 
             state.window_manager.focus_at(cursor_pos);
+            state.refresh_keyboard_focus();
+
+            // Super+left drag moves a window, Super+right drag resizes it
+            let keyboard = state.seat.get_keyboard().unwrap();
+            if keyboard.modifier_state().logo
+                && state.window_manager.surface_under(cursor_pos).is_some()
+            {
+                match button {
+                    BTN_LEFT => state.window_manager.begin_move(&state.output_size),
+                    BTN_RIGHT => state.window_manager.begin_resize(&state.output_size),
+                    _ => {}
+                }
+                if state.window_manager.has_grab() {
+                    return;
+                }
+            }
+        } else if state.launcher.is_pressed() {
+            if let Some(app) = state.launcher.handle_release(cursor_pos.0, cursor_pos.1, state.output_size.w as u32, state.output_size.h as u32) {
+                info!("Launching application: {}", app.name);
+                state.launch_app(&app);
+            }
+            return;
+        } else if state.window_manager.has_grab() {
+            state.window_manager.finish_grab(&state.output_size);
         }
 
         let pointer = state.seat.get_pointer().unwrap();
@@ -253,6 +784,27 @@ impl InputHandler {
 
     /// Handle pointer axis (scroll wheel) events
     fn handle_pointer_axis<B: InputBackend>(state: &mut HeyDM, event: B::PointerAxisEvent) {
+        // Super+scroll zooms, also on the lock and login screens
+        let keyboard = state.seat.get_keyboard().unwrap();
+        if keyboard.modifier_state().logo {
+            state.zoom.scroll(event.amount(Axis::Vertical).unwrap_or(0.0));
+            return;
+        }
+
+        if state.lock.is_locked() {
+            return;
+        }
+
+        // Scrolling over the panel's volume module changes the volume
+        let cursor_pos = state.window_manager.cursor_position();
+        if state.panel_visible() && state.panel.is_over_volume(cursor_pos, state.output_size) {
+            let delta = state.panel.volume_scroll(event.amount(Axis::Vertical).unwrap_or(0.0));
+            if delta != 0 {
+                state.volume.change(delta);
+            }
+            return;
+        }
+
         let pointer = state.seat.get_pointer().unwrap();
         let source = event.source();
 
@@ -270,15 +822,221 @@ impl InputHandler {
     }
 }
 
+// ---- Touchpad gestures ----
+
+impl InputHandler {
+    /// Start of a swipe: three fingers belong to the compositor, others go to the client
+    fn handle_swipe_begin<B: InputBackend>(state: &mut HeyDM, event: B::GestureSwipeBeginEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+
+        if event.fingers() == WORKSPACE_SWIPE_FINGERS && !state.greeter {
+            state.swipe = Some(SwipeTracker::default());
+            return;
+        }
+
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_swipe_begin(
+            state,
+            &GestureSwipeBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    fn handle_swipe_update<B: InputBackend>(state: &mut HeyDM, event: B::GestureSwipeUpdateEvent) {
+        if let Some(swipe) = state.swipe.as_mut() {
+            swipe.dx += event.delta_x();
+            swipe.dy += event.delta_y();
+            return;
+        }
+        if state.lock.is_locked() {
+            return;
+        }
+
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_swipe_update(
+            state,
+            &GestureSwipeUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+            },
+        );
+    }
+
+    /// End of a swipe: a long enough horizontal compositor swipe switches workspace
+    fn handle_swipe_end<B: InputBackend>(state: &mut HeyDM, event: B::GestureSwipeEndEvent) {
+        if let Some(swipe) = state.swipe.take() {
+            if event.cancelled()
+                || swipe.dx.abs() < WORKSPACE_SWIPE_THRESHOLD
+                || swipe.dx.abs() < swipe.dy.abs()
+            {
+                return;
+            }
+
+            // Content follows the fingers: swiping left reveals the next workspace
+            let current = state.window_manager.workspaces().active_workspace();
+            let target = if swipe.dx < 0.0 {
+                (current + 1).min(crate::workspace::WORKSPACE_COUNT)
+            } else {
+                current.saturating_sub(1).max(1)
+            };
+            if target != current {
+                info!("Gesture: swiping to workspace {target}");
+                state.window_manager.switch_workspace(target);
+                state.refresh_keyboard_focus();
+            }
+            return;
+        }
+        if state.lock.is_locked() {
+            return;
+        }
+
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_swipe_end(
+            state,
+            &GestureSwipeEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    /// Pinch gestures are always forwarded to the client under the pointer
+    fn handle_pinch_begin<B: InputBackend>(state: &mut HeyDM, event: B::GesturePinchBeginEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_pinch_begin(
+            state,
+            &GesturePinchBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    fn handle_pinch_update<B: InputBackend>(state: &mut HeyDM, event: B::GesturePinchUpdateEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_pinch_update(
+            state,
+            &GesturePinchUpdateEvent {
+                time: event.time_msec(),
+                delta: event.delta(),
+                scale: event.scale(),
+                rotation: event.rotation(),
+            },
+        );
+    }
+
+    fn handle_pinch_end<B: InputBackend>(state: &mut HeyDM, event: B::GesturePinchEndEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_pinch_end(
+            state,
+            &GesturePinchEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+
+    fn handle_hold_begin<B: InputBackend>(state: &mut HeyDM, event: B::GestureHoldBeginEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_hold_begin(
+            state,
+            &GestureHoldBeginEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                fingers: event.fingers(),
+            },
+        );
+    }
+
+    fn handle_hold_end<B: InputBackend>(state: &mut HeyDM, event: B::GestureHoldEndEvent) {
+        if state.lock.is_locked() {
+            return;
+        }
+        let pointer = state.seat.get_pointer().unwrap();
+        pointer.gesture_hold_end(
+            state,
+            &GestureHoldEndEvent {
+                serial: SERIAL_COUNTER.next_serial(),
+                time: event.time_msec(),
+                cancelled: event.cancelled(),
+            },
+        );
+    }
+}
+
 /// Compositor actions triggered by keybindings
 #[derive(Debug, Clone)]
 enum CompositorAction {
     SpawnTerminal,
     ToggleLauncher,
+    Launch(AppCommand),
+    RunCommand(String),
+    CopyText(String),
+    ToggleTaskManager,
     CloseWindow,
     ToggleFullscreen,
-    TileLeft,
-    TileRight,
-    CycleFocus,
+    ToggleMaximize,
+    Minimize,
+    Snap(SnapDirection),
+    ToggleTiling,
+    CycleLayout,
+    GrowMaster,
+    ShrinkMaster,
+    ShowSwitcher,
+    CommitSwitcher,
+    ShowOverview,
+    CommitOverview,
+    ToggleScratchpad,
+    SwitchWorkspace(usize),
+    MoveToWorkspace(usize),
+    /// Apply the layout preset bound to this digit
+    ApplyPreset(usize),
+    LockScreen,
+    /// Lock and show a second login screen on another VT
+    SwitchUser,
+    ToggleLargeCursor,
+    CycleColorFilter,
+    ToggleNightLight,
+    /// Frame rate and frame times in the corner
+    TogglePerfOverlay,
+    /// Raise or lower the volume by this many percent
+    ChangeVolume(i32),
+    ToggleMute,
+    /// Control the panel's media player
+    Media(MediaControl),
+    /// Raise or lower the screen brightness by this many percent
+    ChangeBrightness(i32),
+    /// Switch to this virtual terminal
+    SwitchVt(i32),
     ExitCompositor,
 }
+
+impl CompositorAction {
+    /// Bindings that stay available in greeter mode
+    fn is_accessibility(&self) -> bool {
+        matches!(
+            self,
+            Self::ToggleLargeCursor | Self::CycleColorFilter | Self::ToggleNightLight
+        )
+    }
+}
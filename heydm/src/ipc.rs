@@ -0,0 +1,571 @@
+// =============================================================================
+// heyDM — IPC Socket
+//
+// A Unix socket at $XDG_RUNTIME_DIR/heydm-<wayland display>.sock (exported to
+// children as HEYDM_SOCK) for scripts and task-manager style tools. Without
+// XDG_RUNTIME_DIR there is no IPC, rather than a socket in a shared /tmp, and
+// a socket another heyDM still answers on is never taken over. Each
+// connection sends one command line and receives one JSON object back:
+//
+//     list windows        → {"success": true, "windows": [...]}
+//     list workspaces     → {"success": true, "workspaces": [...]}
+//     list outputs        → {"success": true, "outputs": [...]}   (EDID identities;
+//                            driven ones with their current mode, transform,
+//                            scale and position)
+//     layout              → {"success": true, "layout": {...}}   (the window
+//                            manager's state, for debugging; see window.rs)
+//     focus <id>          → {"success": true}   (switches workspace, restores)
+//     close <id>          → {"success": true}   (asks the window to close)
+//     move <id> <workspace> → {"success": true}
+//     workspace <n>       → {"success": true}
+//     kill <id> [signal]  → {"success": true}   (signal: TERM (default), KILL, ...)
+//     config set <section> <key> <value> → {"success": true, "applied": ...}
+//                            (`applied` false: takes effect on the next start;
+//                            the file is not changed)
+//     reload              → {"success": true, "errors": n, "warnings": n}
+//                            (reads the config file again, like SIGHUP)
+//     wallpaper <path>    → {"success": true}   (`none` removes the wallpaper)
+//     wallpaper mode <fill|fit|center|tile> → {"success": true}
+//     wallpaper output <connector> <path> → {"success": true}   (`none` goes
+//                            back to the default wallpaper)
+//     latency             → {"success": true, "enabled": ..., "submit": {...},
+//                            "present": {...}}   (p50/p99/max in ms)
+//     latency on|off|reset → {"success": true}
+//     perf                → {"success": true, "enabled": ..., "fps": n,
+//                            "frame_time": {...}, "render_time": {...},
+//                            "damage_px": n, "clients": n, "input": {...}}
+//     perf on|off|reset|overlay → {"success": true}   (see perf.rs)
+//     status              → status bar object (schema in status.rs)
+//     subscribe status    → status bar object, then one per change
+//     subscribe events    → {"success": true}, then one event per line
+//     subscribe <window|workspace|output>... → only these events (events.rs)
+//     preset <name>       → {"success": true}   (applies a layout preset)
+//     switch-user         → {"success": true}   (locks, opens a second login
+//                            screen; see switchuser.rs)
+//
+// `heyctl` (see ctl.rs) sends these from the command line. Failures reply
+// {"success": false, "error": "..."}.
+// =============================================================================
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use calloop::generic::Generic;
+use calloop::{Interest, LoopHandle, Mode, PostAction};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use serde_json::{json, Value};
+use smithay::output::Output;
+use tracing::{debug, info, warn};
+
+use crate::events::{EventSubscribers, Snapshot};
+use crate::latency::LatencyStats;
+use crate::output;
+use crate::presets;
+use crate::sandbox;
+use crate::schema::Severity;
+use crate::state::HeyDM;
+use crate::status;
+use crate::wallpaper::{OutputWallpaper, WallpaperMode};
+use crate::window::WindowElement;
+use crate::workspace::WORKSPACE_COUNT;
+
+/// Longest accepted command line
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// Bound socket; the file is removed again on drop
+pub struct IpcServer {
+    path: PathBuf,
+}
+
+impl IpcServer {
+    /// Bind the socket for the given Wayland display and start serving
+    pub fn start(
+        loop_handle: &LoopHandle<'static, HeyDM>,
+        wayland_display: &str,
+    ) -> std::io::Result<Self> {
+        let path = socket_path(wayland_display)
+            .ok_or_else(|| std::io::Error::other("XDG_RUNTIME_DIR is not set"))?;
+        // A stale socket from a crashed instance would make bind fail; a
+        // live one belongs to another heyDM
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(std::io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("{} is in use by another heyDM", path.display()),
+                ));
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+
+        let handle = loop_handle.clone();
+        loop_handle
+            .insert_source(
+                Generic::new(listener, Interest::READ, Mode::Level),
+                move |_, listener, _| {
+                    loop {
+                        match listener.as_ref().accept() {
+                            Ok((stream, _)) => accept_client(&handle, stream),
+                            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                            Err(e) => {
+                                warn!("IPC accept failed: {e}");
+                                break;
+                            }
+                        }
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+        std::env::set_var("HEYDM_SOCK", &path);
+        info!("IPC socket: {}", path.display());
+        Ok(Self { path })
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Socket path for a Wayland display name; None without XDG_RUNTIME_DIR
+pub fn socket_path(wayland_display: &str) -> Option<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")?;
+    Some(PathBuf::from(runtime_dir).join(format!("heydm-{wayland_display}.sock")))
+}
+
+/// Socket of the running heyDM as seen by a client: $HEYDM_SOCK, or derived
+/// from $WAYLAND_DISPLAY
+pub fn client_socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("HEYDM_SOCK") {
+        return Some(PathBuf::from(path));
+    }
+    std::env::var("WAYLAND_DISPLAY")
+        .ok()
+        .and_then(|display| socket_path(&display))
+}
+
+/// Read one command line from a new connection without blocking the loop
+fn accept_client(handle: &LoopHandle<'static, HeyDM>, stream: UnixStream) {
+    if let Err(e) = stream.set_nonblocking(true) {
+        warn!("IPC client setup failed: {e}");
+        return;
+    }
+
+    let mut request = Vec::new();
+    let result = handle.insert_source(
+        Generic::new(stream, Interest::READ, Mode::Level),
+        move |_, stream, state| {
+            let mut chunk = [0u8; 512];
+            loop {
+                match stream.as_ref().read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        request.extend_from_slice(&chunk[..n]);
+                        if request.contains(&b'\n') || request.len() > MAX_REQUEST_LEN {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        return Ok(PostAction::Continue);
+                    }
+                    Err(e) => {
+                        debug!("IPC read failed: {e}");
+                        return Ok(PostAction::Remove);
+                    }
+                }
+            }
+
+            let line = String::from_utf8_lossy(&request);
+            let line = line.lines().next().unwrap_or("").trim();
+
+            // Subscribers keep the connection; it moves to the state
+            if line == "subscribe status" {
+                match stream.as_ref().try_clone() {
+                    Ok(stream) => {
+                        let current = status::bar_status(state);
+                        state.status_subscribers.add(stream, &current);
+                    }
+                    Err(e) => warn!("IPC subscribe failed: {e}"),
+                }
+                return Ok(PostAction::Remove);
+            }
+            let event_kinds = match line.strip_prefix("subscribe ") {
+                Some(kinds) => {
+                    let kinds: Vec<&str> = kinds.split_whitespace().collect();
+                    Some(EventSubscribers::parse_kinds(&kinds))
+                }
+                None => None,
+            };
+            if let Some(Ok(kinds)) = event_kinds {
+                match stream.as_ref().try_clone() {
+                    Ok(stream) => {
+                        let snapshot = Snapshot::take(state);
+                        state.event_subscribers.add(stream, kinds, snapshot);
+                    }
+                    Err(e) => warn!("IPC subscribe failed: {e}"),
+                }
+                return Ok(PostAction::Remove);
+            }
+            let response = match event_kinds {
+                Some(Err(e)) => json!({ "success": false, "error": e }),
+                _ => handle_command(state, line),
+            };
+
+            let mut stream = stream.as_ref();
+            let _ = stream.set_nonblocking(false);
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            if let Err(e) = writeln!(stream, "{response}") {
+                debug!("IPC write failed: {e}");
+            }
+            Ok(PostAction::Remove)
+        },
+    );
+    if let Err(e) = result {
+        warn!("Failed to register IPC client: {e}");
+    }
+}
+
+/// Execute one command and build the JSON reply
+fn handle_command(state: &mut HeyDM, line: &str) -> Value {
+    debug!("IPC command: {line}");
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["list", "windows"] => {
+            let state = &*state;
+            let windows: Vec<Value> = state
+                .window_manager
+                .windows()
+                .iter()
+                .map(|window| window_info(state, window))
+                .collect();
+            json!({ "success": true, "windows": windows })
+        }
+        ["list", "outputs"] => {
+            let outputs: Vec<Value> = state
+                .outputs
+                .iter()
+                .map(|identity| {
+                    let driven = state
+                        .output
+                        .iter()
+                        .chain(&state.extra_outputs)
+                        .find(|o| o.name() == identity.connector);
+                    let mut output = identity.json(driven.is_some());
+                    if let Some(driven) = driven {
+                        output["current"] = output_state(driven);
+                    }
+                    output
+                })
+                .collect();
+            json!({ "success": true, "outputs": outputs })
+        }
+        ["layout"] => json!({ "success": true, "layout": state.window_manager.serialize_layout() }),
+        ["list", "workspaces"] => {
+            let wm = &state.window_manager;
+            let workspaces: Vec<Value> = (1..=WORKSPACE_COUNT)
+                .map(|ws| {
+                    json!({
+                        "number": ws,
+                        "active": wm.workspaces().active_workspace() == ws,
+                        "visible": wm.workspaces().is_visible(ws),
+                        "output": wm.workspaces().output_of(ws),
+                        "windows": wm.windows().iter().filter(|w| w.workspace() == ws).count(),
+                        "layout": wm.layout_name(ws),
+                    })
+                })
+                .collect();
+            json!({ "success": true, "workspaces": workspaces })
+        }
+        ["focus", id] => with_window(id, |id| {
+            let found = state.window_manager.activate(id, &state.output_size);
+            state.refresh_keyboard_focus();
+            found
+        }),
+        ["close", id] => with_window(id, |id| state.window_manager.close(id)),
+        ["move", id, ws] => match parse_workspace(ws) {
+            Ok(ws) => with_window(id, |id| {
+                let found = state.window_manager.move_to_workspace(id, ws, &state.output_size);
+                state.refresh_keyboard_focus();
+                found
+            }),
+            Err(message) => error(&message),
+        },
+        ["workspace", ws] => match parse_workspace(ws) {
+            Ok(ws) => {
+                state.window_manager.switch_workspace(ws);
+                state.refresh_keyboard_focus();
+                json!({ "success": true })
+            }
+            Err(message) => error(&message),
+        },
+        ["config", "set", section, key, _, ..] => {
+            let value = words[4..].join(" ");
+            match state.set_config_value(section, key, &value) {
+                Ok(applied) => json!({ "success": true, "applied": applied }),
+                Err(message) => error(&message),
+            }
+        }
+        ["reload"] => match state.reload_config() {
+            Ok(diagnostics) => {
+                let errors = diagnostics
+                    .iter()
+                    .filter(|d| d.severity == Severity::Error)
+                    .count();
+                json!({
+                    "success": true,
+                    "errors": errors,
+                    "warnings": diagnostics.len() - errors,
+                })
+            }
+            Err(message) => error(&message),
+        },
+        ["kill", id] => kill_window(state, id, "TERM"),
+        ["kill", id, signal] => kill_window(state, id, signal),
+        ["wallpaper", "none"] => {
+            state.set_wallpaper(None);
+            json!({ "success": true })
+        }
+        ["wallpaper", "mode", mode] => match WallpaperMode::parse(mode) {
+            Ok(mode) => {
+                state.wallpaper.set_mode(mode, state.output_size);
+                json!({ "success": true })
+            }
+            Err(message) => error(&message),
+        },
+        ["wallpaper", "output", connector, "none"] => {
+            state.wallpaper.set_output(connector, OutputWallpaper::default(), state.output_size);
+            json!({ "success": true })
+        }
+        ["wallpaper", "output", connector, _, ..] => {
+            let prefix = format!("wallpaper output {connector}");
+            match line.strip_prefix(&prefix) {
+                Some(path) => set_output_wallpaper(state, connector, path.trim()),
+                None => error("expected: wallpaper output <connector> <path>"),
+            }
+        }
+        // The path may contain spaces
+        ["wallpaper", ..] => set_wallpaper(state, line["wallpaper".len()..].trim()),
+        ["status"] => status::bar_status(state),
+        ["switch-user"] => {
+            crate::switchuser::switch_user(state);
+            json!({ "success": true })
+        }
+        ["preset", _, ..] => match presets::apply(state, line["preset".len()..].trim()) {
+            Ok(()) => json!({ "success": true }),
+            Err(message) => error(&message),
+        },
+        ["latency"] => {
+            let latency = &state.latency;
+            json!({
+                "success": true,
+                "enabled": latency.is_enabled(),
+                "submit": latency.submit_stats().map(stats_info),
+                "present": latency.present_stats().map(stats_info),
+            })
+        }
+        ["latency", "on"] => {
+            state.latency.set_enabled(true);
+            json!({ "success": true })
+        }
+        ["latency", "off"] => {
+            state.latency.set_enabled(false);
+            json!({ "success": true })
+        }
+        ["latency", "reset"] => {
+            state.latency.reset();
+            json!({ "success": true })
+        }
+        ["perf"] => {
+            let summary = state.perf.summary();
+            json!({
+                "success": true,
+                "enabled": state.perf.is_enabled(),
+                "overlay": state.perf.overlay_visible(),
+                "fps": summary.fps,
+                "frame_time": summary.frame_time.map(stats_info),
+                "render_time": summary.render_time.map(stats_info),
+                "damage_px": summary.damage,
+                "clients": summary.clients,
+                "input": state.latency.submit_stats().map(stats_info),
+            })
+        }
+        ["perf", "on"] => {
+            state.perf.set_enabled(true);
+            state.latency.set_enabled(true);
+            json!({ "success": true })
+        }
+        ["perf", "off"] => {
+            state.perf.set_enabled(false);
+            json!({ "success": true })
+        }
+        ["perf", "reset"] => {
+            state.perf.reset();
+            json!({ "success": true })
+        }
+        ["perf", "overlay"] => {
+            state.perf.toggle_overlay();
+            if state.perf.is_enabled() {
+                state.latency.set_enabled(true);
+            }
+            json!({ "success": true })
+        }
+        [] => error("empty command"),
+        _ => error(&format!("unknown command '{line}'")),
+    }
+}
+
+/// Mode, transform, scale and position an output is using
+fn output_state(output: &Output) -> Value {
+    let mode = output.current_mode().map(|mode| {
+        format!(
+            "{}x{}@{:.2}",
+            mode.size.w,
+            mode.size.h,
+            f64::from(mode.refresh) / 1000.0
+        )
+    });
+    let position = output.current_location();
+    json!({
+        "mode": mode,
+        "transform": output::transform_name(output.current_transform()),
+        "scale": output.current_scale().fractional_scale(),
+        "position": [position.x, position.y],
+    })
+}
+
+/// Describe a window, including the process that owns it
+fn window_info(state: &HeyDM, window: &WindowElement) -> Value {
+    let (title, app_id) = window.title_and_app_id();
+    let geometry = window.geometry();
+    let focused = state
+        .window_manager
+        .focused_window()
+        .is_some_and(|f| f.id() == window.id());
+
+    let (pid, uid, exe) = match window.owner(&state.display_handle) {
+        Some((pid, uid)) => {
+            let exe = std::fs::read_link(format!("/proc/{pid}/exe"))
+                .ok()
+                .map(|path| path.display().to_string());
+            (Some(pid), Some(uid), exe)
+        }
+        None => (None, None, None),
+    };
+
+    json!({
+        "id": window.id(),
+        "title": title,
+        "app_id": app_id,
+        "workspace": window.workspace(),
+        "focused": focused,
+        "geometry": {
+            "x": geometry.loc.x,
+            "y": geometry.loc.y,
+            "width": geometry.size.w,
+            "height": geometry.size.h,
+        },
+        "pid": pid,
+        "uid": uid,
+        "exe": exe,
+        "sandbox": sandbox::of_window(window).label(),
+    })
+}
+
+/// Latency percentiles in milliseconds
+fn stats_info(stats: LatencyStats) -> Value {
+    let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+    json!({
+        "samples": stats.samples,
+        "p50_ms": ms(stats.p50),
+        "p99_ms": ms(stats.p99),
+        "max_ms": ms(stats.max),
+    })
+}
+
+/// Run `action` on a window id; it returns false for unknown windows
+fn with_window(id: &str, action: impl FnOnce(u32) -> bool) -> Value {
+    let Ok(id) = id.parse::<u32>() else {
+        return error(&format!("invalid window id '{id}'"));
+    };
+    if action(id) {
+        json!({ "success": true })
+    } else {
+        error(&format!("no window with id {id}"))
+    }
+}
+
+fn parse_workspace(value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(ws) if (1..=WORKSPACE_COUNT).contains(&ws) => Ok(ws),
+        _ => Err(format!("invalid workspace '{value}' (expected 1-{WORKSPACE_COUNT})")),
+    }
+}
+
+/// Send a signal to the process owning a window
+fn kill_window(state: &HeyDM, id: &str, signal: &str) -> Value {
+    let Ok(id) = id.parse::<u32>() else {
+        return error(&format!("invalid window id '{id}'"));
+    };
+    let Some(window) = state.window_manager.window_by_id(id) else {
+        return error(&format!("no window with id {id}"));
+    };
+    let upper = signal.to_ascii_uppercase();
+    let Ok(signal) = format!("SIG{}", upper.trim_start_matches("SIG")).parse::<Signal>() else {
+        return error(&format!("unknown signal '{signal}'"));
+    };
+    let Some((pid, _)) = window.owner(&state.display_handle) else {
+        return error("window owner is unknown");
+    };
+    if pid <= 0 || pid as u32 == std::process::id() {
+        return error("refusing to signal the compositor itself");
+    }
+
+    match kill(Pid::from_raw(pid), signal) {
+        Ok(()) => {
+            info!("IPC: sent {signal} to pid {pid} (window {id})");
+            json!({ "success": true })
+        }
+        Err(e) => error(&format!("kill failed: {e}")),
+    }
+}
+
+/// Switch to a new wallpaper; decoding finishes in the background
+fn set_wallpaper(state: &mut HeyDM, path: &str) -> Value {
+    let path = Path::new(path);
+    if !path.is_file() {
+        return error(&format!("no such file '{}'", path.display()));
+    }
+    state.set_wallpaper(Some(path.to_path_buf()));
+    json!({ "success": true })
+}
+
+/// Give one output its own wallpaper, keeping its mode
+fn set_output_wallpaper(state: &mut HeyDM, connector: &str, path: &str) -> Value {
+    let path = Path::new(path);
+    if !path.is_file() {
+        return error(&format!("no such file '{}'", path.display()));
+    }
+    let Some(identity) = state.outputs.iter().find(|output| output.connector == connector) else {
+        return error(&format!("no output '{connector}' (see `list outputs`)"));
+    };
+    let mode = output::config_for(&state.config.outputs, identity).and_then(|(_, config)| config.wallpaper_mode);
+    let wallpaper = OutputWallpaper {
+        path: Some(path.to_path_buf()),
+        mode,
+    };
+    state.wallpaper.set_output(connector, wallpaper, state.output_size);
+    json!({ "success": true })
+}
+
+fn error(message: &str) -> Value {
+    json!({ "success": false, "error": message })
+}
@@ -0,0 +1,148 @@
+// =============================================================================
+// heyDM — Input Latency Measurement
+//
+// A diagnostic mode for validating frame pacing. While enabled, the first
+// input event after each frame is timestamped on receipt, and two intervals
+// are recorded:
+//
+//   submit  — input received → the frame containing it submitted
+//   present — input received → that frame's page flip completed (DRM only)
+//
+// Enabled with HEYDM_LATENCY=1 at startup or at runtime over IPC
+// (`latency on|off|reset`); `latency` reports p50/p99 over the most recent
+// samples.
+// =============================================================================
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+/// Samples kept per interval; older ones are dropped
+const MAX_SAMPLES: usize = 1000;
+
+/// Summary of one interval's samples
+#[derive(Debug, Clone, Copy)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// Rolling window of measured intervals (also used by perf.rs)
+#[derive(Debug, Default)]
+pub struct Samples {
+    values: VecDeque<Duration>,
+}
+
+impl Samples {
+    pub fn push(&mut self, value: Duration) {
+        if self.values.len() == MAX_SAMPLES {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn stats(&self) -> Option<LatencyStats> {
+        if self.values.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.values.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank percentile
+        let rank = |p: usize| sorted[((sorted.len() * p).div_ceil(100)).max(1) - 1];
+        Some(LatencyStats {
+            samples: sorted.len(),
+            p50: rank(50),
+            p99: rank(99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Input-to-frame latency tracker
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    enabled: bool,
+    /// Oldest input not yet part of a submitted frame
+    pending: Option<Instant>,
+    /// Input of the frame waiting for its page flip
+    in_flight: Option<Instant>,
+    submit: Samples,
+    present: Samples,
+}
+
+impl LatencyTracker {
+    /// Tracker enabled when HEYDM_LATENCY is set to a true value
+    pub fn from_env() -> Self {
+        let mut tracker = Self::default();
+        if matches!(
+            std::env::var("HEYDM_LATENCY").as_deref(),
+            Ok("1" | "true" | "yes" | "on")
+        ) {
+            tracker.set_enabled(true);
+        }
+        tracker
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled != self.enabled {
+            info!(
+                "Input latency measurement {}",
+                if enabled { "enabled" } else { "disabled" }
+            );
+        }
+        self.enabled = enabled;
+        self.pending = None;
+        self.in_flight = None;
+    }
+
+    /// Drop all samples collected so far
+    pub fn reset(&mut self) {
+        self.submit = Samples::default();
+        self.present = Samples::default();
+        self.pending = None;
+        self.in_flight = None;
+    }
+
+    /// An input event arrived from the backend
+    pub fn input_received(&mut self) {
+        if self.enabled && self.pending.is_none() {
+            self.pending = Some(Instant::now());
+        }
+    }
+
+    /// A frame was handed to the backend. `awaits_flip` is set when a page
+    /// flip event will follow (DRM).
+    pub fn frame_submitted(&mut self, awaits_flip: bool) {
+        let Some(received) = self.pending.take() else {
+            return;
+        };
+        self.submit.push(received.elapsed());
+        if awaits_flip {
+            self.in_flight = Some(received);
+        }
+    }
+
+    /// The page flip of the last submitted frame completed
+    pub fn frame_presented(&mut self) {
+        if let Some(received) = self.in_flight.take() {
+            self.present.push(received.elapsed());
+        }
+    }
+
+    /// Input → frame submitted
+    pub fn submit_stats(&self) -> Option<LatencyStats> {
+        self.submit.stats()
+    }
+
+    /// Input → page flip completed
+    pub fn present_stats(&self) -> Option<LatencyStats> {
+        self.present.stats()
+    }
+}
@@ -0,0 +1,122 @@
+// =============================================================================
+// heyDM — Launch Feedback
+//
+// Tracks applications that were started from the launcher or a keybinding
+// but have not mapped a window yet. While any launch is pending the renderer
+// draws a busy spinner next to the cursor. A launch completes when a new
+// toplevel appears (matched by client pid when possible) or after
+// LAUNCH_TIMEOUT, whichever comes first.
+//
+// Every launch also gets an xdg-activation token in XDG_ACTIVATION_TOKEN
+// (and DESKTOP_STARTUP_ID for X11-era toolkits). A window that completes a
+// launch, or activates itself with its token, is focused; windows nobody
+// asked for do not steal focus (see HeyDM::request_activation).
+//
+// Every process heyDM starts goes through unblock_signals: the event loop
+// blocks the signals it handles, and children would inherit the mask.
+// =============================================================================
+
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{sigprocmask, SigSet, SigmaskHow};
+use tracing::debug;
+
+/// Give up on a launch that never maps a window after this long
+pub const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An application started by the compositor, waiting for its first window
+#[derive(Debug, Clone)]
+struct PendingLaunch {
+    /// Human-readable name for logging
+    name: String,
+    /// Pid of the spawned process
+    pid: u32,
+    /// When the process was spawned
+    started: Instant,
+}
+
+/// Pending launches that drive the busy cursor
+#[derive(Debug, Default)]
+pub struct LaunchFeedback {
+    pending: Vec<PendingLaunch>,
+    /// xdg-activation tokens handed to launched apps, and when
+    tokens: Vec<(String, Instant)>,
+}
+
+impl LaunchFeedback {
+    /// Record a freshly spawned process
+    pub fn started(&mut self, name: &str, pid: u32, token: String) {
+        self.expire();
+        self.pending.push(PendingLaunch {
+            name: name.to_string(),
+            pid,
+            started: Instant::now(),
+        });
+        self.tokens.push((token, Instant::now()));
+    }
+
+    /// A new toplevel appeared. Complete the launch with the same pid, or
+    /// the oldest one when the window came from a forked helper process.
+    /// Returns whether the window completed a launch.
+    pub fn window_mapped(&mut self, client_pid: Option<u32>) -> bool {
+        self.expire();
+        let idx = client_pid
+            .and_then(|pid| self.pending.iter().position(|l| l.pid == pid))
+            .or(if self.pending.is_empty() { None } else { Some(0) });
+
+        if let Some(idx) = idx {
+            let launch = self.pending.remove(idx);
+            debug!(
+                "'{}' mapped its first window after {:?}",
+                launch.name,
+                launch.started.elapsed()
+            );
+        }
+        idx.is_some()
+    }
+
+    /// Whether an activation token was handed to an app launched no longer
+    /// than LAUNCH_TIMEOUT ago (its window may have mapped since)
+    pub fn is_launch_token(&self, token: &str) -> bool {
+        self.tokens
+            .iter()
+            .any(|(known, created)| known == token && created.elapsed() < LAUNCH_TIMEOUT)
+    }
+
+    /// Drop launches that never mapped a window, and old tokens
+    fn expire(&mut self) {
+        self.tokens.retain(|(_, created)| created.elapsed() < LAUNCH_TIMEOUT);
+        self.pending.retain(|launch| {
+            let alive = launch.started.elapsed() < LAUNCH_TIMEOUT;
+            if !alive {
+                debug!("'{}' did not map a window in time", launch.name);
+            }
+            alive
+        });
+    }
+
+    /// Time since the most recent live launch, used to animate the spinner.
+    /// None when nothing is starting and the normal cursor should be shown.
+    pub fn busy_for(&self) -> Option<Duration> {
+        self.pending
+            .iter()
+            .map(|l| l.started.elapsed())
+            .filter(|elapsed| *elapsed < LAUNCH_TIMEOUT)
+            .min()
+    }
+}
+
+/// Start a child with no signals blocked. heyDM blocks the signals its
+/// event loop handles (SIGHUP, SIGTERM, SIGCHLD) in every thread; a child
+/// inheriting that would ignore them and hold up logout and shutdown.
+pub fn unblock_signals(command: &mut Command) -> &mut Command {
+    // SAFETY: sigprocmask is async-signal-safe, as pre_exec requires
+    unsafe {
+        command.pre_exec(|| {
+            sigprocmask(SigmaskHow::SIG_SETMASK, Some(&SigSet::empty()), None)
+                .map_err(std::io::Error::from)
+        })
+    }
+}
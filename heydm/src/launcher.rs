@@ -6,12 +6,56 @@
 //   2. Presents a searchable list of installed applications
 //   3. Launches the selected application
 //
+// Name, GenericName and Comment are shown in the user's language: the
+// localized keys (Name[de_DE], Name[de], ...) best matching LC_ALL,
+// LC_MESSAGES or LANG win over the untranslated ones.
+//
+// Exec= lines are split into arguments with shell quoting rules (no shell is
+// run) and their field codes expanded. Terminal=true apps run inside the
+// terminal and Path= sets the working directory (default: $HOME).
+//
+// Search is fuzzy (fzf-style subsequence matching: "ffx" finds Firefox).
+// Matches score higher for consecutive characters and word starts, and
+// results are ranked by score plus the app's frecency: launches weighted by
+// how recently the app was last started. With an empty query apps are listed
+// by frecency alone, so the most used ones come first. The launch history
+// persists in $XDG_STATE_HOME/heydm/launcher-history.
+//
+// App icons are resolved from the icon theme and decoded in the background
+// (see icons.rs); the grid shows a placeholder until an icon arrives. The
+// application directories are watched (see appwatch.rs), so installed and
+// removed apps show up without restarting heyDM.
+//
 // Toggled with Super+D and rendered as a centered overlay by the renderer.
+// While open it takes the keyboard: typing edits the query, arrow keys move
+// through the grid (Tab/Shift+Tab, Page Up/Down, Home/End too), the grid
+// scrolls by rows to keep the selection visible, Enter launches and Escape
+// closes.
+//
+// Apps with [Desktop Action ...] sections (Firefox → New Private Window)
+// get a menu of those actions next to their item: Right arrow or a long
+// press opens it, Up/Down pick an action, Enter starts it and Left or Escape
+// go back to the grid.
+//
+// Like rofi, a prefix switches the query into another mode with a single
+// result in place of the grid:
+//   > command      Enter runs the command line through `sh -c`
+//   = expression   the result is shown as you type (see calc.rs) and Enter
+//                  copies it to the clipboard
 // =============================================================================
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use smithay::utils::{Physical, Size};
+use tracing::{debug, info, warn};
+
+use crate::icons::LoadedIcon;
+use crate::texture::Image;
+use crate::theme::UiMetrics;
+use crate::tooltip::TooltipSource;
 
 /// Represents a launchable application parsed from a .desktop file
 #[derive(Debug, Clone)]
@@ -21,14 +65,212 @@ pub struct AppEntry {
     pub name: String,
     /// Generic name / subtitle (e.g., "Web Browser")
     pub generic_name: String,
+    /// Comment= description (e.g., "Browse the World Wide Web")
+    pub comment: String,
     /// The Exec= command to launch the application
     pub exec: String,
+    /// Exec= split into arguments, field codes expanded
+    pub argv: Vec<String>,
+    /// Terminal=true: run inside the terminal
+    pub terminal: bool,
+    /// Path=: working directory of the app
+    pub working_dir: Option<PathBuf>,
     /// Optional icon name
     pub icon: String,
     /// Categories for filtering
     pub categories: Vec<String>,
     /// Source .desktop file path
     pub desktop_file: PathBuf,
+    /// [Desktop Action ...] sections, in the order of Actions=
+    pub actions: Vec<DesktopAction>,
+}
+
+/// An additional entry point of an app, e.g. "New Private Window"
+#[derive(Debug, Clone)]
+pub struct DesktopAction {
+    pub name: String,
+    /// Exec= of the action, split and expanded like the app's
+    pub argv: Vec<String>,
+}
+
+/// What to run for an app chosen in the launcher
+#[derive(Debug, Clone)]
+pub struct AppCommand {
+    /// Display name, for logs and launch feedback
+    pub name: String,
+    pub argv: Vec<String>,
+    pub terminal: bool,
+    pub working_dir: Option<PathBuf>,
+}
+
+/// What Enter in the launcher asked for
+#[derive(Debug, Clone)]
+pub enum LauncherAction {
+    /// Start an app from the grid
+    Launch(AppCommand),
+    /// Run a "> " command line through the shell
+    Run(String),
+    /// Copy a "= " calculator result to the clipboard
+    Copy(String),
+}
+
+/// What the query asks for, by its prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryMode<'a> {
+    /// Search the installed apps
+    Apps,
+    /// "> command": run a shell command
+    Run(&'a str),
+    /// "= expression": evaluate an expression
+    Calc(&'a str),
+}
+
+/// Score of a matched query character
+const SCORE_MATCH: i32 = 16;
+/// Bonus when the previous query character matched right before this one
+const BONUS_CONSECUTIVE: i32 = 12;
+/// Bonus for matching the first character of a word
+const BONUS_WORD_START: i32 = 8;
+/// Penalty per skipped candidate character between matches
+const PENALTY_GAP: i32 = 1;
+/// Score added per recorded launch (capped at USAGE_MAX launches), before
+/// the recency weight
+const BONUS_USAGE: i32 = 3;
+const USAGE_MAX: u32 = 20;
+/// Recency weight in percent by days since the last launch
+const RECENCY_WEIGHTS: &[(u64, i32)] = &[(4, 100), (14, 70), (31, 50), (90, 30)];
+/// Weight of apps not launched for longer than the last bucket
+const RECENCY_WEIGHT_OLD: i32 = 10;
+
+/// How often and when an app was launched
+#[derive(Debug, Clone, Copy, Default)]
+struct LaunchRecord {
+    count: u32,
+    /// Unix time of the last launch in seconds
+    last: u64,
+}
+
+impl LaunchRecord {
+    /// Ranking bonus: the launch count weighted by recency
+    fn frecency(&self, now: u64) -> i32 {
+        let days = now.saturating_sub(self.last) / 86_400;
+        let weight = RECENCY_WEIGHTS
+            .iter()
+            .find(|(max_days, _)| days < *max_days)
+            .map_or(RECENCY_WEIGHT_OLD, |(_, weight)| *weight);
+        self.count.min(USAGE_MAX) as i32 * BONUS_USAGE * weight / 100
+    }
+}
+
+/// How long a grid item must be held to open its actions instead of
+/// launching the app
+const LONG_PRESS: Duration = Duration::from_millis(500);
+
+/// What a pointer press in the launcher hit
+#[derive(Debug, Clone, Copy)]
+enum Press {
+    /// A grid item (index into `apps`), and when
+    Item(usize, Instant),
+    /// A row of the open actions menu
+    Action,
+}
+
+/// Columns and rows of the launcher grid
+const GRID_COLUMNS: i32 = 4;
+const GRID_ROWS: i32 = 3;
+
+/// Geometry of the launcher overlay, shared by the renderer and hit-testing
+#[derive(Debug, Clone, Copy)]
+pub struct LauncherLayout {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    /// Height of the search bar below the top margin
+    pub search_h: i32,
+    /// Top-left corner of the grid and the size of one item
+    pub grid_x: i32,
+    pub grid_y: i32,
+    pub item_w: i32,
+    pub item_h: i32,
+    /// Width and row height of an item's actions menu
+    pub menu_w: i32,
+    pub menu_row_h: i32,
+    /// Output width, so the actions menu stays on screen
+    output_w: i32,
+}
+
+impl LauncherLayout {
+    pub fn new(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Self {
+        let w = ui.px(800).min(output_size.w - ui.px(100)).max(0);
+        let h = ui.px(600).min(output_size.h - ui.px(200)).max(0);
+        let x = (output_size.w - w) / 2;
+        let y = (output_size.h - h) / 2;
+        let search_h = ui.px(50);
+        Self {
+            x,
+            y,
+            w,
+            h,
+            search_h,
+            grid_x: x + ui.px(30),
+            grid_y: y + ui.px(20) + search_h + ui.px(20),
+            item_w: (w - ui.px(60)) / GRID_COLUMNS,
+            item_h: ui.px(100),
+            menu_w: ui.px(240),
+            menu_row_h: ui.px(34),
+            output_w: output_size.w,
+        }
+    }
+
+    /// Top-left corner of the actions menu of the i-th grid item: to the
+    /// right of the item, or to its left when it would leave the screen
+    pub fn action_menu(&self, i: usize) -> (i32, i32) {
+        let (x, y) = self.item(i);
+        if x + self.item_w + self.menu_w <= self.output_w {
+            (x + self.item_w, y)
+        } else {
+            (x - self.menu_w, y)
+        }
+    }
+
+    /// Number of grid items shown at most
+    pub fn capacity(&self) -> usize {
+        (GRID_COLUMNS * GRID_ROWS) as usize
+    }
+
+    /// Top-left corner of the i-th grid item
+    pub fn item(&self, i: usize) -> (i32, i32) {
+        let (row, col) = (i as i32 / GRID_COLUMNS, i as i32 % GRID_COLUMNS);
+        (self.grid_x + col * self.item_w, self.grid_y + row * self.item_h)
+    }
+
+    /// Index of the grid item under (x, y)
+    fn item_at(&self, x: f64, y: f64) -> Option<usize> {
+        let inside = x >= self.x as f64
+            && x <= (self.x + self.w) as f64
+            && y >= self.grid_y as f64
+            && y <= (self.y + self.h) as f64;
+        if !inside || self.item_w <= 0 {
+            return None;
+        }
+        let col = ((x - self.grid_x as f64) / self.item_w as f64).floor() as i32;
+        let row = ((y - self.grid_y as f64) / self.item_h as f64).floor() as i32;
+        if !(0..GRID_COLUMNS).contains(&col) || !(0..GRID_ROWS).contains(&row) {
+            return None;
+        }
+        Some((row * GRID_COLUMNS + col) as usize)
+    }
+
+    /// Row of the actions menu of the i-th grid item under (x, y)
+    fn action_at(&self, i: usize, count: usize, x: f64, y: f64) -> Option<usize> {
+        let (menu_x, menu_y) = self.action_menu(i);
+        if x < menu_x as f64 || x > (menu_x + self.menu_w) as f64 || y < menu_y as f64 {
+            return None;
+        }
+        let row = ((y - menu_y as f64) / self.menu_row_h as f64) as usize;
+        (row < count).then_some(row)
+    }
 }
 
 /// The application launcher overlay
@@ -41,20 +283,44 @@ pub struct AppLauncher {
     filtered: Vec<usize>, // indices into `apps`
     /// Currently selected item index in `filtered`
     selected: usize,
+    /// Index in `filtered` of the first item on screen (start of a row)
+    scroll: usize,
+    /// Selected row of the selected app's actions menu, while it is open
+    action_selected: Option<usize>,
+    /// Pointer press waiting for its release
+    pressed: Option<Press>,
     /// Whether the launcher is currently visible
     visible: bool,
+    /// Launch history by desktop file name
+    history: HashMap<String, LaunchRecord>,
+    /// Decoded icons by Icon= name (see icons.rs)
+    icons: HashMap<String, Image>,
+    /// Result of the expression in calculator mode
+    calc_result: Option<Result<String, String>>,
+    /// UI scale for the overlay geometry
+    ui: UiMetrics,
+    /// Locale suffixes of localized keys to look for, best match first
+    locales: Vec<String>,
 }
 
 #[allow(dead_code)]
 impl AppLauncher {
     /// Create a new launcher, scanning for .desktop files
-    pub fn new() -> Self {
+    pub fn new(ui: UiMetrics) -> Self {
         let mut launcher = Self {
             apps: Vec::new(),
             search_query: String::new(),
             filtered: Vec::new(),
             selected: 0,
+            scroll: 0,
+            action_selected: None,
+            pressed: None,
             visible: false,
+            history: load_history(),
+            icons: HashMap::new(),
+            calc_result: None,
+            ui,
+            locales: locale_variants(),
         };
 
         launcher.scan_desktop_files();
@@ -66,35 +332,68 @@ impl AppLauncher {
 
     /// Scan standard XDG directories for .desktop files
     fn scan_desktop_files(&mut self) {
-        let search_dirs = [
-            "/usr/share/applications",
-            "/usr/local/share/applications",
-            "/var/lib/flatpak/exports/share/applications",
-        ];
-
-        // Also check user-specific directory
-        let home = std::env::var("HOME").unwrap_or_default();
-        let user_dir = format!("{home}/.local/share/applications");
-
-        let mut all_dirs: Vec<&str> = search_dirs.to_vec();
-        if !home.is_empty() {
-            all_dirs.push(&user_dir);
-        }
-
-        for dir in all_dirs {
-            let dir_path = Path::new(dir);
-            if !dir_path.exists() {
+        for dir in application_dirs() {
+            if !dir.exists() {
                 continue;
             }
 
-            debug!("Scanning .desktop files in: {dir}");
-            self.scan_directory(dir_path);
+            debug!("Scanning .desktop files in: {}", dir.display());
+            self.scan_directory(&dir);
         }
 
         // Sort applications alphabetically by name
         self.apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
     }
 
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    /// Scan all directories again from scratch. Returns the icons that
+    /// still need loading.
+    pub fn rescan(&mut self) -> Vec<String> {
+        self.apps.clear();
+        self.scan_desktop_files();
+        self.apps_changed();
+        info!("Launcher rescanned: {} apps found", self.apps.len());
+        self.icon_names()
+            .into_iter()
+            .filter(|icon| !self.icons.contains_key(icon))
+            .collect()
+    }
+
+    /// Re-read one .desktop file after it was created, changed or removed.
+    /// Returns its icon when that still needs loading.
+    pub fn reload_desktop_file(&mut self, path: &Path) -> Option<String> {
+        let before = self.apps.len();
+        self.apps.retain(|app| app.desktop_file != path);
+        let app = self.parse_desktop_file(path);
+        match &app {
+            Some(app) if self.apps.len() == before => info!("Launcher: new app '{}'", app.name),
+            Some(app) => debug!("Launcher: updated '{}'", app.name),
+            None if self.apps.len() != before => info!("Launcher: removed {}", path.display()),
+            None => {}
+        }
+
+        let icon = app.as_ref().map(|app| app.icon.clone());
+        if let Some(app) = app {
+            let key = app.name.to_lowercase();
+            let at = self.apps.partition_point(|other| other.name.to_lowercase() <= key);
+            self.apps.insert(at, app);
+        }
+        self.apps_changed();
+        icon.filter(|icon| !icon.is_empty() && !self.icons.contains_key(icon))
+    }
+
+    /// Indices into `apps` shifted: rebuild the results and drop state that
+    /// points at an app
+    fn apps_changed(&mut self) {
+        self.action_selected = None;
+        self.pressed = None;
+        self.update_filter();
+    }
+
     /// Scan a single directory for .desktop files
     fn scan_directory(&mut self, dir: &Path) {
         let entries = match fs::read_dir(dir) {
@@ -118,13 +417,20 @@ impl AppLauncher {
     fn parse_desktop_file(&self, path: &Path) -> Option<AppEntry> {
         let content = fs::read_to_string(path).ok()?;
 
-        let mut name = String::new();
-        let mut generic_name = String::new();
+        // Localized values with the rank of their locale (see `locale_rank`)
+        let mut name = (String::new(), usize::MAX);
+        let mut generic_name = (String::new(), usize::MAX);
+        let mut comment = (String::new(), usize::MAX);
         let mut exec = String::new();
+        let mut terminal = false;
+        let mut working_dir = None;
         let mut icon = String::new();
         let mut categories = Vec::new();
         let mut no_display = false;
         let mut hidden = false;
+        let mut action_ids: Vec<String> = Vec::new();
+        // (id, Name= and its rank, Exec=) of every [Desktop Action id] section
+        let mut action_sections: Vec<(String, (String, usize), String)> = Vec::new();
         let mut in_desktop_entry = false;
 
         for line in content.lines() {
@@ -133,10 +439,27 @@ impl AppLauncher {
             // Track section headers
             if line.starts_with('[') {
                 in_desktop_entry = line == "[Desktop Entry]";
+                if let Some(id) = line
+                    .strip_prefix("[Desktop Action ")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                {
+                    action_sections.push((id.to_string(), (String::new(), usize::MAX), String::new()));
+                }
                 continue;
             }
 
             if !in_desktop_entry {
+                // Inside an action section, if the last header opened one
+                let Some((key, value)) = line.split_once('=') else { continue };
+                let Some((_, action_name, action_exec)) = action_sections.last_mut() else {
+                    continue;
+                };
+                let key = key.trim();
+                if let Some(rank) = self.locale_rank(key, "Name") {
+                    localize(action_name, value.trim(), rank);
+                } else if key == "Exec" && action_exec.is_empty() {
+                    *action_exec = value.trim().to_string();
+                }
                 continue;
             }
 
@@ -145,27 +468,30 @@ impl AppLauncher {
                 let key = key.trim();
                 let value = value.trim();
 
+                if let Some(rank) = self.locale_rank(key, "Name") {
+                    localize(&mut name, value, rank);
+                    continue;
+                }
+                if let Some(rank) = self.locale_rank(key, "GenericName") {
+                    localize(&mut generic_name, value, rank);
+                    continue;
+                }
+                if let Some(rank) = self.locale_rank(key, "Comment") {
+                    localize(&mut comment, value, rank);
+                    continue;
+                }
+
                 match key {
-                    "Name" if name.is_empty() => name = value.to_string(),
-                    "GenericName" if generic_name.is_empty() => {
-                        generic_name = value.to_string()
-                    }
-                    "Exec" if exec.is_empty() => {
-                        // Remove field codes like %f, %u, %U, etc.
-                        exec = value
-                            .replace("%f", "")
-                            .replace("%F", "")
-                            .replace("%u", "")
-                            .replace("%U", "")
-                            .replace("%d", "")
-                            .replace("%D", "")
-                            .replace("%n", "")
-                            .replace("%N", "")
-                            .replace("%k", "")
-                            .replace("%v", "")
-                            .trim()
-                            .to_string();
+                    "Actions" => {
+                        action_ids = value
+                            .split(';')
+                            .map(|id| id.trim().to_string())
+                            .filter(|id| !id.is_empty())
+                            .collect();
                     }
+                    "Exec" if exec.is_empty() => exec = value.to_string(),
+                    "Terminal" => terminal = value.eq_ignore_ascii_case("true"),
+                    "Path" if !value.is_empty() => working_dir = Some(PathBuf::from(value)),
                     "Icon" if icon.is_empty() => icon = value.to_string(),
                     "Categories" => {
                         categories = value
@@ -186,21 +512,58 @@ impl AppLauncher {
             return None;
         }
 
+        let (name, generic_name, comment) = (name.0, generic_name.0, comment.0);
+
         // Must have both a name and an exec command
         if name.is_empty() || exec.is_empty() {
             return None;
         }
+        let Some(argv) = exec_arguments(&exec, &name, &icon, path) else {
+            debug!("Skipping {}: cannot parse Exec={exec}", path.display());
+            return None;
+        };
+
+        // Only the actions listed in Actions= are shown, in that order
+        let actions = action_ids
+            .iter()
+            .filter_map(|id| {
+                let (_, action_name, action_exec) =
+                    action_sections.iter().find(|(section, _, _)| section == id)?;
+                if action_name.0.is_empty() {
+                    return None;
+                }
+                let argv = exec_arguments(action_exec, &name, &icon, path)?;
+                Some(DesktopAction { name: action_name.0.clone(), argv })
+            })
+            .collect();
 
         Some(AppEntry {
             name,
             generic_name,
+            comment,
             exec,
+            argv,
+            terminal,
+            working_dir,
             icon,
             categories,
             desktop_file: path.to_path_buf(),
+            actions,
         })
     }
 
+    /// Rank of `key` as a variant of the localizable key `base`: the
+    /// position of its locale in `locales` (lower is better), one past the
+    /// end for the untranslated key, None for other keys and locales
+    fn locale_rank(&self, key: &str, base: &str) -> Option<usize> {
+        let rest = key.strip_prefix(base)?;
+        if rest.is_empty() {
+            return Some(self.locales.len());
+        }
+        let locale = rest.strip_prefix('[')?.strip_suffix(']')?;
+        self.locales.iter().position(|l| l == locale)
+    }
+
     // ---- State management ----
 
     /// Toggle the launcher visibility
@@ -210,6 +573,8 @@ impl AppLauncher {
             // Reset state when opening
             self.search_query.clear();
             self.selected = 0;
+            self.scroll = 0;
+            self.action_selected = None;
             self.update_filter();
             info!("Launcher opened");
         } else {
@@ -222,12 +587,16 @@ impl AppLauncher {
         self.visible = true;
         self.search_query.clear();
         self.selected = 0;
+        self.scroll = 0;
+        self.action_selected = None;
         self.update_filter();
     }
 
     /// Hide the launcher
     pub fn hide(&mut self) {
         self.visible = false;
+        self.action_selected = None;
+        self.pressed = None;
     }
 
     /// Whether the launcher is currently visible
@@ -235,6 +604,11 @@ impl AppLauncher {
         self.visible
     }
 
+    /// Index in the filtered list of the first item shown in the grid
+    pub fn scroll_offset(&self) -> usize {
+        self.scroll
+    }
+
     /// Get the index of the currently selected item
     pub fn selected_index(&self) -> Option<usize> {
         if self.filtered.is_empty() {
@@ -244,37 +618,216 @@ impl AppLauncher {
         }
     }
 
+    /// Action names of the selected app and the selected row, while its
+    /// actions menu is open
+    pub fn open_actions(&self) -> Option<(Vec<&str>, usize)> {
+        let row = self.action_selected?;
+        let app = &self.apps[*self.filtered.get(self.selected)?];
+        Some((app.actions.iter().map(|action| action.name.as_str()).collect(), row))
+    }
+
+    /// Open the actions menu of the selected app. Returns false when it has
+    /// no actions.
+    fn show_actions(&mut self) -> bool {
+        let has_actions = self
+            .filtered
+            .get(self.selected)
+            .is_some_and(|&idx| !self.apps[idx].actions.is_empty());
+        if has_actions {
+            self.action_selected = Some(0);
+        }
+        has_actions
+    }
+
     // ---- Search filtering ----
 
+    /// What the current query asks for
+    pub fn query_mode(&self) -> QueryMode<'_> {
+        let query = self.search_query.as_str();
+        if let Some(command) = query.strip_prefix('>') {
+            QueryMode::Run(command.trim())
+        } else if let Some(expr) = query.strip_prefix('=') {
+            QueryMode::Calc(expr.trim())
+        } else {
+            QueryMode::Apps
+        }
+    }
+
+    /// Calculator result (or error) while the query starts with "="
+    pub fn calc_result(&self) -> Option<&Result<String, String>> {
+        self.calc_result.as_ref()
+    }
+
     /// Update the filtered list based on the current search query
     fn update_filter(&mut self) {
-        let query = self.search_query.to_lowercase();
-
-        if query.is_empty() {
-            // Show all apps
-            self.filtered = (0..self.apps.len()).collect();
-        } else {
-            // Filter by name, generic name, and categories
-            self.filtered = self
-                .apps
-                .iter()
-                .enumerate()
-                .filter(|(_, app)| {
-                    app.name.to_lowercase().contains(&query)
-                        || app.generic_name.to_lowercase().contains(&query)
-                        || app
-                            .categories
-                            .iter()
-                            .any(|c| c.to_lowercase().contains(&query))
-                })
-                .map(|(idx, _)| idx)
-                .collect();
+        // The prefix modes replace the app grid with a single result
+        match self.query_mode() {
+            QueryMode::Apps => self.calc_result = None,
+            QueryMode::Run(_) => {
+                self.calc_result = None;
+                self.filtered.clear();
+                return;
+            }
+            QueryMode::Calc(expr) => {
+                self.calc_result = Some(crate::calc::evaluate(expr));
+                self.filtered.clear();
+                return;
+            }
         }
 
+        let query: Vec<char> = self.search_query.to_lowercase().chars().collect();
+
+        // Name matches count fully; generic names and categories only help
+        // when the name does not match as well
+        let now = unix_now();
+        let mut ranked: Vec<(i32, usize)> = self
+            .apps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, app)| {
+                let score = if query.is_empty() {
+                    0
+                } else {
+                    let name = fuzzy_score(&query, &app.name);
+                    let generic = fuzzy_score(&query, &app.generic_name).map(|s| s - 10);
+                    let category = app
+                        .categories
+                        .iter()
+                        .filter_map(|c| fuzzy_score(&query, c))
+                        .max()
+                        .map(|s| s - 20);
+                    name.max(generic).max(category)?
+                };
+                Some((score + self.frecency(app, now), idx))
+            })
+            .collect();
+
+        // Best first; apps are sorted by name, so ties stay alphabetical
+        ranked.sort_by_key(|(score, idx)| (std::cmp::Reverse(*score), *idx));
+        self.filtered = ranked.into_iter().map(|(_, idx)| idx).collect();
+
         // Clamp selection
         if self.selected >= self.filtered.len() && !self.filtered.is_empty() {
             self.selected = self.filtered.len() - 1;
         }
+        self.scroll_to_selection();
+    }
+
+    /// Ranking bonus for frequently and recently launched apps
+    fn frecency(&self, app: &AppEntry, now: u64) -> i32 {
+        self.history
+            .get(&desktop_id(app))
+            .map_or(0, |record| record.frecency(now))
+    }
+
+    /// Record a launch of an app (index into `apps`), persist the history
+    /// and return what to run
+    fn launch_app(&mut self, app_idx: usize) -> AppCommand {
+        let app = &self.apps[app_idx];
+        info!("Launcher: launching '{}' → {}", app.name, app.exec);
+        let record = self.history.entry(desktop_id(app)).or_default();
+        record.count += 1;
+        record.last = unix_now();
+        let command = AppCommand {
+            name: app.name.clone(),
+            argv: app.argv.clone(),
+            terminal: app.terminal,
+            working_dir: app.working_dir.clone(),
+        };
+        save_history(&self.history);
+        command
+    }
+
+    /// Like `launch_app`, but run one of the app's desktop actions
+    fn launch_action(&mut self, app_idx: usize, action_idx: usize) -> Option<AppCommand> {
+        let mut command = self.launch_app(app_idx);
+        let action = self.apps[app_idx].actions.get(action_idx)?;
+        info!("Launcher: action '{}' of '{}'", action.name, command.name);
+        command.name = format!("{} → {}", command.name, action.name);
+        command.argv = action.argv.clone();
+        Some(command)
+    }
+
+    /// Keys while the selected app's actions menu is open
+    fn handle_actions_key(&mut self, row: usize, keysym: xkbcommon::xkb::Keysym) -> Option<LauncherAction> {
+        use xkbcommon::xkb::Keysym as K;
+
+        let count = self.apps[*self.filtered.get(self.selected)?].actions.len();
+        match keysym {
+            K::Return | K::KP_Enter => {
+                let app_idx = *self.filtered.get(self.selected)?;
+                let command = self.launch_action(app_idx, row)?;
+                self.hide();
+                return Some(LauncherAction::Launch(command));
+            }
+            K::Escape | K::Left => self.action_selected = None,
+            K::Up => self.action_selected = Some(row.saturating_sub(1)),
+            K::Down => self.action_selected = Some((row + 1).min(count - 1)),
+            K::Home => self.action_selected = Some(0),
+            K::End => self.action_selected = Some(count - 1),
+            _ => {
+                // Typing goes back to searching
+                if let Some(ch) = keysym.key_char().filter(|c| !c.is_control()) {
+                    self.action_selected = None;
+                    self.type_char(ch);
+                }
+            }
+        }
+        None
+    }
+
+    /// Handle a key press while the launcher is open. Returns the command
+    /// to run when an app was chosen (the launcher closes then).
+    pub fn handle_key(&mut self, keysym: xkbcommon::xkb::Keysym) -> Option<LauncherAction> {
+        use xkbcommon::xkb::Keysym as K;
+
+        if let Some(row) = self.action_selected {
+            return self.handle_actions_key(row, keysym);
+        }
+
+        match keysym {
+            K::Return | K::KP_Enter => {
+                let action = match self.query_mode() {
+                    QueryMode::Apps => {
+                        let app_idx = *self.filtered.get(self.selected)?;
+                        LauncherAction::Launch(self.launch_app(app_idx))
+                    }
+                    QueryMode::Run("") => return None,
+                    QueryMode::Run(command) => {
+                        info!("Launcher: running '{command}'");
+                        LauncherAction::Run(command.to_string())
+                    }
+                    QueryMode::Calc(_) => {
+                        let result = self.calc_result.clone()?.ok()?;
+                        LauncherAction::Copy(result)
+                    }
+                };
+                self.hide();
+                return Some(action);
+            }
+            K::Escape => self.hide(),
+            K::BackSpace => self.backspace(),
+            K::Left => self.select_prev(),
+            K::Right => {
+                if !self.show_actions() {
+                    self.select_next();
+                }
+            }
+            K::Up => self.move_selection(-GRID_COLUMNS as isize),
+            K::Down => self.move_selection(GRID_COLUMNS as isize),
+            K::Tab => self.select_next(),
+            K::ISO_Left_Tab => self.select_prev(),
+            K::Page_Up => self.move_selection(-(GRID_COLUMNS * GRID_ROWS) as isize),
+            K::Page_Down => self.move_selection((GRID_COLUMNS * GRID_ROWS) as isize),
+            K::Home => self.move_selection(isize::MIN),
+            K::End => self.move_selection(isize::MAX),
+            _ => {
+                if let Some(ch) = keysym.key_char().filter(|c| !c.is_control()) {
+                    self.type_char(ch);
+                }
+            }
+        }
+        None
     }
 
     /// Add a character to the search query
@@ -291,17 +844,35 @@ impl AppLauncher {
         self.update_filter();
     }
 
-    /// Move selection up
+    /// Move selection to the previous item
     pub fn select_prev(&mut self) {
-        if self.selected > 0 {
-            self.selected -= 1;
-        }
+        self.move_selection(-1);
     }
 
-    /// Move selection down
+    /// Move selection to the next item
     pub fn select_next(&mut self) {
-        if self.selected + 1 < self.filtered.len() {
-            self.selected += 1;
+        self.move_selection(1);
+    }
+
+    /// Move the selection by `delta` items, stopping at either end
+    fn move_selection(&mut self, delta: isize) {
+        if self.filtered.is_empty() {
+            return;
+        }
+        let last = self.filtered.len() - 1;
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+        self.scroll_to_selection();
+    }
+
+    /// Scroll the grid by whole rows so the selection is on screen
+    fn scroll_to_selection(&mut self) {
+        let columns = GRID_COLUMNS as usize;
+        let page = (GRID_COLUMNS * GRID_ROWS) as usize;
+        let row_start = self.selected - self.selected % columns;
+        if row_start < self.scroll {
+            self.scroll = row_start;
+        } else if row_start >= self.scroll + page {
+            self.scroll = row_start + columns - page;
         }
     }
 
@@ -311,17 +882,37 @@ impl AppLauncher {
         Some(&self.apps[idx].exec)
     }
 
-    /// Get the display entries (name + generic name) for the currently visible items
-    pub fn visible_entries(&self) -> Vec<(&str, &str)> {
+    /// Display entries (name, generic name and icon once loaded) of the
+    /// items on screen, starting at `scroll_offset()`
+    pub fn visible_entries(&self) -> impl ExactSizeIterator<Item = (&str, &str, Option<&Image>)> {
         self.filtered
             .iter()
+            .skip(self.scroll)
+            .take((GRID_COLUMNS * GRID_ROWS) as usize)
             .map(|&idx| {
-                (
-                    self.apps[idx].name.as_str(),
-                    self.apps[idx].generic_name.as_str(),
-                )
+                let app = &self.apps[idx];
+                (app.name.as_str(), app.generic_name.as_str(), self.icons.get(&app.icon))
             })
-            .collect()
+    }
+
+    /// Distinct Icon= names of all apps, for the icon loader
+    pub fn icon_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .apps
+            .iter()
+            .map(|app| app.icon.clone())
+            .filter(|icon| !icon.is_empty())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Store an icon decoded by the icon loader
+    pub fn set_icon(&mut self, icon: LoadedIcon) {
+        if let Some(image) = icon.image {
+            self.icons.insert(icon.name, image);
+        }
     }
 
     /// Get search query
@@ -331,48 +922,313 @@ impl AppLauncher {
 
     // ---- Click handling ----
 
-    /// Handle a click on the launcher overlay
-    /// Returns Some(exec_command) if an app was selected, None otherwise
-    pub fn handle_click(&self, x: f64, y: f64, output_w: u32, output_h: u32) -> Option<String> {
-        if !self.visible {
+    /// Handle a button press on the launcher overlay. Returns whether the
+    /// press hit an item or the open actions menu; the release decides what
+    /// happens (see `handle_release`).
+    pub fn handle_press(&mut self, x: f64, y: f64, output_w: u32, output_h: u32) -> bool {
+        self.pressed = if self.action_at(x, y, output_w, output_h).is_some() {
+            Some(Press::Action)
+        } else {
+            // Clicking anywhere else closes the actions menu
+            self.action_selected = None;
+            self.item_at(x, y, output_w, output_h)
+                .map(|app_idx| Press::Item(app_idx, Instant::now()))
+        };
+        self.pressed.is_some()
+    }
+
+    /// Handle the release of a press `handle_press` accepted. A click on an
+    /// item launches the app, a long press opens its actions instead, and a
+    /// click on an action runs it. Returns the command to run, if any.
+    pub fn handle_release(&mut self, x: f64, y: f64, output_w: u32, output_h: u32) -> Option<AppCommand> {
+        let (pressed_idx, since) = match self.pressed.take()? {
+            Press::Item(app_idx, since) => (app_idx, since),
+            Press::Action => {
+                let row = self.action_at(x, y, output_w, output_h)?;
+                let app_idx = *self.filtered.get(self.selected)?;
+                let command = self.launch_action(app_idx, row)?;
+                self.hide();
+                return Some(command);
+            }
+        };
+        let app_idx = self.item_at(x, y, output_w, output_h)?;
+        // Releasing over another item cancels the click
+        if app_idx != pressed_idx {
+            return None;
+        }
+        if since.elapsed() >= LONG_PRESS {
+            self.selected = self.filtered.iter().position(|&idx| idx == app_idx)?;
+            self.show_actions();
             return None;
         }
+        let command = self.launch_app(app_idx);
+        self.hide();
+        Some(command)
+    }
 
-        let launcher_w = 800.min(output_w.saturating_sub(100));
-        let launcher_h = 600.min(output_h.saturating_sub(200));
-        let launcher_x = (output_w.saturating_sub(launcher_w)) / 2;
-        let launcher_y = (output_h.saturating_sub(launcher_h)) / 2;
+    /// Whether a press accepted by `handle_press` is waiting for its release
+    pub fn is_pressed(&self) -> bool {
+        self.pressed.is_some()
+    }
 
-        if x < launcher_x as f64 || x > (launcher_x + launcher_w) as f64 || y < launcher_y as f64 || y > (launcher_y + launcher_h) as f64 {
+    /// Find the app (index into `apps`) whose grid item is under (x, y)
+    fn item_at(&self, x: f64, y: f64, output_w: u32, output_h: u32) -> Option<usize> {
+        if !self.visible {
             return None;
         }
+        let output_size = Size::from((output_w as i32, output_h as i32));
+        let clicked_idx = LauncherLayout::new(output_size, &self.ui).item_at(x, y)?;
+        self.filtered.get(self.scroll + clicked_idx).copied()
+    }
 
-        let search_bar_h = 50;
-        let items_start_y = launcher_y + 20 + search_bar_h + 20; // 90
-        
-        if y < items_start_y as f64 {
-            return None; // clicked search bar
+    /// Row of the open actions menu under (x, y)
+    fn action_at(&self, x: f64, y: f64, output_w: u32, output_h: u32) -> Option<usize> {
+        self.action_selected?;
+        let count = self.apps[*self.filtered.get(self.selected)?].actions.len();
+        let output_size = Size::from((output_w as i32, output_h as i32));
+        let item = self.selected.checked_sub(self.scroll)?;
+        LauncherLayout::new(output_size, &self.ui).action_at(item, count, x, y)
+    }
+}
+
+impl TooltipSource for AppLauncher {
+    /// Full name, description and command of the hovered grid item
+    fn tooltip_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<String> {
+        let app_idx = self.item_at(pos.0, pos.1, output_size.w as u32, output_size.h as u32)?;
+        let app = &self.apps[app_idx];
+        let description = if app.comment.is_empty() { &app.generic_name } else { &app.comment };
+        Some(if description.is_empty() {
+            format!("{} — {}", app.name, app.exec)
+        } else {
+            format!("{} ({description}) — {}", app.name, app.exec)
+        })
+    }
+}
+
+/// fzf-style score of `query` (lowercase) as a subsequence of `candidate`,
+/// or None when it does not match. Every possible start of the first
+/// character is tried; later characters match greedily.
+fn fuzzy_score(query: &[char], candidate: &str) -> Option<i32> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let text: Vec<char> = candidate.to_lowercase().chars().collect();
+    let word_start = |i: usize| i == 0 || matches!(text[i - 1], ' ' | '-' | '_' | '.' | '/');
+
+    let mut best = None;
+    for start in (0..text.len()).filter(|i| text[*i] == query[0]) {
+        let mut score = SCORE_MATCH + if word_start(start) { BONUS_WORD_START } else { 0 };
+        // Matches at the very beginning rank above matches later on
+        score -= start.min(10) as i32;
+        let mut last = start;
+        let mut matched = true;
+        for q in &query[1..] {
+            let Some(offset) = text[last + 1..].iter().position(|c| c == q) else {
+                matched = false;
+                break;
+            };
+            let pos = last + 1 + offset;
+            score += SCORE_MATCH;
+            if offset == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= offset as i32 * PENALTY_GAP;
+            }
+            if word_start(pos) {
+                score += BONUS_WORD_START;
+            }
+            last = pos;
         }
-        
-        let cols = 4;
-        let item_w = (launcher_w - 60) / cols;
-        let item_h = 100;
-        
-        let col = ((x - (launcher_x as f64 + 30.0)) / item_w as f64) as i32;
-        let row = ((y - items_start_y as f64) / item_h as f64) as i32;
-        
-        if col < 0 || col >= cols as i32 || row < 0 || row >= 3 {
-            return None; // outside grid
+        if matched {
+            best = best.max(Some(score));
         }
-        
-        let clicked_idx = (row * cols as i32 + col) as usize;
+    }
+    best
+}
 
-        if let Some(&app_idx) = self.filtered.get(clicked_idx) {
-            let exec = self.apps[app_idx].exec.clone();
-            info!("Launcher: selected '{}' → {}", self.apps[app_idx].name, exec);
-            Some(exec)
-        } else {
-            None
+/// Directories searched for .desktop files, system-wide first
+pub fn application_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = [
+        "/usr/share/applications",
+        "/usr/local/share/applications",
+        "/var/lib/flatpak/exports/share/applications",
+    ]
+    .iter()
+    .map(PathBuf::from)
+    .collect();
+
+    // Also check user-specific directory
+    let home = std::env::var("HOME").unwrap_or_default();
+    if !home.is_empty() {
+        dirs.push(PathBuf::from(home).join(".local/share/applications"));
+    }
+    dirs
+}
+
+/// Keep `value` if its locale ranks better than the one seen so far
+fn localize(current: &mut (String, usize), value: &str, rank: usize) {
+    if rank < current.1 {
+        *current = (value.to_string(), rank);
+    }
+}
+
+/// Locale suffixes to look for in localized keys, best match first. For
+/// LANG=sr_RS.UTF-8@latin: sr_RS@latin, sr_RS, sr@latin, sr.
+fn locale_variants() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    if locale == "C" || locale == "POSIX" || locale.starts_with("C.") {
+        return Vec::new();
+    }
+
+    let (locale, modifier) = match locale.split_once('@') {
+        Some((locale, modifier)) => (locale, Some(modifier)),
+        None => (locale.as_str(), None),
+    };
+    // The encoding is not part of the key
+    let locale = locale.split('.').next().unwrap_or_default();
+    let (lang, country) = match locale.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (locale, None),
+    };
+    if lang.is_empty() {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{lang}@{modifier}"));
+    }
+    variants.push(lang.to_string());
+    variants
+}
+
+/// Split an Exec= value into arguments and expand its field codes. Files
+/// and URLs (%f, %u, ...) are never passed, so those codes are dropped;
+/// %i, %c and %k become the icon, name and desktop file.
+fn exec_arguments(exec: &str, name: &str, icon: &str, desktop_file: &Path) -> Option<Vec<String>> {
+    let mut argv = Vec::new();
+    for arg in shlex::split(exec)? {
+        match arg.as_str() {
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {}
+            "%i" if icon.is_empty() => {}
+            "%i" => argv.extend(["--icon".to_string(), icon.to_string()]),
+            _ => {
+                let mut expanded = String::new();
+                let mut chars = arg.chars();
+                while let Some(ch) = chars.next() {
+                    if ch != '%' {
+                        expanded.push(ch);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('%') => expanded.push('%'),
+                        Some('c') => expanded.push_str(name),
+                        Some('k') => expanded.push_str(&desktop_file.to_string_lossy()),
+                        // Other codes inside an argument expand to nothing
+                        _ => {}
+                    }
+                }
+                argv.push(expanded);
+            }
         }
     }
+    (!argv.is_empty()).then_some(argv)
+}
+
+/// Stable key of an app in the launch history
+fn desktop_id(app: &AppEntry) -> String {
+    app.desktop_file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| app.name.clone())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// $XDG_STATE_HOME/heydm/launcher-history (~/.local/state by default)
+fn history_path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("heydm").join("launcher-history"))
+}
+
+/// Launch history, one "<count> <last launch> <desktop file>" line per app
+fn load_history() -> HashMap<String, LaunchRecord> {
+    let Some(content) = history_path().and_then(|path| fs::read_to_string(path).ok()) else {
+        return HashMap::new();
+    };
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, ' ');
+            let count = fields.next()?.parse().ok()?;
+            let last = fields.next()?.parse().ok()?;
+            let id = fields.next()?;
+            Some((id.to_string(), LaunchRecord { count, last }))
+        })
+        .collect()
+}
+
+fn save_history(history: &HashMap<String, LaunchRecord>) {
+    let Some(path) = history_path() else {
+        return;
+    };
+    let content: String = history
+        .iter()
+        .map(|(id, record)| format!("{} {} {id}\n", record.count, record.last))
+        .collect();
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&path, content));
+    if let Err(e) = result {
+        warn!("Failed to save launcher history to {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzzy_score;
+
+    fn score(query: &str, candidate: &str) -> Option<i32> {
+        fuzzy_score(&query.chars().collect::<Vec<_>>(), candidate)
+    }
+
+    #[test]
+    fn matches_subsequences_case_insensitively() {
+        assert_eq!(score("", "Firefox"), Some(0));
+        assert!(score("fire", "Firefox").is_some());
+        assert!(score("ffx", "Firefox").is_some());
+        assert_eq!(score("xof", "Firefox"), None);
+        assert_eq!(score("firefoxes", "Firefox"), None);
+    }
+
+    #[test]
+    fn ranks_prefixes_word_starts_and_runs_higher() {
+        assert!(score("term", "Terminal") > score("term", "Xterm Config"));
+        assert!(score("gc", "Google Chrome") > score("gc", "Magic"));
+        assert!(score("abc", "abcxyz") > score("abc", "axbxcx"));
+    }
+
+    #[test]
+    fn tries_every_start_of_the_first_character() {
+        // The later "f" starts a word and a consecutive run
+        let greedy_start = score("fox", "f ox").unwrap();
+        assert!(score("fox", "f fox").unwrap() > greedy_start);
+    }
 }
@@ -0,0 +1,152 @@
+// =============================================================================
+// heyDM — Built-in Lock Screen
+//
+// A minimal session locker so Super+L works out of the box: while locked,
+// all input is captured by the compositor, windows are not drawn, and the
+// renderer shows a clock and password field. Passwords are verified with PAM
+// on a worker thread; the result comes back through a calloop channel.
+// =============================================================================
+
+use calloop::channel::Sender;
+use tracing::info;
+
+use crate::auth;
+
+/// Progress of the current unlock attempt
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LockStatus {
+    /// Waiting for the user to type
+    Idle,
+    /// PAM is checking the password
+    Verifying,
+    /// The last attempt was rejected
+    Failed,
+}
+
+/// Built-in session lock state
+pub struct LockScreen {
+    /// Whether the session is currently locked
+    locked: bool,
+    /// User whose password unlocks the session
+    user: String,
+    /// Password typed so far
+    password: String,
+    /// Progress of the current attempt
+    status: LockStatus,
+    /// Rejected attempts since locking
+    failed_attempts: u32,
+    /// Where worker threads report authentication results
+    results: Sender<bool>,
+}
+
+impl LockScreen {
+    /// Create an unlocked lock screen reporting results to `results`
+    pub fn new(results: Sender<bool>) -> Self {
+        Self {
+            locked: false,
+            user: auth::current_user(),
+            password: String::new(),
+            status: LockStatus::Idle,
+            failed_attempts: 0,
+            results,
+        }
+    }
+
+    /// Lock the session
+    pub fn lock(&mut self) {
+        if self.locked {
+            return;
+        }
+        self.locked = true;
+        self.password.clear();
+        self.status = LockStatus::Idle;
+        self.failed_attempts = 0;
+        info!("Session locked");
+    }
+
+    /// Whether the session is locked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Handle a key press while locked
+    pub fn handle_key(&mut self, keysym: xkbcommon::xkb::Keysym) {
+        use xkbcommon::xkb::Keysym as K;
+
+        if self.status == LockStatus::Verifying {
+            return;
+        }
+
+        match keysym {
+            K::Return | K::KP_Enter => self.submit(),
+            K::BackSpace => {
+                self.password.pop();
+            }
+            K::Escape => self.password.clear(),
+            _ => {
+                if let Some(ch) = keysym.key_char().filter(|c| !c.is_control()) {
+                    self.password.push(ch);
+                    self.status = LockStatus::Idle;
+                }
+            }
+        }
+    }
+
+    /// Verify the typed password on a worker thread
+    fn submit(&mut self) {
+        if self.password.is_empty() {
+            return;
+        }
+
+        self.status = LockStatus::Verifying;
+        let user = self.user.clone();
+        let password = std::mem::take(&mut self.password);
+        let results = self.results.clone();
+
+        std::thread::spawn(move || {
+            let ok = auth::authenticate(&user, &password).is_ok();
+            let _ = results.send(ok);
+        });
+    }
+
+    /// Apply an authentication result from the worker thread
+    pub fn finish_auth(&mut self, success: bool) {
+        if !self.locked {
+            return;
+        }
+
+        if success {
+            self.locked = false;
+            self.status = LockStatus::Idle;
+            info!("Session unlocked");
+        } else {
+            self.failed_attempts += 1;
+            self.status = LockStatus::Failed;
+            info!("Unlock attempt rejected ({} so far)", self.failed_attempts);
+        }
+    }
+
+    // ---- Accessors for the renderer ----
+
+    /// Number of typed characters (never the password itself)
+    pub fn password_len(&self) -> usize {
+        self.password.chars().count()
+    }
+
+    /// Whether the last attempt failed
+    pub fn has_failed(&self) -> bool {
+        self.status == LockStatus::Failed
+    }
+
+    /// Status line shown below the password field
+    pub fn status_text(&self) -> String {
+        match self.status {
+            LockStatus::Idle => format!("Enter password for {}", self.user),
+            LockStatus::Verifying => "Verifying…".to_string(),
+            LockStatus::Failed if self.failed_attempts > 1 => {
+                format!("Incorrect password ({} attempts)", self.failed_attempts)
+            }
+            LockStatus::Failed => "Incorrect password".to_string(),
+        }
+    }
+}
@@ -6,19 +6,109 @@
 // sets up the event loop, and runs the compositor.
 // =============================================================================
 
+mod accent;
+mod accessx;
+#[cfg(test)]
+mod alloc_count;
+mod appwatch;
+mod auth;
+mod backlight;
+mod bluetooth;
+mod calc;
+mod calendar;
+mod config;
+mod ctl;
+mod cursor;
+mod devices;
+mod events;
+mod filters;
+mod gamma;
+mod greeter;
+mod headless;
+mod icons;
+mod ime;
 mod input;
+mod ipc;
+mod latency;
+mod launch;
 mod launcher;
+mod lock;
+mod mpris;
+mod network;
+mod notifications;
+mod notify;
+mod osd;
+mod output;
+mod overview;
 mod panel;
+mod perf;
+mod poller;
+mod portal;
+mod power;
+mod presets;
+mod profiles;
+mod protocol;
+mod realtime;
+mod recovery;
 mod render;
+mod restore;
+mod sandbox;
+mod scanout;
+mod schema;
+mod scratchpad;
+mod seats;
+mod session;
+mod shell;
 mod state;
+mod status;
+mod switcher;
+mod switchuser;
+mod taskman;
+mod tearing;
+mod text;
+mod theme;
+mod tiling;
+mod texture;
+mod tooltip;
+mod toplevel;
+mod tray;
+mod udev;
+mod volume;
+mod wallpaper;
+mod welcome;
 mod window;
+mod workspace;
+mod zoom;
+
+use std::path::PathBuf;
 
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 
+use crate::config::Config;
+use crate::schema::Severity;
 use crate::state::HeyDM;
 
 fn main() {
+    // Tooling subcommands print to stdout and exit before logging starts
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if ctl::invoked_as_heyctl() {
+        std::process::exit(ctl::run(&args));
+    }
+    match args.first().map(String::as_str) {
+        Some("ctl") => std::process::exit(ctl::run(&args[1..])),
+        Some("config-schema") => {
+            println!("{:#}", schema::json_schema());
+            return;
+        }
+        Some("config-check") => std::process::exit(config_check(&args[1..])),
+        Some("bar-status") => std::process::exit(status::run_client(&args[1..])),
+        _ => {}
+    }
+
+    // Before any thread exists (see notify.rs)
+    notify::take_environment();
+
     // Initialize structured logging with RUST_LOG support
     tracing_subscriber::fmt()
         .with_env_filter(
@@ -28,6 +118,7 @@ fn main() {
         .with_target(true)
         .with_thread_ids(false)
         .init();
+    recovery::install_panic_hook();
 
     info!("╔═══════════════════════════════════════╗");
     info!("║         heyDM Compositor v0.1         ║");
@@ -35,21 +126,52 @@ fn main() {
     info!("╚═══════════════════════════════════════╝");
 
     // Determine which backend to use:
+    //   - --headless renders offscreen, driven by a script (see headless.rs)
     //   - If WAYLAND_DISPLAY or DISPLAY is set, use winit (nested compositor for dev)
-//   - Otherwise, use udev/DRM (direct hardware — production path)
-    // NOTE: For heyOS v0.1, heydm is designed to run nested under 'cage' 
-    // for DRM/udev management on bare metal. The internal udev path in 
-    // state.rs is currently a placeholder for future direct-to-hardware support.
-    let use_winit = std::env::var("WAYLAND_DISPLAY").is_ok()
-        || std::env::var("DISPLAY").is_ok();
-
-    if use_winit {
+    //   - Otherwise, use udev/DRM (direct hardware — production path, see udev.rs)
+    let headless = match headless::HeadlessOptions::from_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            error!("{message}");
+            std::process::exit(2);
+        }
+    };
+    let use_winit = headless.is_none()
+        && (std::env::var("WAYLAND_DISPLAY").is_ok() || std::env::var("DISPLAY").is_ok());
+
+    if headless.is_some() {
+        info!("Starting headless with an offscreen output");
+    } else if use_winit {
         info!("Detected existing display server — starting in nested (winit) mode");
     } else {
         info!("No display server detected — starting in direct (udev/DRM) mode");
     }
 
-    match HeyDM::run(use_winit) {
+    // `heydm --greeter <cmd> [args...]`: kiosk compositor for the login screen
+    let greeter = args
+        .iter()
+        .position(|a| a == "--greeter")
+        .map(|idx| args[idx + 1..].to_vec());
+    if greeter.as_ref().is_some_and(Vec::is_empty) {
+        error!("--greeter needs a command to run");
+        std::process::exit(2);
+    }
+    if greeter.is_some() {
+        info!("Greeter mode: running {:?} as the only client", greeter.as_deref().unwrap_or_default());
+    }
+
+    // As the login session's process, take the session down with us
+    let owns_session = !use_winit && headless.is_none();
+    if owns_session {
+        session::become_subreaper();
+    }
+
+    let result = HeyDM::run(use_winit, headless, greeter);
+    notify::stopping();
+    if owns_session {
+        session::teardown(std::env::var("WAYLAND_DISPLAY").ok().as_deref());
+    }
+    match result {
         Ok(()) => info!("heyDM shut down cleanly."),
         Err(e) => {
             error!("heyDM encountered a fatal error: {e}");
@@ -57,3 +179,36 @@ fn main() {
         }
     }
 }
+
+/// `heydm config-check [--json] [path]` — report config diagnostics.
+/// Exits non-zero when the file has errors or cannot be read.
+fn config_check(args: &[String]) -> i32 {
+    let json = args.iter().any(|a| a == "--json");
+    let path = args
+        .iter()
+        .find(|a| !a.starts_with("--"))
+        .map(PathBuf::from)
+        .unwrap_or_else(Config::path);
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("{}: {e}", path.display());
+            return 2;
+        }
+    };
+
+    let (_, diagnostics) = Config::parse(&content);
+    let file = path.display().to_string();
+    if json {
+        let list: Vec<_> = diagnostics.iter().map(|d| d.json(&file)).collect();
+        println!("{:#}", serde_json::Value::Array(list));
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{file}:{diagnostic}");
+        }
+    }
+
+    let failed = diagnostics.iter().any(|d| d.severity == Severity::Error);
+    i32::from(failed)
+}
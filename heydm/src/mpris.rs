@@ -0,0 +1,278 @@
+// =============================================================================
+// heyDM — Media Player Controls
+//
+// Follows MPRIS media players (org.mpris.MediaPlayer2.*) on the session bus
+// for the panel's media module in the middle of the panel: the artist and
+// title of the current track, with previous, play/pause and next buttons.
+// Clicking the track toggles play/pause as well, and the XF86AudioPlay /
+// Pause / Next / Prev keys control the same player (see input.rs).
+//
+// With several players the one playing is shown, preferring the one already
+// shown; when none plays, the last one stays (paused) until it exits.
+//
+// As in network.rs, D-Bus calls happen on the "heydm-mpris" thread and
+// helper threads turn player signals into work for it. The state goes to
+// the panel's update channel (see poller.rs).
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use tracing::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+use crate::poller::PanelUpdate;
+
+const PLAYER_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const PLAYER_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// The track the panel shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaState {
+    pub title: String,
+    /// Artists joined with ", "; may be empty
+    pub artist: String,
+    pub playing: bool,
+}
+
+impl MediaState {
+    /// "Artist — Title", or just the title
+    pub fn label(&self) -> String {
+        if self.artist.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} — {}", self.artist, self.title)
+        }
+    }
+}
+
+/// A button of the media module or a media key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaControl {
+    PlayPause,
+    Next,
+    Previous,
+}
+
+impl MediaControl {
+    fn method(self) -> &'static str {
+        match self {
+            Self::PlayPause => "PlayPause",
+            Self::Next => "Next",
+            Self::Previous => "Previous",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum MprisCommand {
+    /// A player changed, appeared or went away; re-read
+    Changed,
+    Control(MediaControl),
+}
+
+/// Handle to the MPRIS thread
+pub struct MediaPlayers {
+    commands: Sender<MprisCommand>,
+}
+
+impl MediaPlayers {
+    /// Start following media players; the shown track goes to `updates`
+    pub fn start(updates: calloop::channel::Sender<PanelUpdate>) -> Self {
+        let (commands, receiver) = mpsc::channel::<MprisCommand>();
+        let worker_commands = commands.clone();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-mpris".to_string())
+            .spawn(move || match Client::connect(worker_commands, updates) {
+                Ok(client) => client.run(receiver),
+                Err(e) => info!("No session bus for media players ({e})"),
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start media player thread: {e}");
+        }
+        Self { commands }
+    }
+
+    /// Without following media players (greeter mode)
+    pub fn disabled() -> Self {
+        Self {
+            commands: mpsc::channel().0,
+        }
+    }
+
+    /// Send a control to the shown player
+    pub fn control(&self, control: MediaControl) {
+        let _ = self.commands.send(MprisCommand::Control(control));
+    }
+}
+
+/// The session bus connection on the MPRIS thread
+struct Client {
+    connection: Connection,
+    updates: calloop::channel::Sender<PanelUpdate>,
+    /// Bus name of the shown player
+    current: Option<String>,
+    last: Option<Option<MediaState>>,
+}
+
+impl Client {
+    fn connect(
+        commands: Sender<MprisCommand>,
+        updates: calloop::channel::Sender<PanelUpdate>,
+    ) -> zbus::Result<Self> {
+        let connection = Connection::session()?;
+        let properties = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .path(PLAYER_PATH)?
+            .build();
+        let owners = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .sender("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .arg0ns(PLAYER_PREFIX.trim_end_matches('.'))?
+            .build();
+        watch_signals(&connection, properties, commands.clone());
+        watch_signals(&connection, owners, commands);
+        Ok(Self {
+            connection,
+            updates,
+            current: None,
+            last: None,
+        })
+    }
+
+    fn run(mut self, commands: Receiver<MprisCommand>) {
+        if !self.refresh() {
+            return;
+        }
+        while let Ok(command) = commands.recv() {
+            // Players send bursts of signals; read once per burst
+            for command in std::iter::once(command).chain(commands.try_iter()) {
+                if let MprisCommand::Control(control) = command {
+                    self.send_control(control);
+                }
+            }
+            if !self.refresh() {
+                return;
+            }
+        }
+    }
+
+    fn send_control(&self, control: MediaControl) {
+        let Some(player) = &self.current else {
+            return;
+        };
+        let result = self
+            .player(player)
+            .and_then(|proxy| proxy.call::<_, _, ()>(control.method(), &()));
+        if let Err(e) = result {
+            warn!("Media player {player}: {} failed: {e}", control.method());
+        }
+    }
+
+    /// Pick the player to show and send its track if it changed. Returns
+    /// false once the compositor is gone.
+    fn refresh(&mut self) -> bool {
+        let state = self.read_state().unwrap_or_else(|e| {
+            debug!("Reading media players failed: {e}");
+            None
+        });
+        if self.last.as_ref() == Some(&state) {
+            return true;
+        }
+        self.last = Some(state.clone());
+        self.updates.send(PanelUpdate::Media(state)).is_ok()
+    }
+
+    fn read_state(&mut self) -> zbus::Result<Option<MediaState>> {
+        let bus = Proxy::new(
+            &self.connection,
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+        )?;
+        let names: Vec<String> = bus.call("ListNames", &())?;
+        let mut players: Vec<(String, MediaState)> = names
+            .into_iter()
+            .filter(|name| name.starts_with(PLAYER_PREFIX))
+            .filter_map(|name| {
+                let state = self.read_player(&name).ok()?;
+                Some((name, state))
+            })
+            .collect();
+        players.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let current = self.current.as_deref();
+        let shown = players
+            .iter()
+            .position(|(name, state)| state.playing && Some(name.as_str()) == current)
+            .or_else(|| players.iter().position(|(_, state)| state.playing))
+            .or_else(|| players.iter().position(|(name, _)| Some(name.as_str()) == current))
+            .or((!players.is_empty()).then_some(0));
+        let Some(shown) = shown else {
+            self.current = None;
+            return Ok(None);
+        };
+        let (name, state) = players.swap_remove(shown);
+        if self.current.as_deref() != Some(name.as_str()) {
+            info!("Showing media player {name}");
+        }
+        self.current = Some(name);
+        Ok(Some(state))
+    }
+
+    fn read_player(&self, name: &str) -> zbus::Result<MediaState> {
+        let player = self.player(name)?;
+        let status: String = player.get_property("PlaybackStatus")?;
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata")?;
+        let title = metadata
+            .get("xesam:title")
+            .and_then(|title| String::try_from(title.try_clone().ok()?).ok())
+            .unwrap_or_default();
+        let artist = metadata
+            .get("xesam:artist")
+            .and_then(|artist| Vec::<String>::try_from(artist.try_clone().ok()?).ok())
+            .unwrap_or_default()
+            .join(", ");
+        Ok(MediaState {
+            title,
+            artist,
+            playing: status == "Playing",
+        })
+    }
+
+    fn player(&self, name: &str) -> zbus::Result<Proxy<'static>> {
+        zbus::blocking::proxy::Builder::new(&self.connection)
+            .destination(name.to_string())?
+            .path(PLAYER_PATH)?
+            .interface(PLAYER_INTERFACE)?
+            .cache_properties(zbus::proxy::CacheProperties::No)
+            .build()
+    }
+}
+
+/// Forward every signal matching `rule` to the MPRIS thread
+fn watch_signals(connection: &Connection, rule: zbus::MatchRule<'static>, commands: Sender<MprisCommand>) {
+    let connection = connection.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-mpris-events".to_string())
+        .spawn(move || {
+            let messages = match zbus::blocking::MessageIterator::for_match_rule(rule, &connection, None) {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Media players: cannot follow changes: {e}");
+                    return;
+                }
+            };
+            for _ in messages.flatten() {
+                if commands.send(MprisCommand::Changed).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start media player signal thread: {e}");
+    }
+}
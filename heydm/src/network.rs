@@ -0,0 +1,663 @@
+// =============================================================================
+// heyDM — Network
+//
+// Follows NetworkManager over the system bus for the panel's network module:
+// the primary connection's type, SSID and signal strength, and whether it
+// reaches the internet (NM's connectivity check). NM announces every change
+// with PropertiesChanged, so nothing is polled; without NM the
+// "heydm-network" thread falls back to reading /sys/class/net every
+// NETWORK_INTERVAL.
+//
+// Clicking the module opens a quick menu below it listing the Wi-Fi
+// networks in range (strongest first, one row per SSID). Opening it asks
+// the Wi-Fi devices to rescan; clicking a network activates its saved
+// connection, or creates one. Secured networks without a saved password
+// need a secret agent (e.g. nm-applet) to ask for it.
+//
+// As in the tray (tray.rs), all D-Bus calls happen on the worker thread; a
+// helper thread turns NM's signals into work for it. The state goes to the
+// panel's update channel (see poller.rs), the Wi-Fi list through its own
+// calloop channel.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use smithay::utils::{Physical, Rectangle, Size};
+use tracing::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+use crate::poller::PanelUpdate;
+use crate::render::network_rect;
+use crate::theme::UiMetrics;
+
+/// How often /sys/class/net is read without NetworkManager
+const NETWORK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rows in the quick menu
+const MAX_NETWORKS: usize = 8;
+
+const NM: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const ACTIVE_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const DEVICE_INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+const WIRELESS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const AP_INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+/// NM_DEVICE_TYPE_WIFI
+const DEVICE_TYPE_WIFI: u32 = 2;
+
+/// Network connection state
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkStatus {
+    /// Not connected to any network
+    Disconnected,
+    /// Connected via WiFi
+    WiFi,
+    /// Connected via Ethernet
+    Ethernet,
+    /// Connected through something else (VPN, bridge, modem, ...)
+    Other,
+    /// Connection status unknown
+    Unknown,
+}
+
+/// Whether the connection reaches the internet, from NM's connectivity
+/// check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Not checked (or checking is disabled)
+    Unknown,
+    None,
+    /// Behind a captive portal that wants a sign-in
+    Portal,
+    /// Connected to a network without internet access
+    Limited,
+    Full,
+}
+
+impl Connectivity {
+    fn from_nm(value: u32) -> Self {
+        match value {
+            1 => Self::None,
+            2 => Self::Portal,
+            3 => Self::Limited,
+            4 => Self::Full,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// What the panel shows about the network
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkState {
+    pub status: NetworkStatus,
+    /// SSID, interface or connection name
+    pub name: String,
+    /// Wi-Fi signal strength in percent
+    pub strength: Option<u8>,
+    pub connectivity: Connectivity,
+}
+
+impl NetworkState {
+    fn disconnected() -> Self {
+        Self {
+            status: NetworkStatus::Disconnected,
+            name: String::new(),
+            strength: None,
+            connectivity: Connectivity::Unknown,
+        }
+    }
+}
+
+/// Filled bars (1..=4) of a signal strength indicator
+pub fn signal_bars(strength: u8) -> i32 {
+    match strength {
+        0..=24 => 1,
+        25..=49 => 2,
+        50..=74 => 3,
+        _ => 4,
+    }
+}
+
+/// A Wi-Fi network in range, as listed in the quick menu
+#[derive(Debug, Clone, PartialEq)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    /// Signal strength in percent
+    pub strength: u8,
+    pub secured: bool,
+    /// Currently connected
+    pub active: bool,
+    device: String,
+    access_point: String,
+}
+
+/// From the compositor (and the signal thread) to the network thread
+#[derive(Debug)]
+enum NetworkCommand {
+    /// NM changed something; re-read the state
+    Changed,
+    /// The quick menu opened: rescan and send the Wi-Fi list on changes
+    Open,
+    Close,
+    Connect {
+        ssid: String,
+        device: String,
+        access_point: String,
+    },
+}
+
+/// The Wi-Fi quick menu, on the compositor side
+pub struct NetworkMenu {
+    open: bool,
+    networks: Vec<WifiNetwork>,
+    commands: Sender<NetworkCommand>,
+    ui: UiMetrics,
+}
+
+#[allow(dead_code)]
+impl NetworkMenu {
+    /// Start following NetworkManager; the state goes to `updates`, Wi-Fi
+    /// lists for the quick menu to `networks`
+    pub fn start(
+        updates: calloop::channel::Sender<PanelUpdate>,
+        networks: calloop::channel::Sender<Vec<WifiNetwork>>,
+        ui: UiMetrics,
+    ) -> Self {
+        let (commands, receiver) = mpsc::channel::<NetworkCommand>();
+        let worker_commands = commands.clone();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-network".to_string())
+            .spawn(move || match Client::connect(worker_commands, updates.clone(), networks.clone()) {
+                Ok(client) => {
+                    info!("Following NetworkManager");
+                    client.run(receiver);
+                }
+                Err(e) => {
+                    info!("NetworkManager unavailable ({e}), polling /sys/class/net");
+                    poll_sysfs(updates, networks, receiver);
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start network thread: {e}");
+        }
+        Self {
+            open: false,
+            networks: Vec::new(),
+            commands,
+            ui,
+        }
+    }
+
+    /// Without following NetworkManager (greeter mode)
+    pub fn disabled(ui: UiMetrics) -> Self {
+        Self {
+            open: false,
+            networks: Vec::new(),
+            commands: mpsc::channel().0,
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    /// A new Wi-Fi list from the network thread
+    pub fn apply(&mut self, networks: Vec<WifiNetwork>) {
+        self.networks = networks;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn networks(&self) -> &[WifiNetwork] {
+        &self.networks
+    }
+
+    pub fn toggle(&mut self) {
+        if self.open {
+            self.close();
+        } else {
+            self.open = true;
+            self.send(NetworkCommand::Open);
+        }
+    }
+
+    pub fn close(&mut self) {
+        if self.open {
+            self.open = false;
+            self.send(NetworkCommand::Close);
+        }
+    }
+
+    /// Where the menu is drawn: below the network module, inside the output
+    pub fn rect(&self, output_size: Size<i32, Physical>) -> Rectangle<i32, Physical> {
+        let module = network_rect(output_size, &self.ui);
+        let w = self.ui.px(260);
+        let rows = self.networks.len().max(1) as i32;
+        let h = self.row_height() * rows + self.ui.px(8);
+        let x = (module.loc.x + module.size.w - w).clamp(0, (output_size.w - w).max(0));
+        Rectangle::new((x, self.ui.panel_bottom() + self.ui.px(4)).into(), (w, h).into())
+    }
+
+    pub fn row_height(&self) -> i32 {
+        self.ui.px(28)
+    }
+
+    /// Handle a button press while the menu is open; returns true if it
+    /// was consumed. Any click closes the menu.
+    pub fn handle_press(&mut self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        if !self.open {
+            return false;
+        }
+        let point = (pos.0 as i32, pos.1 as i32);
+        let area = self.rect(output_size);
+        if area.contains(point) {
+            let row = (point.1 - area.loc.y - self.ui.px(4)) / self.row_height();
+            let network = usize::try_from(row).ok().and_then(|row| self.networks.get(row));
+            if let Some(network) = network.filter(|network| !network.active) {
+                info!("Connecting to Wi-Fi network {}", network.ssid);
+                self.send(NetworkCommand::Connect {
+                    ssid: network.ssid.clone(),
+                    device: network.device.clone(),
+                    access_point: network.access_point.clone(),
+                });
+            }
+            self.close();
+            return true;
+        }
+        self.close();
+        // A click on the module itself just closes the menu
+        network_rect(output_size, &self.ui).contains(point)
+    }
+
+    fn send(&self, command: NetworkCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+/// The NetworkManager connection on the network thread
+struct Client {
+    connection: Connection,
+    manager: Proxy<'static>,
+    updates: calloop::channel::Sender<PanelUpdate>,
+    networks: calloop::channel::Sender<Vec<WifiNetwork>>,
+    last: Option<NetworkState>,
+    /// The quick menu is open
+    listing: bool,
+}
+
+impl Client {
+    fn connect(
+        commands: Sender<NetworkCommand>,
+        updates: calloop::channel::Sender<PanelUpdate>,
+        networks: calloop::channel::Sender<Vec<WifiNetwork>>,
+    ) -> zbus::Result<Self> {
+        let connection = Connection::system()?;
+        let manager = proxy(&connection, NM_PATH, NM)?;
+        // Fails right away when NM is not running
+        let _: u32 = manager.get_property("State")?;
+        watch_signals(&connection, commands);
+        Ok(Self {
+            connection,
+            manager,
+            updates,
+            networks,
+            last: None,
+            listing: false,
+        })
+    }
+
+    fn run(mut self, commands: Receiver<NetworkCommand>) {
+        self.refresh();
+        while let Ok(command) = commands.recv() {
+            // NM sends bursts of signals; read the state once per burst
+            let mut changed = false;
+            for command in std::iter::once(command).chain(commands.try_iter()) {
+                match command {
+                    NetworkCommand::Changed => changed = true,
+                    NetworkCommand::Open => {
+                        self.listing = true;
+                        self.request_scan();
+                        changed = true;
+                    }
+                    NetworkCommand::Close => self.listing = false,
+                    NetworkCommand::Connect {
+                        ssid,
+                        device,
+                        access_point,
+                    } => {
+                        if let Err(e) = self.activate(&ssid, &device, &access_point) {
+                            warn!("Connecting to {ssid} failed: {e}");
+                        }
+                    }
+                }
+            }
+            if changed && !self.refresh() {
+                return;
+            }
+        }
+    }
+
+    /// Send the state if it changed, and the Wi-Fi list while the menu is
+    /// open. Returns false once the compositor is gone.
+    fn refresh(&mut self) -> bool {
+        let state = self.read_state().unwrap_or_else(|e| {
+            debug!("Reading the network state failed: {e}");
+            NetworkState {
+                status: NetworkStatus::Unknown,
+                ..NetworkState::disconnected()
+            }
+        });
+        if self.last.as_ref() != Some(&state) {
+            self.last = Some(state.clone());
+            if self.updates.send(PanelUpdate::Network(state)).is_err() {
+                return false;
+            }
+        }
+        if self.listing {
+            let networks = self.read_networks().unwrap_or_else(|e| {
+                debug!("Listing Wi-Fi networks failed: {e}");
+                Vec::new()
+            });
+            if self.networks.send(networks).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn read_state(&self) -> zbus::Result<NetworkState> {
+        let connectivity = Connectivity::from_nm(self.manager.get_property("Connectivity")?);
+        let primary: OwnedObjectPath = self.manager.get_property("PrimaryConnection")?;
+        if primary.as_str() == "/" {
+            return Ok(NetworkState {
+                connectivity,
+                ..NetworkState::disconnected()
+            });
+        }
+
+        let active = proxy(&self.connection, primary.as_str(), ACTIVE_INTERFACE)?;
+        let kind: String = active.get_property("Type")?;
+        let id: String = active.get_property("Id")?;
+        let devices: Vec<OwnedObjectPath> = active.get_property("Devices")?;
+        let device = devices.first();
+        let state = match kind.as_str() {
+            "802-11-wireless" => {
+                let access_point = match device {
+                    Some(device) => self.active_access_point(device.as_str())?,
+                    None => None,
+                };
+                match access_point {
+                    Some((ssid, strength)) => NetworkState {
+                        status: NetworkStatus::WiFi,
+                        name: ssid,
+                        strength: Some(strength),
+                        connectivity,
+                    },
+                    None => NetworkState {
+                        status: NetworkStatus::WiFi,
+                        name: id,
+                        strength: None,
+                        connectivity,
+                    },
+                }
+            }
+            "802-3-ethernet" => {
+                let name = match device {
+                    Some(device) => proxy(&self.connection, device.as_str(), DEVICE_INTERFACE)?
+                        .get_property("Interface")?,
+                    None => id,
+                };
+                NetworkState {
+                    status: NetworkStatus::Ethernet,
+                    name,
+                    strength: None,
+                    connectivity,
+                }
+            }
+            _ => NetworkState {
+                status: NetworkStatus::Other,
+                name: id,
+                strength: None,
+                connectivity,
+            },
+        };
+        Ok(state)
+    }
+
+    /// SSID and strength of the access point a Wi-Fi device uses
+    fn active_access_point(&self, device: &str) -> zbus::Result<Option<(String, u8)>> {
+        let wireless = proxy(&self.connection, device, WIRELESS_INTERFACE)?;
+        let path: OwnedObjectPath = wireless.get_property("ActiveAccessPoint")?;
+        if path.as_str() == "/" {
+            return Ok(None);
+        }
+        let access_point = proxy(&self.connection, path.as_str(), AP_INTERFACE)?;
+        let ssid: Vec<u8> = access_point.get_property("Ssid")?;
+        let strength: u8 = access_point.get_property("Strength")?;
+        Ok(Some((String::from_utf8_lossy(&ssid).into_owned(), strength)))
+    }
+
+    fn wifi_devices(&self) -> zbus::Result<Vec<OwnedObjectPath>> {
+        let devices: Vec<OwnedObjectPath> = self.manager.call("GetDevices", &())?;
+        Ok(devices
+            .into_iter()
+            .filter(|device| {
+                proxy(&self.connection, device.as_str(), DEVICE_INTERFACE)
+                    .and_then(|device| device.get_property::<u32>("DeviceType"))
+                    .is_ok_and(|kind| kind == DEVICE_TYPE_WIFI)
+            })
+            .collect())
+    }
+
+    /// Networks in range on all Wi-Fi devices, one per SSID, connected one
+    /// first, then by strength
+    fn read_networks(&self) -> zbus::Result<Vec<WifiNetwork>> {
+        let mut networks: Vec<WifiNetwork> = Vec::new();
+        for device in self.wifi_devices()? {
+            let wireless = proxy(&self.connection, device.as_str(), WIRELESS_INTERFACE)?;
+            let active: OwnedObjectPath = wireless.get_property("ActiveAccessPoint")?;
+            let access_points: Vec<OwnedObjectPath> = wireless.call("GetAllAccessPoints", &())?;
+            for path in access_points {
+                let access_point = proxy(&self.connection, path.as_str(), AP_INTERFACE)?;
+                let ssid: Vec<u8> = access_point.get_property("Ssid")?;
+                if ssid.is_empty() {
+                    // Hidden network
+                    continue;
+                }
+                let flags: u32 = access_point.get_property("Flags")?;
+                let wpa: u32 = access_point.get_property("WpaFlags")?;
+                let rsn: u32 = access_point.get_property("RsnFlags")?;
+                let network = WifiNetwork {
+                    ssid: String::from_utf8_lossy(&ssid).into_owned(),
+                    strength: access_point.get_property("Strength")?,
+                    secured: flags & 0x1 != 0 || wpa != 0 || rsn != 0,
+                    active: path == active,
+                    device: device.to_string(),
+                    access_point: path.to_string(),
+                };
+                match networks.iter_mut().find(|n| n.ssid == network.ssid) {
+                    Some(existing) => {
+                        if network.active || (!existing.active && network.strength > existing.strength) {
+                            *existing = network;
+                        }
+                    }
+                    None => networks.push(network),
+                }
+            }
+        }
+        networks.sort_by(|a, b| b.active.cmp(&a.active).then(b.strength.cmp(&a.strength)));
+        networks.truncate(MAX_NETWORKS);
+        Ok(networks)
+    }
+
+    fn request_scan(&self) {
+        let devices = match self.wifi_devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                debug!("Listing Wi-Fi devices failed: {e}");
+                return;
+            }
+        };
+        for device in devices {
+            let options: HashMap<&str, Value<'_>> = HashMap::new();
+            let result = proxy(&self.connection, device.as_str(), WIRELESS_INTERFACE)
+                .and_then(|wireless| wireless.call::<_, _, ()>("RequestScan", &(options,)));
+            // NM refuses scans shortly after the previous one
+            if let Err(e) = result {
+                debug!("Wi-Fi scan on {device} not started: {e}");
+            }
+        }
+    }
+
+    /// Activate the saved connection for `ssid`, or add one for it
+    fn activate(&self, ssid: &str, device: &str, access_point: &str) -> zbus::Result<()> {
+        let device = ObjectPath::try_from(device)?;
+        let access_point = ObjectPath::try_from(access_point)?;
+        match self.saved_connection(ssid)? {
+            Some(connection) => {
+                let _: OwnedObjectPath = self
+                    .manager
+                    .call("ActivateConnection", &(connection, device, access_point))?;
+            }
+            None => {
+                let settings: HashMap<&str, HashMap<&str, Value<'_>>> = HashMap::new();
+                let _: (OwnedObjectPath, OwnedObjectPath) = self
+                    .manager
+                    .call("AddAndActivateConnection", &(settings, device, access_point))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A saved Wi-Fi connection profile for `ssid`
+    fn saved_connection(&self, ssid: &str) -> zbus::Result<Option<OwnedObjectPath>> {
+        let settings = proxy(&self.connection, SETTINGS_PATH, SETTINGS_INTERFACE)?;
+        let connections: Vec<OwnedObjectPath> = settings.call("ListConnections", &())?;
+        for path in connections {
+            let connection = proxy(&self.connection, path.as_str(), CONNECTION_INTERFACE)?;
+            let settings: HashMap<String, HashMap<String, OwnedValue>> = connection.call("GetSettings", &())?;
+            let saved_ssid = settings
+                .get("802-11-wireless")
+                .and_then(|wireless| wireless.get("ssid"))
+                .and_then(|ssid| Vec::<u8>::try_from(ssid.try_clone().ok()?).ok());
+            if saved_ssid.as_deref() == Some(ssid.as_bytes()) {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A proxy that does not cache properties: the state is re-read whenever
+/// NM signals a change
+fn proxy(connection: &Connection, path: &str, interface: &'static str) -> zbus::Result<Proxy<'static>> {
+    zbus::blocking::proxy::Builder::new(connection)
+        .destination(NM)?
+        .path(path.to_string())?
+        .interface(interface)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+}
+
+/// Forward every signal from NM's objects to the network thread
+fn watch_signals(connection: &Connection, commands: Sender<NetworkCommand>) {
+    let connection = connection.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-network-events".to_string())
+        .spawn(move || {
+            let rule = zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .path_namespace(NM_PATH)
+                .map(|builder| builder.build());
+            let messages = rule.and_then(|rule| {
+                zbus::blocking::MessageIterator::for_match_rule(rule, &connection, None)
+            });
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Network: cannot follow NetworkManager: {e}");
+                    return;
+                }
+            };
+            for _ in messages.flatten() {
+                if commands.send(NetworkCommand::Changed).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start network signal thread: {e}");
+    }
+}
+
+/// Without NetworkManager: read sysfs every NETWORK_INTERVAL; the quick
+/// menu stays empty
+fn poll_sysfs(
+    updates: calloop::channel::Sender<PanelUpdate>,
+    networks: calloop::channel::Sender<Vec<WifiNetwork>>,
+    commands: Receiver<NetworkCommand>,
+) {
+    let mut last = None;
+    loop {
+        let state = read_sysfs();
+        if last.as_ref() != Some(&state) {
+            last = Some(state.clone());
+            if updates.send(PanelUpdate::Network(state)).is_err() {
+                return;
+            }
+        }
+        match commands.recv_timeout(NETWORK_INTERVAL) {
+            Ok(NetworkCommand::Open) => {
+                if networks.send(Vec::new()).is_err() {
+                    return;
+                }
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// The first interface that is up, by type
+fn read_sysfs() -> NetworkState {
+    if let Ok(entries) = fs::read_dir("/sys/class/net") {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "lo" {
+                continue;
+            }
+
+            let up = fs::read_to_string(entry.path().join("operstate"))
+                .is_ok_and(|state| state.trim() == "up");
+            if !up {
+                continue;
+            }
+            let status = if name.starts_with("wl") {
+                NetworkStatus::WiFi
+            } else if name.starts_with("en") || name.starts_with("eth") {
+                NetworkStatus::Ethernet
+            } else {
+                continue;
+            };
+            return NetworkState {
+                status,
+                name,
+                strength: None,
+                connectivity: Connectivity::Unknown,
+            };
+        }
+    }
+    NetworkState::disconnected()
+}
@@ -0,0 +1,409 @@
+// =============================================================================
+// heyDM — Notification Daemon
+//
+// Implements org.freedesktop.Notifications, so notify-send, browsers, chat
+// clients etc. can show notifications. They appear as toasts stacked in the
+// top-right corner below the panel:
+//
+//   - A toast disappears after its timeout (DEFAULT_TIMEOUT when the app
+//     leaves it to the server; critical ones stay until dismissed)
+//   - Clicking a toast invokes its "default" action, its buttons invoke the
+//     other actions, and × dismisses it
+//   - The panel's DND module toggles do-not-disturb: while on, only critical
+//     notifications are shown
+//
+// The D-Bus connection lives on its own thread, like the settings portal
+// (portal.rs). Incoming notifications reach the compositor through a calloop
+// channel; closed notifications and invoked actions go back through a
+// std channel and are emitted as signals from that thread.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use smithay::utils::{Physical, Rectangle, Size};
+use tracing::{debug, info, warn};
+use zbus::object_server::SignalContext;
+use zbus::zvariant::OwnedValue;
+
+use crate::theme::UiMetrics;
+
+const BUS_NAME: &str = "org.freedesktop.Notifications";
+const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// Timeout of notifications that leave it to the server
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Toasts shown at once; older ones wait until a slot frees up
+const MAX_TOASTS: usize = 4;
+
+/// Urgency hint value of critical notifications
+const URGENCY_CRITICAL: u8 = 2;
+
+/// Why a notification was closed, as defined by the spec
+#[derive(Debug, Clone, Copy)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    /// By a CloseNotification call
+    Closed = 3,
+}
+
+/// One notification as sent by an app
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// (key, label) pairs; the "default" action has no button
+    pub actions: Vec<(String, String)>,
+    /// None = until dismissed
+    pub timeout: Option<Duration>,
+    pub critical: bool,
+}
+
+impl Notification {
+    /// Actions drawn as buttons
+    pub fn buttons(&self) -> impl Iterator<Item = &(String, String)> {
+        self.actions.iter().filter(|(key, _)| key != "default")
+    }
+
+    fn has_default_action(&self) -> bool {
+        self.actions.iter().any(|(key, _)| key == "default")
+    }
+}
+
+/// From the D-Bus thread to the compositor
+#[derive(Debug)]
+pub enum NotificationEvent {
+    Show(Notification),
+    Close(u32),
+}
+
+/// From the compositor to the D-Bus thread
+#[derive(Debug)]
+enum Signal {
+    Closed(u32, CloseReason),
+    ActionInvoked(u32, String),
+}
+
+struct NotificationServer {
+    /// Shared with Notifications, which hands out ids to heyDM's own
+    /// notifications
+    next_id: Arc<AtomicU32>,
+    events: calloop::channel::Sender<NotificationEvent>,
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl NotificationServer {
+    fn get_capabilities(&self) -> Vec<&str> {
+        vec!["body", "actions"]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let id = if replaces_id != 0 {
+            replaces_id
+        } else {
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        };
+        let critical = hints
+            .get("urgency")
+            .and_then(|urgency| u8::try_from(urgency).ok())
+            == Some(URGENCY_CRITICAL);
+        let timeout = match expire_timeout {
+            0 => None,
+            ms if ms > 0 => Some(Duration::from_millis(ms as u64)),
+            _ if critical => None,
+            _ => Some(DEFAULT_TIMEOUT),
+        };
+        let actions = actions
+            .chunks_exact(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+        let _ = self.events.send(NotificationEvent::Show(Notification {
+            id,
+            app_name,
+            summary,
+            body,
+            actions,
+            timeout,
+            critical,
+        }));
+        id
+    }
+
+    fn close_notification(&self, id: u32) {
+        let _ = self.events.send(NotificationEvent::Close(id));
+    }
+
+    fn get_server_information(&self) -> (&str, &str, &str, &str) {
+        ("heyDM", "heyOS", env!("CARGO_PKG_VERSION"), "1.2")
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(ctxt: &SignalContext<'_>, id: u32, reason: u32) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(ctxt: &SignalContext<'_>, id: u32, action_key: &str) -> zbus::Result<()>;
+}
+
+/// Toasts on screen and the do-not-disturb switch
+pub struct Notifications {
+    /// Oldest first
+    toasts: Vec<Notification>,
+    /// Bumped whenever a toast is shown again, so stale timers do nothing
+    generations: HashMap<u32, u64>,
+    next_generation: u64,
+    dnd: bool,
+    next_id: Arc<AtomicU32>,
+    signals: Sender<Signal>,
+    ui: UiMetrics,
+}
+
+#[allow(dead_code)]
+impl Notifications {
+    /// Claim the notification bus name and start serving. Without a session
+    /// bus, or with another notification daemon running, apps simply cannot
+    /// notify.
+    pub fn start(events: calloop::channel::Sender<NotificationEvent>, ui: UiMetrics) -> Self {
+        let (signals, receiver) = mpsc::channel::<Signal>();
+        let next_id = Arc::new(AtomicU32::new(1));
+        let server_next_id = next_id.clone();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-notify".to_string())
+            .spawn(move || {
+                let server = NotificationServer {
+                    next_id: server_next_id,
+                    events,
+                };
+                let connection = zbus::blocking::connection::Builder::session()
+                    .and_then(|builder| builder.name(BUS_NAME))
+                    .and_then(|builder| builder.serve_at(OBJECT_PATH, server))
+                    .and_then(|builder| builder.build());
+                let connection = match connection {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        warn!("Notification daemon unavailable: {e}");
+                        return;
+                    }
+                };
+                info!("Notification daemon serving as {BUS_NAME}");
+
+                for signal in receiver {
+                    let iface = connection
+                        .object_server()
+                        .interface::<_, NotificationServer>(OBJECT_PATH);
+                    let result = iface.and_then(|iface| {
+                        let ctxt = iface.signal_context();
+                        zbus::block_on(async {
+                            match &signal {
+                                Signal::Closed(id, reason) => {
+                                    NotificationServer::notification_closed(ctxt, *id, *reason as u32).await
+                                }
+                                Signal::ActionInvoked(id, key) => {
+                                    NotificationServer::action_invoked(ctxt, *id, key).await
+                                }
+                            }
+                        })
+                    });
+                    if let Err(e) = result {
+                        warn!("Failed to emit {signal:?}: {e}");
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start notification thread: {e}");
+        }
+        Self {
+            toasts: Vec::new(),
+            generations: HashMap::new(),
+            next_generation: 0,
+            dnd: false,
+            next_id,
+            signals,
+            ui,
+        }
+    }
+
+    /// Toasts shown by heyDM itself only, without the D-Bus daemon (greeter
+    /// mode)
+    pub fn disabled(ui: UiMetrics) -> Self {
+        Self {
+            toasts: Vec::new(),
+            generations: HashMap::new(),
+            next_generation: 0,
+            dnd: false,
+            next_id: Arc::new(AtomicU32::new(1)),
+            signals: mpsc::channel().0,
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    /// A fresh id for a notification heyDM shows itself
+    pub fn next_id(&self) -> u32 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Show a notification (replacing one with the same id). Returns the
+    /// generation its expiry timer must match, or None when it was not
+    /// shown.
+    pub fn show(&mut self, notification: Notification) -> Option<u64> {
+        if self.dnd && !notification.critical {
+            debug!("Do not disturb: dropping notification {}", notification.id);
+            self.send(Signal::Closed(notification.id, CloseReason::Expired));
+            return None;
+        }
+        debug!("Notification {} from {}: {}", notification.id, notification.app_name, notification.summary);
+        let id = notification.id;
+        match self.toasts.iter_mut().find(|toast| toast.id == id) {
+            Some(toast) => *toast = notification,
+            None => self.toasts.push(notification),
+        }
+        self.next_generation += 1;
+        self.generations.insert(id, self.next_generation);
+        Some(self.next_generation)
+    }
+
+    /// Expiry timer of a toast fired
+    pub fn expire(&mut self, id: u32, generation: u64) {
+        if self.generations.get(&id) == Some(&generation) {
+            self.close(id, CloseReason::Expired);
+        }
+    }
+
+    /// Remove a toast and tell its app why
+    pub fn close(&mut self, id: u32, reason: CloseReason) {
+        let Some(index) = self.toasts.iter().position(|toast| toast.id == id) else {
+            return;
+        };
+        self.toasts.remove(index);
+        self.generations.remove(&id);
+        self.send(Signal::Closed(id, reason));
+    }
+
+    /// Timeout of the toast with this id
+    pub fn timeout(&self, id: u32) -> Option<Duration> {
+        self.toasts.iter().find(|toast| toast.id == id)?.timeout
+    }
+
+    pub fn is_dnd(&self) -> bool {
+        self.dnd
+    }
+
+    pub fn toggle_dnd(&mut self) {
+        self.dnd = !self.dnd;
+        info!("Do not disturb {}", if self.dnd { "on" } else { "off" });
+    }
+
+    /// Toasts on screen with their rectangles, newest on top
+    pub fn visible(
+        &self,
+        output_size: Size<i32, Physical>,
+    ) -> impl Iterator<Item = (&Notification, Rectangle<i32, Physical>)> {
+        let ui = self.ui;
+        let mut y = ui.panel_bottom() + ui.px(12);
+        self.toasts.iter().rev().take(MAX_TOASTS).map(move |toast| {
+            let h = toast_height(toast, &ui);
+            let w = ui.px(360);
+            let rect = Rectangle::new(
+                (output_size.w - ui.panel_margin() - w, y).into(),
+                (w, h).into(),
+            );
+            y += h + ui.px(8);
+            (toast, rect)
+        })
+    }
+
+    /// Handle a click; toasts take clicks before anything below them.
+    /// Returns true if the click was consumed
+    pub fn handle_click(&mut self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        let point = (pos.0 as i32, pos.1 as i32);
+        let hit = self
+            .visible(output_size)
+            .find(|(_, rect)| rect.contains(point))
+            .map(|(toast, rect)| {
+                let button = toast
+                    .buttons()
+                    .enumerate()
+                    .find(|(i, _)| action_button_rect(rect, *i, toast.buttons().count(), &self.ui).contains(point))
+                    .map(|(_, (key, _))| key.clone());
+                let on_close = close_button_rect(rect, &self.ui).contains(point);
+                (toast.id, toast.has_default_action(), button, on_close)
+            });
+        let Some((id, has_default, button, on_close)) = hit else {
+            return false;
+        };
+
+        let action = if on_close {
+            None
+        } else {
+            button.or_else(|| has_default.then(|| "default".to_string()))
+        };
+        if let Some(key) = action {
+            debug!("Notification {id}: action '{key}'");
+            self.send(Signal::ActionInvoked(id, key));
+        }
+        self.close(id, CloseReason::Dismissed);
+        true
+    }
+
+    fn send(&self, signal: Signal) {
+        let _ = self.signals.send(signal);
+    }
+}
+
+fn toast_height(toast: &Notification, ui: &UiMetrics) -> i32 {
+    let text = if toast.body.is_empty() { ui.px(48) } else { ui.px(72) };
+    if toast.buttons().next().is_some() {
+        text + ui.px(36)
+    } else {
+        text
+    }
+}
+
+/// The × in a toast's top-right corner
+pub fn close_button_rect(toast: Rectangle<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let size = ui.px(24);
+    Rectangle::new(
+        (toast.loc.x + toast.size.w - size - ui.px(6), toast.loc.y + ui.px(6)).into(),
+        (size, size).into(),
+    )
+}
+
+/// Action button `index` of `count`, in a row along the toast's bottom
+pub fn action_button_rect(
+    toast: Rectangle<i32, Physical>,
+    index: usize,
+    count: usize,
+    ui: &UiMetrics,
+) -> Rectangle<i32, Physical> {
+    let pad = ui.px(12);
+    let gap = ui.px(6);
+    let count = count.max(1) as i32;
+    let w = (toast.size.w - pad * 2 - gap * (count - 1)) / count;
+    let h = ui.px(28);
+    Rectangle::new(
+        (toast.loc.x + pad + index as i32 * (w + gap), toast.loc.y + toast.size.h - h - ui.px(8)).into(),
+        (w, h).into(),
+    )
+}
@@ -0,0 +1,113 @@
+// =============================================================================
+// heyDM — systemd Notifications
+//
+// Run from a unit with Type=notify, heyDM reports READY=1 once its event
+// loop runs and STOPPING=1 when it shuts down. With WatchdogSec= set it
+// sends WATCHDOG=1 at half that interval from a timer on the event loop,
+// so a compositor stuck in a frame or a client request stops the pings and
+// systemd restarts it. heyOS itself starts heyDM from greetd, which passes
+// no $NOTIFY_SOCKET, so this only matters for units of your own.
+//
+// `take_environment` reads the variables and takes them out of the
+// environment first thing in main, before any thread could read it; apps
+// started from the session must not talk to heyDM's service manager.
+// =============================================================================
+
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use calloop::timer::{TimeoutAction, Timer};
+use calloop::LoopHandle;
+use tracing::{debug, info, warn};
+
+use crate::state::HeyDM;
+
+/// $NOTIFY_SOCKET and the watchdog interval, as found at startup
+#[derive(Default)]
+struct Environment {
+    socket: Option<String>,
+    watchdog: Option<Duration>,
+}
+
+static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+
+/// Read and clear the service manager's variables. Must run while heyDM
+/// has a single thread: changing the environment races other threads'
+/// getenv and process spawns.
+pub fn take_environment() {
+    let environment = Environment {
+        socket: std::env::var("NOTIFY_SOCKET").ok(),
+        watchdog: watchdog_interval(),
+    };
+    for name in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+        std::env::remove_var(name);
+    }
+    let _ = ENVIRONMENT.set(environment);
+}
+
+fn socket() -> Option<&'static str> {
+    ENVIRONMENT.get_or_init(Environment::default).socket.as_deref()
+}
+
+/// Send a state line like "READY=1"
+fn notify(state: &str) {
+    let Some(path) = socket() else {
+        return;
+    };
+    let address = match path.strip_prefix('@') {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        None => SocketAddr::from_pathname(path),
+    };
+    let sent = address.and_then(|address| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address));
+    if let Err(e) = sent {
+        debug!("sd_notify {state}: {e}");
+    }
+}
+
+/// Half the watchdog timeout, when the watchdog is meant for this process
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Report READY=1 from the event loop and feed the watchdog from it
+pub fn start(loop_handle: &LoopHandle<'static, HeyDM>) {
+    if socket().is_none() {
+        return;
+    }
+    let watchdog = ENVIRONMENT.get().and_then(|environment| environment.watchdog);
+
+    let mut ready = false;
+    let inserted = loop_handle.insert_source(Timer::immediate(), move |_, _, _| {
+        if !ready {
+            ready = true;
+            notify("READY=1");
+        }
+        match watchdog {
+            Some(interval) => {
+                notify("WATCHDOG=1");
+                TimeoutAction::ToDuration(interval)
+            }
+            None => TimeoutAction::Drop,
+        }
+    });
+    match inserted {
+        Ok(_) if watchdog.is_some() => info!("Feeding the systemd watchdog every {watchdog:?}"),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to start systemd notifications: {e}"),
+    }
+}
+
+/// Report STOPPING=1 on shutdown
+pub fn stopping() {
+    notify("STOPPING=1");
+}
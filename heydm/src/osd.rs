@@ -0,0 +1,55 @@
+// =============================================================================
+// heyDM — On-Screen Indicator
+//
+// A small box near the bottom of the screen with a label and a level bar,
+// shown for OSD_DURATION after a setting changes from the keyboard (e.g.
+// the brightness keys, see backlight.rs). It updates in place while keys are
+// pressed repeatedly.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+/// How long the indicator stays on screen after the last change
+pub const OSD_DURATION: Duration = Duration::from_millis(1500);
+
+/// The indicator currently shown
+#[derive(Debug)]
+struct Shown {
+    label: &'static str,
+    /// 0..=100
+    percent: i32,
+    since: Instant,
+}
+
+/// State of the on-screen indicator
+#[derive(Debug, Default)]
+pub struct Osd {
+    shown: Option<Shown>,
+}
+
+impl Osd {
+    /// Show `label` at `percent`, restarting the timeout
+    pub fn show(&mut self, label: &'static str, percent: i32) {
+        self.shown = Some(Shown {
+            label,
+            percent: percent.clamp(0, 100),
+            since: Instant::now(),
+        });
+    }
+
+    /// Correct the level of a visible indicator without extending it, e.g.
+    /// when the real value arrives after a predicted one
+    pub fn update(&mut self, label: &str, percent: i32) {
+        if let Some(shown) = self.shown.as_mut().filter(|shown| shown.label == label) {
+            shown.percent = percent.clamp(0, 100);
+        }
+    }
+
+    /// Label and level to draw this frame
+    pub fn visible(&self) -> Option<(&'static str, i32)> {
+        self.shown
+            .as_ref()
+            .filter(|shown| shown.since.elapsed() < OSD_DURATION)
+            .map(|shown| (shown.label, shown.percent))
+    }
+}
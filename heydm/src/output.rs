@@ -0,0 +1,432 @@
+// =============================================================================
+// heyDM — Output Identity & Configuration
+//
+// Outputs are configured by the monitor rather than the port it is plugged
+// into, so a layout survives moving a monitor to another port or dock:
+//
+//     [output "Dell U2720Q"]
+//     mode = 3840x2160@60      # resolution and refresh rate (Hz optional)
+//     transform = 90           # normal, 90, 180, 270, flipped, flipped-90, ...
+//     scale = 1.5              # HiDPI scale, fractional values work too
+//     position = 0,0           # top-left corner in the global space
+//     wallpaper = ~/Pictures/left.png   # see wallpaper.rs
+//     wallpaper_mode = fit
+//     vrr = fullscreen         # adaptive sync, see tearing.rs
+//     allow_tearing = true
+//
+// Without `mode` the monitor's preferred mode is used; a mode the monitor
+// does not offer is reported and the preferred one used instead. Positions
+// are in logical pixels (after transform and scale), so a 3840x2160 monitor
+// at scale 2 followed by one to its right uses `position = 1920,0`.
+// Reloading the config (see config.rs) applies all of these live.
+//
+// The section name is matched case-insensitively against the monitor's
+// identity read from its EDID — "<make> <model> <serial>", "<make> <model>"
+// or just "<model>" — and, as a fallback, the connector name (DP-1). When
+// several sections match, the most specific one wins. The IPC command
+// `list outputs` reports the identities of the connected monitors.
+// =============================================================================
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use smithay::utils::Transform;
+
+use crate::tearing::VrrMode;
+use crate::wallpaper::{self, WallpaperMode};
+
+/// Output scales accepted in the config
+pub const MIN_OUTPUT_SCALE: f64 = 0.5;
+pub const MAX_OUTPUT_SCALE: f64 = 4.0;
+
+/// Accepted `transform` values
+pub const OUTPUT_TRANSFORMS: &[&str] = &[
+    "normal",
+    "90",
+    "180",
+    "270",
+    "flipped",
+    "flipped-90",
+    "flipped-180",
+    "flipped-270",
+];
+
+/// A `mode = <width>x<height>[@<hz>]` setting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeRequest {
+    pub width: u16,
+    pub height: u16,
+    /// Refresh rate in Hz; None takes the highest one
+    pub refresh: Option<f64>,
+}
+
+impl ModeRequest {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid mode '{value}' (expected e.g. 1920x1080@60)");
+        let (size, refresh) = match value.split_once('@') {
+            Some((size, refresh)) => {
+                let refresh: f64 = refresh
+                    .trim()
+                    .trim_end_matches("Hz")
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid())?;
+                if refresh <= 0.0 {
+                    return Err(invalid());
+                }
+                (size, Some(refresh))
+            }
+            None => (value, None),
+        };
+        let (width, height) = size.trim().split_once('x').ok_or_else(invalid)?;
+        Ok(Self {
+            width: width.trim().parse().map_err(|_| invalid())?,
+            height: height.trim().parse().map_err(|_| invalid())?,
+            refresh,
+        })
+    }
+}
+
+impl std::fmt::Display for ModeRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)?;
+        if let Some(refresh) = self.refresh {
+            write!(f, "@{refresh}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `transform` value
+pub fn parse_transform(value: &str) -> Result<Transform, String> {
+    Ok(match value {
+        "normal" => Transform::Normal,
+        "90" => Transform::_90,
+        "180" => Transform::_180,
+        "270" => Transform::_270,
+        "flipped" => Transform::Flipped,
+        "flipped-90" => Transform::Flipped90,
+        "flipped-180" => Transform::Flipped180,
+        "flipped-270" => Transform::Flipped270,
+        _ => return Err(format!("invalid transform '{value}'")),
+    })
+}
+
+/// Config name of a transform
+pub fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::Normal => "normal",
+        Transform::_90 => "90",
+        Transform::_180 => "180",
+        Transform::_270 => "270",
+        Transform::Flipped => "flipped",
+        Transform::Flipped90 => "flipped-90",
+        Transform::Flipped180 => "flipped-180",
+        Transform::Flipped270 => "flipped-270",
+    }
+}
+
+/// Settings from one `[output "name"]` section
+#[derive(Debug, Clone, Default)]
+pub struct OutputConfig {
+    pub mode: Option<ModeRequest>,
+    pub transform: Option<Transform>,
+    pub scale: Option<f64>,
+    /// Top-left corner in the global compositor space
+    pub position: Option<(i32, i32)>,
+    /// Wallpaper and mode instead of the ones from [appearance]
+    pub wallpaper: Option<PathBuf>,
+    pub wallpaper_mode: Option<WallpaperMode>,
+    /// Adaptive sync; off unless set
+    pub vrr: Option<VrrMode>,
+    /// Whether clients may ask for tearing; allowed unless set
+    pub allow_tearing: Option<bool>,
+}
+
+impl OutputConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "mode" => self.mode = Some(ModeRequest::parse(value)?),
+            "transform" => self.transform = Some(parse_transform(value)?),
+            "scale" => {
+                let scale: f64 = value
+                    .parse()
+                    .map_err(|_| format!("invalid scale '{value}'"))?;
+                self.scale = Some(scale.clamp(MIN_OUTPUT_SCALE, MAX_OUTPUT_SCALE));
+            }
+            "position" => {
+                let parsed = value
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+                let Some(position) = parsed else {
+                    return Err(format!("invalid position '{value}' (expected x,y)"));
+                };
+                self.position = Some(position);
+            }
+            "wallpaper" => self.wallpaper = Some(wallpaper::expand_home(value)),
+            "wallpaper_mode" => self.wallpaper_mode = Some(WallpaperMode::parse(value)?),
+            "vrr" => self.vrr = Some(VrrMode::parse(value)?),
+            "allow_tearing" => {
+                self.allow_tearing = Some(match value {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("expected true or false, got '{value}'")),
+                })
+            }
+            _ => return Err(format!("unknown output setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// Who a connected monitor is, from its connector and EDID
+#[derive(Debug, Clone, Default)]
+pub struct OutputIdentity {
+    /// Connector name, e.g. "DP-1"
+    pub connector: String,
+    /// Manufacturer, e.g. "Dell" (the PNP id when the vendor is not known)
+    pub make: String,
+    /// Monitor name descriptor, e.g. "DELL U2720Q"
+    pub model: String,
+    /// Serial number string, or the numeric serial when there is none
+    pub serial: String,
+}
+
+impl OutputIdentity {
+    /// Identify a connector from its EDID blob, if it has one
+    pub fn new(connector: &str, edid: Option<&[u8]>) -> Self {
+        let mut identity = Self {
+            connector: connector.to_string(),
+            ..Self::default()
+        };
+        if let Some(edid) = edid.filter(|edid| is_valid_edid(edid)) {
+            identity.make = manufacturer(edid);
+            identity.model = descriptor(edid, DESCRIPTOR_NAME)
+                .unwrap_or_else(|| format!("0x{:04X}", u16::from_le_bytes([edid[10], edid[11]])));
+            identity.serial = descriptor(edid, DESCRIPTOR_SERIAL).unwrap_or_else(|| {
+                match u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]) {
+                    0 => String::new(),
+                    serial => serial.to_string(),
+                }
+            });
+        }
+        identity
+    }
+
+    /// "Dell U2720Q ABC123", or the connector name without an EDID
+    pub fn description(&self) -> String {
+        let model = self.model_without_make();
+        let description = [self.make.as_str(), &model, &self.serial]
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if description.is_empty() {
+            self.connector.clone()
+        } else {
+            description
+        }
+    }
+
+    /// The model with a leading make removed ("DELL U2720Q" → "U2720Q")
+    fn model_without_make(&self) -> String {
+        let lower = self.model.to_lowercase();
+        let make = self.make.to_lowercase();
+        match lower.strip_prefix(&make) {
+            Some(rest) if !make.is_empty() && rest.starts_with(' ') => {
+                self.model[self.model.len() - rest.len()..].trim().to_string()
+            }
+            _ => self.model.clone(),
+        }
+    }
+
+    /// How specifically a config section name picks this output: lower is
+    /// more specific, None when it does not match
+    fn match_rank(&self, name: &str) -> Option<u8> {
+        let name = name.trim().to_lowercase();
+        let is = |candidate: &str| candidate.to_lowercase() == name;
+        if !self.model.is_empty() {
+            let model = self.model_without_make();
+            if !self.serial.is_empty()
+                && (is(&format!("{} {model} {}", self.make, self.serial))
+                    || is(&format!("{} {}", self.model, self.serial)))
+            {
+                return Some(0);
+            }
+            if is(&format!("{} {model}", self.make)) || is(&self.model) || is(&model) {
+                return Some(1);
+            }
+        }
+        is(&self.connector).then_some(2)
+    }
+
+    pub fn json(&self, active: bool) -> Value {
+        json!({
+            "connector": self.connector,
+            "name": self.description(),
+            "make": self.make,
+            "model": self.model,
+            "serial": self.serial,
+            "active": active,
+        })
+    }
+}
+
+/// The config section that applies to an output, if any
+pub fn config_for<'a>(
+    configs: &'a BTreeMap<String, OutputConfig>,
+    identity: &OutputIdentity,
+) -> Option<(&'a str, &'a OutputConfig)> {
+    configs
+        .iter()
+        .filter_map(|(name, config)| Some((identity.match_rank(name)?, name.as_str(), config)))
+        .min_by_key(|(rank, _, _)| *rank)
+        .map(|(_, name, config)| (name, config))
+}
+
+// ---- EDID parsing ----
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+/// Display descriptor tags of the four 18-byte blocks at offset 54
+const DESCRIPTOR_SERIAL: u8 = 0xFF;
+const DESCRIPTOR_NAME: u8 = 0xFC;
+
+/// Manufacturers by PNP id, for the common ones
+const VENDORS: &[(&str, &str)] = &[
+    ("ACR", "Acer"),
+    ("AOC", "AOC"),
+    ("APP", "Apple"),
+    ("AUS", "ASUS"),
+    ("BNQ", "BenQ"),
+    ("BOE", "BOE"),
+    ("DEL", "Dell"),
+    ("ENC", "EIZO"),
+    ("GSM", "LG"),
+    ("HWP", "HP"),
+    ("IVM", "iiyama"),
+    ("LEN", "Lenovo"),
+    ("MSI", "MSI"),
+    ("NEC", "NEC"),
+    ("PHL", "Philips"),
+    ("SAM", "Samsung"),
+    ("SHP", "Sharp"),
+    ("SNY", "Sony"),
+    ("VSC", "ViewSonic"),
+];
+
+fn is_valid_edid(edid: &[u8]) -> bool {
+    edid.len() >= 128 && edid[..8] == EDID_HEADER
+}
+
+/// Vendor from the three letters packed into bytes 8-9
+fn manufacturer(edid: &[u8]) -> String {
+    let packed = u16::from_be_bytes([edid[8], edid[9]]);
+    let letter = |shift: u16| (b'A' - 1 + ((packed >> shift) & 0x1F) as u8) as char;
+    let id: String = [letter(10), letter(5), letter(0)].iter().collect();
+    VENDORS
+        .iter()
+        .find(|(pnp, _)| *pnp == id)
+        .map_or(id, |(_, name)| name.to_string())
+}
+
+/// Text of the display descriptor with the given tag
+fn descriptor(edid: &[u8], tag: u8) -> Option<String> {
+    edid[54..126].chunks_exact(18).find_map(|block| {
+        if block[..3] != [0, 0, 0] || block[3] != tag {
+            return None;
+        }
+        // Up to 13 bytes, terminated by a line feed and padded with spaces
+        let text: String = block[5..]
+            .iter()
+            .take_while(|&&b| b != b'\n')
+            .map(|&b| b as char)
+            .collect();
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An EDID for a Dell (PNP id DEL) with product code 0xA0D1, numeric
+    /// serial 12345 and the given display descriptors
+    fn edid(descriptors: &[(u8, &str)]) -> Vec<u8> {
+        let mut edid = vec![0u8; 128];
+        edid[..8].copy_from_slice(&EDID_HEADER);
+        edid[8..10].copy_from_slice(&0x10ACu16.to_be_bytes());
+        edid[10..12].copy_from_slice(&0xA0D1u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&12345u32.to_le_bytes());
+        for (i, (tag, text)) in descriptors.iter().enumerate() {
+            let block = &mut edid[54 + i * 18..72 + i * 18];
+            block[3] = *tag;
+            block[5..].fill(b' ');
+            block[5..5 + text.len()].copy_from_slice(text.as_bytes());
+            if text.len() < 13 {
+                block[5 + text.len()] = b'\n';
+            }
+        }
+        edid
+    }
+
+    #[test]
+    fn reads_name_and_serial_descriptors() {
+        let edid = edid(&[(DESCRIPTOR_NAME, "DELL U2720Q"), (DESCRIPTOR_SERIAL, "ABC123")]);
+        let identity = OutputIdentity::new("DP-1", Some(&edid));
+        assert_eq!(identity.make, "Dell");
+        assert_eq!(identity.model, "DELL U2720Q");
+        assert_eq!(identity.serial, "ABC123");
+        assert_eq!(identity.description(), "Dell U2720Q ABC123");
+    }
+
+    #[test]
+    fn falls_back_to_product_code_and_numeric_serial() {
+        let identity = OutputIdentity::new("HDMI-A-1", Some(&edid(&[])));
+        assert_eq!(identity.model, "0xA0D1");
+        assert_eq!(identity.serial, "12345");
+    }
+
+    #[test]
+    fn keeps_unknown_vendor_ids() {
+        let mut edid = edid(&[]);
+        // "XYZ"
+        let packed = (24u16 << 10) | (25 << 5) | 26;
+        edid[8..10].copy_from_slice(&packed.to_be_bytes());
+        assert_eq!(OutputIdentity::new("DP-1", Some(&edid)).make, "XYZ");
+    }
+
+    #[test]
+    fn ignores_invalid_edids() {
+        let mut broken = edid(&[(DESCRIPTOR_NAME, "DELL U2720Q")]);
+        broken[0] = 0xFF;
+        for edid in [None, Some(&broken[..]), Some(&broken[..64])] {
+            let identity = OutputIdentity::new("eDP-1", edid);
+            assert!(identity.make.is_empty() && identity.model.is_empty());
+            assert_eq!(identity.description(), "eDP-1");
+        }
+    }
+
+    #[test]
+    fn most_specific_config_section_wins() {
+        let edid = edid(&[(DESCRIPTOR_NAME, "DELL U2720Q"), (DESCRIPTOR_SERIAL, "ABC123")]);
+        let identity = OutputIdentity::new("DP-1", Some(&edid));
+        let configs = |names: &[&str]| -> BTreeMap<String, OutputConfig> {
+            names.iter().map(|name| (name.to_string(), OutputConfig::default())).collect()
+        };
+        let pick = |names: &[&str]| {
+            config_for(&configs(names), &identity).map(|(name, _)| name.to_string())
+        };
+
+        assert_eq!(
+            pick(&["DP-1", "Dell U2720Q", "dell u2720q abc123"]).as_deref(),
+            Some("dell u2720q abc123")
+        );
+        assert_eq!(pick(&["DP-1", "U2720Q"]).as_deref(), Some("U2720Q"));
+        assert_eq!(pick(&["DP-1", "HDMI-A-1"]).as_deref(), Some("DP-1"));
+        assert_eq!(pick(&["HDMI-A-1", "Dell U2720Q XYZ"]), None);
+    }
+}
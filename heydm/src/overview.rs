@@ -0,0 +1,233 @@
+// =============================================================================
+// heyDM — Window Overview
+//
+// Super+W shows every window of the visible workspaces at once: each one
+// shrinks from where it is into a grid of cards, keeping its proportions.
+// While it is open:
+//
+//     arrow keys        move the selection through the grid
+//     Enter / click     focus the selected (clicked) window
+//     Escape / Super+W  close without changing focus
+//
+// Clicking outside the cards closes it as well. On the way out the cards
+// grow back to their windows, the chosen one on top.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use smithay::utils::{Physical, Rectangle, Size};
+
+use crate::theme::UiMetrics;
+use crate::window::WindowManager;
+
+/// How long cards take to move between a window and the grid
+const ANIMATION: Duration = Duration::from_millis(220);
+/// Space between cards and around the grid at scale 1
+const GAP: i32 = 32;
+/// Room for the title under each card at scale 1
+const TITLE_HEIGHT: i32 = 28;
+
+/// One window in the overview
+#[derive(Debug, Clone)]
+pub struct OverviewEntry {
+    pub window_id: u32,
+    pub title: String,
+    pub app_id: String,
+    /// Where the window is on screen
+    pub geometry: Rectangle<i32, Physical>,
+}
+
+#[derive(Debug)]
+pub struct Overview {
+    entries: Vec<OverviewEntry>,
+    selected: usize,
+    visible: bool,
+    /// When the cards started moving, and whether towards the windows
+    started: Instant,
+    closing: bool,
+}
+
+impl Default for Overview {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            selected: 0,
+            visible: false,
+            started: Instant::now(),
+            closing: false,
+        }
+    }
+}
+
+impl Overview {
+    /// Open on the windows of the visible workspaces, the focused one
+    /// selected. Returns false when there are none.
+    pub fn show(&mut self, wm: &WindowManager) -> bool {
+        let focused = wm.focused_window().map(|window| window.id());
+        self.entries = wm
+            .visible_windows()
+            .map(|window| {
+                let (title, app_id) = window.title_and_app_id();
+                let geometry = window.geometry();
+                OverviewEntry {
+                    window_id: window.id(),
+                    title: title.unwrap_or_default(),
+                    app_id: app_id.unwrap_or_default(),
+                    geometry: Rectangle::new(
+                        (geometry.loc.x, geometry.loc.y).into(),
+                        (geometry.size.w, geometry.size.h).into(),
+                    ),
+                }
+            })
+            .collect();
+        if self.entries.is_empty() {
+            return false;
+        }
+        self.selected = self
+            .entries
+            .iter()
+            .position(|entry| Some(entry.window_id) == focused)
+            .unwrap_or(self.entries.len() - 1);
+        self.visible = true;
+        self.closing = false;
+        self.started = Instant::now();
+        true
+    }
+
+    /// Start the closing animation
+    pub fn hide(&mut self) {
+        if self.visible && !self.closing {
+            // Reverse from wherever an unfinished opening got to
+            let elapsed = self.started.elapsed().min(ANIMATION);
+            self.started = Instant::now() - (ANIMATION - elapsed);
+            self.closing = true;
+        }
+    }
+
+    /// Whether the overview is on screen, including its closing animation
+    pub fn is_visible(&self) -> bool {
+        self.visible && !(self.closing && self.started.elapsed() >= ANIMATION)
+    }
+
+    /// Whether it takes input; not while closing
+    pub fn is_open(&self) -> bool {
+        self.visible && !self.closing
+    }
+
+    /// Close and return the selected window, to focus
+    pub fn commit(&mut self) -> Option<u32> {
+        let id = self.selected_id();
+        self.hide();
+        id
+    }
+
+    /// Select the card at a scene position; false when there is none
+    pub fn select_at(&mut self, pos: (f64, f64), output_size: Size<i32, Physical>, ui: &UiMetrics) -> bool {
+        let hit = self
+            .slots(output_size, ui)
+            .iter()
+            .position(|slot| slot.to_f64().contains(pos));
+        if let Some(index) = hit {
+            self.selected = index;
+        }
+        hit.is_some()
+    }
+
+    /// Move the selection by whole cards across (`dx`) and rows (`dy`)
+    pub fn step(&mut self, dx: i32, dy: i32) {
+        let count = self.entries.len() as i32;
+        if count == 0 {
+            return;
+        }
+        let (columns, _) = grid(self.entries.len());
+        let columns = columns as i32;
+        let selected = self.selected as i32;
+        let target = if dy != 0 {
+            let target = selected + dy * columns;
+            if (0..count).contains(&target) {
+                target
+            } else {
+                selected
+            }
+        } else {
+            (selected + dx).clamp(0, count - 1)
+        };
+        self.selected = target as usize;
+    }
+
+    /// The window the selection is on
+    pub fn selected_id(&self) -> Option<u32> {
+        self.entries.get(self.selected).map(|entry| entry.window_id)
+    }
+
+    /// The entries with where to draw them this frame, bottom to top
+    pub fn cards(&self, output_size: Size<i32, Physical>, ui: &UiMetrics) -> Vec<(&OverviewEntry, Rectangle<i32, Physical>)> {
+        let t = (self.started.elapsed().as_secs_f64() / ANIMATION.as_secs_f64()).min(1.0);
+        let t = if self.closing { 1.0 - t } else { t };
+        let eased = t * t * (3.0 - 2.0 * t);
+
+        let mut cards: Vec<_> = self
+            .entries
+            .iter()
+            .zip(self.slots(output_size, ui))
+            .map(|(entry, slot)| (entry, lerp(entry.geometry, slot, eased)))
+            .collect();
+        if self.closing {
+            // The chosen window lands on top
+            let selected = cards.remove(self.selected.min(cards.len() - 1));
+            cards.push(selected);
+        }
+        cards
+    }
+
+    /// Final card positions: a grid below the panel, each window scaled
+    /// down to fit its cell
+    fn slots(&self, output_size: Size<i32, Physical>, ui: &UiMetrics) -> Vec<Rectangle<i32, Physical>> {
+        let count = self.entries.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        let (columns, rows) = grid(count);
+        let gap = ui.px(GAP);
+        let title_h = ui.px(TITLE_HEIGHT);
+        let top = ui.panel_bottom() + gap;
+        let cell_w = ((output_size.w - gap) / columns as i32 - gap).max(1);
+        let cell_h = ((output_size.h - top - gap) / rows as i32 - gap - title_h).max(1);
+
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let (column, row) = (i % columns, i / columns);
+                // The last row is centered when it is not full
+                let in_row = if row == rows - 1 { count - row * columns } else { columns };
+                let row_w = in_row as i32 * (cell_w + gap) - gap;
+                let row_x = (output_size.w - row_w) / 2;
+
+                let size = entry.geometry.size;
+                let scale = (cell_w as f64 / size.w.max(1) as f64)
+                    .min(cell_h as f64 / size.h.max(1) as f64)
+                    .min(1.0);
+                let w = (size.w as f64 * scale).round() as i32;
+                let h = (size.h as f64 * scale).round() as i32;
+                let x = row_x + column as i32 * (cell_w + gap) + (cell_w - w) / 2;
+                let y = top + row as i32 * (cell_h + title_h + gap) + (cell_h - h) / 2;
+                Rectangle::new((x, y).into(), (w, h).into())
+            })
+            .collect()
+    }
+}
+
+/// Columns and rows for `count` cards, wider than tall
+fn grid(count: usize) -> (usize, usize) {
+    let columns = (count as f64).sqrt().ceil().max(1.0) as usize;
+    (columns, count.div_ceil(columns))
+}
+
+fn lerp(from: Rectangle<i32, Physical>, to: Rectangle<i32, Physical>, t: f64) -> Rectangle<i32, Physical> {
+    let mix = |a: i32, b: i32| (a as f64 + (b - a) as f64 * t).round() as i32;
+    Rectangle::new(
+        (mix(from.loc.x, to.loc.x), mix(from.loc.y, to.loc.y)).into(),
+        (mix(from.size.w, to.size.w), mix(from.size.h, to.size.h)).into(),
+    )
+}
@@ -3,159 +3,120 @@
 //
 // The top bar of the heyOS desktop, displaying:
 //   - Left:   "heyOS" branding / launcher trigger button
-//   - Center: Window title of focused application
-//   - Right:  Network status, battery level, clock
+//   - Center: Taskbar with the active workspace's windows; clicking a button
+//             restores a minimized window, focuses another one or
+//             minimizes the focused one. The media player's track and
+//             controls (see mpris.rs) sit in the middle while one runs
+//   - Right:  Focused window title and app id, tray icons (see tray.rs),
+//             do-not-disturb toggle (see
+//             notifications.rs), power profile (click for the next one,
+//             see profiles.rs), volume (scroll to change, click to mute),
+//             Bluetooth (click for devices, see bluetooth.rs),
+//             network (click for Wi-Fi networks, see network.rs),
+//             brightness, battery level, clock (click for the calendar,
+//             see calendar.rs)
 //
-// Uses fontdue for software text rasterization into pixel buffers that are
-// uploaded as GPU textures for drawing.
+// The clock is read by the poller (poller.rs), the battery by the UPower
+// watcher (power.rs), the network from NetworkManager (network.rs) and
+// Bluetooth from BlueZ (bluetooth.rs), each on its own thread, and applied
+// here when they change.
 // =============================================================================
 
-use chrono::Local;
-use std::fs;
-use std::path::Path;
+use smithay::utils::{Physical, Size};
 use tracing::debug;
 
-/// Height of the status panel in pixels
-#[allow(dead_code)]
-pub const PANEL_HEIGHT: i32 = 32;
+use crate::bluetooth::BluetoothState;
+use crate::mpris::{MediaControl, MediaState};
+use crate::network::{Connectivity, NetworkState, NetworkStatus};
+use crate::poller::PanelUpdate;
+use crate::profiles::ProfileState;
+use crate::volume::{VolumeState, VOLUME_STEP};
+use crate::render::{
+    bluetooth_rect, clock_rect, dnd_rect, media_button_rect, media_rect, network_rect,
+    power_profile_rect, taskbar_button_rect, volume_rect, workspace_indicator_rect,
+};
+use crate::theme::UiMetrics;
+use crate::tooltip::TooltipSource;
+use crate::window::{WindowElement, WindowManager};
+use crate::workspace::WORKSPACE_COUNT;
+
+/// Scroll distance (one mouse wheel notch) per volume step
+const VOLUME_SCROLL_DISTANCE: f64 = 15.0;
 
 /// Status panel state and data
 pub struct StatusPanel {
-    /// Cached clock string (updated once per second)
+    /// Clock string (updated every second by the poller)
     clock_text: String,
-    /// Last update timestamp (seconds)
-    last_update_sec: i64,
     /// Battery percentage (0-100, or -1 if no battery)
     battery_percent: i32,
     /// Whether the battery is charging
     battery_charging: bool,
-    /// Network connection status
-    network_status: NetworkStatus,
-    /// Network SSID or interface name
-    network_name: String,
-}
-
-/// Network connection state
-#[derive(Debug, Clone, PartialEq)]
-pub enum NetworkStatus {
-    /// Not connected to any network
-    Disconnected,
-    /// Connected via WiFi
-    WiFi,
-    /// Connected via Ethernet
-    Ethernet,
-    /// Connection status unknown
-    Unknown,
+    /// Network connection, SSID and signal
+    network: NetworkState,
+    /// Default sink volume (-1 without a sound server)
+    volume: VolumeState,
+    /// Scroll distance over the volume module not yet turned into steps
+    volume_scroll: f64,
+    /// Backlight brightness in percent (-1 without a backlight)
+    brightness: i32,
+    /// Power profile or CPU governor
+    power_profile: ProfileState,
+    /// Adapter power and paired devices
+    bluetooth: BluetoothState,
+    /// The shown media player's track
+    media: Option<MediaState>,
+    /// UI scale the panel is drawn at
+    ui: UiMetrics,
 }
 
 #[allow(dead_code)]
 impl StatusPanel {
-    /// Create a new status panel with initial state
-    pub fn new() -> Self {
-        let mut panel = Self {
+    /// Create a new status panel; values arrive from the poller
+    pub fn new(ui: UiMetrics) -> Self {
+        Self {
             clock_text: String::new(),
-            last_update_sec: 0,
             battery_percent: -1,
             battery_charging: false,
-            network_status: NetworkStatus::Unknown,
-            network_name: String::new(),
-        };
-        panel.update();
-        panel
-    }
-
-    /// Update all panel data (called each frame, but internally rate-limited)
-    pub fn update(&mut self) {
-        let now = Local::now();
-        let current_sec = now.timestamp();
-
-        // Only update once per second (no need to read sysfs 60 times/sec)
-        if current_sec == self.last_update_sec {
-            return;
+            network: NetworkState {
+                status: NetworkStatus::Unknown,
+                name: String::new(),
+                strength: None,
+                connectivity: Connectivity::Unknown,
+            },
+            volume: VolumeState {
+                percent: -1,
+                muted: false,
+            },
+            volume_scroll: 0.0,
+            brightness: -1,
+            power_profile: ProfileState::default(),
+            bluetooth: BluetoothState::default(),
+            media: None,
+            ui,
         }
-        self.last_update_sec = current_sec;
-
-        // ---- Update clock ----
-        self.clock_text = now.format("%a %b %d  %H:%M").to_string();
-
-        // ---- Update battery ----
-        self.update_battery();
-
-        // ---- Update network ----
-        self.update_network();
     }
 
-    /// Read battery status from /sys/class/power_supply/
-    fn update_battery(&mut self) {
-        let bat_path = Path::new("/sys/class/power_supply/BAT0");
-
-        if !bat_path.exists() {
-            // Try BAT1 as fallback
-            let bat1_path = Path::new("/sys/class/power_supply/BAT1");
-            if !bat1_path.exists() {
-                self.battery_percent = -1; // No battery found (desktop/VM)
-                return;
-            }
-            self.read_battery_sysfs(bat1_path);
-            return;
-        }
-
-        self.read_battery_sysfs(bat_path);
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
     }
 
-    /// Parse battery info from a sysfs power_supply path
-    fn read_battery_sysfs(&mut self, path: &Path) {
-        // Read capacity (percentage)
-        if let Ok(capacity_str) = fs::read_to_string(path.join("capacity")) {
-            if let Ok(capacity) = capacity_str.trim().parse::<i32>() {
-                self.battery_percent = capacity.clamp(0, 100);
-            }
-        }
-
-        // Read charging status
-        if let Ok(status_str) = fs::read_to_string(path.join("status")) {
-            let status = status_str.trim().to_lowercase();
-            self.battery_charging = status == "charging" || status == "full";
-        }
-    }
-
-    /// Read network status from NetworkManager via /sys or simple checks
-    fn update_network(&mut self) {
-        // Check for wired connection first
-        let net_path = Path::new("/sys/class/net");
-
-        if let Ok(entries) = fs::read_dir(net_path) {
-            for entry in entries.flatten() {
-                let name = entry.file_name().to_string_lossy().to_string();
-
-                // Skip loopback
-                if name == "lo" {
-                    continue;
-                }
-
-                // Check if interface is up by reading operstate
-                let operstate_path = entry.path().join("operstate");
-                if let Ok(state) = fs::read_to_string(&operstate_path) {
-                    if state.trim() == "up" {
-                        if name.starts_with("wl") || name.starts_with("wlan") {
-                            self.network_status = NetworkStatus::WiFi;
-                            self.network_name = name;
-                            return;
-                        } else if name.starts_with("en")
-                            || name.starts_with("eth")
-                            || name.starts_with("ens")
-                        {
-                            self.network_status = NetworkStatus::Ethernet;
-                            self.network_name = name;
-                            return;
-                        }
-                    }
-                }
+    /// Take a changed value from the poller (see poller.rs)
+    pub fn apply(&mut self, update: PanelUpdate) {
+        debug!("Panel update: {update:?}");
+        match update {
+            PanelUpdate::Clock(text) => self.clock_text = text,
+            PanelUpdate::Battery(battery) => {
+                self.battery_percent = battery.percent;
+                self.battery_charging = battery.charging;
             }
+            PanelUpdate::Network(network) => self.network = network,
+            PanelUpdate::Volume(volume) => self.volume = volume,
+            PanelUpdate::Brightness(percent) => self.brightness = percent,
+            PanelUpdate::PowerProfile(profile) => self.power_profile = profile,
+            PanelUpdate::Bluetooth(bluetooth) => self.bluetooth = bluetooth,
+            PanelUpdate::Media(media) => self.media = media,
         }
-
-        self.network_status = NetworkStatus::Disconnected;
-        self.network_name.clear();
     }
 
     // ---- Public accessors for the renderer ----
@@ -199,24 +160,167 @@ impl StatusPanel {
 
     /// Get network status
     pub fn network_status(&self) -> &NetworkStatus {
-        &self.network_status
+        &self.network.status
     }
 
-    /// Get a display string for network status
+    /// Get a display string for network status; Wi-Fi signal bars are
+    /// drawn next to it
     pub fn network_text(&self) -> String {
-        match &self.network_status {
-            NetworkStatus::WiFi => format!("WiFi: {}", self.network_name),
-            NetworkStatus::Ethernet => format!("Eth: {}", self.network_name),
+        match &self.network.status {
+            NetworkStatus::WiFi if self.network.strength.is_some() => self.network.name.clone(),
+            NetworkStatus::WiFi => format!("WiFi: {}", self.network.name),
+            NetworkStatus::Ethernet => format!("Eth: {}", self.network.name),
+            NetworkStatus::Other => self.network.name.clone(),
             NetworkStatus::Disconnected => "Disconnected".to_string(),
             NetworkStatus::Unknown => "Network: ?".to_string(),
         }
     }
 
+    /// Wi-Fi signal strength in percent
+    pub fn network_strength(&self) -> Option<u8> {
+        self.network.strength
+    }
+
+    /// False while connected without internet access
+    pub fn network_online(&self) -> bool {
+        !matches!(
+            self.network.connectivity,
+            Connectivity::None | Connectivity::Portal | Connectivity::Limited
+        )
+    }
+
+    /// Backlight brightness in percent (-1 without a backlight)
+    pub fn brightness(&self) -> i32 {
+        self.brightness
+    }
+
+    /// Get a display string for the brightness; empty without a backlight
+    pub fn brightness_text(&self) -> String {
+        if self.brightness < 0 {
+            String::new()
+        } else {
+            format!("☀ {}%", self.brightness)
+        }
+    }
+
+    /// Get a display string for the volume; empty without a sound server
+    pub fn volume_text(&self) -> String {
+        match self.volume {
+            VolumeState { percent, .. } if percent < 0 => String::new(),
+            VolumeState { muted: true, .. } => "Vol: muted".to_string(),
+            VolumeState { percent, .. } => format!("Vol: {percent}%"),
+        }
+    }
+
+    /// Whether `pos` is over the clock
+    pub fn is_over_clock(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        clock_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// Whether `pos` is over the network module
+    pub fn is_over_network(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        network_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// Whether `pos` is over the do-not-disturb toggle
+    pub fn is_over_dnd(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        dnd_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// The shown media player's track, if any
+    pub fn media(&self) -> Option<&MediaState> {
+        self.media.as_ref()
+    }
+
+    /// The media control under `pos`; clicking the track plays or pauses
+    pub fn media_control_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<MediaControl> {
+        self.media.as_ref()?;
+        let point = (pos.0 as i32, pos.1 as i32);
+        let controls = [MediaControl::Previous, MediaControl::PlayPause, MediaControl::Next];
+        let button = (0..controls.len()).find(|i| media_button_rect(*i, output_size, &self.ui).contains(point));
+        match button {
+            Some(i) => Some(controls[i]),
+            None => media_rect(output_size, &self.ui)
+                .contains(point)
+                .then_some(MediaControl::PlayPause),
+        }
+    }
+
+    /// Whether `pos` is over the Bluetooth module
+    pub fn is_over_bluetooth(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        self.bluetooth.available
+            && bluetooth_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// The power profile module's text; empty when nothing is known
+    pub fn power_profile_text(&self) -> &str {
+        self.power_profile.label()
+    }
+
+    /// Whether clicking the power profile module switches profiles
+    pub fn power_profile_switchable(&self) -> bool {
+        self.power_profile.switchable
+    }
+
+    /// Whether `pos` is over a power profile module that can switch
+    pub fn is_over_power_profile(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        self.power_profile.switchable
+            && power_profile_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// Whether `pos` is over the volume module
+    pub fn is_over_volume(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> bool {
+        self.volume.percent >= 0
+            && volume_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32))
+    }
+
+    /// Turn scrolling over the volume module into a volume change in
+    /// percent: positive when scrolling up, 0 until a full step was
+    /// scrolled
+    pub fn volume_scroll(&mut self, amount: f64) -> i32 {
+        self.volume_scroll -= amount;
+        let steps = (self.volume_scroll / VOLUME_SCROLL_DISTANCE).trunc();
+        self.volume_scroll -= steps * VOLUME_SCROLL_DISTANCE;
+        steps as i32 * VOLUME_STEP
+    }
+
+    /// Windows listed in the taskbar: those on the active workspace,
+    /// minimized ones included, oldest first; not the scratchpad
+    pub fn taskbar_windows(wm: &WindowManager) -> Vec<&WindowElement> {
+        let ws = wm.workspaces().active_workspace();
+        let mut windows: Vec<_> = wm
+            .windows()
+            .iter()
+            .filter(|w| w.workspace() == ws && !w.is_scratchpad())
+            .collect();
+        windows.sort_by_key(|w| w.id());
+        windows
+    }
+
+    /// Window of the taskbar button under the pointer
+    pub fn taskbar_window_at(
+        &self,
+        wm: &WindowManager,
+        pos: (f64, f64),
+        output_size: Size<i32, Physical>,
+    ) -> Option<u32> {
+        let windows = Self::taskbar_windows(wm);
+        let point = (pos.0 as i32, pos.1 as i32);
+        windows
+            .iter()
+            .enumerate()
+            .find(|(i, _)| {
+                taskbar_button_rect(*i, windows.len(), self.media.is_some(), output_size, &self.ui)
+                    .is_some_and(|r| r.contains(point))
+            })
+            .map(|(_, w)| w.id())
+    }
+
     /// Handle a click on the panel area
     /// Returns true if the click was consumed
     pub fn handle_click(&mut self, x: f64, _y: f64) -> bool {
-        // Left side (first 100px) — "heyOS" button / launcher trigger
-        if x < 100.0 {
+        // Left side (first 100px at scale 1) — "heyOS" button / launcher trigger
+        if x < self.ui.px(100) as f64 {
             debug!("Panel: heyOS button clicked");
             return true; // The caller should toggle the launcher
         }
@@ -224,3 +328,85 @@ impl StatusPanel {
         false
     }
 }
+
+impl TooltipSource for StatusPanel {
+    /// Describe the panel module under the pointer
+    fn tooltip_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<String> {
+        let (x, y) = pos;
+        if y < self.ui.panel_margin() as f64 || y > self.ui.panel_bottom() as f64 {
+            return None;
+        }
+
+        let point = (x as i32, y as i32);
+        if let Some(ws) = (1..=WORKSPACE_COUNT).find(|ws| workspace_indicator_rect(*ws, &self.ui).contains(point)) {
+            return Some(format!("Workspace {ws} (Super+{ws})"));
+        }
+
+        if x < self.ui.px(100) as f64 {
+            return Some("Applications (Super+D)".to_string());
+        }
+
+        if self.is_over_clock(pos, output_size) {
+            return Some("Calendar".to_string());
+        }
+
+        if self.is_over_network(pos, output_size) {
+            let state = match self.network.connectivity {
+                Connectivity::Portal => "Sign in to the network",
+                Connectivity::Limited | Connectivity::None => "No internet access",
+                _ => "Wi-Fi networks",
+            };
+            return Some(match self.network.strength {
+                Some(strength) => format!("{state} (signal {strength}%)"),
+                None => state.to_string(),
+            });
+        }
+
+        if let Some(control) = self.media_control_at(pos, output_size) {
+            return Some(match control {
+                MediaControl::Previous => "Previous track".to_string(),
+                MediaControl::Next => "Next track".to_string(),
+                MediaControl::PlayPause => self.media.as_ref().map(MediaState::label).unwrap_or_default(),
+            });
+        }
+
+        if self.is_over_bluetooth(pos, output_size) {
+            if !self.bluetooth.powered {
+                return Some("Bluetooth is off".to_string());
+            }
+            let connected: Vec<String> = self
+                .bluetooth
+                .connected()
+                .map(|device| match device.battery {
+                    Some(battery) => format!("{} ({battery}%)", device.name),
+                    None => device.name.clone(),
+                })
+                .collect();
+            return Some(if connected.is_empty() {
+                "Bluetooth: no devices connected".to_string()
+            } else {
+                format!("Bluetooth: {}", connected.join(", "))
+            });
+        }
+
+        if self.is_over_dnd(pos, output_size) {
+            return Some("Do not disturb: only critical notifications".to_string());
+        }
+
+        if self.is_over_volume(pos, output_size) {
+            return Some("Scroll to change the volume, click to mute".to_string());
+        }
+
+        if power_profile_rect(output_size, &self.ui).contains((pos.0 as i32, pos.1 as i32)) {
+            let profile = &self.power_profile;
+            if profile.switchable {
+                return Some(format!("Power profile: {}; click to switch", profile.active));
+            }
+            if !profile.active.is_empty() {
+                return Some(format!("CPU governor: {}", profile.active));
+            }
+        }
+
+        None
+    }
+}
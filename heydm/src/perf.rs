@@ -0,0 +1,174 @@
+// =============================================================================
+// heyDM — Performance Metrics
+//
+// An optional recorder for how the compositor is doing, per frame:
+//
+//   frame time  — time between two submitted frames (what the eye sees)
+//   render time — building and submitting the frame
+//   damage      — pixels redrawn; heyDM repaints the whole output for a
+//                 composited frame and nothing for a directly scanned-out
+//                 one (see scanout.rs)
+//
+// plus the number of connected Wayland clients and the input latency
+// measured by latency.rs, which is switched on along with it.
+//
+// Enabled with HEYDM_PERF=1 at startup, over IPC (`perf on|off|reset`) or
+// by showing the overlay: Super+Alt+P toggles a small box in the top right
+// corner with the frame rate and times. While enabled a summary is logged
+// every LOG_INTERVAL with structured fields (target heydm::perf), e.g. for
+// `journalctl -o json`.
+// =============================================================================
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+use crate::latency::{LatencyStats, Samples};
+
+/// How often a summary is logged while enabled
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The frame rate is counted over this window
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// Connected Wayland clients, counted by ClientState (see state.rs)
+static CLIENTS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn client_connected() {
+    CLIENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn client_disconnected() {
+    CLIENTS.fetch_sub(1, Ordering::Relaxed);
+}
+
+pub fn client_count() -> usize {
+    CLIENTS.load(Ordering::Relaxed)
+}
+
+/// Metrics of the recent frames
+#[derive(Debug, Default)]
+pub struct PerfMetrics {
+    enabled: bool,
+    overlay: bool,
+    frame_time: Samples,
+    render_time: Samples,
+    /// Submission times within FPS_WINDOW
+    recent: VecDeque<Instant>,
+    last_frame: Option<Instant>,
+    frames: u64,
+    damage: u64,
+    last_log: Option<Instant>,
+}
+
+/// Summary for IPC and the overlay
+#[derive(Debug, Clone, Copy)]
+pub struct PerfSummary {
+    pub fps: usize,
+    pub frame_time: Option<LatencyStats>,
+    pub render_time: Option<LatencyStats>,
+    /// Average pixels redrawn per frame
+    pub damage: u64,
+    pub clients: usize,
+}
+
+impl PerfMetrics {
+    /// Metrics enabled when HEYDM_PERF is set to a true value
+    pub fn from_env() -> Self {
+        Self {
+            enabled: matches!(
+                std::env::var("HEYDM_PERF").as_deref(),
+                Ok("1" | "true" | "yes" | "on")
+            ),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled != self.enabled {
+            info!("Performance metrics {}", if enabled { "enabled" } else { "disabled" });
+        }
+        self.enabled = enabled;
+        if !enabled {
+            self.overlay = false;
+        }
+        self.last_frame = None;
+    }
+
+    pub fn overlay_visible(&self) -> bool {
+        self.overlay
+    }
+
+    /// Show or hide the overlay; showing it enables the metrics
+    pub fn toggle_overlay(&mut self) {
+        self.overlay = !self.overlay;
+        if self.overlay {
+            self.set_enabled(true);
+        }
+    }
+
+    /// Drop all samples collected so far
+    pub fn reset(&mut self) {
+        let (enabled, overlay) = (self.enabled, self.overlay);
+        *self = Self::default();
+        self.enabled = enabled;
+        self.overlay = overlay;
+    }
+
+    /// A frame that took `render` to build went to the backend, redrawing
+    /// `damage` pixels
+    pub fn frame_submitted(&mut self, render: Duration, damage: u64) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            self.frame_time.push(now - last);
+        }
+        self.render_time.push(render);
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|at| now - *at > FPS_WINDOW) {
+            self.recent.pop_front();
+        }
+        self.frames += 1;
+        self.damage += damage;
+
+        if self.last_log.is_none_or(|at| now - at >= LOG_INTERVAL) {
+            self.last_log = Some(now);
+            self.log();
+        }
+    }
+
+    pub fn summary(&self) -> PerfSummary {
+        PerfSummary {
+            fps: self.recent.len(),
+            frame_time: self.frame_time.stats(),
+            render_time: self.render_time.stats(),
+            damage: self.damage.checked_div(self.frames).unwrap_or(0),
+            clients: client_count(),
+        }
+    }
+
+    fn log(&self) {
+        let summary = self.summary();
+        let ms = |stats: Option<LatencyStats>, pick: fn(&LatencyStats) -> Duration| {
+            stats.map_or(0.0, |s| pick(&s).as_secs_f64() * 1000.0)
+        };
+        info!(
+            target: "heydm::perf",
+            fps = summary.fps,
+            frame_p50_ms = ms(summary.frame_time, |s| s.p50),
+            frame_p99_ms = ms(summary.frame_time, |s| s.p99),
+            render_p99_ms = ms(summary.render_time, |s| s.p99),
+            damage_px = summary.damage,
+            clients = summary.clients,
+            "Frame metrics"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+// =============================================================================
+// heyDM — Panel Data Poller
+//
+// Reads the panel's data sources on a worker thread, each on its own
+// interval:
+//
+//     clock      every second (aligned to the second boundary)
+//     volume     every 2 s    (pactl, see volume.rs)
+//     backlight  every 2 s    (/sys/class/backlight, see backlight.rs)
+//
+// Only changed values are sent back through a calloop channel, so the
+// compositor thread never touches sysfs and the panel only changes when
+// something did. The battery (UPower, see power.rs), the network
+// (NetworkManager, see network.rs), Bluetooth (BlueZ, see bluetooth.rs),
+// the power profile (see profiles.rs) and media players (see mpris.rs) are
+// followed on their own threads and use the same channel.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use calloop::channel::Sender;
+use chrono::Local;
+use tracing::warn;
+
+use crate::backlight;
+use crate::bluetooth::BluetoothState;
+use crate::mpris::MediaState;
+use crate::network::NetworkState;
+use crate::profiles::ProfileState;
+use crate::volume::{self, VolumeState};
+
+const CLOCK_INTERVAL: Duration = Duration::from_secs(1);
+const VOLUME_INTERVAL: Duration = Duration::from_secs(2);
+const BACKLIGHT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Battery state; `percent` is -1 without a battery
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryState {
+    pub percent: i32,
+    pub charging: bool,
+}
+
+/// A changed panel value
+#[derive(Debug, Clone, PartialEq)]
+pub enum PanelUpdate {
+    Clock(String),
+    Battery(BatteryState),
+    Network(NetworkState),
+    Volume(VolumeState),
+    /// Brightness in percent, -1 without a backlight
+    Brightness(i32),
+    PowerProfile(ProfileState),
+    Bluetooth(BluetoothState),
+    /// The track of the shown media player; None without one
+    Media(Option<MediaState>),
+}
+
+/// One polled source and when it is due next
+struct Source {
+    interval: Duration,
+    due: Instant,
+    last: Option<PanelUpdate>,
+    read: fn() -> PanelUpdate,
+}
+
+/// Start polling; updates are sent to `updates` until the receiver is gone
+pub fn start(updates: Sender<PanelUpdate>) {
+    let spawned = std::thread::Builder::new()
+        .name("heydm-panel".to_string())
+        .spawn(move || {
+            let now = Instant::now();
+            let mut sources = [
+                (CLOCK_INTERVAL, read_clock as fn() -> PanelUpdate),
+                (VOLUME_INTERVAL, read_volume),
+                (BACKLIGHT_INTERVAL, read_brightness),
+            ]
+            .map(|(interval, read)| Source {
+                interval,
+                due: now,
+                last: None,
+                read,
+            });
+
+            loop {
+                let now = Instant::now();
+                for source in sources.iter_mut().filter(|s| s.due <= now) {
+                    source.due = now + source.interval;
+                    let value = (source.read)();
+                    if source.last.as_ref() == Some(&value) {
+                        continue;
+                    }
+                    source.last = Some(value.clone());
+                    if updates.send(value).is_err() {
+                        return;
+                    }
+                }
+
+                // The clock ticks on the second boundary
+                let millis = Local::now().timestamp_subsec_millis().min(999) as u64;
+                sources[0].due = sources[0].due.min(now + Duration::from_millis(1000 - millis));
+
+                let next = sources.iter().map(|s| s.due).min().unwrap_or(now + CLOCK_INTERVAL);
+                std::thread::sleep(next.saturating_duration_since(Instant::now()));
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start panel poller: {e}");
+    }
+}
+
+fn read_clock() -> PanelUpdate {
+    PanelUpdate::Clock(Local::now().format("%a %b %d  %H:%M").to_string())
+}
+
+fn read_volume() -> PanelUpdate {
+    PanelUpdate::Volume(volume::read())
+}
+
+fn read_brightness() -> PanelUpdate {
+    PanelUpdate::Brightness(backlight::read())
+}
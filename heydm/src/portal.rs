@@ -0,0 +1,217 @@
+// =============================================================================
+// heyDM — Settings Portal Backend
+//
+// Implements org.freedesktop.impl.portal.Settings so xdg-desktop-portal can
+// hand the desktop's appearance to applications (GTK, Qt, Firefox, ...):
+//
+//     org.freedesktop.appearance  color-scheme  u      1 dark / 2 light
+//     org.freedesktop.appearance  accent-color  (ddd)  wallpaper accent
+//
+// The D-Bus connection lives on its own thread; the compositor pushes new
+// accent colors and color schemes through a channel and the thread emits
+// SettingChanged.
+// xdg-desktop-portal finds this backend through
+// /usr/share/xdg-desktop-portal/portals/heydm.portal.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+
+use tracing::{info, warn};
+
+use crate::wallpaper::ColorScheme;
+use zbus::object_server::SignalContext;
+use zbus::zvariant::{OwnedValue, Value};
+
+const BUS_NAME: &str = "org.freedesktop.impl.portal.desktop.heydm";
+const OBJECT_PATH: &str = "/org/freedesktop/portal/desktop";
+const APPEARANCE: &str = "org.freedesktop.appearance";
+
+/// Appearance values served to clients
+#[derive(Debug, Default)]
+struct Appearance {
+    color_scheme: ColorScheme,
+    /// sRGB accent color, components in 0.0..=1.0
+    accent: Option<[f64; 3]>,
+}
+
+struct PortalSettings {
+    appearance: Arc<Mutex<Appearance>>,
+}
+
+/// A changed appearance value sent to the portal thread
+#[derive(Debug, Clone, Copy)]
+enum PortalUpdate {
+    Accent([f64; 3]),
+    ColorScheme(ColorScheme),
+}
+
+/// Portal value of a color scheme
+fn scheme_value(color_scheme: ColorScheme) -> u32 {
+    match color_scheme {
+        ColorScheme::Dark => 1,
+        ColorScheme::Light => 2,
+    }
+}
+
+impl PortalSettings {
+    /// Every setting in the appearance namespace
+    fn values(&self) -> HashMap<String, OwnedValue> {
+        let mut values = HashMap::new();
+        let (color_scheme, accent) = {
+            let appearance = self.appearance.lock().unwrap();
+            (appearance.color_scheme, appearance.accent)
+        };
+        if let Some(value) = owned(Value::from(scheme_value(color_scheme))) {
+            values.insert("color-scheme".to_string(), value);
+        }
+        if let Some(value) = accent.and_then(|[r, g, b]| owned(Value::from((r, g, b)))) {
+            values.insert("accent-color".to_string(), value);
+        }
+        values
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Settings")]
+impl PortalSettings {
+    /// Settings of all namespaces matching one of `namespaces` (a trailing
+    /// `*` matches a prefix; an empty list matches everything)
+    fn read_all(&self, namespaces: Vec<String>) -> HashMap<String, HashMap<String, OwnedValue>> {
+        let matches = namespaces.is_empty()
+            || namespaces.iter().any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => APPEARANCE.starts_with(prefix),
+                None => pattern == APPEARANCE,
+            });
+
+        let mut result = HashMap::new();
+        if matches {
+            result.insert(APPEARANCE.to_string(), self.values());
+        }
+        result
+    }
+
+    /// A single setting
+    fn read(&self, namespace: &str, key: &str) -> zbus::fdo::Result<OwnedValue> {
+        if namespace != APPEARANCE {
+            return Err(zbus::fdo::Error::UnknownProperty(format!(
+                "unknown namespace '{namespace}'"
+            )));
+        }
+        self.values()
+            .remove(key)
+            .ok_or_else(|| zbus::fdo::Error::UnknownProperty(format!("unknown key '{key}'")))
+    }
+
+    #[zbus(signal)]
+    async fn setting_changed(
+        ctxt: &SignalContext<'_>,
+        namespace: &str,
+        key: &str,
+        value: Value<'_>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn version(&self) -> u32 {
+        1
+    }
+}
+
+/// Handle to the portal thread
+pub struct SettingsPortal {
+    updates: Sender<PortalUpdate>,
+}
+
+impl SettingsPortal {
+    /// Claim the portal bus name and start serving. Without a session bus
+    /// the accent is simply not exported.
+    pub fn start(color_scheme: ColorScheme) -> Self {
+        let (updates, receiver) = mpsc::channel::<PortalUpdate>();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-portal".to_string())
+            .spawn(move || {
+                let appearance = Arc::new(Mutex::new(Appearance {
+                    color_scheme,
+                    accent: None,
+                }));
+                let connection = zbus::blocking::connection::Builder::session()
+                    .and_then(|builder| builder.name(BUS_NAME))
+                    .and_then(|builder| {
+                        builder.serve_at(
+                            OBJECT_PATH,
+                            PortalSettings {
+                                appearance: appearance.clone(),
+                            },
+                        )
+                    })
+                    .and_then(|builder| builder.build());
+                let connection = match connection {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        warn!("Settings portal unavailable: {e}");
+                        return;
+                    }
+                };
+                info!("Settings portal serving as {BUS_NAME}");
+
+                for update in receiver {
+                    let (key, value) = {
+                        let mut appearance = appearance.lock().unwrap();
+                        match update {
+                            PortalUpdate::Accent(accent) => {
+                                appearance.accent = Some(accent);
+                                ("accent-color", Value::from((accent[0], accent[1], accent[2])))
+                            }
+                            PortalUpdate::ColorScheme(color_scheme) => {
+                                appearance.color_scheme = color_scheme;
+                                ("color-scheme", Value::from(scheme_value(color_scheme)))
+                            }
+                        }
+                    };
+                    let iface = connection
+                        .object_server()
+                        .interface::<_, PortalSettings>(OBJECT_PATH);
+                    let result = iface.and_then(|iface| {
+                        zbus::block_on(PortalSettings::setting_changed(
+                            iface.signal_context(),
+                            APPEARANCE,
+                            key,
+                            value,
+                        ))
+                    });
+                    if let Err(e) = result {
+                        warn!("Failed to announce {key}: {e}");
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start settings portal thread: {e}");
+        }
+        Self { updates }
+    }
+
+    /// A portal that never claims the bus name, for the login screen
+    pub fn disabled() -> Self {
+        Self {
+            updates: mpsc::channel().0,
+        }
+    }
+
+    /// Publish a new accent color
+    pub fn set_accent(&self, accent: [f32; 4]) {
+        let _ = self.updates.send(PortalUpdate::Accent([
+            accent[0] as f64,
+            accent[1] as f64,
+            accent[2] as f64,
+        ]));
+    }
+
+    /// Publish a new color scheme
+    pub fn set_color_scheme(&self, color_scheme: ColorScheme) {
+        let _ = self.updates.send(PortalUpdate::ColorScheme(color_scheme));
+    }
+}
+
+fn owned(value: Value<'_>) -> Option<OwnedValue> {
+    value.try_to_owned().ok()
+}
@@ -0,0 +1,326 @@
+// =============================================================================
+// heyDM — Battery Warnings
+//
+// Watches the battery through UPower's DisplayDevice (the combined state of
+// all batteries) and warns when it runs low:
+//
+//     [power]
+//     warn_levels = 20, 10, 5
+//     critical_level = 3
+//     critical_action = suspend
+//
+// Each warn level crossed while discharging shows a notification (critical
+// at the lowest level) and the battery on the on-screen indicator; every
+// level warns once per discharge. At `critical_level` heyDM optionally asks
+// logind to suspend, hibernate or power off.
+//
+// UPower pushes changes with PropertiesChanged, so nothing is polled while
+// it runs; without it the "heydm-power" thread falls back to reading
+// /sys/class/power_supply every BATTERY_INTERVAL. Either way the values go
+// to the panel's update channel (see poller.rs).
+// =============================================================================
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use calloop::channel::Sender;
+use tracing::{info, warn};
+use zbus::blocking::fdo::PropertiesProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::names::InterfaceName;
+
+use crate::poller::{BatteryState, PanelUpdate};
+
+/// Label of the on-screen indicator
+pub const BATTERY_LABEL: &str = "Battery";
+
+/// How often sysfs is read without UPower
+const BATTERY_INTERVAL: Duration = Duration::from_secs(30);
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+/// UPower device states counted as "on AC" (charging, fully charged)
+const UPOWER_CHARGING: u32 = 1;
+const UPOWER_FULL: u32 = 4;
+
+/// What to do when the battery reaches `critical_level`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CriticalAction {
+    None,
+    Suspend,
+    Hibernate,
+    PowerOff,
+}
+
+pub const CRITICAL_ACTIONS: &[&str] = &["none", "suspend", "hibernate", "poweroff"];
+
+impl CriticalAction {
+    /// logind Manager method
+    fn method(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Suspend => Some("Suspend"),
+            Self::Hibernate => Some("Hibernate"),
+            Self::PowerOff => Some("PowerOff"),
+        }
+    }
+}
+
+/// `[power]` settings from the config file
+#[derive(Debug, Clone)]
+pub struct PowerConfig {
+    /// Percentages that warn, highest first
+    pub warn_levels: Vec<i32>,
+    pub critical_level: i32,
+    pub critical_action: CriticalAction,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            warn_levels: vec![20, 10, 5],
+            critical_level: 3,
+            critical_action: CriticalAction::None,
+        }
+    }
+}
+
+impl PowerConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "warn_levels" => {
+                let mut levels = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|level| !level.is_empty())
+                    .map(|level| match level.trim_end_matches('%').parse::<i32>() {
+                        Ok(level) if (1..=100).contains(&level) => Ok(level),
+                        _ => Err(format!("invalid battery level '{level}'")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                levels.sort_unstable_by(|a, b| b.cmp(a));
+                levels.dedup();
+                self.warn_levels = levels;
+            }
+            "critical_level" => {
+                let level: i32 = value
+                    .parse()
+                    .map_err(|_| format!("invalid battery level '{value}'"))?;
+                self.critical_level = level.clamp(1, 50);
+            }
+            "critical_action" => {
+                self.critical_action = match value {
+                    "none" => CriticalAction::None,
+                    "suspend" => CriticalAction::Suspend,
+                    "hibernate" => CriticalAction::Hibernate,
+                    "poweroff" => CriticalAction::PowerOff,
+                    _ => return Err(format!("unknown critical action '{value}'")),
+                };
+            }
+            _ => return Err(format!("unknown power setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// A warning to show, decided by BatteryWarnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatteryWarning {
+    pub percent: i32,
+    /// Crossed the lowest warn level
+    pub critical: bool,
+}
+
+/// Which levels have already warned during the current discharge
+#[derive(Debug, Default)]
+pub struct BatteryWarnings {
+    /// Lowest level warned about
+    warned: Option<i32>,
+    critical_done: bool,
+    /// Id of the warning toast, reused so warnings replace each other
+    pub notification_id: Option<u32>,
+}
+
+impl BatteryWarnings {
+    /// Feed a new battery state; returns a warning when a level was crossed
+    pub fn update(&mut self, config: &PowerConfig, battery: BatteryState) -> Option<BatteryWarning> {
+        if battery.percent < 0 || battery.charging {
+            self.warned = None;
+            self.critical_done = false;
+            return None;
+        }
+        let level = config
+            .warn_levels
+            .iter()
+            .copied()
+            .filter(|&level| battery.percent <= level)
+            .min()?;
+        if self.warned.is_some_and(|warned| warned <= level) {
+            return None;
+        }
+        self.warned = Some(level);
+        Some(BatteryWarning {
+            percent: battery.percent,
+            critical: config.warn_levels.last() == Some(&level),
+        })
+    }
+
+    /// Run the critical action once per discharge when the battery is at or
+    /// below `critical_level`
+    pub fn check_critical(&mut self, config: &PowerConfig, battery: BatteryState) {
+        if self.critical_done
+            || battery.percent < 0
+            || battery.charging
+            || battery.percent > config.critical_level
+        {
+            return;
+        }
+        self.critical_done = true;
+        let Some(method) = config.critical_action.method() else {
+            return;
+        };
+        warn!("Battery at {}%, asking logind to {method}", battery.percent);
+        let spawned = std::thread::Builder::new()
+            .name("heydm-power-action".to_string())
+            .spawn(move || {
+                if let Err(e) = logind_call(method) {
+                    warn!("logind {method} failed: {e}");
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start power action thread: {e}");
+        }
+    }
+}
+
+/// Start watching the battery; updates go to `updates` until the receiver
+/// is gone
+pub fn start(updates: Sender<PanelUpdate>) {
+    let spawned = std::thread::Builder::new()
+        .name("heydm-power".to_string())
+        .spawn(move || {
+            match watch_upower(&updates) {
+                Ok(()) => return,
+                Err(e) => info!("UPower unavailable ({e}), polling the battery in sysfs"),
+            }
+            let mut last = None;
+            loop {
+                let battery = read_sysfs();
+                if last != Some(battery) {
+                    last = Some(battery);
+                    if updates.send(PanelUpdate::Battery(battery)).is_err() {
+                        return;
+                    }
+                }
+                std::thread::sleep(BATTERY_INTERVAL);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start battery watcher: {e}");
+    }
+}
+
+/// Send the DisplayDevice state and again on every change. Returns Ok when
+/// the receiver is gone, Err when UPower cannot be used.
+fn watch_upower(updates: &Sender<PanelUpdate>) -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    // Read fresh on every signal rather than racing the proxy's own cache
+    let device: Proxy<'_> = zbus::blocking::proxy::Builder::new(&connection)
+        .destination(UPOWER)?
+        .path(DISPLAY_DEVICE)?
+        .interface(DEVICE_INTERFACE)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()?;
+    let properties = PropertiesProxy::builder(&connection)
+        .destination(UPOWER)?
+        .path(DISPLAY_DEVICE)?
+        .build()?;
+    let changes = properties.receive_properties_changed()?;
+    let interface = InterfaceName::from_static_str(DEVICE_INTERFACE)?;
+
+    let mut last = read_upower(&device)?;
+    info!("Watching the battery through UPower");
+    if updates.send(PanelUpdate::Battery(last)).is_err() {
+        return Ok(());
+    }
+    for change in changes {
+        let Ok(args) = change.args() else {
+            continue;
+        };
+        if args.interface_name() != &interface {
+            continue;
+        }
+        let battery = match read_upower(&device) {
+            Ok(battery) => battery,
+            Err(e) => {
+                warn!("Reading the battery from UPower failed: {e}");
+                continue;
+            }
+        };
+        if battery == last {
+            continue;
+        }
+        last = battery;
+        if updates.send(PanelUpdate::Battery(battery)).is_err() {
+            return Ok(());
+        }
+    }
+    Err(zbus::Error::Failure("UPower signal stream ended".to_string()))
+}
+
+fn read_upower(device: &Proxy<'_>) -> zbus::Result<BatteryState> {
+    let present: bool = device.get_property("IsPresent")?;
+    if !present {
+        return Ok(BatteryState {
+            percent: -1,
+            charging: false,
+        });
+    }
+    let percentage: f64 = device.get_property("Percentage")?;
+    let state: u32 = device.get_property("State")?;
+    Ok(BatteryState {
+        percent: (percentage.round() as i32).clamp(0, 100),
+        charging: state == UPOWER_CHARGING || state == UPOWER_FULL,
+    })
+}
+
+/// Battery status from /sys/class/power_supply/BAT0 (or BAT1)
+fn read_sysfs() -> BatteryState {
+    let path = ["/sys/class/power_supply/BAT0", "/sys/class/power_supply/BAT1"]
+        .into_iter()
+        .map(Path::new)
+        .find(|path| path.exists());
+    let Some(path) = path else {
+        // No battery found (desktop/VM)
+        return BatteryState {
+            percent: -1,
+            charging: false,
+        };
+    };
+
+    let percent = fs::read_to_string(path.join("capacity"))
+        .ok()
+        .and_then(|capacity| capacity.trim().parse::<i32>().ok())
+        .map_or(-1, |capacity| capacity.clamp(0, 100));
+    let charging = fs::read_to_string(path.join("status")).is_ok_and(|status| {
+        let status = status.trim().to_lowercase();
+        status == "charging" || status == "full"
+    });
+    BatteryState { percent, charging }
+}
+
+/// Suspend / Hibernate / PowerOff on the logind manager, non-interactive
+fn logind_call(method: &str) -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    let manager = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    manager.call::<_, _, ()>(method, &(false,))
+}
@@ -0,0 +1,199 @@
+// =============================================================================
+// heyDM — Layout Presets
+//
+// Named window arrangements from the config file:
+//
+//     [preset "coding"]
+//     workspace = 2                        # default: the active workspace
+//     key = 1                              # Super+Ctrl+1 applies it
+//     Alacritty = 0 0 60 100 alacritty
+//     firefox   = 60 0 40 100 firefox
+//
+// Every other key is an app id; its value is the window's x, y, width and
+// height in percent of the work area, optionally followed by the command
+// that starts the app. Applying a preset (keybinding or `preset <name>` over
+// IPC) moves matching windows to the workspace and places them; apps without
+// a window are launched and placed once their first window maps.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use crate::state::HeyDM;
+use crate::workspace::WORKSPACE_COUNT;
+
+/// How long a launched app has to map its window to still be placed
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One window of a preset
+#[derive(Debug, Clone)]
+pub struct PresetWindow {
+    pub app_id: String,
+    /// x, y, width and height as fractions of the work area
+    pub area: [f64; 4],
+    /// Command that starts the app when it has no window
+    pub command: Option<String>,
+}
+
+/// A `[preset "name"]` section
+#[derive(Debug, Clone, Default)]
+pub struct LayoutPreset {
+    pub workspace: Option<usize>,
+    /// Digit of the Super+Ctrl binding
+    pub key: Option<usize>,
+    pub windows: Vec<PresetWindow>,
+}
+
+impl LayoutPreset {
+    /// Set `workspace` or `key` from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let number = || {
+            value
+                .parse::<usize>()
+                .ok()
+                .filter(|n| (1..=WORKSPACE_COUNT).contains(n))
+                .ok_or_else(|| format!("expected 1-{WORKSPACE_COUNT}, got '{value}'"))
+        };
+        match key {
+            "workspace" => self.workspace = Some(number()?),
+            "key" => self.key = Some(number()?),
+            _ => return Err(format!("unknown preset setting '{key}'")),
+        }
+        Ok(())
+    }
+
+    /// Add a `<app id> = <x> <y> <w> <h> [command]` line
+    pub fn add_window(&mut self, app_id: &str, value: &str) -> Result<(), String> {
+        let mut fields = value.split_whitespace();
+        let mut area = [0.0; 4];
+        for slot in &mut area {
+            let field = fields.next().ok_or_else(|| {
+                format!("expected 'x y width height [command]' in percent, got '{value}'")
+            })?;
+            let percent: f64 = field
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| format!("invalid percentage '{field}'"))?;
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(format!("{percent} is out of range (0..=100)"));
+            }
+            *slot = percent / 100.0;
+        }
+        if area[2] == 0.0 || area[3] == 0.0 {
+            return Err("width and height must not be 0".to_string());
+        }
+
+        let command = fields.collect::<Vec<_>>().join(" ");
+        self.windows.push(PresetWindow {
+            app_id: app_id.to_string(),
+            area,
+            command: (!command.is_empty()).then_some(command),
+        });
+        Ok(())
+    }
+}
+
+/// A preset window waiting for its launched app to map
+#[derive(Debug)]
+struct Pending {
+    app_id: String,
+    workspace: usize,
+    area: [f64; 4],
+    expires: Instant,
+}
+
+/// Placements waiting for launched apps
+#[derive(Debug, Default)]
+pub struct PendingPlacements {
+    pending: Vec<Pending>,
+}
+
+/// Apply a preset by name
+pub fn apply(state: &mut HeyDM, name: &str) -> Result<(), String> {
+    let preset = state
+        .config
+        .presets
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("no preset named '{name}'"))?;
+    info!("Applying layout preset '{name}'");
+
+    let ws = preset
+        .workspace
+        .unwrap_or_else(|| state.window_manager.workspaces().active_workspace());
+    let mut placed = Vec::new();
+    for slot in &preset.windows {
+        let existing = state
+            .window_manager
+            .windows()
+            .iter()
+            .find(|w| {
+                !placed.contains(&w.id())
+                    && w.title_and_app_id().1.as_deref() == Some(slot.app_id.as_str())
+            })
+            .map(|w| w.id());
+
+        match (existing, &slot.command) {
+            (Some(id), _) => {
+                state
+                    .window_manager
+                    .place_window(id, ws, slot.area, &state.output_size);
+                placed.push(id);
+            }
+            (None, Some(command)) => {
+                state.launch(command);
+                state.pending_placements.pending.push(Pending {
+                    app_id: slot.app_id.clone(),
+                    workspace: ws,
+                    area: slot.area,
+                    expires: Instant::now() + LAUNCH_TIMEOUT,
+                });
+            }
+            (None, None) => debug!("Preset '{name}': no window for '{}'", slot.app_id),
+        }
+    }
+
+    state.window_manager.switch_workspace(ws);
+    state.refresh_keyboard_focus();
+    Ok(())
+}
+
+/// Apply the preset bound to Super+Ctrl+<key>
+pub fn apply_key(state: &mut HeyDM, key: usize) {
+    let name = state
+        .config
+        .presets
+        .iter()
+        .find(|(_, preset)| preset.key == Some(key))
+        .map(|(name, _)| name.clone());
+    if let Some(name) = name {
+        let _ = apply(state, &name);
+    }
+}
+
+/// A window mapped: place it if a preset launched its app
+pub fn window_mapped(state: &mut HeyDM, id: u32) {
+    let pending = &mut state.pending_placements.pending;
+    if pending.is_empty() {
+        return;
+    }
+    let now = Instant::now();
+    pending.retain(|p| p.expires > now);
+
+    let Some(app_id) = state
+        .window_manager
+        .window_by_id(id)
+        .and_then(|w| w.title_and_app_id().1)
+    else {
+        return;
+    };
+    let Some(idx) = pending.iter().position(|p| p.app_id == app_id) else {
+        return;
+    };
+    let placement = pending.remove(idx);
+    state
+        .window_manager
+        .place_window(id, placement.workspace, placement.area, &state.output_size);
+    state.refresh_keyboard_focus();
+}
@@ -0,0 +1,250 @@
+// =============================================================================
+// heyDM — Power Profiles
+//
+// The panel's power profile module shows the active power-profiles-daemon
+// profile (performance, balanced or power-saver); clicking it switches to
+// the next one the machine offers. Changes made elsewhere (GNOME settings,
+// `powerprofilesctl`) show up through PropertiesChanged.
+//
+// Without power-profiles-daemon the module shows the CPU frequency governor
+// from /sys/devices/system/cpu/cpu0/cpufreq instead, read-only: switching
+// the governor needs root. The daemon is reached under its current name
+// (org.freedesktop.UPower.PowerProfiles) or the old one (net.hadess).
+//
+// D-Bus runs on the "heydm-profiles" thread; states go to the panel's
+// update channel (see poller.rs).
+// =============================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use tracing::{info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+use crate::poller::PanelUpdate;
+
+/// power-profiles-daemon's bus names, paths and interfaces, newest first
+const DAEMONS: &[(&str, &str)] = &[
+    ("org.freedesktop.UPower.PowerProfiles", "/org/freedesktop/UPower/PowerProfiles"),
+    ("net.hadess.PowerProfiles", "/net/hadess/PowerProfiles"),
+];
+
+const GOVERNOR_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq/scaling_governor";
+
+/// How often the governor is read without the daemon
+const GOVERNOR_INTERVAL: Duration = Duration::from_secs(10);
+
+/// What the power profile module shows
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileState {
+    /// Active profile, or the CPU governor; empty when neither is known
+    pub active: String,
+    /// Whether clicking switches profiles (power-profiles-daemon runs)
+    pub switchable: bool,
+}
+
+impl ProfileState {
+    /// Short label for the panel
+    pub fn label(&self) -> &str {
+        match self.active.as_str() {
+            "performance" => "Perf",
+            "balanced" => "Balanced",
+            "power-saver" => "Saver",
+            other => other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ProfileCommand {
+    /// Switch to the next profile
+    Cycle,
+    /// The daemon signalled a change
+    Changed,
+}
+
+/// Handle to the power profile thread
+pub struct PowerProfiles {
+    commands: Sender<ProfileCommand>,
+}
+
+impl PowerProfiles {
+    /// Start following the active profile; states go to `updates`
+    pub fn start(updates: calloop::channel::Sender<PanelUpdate>) -> Self {
+        let (commands, receiver) = mpsc::channel::<ProfileCommand>();
+        let worker_commands = commands.clone();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-profiles".to_string())
+            .spawn(move || match Daemon::connect(worker_commands) {
+                Ok(daemon) => {
+                    info!("Following power-profiles-daemon");
+                    daemon.run(receiver, updates);
+                }
+                Err(e) => {
+                    info!("power-profiles-daemon unavailable ({e}), showing the CPU governor");
+                    poll_governor(updates);
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start power profile thread: {e}");
+        }
+        Self { commands }
+    }
+
+    /// Without following any profile (greeter mode)
+    pub fn disabled() -> Self {
+        Self {
+            commands: mpsc::channel().0,
+        }
+    }
+
+    /// Switch to the next power profile
+    pub fn cycle(&self) {
+        let _ = self.commands.send(ProfileCommand::Cycle);
+    }
+}
+
+struct Daemon {
+    proxy: Proxy<'static>,
+}
+
+impl Daemon {
+    fn connect(commands: Sender<ProfileCommand>) -> zbus::Result<Self> {
+        let connection = Connection::system()?;
+        let mut last_error = None;
+        for (name, path) in DAEMONS {
+            let proxy = zbus::blocking::proxy::Builder::new(&connection)
+                .destination(*name)?
+                .path(*path)?
+                .interface(*name)?
+                .cache_properties(zbus::proxy::CacheProperties::No)
+                .build()?;
+            // Fails right away when this name is not on the bus
+            match proxy.get_property::<String>("ActiveProfile") {
+                Ok(_) => {
+                    watch_changes(&connection, path, commands);
+                    return Ok(Self { proxy });
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| zbus::Error::Failure("no daemon".to_string())))
+    }
+
+    fn run(self, commands: Receiver<ProfileCommand>, updates: calloop::channel::Sender<PanelUpdate>) {
+        let mut last = None;
+        let mut changed = true;
+        loop {
+            if changed {
+                let state = ProfileState {
+                    active: self.proxy.get_property("ActiveProfile").unwrap_or_default(),
+                    switchable: true,
+                };
+                if last.as_ref() != Some(&state) {
+                    last = Some(state.clone());
+                    if updates.send(PanelUpdate::PowerProfile(state)).is_err() {
+                        return;
+                    }
+                }
+            }
+            let Ok(command) = commands.recv() else {
+                return;
+            };
+            changed = match command {
+                ProfileCommand::Changed => true,
+                ProfileCommand::Cycle => {
+                    let active = last.as_ref().map(|state| state.active.as_str()).unwrap_or("");
+                    self.cycle(active);
+                    true
+                }
+            };
+        }
+    }
+
+    /// Set the profile after `active` in the daemon's list
+    fn cycle(&self, active: &str) {
+        let profiles = match self.profiles() {
+            Ok(profiles) if !profiles.is_empty() => profiles,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Reading power profiles failed: {e}");
+                return;
+            }
+        };
+        let next = profiles
+            .iter()
+            .position(|profile| profile == active)
+            .map_or(0, |i| (i + 1) % profiles.len());
+        let profile = profiles[next].as_str();
+        info!("Switching to the {profile} power profile");
+        if let Err(e) = self.proxy.set_property("ActiveProfile", profile) {
+            warn!("Switching power profiles failed: {e}");
+        }
+    }
+
+    /// Names of the offered profiles, in the daemon's order
+    fn profiles(&self) -> zbus::Result<Vec<String>> {
+        let profiles: Vec<HashMap<String, OwnedValue>> = self.proxy.get_property("Profiles")?;
+        Ok(profiles
+            .into_iter()
+            .filter_map(|mut profile| String::try_from(profile.remove("Profile")?).ok())
+            .collect())
+    }
+}
+
+/// Tell the profile thread whenever the daemon's properties change
+fn watch_changes(connection: &Connection, path: &'static str, commands: Sender<ProfileCommand>) {
+    let connection = connection.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-profiles-events".to_string())
+        .spawn(move || {
+            let rule = zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface("org.freedesktop.DBus.Properties")
+                .and_then(|builder| builder.path(path))
+                .map(|builder| builder.build());
+            let messages = rule.and_then(|rule| {
+                zbus::blocking::MessageIterator::for_match_rule(rule, &connection, None)
+            });
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Power profiles: cannot follow changes: {e}");
+                    return;
+                }
+            };
+            for _ in messages.flatten() {
+                if commands.send(ProfileCommand::Changed).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start power profile signal thread: {e}");
+    }
+}
+
+/// Without the daemon: send the CPU governor every GOVERNOR_INTERVAL while
+/// it changes; nothing at all without cpufreq
+fn poll_governor(updates: calloop::channel::Sender<PanelUpdate>) {
+    let mut last = None;
+    loop {
+        let Ok(governor) = fs::read_to_string(GOVERNOR_PATH) else {
+            return;
+        };
+        let state = ProfileState {
+            active: governor.trim().to_string(),
+            switchable: false,
+        };
+        if last.as_ref() != Some(&state) {
+            last = Some(state.clone());
+            if updates.send(PanelUpdate::PowerProfile(state)).is_err() {
+                return;
+            }
+        }
+        std::thread::sleep(GOVERNOR_INTERVAL);
+    }
+}
@@ -0,0 +1,150 @@
+// =============================================================================
+// heyDM — Protocol Validation
+//
+// Checks each toplevel commit for state the xdg-shell protocol forbids and
+// posts the matching protocol error, which disconnects the client:
+//
+//   - a buffer attached before the first configure was acked
+//     (xdg_surface.not_constructed, posted through smithay)
+//   - a minimum size larger than the maximum size
+//     (xdg_toplevel.invalid_size)
+//
+// Some shipping apps get this wrong and work fine otherwise. Their app ids
+// can be listed in the config, in which case violations are only logged
+// (once per window) and the commit is handled as usual:
+//
+//     [compat]
+//     tolerate_protocol_errors = org.example.Broken, legacy-app
+// =============================================================================
+
+use std::collections::HashSet;
+
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
+use smithay::reexports::wayland_server::backend::ObjectId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::Resource;
+use smithay::wayland::compositor::{with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::shell::xdg::{SurfaceCachedState, XdgToplevelSurfaceData};
+use tracing::warn;
+
+use crate::window::WindowElement;
+
+/// Settings from the `[compat]` section
+#[derive(Debug, Clone, Default)]
+pub struct CompatConfig {
+    /// App ids whose protocol violations are logged instead of posted
+    pub tolerate_protocol_errors: Vec<String>,
+}
+
+impl CompatConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "tolerate_protocol_errors" => {
+                self.tolerate_protocol_errors = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|app_id| !app_id.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            _ => return Err(format!("unknown compat setting '{key}'")),
+        }
+        Ok(())
+    }
+
+    fn tolerates(&self, app_id: Option<&str>) -> bool {
+        app_id.is_some_and(|app_id| self.tolerate_protocol_errors.iter().any(|id| id == app_id))
+    }
+}
+
+/// A protocol violation found in a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Violation {
+    /// Buffer committed before the initial configure was acked
+    UnconfiguredBuffer,
+    /// min_size exceeds max_size on some axis
+    InvalidSizeHints,
+}
+
+impl Violation {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::UnconfiguredBuffer => "buffer attached before the initial configure was acked",
+            Self::InvalidSizeHints => "minimum size is larger than the maximum size",
+        }
+    }
+}
+
+/// Validates toplevel commits and remembers which tolerated violations have
+/// already been logged
+#[derive(Debug, Default)]
+pub struct ProtocolChecker {
+    warned: HashSet<(ObjectId, Violation)>,
+}
+
+impl ProtocolChecker {
+    /// Check a toplevel's committed state. Returns false if a protocol error
+    /// was posted and the commit should not be processed further.
+    pub fn check_commit(&mut self, window: &WindowElement, compat: &CompatConfig) -> bool {
+        let toplevel = window.toplevel();
+        let Some(violation) = find_violation(toplevel.wl_surface()) else {
+            return true;
+        };
+
+        let (_, app_id) = window.title_and_app_id();
+        let name = app_id.as_deref().unwrap_or("<no app id>");
+        if compat.tolerates(app_id.as_deref()) {
+            let key = (toplevel.wl_surface().id(), violation);
+            if self.warned.insert(key) {
+                warn!("Tolerating protocol violation from '{name}': {}", violation.describe());
+            }
+            return true;
+        }
+
+        warn!("Protocol violation from '{name}': {}", violation.describe());
+        match violation {
+            Violation::UnconfiguredBuffer => {
+                // Posts the error on the xdg_surface, which only smithay holds
+                toplevel.ensure_configured();
+            }
+            Violation::InvalidSizeHints => {
+                toplevel
+                    .xdg_toplevel()
+                    .post_error(xdg_toplevel::Error::InvalidSize, violation.describe());
+            }
+        }
+        false
+    }
+
+    /// Forget a destroyed surface
+    pub fn forget(&mut self, surface: &WlSurface) {
+        let id = surface.id();
+        self.warned.retain(|(surface, _)| *surface != id);
+    }
+}
+
+fn find_violation(surface: &WlSurface) -> Option<Violation> {
+    with_states(surface, |states| {
+        let configured = states
+            .data_map
+            .get::<XdgToplevelSurfaceData>()
+            .is_some_and(|data| data.lock().unwrap().configured);
+        let has_buffer = matches!(
+            states.cached_state.get::<SurfaceAttributes>().current().buffer,
+            Some(BufferAssignment::NewBuffer(_))
+        );
+        if has_buffer && !configured {
+            return Some(Violation::UnconfiguredBuffer);
+        }
+
+        let mut cached = states.cached_state.get::<SurfaceCachedState>();
+        let current = cached.current();
+        let (min, max) = (current.min_size, current.max_size);
+        let exceeds = |min: i32, max: i32| max > 0 && min > max;
+        if exceeds(min.w, max.w) || exceeds(min.h, max.h) {
+            return Some(Violation::InvalidSizeHints);
+        }
+        None
+    })
+}
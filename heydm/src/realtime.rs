@@ -0,0 +1,163 @@
+// =============================================================================
+// heyDM — Realtime Scheduling
+//
+// Optionally runs the compositor thread (input, rendering, client requests)
+// with the SCHED_RR realtime policy, so a busy desktop cannot delay frames
+// or input:
+//
+//     [performance]
+//     realtime = true
+//     priority = 10
+//
+// heyDM first sets the policy itself, which needs CAP_SYS_NICE or an
+// RLIMIT_RTPRIO; otherwise it asks RealtimeKit (rtkit-daemon) over the
+// system bus. The request to rtkit runs on a short-lived thread, so a
+// stalled D-Bus never holds up startup.
+//
+// SCHED_RESET_ON_FORK keeps the policy off every thread and process created
+// afterwards. That matters: the blocking work — D-Bus (portal.rs), sysfs
+// polling (poller.rs), icon and wallpaper decoding, PAM — lives on worker
+// threads that must never compete with the compositor thread, and launched
+// apps must not inherit realtime priority.
+// =============================================================================
+
+use std::time::Duration;
+
+use tracing::{info, warn};
+use zbus::blocking::{Connection, Proxy};
+
+/// Highest priority accepted in the config; rtkit allows less by default
+pub const MAX_PRIORITY: i32 = 99;
+
+/// CPU time in µs the thread may use without blocking before the kernel
+/// sends SIGXCPU; rtkit refuses threads without such a limit
+const RTTIME_LIMIT_US: u64 = 200_000;
+
+/// How long to wait for rtkit before giving up
+const RTKIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `[performance]` settings from the config file
+#[derive(Debug, Clone)]
+pub struct PerformanceConfig {
+    /// Run the compositor thread with SCHED_RR
+    pub realtime: bool,
+    /// SCHED_RR priority, 1..=MAX_PRIORITY
+    pub priority: i32,
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            realtime: false,
+            priority: 10,
+        }
+    }
+}
+
+impl PerformanceConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "realtime" => {
+                self.realtime = match value {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("expected true or false, got '{value}'")),
+                }
+            }
+            "priority" => {
+                let priority: i32 = value
+                    .parse()
+                    .map_err(|_| format!("invalid priority '{value}'"))?;
+                self.priority = priority.clamp(1, MAX_PRIORITY);
+            }
+            _ => return Err(format!("unknown performance setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// Make the calling thread realtime if the config asks for it. Must be
+/// called on the compositor thread.
+pub fn setup(config: &PerformanceConfig) {
+    if !config.realtime {
+        return;
+    }
+    match set_scheduler(config.priority) {
+        Ok(()) => info!("Compositor thread runs with SCHED_RR priority {}", config.priority),
+        Err(e) => {
+            info!("Cannot set SCHED_RR directly ({e}), asking RealtimeKit");
+            request_from_rtkit(config.priority);
+        }
+    }
+}
+
+/// sched_setscheduler on the calling thread
+fn set_scheduler(priority: i32) -> std::io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    let policy = libc::SCHED_RR | libc::SCHED_RESET_ON_FORK;
+    // SAFETY: `param` is a valid sched_param; pid 0 is the calling thread
+    if unsafe { libc::sched_setscheduler(0, policy, &param) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Ask rtkit to make the calling thread realtime, from a helper thread
+fn request_from_rtkit(priority: i32) {
+    // SAFETY: gettid has no preconditions
+    let thread = unsafe { libc::gettid() } as u64;
+    if let Err(e) = limit_rttime() {
+        warn!("Cannot set RLIMIT_RTTIME for rtkit: {e}");
+        return;
+    }
+
+    let spawned = std::thread::Builder::new()
+        .name("heydm-rtkit".to_string())
+        .spawn(move || match rtkit_make_realtime(thread, priority) {
+            Ok(granted) => info!("RealtimeKit: compositor thread runs with SCHED_RR priority {granted}"),
+            Err(e) => warn!("RealtimeKit refused realtime scheduling: {e}"),
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start rtkit thread: {e}");
+    }
+}
+
+/// rtkit only hands out realtime scheduling to threads with a CPU time limit
+fn limit_rttime() -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: RTTIME_LIMIT_US,
+        rlim_max: RTTIME_LIMIT_US,
+    };
+    // SAFETY: `limit` is a valid rlimit
+    if unsafe { libc::setrlimit(libc::RLIMIT_RTTIME, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// MakeThreadRealtime on org.freedesktop.RealtimeKit1, clamped to what
+/// rtkit allows. Returns the granted priority.
+fn rtkit_make_realtime(thread: u64, priority: i32) -> zbus::Result<i32> {
+    let connection = zbus::blocking::connection::Builder::system()?
+        .method_timeout(RTKIT_TIMEOUT)
+        .build()?;
+    let rtkit = rtkit_proxy(&connection)?;
+    let max: i32 = rtkit.get_property("MaxRealtimePriority")?;
+    let priority = priority.min(max).max(1);
+    rtkit.call::<_, _, ()>("MakeThreadRealtime", &(thread, priority as u32))?;
+    Ok(priority)
+}
+
+fn rtkit_proxy(connection: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        connection,
+        "org.freedesktop.RealtimeKit1",
+        "/org/freedesktop/RealtimeKit1",
+        "org.freedesktop.RealtimeKit1",
+    )
+}
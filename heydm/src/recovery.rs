@@ -0,0 +1,96 @@
+// =============================================================================
+// heyDM — Panic Recovery
+//
+// A bug in one input event or one client request should not take the whole
+// session and every app down with it. Input handling and Wayland request
+// dispatch run through `guard`, which catches a panic, logs it, puts the
+// state back into a consistent shape where that is safe:
+//
+//   - a window move/resize grab in progress is dropped
+//   - smithay's pointer and keyboard grabs are released
+//   - keyboard focus is recomputed from the window manager
+//
+// and carries on with the next event. The panic hook logs every panic
+// (also on worker threads) with a backtrace through tracing, so it ends up
+// in the journal next to the surrounding log lines.
+//
+// Panics that keep coming are not recoverable: after MAX_PANICS within
+// PANIC_WINDOW the panic is let through and the session ends as before.
+// =============================================================================
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use smithay::utils::SERIAL_COUNTER;
+use tracing::{error, warn};
+
+use crate::state::HeyDM;
+
+/// Panics tolerated within PANIC_WINDOW before giving up
+const MAX_PANICS: usize = 5;
+const PANIC_WINDOW: Duration = Duration::from_secs(60);
+
+/// Recent caught panics
+#[derive(Debug, Default)]
+pub struct Recovery {
+    panics: VecDeque<Instant>,
+}
+
+/// Log panics with their location and a backtrace
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let thread = std::thread::current();
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        error!(
+            "Panic in thread '{}' at {location}: {message}\n{}",
+            thread.name().unwrap_or("unnamed"),
+            Backtrace::force_capture()
+        );
+    }));
+}
+
+/// Run `f`, surviving a panic in it. Returns None when it panicked.
+pub fn guard<R>(state: &mut HeyDM, what: &str, f: impl FnOnce(&mut HeyDM) -> R) -> Option<R> {
+    let payload = match panic::catch_unwind(AssertUnwindSafe(|| f(state))) {
+        Ok(result) => return Some(result),
+        Err(payload) => payload,
+    };
+
+    let now = Instant::now();
+    let panics = &mut state.recovery.panics;
+    panics.retain(|at| now.duration_since(*at) < PANIC_WINDOW);
+    panics.push_back(now);
+    if panics.len() > MAX_PANICS {
+        error!("{} panics within {PANIC_WINDOW:?}, giving up", panics.len());
+        panic::resume_unwind(payload);
+    }
+    warn!("Recovered from a panic in {what}");
+    repair(state);
+    None
+}
+
+/// Drop whatever interaction the panic may have left half done
+fn repair(state: &mut HeyDM) {
+    if state.window_manager.has_grab() {
+        state.window_manager.end_grab();
+    }
+    let time = Duration::from(state.clock.now()).as_millis() as u32;
+    if let Some(pointer) = state.seat.get_pointer() {
+        pointer.unset_grab(state, SERIAL_COUNTER.next_serial(), time);
+    }
+    if let Some(keyboard) = state.seat.get_keyboard() {
+        keyboard.unset_grab(state);
+    }
+    state.refresh_keyboard_focus();
+}
@@ -1,15 +1,34 @@
 // =============================================================================
 // heyDM — Renderer
 //
-// Renders the desktop: background, windows, panel, launcher, cursor.
-// Uses a GlesFrame obtained from the winit/DRM backend's render surface.
+// Renders the desktop: background (wallpaper), windows, panel, launcher, task manager,
+// cursor.
+//
+// Drawing happens in two steps:
+//   1. build_scene() walks the compositor state and records a Scene — an
+//      ordered list of rectangles and text labels.
+//   2. draw_scene() replays that list into a frame obtained from the
+//      winit/DRM backend's render surface.
+// Recording first lets the backend upload text textures (which needs the
+// renderer) before the frame borrows it.
 // =============================================================================
 
+use chrono::Local;
 use smithay::backend::renderer::Frame;
-use smithay::output::Output;
-use smithay::utils::{Physical, Rectangle, Size};
+use smithay::input::pointer::CursorIcon;
+use smithay::utils::{Physical, Rectangle, Size, Transform};
 
+use crate::icons::ICON_SIZE;
+use crate::launcher::{LauncherLayout, QueryMode};
+use crate::network::signal_bars;
+use crate::notifications::{action_button_rect, close_button_rect};
+use crate::tray::{MAX_TRAY_ICONS, TRAY_ICON_SIZE};
 use crate::state::HeyDM;
+use crate::switcher::{APP_ID_CHARS, TITLE_CHARS};
+use crate::taskman::{format_memory, SortColumn};
+use crate::text::TextKey;
+use crate::texture::{CachedTexture, Image, TextureCache};
+use crate::theme::UiMetrics;
 
 /// Color constants for the heyOS desktop theme (End-4 inspired)
 pub mod colors {
@@ -18,137 +37,1447 @@ pub mod colors {
     pub const ACCENT_CRIMSON: [f32; 4]     = [0.83, 0.23, 0.28, 1.0];
     pub const ACCENT_CYAN: [f32; 4]        = [0.29, 0.70, 0.83, 1.0];
     pub const LAUNCHER_BG: [f32; 4]        = [0.06, 0.06, 0.09, 0.98];
-    pub const BORDER_FOCUSED: [f32; 4]     = [0.83, 0.23, 0.28, 1.0]; // Crimson
     pub const BORDER_UNFOCUSED: [f32; 4]   = [0.15, 0.15, 0.20, 0.60];
+    pub const TEXT_PRIMARY: [f32; 4]       = [0.92, 0.92, 0.95, 1.0];
+    pub const TEXT_SECONDARY: [f32; 4]     = [0.60, 0.60, 0.68, 1.0];
 }
 
-pub const PANEL_HEIGHT: i32 = 44;
-pub const PANEL_MARGIN: i32 = 10;
-pub const BORDER_WIDTH: i32 = 3;
-
 /// Build a Rectangle from (x, y, w, h)
 fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Physical> {
     Rectangle::new((x, y).into(), (w, h).into())
 }
 
+/// The first `max_chars` characters of `text`, without copying it
+fn truncate(text: &str, max_chars: usize) -> &str {
+    text.char_indices().nth(max_chars).map_or(text, |(end, _)| &text[..end])
+}
+
+/// Hit area of a workspace indicator in the panel (the dot is centered inside)
+pub fn workspace_indicator_rect(ws: usize, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let slot = ui.px(16);
+    rect(
+        ui.panel_margin() + ui.px(96) + (ws as i32 - 1) * slot,
+        ui.panel_margin(),
+        slot,
+        ui.panel_height(),
+    )
+}
+
+/// The clock at the right end of the status area; clicking it opens the
+/// calendar
+pub fn clock_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(140);
+    rect(
+        output_size.w - ui.panel_margin() - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The network module, left of the brightness / battery / clock status
+/// area; clicking it opens the Wi-Fi menu
+pub fn network_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(150);
+    rect(
+        output_size.w - ui.panel_margin() - ui.px(280) - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The Bluetooth module, left of the network; clicking it opens the
+/// Bluetooth menu
+pub fn bluetooth_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(40);
+    rect(
+        network_rect(output_size, ui).loc.x - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The volume module, left of Bluetooth
+pub fn volume_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(88);
+    rect(
+        bluetooth_rect(output_size, ui).loc.x - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The power profile module, left of the volume
+pub fn power_profile_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(80);
+    rect(
+        volume_rect(output_size, ui).loc.x - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The do-not-disturb toggle, left of the power profile
+pub fn dnd_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(48);
+    rect(
+        power_profile_rect(output_size, ui).loc.x - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// Slot of tray icon `index`, filled from the DND toggle leftwards
+pub fn tray_icon_rect(index: usize, output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let slot = ui.px(TRAY_ICON_SIZE + 8);
+    rect(
+        dnd_rect(output_size, ui).loc.x - (index as i32 + 1) * slot,
+        ui.panel_margin(),
+        slot,
+        ui.panel_height(),
+    )
+}
+
+/// Where the panel shows the focused window's title, left of the tray
+pub fn focused_title_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(200);
+    rect(
+        tray_icon_rect(MAX_TRAY_ICONS - 1, output_size, ui).loc.x - ui.px(8) - width,
+        ui.panel_margin(),
+        width,
+        ui.panel_height(),
+    )
+}
+
+/// The media module in the middle of the panel: previous, play/pause and
+/// next buttons, then the track
+pub fn media_rect(output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let width = ui.px(320);
+    rect((output_size.w - width) / 2, ui.panel_margin(), width, ui.panel_height())
+}
+
+/// Button `index` (previous, play/pause, next) of the media module
+pub fn media_button_rect(index: usize, output_size: Size<i32, Physical>, ui: &UiMetrics) -> Rectangle<i32, Physical> {
+    let module = media_rect(output_size, ui);
+    let slot = ui.px(24);
+    rect(module.loc.x + index as i32 * slot, module.loc.y, slot, module.size.h)
+}
+
+/// A taskbar button in the panel, between the workspace indicators and the
+/// status area (or the media module, while it is shown); None when it does
+/// not fit
+pub fn taskbar_button_rect(
+    index: usize,
+    count: usize,
+    media: bool,
+    output_size: Size<i32, Physical>,
+    ui: &UiMetrics,
+) -> Option<Rectangle<i32, Physical>> {
+    let start = workspace_indicator_rect(crate::workspace::WORKSPACE_COUNT, ui).loc.x + ui.px(32);
+    let end = if media {
+        media_rect(output_size, ui).loc.x - ui.px(8)
+    } else {
+        focused_title_rect(output_size, ui).loc.x - ui.px(8)
+    };
+    let width = ((end - start) / count.max(1) as i32).min(ui.px(180));
+    if width < ui.px(40) {
+        return None;
+    }
+    let x = start + index as i32 * width;
+    Some(rect(
+        x + ui.px(2),
+        ui.panel_margin() + ui.px(6),
+        width - ui.px(4),
+        ui.panel_height() - ui.px(12),
+    ))
+}
+
+/// A single recorded drawing operation
+#[derive(Debug, Clone)]
+pub enum DrawCmd {
+    /// A solid (optionally translucent) rectangle
+    Rect {
+        rect: Rectangle<i32, Physical>,
+        color: [f32; 4],
+    },
+    /// A line of text with its top-left corner at (x, y)
+    Text { x: i32, y: i32, key: TextKey },
+    /// An image drawn at its natural size with its top-left corner at (x, y)
+    Image { x: i32, y: i32, image: Image },
+}
+
+/// Ordered list of drawing operations for one frame
+#[derive(Debug, Default)]
+pub struct Scene {
+    cmds: Vec<DrawCmd>,
+}
+
+impl Scene {
+    /// Record a solid rectangle
+    pub fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: [f32; 4]) {
+        if w > 0 && h > 0 {
+            self.cmds.push(DrawCmd::Rect {
+                rect: rect(x, y, w, h),
+                color,
+            });
+        }
+    }
+
+    /// Record a line of text
+    pub fn text(&mut self, x: i32, y: i32, text: &str, size: u32, color: [f32; 4]) {
+        if !text.is_empty() {
+            self.cmds.push(DrawCmd::Text {
+                x,
+                y,
+                key: TextKey::new(text, size, color),
+            });
+        }
+    }
+
+    /// Record an image at its natural size
+    pub fn image(&mut self, x: i32, y: i32, image: &Image) {
+        self.cmds.push(DrawCmd::Image {
+            x,
+            y,
+            image: image.clone(),
+        });
+    }
+
+    /// Drop all operations, keeping the allocation
+    pub fn clear(&mut self) {
+        self.cmds.clear();
+    }
+
+    /// All recorded operations in paint order
+    pub fn commands(&self) -> &[DrawCmd] {
+        &self.cmds
+    }
+}
+
+/// Maps scene coordinates into the frame: identity, or the magnifier's
+/// zoomed-in part of the scene (see zoom.rs)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// Scene point drawn at the frame's top-left corner
+    origin: (f64, f64),
+    scale: f64,
+}
+
+impl View {
+    pub const IDENTITY: Self = Self {
+        origin: (0.0, 0.0),
+        scale: 1.0,
+    };
+
+    pub fn new(origin: (f64, f64), scale: f64) -> Self {
+        Self { origin, scale }
+    }
+
+    /// Where a scene rectangle lands in the frame
+    fn map(&self, rect: Rectangle<i32, Physical>) -> Rectangle<i32, Physical> {
+        if *self == Self::IDENTITY {
+            return rect;
+        }
+        let x0 = ((rect.loc.x as f64 - self.origin.0) * self.scale).floor() as i32;
+        let y0 = ((rect.loc.y as f64 - self.origin.1) * self.scale).floor() as i32;
+        let x1 = (((rect.loc.x + rect.size.w) as f64 - self.origin.0) * self.scale).ceil() as i32;
+        let y1 = (((rect.loc.y + rect.size.h) as f64 - self.origin.1) * self.scale).ceil() as i32;
+        Rectangle::new((x0, y0).into(), (x1 - x0, y1 - y0).into())
+    }
+}
+
 pub struct Renderer;
 
 impl Renderer {
-    /// Render a full frame into the given frame.
-    pub fn render_frame<F: Frame>(
-        state: &HeyDM,
-        frame: &mut F,
-        _output: &Output,
-        output_size: Size<i32, Physical>,
-    ) -> Result<(), Box<dyn std::error::Error>> 
-    where F::Error: 'static
-    {
+    /// Record everything visible on the output into a scene, replacing
+    /// what it held. Backends keep one scene and pass it every frame so its
+    /// buffer is reused.
+    pub fn build_scene(state: &HeyDM, output_size: Size<i32, Physical>, scene: &mut Scene) {
+        scene.clear();
+
+        let accent = state.accent_color();
+        let ui = &state.ui;
+
         // ---- 1. Background ----
-        frame.clear(
-            colors::BG_DARK.into(),
-            &[rect(0, 0, output_size.w, output_size.h)],
-        )?;
+        scene.rect(0, 0, output_size.w, output_size.h, colors::BG_DARK);
+        if let Some((wallpaper, (x, y))) = state.wallpaper.image() {
+            scene.image(x, y, wallpaper);
+        }
+        // Further outputs, next to this one (udev only)
+        for area in state.window_manager.output_areas() {
+            scene.rect(area.loc.x, area.loc.y, area.size.w, area.size.h, colors::BG_DARK);
+        }
+
+        if state.lock.is_locked() {
+            Self::build_lock_screen(state, scene, output_size);
+            Self::build_cursor(state, scene);
+            return;
+        }
 
         // ---- 2. Windows ----
-        let focused_idx = state.window_manager.windows().len().checked_sub(1);
-        for (idx, window) in state.window_manager.windows().iter().enumerate() {
+        let focused = state.window_manager.focused_window().map(|w| w.toplevel());
+        for window in state.window_manager.visible_windows() {
             let geom = window.geometry();
-            let is_focused = Some(idx) == focused_idx;
+            let is_focused = Some(window.toplevel()) == focused;
             let border_color = if is_focused {
-                colors::BORDER_FOCUSED.into()
+                accent
             } else {
-                colors::BORDER_UNFOCUSED.into()
+                colors::BORDER_UNFOCUSED
             };
 
             // Draw thick borders
-            let b = BORDER_WIDTH;
-            frame.clear(border_color, &[
-                rect(geom.loc.x - b, geom.loc.y - b, geom.size.w + 2 * b, b), // Top
-                rect(geom.loc.x - b, geom.loc.y + geom.size.h, geom.size.w + 2 * b, b), // Bottom
-                rect(geom.loc.x - b, geom.loc.y, b, geom.size.h), // Left
-                rect(geom.loc.x + geom.size.w, geom.loc.y, b, geom.size.h), // Right
-            ])?;
-        }
-
-        // ---- 3. Island Panel (Floating) ----
-        let panel_w = output_size.w - (PANEL_MARGIN * 2);
-        let panel_x = PANEL_MARGIN;
-        let panel_y = PANEL_MARGIN;
-
-        // Main Panel Bar
-        frame.clear(
-            colors::PANEL_BG.into(),
-            &[rect(panel_x, panel_y, panel_w, PANEL_HEIGHT)],
-        )?;
-
-        // Decorative Accent Line (Bottom of panel)
-        frame.clear(
-            colors::ACCENT_CRIMSON.into(),
-            &[rect(panel_x + 20, panel_y + PANEL_HEIGHT - 2, 60, 2)],
-        )?;
+            let b = ui.border_width();
+            scene.rect(geom.loc.x - b, geom.loc.y - b, geom.size.w + 2 * b, b, border_color); // Top
+            scene.rect(geom.loc.x - b, geom.loc.y + geom.size.h, geom.size.w + 2 * b, b, border_color); // Bottom
+            scene.rect(geom.loc.x - b, geom.loc.y, b, geom.size.h, border_color); // Left
+            scene.rect(geom.loc.x + geom.size.w, geom.loc.y, b, geom.size.h, border_color); // Right
+        }
+
+        // Input method candidates below the text cursor of their window
+        for (loc, image) in state.ime_popups.placed(&state.window_manager, state.output_scale, output_size) {
+            scene.image(loc.x, loc.y, image);
+        }
+
+        // Snap preview while dragging a window to an edge or corner
+        if let Some(area) = state.window_manager.snap_preview(&output_size) {
+            let mut fill = accent;
+            fill[3] = 0.2;
+            scene.rect(area.loc.x, area.loc.y, area.size.w, area.size.h, fill);
+            let b = ui.px(2);
+            scene.rect(area.loc.x, area.loc.y, area.size.w, b, accent);
+            scene.rect(area.loc.x, area.loc.y + area.size.h - b, area.size.w, b, accent);
+            scene.rect(area.loc.x, area.loc.y, b, area.size.h, accent);
+            scene.rect(area.loc.x + area.size.w - b, area.loc.y, b, area.size.h, accent);
+        }
+
+        // Overview cards cover the windows; the panel stays on top
+        if state.overview.is_visible() {
+            Self::build_overview(state, scene, output_size);
+        }
+
+        // ---- 3. Island Panel (Floating; hidden in greeter mode and under
+        // fullscreen windows) ----
+        if state.panel_visible() {
+            let panel_h = ui.panel_height();
+            let panel_w = output_size.w - (ui.panel_margin() * 2);
+            let panel_x = ui.panel_margin();
+            let panel_y = ui.panel_margin();
+
+            // Main Panel Bar
+            scene.rect(panel_x, panel_y, panel_w, panel_h, colors::PANEL_BG);
+
+            // Decorative Accent Line (Bottom of panel)
+            let line = ui.px(2);
+            scene.rect(panel_x + ui.px(20), panel_y + panel_h - line, ui.px(60), line, accent);
+
+            // Branding / launcher button, above the accent line
+            let brand_size = ui.font(15);
+            let (brand_w, brand_h) = state.text.measure("heyOS", brand_size);
+            scene.text(
+                panel_x + ui.px(50) - brand_w / 2,
+                panel_y + (panel_h - brand_h) / 2,
+                "heyOS",
+                brand_size,
+                colors::TEXT_PRIMARY,
+            );
+
+            // Workspace indicators (active = crimson, shown on another
+            // output = light, occupied = cyan, empty = dim)
+            let workspaces = state.window_manager.workspaces();
+            let active_ws = workspaces.active_workspace();
+            for ws in 1..=crate::workspace::WORKSPACE_COUNT {
+                let occupied = state
+                    .window_manager
+                    .windows()
+                    .iter()
+                    .any(|w| w.workspace() == ws);
+                let color = if ws == active_ws {
+                    accent
+                } else if workspaces.is_visible(ws) {
+                    colors::TEXT_SECONDARY
+                } else if occupied {
+                    colors::ACCENT_CYAN
+                } else {
+                    colors::BORDER_UNFOCUSED
+                };
+                let slot = workspace_indicator_rect(ws, ui);
+                let dot = ui.px(8);
+                scene.rect(
+                    slot.loc.x + (slot.size.w - dot) / 2,
+                    panel_y + (panel_h - dot) / 2,
+                    dot,
+                    dot,
+                    color,
+                );
+            }
+
+            // Status area: brightness, battery, clock (right-aligned)
+            let brightness = state.panel.brightness_text();
+            let status = [
+                brightness.as_str(),
+                state.panel.battery_text().as_str(),
+                state.panel.clock_text(),
+            ]
+            .iter()
+            .filter(|module| !module.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("   ");
+            let status_size = ui.font(13);
+            let (status_w, status_h) = state.text.measure(&status, status_size);
+            scene.text(
+                panel_x + panel_w - ui.px(16) - status_w,
+                panel_y + (panel_h - status_h) / 2,
+                &status,
+                status_size,
+                colors::TEXT_PRIMARY,
+            );
+
+            // Network: signal bars for Wi-Fi, dimmed without internet access
+            let network = network_rect(output_size, ui);
+            let network_size = ui.font(13);
+            let mut network_x = network.loc.x + ui.px(8);
+            if let Some(strength) = state.panel.network_strength() {
+                let bars_h = ui.px(12);
+                Self::build_signal_bars(
+                    state,
+                    scene,
+                    network_x,
+                    network.loc.y + (network.size.h - bars_h) / 2,
+                    strength,
+                    bars_h,
+                );
+                network_x += ui.px(22);
+            }
+            let network_text = state.panel.network_text();
+            let (char_w, _) = state.text.measure("m", network_size);
+            let max_chars = ((network.loc.x + network.size.w - network_x) / char_w.max(1)).max(1) as usize;
+            let network_text = truncate(&network_text, max_chars);
+            let (_, network_h) = state.text.measure(network_text, network_size);
+            scene.text(
+                network_x,
+                network.loc.y + (network.size.h - network_h) / 2,
+                network_text,
+                network_size,
+                if state.panel.network_online() { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY },
+            );
+
+            // Bluetooth: accent with a device connected, dimmed while off
+            let bluetooth = state.bluetooth_menu.state();
+            if bluetooth.available {
+                let area = bluetooth_rect(output_size, ui);
+                let bt_size = ui.font(12);
+                let (bt_w, bt_h) = state.text.measure("BT", bt_size);
+                let color = if !bluetooth.powered {
+                    colors::TEXT_SECONDARY
+                } else if bluetooth.connected().next().is_some() {
+                    accent
+                } else {
+                    colors::TEXT_PRIMARY
+                };
+                scene.text(
+                    area.loc.x + (area.size.w - bt_w) / 2,
+                    area.loc.y + (area.size.h - bt_h) / 2,
+                    "BT",
+                    bt_size,
+                    color,
+                );
+            }
+
+            // Volume
+            let volume = state.panel.volume_text();
+            if !volume.is_empty() {
+                let area = volume_rect(output_size, ui);
+                let volume_size = ui.font(13);
+                let (volume_w, volume_h) = state.text.measure(&volume, volume_size);
+                scene.text(
+                    area.loc.x + (area.size.w - volume_w) / 2,
+                    area.loc.y + (area.size.h - volume_h) / 2,
+                    &volume,
+                    volume_size,
+                    colors::TEXT_PRIMARY,
+                );
+            }
+
+            // Power profile, dimmed when it is only the CPU governor
+            let profile = state.panel.power_profile_text();
+            if !profile.is_empty() {
+                let area = power_profile_rect(output_size, ui);
+                let profile_size = ui.font(13);
+                let (char_w, _) = state.text.measure("m", profile_size);
+                let profile = truncate(profile, (area.size.w / char_w.max(1)).max(1) as usize);
+                let (profile_w, profile_h) = state.text.measure(profile, profile_size);
+                let switchable = state.panel.power_profile_switchable();
+                scene.text(
+                    area.loc.x + (area.size.w - profile_w) / 2,
+                    area.loc.y + (area.size.h - profile_h) / 2,
+                    profile,
+                    profile_size,
+                    if switchable { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY },
+                );
+            }
+
+            // Do-not-disturb toggle: accent while on
+            let dnd = dnd_rect(output_size, ui);
+            let dnd_size = ui.font(12);
+            let (dnd_w, dnd_h) = state.text.measure("DND", dnd_size);
+            scene.text(
+                dnd.loc.x + (dnd.size.w - dnd_w) / 2,
+                dnd.loc.y + (dnd.size.h - dnd_h) / 2,
+                "DND",
+                dnd_size,
+                if state.notifications.is_dnd() { accent } else { colors::TEXT_SECONDARY },
+            );
+
+            // Tray icons; a placeholder dot for items without a usable icon
+            for (i, item) in state.tray.items().iter().enumerate() {
+                let slot = tray_icon_rect(i, output_size, ui);
+                match &item.icon {
+                    Some(icon) => scene.image(
+                        slot.loc.x + (slot.size.w - icon.width) / 2,
+                        slot.loc.y + (slot.size.h - icon.height) / 2,
+                        icon,
+                    ),
+                    None => {
+                        let dot = ui.px(8);
+                        scene.rect(
+                            slot.loc.x + (slot.size.w - dot) / 2,
+                            slot.loc.y + (slot.size.h - dot) / 2,
+                            dot,
+                            dot,
+                            colors::TEXT_SECONDARY,
+                        );
+                    }
+                }
+            }
+
+            // Title of the focused window, its app id in front while it fits
+            if let Some(window) = state.window_manager.focused_window() {
+                let (title, app_id) = window.title_and_app_id();
+                let (title, app_id) = match (title, app_id) {
+                    (Some(title), app_id) => (Some(title), app_id),
+                    (None, app_id) => (app_id, None),
+                };
+                if let Some(title) = title {
+                    let area = focused_title_rect(output_size, ui);
+                    let title_size = ui.font(13);
+                    let max_chars = (area.size.w / (title_size as i32 / 2).max(1)).max(1);
+                    let title = truncate(&title, max_chars as usize);
+                    let (title_w, title_h) = state.text.measure(title, title_size);
+                    let title_x = area.loc.x + area.size.w - title_w.min(area.size.w);
+                    scene.text(
+                        title_x,
+                        area.loc.y + (area.size.h - title_h) / 2,
+                        title,
+                        title_size,
+                        colors::TEXT_PRIMARY,
+                    );
+
+                    let app_size = ui.font(11);
+                    let app_id = app_id.filter(|app_id| !title.eq_ignore_ascii_case(app_id));
+                    if let Some(app_id) = app_id {
+                        let (app_w, app_h) = state.text.measure(&app_id, app_size);
+                        let app_x = title_x - ui.px(8) - app_w;
+                        if app_x >= area.loc.x {
+                            scene.text(
+                                app_x,
+                                area.loc.y + (area.size.h - app_h) / 2,
+                                &app_id,
+                                app_size,
+                                colors::TEXT_SECONDARY,
+                            );
+                        }
+                    }
+                }
+            }
+
+            // Taskbar (minimized windows dimmed)
+            let taskbar = crate::panel::StatusPanel::taskbar_windows(&state.window_manager);
+            let focused_id = state.window_manager.focused_window().map(|w| w.id());
+            let media = state.panel.media();
+            for (i, window) in taskbar.iter().enumerate() {
+                let Some(button) = taskbar_button_rect(i, taskbar.len(), media.is_some(), output_size, ui) else {
+                    break;
+                };
+                let (bg, fg) = if window.is_minimized() {
+                    ([1.0, 1.0, 1.0, 0.02], colors::TEXT_SECONDARY)
+                } else if Some(window.id()) == focused_id {
+                    let mut bg = accent;
+                    bg[3] = 0.25;
+                    (bg, colors::TEXT_PRIMARY)
+                } else {
+                    ([1.0, 1.0, 1.0, 0.06], colors::TEXT_PRIMARY)
+                };
+                scene.rect(button.loc.x, button.loc.y, button.size.w, button.size.h, bg);
+
+                let (title, app_id) = window.title_and_app_id();
+                let label = title.or(app_id).unwrap_or_else(|| "Window".to_string());
+                let label_size = ui.font(13);
+                let max_chars = ((button.size.w - ui.px(16)) / (label_size as i32 / 2).max(1)).max(1);
+                let label = truncate(&label, max_chars as usize);
+                let (_, label_h) = state.text.measure(label, label_size);
+                scene.text(
+                    button.loc.x + ui.px(8),
+                    button.loc.y + (button.size.h - label_h) / 2,
+                    label,
+                    label_size,
+                    fg,
+                );
+            }
+
+            // Media player: previous / play-pause / next, then the track
+            if let Some(media) = media {
+                let control_size = ui.font(13);
+                let play = if media.playing { "❚❚" } else { "▶" };
+                for (i, glyph) in ["«", play, "»"].into_iter().enumerate() {
+                    let slot = media_button_rect(i, output_size, ui);
+                    let (glyph_w, glyph_h) = state.text.measure(glyph, control_size);
+                    scene.text(
+                        slot.loc.x + (slot.size.w - glyph_w) / 2,
+                        slot.loc.y + (slot.size.h - glyph_h) / 2,
+                        glyph,
+                        control_size,
+                        colors::TEXT_PRIMARY,
+                    );
+                }
+                let area = media_rect(output_size, ui);
+                let track_x = media_button_rect(2, output_size, ui).loc.x + ui.px(32);
+                let track_size = ui.font(12);
+                let (char_w, _) = state.text.measure("m", track_size);
+                let label = media.label();
+                let max_chars = ((area.loc.x + area.size.w - track_x) / char_w.max(1)).max(1) as usize;
+                let label = truncate(&label, max_chars);
+                let (_, label_h) = state.text.measure(label, track_size);
+                scene.text(
+                    track_x,
+                    area.loc.y + (area.size.h - label_h) / 2,
+                    label,
+                    track_size,
+                    if media.playing { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY },
+                );
+            }
+        }
+
+        // Notification toasts, top-right below the panel, and the tray menu
+        if !state.greeter {
+            Self::build_notifications(state, scene, output_size);
+            Self::build_tray_menu(state, scene, output_size);
+            if state.bluetooth_menu.is_open() {
+                Self::build_bluetooth_menu(state, scene, output_size);
+            }
+            if state.network_menu.is_open() {
+                Self::build_network_menu(state, scene, output_size);
+            }
+            if state.calendar.is_visible() {
+                Self::build_calendar(state, scene, output_size);
+            }
+        }
 
         // ---- 4. Launcher (Grid Style) ----
         if state.launcher.is_visible() {
             // Dark overlay
-            frame.clear(
-                [0.0_f32, 0.0, 0.0, 0.7].into(),
-                &[rect(0, 0, output_size.w, output_size.h)],
-            )?;
+            scene.rect(0, 0, output_size.w, output_size.h, [0.0, 0.0, 0.0, 0.7]);
 
-            let lw = 800.min(output_size.w - 100).max(0);
-            let lh = 600.min(output_size.h - 200).max(0);
-            let lx = (output_size.w - lw) / 2;
-            let ly = (output_size.h - lh) / 2;
+            let layout = LauncherLayout::new(output_size, ui);
+            let (lx, ly, lw) = (layout.x, layout.y, layout.w);
 
             // Launcher Box
-            frame.clear(colors::LAUNCHER_BG.into(), &[rect(lx, ly, lw, lh)])?;
-            
+            scene.rect(lx, ly, lw, layout.h, colors::LAUNCHER_BG);
+
             // Search Bar Area
-            frame.clear(
-                [0.12_f32, 0.12, 0.18, 1.0].into(),
-                &[rect(lx + 20, ly + 20, lw - 40, 50)],
-            )?;
+            let margin = ui.px(20);
+            scene.rect(lx + margin, ly + margin, lw - margin * 2, layout.search_h, [0.12, 0.12, 0.18, 1.0]);
+            let query = state.launcher.search_query();
+            let query_size = ui.font(16);
+            let (_, query_h) = state.text.measure("Type to search", query_size);
+            let query_y = ly + margin + (layout.search_h - query_h) / 2;
+            let query_x = lx + ui.px(36);
+            if query.is_empty() {
+                scene.text(query_x, query_y, "Type to search", query_size, colors::TEXT_SECONDARY);
+            } else {
+                scene.text(query_x, query_y, query, query_size, colors::TEXT_PRIMARY);
+            }
+            // Caret after the query
+            let (caret_x, _) = if query.is_empty() { (0, 0) } else { state.text.measure(query, query_size) };
+            scene.rect(query_x + caret_x + ui.px(2), query_y, ui.px(2).max(1), query_h, accent);
 
             // Grid Items
-            let cols = 4;
-            let item_w = (lw - 60) / cols;
-            let item_h = 100;
-            
+            let (item_w, item_h) = (layout.item_w, layout.item_h);
+            let inset = ui.px(5);
+            let icon_size = ui.px(ICON_SIZE as i32);
+            let name_size = ui.font(13);
+
             let visible_apps = state.launcher.visible_entries();
-            let count = visible_apps.len().min(12);
-            
-            for i in 0..count { // Draw dynamically based on available apps
-                let row = i as i32 / cols;
-                let col = i as i32 % cols;
-                let ix = lx + 30 + (col * item_w);
-                let iy = ly + 90 + (row * item_h);
-                
-                let is_selected = state.launcher.selected_index() == Some(i as usize);
+            let count = visible_apps.len().min(layout.capacity());
+            let first = state.launcher.scroll_offset();
+
+            // Prefix modes: one result row instead of the grid
+            let result = match state.launcher.query_mode() {
+                QueryMode::Apps => None,
+                QueryMode::Run("") => Some(("Type a command to run".to_string(), colors::TEXT_SECONDARY, "")),
+                QueryMode::Run(command) => Some((format!("Run: {command}"), colors::TEXT_PRIMARY, "Enter to run")),
+                QueryMode::Calc(_) => Some(match state.launcher.calc_result() {
+                    Some(Ok(value)) => (format!("= {value}"), colors::TEXT_PRIMARY, "Enter to copy"),
+                    Some(Err(e)) => (e.clone(), colors::TEXT_SECONDARY, ""),
+                    None => (String::new(), colors::TEXT_SECONDARY, ""),
+                }),
+            };
+            if let Some((text, color, hint)) = &result {
+                let row_h = ui.px(56);
+                let row_x = lx + margin;
+                let row_w = lw - margin * 2;
+                let mut highlight = accent;
+                highlight[3] = 0.2;
+                scene.rect(row_x, layout.grid_y, row_w, row_h, highlight);
+                scene.rect(row_x, layout.grid_y, ui.px(3), row_h, accent);
+
+                let text_size = ui.font(20);
+                let (_, text_h) = state.text.measure("=", text_size);
+                scene.text(row_x + ui.px(16), layout.grid_y + (row_h - text_h) / 2, text, text_size, *color);
+                if !hint.is_empty() {
+                    let hint_size = ui.font(13);
+                    let (hint_w, hint_h) = state.text.measure(hint, hint_size);
+                    scene.text(row_x + row_w - hint_w - ui.px(16), layout.grid_y + (row_h - hint_h) / 2, hint, hint_size, colors::TEXT_SECONDARY);
+                }
+            }
+
+            if count == 0 && result.is_none() {
+                let message = "No matching applications";
+                let (message_w, _) = state.text.measure(message, query_size);
+                scene.text(lx + (lw - message_w) / 2, layout.grid_y + ui.px(40), message, query_size, colors::TEXT_SECONDARY);
+            }
+
+            for (i, (app_name, _, app_icon)) in visible_apps.enumerate() {
+                let (ix, iy) = layout.item(i);
+
+                let is_selected = state.launcher.selected_index() == Some(first + i);
                 let item_bg = if is_selected {
-                    let mut c = colors::ACCENT_CRIMSON;
+                    let mut c = accent;
                     c[3] = 0.2;
-                    c.into()
+                    c
                 } else {
-                    [1.0_f32, 1.0, 1.0, 0.03].into()
+                    [1.0, 1.0, 1.0, 0.03]
                 };
 
-                frame.clear(item_bg, &[rect(ix + 5, iy + 5, item_w - 10, item_h - 10)])?;
-                
-                // Icon Placeholder
-                frame.clear(
-                    if is_selected { colors::ACCENT_CRIMSON.into() } else { colors::ACCENT_CYAN.into() },
-                    &[rect(ix + (item_w / 2) - 15, iy + 20, 30, 30)]
-                )?;
+                scene.rect(ix + inset, iy + inset, item_w - inset * 2, item_h - inset * 2, item_bg);
+
+                // Icon, or a placeholder until (or unless) it is loaded
+                let icon_y = iy + ui.px(15);
+                match app_icon {
+                    Some(icon) => scene.image(
+                        ix + (item_w - icon.width) / 2,
+                        icon_y + (icon_size - icon.height) / 2,
+                        icon,
+                    ),
+                    None => scene.rect(
+                        ix + (item_w - ui.px(30)) / 2,
+                        icon_y + ui.px(5),
+                        ui.px(30),
+                        ui.px(30),
+                        if is_selected { accent } else { colors::ACCENT_CYAN },
+                    ),
+                }
+
+                let (char_w, _) = state.text.measure("m", name_size);
+                let max_chars = ((item_w - ui.px(20)) / char_w.max(1)).max(1) as usize;
+                let name = truncate(app_name, max_chars);
+                let (name_w, _) = state.text.measure(name, name_size);
+                scene.text(ix + (item_w - name_w) / 2, icon_y + icon_size + ui.px(7), name, name_size, colors::TEXT_PRIMARY);
+            }
+
+            // Desktop actions of the selected app, next to its item
+            let selected_item = state.launcher.selected_index().and_then(|s| s.checked_sub(first));
+            if let (Some((actions, row)), Some(item)) = (state.launcher.open_actions(), selected_item) {
+                let (mx, my) = layout.action_menu(item);
+                let row_h = layout.menu_row_h;
+                scene.rect(mx, my, layout.menu_w, row_h * actions.len() as i32, colors::LAUNCHER_BG);
+                for (i, action) in actions.iter().enumerate() {
+                    let ry = my + row_h * i as i32;
+                    if i == row {
+                        let mut highlight = accent;
+                        highlight[3] = 0.2;
+                        scene.rect(mx, ry, layout.menu_w, row_h, highlight);
+                        scene.rect(mx, ry, ui.px(3), row_h, accent);
+                    }
+                    let (_, text_h) = state.text.measure(action, name_size);
+                    scene.text(mx + ui.px(12), ry + (row_h - text_h) / 2, action, name_size, colors::TEXT_PRIMARY);
+                }
+            }
+        }
+
+        // ---- 5. Task Manager ----
+        if state.task_manager.is_visible() {
+            Self::build_task_manager(state, scene, output_size);
+        }
+
+        // ---- 6. Window Switcher ----
+        if state.switcher.is_visible() {
+            Self::build_switcher(state, scene, output_size);
+        }
+
+        // ---- 7. On-screen indicator and tooltip ----
+        if let Some((label, percent)) = state.osd.visible() {
+            Self::build_osd(state, scene, label, percent, output_size);
+        }
+        if let Some(text) = state.tooltips.visible_text() {
+            Self::build_tooltip(state, scene, text, output_size);
+        }
+        if state.perf.overlay_visible() {
+            Self::build_perf_overlay(state, scene, output_size);
+        }
+
+        // ---- 8. Cursor (Glow) ----
+        Self::build_cursor(state, scene);
+    }
+
+    /// Task manager: one row per window with its process's CPU and memory
+    fn build_task_manager(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let tm = &state.task_manager;
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        scene.rect(0, 0, output_size.w, output_size.h, [0.0, 0.0, 0.0, 0.7]);
+
+        let row_h = ui.px(28);
+        let text_size = ui.font(14);
+        let w = ui.px(760).min(output_size.w - ui.px(100)).max(0);
+        let h = ui.px(520).min(output_size.h - ui.px(160)).max(0);
+        let x = (output_size.w - w) / 2;
+        let y = (output_size.h - h) / 2;
+        scene.rect(x, y, w, h, colors::LAUNCHER_BG);
+        scene.rect(x, y, w, 2, accent);
+        let pad = ui.px(20);
+        scene.text(x + pad, y + ui.px(16), "Task Manager", ui.font(20), colors::TEXT_PRIMARY);
+
+        // Column headers; the sort column is highlighted
+        let col_pid = x + w - ui.px(330);
+        let col_cpu = x + w - ui.px(230);
+        let col_mem = x + w - ui.px(130);
+        let header_y = y + ui.px(56);
+        let sort = tm.sort_column();
+        let header_color = |column| {
+            if sort == column {
+                accent
+            } else {
+                colors::TEXT_SECONDARY
+            }
+        };
+        scene.text(x + pad, header_y, "Window", text_size, header_color(SortColumn::Name));
+        scene.text(col_pid, header_y, "PID", text_size, colors::TEXT_SECONDARY);
+        scene.text(col_cpu, header_y, "CPU", text_size, header_color(SortColumn::Cpu));
+        scene.text(col_mem, header_y, "Memory", text_size, header_color(SortColumn::Memory));
+        scene.rect(x + pad, header_y + ui.px(24), w - pad * 2, 1, colors::BORDER_UNFOCUSED);
+
+        // Rows, scrolled so the selection stays visible
+        let list_y = header_y + ui.px(32);
+        let max_rows = ((y + h - ui.px(48) - list_y) / row_h).max(1) as usize;
+        let rows = tm.rows();
+        let selected = tm.selected_index();
+        let first = selected.map_or(0, |idx| (idx + 1).saturating_sub(max_rows));
+
+        if rows.is_empty() {
+            scene.text(x + pad, list_y + ui.px(6), "No windows", text_size, colors::TEXT_SECONDARY);
+        }
+        for (i, row) in rows.iter().enumerate().skip(first).take(max_rows) {
+            let ry = list_y + (i - first) as i32 * row_h;
+            if Some(i) == selected {
+                let mut highlight = accent;
+                highlight[3] = 0.2;
+                scene.rect(x + ui.px(12), ry, w - ui.px(24), row_h, highlight);
+            }
+
+            let text_y = ry + ui.px(6);
+            let name = truncate(&row.name, 48);
+            scene.text(x + pad, text_y, name, text_size, colors::TEXT_PRIMARY);
+            let pid = row.pid.map_or_else(|| "?".to_string(), |pid| pid.to_string());
+            scene.text(col_pid, text_y, &pid, text_size, colors::TEXT_SECONDARY);
+            scene.text(col_cpu, text_y, &format!("{:.1}%", row.cpu), text_size, colors::TEXT_PRIMARY);
+            scene.text(col_mem, text_y, &format_memory(row.memory_kb), text_size, colors::TEXT_PRIMARY);
+        }
+
+        scene.text(x + pad, y + h - ui.px(32), &tm.footer_text(), ui.font(13), colors::TEXT_SECONDARY);
+    }
+
+    /// Window switcher: centered list of app ids and titles, the selection
+    /// highlighted
+    fn build_switcher(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let switcher = &state.switcher;
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        let entries = switcher.entries();
+        let selected = switcher.selected_index();
+
+        let layout = switcher.layout(output_size);
+        let (x, y, w, h) = (layout.area.loc.x, layout.area.loc.y, layout.area.size.w, layout.area.size.h);
+        let (row_h, first) = (layout.row_h, layout.first);
+        scene.rect(x, y, w, h, colors::LAUNCHER_BG);
+        scene.rect(x, y, w, 2, accent);
+
+        for (i, entry) in entries.iter().enumerate().skip(first).take(layout.max_rows) {
+            let ry = layout.rows_y + (i - first) as i32 * row_h;
+            if i == selected {
+                let mut highlight = accent;
+                highlight[3] = 0.2;
+                scene.rect(x + ui.px(8), ry, w - ui.px(16), row_h, highlight);
+                scene.rect(x + ui.px(8), ry, ui.px(3), row_h, accent);
+            }
+
+            let app_id = if entry.app_id.is_empty() {
+                "unknown"
+            } else {
+                entry.app_id.as_str()
+            };
+            let app_id = truncate(app_id, APP_ID_CHARS);
+            let title = truncate(&entry.title, TITLE_CHARS);
+            let text_size = ui.font(14);
+            let (_, text_h) = state.text.measure(app_id, text_size);
+            let text_y = ry + (row_h - text_h) / 2;
+            scene.text(x + ui.px(20), text_y, app_id, text_size, colors::ACCENT_CYAN);
+            scene.text(x + ui.px(200), text_y, title, text_size, colors::TEXT_PRIMARY);
+        }
+    }
+
+    /// Window overview: a card per window with its app id inside and its
+    /// title below, the selection outlined
+    fn build_overview(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        scene.rect(0, 0, output_size.w, output_size.h, [0.0, 0.0, 0.0, 0.6]);
+
+        let cards = state.overview.cards(output_size, ui);
+        let selected_id = state.overview.selected_id();
+        for (entry, card) in cards {
+            let (x, y, w, h) = (card.loc.x, card.loc.y, card.size.w, card.size.h);
+            scene.rect(x, y, w, h, colors::LAUNCHER_BG);
+            let b = ui.border_width();
+            let border = if Some(entry.window_id) == selected_id {
+                accent
+            } else {
+                colors::BORDER_UNFOCUSED
+            };
+            scene.rect(x - b, y - b, w + 2 * b, b, border);
+            scene.rect(x - b, y + h, w + 2 * b, b, border);
+            scene.rect(x - b, y, b, h, border);
+            scene.rect(x + w, y, b, h, border);
+
+            let app_id = if entry.app_id.is_empty() { "unknown" } else { entry.app_id.as_str() };
+            let app_size = ui.font(16);
+            let (char_w, _) = state.text.measure("m", app_size);
+            let app_id = truncate(app_id, (w / char_w.max(1)).max(1) as usize);
+            let (app_w, app_h) = state.text.measure(app_id, app_size);
+            scene.text(x + (w - app_w) / 2, y + (h - app_h) / 2, app_id, app_size, colors::ACCENT_CYAN);
+
+            let title_size = ui.font(13);
+            let (char_w, _) = state.text.measure("m", title_size);
+            let title = truncate(&entry.title, (w / char_w.max(1)).max(1) as usize);
+            let (title_w, _) = state.text.measure(title, title_size);
+            scene.text(x + (w - title_w) / 2, y + h + b + ui.px(6), title, title_size, colors::TEXT_PRIMARY);
+        }
+    }
+
+    /// Notification toasts: app name, summary, body, action buttons and ×
+    fn build_notifications(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        let pad = ui.px(12);
+        let small = ui.font(11);
+        let text_size = ui.font(13);
+        for (toast, area) in state.notifications.visible(output_size) {
+            let (x, y, w) = (area.loc.x, area.loc.y, area.size.w);
+            scene.rect(x, y, w, area.size.h, colors::LAUNCHER_BG);
+            scene.rect(x, y, ui.px(3), area.size.h, if toast.critical { colors::ACCENT_CRIMSON } else { accent });
+
+            let close = close_button_rect(area, ui);
+            let (close_w, close_h) = state.text.measure("×", text_size);
+            scene.text(close.loc.x + (close.size.w - close_w) / 2, close.loc.y + (close.size.h - close_h) / 2, "×", text_size, colors::TEXT_SECONDARY);
+
+            let (char_w, _) = state.text.measure("m", text_size);
+            let max_chars = ((w - pad * 2 - close.size.w) / char_w.max(1)).max(1) as usize;
+            scene.text(x + pad, y + ui.px(8), truncate(&toast.app_name, max_chars), small, colors::TEXT_SECONDARY);
+            scene.text(x + pad, y + ui.px(24), truncate(&toast.summary, max_chars), text_size, colors::TEXT_PRIMARY);
+            if !toast.body.is_empty() {
+                let body = toast.body.lines().next().unwrap_or("");
+                scene.text(x + pad, y + ui.px(46), truncate(body, max_chars), text_size, colors::TEXT_SECONDARY);
+            }
+
+            let count = toast.buttons().count();
+            for (i, (_, label)) in toast.buttons().enumerate() {
+                let button = action_button_rect(area, i, count, ui);
+                scene.rect(button.loc.x, button.loc.y, button.size.w, button.size.h, [1.0, 1.0, 1.0, 0.08]);
+                let max_chars = (button.size.w / char_w.max(1)).max(1) as usize;
+                let label = truncate(label, max_chars);
+                let (label_w, label_h) = state.text.measure(label, text_size);
+                scene.text(
+                    button.loc.x + (button.size.w - label_w) / 2,
+                    button.loc.y + (button.size.h - label_h) / 2,
+                    label,
+                    text_size,
+                    colors::TEXT_PRIMARY,
+                );
+            }
+        }
+    }
+
+    /// Month calendar below the clock, today highlighted
+    fn build_calendar(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        let calendar = &state.calendar;
+        let area = calendar.rect(output_size);
+        let (x, y) = (area.loc.x, area.loc.y);
+        scene.rect(x, y, area.size.w, area.size.h, colors::LAUNCHER_BG);
+        scene.rect(x, y, area.size.w, 2, accent);
+
+        let view = calendar.view();
+        let pad = ui.px(12);
+        let cell = calendar.cell_size();
+        let text_size = ui.font(13);
+        let (title_w, _) = state.text.measure(&view.title, ui.font(15));
+        scene.text(x + (area.size.w - title_w) / 2, y + ui.px(12), &view.title, ui.font(15), colors::TEXT_PRIMARY);
+
+        for (column, weekday) in ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"].iter().enumerate() {
+            let (w, _) = state.text.measure(weekday, text_size);
+            let cx = x + pad + column as i32 * cell;
+            scene.text(cx + (cell - w) / 2, y + ui.px(40), weekday, text_size, colors::TEXT_SECONDARY);
+        }
+
+        let grid_y = y + calendar.header_height();
+        for day in 1..=view.days {
+            let index = (view.first_column + day - 1) as i32;
+            let cx = x + pad + (index % 7) * cell;
+            let cy = grid_y + (index / 7) * cell;
+            if view.today == Some(day) {
+                let inset = ui.px(3);
+                scene.rect(cx + inset, cy + inset, cell - inset * 2, cell - inset * 2, accent);
+            }
+            let label = day.to_string();
+            let (w, h) = state.text.measure(&label, text_size);
+            let color = if index % 7 >= 5 { colors::TEXT_SECONDARY } else { colors::TEXT_PRIMARY };
+            scene.text(cx + (cell - w) / 2, cy + (cell - h) / 2, &label, text_size, color);
+        }
+    }
+
+    /// The open tray menu below its icon
+    fn build_tray_menu(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let (Some(menu), Some(area)) = (state.tray.open_menu(), state.tray.menu_rect(output_size)) else {
+            return;
+        };
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        scene.rect(area.loc.x, area.loc.y, area.size.w, area.size.h, colors::LAUNCHER_BG);
+        scene.rect(area.loc.x, area.loc.y, area.size.w, 2, accent);
+
+        let row_h = state.tray.menu_row_height();
+        let text_size = ui.font(13);
+        let (char_w, _) = state.text.measure("m", text_size);
+        let max_chars = ((area.size.w - ui.px(40)) / char_w.max(1)).max(1) as usize;
+        for (i, entry) in menu.entries.iter().enumerate() {
+            let ry = area.loc.y + ui.px(4) + i as i32 * row_h;
+            if entry.separator {
+                scene.rect(area.loc.x + ui.px(8), ry + row_h / 2, area.size.w - ui.px(16), 1, colors::BORDER_UNFOCUSED);
+                continue;
+            }
+            let color = if entry.enabled { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
+            let label = truncate(&entry.label, max_chars);
+            let (_, text_h) = state.text.measure(label, text_size);
+            if entry.checked == Some(true) {
+                scene.text(area.loc.x + ui.px(10), ry + (row_h - text_h) / 2, "✓", text_size, accent);
+            }
+            scene.text(area.loc.x + ui.px(28), ry + (row_h - text_h) / 2, label, text_size, color);
+        }
+    }
+
+    /// Wi-Fi quick menu below the network module: one row per network with
+    /// its signal, the connected one checked
+    fn build_network_menu(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let menu = &state.network_menu;
+        let area = menu.rect(output_size);
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        scene.rect(area.loc.x, area.loc.y, area.size.w, area.size.h, colors::LAUNCHER_BG);
+        scene.rect(area.loc.x, area.loc.y, area.size.w, 2, accent);
+
+        let row_h = menu.row_height();
+        let text_size = ui.font(13);
+        let small_size = ui.font(11);
+        if menu.networks().is_empty() {
+            let (_, text_h) = state.text.measure("No Wi-Fi networks", text_size);
+            let ry = area.loc.y + ui.px(4);
+            scene.text(area.loc.x + ui.px(28), ry + (row_h - text_h) / 2, "No Wi-Fi networks", text_size, colors::TEXT_SECONDARY);
+            return;
+        }
+
+        let (char_w, _) = state.text.measure("m", text_size);
+        let max_chars = ((area.size.w - ui.px(120)) / char_w.max(1)).max(1) as usize;
+        for (i, network) in menu.networks().iter().enumerate() {
+            let ry = area.loc.y + ui.px(4) + i as i32 * row_h;
+            let label = truncate(&network.ssid, max_chars);
+            let (_, text_h) = state.text.measure(label, text_size);
+            if network.active {
+                scene.text(area.loc.x + ui.px(10), ry + (row_h - text_h) / 2, "✓", text_size, accent);
+            }
+            scene.text(area.loc.x + ui.px(28), ry + (row_h - text_h) / 2, label, text_size, colors::TEXT_PRIMARY);
+
+            let bars_h = ui.px(12);
+            let bars_x = area.loc.x + area.size.w - ui.px(28);
+            Self::build_signal_bars(state, scene, bars_x, ry + (row_h - bars_h) / 2, network.strength, bars_h);
+            if network.secured {
+                let (lock_w, lock_h) = state.text.measure("secured", small_size);
+                scene.text(bars_x - ui.px(8) - lock_w, ry + (row_h - lock_h) / 2, "secured", small_size, colors::TEXT_SECONDARY);
+            }
+        }
+    }
+
+    fn build_bluetooth_menu(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let menu = &state.bluetooth_menu;
+        let area = menu.rect(output_size);
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        scene.rect(area.loc.x, area.loc.y, area.size.w, area.size.h, colors::LAUNCHER_BG);
+        scene.rect(area.loc.x, area.loc.y, area.size.w, 2, accent);
+
+        let row_h = menu.row_height();
+        let text_size = ui.font(13);
+        let small_size = ui.font(11);
+        let powered = menu.state().powered;
+
+        // Power row
+        let ry = area.loc.y + ui.px(4);
+        let (_, text_h) = state.text.measure("Bluetooth", text_size);
+        scene.text(area.loc.x + ui.px(28), ry + (row_h - text_h) / 2, "Bluetooth", text_size, colors::TEXT_PRIMARY);
+        let switch = if powered { "On" } else { "Off" };
+        let (switch_w, switch_h) = state.text.measure(switch, text_size);
+        scene.text(
+            area.loc.x + area.size.w - ui.px(12) - switch_w,
+            ry + (row_h - switch_h) / 2,
+            switch,
+            text_size,
+            if powered { accent } else { colors::TEXT_SECONDARY },
+        );
+
+        let (char_w, _) = state.text.measure("m", text_size);
+        let max_chars = ((area.size.w - ui.px(90)) / char_w.max(1)).max(1) as usize;
+        for (i, device) in menu.devices().iter().enumerate() {
+            let ry = area.loc.y + ui.px(4) + (i as i32 + 1) * row_h;
+            let label = truncate(&device.name, max_chars);
+            let (_, text_h) = state.text.measure(label, text_size);
+            if device.connected {
+                scene.text(area.loc.x + ui.px(10), ry + (row_h - text_h) / 2, "✓", text_size, accent);
+            }
+            let color = if powered { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
+            scene.text(area.loc.x + ui.px(28), ry + (row_h - text_h) / 2, label, text_size, color);
+            if let Some(battery) = device.battery {
+                let level = format!("{battery}%");
+                let (level_w, level_h) = state.text.measure(&level, small_size);
+                scene.text(
+                    area.loc.x + area.size.w - ui.px(12) - level_w,
+                    ry + (row_h - level_h) / 2,
+                    &level,
+                    small_size,
+                    colors::TEXT_SECONDARY,
+                );
+            }
+        }
+    }
+
+    /// Four rising bars, filled up to the signal strength
+    fn build_signal_bars(state: &HeyDM, scene: &mut Scene, x: i32, y: i32, strength: u8, h: i32) {
+        let ui = &state.ui;
+        let filled = signal_bars(strength);
+        let bar_w = ui.px(3);
+        for bar in 0..4 {
+            let bar_h = h * (bar + 1) / 4;
+            let color = if bar < filled { colors::TEXT_PRIMARY } else { colors::BORDER_UNFOCUSED };
+            scene.rect(x + bar * (bar_w + ui.px(2)), y + h - bar_h, bar_w, bar_h, color);
+        }
+    }
+
+    /// Level indicator centered above the bottom edge: label and a bar
+    fn build_osd(state: &HeyDM, scene: &mut Scene, label: &str, percent: i32, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let accent = state.accent_color();
+        let w = ui.px(280);
+        let h = ui.px(72);
+        let x = (output_size.w - w) / 2;
+        let y = output_size.h - h - ui.px(96);
+        scene.rect(x, y, w, h, colors::LAUNCHER_BG);
+
+        let pad = ui.px(16);
+        let text_size = ui.font(14);
+        let value = format!("{percent}%");
+        let (value_w, _) = state.text.measure(&value, text_size);
+        scene.text(x + pad, y + ui.px(12), label, text_size, colors::TEXT_PRIMARY);
+        scene.text(x + w - pad - value_w, y + ui.px(12), &value, text_size, colors::TEXT_SECONDARY);
+
+        let bar_w = w - pad * 2;
+        let bar_h = ui.px(6);
+        let bar_y = y + h - pad - bar_h;
+        scene.rect(x + pad, bar_y, bar_w, bar_h, colors::BORDER_UNFOCUSED);
+        scene.rect(x + pad, bar_y, bar_w * percent / 100, bar_h, accent);
+    }
+
+    /// Frame rate, frame and render times, clients and input latency in the
+    /// top right corner (see perf.rs)
+    fn build_perf_overlay(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let summary = state.perf.summary();
+        let ms = |stats: Option<crate::latency::LatencyStats>| match stats {
+            Some(stats) => format!(
+                "{:.1} ms (p99 {:.1})",
+                stats.p50.as_secs_f64() * 1000.0,
+                stats.p99.as_secs_f64() * 1000.0
+            ),
+            None => "–".to_string(),
+        };
+        let lines = [
+            format!("{} fps", summary.fps),
+            format!("frame  {}", ms(summary.frame_time)),
+            format!("render {}", ms(summary.render_time)),
+            format!("input  {}", ms(state.latency.submit_stats())),
+            format!("{} clients", summary.clients),
+        ];
+
+        let text_size = ui.font(12);
+        let pad = ui.px(8);
+        let line_h = state.text.measure("0", text_size).1 + ui.px(4);
+        let w = lines
+            .iter()
+            .map(|line| state.text.measure(line, text_size).0)
+            .max()
+            .unwrap_or(0)
+            + pad * 2;
+        let h = line_h * lines.len() as i32 + pad * 2;
+        let x = output_size.w - w - ui.px(12);
+        let y = ui.px(12);
+        scene.rect(x, y, w, h, colors::LAUNCHER_BG);
+        for (i, line) in lines.iter().enumerate() {
+            let color = if i == 0 { colors::TEXT_PRIMARY } else { colors::TEXT_SECONDARY };
+            scene.text(x + pad, y + pad + line_h * i as i32, line, text_size, color);
+        }
+    }
+
+    /// Tooltip box below-right of the cursor, kept inside the output
+    fn build_tooltip(state: &HeyDM, scene: &mut Scene, text: &str, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let padding = ui.px(6);
+        let text_size = ui.font(13);
+        let (text_w, text_h) = state.text.measure(text, text_size);
+        let box_w = text_w + padding * 2;
+        let box_h = text_h + padding * 2;
+
+        let (cx, cy) = state.window_manager.cursor_position();
+        let mut x = cx as i32 + ui.px(12);
+        let mut y = cy as i32 + ui.px(20);
+        if x + box_w > output_size.w {
+            x = (output_size.w - box_w).max(0);
+        }
+        if y + box_h > output_size.h {
+            // Flip above the cursor when there is no room below
+            y = (cy as i32 - box_h - ui.px(8)).max(0);
+        }
+
+        scene.rect(x - 1, y - 1, box_w + 2, box_h + 2, colors::BORDER_UNFOCUSED);
+        scene.rect(x, y, box_w, box_h, colors::LAUNCHER_BG);
+        scene.text(x + padding, y + padding, text, text_size, colors::TEXT_PRIMARY);
+    }
+
+    /// Built-in lock screen: clock, date, password field and status line
+    fn build_lock_screen(state: &HeyDM, scene: &mut Scene, output_size: Size<i32, Physical>) {
+        let ui = &state.ui;
+        let center_x = output_size.w / 2;
+        let now = Local::now();
+
+        let time = now.format("%H:%M").to_string();
+        let time_size = ui.font(96);
+        let (time_w, time_h) = state.text.measure(&time, time_size);
+        let time_y = output_size.h / 2 - ui.px(200);
+        scene.text(center_x - time_w / 2, time_y, &time, time_size, colors::TEXT_PRIMARY);
+
+        let date = now.format("%A, %B %e").to_string();
+        let date_size = ui.font(24);
+        let (date_w, _) = state.text.measure(&date, date_size);
+        scene.text(center_x - date_w / 2, time_y + time_h + ui.px(8), &date, date_size, colors::TEXT_SECONDARY);
+
+        // Password field: one dot per typed character
+        let field_w = ui.px(320);
+        let field_h = ui.px(44);
+        let field_x = center_x - field_w / 2;
+        let field_y = output_size.h / 2 + ui.px(20);
+        let field_border = if state.lock.has_failed() {
+            colors::ACCENT_CRIMSON
+        } else {
+            colors::ACCENT_CYAN
+        };
+        let border = ui.px(2);
+        scene.rect(field_x - border, field_y - border, field_w + border * 2, field_h + border * 2, field_border);
+        scene.rect(field_x, field_y, field_w, field_h, colors::PANEL_BG);
+
+        let dots = state.lock.password_len().min(20) as i32;
+        let dot_size = ui.px(10);
+        let dots_w = dots * dot_size * 2 - dot_size;
+        for i in 0..dots {
+            scene.rect(
+                center_x - dots_w / 2 + i * dot_size * 2,
+                field_y + (field_h - dot_size) / 2,
+                dot_size,
+                dot_size,
+                colors::TEXT_PRIMARY,
+            );
+        }
+
+        let status = state.lock.status_text();
+        let status_size = ui.font(16);
+        let (status_w, _) = state.text.measure(&status, status_size);
+        scene.text(
+            center_x - status_w / 2,
+            field_y + field_h + ui.px(16),
+            &status,
+            status_size,
+            colors::TEXT_SECONDARY,
+        );
+    }
+
+    /// The software cursor (theme or client image at its hotspot), with a
+    /// busy indicator while an app is starting
+    fn build_cursor(state: &HeyDM, scene: &mut Scene) {
+        // Cursors of additional seats, below seat0's
+        for seat in state.seats.iter() {
+            let (x, y) = seat.cursor_position();
+            match state.cursor.default_frame() {
+                Some(frame) => {
+                    let (hx, hy) = frame.hotspot;
+                    scene.image(x as i32 - hx, y as i32 - hy, &frame.image);
+                }
+                None => scene.rect(x as i32 - 4, y as i32 - 4, 8, 8, colors::ACCENT_CRIMSON),
             }
         }
 
-        // ---- 5. Cursor (Glow) ----
         let (cx, cy) = state.window_manager.cursor_position();
-        frame.clear(
-            colors::ACCENT_CYAN.into(),
-            &[rect(cx as i32 - 4, cy as i32 - 4, 8, 8)],
-        )?;
+        let busy_for = state.launches.busy_for();
+        // Themes with a "progress" cursor replace the drawn spinner
+        let themed_busy =
+            busy_for.is_some() && state.cursor.has_theme_cursor(CursorIcon::Progress);
+
+        match state.cursor.current_frame(themed_busy) {
+            Some(frame) => {
+                let (hx, hy) = frame.hotspot;
+                scene.image(cx as i32 - hx, cy as i32 - hy, &frame.image);
+            }
+            None if state.cursor.is_hidden() => return,
+            // No cursor theme installed
+            None => scene.rect(cx as i32 - 4, cy as i32 - 4, 8, 8, colors::ACCENT_CYAN),
+        }
+
+        if let Some(busy_for) = busy_for.filter(|_| !themed_busy) {
+            // Eight dots on a ring; the bright one advances every 100ms
+            let center = (cx as f32 + 18.0, cy as f32 + 18.0);
+            let head = (busy_for.as_millis() / 100 % 8) as usize;
+            for i in 0..8 {
+                let angle = i as f32 * std::f32::consts::TAU / 8.0;
+                let x = center.0 + angle.cos() * 7.0;
+                let y = center.1 + angle.sin() * 7.0;
+                let age = (head + 8 - i) % 8;
+                let mut color = colors::ACCENT_CYAN;
+                color[3] = 1.0 - age as f32 * 0.11;
+                scene.rect(x as i32 - 2, y as i32 - 2, 4, 4, color);
+            }
+        }
+    }
+
+    /// Replay a recorded scene into the given frame, through `view`.
+    pub fn draw_scene<F: Frame>(
+        frame: &mut F,
+        scene: &Scene,
+        textures: &TextureCache<F::TextureId>,
+        view: View,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F::Error: 'static,
+    {
+        for cmd in scene.commands() {
+            match cmd {
+                DrawCmd::Rect { rect, color } => {
+                    let rect = view.map(*rect);
+                    let damage = Rectangle::from_size(rect.size);
+                    let color = textures.matrix().apply(*color);
+                    frame.draw_solid(rect, &[damage], color.into())?;
+                }
+                // Textures the cache could not create (e.g. no font) are skipped
+                DrawCmd::Text { x, y, key } => {
+                    if let Some(cached) = textures.text(key) {
+                        Self::draw_texture(frame, cached, *x, *y, view)?;
+                    }
+                }
+                DrawCmd::Image { x, y, image } => {
+                    if let Some(cached) = textures.image(image) {
+                        Self::draw_texture(frame, cached, *x, *y, view)?;
+                    }
+                }
+            }
+        }
 
         Ok(())
     }
+
+    /// Draw a cached texture at its natural size, as seen through `view`
+    fn draw_texture<F: Frame>(
+        frame: &mut F,
+        cached: &CachedTexture<F::TextureId>,
+        x: i32,
+        y: i32,
+        view: View,
+    ) -> Result<(), F::Error> {
+        let dst = view.map(rect(x, y, cached.size.w, cached.size.h));
+        frame.render_texture_from_to(
+            &cached.texture,
+            Rectangle::from_size(cached.size).to_f64(),
+            dst,
+            &[Rectangle::from_size(dst.size)],
+            &[],
+            Transform::Normal,
+            1.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::alloc_count::allocations;
+    use crate::texture::PixelBuffer;
+
+    #[test]
+    fn truncating_labels_does_not_copy_them() {
+        let mut label = "";
+        assert_eq!(allocations(|| label = truncate("Über den Wolken", 4)), 0);
+        assert_eq!(label, "Über");
+        assert_eq!(truncate("short", 10), "short");
+    }
+
+    #[test]
+    fn rebuilding_a_scene_reuses_its_buffer() {
+        let image: Image = Arc::new(PixelBuffer {
+            width: 2,
+            height: 2,
+            pixels: vec![0; 16],
+        });
+        let fill = |scene: &mut Scene| {
+            scene.clear();
+            for i in 0..64 {
+                scene.rect(i, i, 10, 10, colors::PANEL_BG);
+                scene.image(i, i, &image);
+            }
+        };
+
+        let mut scene = Scene::default();
+        fill(&mut scene);
+        assert_eq!(allocations(|| fill(&mut scene)), 0);
+        assert_eq!(scene.commands().len(), 128);
+    }
 }
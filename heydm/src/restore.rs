@@ -0,0 +1,218 @@
+// =============================================================================
+// heyDM — Layout Restore
+//
+// The window layout survives restarts and reboots. On exit (including the
+// SIGTERM of a shutdown) heyDM writes every window's app id, title,
+// workspace, floating geometry and fullscreen / maximized / tiled state, and
+// which workspaces are tiled with which layout, to
+//
+//     $XDG_STATE_HOME/heydm/window-layout.json   (~/.local/state by default)
+//
+// At the next start the tiling layouts come back right away. A window that
+// maps within RESTORE_WINDOW of startup takes the place of the first saved
+// window with its app id (one with the same title if there is one), which
+// is then used up. Windows without an app id and the scratchpad are not
+// saved; saved windows that do not reappear are forgotten at the next exit.
+// The login screen and headless runs neither save nor restore.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use smithay::utils::{Point, Rectangle, Size};
+use tracing::{debug, info, warn};
+
+use crate::state::HeyDM;
+use crate::tiling::{Layout, WorkspaceLayout};
+use crate::window::{Placement, TileSide};
+use crate::workspace::WORKSPACE_COUNT;
+
+/// How long after startup reappearing windows are put back
+const RESTORE_WINDOW: Duration = Duration::from_secs(300);
+
+/// A window as it was at the last exit
+#[derive(Debug)]
+struct SavedWindow {
+    app_id: String,
+    title: Option<String>,
+    placement: Placement,
+}
+
+/// Saved windows waiting to reappear
+#[derive(Debug)]
+pub struct LayoutRestore {
+    saved: Vec<SavedWindow>,
+    until: Instant,
+}
+
+/// Read the saved layout: restore the tiling layouts and keep the windows
+/// for when they map
+pub fn load(state: &mut HeyDM) {
+    let saved = layout_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| match serde_json::from_str::<Value>(&content) {
+            Ok(saved) => Some(saved),
+            Err(e) => {
+                warn!("Ignoring the saved window layout: {e}");
+                None
+            }
+        })
+        .unwrap_or(Value::Null);
+
+    let windows: Vec<SavedWindow> = saved["windows"]
+        .as_array()
+        .map(|windows| windows.iter().filter_map(parse_window).collect())
+        .unwrap_or_default();
+    if let Some(workspaces) = saved["workspaces"].as_array() {
+        let layouts: HashMap<usize, WorkspaceLayout> =
+            workspaces.iter().filter_map(parse_workspace).collect();
+        state.window_manager.restore_tiling_layouts(layouts);
+    }
+    if !windows.is_empty() {
+        info!("Restoring the layout of {} window(s) as they reappear", windows.len());
+    }
+    state.layout_restore = Some(LayoutRestore {
+        saved: windows,
+        until: Instant::now() + RESTORE_WINDOW,
+    });
+}
+
+/// A window mapped: put it back where a saved window with its app id was
+pub fn window_mapped(state: &mut HeyDM, id: u32) {
+    let Some(restore) = state.layout_restore.as_mut() else {
+        return;
+    };
+    if restore.saved.is_empty() || Instant::now() > restore.until {
+        return;
+    }
+    let Some((title, Some(app_id))) = state
+        .window_manager
+        .window_by_id(id)
+        .map(|w| w.title_and_app_id())
+    else {
+        return;
+    };
+    let same_app = |saved: &SavedWindow| saved.app_id == app_id;
+    let Some(idx) = restore
+        .saved
+        .iter()
+        .position(|saved| same_app(saved) && saved.title == title)
+        .or_else(|| restore.saved.iter().position(same_app))
+    else {
+        return;
+    };
+    let saved = restore.saved.remove(idx);
+    debug!("Restoring the place of {app_id} window {id}");
+    state
+        .window_manager
+        .restore_placement(id, &saved.placement, &state.output_size);
+    state.refresh_keyboard_focus();
+}
+
+/// Write the current layout (on exit)
+pub fn save(state: &HeyDM) {
+    if state.layout_restore.is_none() {
+        return;
+    }
+    let Some(path) = layout_path() else {
+        return;
+    };
+    let wm = &state.window_manager;
+    let windows: Vec<Value> = wm
+        .windows()
+        .iter()
+        .filter(|window| !window.is_scratchpad())
+        .filter_map(|window| {
+            let (title, app_id) = window.title_and_app_id();
+            Some(window_json(&app_id?, title.as_deref(), &wm.placement(window)))
+        })
+        .collect();
+    let workspaces: Vec<Value> = wm
+        .tiling_layouts()
+        .into_iter()
+        .map(|(ws, layout)| {
+            json!({
+                "number": ws,
+                "layout": layout.layout.name(),
+                "master_ratio": layout.master_ratio,
+            })
+        })
+        .collect();
+    let content = json!({ "windows": windows, "workspaces": workspaces });
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(&path, format!("{content:#}\n")));
+    match result {
+        Ok(()) => info!("Saved the layout of {} window(s)", windows.len()),
+        Err(e) => warn!("Failed to save the window layout to {}: {e}", path.display()),
+    }
+}
+
+fn window_json(app_id: &str, title: Option<&str>, placement: &Placement) -> Value {
+    let geometry = placement.geometry;
+    json!({
+        "app_id": app_id,
+        "title": title,
+        "workspace": placement.workspace,
+        "x": geometry.loc.x,
+        "y": geometry.loc.y,
+        "width": geometry.size.w,
+        "height": geometry.size.h,
+        "fullscreen": placement.fullscreen,
+        "maximized": placement.maximized,
+        "tiled": placement.tiled.map(TileSide::name),
+        "floating": placement.floating,
+        "minimized": placement.minimized,
+    })
+}
+
+fn parse_window(value: &Value) -> Option<SavedWindow> {
+    let number = |key: &str| value[key].as_i64().and_then(|n| i32::try_from(n).ok());
+    let flag = |key: &str| value[key].as_bool().unwrap_or(false);
+    let geometry = Rectangle::new(
+        Point::from((number("x")?, number("y")?)),
+        Size::from((number("width")?, number("height")?)),
+    );
+    if geometry.size.w <= 0 || geometry.size.h <= 0 {
+        return None;
+    }
+    Some(SavedWindow {
+        app_id: value["app_id"].as_str()?.to_string(),
+        title: value["title"].as_str().map(str::to_string),
+        placement: Placement {
+            workspace: usize::try_from(number("workspace")?).ok()?,
+            geometry,
+            fullscreen: flag("fullscreen"),
+            maximized: flag("maximized"),
+            tiled: value["tiled"].as_str().and_then(TileSide::parse),
+            floating: flag("floating"),
+            minimized: flag("minimized"),
+        },
+    })
+}
+
+fn parse_workspace(value: &Value) -> Option<(usize, WorkspaceLayout)> {
+    let ws = usize::try_from(value["number"].as_u64()?)
+        .ok()
+        .filter(|ws| (1..=WORKSPACE_COUNT).contains(ws))?;
+    let mut layout = WorkspaceLayout {
+        layout: Layout::parse(value["layout"].as_str()?)?,
+        master_ratio: value["master_ratio"].as_f64()?,
+    };
+    // Clamps a hand-edited ratio
+    layout.adjust_ratio(0.0);
+    Some((ws, layout))
+}
+
+/// $XDG_STATE_HOME/heydm/window-layout.json (~/.local/state by default)
+fn layout_path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("heydm").join("window-layout.json"))
+}
@@ -0,0 +1,147 @@
+// =============================================================================
+// heyDM — Sandboxed Clients
+//
+// Each new Wayland connection is labelled with the sandbox its process runs
+// in, decided once from the peer credentials of the socket:
+//
+//   - Flatpak: the process root has a /.flatpak-info (which apps inside the
+//     sandbox cannot write); its [Application] name is the app id
+//   - Snap:    the AppArmor profile is "snap.<name>.<app>" in enforce mode;
+//     classic snaps are not confined and count as host apps
+//   - host:    anything else
+//
+// The label ("host", "flatpak:<app id>", "snap:<name>") is kept on the
+// client's ClientState and restricts what the client can do:
+//
+//   - no hey_shell_v1, even when its executable is listed as trusted
+//     (see shell.rs)
+//   - no input method or virtual keyboard, which could type into or read
+//     the keystrokes of other windows
+//
+// Window rules can match the label as well as the app id:
+//
+//     [window "flatpak:com.valvesoftware.Steam"]
+//     tearing = true
+//
+//     [window "sandboxed"]          # every Flatpak and Snap app
+//     vrr = false
+//
+// A sandboxed app only gets the rules of the app id its sandbox was made
+// for, not of whatever app id it claims on its windows.
+// =============================================================================
+
+use std::os::unix::net::UnixStream;
+
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use smithay::reexports::wayland_server::{Client, Resource};
+use tracing::info;
+
+use crate::state::ClientState;
+use crate::window::WindowElement;
+
+/// Window rule section matching every sandboxed app
+pub const SANDBOXED_RULE: &str = "sandboxed";
+
+/// The sandbox a client runs in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Sandbox {
+    #[default]
+    Host,
+    Flatpak { app_id: String },
+    Snap { name: String },
+}
+
+impl Sandbox {
+    pub fn is_sandboxed(&self) -> bool {
+        !matches!(self, Self::Host)
+    }
+
+    /// "host", "flatpak:<app id>" or "snap:<name>"
+    pub fn label(&self) -> String {
+        match self {
+            Self::Host => "host".to_string(),
+            Self::Flatpak { app_id } => format!("flatpak:{app_id}"),
+            Self::Snap { name } => format!("snap:{name}"),
+        }
+    }
+
+    /// Whether a window of this client may use the rules of `app_id`
+    pub fn may_claim(&self, app_id: &str) -> bool {
+        match self {
+            Self::Host => true,
+            Self::Flatpak { app_id: own } => own == app_id,
+            // Snap app ids are not tied to the snap name
+            Self::Snap { .. } => false,
+        }
+    }
+}
+
+/// The sandbox of the process on the other end of a new connection
+pub fn detect(stream: &UnixStream) -> Sandbox {
+    let Ok(credentials) = getsockopt(stream, PeerCredentials) else {
+        return Sandbox::Host;
+    };
+    let pid = credentials.pid();
+    let sandbox = flatpak(pid).or_else(|| snap(pid)).unwrap_or_default();
+    if sandbox.is_sandboxed() {
+        info!("Sandboxed client connected (pid {pid}): {}", sandbox.label());
+    }
+    sandbox
+}
+
+/// The app id from the sandbox's /.flatpak-info
+fn flatpak(pid: i32) -> Option<Sandbox> {
+    let info = std::fs::read_to_string(format!("/proc/{pid}/root/.flatpak-info")).ok()?;
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if let Some(name) = line.strip_prefix("name=").filter(|_| in_application) {
+            return Some(Sandbox::Flatpak {
+                app_id: name.trim().to_string(),
+            });
+        }
+    }
+    // A sandbox without a name is still a sandbox
+    Some(Sandbox::Flatpak {
+        app_id: String::new(),
+    })
+}
+
+/// The snap name from a confining "snap.<name>.<app> (enforce)" profile
+fn snap(pid: i32) -> Option<Sandbox> {
+    let label = std::fs::read_to_string(format!("/proc/{pid}/attr/apparmor/current"))
+        .or_else(|_| std::fs::read_to_string(format!("/proc/{pid}/attr/current")))
+        .ok()?;
+    let (profile, mode) = label.trim().split_once(' ')?;
+    if mode != "(enforce)" {
+        return None;
+    }
+    let name = profile.strip_prefix("snap.")?.split('.').next()?;
+    Some(Sandbox::Snap {
+        name: name.to_string(),
+    })
+}
+
+/// The sandbox of a connected client
+pub fn of_client(client: &Client) -> Sandbox {
+    client
+        .get_data::<ClientState>()
+        .map(|data| data.sandbox.clone())
+        .unwrap_or_default()
+}
+
+/// Filter for globals sandboxed clients must not see
+pub fn is_unconfined(client: &Client) -> bool {
+    !of_client(client).is_sandboxed()
+}
+
+/// The sandbox of the client owning `window`
+pub fn of_window(window: &WindowElement) -> Sandbox {
+    window
+        .toplevel()
+        .wl_surface()
+        .client()
+        .map(|client| of_client(&client))
+        .unwrap_or_default()
+}
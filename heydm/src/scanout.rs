@@ -0,0 +1,84 @@
+// =============================================================================
+// heyDM — Direct Scanout
+//
+// A fullscreen window whose buffer covers the whole output does not need to
+// be composited: the DRM backend puts the client's dmabuf straight on the
+// primary plane, which saves a copy every frame (lower latency, less power).
+// This only happens while nothing would be drawn on top of the window — no
+// launcher, switcher, notification, menu, tooltip or visible pointer (see
+// HeyDM::overlays_visible); as soon as one appears heyDM composites again.
+// Clients get dmabuf support from the linux-dmabuf global the DRM backend
+// creates.
+// =============================================================================
+
+use std::time::Duration;
+
+use smithay::backend::allocator::dmabuf::Dmabuf;
+use smithay::backend::allocator::Buffer;
+use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
+use smithay::reexports::wayland_server::protocol::wl_output;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Physical, Size};
+use smithay::wayland::compositor::{get_children, with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::dmabuf::get_dmabuf;
+use smithay::wayland::viewporter::ViewportCachedState;
+
+use crate::state::HeyDM;
+
+/// A client buffer that can go on the primary plane as it is
+pub struct Candidate {
+    pub surface: WlSurface,
+    pub buffer: WlBuffer,
+    pub dmabuf: Dmabuf,
+}
+
+/// The fullscreen window's buffer, when nothing covers it and it fills an
+/// output of `mode_size` without scaling, cropping or a transform
+pub fn candidate(state: &HeyDM, mode_size: Size<i32, Physical>) -> Option<Candidate> {
+    // Client buffers would skip the color filter
+    if state.overlays_visible() || state.color.is_active() {
+        return None;
+    }
+    let surface = state.fullscreen_window()?.wl_surface()?;
+    // Subsurfaces (e.g. a video player's controls) need compositing
+    if !get_children(&surface).is_empty() {
+        return None;
+    }
+
+    let (buffer, dmabuf) = with_states(&surface, |states| {
+        if states.cached_state.get::<ViewportCachedState>().current().src.is_some() {
+            return None;
+        }
+        let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+        let attributes = attributes.current();
+        if attributes.buffer_scale != 1 || attributes.buffer_transform != wl_output::Transform::Normal {
+            return None;
+        }
+        let Some(BufferAssignment::NewBuffer(buffer)) = &attributes.buffer else {
+            return None;
+        };
+        let dmabuf = get_dmabuf(buffer).ok()?.clone();
+        Some((buffer.clone(), dmabuf))
+    })?;
+
+    let size = dmabuf.size();
+    if (size.w, size.h) != (mode_size.w, mode_size.h) {
+        return None;
+    }
+    Some(Candidate {
+        surface,
+        buffer,
+        dmabuf,
+    })
+}
+
+/// Tell the client its buffer reached the screen, so it draws the next one
+pub fn send_frame(state: &HeyDM, surface: &WlSurface) {
+    let time = Duration::from(state.clock.now()).as_millis() as u32;
+    with_states(surface, |states| {
+        let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+        for callback in attributes.current().frame_callbacks.drain(..) {
+            callback.done(time);
+        }
+    });
+}
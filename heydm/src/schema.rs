@@ -0,0 +1,774 @@
+// =============================================================================
+// heyDM — Configuration Schema & Diagnostics
+//
+// Describes every section and key the config file accepts. The same table
+// drives three things:
+//   - Validation while parsing (unknown keys, bad values, typo suggestions)
+//   - `heydm config-schema`, which prints a JSON schema for editors and the
+//     settings app (sections become top-level objects, keys their properties)
+//   - `heydm config-check`, which reports diagnostics for a config file
+// =============================================================================
+
+use std::fmt;
+
+use serde_json::{json, Map, Value};
+
+use crate::accessx::MAX_KEY_DELAY_MS;
+use crate::devices::{ACCEL_PROFILES, SCROLL_METHODS};
+use crate::filters::{COLOR_FILTERS, NIGHT_LIGHT_MODES};
+use crate::output::{MAX_OUTPUT_SCALE, MIN_OUTPUT_SCALE, OUTPUT_TRANSFORMS};
+use crate::power::CRITICAL_ACTIONS;
+use crate::realtime::MAX_PRIORITY;
+use crate::tearing::VRR_MODES;
+use crate::theme::{MAX_SCALE, MIN_SCALE};
+use crate::tiling::{LAYOUTS, MAX_MASTER_RATIO, MIN_MASTER_RATIO};
+use crate::wallpaper::{COLOR_SCHEMES, WALLPAPER_MODES};
+use crate::workspace::WORKSPACE_COUNT;
+
+/// The type of a configuration value
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum ValueType {
+    Bool,
+    Integer { min: i64, max: i64 },
+    Float { min: f64, max: f64 },
+    Enum(&'static [&'static str]),
+    String,
+    /// "primary", "secondary" or an output connector name
+    Output,
+}
+
+impl ValueType {
+    /// Check a raw value, returning a description of the problem if invalid
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Self::Bool => match value {
+                "true" | "false" | "yes" | "no" | "on" | "off" => Ok(()),
+                _ => Err(format!("expected true or false, got '{value}'")),
+            },
+            Self::Integer { min, max } => match value.parse::<i64>() {
+                Ok(n) if (*min..=*max).contains(&n) => Ok(()),
+                Ok(n) => Err(format!("{n} is out of range ({min}..={max})")),
+                Err(_) => Err(format!("expected an integer, got '{value}'")),
+            },
+            Self::Float { min, max } => match value.parse::<f64>() {
+                Ok(n) if (*min..=*max).contains(&n) => Ok(()),
+                Ok(n) => Err(format!("{n} is out of range ({min}..={max})")),
+                Err(_) => Err(format!("expected a number, got '{value}'")),
+            },
+            Self::Enum(options) => {
+                if options.contains(&value) {
+                    Ok(())
+                } else {
+                    let mut message =
+                        format!("invalid value '{value}' (expected {})", options.join(", "));
+                    if let Some(suggestion) = suggest(value, options.iter().copied()) {
+                        message.push_str(&format!(", did you mean '{suggestion}'?"));
+                    }
+                    Err(message)
+                }
+            }
+            Self::String | Self::Output => {
+                if value.is_empty() {
+                    Err("value must not be empty".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn json(&self) -> Value {
+        match self {
+            Self::Bool => json!({ "type": "boolean" }),
+            Self::Integer { min, max } => {
+                json!({ "type": "integer", "minimum": min, "maximum": max })
+            }
+            Self::Float { min, max } => {
+                json!({ "type": "number", "minimum": min, "maximum": max })
+            }
+            Self::Enum(options) => json!({ "type": "string", "enum": options }),
+            Self::String => json!({ "type": "string", "minLength": 1 }),
+            Self::Output => json!({
+                "type": "string",
+                "minLength": 1,
+                "examples": ["primary", "secondary", "HDMI-A-1"],
+            }),
+        }
+    }
+}
+
+/// A fixed key inside a section
+#[derive(Debug)]
+pub struct KeySchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub ty: ValueType,
+    pub default: Option<&'static str>,
+}
+
+/// A family of keys matched by pattern (e.g. workspace numbers)
+#[derive(Debug)]
+pub struct PatternKeys {
+    /// Regular expression used in the JSON schema
+    pub pattern: &'static str,
+    pub description: &'static str,
+    pub ty: ValueType,
+}
+
+/// A `[section]` (or `[section "arg"]`) of the config file
+#[derive(Debug)]
+pub struct SectionSchema {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Description of the header argument, for sections that take one
+    pub arg: Option<&'static str>,
+    pub keys: &'static [KeySchema],
+    pub pattern_keys: Option<PatternKeys>,
+}
+
+impl SectionSchema {
+    pub fn key(&self, name: &str) -> Option<&KeySchema> {
+        self.keys.iter().find(|k| k.name == name)
+    }
+
+    fn json(&self) -> Value {
+        let mut properties = Map::new();
+        for key in self.keys {
+            let mut value = key.ty.json();
+            value["description"] = json!(key.description);
+            if let Some(default) = key.default {
+                value["default"] = json!(default);
+            }
+            properties.insert(key.name.to_string(), value);
+        }
+
+        let mut schema = json!({
+            "type": "object",
+            "description": self.description,
+            "properties": properties,
+            "additionalProperties": false,
+        });
+        if let Some(pattern) = &self.pattern_keys {
+            let mut value = pattern.ty.json();
+            value["description"] = json!(pattern.description);
+            let mut patterns = Map::new();
+            patterns.insert(pattern.pattern.to_string(), value);
+            schema["patternProperties"] = Value::Object(patterns);
+        }
+
+        match self.arg {
+            // `[output "DP-1"]` → { "output": { "DP-1": { ... } } }
+            Some(arg) => json!({
+                "type": "object",
+                "description": format!("{} Keyed by {arg}.", self.description),
+                "additionalProperties": schema,
+            }),
+            None => schema,
+        }
+    }
+}
+
+/// libinput settings shared by `[input]` and `[device "name"]`
+const INPUT_KEYS: &[KeySchema] = &[
+    KeySchema {
+        name: "tap",
+        description: "Tap-to-click on touchpads",
+        ty: ValueType::Bool,
+        default: None,
+    },
+    KeySchema {
+        name: "natural_scroll",
+        description: "Scroll content in the direction the fingers move",
+        ty: ValueType::Bool,
+        default: None,
+    },
+    KeySchema {
+        name: "accel_profile",
+        description: "Pointer acceleration curve",
+        ty: ValueType::Enum(ACCEL_PROFILES),
+        default: None,
+    },
+    KeySchema {
+        name: "accel_speed",
+        description: "Pointer speed from -1.0 (slowest) to 1.0 (fastest)",
+        ty: ValueType::Float { min: -1.0, max: 1.0 },
+        default: Some("0.0"),
+    },
+    KeySchema {
+        name: "scroll_method",
+        description: "How scroll events are generated",
+        ty: ValueType::Enum(SCROLL_METHODS),
+        default: None,
+    },
+];
+
+/// Every section heydm understands
+pub const SCHEMA: &[SectionSchema] = &[
+    SectionSchema {
+        name: "workspaces",
+        description: "Pin workspaces to outputs. Keys are a workspace number or range.",
+        arg: None,
+        keys: &[],
+        pattern_keys: Some(PatternKeys {
+            pattern: "^[1-9](-[1-9])?$",
+            description: "Output for the workspace(s): primary, secondary or a connector name",
+            ty: ValueType::Output,
+        }),
+    },
+    SectionSchema {
+        name: "input",
+        description: "libinput settings applied to every input device.",
+        arg: None,
+        keys: INPUT_KEYS,
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "keyboard",
+        description: "XKB keymap of every keyboard.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "layout",
+                description: "Comma-separated XKB layouts, e.g. us,de",
+                ty: ValueType::String,
+                default: Some("us"),
+            },
+            KeySchema {
+                name: "variant",
+                description: "XKB variants, one per layout, e.g. nodeadkeys",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "options",
+                description: "XKB options, e.g. grp:alt_shift_toggle,caps:escape",
+                ty: ValueType::String,
+                default: None,
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "accessibility",
+        description: "Keyboard accessibility filters.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "sticky_keys",
+                description: "A modifier tapped on its own applies to the next key; \
+                              tapped twice it stays down until tapped again",
+                ty: ValueType::Bool,
+                default: Some("false"),
+            },
+            KeySchema {
+                name: "slow_keys",
+                description: "Milliseconds a key must be held before it counts (0 = off)",
+                ty: ValueType::Integer { min: 0, max: MAX_KEY_DELAY_MS },
+                default: Some("0"),
+            },
+            KeySchema {
+                name: "bounce_keys",
+                description: "Milliseconds after a release during which the same key is \
+                              ignored (0 = off)",
+                ty: ValueType::Integer { min: 0, max: MAX_KEY_DELAY_MS },
+                default: Some("0"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "device",
+        description: "libinput settings for one device, overriding [input].",
+        arg: Some("the libinput device name"),
+        keys: INPUT_KEYS,
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "tiling",
+        description: "Automatic tiling (toggled per workspace with Super+T).",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "enabled",
+                description: "Tile every workspace from the start",
+                ty: ValueType::Bool,
+                default: Some("false"),
+            },
+            KeySchema {
+                name: "layout",
+                description: "Layout for newly tiled workspaces",
+                ty: ValueType::Enum(LAYOUTS),
+                default: Some("master_stack"),
+            },
+            KeySchema {
+                name: "master_ratio",
+                description: "Share of the width taken by the master area",
+                ty: ValueType::Float { min: MIN_MASTER_RATIO, max: MAX_MASTER_RATIO },
+                default: Some("0.55"),
+            },
+            KeySchema {
+                name: "gap",
+                description: "Pixels around and between tiled windows",
+                ty: ValueType::Integer { min: 0, max: 64 },
+                default: Some("8"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "appearance",
+        description: "Wallpaper, accent color and icon theme.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "wallpaper",
+                description: "Image file drawn behind the windows (PNG, JPEG or WebP)",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "wallpaper_mode",
+                description: "How the wallpaper is laid out: scaled to cover (fill) or to fit, \
+                              centered unscaled, or tiled",
+                ty: ValueType::Enum(WALLPAPER_MODES),
+                default: Some("fill"),
+            },
+            KeySchema {
+                name: "dynamic_accent",
+                description: "Use the wallpaper's dominant color as accent and export it to apps",
+                ty: ValueType::Bool,
+                default: Some("true"),
+            },
+            KeySchema {
+                name: "accent",
+                description: "Accent color as #rrggbb, used without a wallpaper accent",
+                ty: ValueType::String,
+                default: Some("#d43b47"),
+            },
+            KeySchema {
+                name: "color_scheme",
+                description: "Whether apps should prefer a dark or a light style",
+                ty: ValueType::Enum(COLOR_SCHEMES),
+                default: Some("dark"),
+            },
+            KeySchema {
+                name: "icon_theme",
+                description: "Icon theme for application icons in the launcher",
+                ty: ValueType::String,
+                default: Some("hicolor"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "theme",
+        description: "Font and scale of the panel, launcher and overlays.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "font",
+                description: "Font family name or path to a .ttf/.otf file",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "font_size",
+                description: "Base text size in pixels before scaling",
+                ty: ValueType::Integer { min: 6, max: 48 },
+                default: Some("13"),
+            },
+            KeySchema {
+                name: "scale",
+                description: "Extra scale factor for compositor-drawn UI, on top of the output scale",
+                ty: ValueType::Float { min: MIN_SCALE, max: MAX_SCALE },
+                default: Some("1.0"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "color",
+        description: "Color filter and night light for the whole output.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "filter",
+                description: "Color filter at startup; Super+Alt+F cycles through them",
+                ty: ValueType::Enum(COLOR_FILTERS),
+                default: Some("none"),
+            },
+            KeySchema {
+                name: "night_light",
+                description: "Warmer colors: on the schedule below, always or never; \
+                              Super+Alt+N overrides it",
+                ty: ValueType::Enum(NIGHT_LIGHT_MODES),
+                default: Some("auto"),
+            },
+            KeySchema {
+                name: "night_start",
+                description: "Local time (HH:MM) night light starts fading in",
+                ty: ValueType::String,
+                default: Some("20:00"),
+            },
+            KeySchema {
+                name: "night_end",
+                description: "Local time (HH:MM) night light has faded out",
+                ty: ValueType::String,
+                default: Some("07:00"),
+            },
+            KeySchema {
+                name: "night_temperature",
+                description: "Color temperature of night light in kelvin",
+                ty: ValueType::Integer { min: 1000, max: 6500 },
+                default: Some("4000"),
+            },
+            KeySchema {
+                name: "latitude",
+                description: "Degrees north; with longitude, night light follows sunset and \
+                              sunrise instead of night_start and night_end",
+                ty: ValueType::Float { min: -90.0, max: 90.0 },
+                default: None,
+            },
+            KeySchema {
+                name: "longitude",
+                description: "Degrees east, used with latitude",
+                ty: ValueType::Float { min: -180.0, max: 180.0 },
+                default: None,
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "preset",
+        description: "A named window layout, applied with its key binding or over IPC.",
+        arg: Some("the preset name"),
+        keys: &[
+            KeySchema {
+                name: "workspace",
+                description: "Workspace the windows are moved to (default: the active one)",
+                ty: ValueType::Integer { min: 1, max: WORKSPACE_COUNT as i64 },
+                default: None,
+            },
+            KeySchema {
+                name: "key",
+                description: "Digit of the Super+Ctrl binding that applies the preset",
+                ty: ValueType::Integer { min: 1, max: WORKSPACE_COUNT as i64 },
+                default: None,
+            },
+        ],
+        pattern_keys: Some(PatternKeys {
+            pattern: "^[A-Za-z0-9._-]+$",
+            description: "App id: 'x y width height [command]', geometry in percent of the \
+                          work area and the command that starts the app when it has no window",
+            ty: ValueType::String,
+        }),
+    },
+    SectionSchema {
+        name: "output",
+        description: "Settings of a monitor, identified by its EDID rather than the port.",
+        arg: Some("the monitor, e.g. \"Dell U2720Q\" (see `list outputs`), or a connector name"),
+        keys: &[
+            KeySchema {
+                name: "mode",
+                description: "Resolution and refresh rate as 'WIDTHxHEIGHT[@HZ]'; the preferred mode by default",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "transform",
+                description: "Rotation (clockwise) and flipping of the picture",
+                ty: ValueType::Enum(OUTPUT_TRANSFORMS),
+                default: Some("normal"),
+            },
+            KeySchema {
+                name: "scale",
+                description: "Scale factor for clients (fractional, e.g. 1.25 or 1.5) and heyDM's own UI",
+                ty: ValueType::Float {
+                    min: MIN_OUTPUT_SCALE,
+                    max: MAX_OUTPUT_SCALE,
+                },
+                default: Some("1.0"),
+            },
+            KeySchema {
+                name: "position",
+                description: "Top-left corner in the global space as 'x,y', in logical pixels",
+                ty: ValueType::String,
+                default: Some("0,0"),
+            },
+            KeySchema {
+                name: "wallpaper",
+                description: "Image file for this monitor instead of [appearance] wallpaper",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "wallpaper_mode",
+                description: "Wallpaper layout for this monitor instead of [appearance] wallpaper_mode",
+                ty: ValueType::Enum(WALLPAPER_MODES),
+                default: None,
+            },
+            KeySchema {
+                name: "vrr",
+                description: "Adaptive sync: always, only for fullscreen windows scanned out directly, or never",
+                ty: ValueType::Enum(VRR_MODES),
+                default: Some("off"),
+            },
+            KeySchema {
+                name: "allow_tearing",
+                description: "Let fullscreen clients that ask for it (wp_tearing_control) present without waiting for vblank",
+                ty: ValueType::Bool,
+                default: Some("true"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "window",
+        description: "Presentation settings of one application's windows.",
+        arg: Some("the app id, e.g. \"steam_app_570\", a sandbox label like \"flatpak:com.valvesoftware.Steam\", or \"sandboxed\""),
+        keys: &[
+            KeySchema {
+                name: "tearing",
+                description: "Tear while fullscreen even without the client asking (true), or never (false)",
+                ty: ValueType::Bool,
+                default: None,
+            },
+            KeySchema {
+                name: "vrr",
+                description: "Allow adaptive sync while the window is fullscreen",
+                ty: ValueType::Bool,
+                default: Some("true"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "seat",
+        description: "An additional seat driven by its own input devices.",
+        arg: Some("the seat name, e.g. seat1"),
+        keys: &[
+            KeySchema {
+                name: "devices",
+                description: "Comma-separated libinput device names that belong to the seat",
+                ty: ValueType::String,
+                default: None,
+            },
+            KeySchema {
+                name: "output",
+                description: "Connector name of the output the seat's cursor stays on (e.g. HDMI-A-1)",
+                ty: ValueType::String,
+                default: None,
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "shell",
+        description: "heyOS shell components allowed to use the hey_shell_v1 protocol.",
+        arg: None,
+        keys: &[KeySchema {
+            name: "trusted",
+            description: "Comma-separated executable paths that may set the wallpaper, \
+                          follow workspaces and reserve screen edges",
+            ty: ValueType::String,
+            default: Some("/usr/bin/hey-dock, /usr/bin/hey-settings"),
+        }],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "performance",
+        description: "Scheduling of the compositor thread.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "realtime",
+                description: "Run input and rendering with SCHED_RR (directly with CAP_SYS_NICE, \
+                              otherwise through RealtimeKit)",
+                ty: ValueType::Bool,
+                default: Some("false"),
+            },
+            KeySchema {
+                name: "priority",
+                description: "SCHED_RR priority; RealtimeKit may grant less",
+                ty: ValueType::Integer {
+                    min: 1,
+                    max: MAX_PRIORITY as i64,
+                },
+                default: Some("10"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "power",
+        description: "Low battery warnings.",
+        arg: None,
+        keys: &[
+            KeySchema {
+                name: "warn_levels",
+                description: "Comma-separated battery percentages that show a warning while \
+                              discharging; the lowest one is critical",
+                ty: ValueType::String,
+                default: Some("20, 10, 5"),
+            },
+            KeySchema {
+                name: "critical_level",
+                description: "Battery percentage that triggers critical_action",
+                ty: ValueType::Integer { min: 1, max: 50 },
+                default: Some("3"),
+            },
+            KeySchema {
+                name: "critical_action",
+                description: "What logind does at critical_level",
+                ty: ValueType::Enum(CRITICAL_ACTIONS),
+                default: Some("none"),
+            },
+        ],
+        pattern_keys: None,
+    },
+    SectionSchema {
+        name: "compat",
+        description: "Workarounds for misbehaving clients.",
+        arg: None,
+        keys: &[KeySchema {
+            name: "tolerate_protocol_errors",
+            description: "Comma-separated app ids whose protocol violations are logged \
+                          instead of disconnecting the client",
+            ty: ValueType::String,
+            default: None,
+        }],
+        pattern_keys: None,
+    },
+];
+
+/// Look up a section by name
+pub fn section(name: &str) -> Option<&'static SectionSchema> {
+    SCHEMA.iter().find(|s| s.name == name)
+}
+
+/// JSON schema for the whole config file (sections as top-level properties)
+pub fn json_schema() -> Value {
+    let sections: Map<String, Value> = SCHEMA
+        .iter()
+        .map(|section| (section.name.to_string(), section.json()))
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "heyDM configuration",
+        "description": format!(
+            "INI-style config at $XDG_CONFIG_HOME/heydm/config. Each [section] maps to a \
+             top-level property. Workspaces are numbered 1-{WORKSPACE_COUNT}."
+        ),
+        "type": "object",
+        "properties": sections,
+        "additionalProperties": false,
+    })
+}
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The setting was ignored but the rest of the file is fine
+    Warning,
+    /// The line could not be understood at all
+    Error,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+/// A problem found while parsing the config file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line and column
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn warning(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Machine-readable form for `config-check --json`
+    pub fn json(&self, file: &str) -> Value {
+        json!({
+            "file": file,
+            "line": self.line,
+            "column": self.column,
+            "severity": self.severity.as_str(),
+            "message": self.message,
+        })
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line,
+            self.column,
+            self.severity.as_str(),
+            self.message
+        )
+    }
+}
+
+/// "unknown <what> '<name>'", plus a suggestion when a close match exists
+pub fn unknown(what: &str, name: &str, candidates: impl Iterator<Item = &'static str>) -> String {
+    match suggest(name, candidates) {
+        Some(suggestion) => format!("unknown {what} '{name}', did you mean '{suggestion}'?"),
+        None => format!("unknown {what} '{name}'"),
+    }
+}
+
+/// The closest candidate within a typo-sized edit distance
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(2);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
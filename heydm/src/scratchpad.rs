@@ -0,0 +1,86 @@
+// =============================================================================
+// heyDM — Scratchpad
+//
+// A dropdown terminal: Super+grave slides it down from the top edge over
+// whatever workspace is active, focuses it if it is shown but not focused,
+// and slides it back up otherwise. It follows workspace switches while
+// shown and keeps running while hidden.
+//
+// The first press starts the terminal with the app id heydm-scratchpad;
+// the window that maps with that app id becomes the scratchpad (see
+// WindowManager::adopt_scratchpad). Closing it ends the scratchpad until
+// the next press.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use calloop::timer::{TimeoutAction, Timer};
+use tracing::{info, warn};
+
+use crate::state::{HeyDM, TERMINAL};
+
+/// App id the scratchpad terminal is started with
+pub const SCRATCHPAD_APP_ID: &str = "heydm-scratchpad";
+
+/// A started terminal that has not mapped yet keeps later presses from
+/// starting another for this long
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Slide animation step
+const FRAME: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Default)]
+pub struct Scratchpad {
+    launched: Option<Instant>,
+}
+
+/// Super+grave
+pub fn toggle(state: &mut HeyDM) {
+    if state.window_manager.scratchpad().is_some() {
+        if state.window_manager.toggle_scratchpad(&state.output_size) {
+            animate(state);
+        }
+        state.refresh_keyboard_focus();
+        return;
+    }
+    let launching = state
+        .scratchpad
+        .launched
+        .is_some_and(|launched| launched.elapsed() < LAUNCH_TIMEOUT);
+    if launching {
+        return;
+    }
+    info!("Starting the scratchpad terminal");
+    state.scratchpad.launched = Some(Instant::now());
+    state.launch(&format!("{TERMINAL} --class {SCRATCHPAD_APP_ID}"));
+}
+
+/// A window mapped: adopt it if it is the scratchpad terminal
+pub fn window_mapped(state: &mut HeyDM, id: u32) {
+    let is_scratchpad = state
+        .window_manager
+        .window_by_id(id)
+        .and_then(|w| w.title_and_app_id().1)
+        .is_some_and(|app_id| app_id == SCRATCHPAD_APP_ID);
+    if !is_scratchpad || !state.window_manager.adopt_scratchpad(id, &state.output_size) {
+        return;
+    }
+    state.scratchpad.launched = None;
+    state.refresh_keyboard_focus();
+    animate(state);
+}
+
+/// Move the scratchpad every frame until its slide is over
+fn animate(state: &mut HeyDM) {
+    let result = state
+        .loop_handle
+        .insert_source(Timer::from_duration(FRAME), |_, _, state| {
+            if state.window_manager.animate_scratchpad(&state.output_size) {
+                return TimeoutAction::ToDuration(FRAME);
+            }
+            state.refresh_keyboard_focus();
+            TimeoutAction::Drop
+        });
+    if let Err(e) = result {
+        warn!("Failed to animate the scratchpad: {e}");
+    }
+}
@@ -0,0 +1,261 @@
+// =============================================================================
+// heyDM — Additional Seats
+//
+// Lets two people share one machine with their own keyboard and mouse.
+// Input devices listed in a `[seat "name"]` section drive that seat instead
+// of seat0:
+//
+//     [seat "seat1"]
+//     devices = USB Keyboard, Logitech USB Optical Mouse
+//     output = HDMI-A-1
+//
+// Each additional seat has its own wl_seat, keyboard focus, pointer focus
+// and cursor (drawn with the theme's default arrow). Clicking a window gives
+// it the seat's keyboard focus without touching seat0's focus or stacking.
+// Compositor keybindings, gestures, the panel and the overlays stay with
+// seat0. A seat's cursor stays on its `output`, so each person works on
+// their own monitor; without one, or while it is unplugged, the seat uses
+// the primary output.
+// =============================================================================
+
+use std::collections::BTreeMap;
+
+use smithay::backend::input::{
+    AbsolutePositionEvent, Axis, ButtonState, Device as _, Event, InputBackend, InputEvent,
+    KeyboardKeyEvent, PointerAxisEvent, PointerButtonEvent, PointerMotionEvent,
+};
+use smithay::input::keyboard::FilterResult;
+use smithay::input::pointer::{AxisFrame, ButtonEvent, MotionEvent};
+use smithay::input::{Seat, SeatState};
+use smithay::reexports::wayland_server::DisplayHandle;
+use smithay::utils::{Logical, Rectangle, SERIAL_COUNTER};
+use tracing::{info, warn};
+
+use crate::state::HeyDM;
+
+/// Settings from one `[seat "name"]` section
+#[derive(Debug, Clone, Default)]
+pub struct SeatConfig {
+    /// libinput device names that belong to the seat
+    pub devices: Vec<String>,
+    /// Connector name of the output the seat works on
+    pub output: Option<String>,
+}
+
+impl SeatConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "devices" => {
+                self.devices = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            "output" => self.output = Some(value.trim().to_string()).filter(|name| !name.is_empty()),
+            _ => return Err(format!("unknown seat setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// A seat other than seat0
+pub struct ExtraSeat {
+    pub seat: Seat<HeyDM>,
+    devices: Vec<String>,
+    output: Option<String>,
+    /// Cursor position in output coordinates
+    cursor: (f64, f64),
+}
+
+impl ExtraSeat {
+    pub fn cursor_position(&self) -> (f64, f64) {
+        self.cursor
+    }
+}
+
+/// All configured additional seats
+#[derive(Default)]
+pub struct Seats {
+    extra: Vec<ExtraSeat>,
+}
+
+impl Seats {
+    /// Create a wl_seat with keyboard and pointer for every configured seat
+    pub fn new(
+        config: &BTreeMap<String, SeatConfig>,
+        main_seat: &str,
+        seat_state: &mut SeatState<HeyDM>,
+        display_handle: &DisplayHandle,
+    ) -> Self {
+        let mut extra = Vec::new();
+        for (name, seat_config) in config {
+            if name == main_seat {
+                warn!("[seat \"{name}\"] names the default seat, ignoring it");
+                continue;
+            }
+            let mut seat = seat_state.new_wl_seat(display_handle, name.clone());
+            if let Err(e) = seat.add_keyboard(Default::default(), 200, 25) {
+                warn!("Failed to add a keyboard to seat '{name}': {e}");
+                continue;
+            }
+            seat.add_pointer();
+            info!("Seat '{name}' created for {}", seat_config.devices.join(", "));
+            extra.push(ExtraSeat {
+                seat,
+                devices: seat_config.devices.clone(),
+                output: seat_config.output.clone(),
+                cursor: (0.0, 0.0),
+            });
+        }
+        Self { extra }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extra.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ExtraSeat> {
+        self.extra.iter()
+    }
+
+    /// Index of the additional seat an input device belongs to
+    fn seat_for_device(&self, name: &str) -> Option<usize> {
+        self.extra
+            .iter()
+            .position(|seat| seat.devices.iter().any(|device| device == name))
+    }
+}
+
+/// Route an event from a device assigned to an additional seat. Returns
+/// false if the event belongs to seat0.
+pub fn handle_input<B: InputBackend>(state: &mut HeyDM, event: &InputEvent<B>) -> bool {
+    if state.seats.is_empty() {
+        return false;
+    }
+    let device = match event {
+        InputEvent::Keyboard { event } => event.device(),
+        InputEvent::PointerMotion { event } => event.device(),
+        InputEvent::PointerMotionAbsolute { event } => event.device(),
+        InputEvent::PointerButton { event } => event.device(),
+        InputEvent::PointerAxis { event } => event.device(),
+        InputEvent::GestureSwipeBegin { event } => event.device(),
+        InputEvent::GestureSwipeUpdate { event } => event.device(),
+        InputEvent::GestureSwipeEnd { event } => event.device(),
+        InputEvent::GesturePinchBegin { event } => event.device(),
+        InputEvent::GesturePinchUpdate { event } => event.device(),
+        InputEvent::GesturePinchEnd { event } => event.device(),
+        InputEvent::GestureHoldBegin { event } => event.device(),
+        InputEvent::GestureHoldEnd { event } => event.device(),
+        _ => return false,
+    };
+    let Some(index) = state.seats.seat_for_device(&device.name()) else {
+        return false;
+    };
+    // Nobody but seat0 can unlock the session
+    if state.lock.is_locked() {
+        return true;
+    }
+
+    match event {
+        InputEvent::Keyboard { event } => {
+            let keyboard = state.seats.extra[index].seat.get_keyboard().unwrap();
+            keyboard.input::<(), _>(
+                state,
+                event.key_code(),
+                event.state(),
+                SERIAL_COUNTER.next_serial(),
+                event.time_msec(),
+                |_, _, _| FilterResult::Forward,
+            );
+        }
+        InputEvent::PointerMotion { event } => {
+            let area = seat_area(state, index);
+            let (left, top) = (area.loc.x as f64, area.loc.y as f64);
+            let seat = &mut state.seats.extra[index];
+            seat.cursor = (
+                (seat.cursor.0 + event.delta_x()).clamp(left, left + area.size.w as f64),
+                (seat.cursor.1 + event.delta_y()).clamp(top, top + area.size.h as f64),
+            );
+            pointer_motion(state, index, event.time_msec());
+        }
+        InputEvent::PointerMotionAbsolute { event } => {
+            let area = seat_area(state, index);
+            state.seats.extra[index].cursor = (
+                area.loc.x as f64 + event.x_transformed(area.size.w),
+                area.loc.y as f64 + event.y_transformed(area.size.h),
+            );
+            pointer_motion(state, index, event.time_msec());
+        }
+        InputEvent::PointerButton { event } => {
+            let seat = state.seats.extra[index].seat.clone();
+            if event.state() == ButtonState::Pressed {
+                let cursor = state.seats.extra[index].cursor;
+                let focus = state
+                    .window_manager
+                    .surface_under(cursor)
+                    .map(|(surface, _)| surface);
+                let keyboard = seat.get_keyboard().unwrap();
+                keyboard.set_focus(state, focus, SERIAL_COUNTER.next_serial());
+            }
+            let pointer = seat.get_pointer().unwrap();
+            pointer.button(
+                state,
+                &ButtonEvent {
+                    button: event.button_code(),
+                    state: event.state(),
+                    serial: SERIAL_COUNTER.next_serial(),
+                    time: event.time_msec(),
+                },
+            );
+            pointer.frame(state);
+        }
+        InputEvent::PointerAxis { event } => {
+            let mut frame = AxisFrame::new(event.time_msec()).source(event.source());
+            if let Some(amount) = event.amount(Axis::Horizontal) {
+                frame = frame.value(Axis::Horizontal, amount);
+            }
+            if let Some(amount) = event.amount(Axis::Vertical) {
+                frame = frame.value(Axis::Vertical, amount);
+            }
+            let pointer = state.seats.extra[index].seat.get_pointer().unwrap();
+            pointer.axis(state, frame);
+            pointer.frame(state);
+        }
+        // Gestures are compositor features of seat0
+        _ => {}
+    }
+    true
+}
+
+/// Area of the output the seat works on
+fn seat_area(state: &HeyDM, index: usize) -> Rectangle<i32, Logical> {
+    let output = state.seats.extra[index].output.as_deref();
+    output
+        .and_then(|name| state.window_manager.extra_output_area(name))
+        .unwrap_or_else(|| Rectangle::from_size((state.output_size.w, state.output_size.h).into()))
+}
+
+/// Send the seat's pointer to the surface under its cursor
+fn pointer_motion(state: &mut HeyDM, index: usize, time: u32) {
+    let seat = &state.seats.extra[index];
+    let location = seat.cursor;
+    let pointer = seat.seat.get_pointer().unwrap();
+    let under = state
+        .window_manager
+        .surface_under(location)
+        .map(|(surface, pos)| (surface, pos.into()));
+    let location = state.window_manager.client_point(location);
+    pointer.motion(
+        state,
+        under,
+        &MotionEvent {
+            location,
+            serial: SERIAL_COUNTER.next_serial(),
+            time,
+        },
+    );
+    pointer.frame(state);
+}
@@ -0,0 +1,196 @@
+// =============================================================================
+// heyDM — Session Lifecycle
+//
+// heyDM is the session process greetd starts after login. greetd runs
+// pam_close_session and brings the greeter back as soon as that process
+// exits, so everything belonging to the session has to be gone by then:
+// apps that keep running, or a Wayland socket left in XDG_RUNTIME_DIR, break
+// the next login (a stale WAYLAND_DISPLAY, a second instance of a
+// single-instance app).
+//
+// heyDM therefore marks itself a child subreaper at startup, so apps that
+// daemonize stay its descendants instead of moving to init, and on exit:
+//   1. sends SIGTERM to every descendant, and when heyDM leads its session
+//      to every other process of the session too
+//   2. waits up to TEARDOWN_GRACE for them, then SIGKILLs the rest
+//   3. removes its Wayland socket, lock file and IPC socket
+// and only then returns, letting greetd close the PAM session. Without
+// leading the session heyDM shares it with its parents (the hey-greeter
+// session wrapper, its private D-Bus daemon, the login screen), which are
+// not its to stop.
+//
+// During the session, SIGCHLD reaps exited children: apps started without
+// anyone waiting for them and orphans handed to heyDM as subreaper.
+// =============================================================================
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{getpid, getsid, Pid};
+use tracing::{debug, info, warn};
+
+use crate::ipc;
+
+/// How long session processes get to exit after SIGTERM
+const TEARDOWN_GRACE: Duration = Duration::from_secs(3);
+
+/// Keep orphaned descendants (double-forking apps) as our children
+pub fn become_subreaper() {
+    // SAFETY: prctl with PR_SET_CHILD_SUBREAPER only changes a process flag
+    let result = unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) };
+    if result != 0 {
+        warn!(
+            "Could not become child subreaper: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// End the session: stop its processes and remove its sockets
+pub fn teardown(wayland_display: Option<&str>) {
+    let started = Instant::now();
+    let whole_session = leads_session();
+    if !whole_session {
+        info!("Not the session leader: only stopping heyDM's own descendants");
+    }
+    let processes = session_processes(whole_session);
+    if !processes.is_empty() {
+        info!("Ending session: stopping {} process(es)", processes.len());
+        signal_all(&processes, Signal::SIGTERM);
+        while started.elapsed() < TEARDOWN_GRACE && !session_processes(whole_session).is_empty() {
+            reap();
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        let remaining = session_processes(whole_session);
+        if !remaining.is_empty() {
+            warn!("Killing {} process(es) that ignored SIGTERM", remaining.len());
+            signal_all(&remaining, Signal::SIGKILL);
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        reap();
+    }
+
+    if let Some(display) = wayland_display {
+        remove_stale_sockets(display);
+    }
+    info!("Session ended in {:?}", started.elapsed());
+}
+
+/// Collect exited children of the main thread, which the SIGCHLD source
+/// runs on: apps heyDM started without waiting for them, and orphans, which
+/// the kernel hands to the subreaper's main thread. Children of other
+/// threads (pactl, the greeter client) are left to the threads waiting for
+/// them.
+pub fn reap_children() {
+    let flags = WaitPidFlag::WNOHANG | WaitPidFlag::__WNOTHREAD;
+    loop {
+        match waitpid(Pid::from_raw(-1), Some(flags)) {
+            Ok(WaitStatus::StillAlive) | Err(_) => break,
+            Ok(status) => debug!("Reaped {status:?}"),
+        }
+    }
+}
+
+/// Whether heyDM started the session it runs in (greetd running it
+/// directly) rather than sharing it with a parent
+fn leads_session() -> bool {
+    getsid(None).is_ok_and(|session| session == getpid())
+}
+
+/// Live processes below heyDM in the process tree, and with `whole_session`
+/// the rest of its session
+fn session_processes(whole_session: bool) -> Vec<Pid> {
+    let me = getpid();
+    let session = getsid(None).ok().filter(|_| whole_session);
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut parents = HashMap::new();
+    let mut in_session = HashSet::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<i32>().ok()) else {
+            continue;
+        };
+        let Some((state, ppid, sid)) = read_stat(pid) else {
+            continue;
+        };
+        // Zombies only need reaping
+        if state == 'Z' || pid == me.as_raw() {
+            continue;
+        }
+        parents.insert(pid, ppid);
+        if Some(Pid::from_raw(sid)) == session {
+            in_session.insert(pid);
+        }
+    }
+
+    parents
+        .keys()
+        .copied()
+        .filter(|&pid| in_session.contains(&pid) || descends_from(pid, me.as_raw(), &parents))
+        .map(Pid::from_raw)
+        .collect()
+}
+
+fn descends_from(mut pid: i32, ancestor: i32, parents: &HashMap<i32, i32>) -> bool {
+    // Bounded walk: pid reuse could in theory form a cycle in a snapshot
+    for _ in 0..64 {
+        match parents.get(&pid) {
+            Some(&parent) if parent == ancestor => return true,
+            Some(&parent) if parent > 1 => pid = parent,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// State, parent pid and session id from /proc/<pid>/stat
+fn read_stat(pid: i32) -> Option<(char, i32, i32)> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name may contain spaces and parentheses
+    let rest = &stat[stat.rfind(')')? + 2..];
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let _pgrp = fields.next()?;
+    let sid = fields.next()?.parse().ok()?;
+    Some((state, ppid, sid))
+}
+
+fn signal_all(processes: &[Pid], signal: Signal) {
+    for &pid in processes {
+        if let Err(e) = kill(pid, signal) {
+            debug!("{signal} to {pid} failed: {e}");
+        }
+    }
+}
+
+/// Collect exited children so they do not linger as zombies
+fn reap() {
+    while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+        if matches!(status, WaitStatus::StillAlive) {
+            break;
+        }
+    }
+}
+
+/// The Wayland socket, its lock file and the IPC socket, in case a crash or
+/// a leaked file descriptor left them behind
+fn remove_stale_sockets(display: &str) {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let mut paths: Vec<PathBuf> = ipc::socket_path(display).into_iter().collect();
+    if let Some(dir) = runtime_dir {
+        paths.push(dir.join(display));
+        paths.push(dir.join(format!("{display}.lock")));
+    }
+    for path in paths {
+        match std::fs::remove_file(&path) {
+            Ok(()) => debug!("Removed {}", path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Could not remove {}: {e}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,356 @@
+// =============================================================================
+// heyDM — heyOS Shell Protocol
+//
+// Server side of hey_shell_v1 (protocols/hey-shell-v1.xml), a private
+// Wayland protocol for trusted heyOS components (dock, settings app):
+//
+//   - set_wallpaper   replace the wallpaper
+//   - get_workspaces  follow the workspaces, switch between them
+//   - reserve_edge    keep maximized and tiled windows away from a strip
+//                     along a screen edge
+//
+// The code is generated from the XML by wayland-scanner at build time. The
+// global is only visible to clients whose executable is listed in the
+// config:
+//
+//     [shell]
+//     trusted = /usr/bin/hey-dock, /usr/bin/hey-settings
+//
+// Trust is decided once per connection from the peer credentials of the
+// socket (see `is_trusted`); sandboxed clients are never trusted (see
+// sandbox.rs).
+// =============================================================================
+
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+use tracing::info;
+
+use crate::state::{ClientState, HeyDM};
+use crate::workspace::WORKSPACE_COUNT;
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals, unused_imports)]
+#[allow(clippy::all)]
+pub mod protocol {
+    use smithay::reexports::wayland_server;
+    use smithay::reexports::wayland_server::protocol::*;
+
+    pub mod __interfaces {
+        use smithay::reexports::wayland_server::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/hey-shell-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_server_code!("protocols/hey-shell-v1.xml");
+}
+
+use protocol::hey_edge_reservation_v1::{self, HeyEdgeReservationV1};
+use protocol::hey_shell_manager_v1::{self, Edge, HeyShellManagerV1};
+use protocol::hey_workspaces_v1::{self, HeyWorkspacesV1};
+
+/// Version of hey_shell_manager_v1 heyDM implements
+const MANAGER_VERSION: u32 = 1;
+
+/// Bits of the hey_workspaces_v1.workspace state argument
+const WORKSPACE_ACTIVE: u32 = 1;
+const WORKSPACE_VISIBLE: u32 = 2;
+const WORKSPACE_OCCUPIED: u32 = 4;
+const WORKSPACE_URGENT: u32 = 8;
+
+/// Settings from the `[shell]` section
+#[derive(Debug, Clone)]
+pub struct ShellConfig {
+    /// Executables allowed to bind hey_shell_manager_v1
+    pub trusted: Vec<PathBuf>,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            trusted: vec![
+                PathBuf::from("/usr/bin/hey-dock"),
+                PathBuf::from("/usr/bin/hey-settings"),
+            ],
+        }
+    }
+}
+
+impl ShellConfig {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "trusted" => {
+                self.trusted = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(PathBuf::from)
+                    .collect();
+            }
+            _ => return Err(format!("unknown shell setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// Whether the process on the other end of a new connection runs one of
+/// the trusted executables
+pub fn is_trusted(stream: &UnixStream, config: &ShellConfig) -> bool {
+    if config.trusted.is_empty() {
+        return false;
+    }
+    let Ok(credentials) = getsockopt(stream, PeerCredentials) else {
+        return false;
+    };
+    let Ok(exe) = std::fs::read_link(format!("/proc/{}/exe", credentials.pid())) else {
+        return false;
+    };
+    let trusted = config.trusted.iter().any(|path| *path == exe);
+    if trusted {
+        info!("Trusted shell client connected: {}", exe.display());
+    }
+    trusted
+}
+
+/// A hey_edge_reservation_v1 and the strip it reserves
+#[derive(Debug)]
+struct Reservation {
+    resource: HeyEdgeReservationV1,
+    edge: Edge,
+    size: i32,
+}
+
+/// Protocol objects of connected shell components
+#[derive(Debug, Default)]
+pub struct ShellState {
+    workspace_watchers: Vec<HeyWorkspacesV1>,
+    reservations: Vec<Reservation>,
+    /// Workspace state last sent to the watchers
+    last_workspaces: Vec<(u32, u32, u32)>,
+}
+
+impl ShellState {
+    /// Create the hey_shell_manager_v1 global
+    pub fn new(display_handle: &DisplayHandle) -> Self {
+        display_handle.create_global::<HeyDM, HeyShellManagerV1, ()>(MANAGER_VERSION, ());
+        Self::default()
+    }
+
+    /// Space reserved along each edge: top, bottom, left, right
+    fn reserved_edges(&self) -> [i32; 4] {
+        let mut edges = [0; 4];
+        for reservation in &self.reservations {
+            let slot = match reservation.edge {
+                Edge::Top => 0,
+                Edge::Bottom => 1,
+                Edge::Left => 2,
+                Edge::Right => 3,
+            };
+            edges[slot] += reservation.size;
+        }
+        edges
+    }
+}
+
+/// Current (number, state, windows) of every workspace
+fn workspace_snapshot(state: &HeyDM) -> Vec<(u32, u32, u32)> {
+    let wm = &state.window_manager;
+    let workspaces = wm.workspaces();
+    let active = workspaces.active_workspace();
+    (1..=WORKSPACE_COUNT)
+        .map(|ws| {
+            let windows: Vec<_> = wm.windows().iter().filter(|w| w.workspace() == ws).collect();
+            let mut bits = 0;
+            if ws == active {
+                bits |= WORKSPACE_ACTIVE;
+            }
+            if workspaces.is_visible(ws) {
+                bits |= WORKSPACE_VISIBLE;
+            }
+            if !windows.is_empty() {
+                bits |= WORKSPACE_OCCUPIED;
+            }
+            if windows.iter().any(|w| w.is_urgent()) {
+                bits |= WORKSPACE_URGENT;
+            }
+            (ws as u32, bits, windows.len() as u32)
+        })
+        .collect()
+}
+
+fn send_workspaces(watcher: &HeyWorkspacesV1, snapshot: &[(u32, u32, u32)]) {
+    for &(number, bits, windows) in snapshot {
+        watcher.workspace(number, bits, windows);
+    }
+    watcher.done();
+}
+
+/// Send the workspaces to every watcher if they changed
+pub fn publish_workspaces(state: &mut HeyDM) {
+    if state.shell.workspace_watchers.is_empty() {
+        return;
+    }
+    let snapshot = workspace_snapshot(state);
+    if snapshot == state.shell.last_workspaces {
+        return;
+    }
+    for watcher in &state.shell.workspace_watchers {
+        send_workspaces(watcher, &snapshot);
+    }
+    state.shell.last_workspaces = snapshot;
+}
+
+/// Hand the summed reservations to the window manager
+fn apply_reservations(state: &mut HeyDM) {
+    let edges = state.shell.reserved_edges();
+    state
+        .window_manager
+        .set_reserved_edges(edges, &state.output_size);
+}
+
+impl GlobalDispatch<HeyShellManagerV1, ()> for HeyDM {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<HeyShellManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, _global_data: &()) -> bool {
+        client
+            .get_data::<ClientState>()
+            .is_some_and(|data| data.trusted_shell)
+    }
+}
+
+impl Dispatch<HeyShellManagerV1, ()> for HeyDM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &HeyShellManagerV1,
+        request: hey_shell_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            hey_shell_manager_v1::Request::Destroy => {}
+            hey_shell_manager_v1::Request::SetWallpaper { path } => {
+                let path = (!path.is_empty()).then(|| PathBuf::from(path));
+                if path.as_ref().is_some_and(|path| !path.is_absolute()) {
+                    resource.post_error(
+                        hey_shell_manager_v1::Error::InvalidPath,
+                        "wallpaper path must be absolute",
+                    );
+                    return;
+                }
+                info!("Shell client set the wallpaper to {path:?}");
+                state.wallpaper.set(path, state.output_size);
+            }
+            hey_shell_manager_v1::Request::GetWorkspaces { id } => {
+                let watcher = data_init.init(id, ());
+                send_workspaces(&watcher, &workspace_snapshot(state));
+                state.shell.workspace_watchers.push(watcher);
+            }
+            hey_shell_manager_v1::Request::ReserveEdge { id, edge, size } => {
+                let reservation = data_init.init(id, ());
+                let WEnum::Value(edge) = edge else {
+                    resource.post_error(
+                        hey_shell_manager_v1::Error::InvalidEdge,
+                        "unknown edge",
+                    );
+                    return;
+                };
+                state.shell.reservations.push(Reservation {
+                    resource: reservation,
+                    edge,
+                    size: size.min(i32::MAX as u32) as i32,
+                });
+                apply_reservations(state);
+            }
+        }
+    }
+}
+
+impl Dispatch<HeyWorkspacesV1, ()> for HeyDM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &HeyWorkspacesV1,
+        request: hey_workspaces_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            hey_workspaces_v1::Request::Activate { number } => {
+                let ws = number as usize;
+                if !(1..=WORKSPACE_COUNT).contains(&ws) {
+                    resource.post_error(
+                        hey_workspaces_v1::Error::InvalidWorkspace,
+                        format!("workspace {number} does not exist"),
+                    );
+                    return;
+                }
+                state.window_manager.switch_workspace(ws);
+                state.refresh_keyboard_focus();
+            }
+            hey_workspaces_v1::Request::Destroy => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &HeyWorkspacesV1, _data: &()) {
+        state
+            .shell
+            .workspace_watchers
+            .retain(|watcher| watcher != resource);
+    }
+}
+
+impl Dispatch<HeyEdgeReservationV1, ()> for HeyDM {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &HeyEdgeReservationV1,
+        request: hey_edge_reservation_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            hey_edge_reservation_v1::Request::SetSize { size } => {
+                let Some(reservation) = state
+                    .shell
+                    .reservations
+                    .iter_mut()
+                    .find(|reservation| reservation.resource == *resource)
+                else {
+                    return;
+                };
+                reservation.size = size.min(i32::MAX as u32) as i32;
+                apply_reservations(state);
+            }
+            hey_edge_reservation_v1::Request::Destroy => {}
+        }
+    }
+
+    fn destroyed(state: &mut Self, _client: ClientId, resource: &HeyEdgeReservationV1, _data: &()) {
+        let before = state.shell.reservations.len();
+        state
+            .shell
+            .reservations
+            .retain(|reservation| reservation.resource != *resource);
+        if state.shell.reservations.len() != before {
+            apply_reservations(state);
+        }
+    }
+}
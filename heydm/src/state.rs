@@ -3,63 +3,160 @@
 // =============================================================================
 
 
+use std::io::Write;
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
+use calloop::signals::{Signal, Signals};
+use calloop::timer::{TimeoutAction, Timer};
 use calloop::{EventLoop, LoopHandle, LoopSignal};
+use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::renderer::glow::GlowRenderer;
 use smithay::backend::renderer::{Frame, Renderer as SmithayRenderer};
+use smithay::backend::session::libseat::LibSeatSession;
+use smithay::backend::session::Session;
 use smithay::backend::winit::{self, WinitEvent};
 
 use smithay::delegate_compositor;
 use smithay::delegate_data_device;
+use smithay::delegate_dmabuf;
+use smithay::delegate_fractional_scale;
+use smithay::delegate_input_method_manager;
 use smithay::delegate_output;
+use smithay::delegate_pointer_gestures;
 use smithay::delegate_seat;
 use smithay::delegate_shm;
+use smithay::delegate_tearing_control;
+use smithay::delegate_text_input_manager;
+use smithay::delegate_viewporter;
+use smithay::delegate_virtual_keyboard_manager;
+use smithay::delegate_xdg_activation;
 use smithay::delegate_xdg_shell;
 
 use smithay::input::{Seat, SeatHandler, SeatState};
+use smithay::output::{Output, Scale};
+use smithay::reexports::input::Device as InputDevice;
 use smithay::reexports::wayland_server::backend::ClientData;
 use smithay::reexports::wayland_server::protocol::wl_buffer;
 use smithay::reexports::wayland_server::protocol::wl_seat::WlSeat;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 use smithay::reexports::wayland_server::{Display, DisplayHandle, Resource};
-use smithay::utils::{Clock, Monotonic, Size, Transform};
+use smithay::utils::{Clock, Logical, Monotonic, Rectangle, Size, Transform, SERIAL_COUNTER};
 use smithay::wayland::buffer::BufferHandler;
 use smithay::wayland::compositor::{
-    CompositorClientState, CompositorHandler, CompositorState,
+    with_states, CompositorClientState, CompositorHandler, CompositorState,
+};
+use smithay::wayland::dmabuf::{DmabufGlobal, DmabufHandler, DmabufState, ImportNotifier};
+use smithay::wayland::fractional_scale::{
+    with_fractional_scale, FractionalScaleHandler, FractionalScaleManagerState,
+};
+use smithay::wayland::input_method::{
+    InputMethodHandler, InputMethodManagerState, PopupSurface as InputMethodPopup,
 };
 use smithay::wayland::output::{OutputHandler, OutputManagerState};
+use smithay::wayland::pointer_gestures::PointerGesturesState;
 use smithay::wayland::selection::data_device::{
     DataDeviceHandler, DataDeviceState, WaylandDndGrabHandler,
 };
-use smithay::wayland::selection::SelectionHandler;
+use smithay::wayland::selection::data_device::set_data_device_selection;
+use smithay::wayland::selection::{SelectionHandler, SelectionTarget};
 use smithay::wayland::shell::xdg::{
     PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
 };
 use smithay::wayland::shm::{ShmHandler, ShmState};
+use smithay::wayland::tearing_control::TearingControlState;
+use smithay::wayland::text_input::TextInputManagerState;
+use smithay::wayland::viewporter::ViewporterState;
+use smithay::wayland::virtual_keyboard::VirtualKeyboardManagerState;
+use smithay::wayland::xdg_activation::{
+    XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData,
+};
 use smithay::wayland::socket::ListeningSocketSource;
 
-use tracing::{error, info};
-
-use crate::input::InputHandler;
-use crate::launcher::AppLauncher;
+use tracing::{info, warn};
+
+use crate::accessx::KeyAccess;
+use crate::backlight::{BacklightControl, BRIGHTNESS_LABEL};
+use crate::bluetooth::BluetoothMenu;
+use crate::calendar::Calendar;
+use crate::config::Config;
+use crate::cursor::CursorManager;
+use crate::events::{EventSubscribers, Snapshot};
+use crate::filters::ColorFilters;
+use crate::gamma::GammaControls;
+use crate::icons::ICON_SIZE;
+use crate::ime::ImePopups;
+use crate::input::{InputHandler, SwipeTracker};
+use crate::ipc::IpcServer;
+use crate::latency::LatencyTracker;
+use crate::launch::{LaunchFeedback, LAUNCH_TIMEOUT};
+use crate::launcher::{AppCommand, AppLauncher};
+use crate::lock::LockScreen;
+use crate::mpris::MediaPlayers;
+use crate::network::NetworkMenu;
+use crate::notifications::{
+    CloseReason, Notification, NotificationEvent, Notifications, DEFAULT_TIMEOUT,
+};
+use crate::osd::Osd;
+use crate::output::{OutputConfig, OutputIdentity};
+use crate::overview::Overview;
 use crate::panel::StatusPanel;
+use crate::perf::PerfMetrics;
+use crate::poller::{BatteryState, PanelUpdate};
+use crate::portal::SettingsPortal;
+use crate::power::{BatteryWarnings, BATTERY_LABEL};
+use crate::presets::PendingPlacements;
+use crate::restore::LayoutRestore;
+use crate::profiles::PowerProfiles;
+use crate::protocol::ProtocolChecker;
+use crate::recovery::Recovery;
+use crate::render::colors;
+use crate::sandbox::Sandbox;
+use crate::schema::Diagnostic;
+use crate::scratchpad::Scratchpad;
+use crate::seats::Seats;
+use crate::shell::ShellState;
+use crate::status::StatusSubscribers;
+use crate::switcher::WindowSwitcher;
+use crate::taskman::{TaskManager, SAMPLE_INTERVAL};
+use crate::text::TextRenderer;
+use crate::texture::TextureCache;
+use crate::theme::UiMetrics;
+use crate::tooltip::{TooltipSource, Tooltips};
+use crate::tray::Tray;
+use crate::volume::VolumeControl;
+use crate::wallpaper::{OutputWallpaper, Wallpaper};
 use crate::window::{WindowElement, WindowManager};
+use crate::zoom::Zoom;
+
+/// Terminal for Super+Enter and Terminal=true apps
+pub const TERMINAL: &str = "alacritty";
+
+/// Mime types the compositor offers its clipboard text as
+const TEXT_MIME_TYPES: &[&str] = &["text/plain;charset=utf-8", "text/plain", "UTF8_STRING"];
 
 /// Client-specific state tracked by the Wayland display
 #[derive(Default)]
 pub struct ClientState {
     pub compositor_state: CompositorClientState,
+    /// Runs a trusted executable and may bind hey_shell_v1 (see shell.rs)
+    pub trusted_shell: bool,
+    /// Flatpak or Snap the client runs in (see sandbox.rs)
+    pub sandbox: Sandbox,
 }
 
 impl ClientData for ClientState {
-    fn initialized(&self, _client_id: smithay::reexports::wayland_server::backend::ClientId) {}
+    fn initialized(&self, _client_id: smithay::reexports::wayland_server::backend::ClientId) {
+        crate::perf::client_connected();
+    }
     fn disconnected(
         &self,
         _client_id: smithay::reexports::wayland_server::backend::ClientId,
         _reason: smithay::reexports::wayland_server::backend::DisconnectReason,
     ) {
+        crate::perf::client_disconnected();
     }
 }
 
@@ -77,24 +174,131 @@ pub struct HeyDM {
     pub seat_state: SeatState<Self>,
     pub data_device_state: DataDeviceState,
     pub output_manager_state: OutputManagerState,
+    pub pointer_gestures_state: PointerGesturesState,
+    pub xdg_activation_state: XdgActivationState,
+    pub fractional_scale_state: FractionalScaleManagerState,
+    pub viewporter_state: ViewporterState,
+    /// wp_tearing_control_v1 presentation hints (see tearing.rs)
+    pub tearing_control_state: TearingControlState,
+    /// linux-dmabuf; the DRM backend creates the global (see scanout.rs)
+    pub dmabuf_state: DmabufState,
+    /// text-input-v3, input-method-v2 and virtual-keyboard-v1, for IMEs
+    /// such as fcitx5 and on-screen keyboards
+    pub text_input_state: TextInputManagerState,
+    pub input_method_state: InputMethodManagerState,
+    pub virtual_keyboard_state: VirtualKeyboardManagerState,
 
     pub seat: Seat<Self>,
     pub seat_name: String,
+    /// Seats configured in addition to seat0 (see seats.rs)
+    pub seats: Seats,
 
+    pub config: Config,
     pub window_manager: WindowManager,
     pub panel: StatusPanel,
     pub launcher: AppLauncher,
+    pub lock: LockScreen,
+    pub task_manager: TaskManager,
+    pub switcher: WindowSwitcher,
+    /// Super+W grid of all windows
+    pub overview: Overview,
+    pub text: TextRenderer,
+    /// Scaled sizes of compositor-drawn UI (`[theme]` and output scale)
+    pub ui: UiMetrics,
+    /// Scale of the driven output; clients render at it (fractional-scale-v1)
+    pub output_scale: f64,
+    pub tooltips: Tooltips,
+    pub launches: LaunchFeedback,
+    pub cursor: CursorManager,
+    /// Input method candidate popups
+    pub ime_popups: ImePopups,
+    /// Screen magnifier (Super+scroll)
+    pub zoom: Zoom,
+    /// Color filter and night light
+    pub color: ColorFilters,
+    /// Sticky, slow and bounce keys
+    pub access: KeyAccess,
+    /// Gamma ramps set by clients (wlr-gamma-control)
+    pub gamma: GammaControls,
+    pub wallpaper: Wallpaper,
+    pub portal: SettingsPortal,
+    pub volume: VolumeControl,
+    pub power_profiles: PowerProfiles,
+    /// MPRIS players for the panel's media module
+    pub media: MediaPlayers,
+    pub backlight: BacklightControl,
+    /// Transient level indicator (brightness, low battery)
+    pub osd: Osd,
+    pub notifications: Notifications,
+    /// Low battery levels already warned about
+    pub battery_warnings: BatteryWarnings,
+    /// StatusNotifierItem icons in the panel
+    pub tray: Tray,
+    /// Wi-Fi networks opened from the panel's network module
+    pub network_menu: NetworkMenu,
+    /// Adapter power and paired devices, from the panel's Bluetooth module
+    pub bluetooth_menu: BluetoothMenu,
+    /// Month calendar opened from the panel clock
+    pub calendar: Calendar,
+    /// Preset windows waiting for their launched apps
+    pub pending_placements: PendingPlacements,
+    /// Windows of the last session waiting to reappear (see restore.rs;
+    /// None for the login screen and headless runs)
+    pub layout_restore: Option<LayoutRestore>,
+    /// The dropdown terminal being started
+    pub scratchpad: Scratchpad,
+    /// xdg-shell protocol violation checks
+    pub protocol: ProtocolChecker,
+    /// Input-to-frame latency diagnostics
+    pub latency: LatencyTracker,
+    /// Frame metrics and the FPS overlay (see perf.rs)
+    pub perf: PerfMetrics,
+    /// IPC clients following the status bar export
+    pub status_subscribers: StatusSubscribers,
+    /// IPC clients following window, workspace and output events
+    pub event_subscribers: EventSubscribers,
+    /// hey_shell_v1 objects of trusted shell components
+    pub shell: ShellState,
+    /// Panics caught in event handling (see recovery.rs)
+    pub recovery: Recovery,
+    /// Running as the login screen's kiosk compositor (see greeter.rs)
+    pub greeter: bool,
+    /// Three-finger swipe in progress (consumed by the compositor)
+    pub swipe: Option<SwipeTracker>,
+    /// Monitors connected to any GPU (udev only)
+    pub outputs: Vec<OutputIdentity>,
+    /// The primary output, with the panel
+    pub output: Option<Output>,
+    /// Further outputs driven next to it (udev only, see window.rs)
+    pub extra_outputs: Vec<Output>,
+    /// Mode and transform of the driven output need applying again (the
+    /// DRM backend does so before its next frame)
+    pub output_modeset: bool,
+    /// libinput devices, configured again when the config is reloaded
+    pub input_devices: Vec<InputDevice>,
+    /// The libseat session, used to switch VTs (udev only)
+    pub session: Option<LibSeatSession>,
 
     pub output_size: Size<i32, smithay::utils::Physical>,
 }
 
 impl HeyDM {
     /// Main entry point: sets up the compositor and runs the event loop.
-    pub fn run(use_winit: bool) -> Result<(), Box<dyn std::error::Error>> {
+    /// With `greeter`, heyDM runs that command as its only client and exits
+    /// with it.
+    pub fn run(
+        use_winit: bool,
+        headless: Option<crate::headless::HeadlessOptions>,
+        greeter: Option<Vec<String>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let mut display = Display::<Self>::new()?;
         let display_handle = display.handle();
 
         let mut event_loop: EventLoop<Self> = EventLoop::try_new()?;
+        // Blocked before any thread starts, so every thread inherits it
+        let reload_signal = Signals::new(&[Signal::SIGHUP])?;
+        let stop_signal = Signals::new(&[Signal::SIGTERM])?;
+        let child_signal = Signals::new(&[Signal::SIGCHLD])?;
         let loop_handle = event_loop.handle();
         let loop_signal = event_loop.get_signal();
         let clock = Clock::new();
@@ -103,20 +307,101 @@ impl HeyDM {
         let xdg_shell_state = XdgShellState::new::<Self>(&display_handle);
         let shm_state = ShmState::new::<Self>(&display_handle, vec![]);
         let output_manager_state = OutputManagerState::new_with_xdg_output::<Self>(&display_handle);
+        let pointer_gestures_state = PointerGesturesState::new::<Self>(&display_handle);
+        let xdg_activation_state = XdgActivationState::new::<Self>(&display_handle);
+        let fractional_scale_state = FractionalScaleManagerState::new::<Self>(&display_handle);
+        let viewporter_state = ViewporterState::new::<Self>(&display_handle);
+        let tearing_control_state = TearingControlState::new::<Self>(&display_handle);
+        let text_input_state = TextInputManagerState::new::<Self>(&display_handle);
+        let input_method_state =
+            InputMethodManagerState::new::<Self, _>(&display_handle, crate::sandbox::is_unconfined);
+        let virtual_keyboard_state =
+            VirtualKeyboardManagerState::new::<Self, _>(&display_handle, crate::sandbox::is_unconfined);
         let mut seat_state = SeatState::new();
         let data_device_state = DataDeviceState::new::<Self>(&display_handle);
 
         let seat_name = "seat0".to_string();
         let mut seat = seat_state.new_wl_seat(&display_handle, seat_name.clone());
 
-        seat.add_keyboard(Default::default(), 200, 25)?;
+        let config = Config::load();
+        seat.add_keyboard(config.keyboard.xkb(), 200, 25)?;
         seat.add_pointer();
 
         info!("Wayland protocols initialized, seat '{seat_name}' created");
 
-        let panel = StatusPanel::new();
-        let launcher = AppLauncher::new();
-        let window_manager = WindowManager::new();
+        let seats = Seats::new(&config.seats, &seat_name, &mut seat_state, &display_handle);
+        let ui = UiMetrics::new(&config.theme);
+        let color = ColorFilters::new(&config.color);
+        let access = KeyAccess::new(&config.accessibility);
+        let panel = StatusPanel::new(ui);
+        let launcher = AppLauncher::new(ui);
+        let mut window_manager = WindowManager::new(&config);
+        window_manager.set_kiosk(greeter.is_some());
+        let (auth_sender, auth_results) = calloop::channel::channel();
+        let lock = LockScreen::new(auth_sender);
+        let text = TextRenderer::new(config.theme.font.as_deref());
+        let (wallpaper_sender, wallpaper_results) = calloop::channel::channel();
+        let wallpaper = Wallpaper::new(
+            config.appearance.wallpaper.clone(),
+            config.appearance.wallpaper_mode,
+            wallpaper_sender,
+        );
+        let (icon_sender, icon_results) = calloop::channel::channel();
+        crate::icons::load_all(
+            launcher.icon_names(),
+            config.appearance.icon_theme.clone(),
+            ui.px(ICON_SIZE as i32) as u32,
+            icon_sender.clone(),
+        );
+        // The login screen runs its one client and nothing that talks to
+        // the session bus, the network or the power supply
+        let services = greeter.is_none();
+        let portal = if services {
+            SettingsPortal::start(config.appearance.color_scheme)
+        } else {
+            SettingsPortal::disabled()
+        };
+        if let Some(accent) = config.appearance.accent {
+            portal.set_accent(accent);
+        }
+        // Changed clock / battery / network / volume / brightness / power
+        // profile values for the panel
+        let (panel_sender, panel_updates) = calloop::channel::channel();
+        let (notification_sender, notification_events) = calloop::channel::channel();
+        let (network_sender, network_lists) = calloop::channel::channel();
+        let (tray_sender, tray_updates) = calloop::channel::channel();
+        let (
+            volume,
+            power_profiles,
+            media,
+            backlight,
+            notifications,
+            network_menu,
+            bluetooth_menu,
+            tray,
+        ) = if services {
+            (
+                VolumeControl::start(panel_sender.clone()),
+                PowerProfiles::start(panel_sender.clone()),
+                MediaPlayers::start(panel_sender.clone()),
+                BacklightControl::start(panel_sender.clone()),
+                Notifications::start(notification_sender, ui),
+                NetworkMenu::start(panel_sender.clone(), network_sender, ui),
+                BluetoothMenu::start(panel_sender.clone(), ui),
+                Tray::start(tray_sender, config.appearance.icon_theme.clone(), ui),
+            )
+        } else {
+            (
+                VolumeControl::disabled(),
+                PowerProfiles::disabled(),
+                MediaPlayers::disabled(),
+                BacklightControl::disabled(),
+                Notifications::disabled(ui),
+                NetworkMenu::disabled(ui),
+                BluetoothMenu::disabled(ui),
+                Tray::disabled(ui),
+            )
+        };
         let output_size = Size::from((1920, 1080));
 
         let mut state = Self {
@@ -130,27 +415,208 @@ impl HeyDM {
             seat_state,
             data_device_state,
             output_manager_state,
+            pointer_gestures_state,
+            xdg_activation_state,
+            fractional_scale_state,
+            viewporter_state,
+            tearing_control_state,
+            dmabuf_state: DmabufState::new(),
+            text_input_state,
+            input_method_state,
+            virtual_keyboard_state,
             seat,
             seat_name,
+            seats,
+            config,
             window_manager,
             panel,
             launcher,
+            lock,
+            task_manager: TaskManager::new(),
+            switcher: WindowSwitcher::new(ui),
+            overview: Overview::default(),
+            text,
+            ui,
+            output_scale: 1.0,
+            tooltips: Tooltips::default(),
+            launches: LaunchFeedback::default(),
+            cursor: CursorManager::new(),
+            ime_popups: ImePopups::default(),
+            zoom: Zoom::default(),
+            color,
+            access,
+            gamma: GammaControls::new(&display_handle),
+            wallpaper,
+            portal,
+            volume,
+            power_profiles,
+            media,
+            backlight,
+            osd: Osd::default(),
+            notifications,
+            battery_warnings: BatteryWarnings::default(),
+            tray,
+            network_menu,
+            bluetooth_menu,
+            calendar: Calendar::new(ui),
+            pending_placements: PendingPlacements::default(),
+            layout_restore: None,
+            scratchpad: Scratchpad::default(),
+            protocol: ProtocolChecker::default(),
+            latency: LatencyTracker::from_env(),
+            perf: PerfMetrics::from_env(),
+            status_subscribers: StatusSubscribers::default(),
+            event_subscribers: EventSubscribers::default(),
+            shell: ShellState::new(&display_handle),
+            recovery: Recovery::default(),
+            greeter: greeter.is_some(),
+            swipe: None,
+            outputs: Vec::new(),
+            output: None,
+            extra_outputs: Vec::new(),
+            output_modeset: false,
+            input_devices: Vec::new(),
+            session: None,
             output_size,
         };
 
+        // Lock screen authentication results from the PAM worker thread
+        loop_handle.insert_source(auth_results, |event, _, state| {
+            if let calloop::channel::Event::Msg(success) = event {
+                state.lock.finish_auth(success);
+                if !state.lock.is_locked() {
+                    state.refresh_keyboard_focus();
+                }
+            }
+        })?;
+
+        // No battery actions or panel polling on the login screen
+        if !state.greeter {
+            crate::power::start(panel_sender.clone());
+            crate::poller::start(panel_sender);
+        }
+        loop_handle.insert_source(panel_updates, |event, _, state| {
+            if let calloop::channel::Event::Msg(update) = event {
+                match update {
+                    PanelUpdate::Brightness(percent) => state.osd.update(BRIGHTNESS_LABEL, percent),
+                    PanelUpdate::Battery(battery) => state.check_battery(battery),
+                    PanelUpdate::Bluetooth(bluetooth) => state.bluetooth_menu.apply(bluetooth.clone()),
+                    _ => {}
+                }
+                state.panel.apply(update);
+            }
+        })?;
+
+        // Notifications from the D-Bus thread
+        loop_handle.insert_source(notification_events, |event, _, state| match event {
+            calloop::channel::Event::Msg(NotificationEvent::Show(notification)) => {
+                state.show_notification(notification);
+            }
+            calloop::channel::Event::Msg(NotificationEvent::Close(id)) => {
+                state.notifications.close(id, CloseReason::Closed);
+            }
+            calloop::channel::Event::Closed => {}
+        })?;
+
+        // Tray items and menus from the tray thread
+        loop_handle.insert_source(tray_updates, |event, _, state| {
+            if let calloop::channel::Event::Msg(update) = event {
+                state.tray.apply(update);
+            }
+        })?;
+
+        // Wi-Fi networks for the quick menu from the network thread
+        loop_handle.insert_source(network_lists, |event, _, state| {
+            if let calloop::channel::Event::Msg(networks) = event {
+                state.network_menu.apply(networks);
+            }
+        })?;
+
+        // Decoded wallpapers (and their accent color) from the worker thread
+        // Launcher icons from the icon loader thread
+        loop_handle.insert_source(icon_results, |event, _, state| {
+            if let calloop::channel::Event::Msg(icon) = event {
+                state.launcher.set_icon(icon);
+            }
+        })?;
+
+        // Apps installed or removed while running
+        if !state.greeter {
+            crate::appwatch::watch(
+                &loop_handle,
+                state.config.appearance.icon_theme.clone(),
+                state.ui.px(ICON_SIZE as i32) as u32,
+                icon_sender,
+            );
+            // First login: hey-welcome sets up keyboard and appearance
+            crate::welcome::start_if_needed(&loop_handle);
+        }
+
+        loop_handle.insert_source(wallpaper_results, |event, _, state| {
+            if let calloop::channel::Event::Msg(result) = event {
+                match result {
+                    Ok(loaded) => {
+                        if state.wallpaper.finish(loaded) && state.config.appearance.dynamic_accent {
+                            if let Some(accent) = state.wallpaper.accent() {
+                                state.portal.set_accent(accent);
+                            }
+                        }
+                    }
+                    Err(e) => tracing::warn!("Failed to load wallpaper: {e}"),
+                }
+            }
+        })?;
+
         // Add the Wayland display socket to the event loop
         let listening_socket = ListeningSocketSource::new_auto()?;
         let socket_name = listening_socket.socket_name().to_os_string();
         info!("Wayland socket: {:?}", socket_name);
         
+        // Command socket for heyctl-style tools; heyDM runs fine without it.
+        // The login screen offers no IPC.
+        let _ipc = if state.greeter {
+            None
+        } else {
+            match IpcServer::start(&loop_handle, &socket_name.to_string_lossy()) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    tracing::warn!("Failed to start IPC socket: {e}");
+                    None
+                }
+            }
+        };
+
+        // Greeter mode: run the login client and exit together with it
+        if let Some(command) = &greeter {
+            let (exit_sender, exits) = calloop::channel::channel();
+            loop_handle.insert_source(exits, |event, _, state| {
+                if let calloop::channel::Event::Msg(result) = event {
+                    match result {
+                        Ok(status) => info!("Greeter client exited ({status}) — shutting down"),
+                        Err(e) => tracing::warn!("Lost greeter client: {e} — shutting down"),
+                    }
+                    state.loop_signal.stop();
+                }
+            })?;
+            crate::greeter::spawn(command, &socket_name, exit_sender)?;
+        }
+
         // Save the original display for nested mode before we potentially overwrite it
         let original_wayland_display = std::env::var("WAYLAND_DISPLAY").ok();
 
         // ListeningSocketSource implements calloop 0.14 EventSource natively
         loop_handle.insert_source(listening_socket, |client_stream, _, state| {
+            let sandbox = crate::sandbox::detect(&client_stream);
+            let client_state = ClientState {
+                // A sandbox may contain a copy of a trusted executable
+                trusted_shell: !sandbox.is_sandboxed()
+                    && crate::shell::is_trusted(&client_stream, &state.config.shell),
+                sandbox,
+                ..Default::default()
+            };
             if let Err(e) = state
                 .display_handle
-                .insert_client(client_stream, Arc::new(ClientState::default()))
+                .insert_client(client_stream, Arc::new(client_state))
             {
                 tracing::warn!("Failed to insert client: {e}");
             }
@@ -167,7 +633,34 @@ impl HeyDM {
             },
         )?;
 
-        if use_winit {
+        // SIGHUP reloads the config file
+        loop_handle.insert_source(reload_signal, |_, _, state| {
+            if let Err(e) = state.reload_config() {
+                warn!("Config reload failed: {e}");
+            }
+        })?;
+        // SIGTERM (logout, shutdown) ends the session like Super+Shift+E, so
+        // the layout is saved and the session torn down
+        loop_handle.insert_source(stop_signal, |_, _, state| {
+            info!("SIGTERM received — shutting down");
+            state.loop_signal.stop();
+        })?;
+        // Exited apps and orphaned daemons would otherwise stay zombies
+        loop_handle.insert_source(child_signal, |_, _, _| {
+            crate::session::reap_children();
+        })?;
+
+        // Worker threads are running by now; realtime scheduling is not
+        // inherited by anything started later
+        crate::realtime::setup(&state.config.performance);
+        crate::notify::start(&loop_handle);
+
+        if !state.greeter && headless.is_none() {
+            crate::restore::load(&mut state);
+        }
+        if let Some(options) = headless {
+            crate::headless::run(&mut event_loop, &mut display, &mut state, socket_name, options)?;
+        } else if use_winit {
             // Restore original display for winit to connect to parent compositor
             if let Some(display_env) = original_wayland_display {
                 std::env::set_var("WAYLAND_DISPLAY", display_env);
@@ -175,9 +668,10 @@ impl HeyDM {
             Self::run_winit(&mut event_loop, &mut display, &mut state, socket_name)?;
         } else {
             std::env::set_var("WAYLAND_DISPLAY", &socket_name);
-            Self::run_udev(&mut event_loop, &mut display, &mut state)?;
+            crate::udev::run(&mut event_loop, &mut display, &mut state)?;
         }
 
+        crate::restore::save(&state);
         Ok(())
     }
 
@@ -222,25 +716,35 @@ impl HeyDM {
         );
         output.set_preferred(mode);
         output.create_global::<Self>(&state.display_handle);
+        state.output = Some(output.clone());
+        state.apply_output_config();
+        state
+            .window_manager
+            .output_added(&output.name(), None, &state.output_size);
+        state.wallpaper.select_output(&output.name(), state.output_size);
 
         info!(
             "Winit backend started, output size: {}x{}",
             state.output_size.w, state.output_size.h
         );
 
+        let mut texture_cache = TextureCache::new();
+        let mut scene = crate::render::Scene::default();
         let mut running = true;
         while running {
             winit_evt.dispatch_new_events(|event| match event {
                 WinitEvent::Resized { size, .. } => {
-                    state.output_size = size;
                     let mode = smithay::output::Mode {
                         size,
                         refresh: 60_000,
                     };
                     output.change_current_state(Some(mode), None, None, None);
+                    state.set_output_size(size);
                 }
                 WinitEvent::Input(input_event) => {
-                    InputHandler::handle_input(state, input_event);
+                    crate::recovery::guard(state, "input handling", |state| {
+                        InputHandler::handle_input(state, input_event)
+                    });
                 }
                 WinitEvent::Focus(_) => {}
                 WinitEvent::Redraw => {}
@@ -256,33 +760,483 @@ impl HeyDM {
             }
 
             // Winit backend render path
+            let render_started = std::time::Instant::now();
             {
+                crate::render::Renderer::build_scene(state, state.output_size, &mut scene);
                 let (renderer, mut target) = backend.bind()?;
+                texture_cache.prepare(renderer, &state.text, &scene, state.color.matrix())?;
+
                 let mut frame = renderer
                     .render(&mut target, state.output_size, smithay::utils::Transform::Normal)?;
                 
-                crate::render::Renderer::render_frame(state, &mut frame, &output, state.output_size)?;
+                let view = state.zoom.frame(state.window_manager.cursor_position(), state.output_size);
+                crate::render::Renderer::draw_scene(&mut frame, &scene, &texture_cache, view)?;
                 
                 let _ = frame.finish()?;
             }
             backend.submit(None)?;
+            state.latency.frame_submitted(false);
+            let area = state.output_size.w as u64 * state.output_size.h as u64;
+            state.perf.frame_submitted(render_started.elapsed(), area);
 
+            crate::recovery::guard(state, "client dispatch", |state| display.dispatch_clients(state))
+                .transpose()?;
             display.flush_clients()?;
             event_loop.dispatch(Some(Duration::from_millis(16)), state)?;
+            state.publish_status();
+            state.publish_events();
+            crate::shell::publish_workspaces(state);
         }
 
         Ok(())
     }
 
-    /// Run using udev/DRM backend (direct hardware — production path)
-    fn run_udev(
-        _event_loop: &mut EventLoop<Self>,
-        _display: &mut Display<Self>,
-        _state: &mut Self,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        error!("Direct DRM/udev backend is not fully implemented for rendering.");
-        error!("Please run heydm via a Wayland compositor like cage (which provides WAYLAND_DISPLAY).");
-        std::process::exit(1);
+    /// Give keyboard focus to the window manager's focused window
+    /// (or to nobody while the session is locked) and mark it activated
+    pub fn refresh_keyboard_focus(&mut self) {
+        let surface = if self.lock.is_locked() {
+            None
+        } else {
+            self.window_manager
+                .focused_window()
+                .and_then(|w| w.wl_surface())
+        };
+        self.window_manager.sync_activation(!self.lock.is_locked());
+        let keyboard = self.seat.get_keyboard().unwrap();
+        keyboard.set_focus(self, surface, SERIAL_COUNTER.next_serial());
+    }
+
+    /// Spawn a command line (split like a shell would, without running
+    /// one) and show launch feedback until it maps a window
+    pub fn launch(&mut self, program: &str) {
+        match shlex::split(program) {
+            Some(argv) => self.spawn(program, &argv, None),
+            None => tracing::warn!("Failed to launch {program}: unbalanced quotes"),
+        }
+    }
+
+    /// Spawn an app chosen in the launcher, inside the terminal for
+    /// Terminal=true entries
+    pub fn launch_app(&mut self, app: &AppCommand) {
+        let mut argv = app.argv.clone();
+        if app.terminal {
+            argv.splice(0..0, [TERMINAL.to_string(), "-e".to_string()]);
+        }
+        self.spawn(&app.name, &argv, app.working_dir.as_deref());
+    }
+
+    /// Run a command line through the shell, as typed after "> " in the
+    /// launcher
+    pub fn run_shell(&mut self, command: &str) {
+        let argv = ["sh", "-c", command].map(str::to_string);
+        self.spawn(command, &argv, None);
+    }
+
+    /// Offer `text` as the clipboard contents, owned by the compositor
+    pub fn set_clipboard(&mut self, text: String) {
+        set_data_device_selection(
+            &self.display_handle,
+            &self.seat,
+            TEXT_MIME_TYPES.iter().map(|mime| mime.to_string()).collect(),
+            Arc::from(text.into_bytes()),
+        );
+    }
+
+    /// Start a process in `dir` (default: $HOME) with the session
+    /// environment
+    fn spawn(&mut self, name: &str, argv: &[String], dir: Option<&Path>) {
+        let Some((executable, args)) = argv.split_first() else {
+            return;
+        };
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let dir = dir
+            .filter(|dir| dir.is_dir())
+            .map(Path::to_path_buf)
+            .or(home)
+            .unwrap_or_else(|| PathBuf::from("/"));
+
+        // The app focuses its window with this once it maps
+        let (token, _) = self.xdg_activation_state.create_external_token(None);
+        let token = token.to_string();
+
+        let mut command = std::process::Command::new(executable);
+        command
+            .args(args)
+            .current_dir(dir)
+            .env("XDG_SESSION_TYPE", "wayland")
+            .env("XDG_CURRENT_DESKTOP", "heyDM")
+            // Toolkits that default to X11 should use Wayland
+            .env("GDK_BACKEND", "wayland,x11")
+            .env("QT_QPA_PLATFORM", "wayland;xcb")
+            .env("SDL_VIDEODRIVER", "wayland")
+            .env("MOZ_ENABLE_WAYLAND", "1")
+            .env("XDG_ACTIVATION_TOKEN", &token)
+            .env("DESKTOP_STARTUP_ID", &token);
+        crate::launch::unblock_signals(&mut command);
+        match command.spawn() {
+            Ok(child) => self.launches.started(name, child.id(), token),
+            Err(e) => tracing::warn!("Failed to launch {name}: {e}"),
+        }
+    }
+
+    /// The focused window when it is fullscreen and on screen, on the
+    /// output with the panel
+    pub fn fullscreen_window(&self) -> Option<&WindowElement> {
+        let wm = &self.window_manager;
+        wm.focused_window().filter(|window| {
+            window.is_fullscreen() && wm.is_window_visible(window) && wm.is_on_first_output(window)
+        })
+    }
+
+    /// The panel is hidden on the login screen and under fullscreen windows
+    pub fn panel_visible(&self) -> bool {
+        !self.greeter && self.fullscreen_window().is_none()
+    }
+
+    /// Whether anything is drawn on top of the windows: the panel,
+    /// compositor UI or a pointer
+    pub fn overlays_visible(&self) -> bool {
+        let output_size = self.output_size;
+        self.panel_visible()
+            || self.lock.is_locked()
+            || self.launcher.is_visible()
+            || self.task_manager.is_visible()
+            || self.switcher.is_visible()
+            || self.overview.is_visible()
+            || self.calendar.is_visible()
+            || self.network_menu.is_open()
+            || self.bluetooth_menu.is_open()
+            || self.tray.open_menu().is_some()
+            || self.notifications.visible(output_size).next().is_some()
+            || self.osd.visible().is_some()
+            || self.tooltips.visible_text().is_some()
+            || self.window_manager.snap_preview(&output_size).is_some()
+            || !self.cursor.is_hidden()
+            || self.ime_popups.is_visible()
+            || self.launches.busy_for().is_some()
+            || !self.seats.is_empty()
+            || self.zoom.is_active()
+    }
+
+    /// Look up the tooltip under the pointer. Overlays shadow the panel.
+    pub fn update_tooltip(&mut self, pos: (f64, f64)) {
+        if self.task_manager.is_visible() || self.greeter {
+            self.tooltips.hover(None);
+            return;
+        }
+        if self.switcher.is_visible() {
+            let text = self.switcher.tooltip_at(pos, self.output_size);
+            self.tooltips.hover(text);
+            return;
+        }
+        let source: &dyn TooltipSource = if self.launcher.is_visible() {
+            &self.launcher
+        } else if self.panel_visible() {
+            &self.panel
+        } else {
+            self.tooltips.hover(None);
+            return;
+        };
+        let text = source
+            .tooltip_at(pos, self.output_size)
+            .or_else(|| self.tray.tooltip_at(pos, self.output_size).filter(|_| !self.launcher.is_visible()));
+        self.tooltips.hover(text);
+    }
+
+    /// Open or close the task manager; while open it re-samples usage
+    /// every SAMPLE_INTERVAL
+    pub fn toggle_task_manager(&mut self) {
+        if self.task_manager.is_visible() {
+            self.task_manager.hide();
+            return;
+        }
+
+        self.launcher.hide();
+        let generation = self
+            .task_manager
+            .show(&self.window_manager, &self.display_handle);
+        let result = self.loop_handle.insert_source(
+            Timer::from_duration(SAMPLE_INTERVAL),
+            move |_, _, state| {
+                if !state.task_manager.is_current(generation) {
+                    return TimeoutAction::Drop;
+                }
+                state
+                    .task_manager
+                    .refresh(&state.window_manager, &state.display_handle);
+                TimeoutAction::ToDuration(SAMPLE_INTERVAL)
+            },
+        );
+        if let Err(e) = result {
+            tracing::warn!("Failed to schedule task manager sampling: {e}");
+        }
+    }
+
+    /// Show a notification toast and schedule its expiry
+    pub fn show_notification(&mut self, notification: Notification) {
+        let id = notification.id;
+        let Some(generation) = self.notifications.show(notification) else {
+            return;
+        };
+        let Some(timeout) = self.notifications.timeout(id) else {
+            return;
+        };
+        let result = self
+            .loop_handle
+            .insert_source(Timer::from_duration(timeout), move |_, _, state| {
+                state.notifications.expire(id, generation);
+                TimeoutAction::Drop
+            });
+        if let Err(e) = result {
+            tracing::warn!("Failed to schedule notification expiry: {e}");
+        }
+    }
+
+    /// Warn about a low battery and run the critical action
+    fn check_battery(&mut self, battery: BatteryState) {
+        if battery.charging {
+            // A critical warning stays until dismissed; plugging in does it
+            if let Some(id) = self.battery_warnings.notification_id {
+                self.notifications.close(id, CloseReason::Expired);
+            }
+        }
+        let config = &self.config.power;
+        if let Some(warning) = self.battery_warnings.update(config, battery) {
+            let id = match self.battery_warnings.notification_id {
+                Some(id) => id,
+                None => {
+                    let id = self.notifications.next_id();
+                    self.battery_warnings.notification_id = Some(id);
+                    id
+                }
+            };
+            self.osd.show(BATTERY_LABEL, warning.percent);
+            self.show_notification(Notification {
+                id,
+                app_name: "heyDM".to_string(),
+                summary: if warning.critical {
+                    "Battery critically low".to_string()
+                } else {
+                    "Battery low".to_string()
+                },
+                body: format!("{}% remaining. Connect the charger.", warning.percent),
+                actions: Vec::new(),
+                timeout: if warning.critical {
+                    None
+                } else {
+                    Some(DEFAULT_TIMEOUT)
+                },
+                critical: warning.critical,
+            });
+        }
+        self.battery_warnings.check_critical(&self.config.power, battery);
+    }
+
+    /// Read the config file again and apply what can change while running
+    /// (see config.rs). Returns the problems found in the file.
+    pub fn reload_config(&mut self) -> Result<Vec<Diagnostic>, String> {
+        let (config, diagnostics) = Config::reload()?;
+        let old = std::mem::replace(&mut self.config, config);
+
+        if old.keyboard != self.config.keyboard {
+            let xkb = self.config.keyboard.clone();
+            if let Some(keyboard) = self.seat.get_keyboard() {
+                if let Err(e) = keyboard.set_xkb_config(self, xkb.xkb()) {
+                    warn!("Failed to apply the keyboard layout: {e:?}");
+                }
+            }
+        }
+        for device in &mut self.input_devices {
+            self.config.input.apply(device);
+        }
+        self.window_manager
+            .set_tiling(&self.config.tiling, &self.output_size);
+        self.update_ui();
+        self.color.set_config(&self.config.color);
+        let released = self.access.set_config(&self.config.accessibility);
+        InputHandler::release_keys(self, released);
+
+        let appearance = &self.config.appearance;
+        if old.appearance.wallpaper != appearance.wallpaper {
+            self.wallpaper.set(appearance.wallpaper.clone(), self.output_size);
+        }
+        if old.appearance.wallpaper_mode != appearance.wallpaper_mode {
+            self.wallpaper.set_mode(appearance.wallpaper_mode, self.output_size);
+        }
+        if old.appearance.color_scheme != appearance.color_scheme {
+            self.portal.set_color_scheme(appearance.color_scheme);
+        }
+        if old.appearance.accent != appearance.accent
+            || old.appearance.dynamic_accent != appearance.dynamic_accent
+        {
+            self.portal.set_accent(self.accent_color());
+        }
+        self.apply_output_config();
+
+        info!("Configuration reloaded");
+        Ok(diagnostics)
+    }
+
+    /// The `[output]` section for a connector, or the defaults
+    pub fn output_config(&self, connector: &str) -> OutputConfig {
+        let identity = self
+            .outputs
+            .iter()
+            .find(|identity| identity.connector == connector)
+            .cloned()
+            .unwrap_or_else(|| OutputIdentity::new(connector, None));
+        crate::output::config_for(&self.config.outputs, &identity)
+            .map(|(_, config)| config.clone())
+            .unwrap_or_default()
+    }
+
+    /// Apply the `[output]` section matching the primary output: scale,
+    /// position and its own wallpaper here, mode and transform through the
+    /// backend, which also applies the sections of further outputs
+    pub fn apply_output_config(&mut self) {
+        let Some(output) = self.output.clone() else {
+            return;
+        };
+        let name = output.name();
+        let config = self.output_config(&name);
+
+        self.output_modeset = true;
+        let scale = config.scale.unwrap_or(1.0);
+        output.change_current_state(
+            None,
+            None,
+            Some(Scale::Fractional(scale)),
+            Some(config.position.unwrap_or((0, 0)).into()),
+        );
+        self.set_output_scale(scale);
+        let own_wallpaper = OutputWallpaper {
+            path: config.wallpaper,
+            mode: config.wallpaper_mode,
+        };
+        self.wallpaper.set_output(&name, own_wallpaper, self.output_size);
+    }
+
+    /// Switch to another virtual terminal (Ctrl+Alt+F<n>). The session
+    /// pauses until the user switches back; nested heyDM ignores this.
+    pub fn change_vt(&mut self, vt: i32) {
+        let Some(session) = self.session.as_mut() else {
+            return;
+        };
+        info!("Switching to VT {vt}");
+        if let Err(e) = session.change_vt(vt) {
+            warn!("Could not switch to VT {vt}: {e}");
+        }
+    }
+
+    /// The driven output's scale changed: clients are told to render at it
+    /// and compositor-drawn UI follows
+    pub fn set_output_scale(&mut self, scale: f64) {
+        if scale == self.output_scale {
+            return;
+        }
+        info!("Output scale {scale}");
+        self.output_scale = scale;
+        self.cursor.set_scale(scale);
+        for window in self.window_manager.windows() {
+            crate::window::send_scale(window.toplevel().wl_surface(), scale);
+        }
+        self.update_ui();
+    }
+
+    /// Rebuild the UI metrics from `[theme]` and the output scale
+    pub fn update_ui(&mut self) {
+        let ui = UiMetrics::with_output_scale(&self.config.theme, self.output_scale);
+        self.ui = ui;
+        self.panel.set_ui(ui);
+        self.launcher.set_ui(ui);
+        self.switcher.set_ui(ui);
+        self.tray.set_ui(ui);
+        self.notifications.set_ui(ui);
+        self.network_menu.set_ui(ui);
+        self.bluetooth_menu.set_ui(ui);
+        self.calendar.set_ui(ui);
+        self.window_manager
+            .set_scale(self.output_scale, &ui, &self.output_size);
+    }
+
+    /// The driven output changed size (mode, transform or window size)
+    pub fn set_output_size(&mut self, size: Size<i32, smithay::utils::Physical>) {
+        if size == self.output_size {
+            return;
+        }
+        self.output_size = size;
+        self.window_manager.arrange_all(&size);
+        self.wallpaper.reload(size);
+    }
+
+    /// Change a config value at runtime (not saved to the file). Returns
+    /// whether it took effect right away; the rest apply on the next start.
+    pub fn set_config_value(&mut self, section: &str, key: &str, value: &str) -> Result<bool, String> {
+        self.config.set_value(section, None, key, value)?;
+        info!("Config: [{section}] {key} = {value}");
+        let applied = match (section, key) {
+            ("appearance", "wallpaper") => {
+                self.set_wallpaper(self.config.appearance.wallpaper.clone());
+                true
+            }
+            ("appearance", "wallpaper_mode") => {
+                self.wallpaper
+                    .set_mode(self.config.appearance.wallpaper_mode, self.output_size);
+                true
+            }
+            ("appearance", "accent" | "dynamic_accent") => {
+                self.portal.set_accent(self.accent_color());
+                true
+            }
+            // Read whenever they are used
+            ("power" | "compat" | "shell", _) => true,
+            _ => false,
+        };
+        Ok(applied)
+    }
+
+    /// Change the wallpaper (None = plain background)
+    pub fn set_wallpaper(&mut self, path: Option<PathBuf>) {
+        self.wallpaper.set(path, self.output_size);
+    }
+
+    /// Accent for focused borders, the panel and selections: the
+    /// wallpaper's color with dynamic_accent, crimson otherwise
+    pub fn accent_color(&self) -> [f32; 4] {
+        self.wallpaper
+            .accent()
+            .filter(|_| self.config.appearance.dynamic_accent)
+            .or(self.config.appearance.accent)
+            .unwrap_or(colors::ACCENT_CRIMSON)
+    }
+
+    /// Push the status bar export to subscribers when it changed
+    pub fn publish_status(&mut self) {
+        if self.status_subscribers.is_empty() {
+            return;
+        }
+        let status = crate::status::bar_status(self);
+        self.status_subscribers.publish(&status);
+    }
+
+    /// Send window, workspace and output events to subscribers
+    pub fn publish_events(&mut self) {
+        if self.event_subscribers.is_empty() {
+            return;
+        }
+        let snapshot = Snapshot::take(self);
+        self.event_subscribers.publish(snapshot);
+    }
+
+    /// Lock the session with the built-in lock screen
+    pub fn lock_session(&mut self) {
+        self.window_manager.end_grab();
+        self.launcher.hide();
+        self.task_manager.hide();
+        self.switcher.hide();
+        self.lock.lock();
+        self.cursor.reset();
+        self.refresh_keyboard_focus();
     }
 }
 
@@ -304,7 +1258,26 @@ impl CompositorHandler for HeyDM {
 
     fn commit(&mut self, surface: &WlSurface) {
         tracing::debug!("Surface commit: {:?}", surface.id());
-        self.window_manager.handle_commit(surface);
+        if self.cursor.is_cursor_surface(surface) {
+            self.cursor.surface_committed(surface);
+            return;
+        }
+        if self.ime_popups.surface_committed(surface) {
+            crate::scanout::send_frame(self, surface);
+            return;
+        }
+        if let Some(window) = self.window_manager.window_for_surface(surface) {
+            if !self.protocol.check_commit(window, &self.config.compat) {
+                return;
+            }
+        }
+        if self.window_manager.handle_commit(surface, &self.output_size) {
+            if let Some(id) = self.window_manager.window_for_surface(surface).map(|w| w.id()) {
+                crate::restore::window_mapped(self, id);
+                crate::presets::window_mapped(self, id);
+                crate::scratchpad::window_mapped(self, id);
+            }
+        }
     }
 }
 
@@ -317,20 +1290,67 @@ impl XdgShellHandler for HeyDM {
 
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
         info!("New toplevel window created");
+        let client_pid = surface
+            .wl_surface()
+            .client()
+            .and_then(|client| client.get_credentials(&self.display_handle).ok())
+            .map(|credentials| credentials.pid as u32);
+        let launched = self.launches.window_mapped(client_pid);
+        // Focus stealing prevention: a window takes focus when it was just
+        // launched, nothing else has focus or it belongs to the focused app
+        // (a dialog or a new document window)
+        let focused_client = self
+            .window_manager
+            .focused_window()
+            .filter(|window| self.window_manager.is_window_visible(window))
+            .map(|window| window.toplevel().wl_surface().client());
+        let focus = match focused_client {
+            None => true,
+            Some(client) => launched || (client.is_some() && client == surface.wl_surface().client()),
+        };
+
+        crate::window::send_scale(surface.wl_surface(), self.output_scale);
         self.window_manager
-            .add_window(WindowElement::new(surface), &self.output_size);
+            .add_window(WindowElement::new(surface), focus, &self.output_size);
 
         let window = self.window_manager.windows().last().unwrap();
         window.toplevel().send_configure();
+        self.refresh_keyboard_focus();
     }
 
-    fn new_popup(&mut self, _surface: PopupSurface, _positioner: PositionerState) {
+    fn new_popup(&mut self, surface: PopupSurface, _positioner: PositionerState) {
         tracing::debug!("New popup surface created");
+        crate::window::send_scale(surface.wl_surface(), self.output_scale);
     }
 
     fn toplevel_destroyed(&mut self, surface: ToplevelSurface) {
         info!("Toplevel window destroyed");
-        self.window_manager.remove_window(&surface);
+        self.protocol.forget(surface.wl_surface());
+        self.window_manager
+            .remove_window(&surface, &self.output_size);
+        self.refresh_keyboard_focus();
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        self.window_manager
+            .set_maximized(&surface, true, &self.output_size);
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        self.window_manager
+            .set_maximized(&surface, false, &self.output_size);
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        self.window_manager.minimize(&surface, &self.output_size);
+        self.refresh_keyboard_focus();
+    }
+
+    fn move_request(&mut self, surface: ToplevelSurface, _seat: WlSeat, _serial: smithay::utils::Serial) {
+        if self.window_manager.focus_toplevel(&surface) {
+            self.window_manager.begin_move(&self.output_size);
+            self.refresh_keyboard_focus();
+        }
     }
 
     fn grab(&mut self, _surface: PopupSurface, _seat: WlSeat, _serial: smithay::utils::Serial) {}
@@ -363,9 +1383,13 @@ impl SeatHandler for HeyDM {
 
     fn cursor_image(
         &mut self,
-        _seat: &Seat<Self>,
-        _image: smithay::input::pointer::CursorImageStatus,
+        seat: &Seat<Self>,
+        image: smithay::input::pointer::CursorImageStatus,
     ) {
+        // Additional seats always draw the default arrow
+        if *seat == self.seat {
+            self.cursor.set_status(image);
+        }
     }
 
     fn focus_changed(
@@ -385,7 +1409,25 @@ impl DataDeviceHandler for HeyDM {
 }
 
 impl SelectionHandler for HeyDM {
-    type SelectionUserData = ();
+    /// Contents of a selection the compositor owns (see `set_clipboard`)
+    type SelectionUserData = Arc<[u8]>;
+
+    fn send_selection(
+        &mut self,
+        _ty: SelectionTarget,
+        _mime_type: String,
+        fd: OwnedFd,
+        _seat: Seat<Self>,
+        user_data: &Self::SelectionUserData,
+    ) {
+        // Written off the event loop; a slow reader must not stall it
+        let data = user_data.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = std::fs::File::from(fd).write_all(&data) {
+                tracing::warn!("Failed to send the clipboard: {e}");
+            }
+        });
+    }
 }
 
 impl WaylandDndGrabHandler for HeyDM {}
@@ -395,3 +1437,103 @@ delegate_data_device!(HeyDM);
 impl OutputHandler for HeyDM {}
 
 delegate_output!(HeyDM);
+
+delegate_pointer_gestures!(HeyDM);
+
+// Text input follows keyboard focus, and an input method grabs the keyboard
+// while it is active; both are handled by smithay. Candidate popups are
+// kept in ime.rs and drawn below the text field's cursor.
+impl InputMethodHandler for HeyDM {
+    fn new_popup(&mut self, surface: InputMethodPopup) {
+        crate::window::send_scale(surface.wl_surface(), self.output_scale);
+        self.ime_popups.add(surface);
+    }
+
+    fn popup_repositioned(&mut self, surface: InputMethodPopup) {
+        self.ime_popups.repositioned(surface);
+    }
+
+    fn dismiss_popup(&mut self, surface: InputMethodPopup) {
+        self.ime_popups.remove(&surface);
+    }
+
+    fn parent_geometry(&self, parent: &WlSurface) -> Rectangle<i32, Logical> {
+        self.window_manager
+            .window_for_surface(parent)
+            .map(|window| window.geometry())
+            .unwrap_or_default()
+    }
+}
+
+delegate_input_method_manager!(HeyDM);
+delegate_text_input_manager!(HeyDM);
+delegate_virtual_keyboard_manager!(HeyDM);
+delegate_tearing_control!(HeyDM);
+
+impl XdgActivationHandler for HeyDM {
+    fn activation_state(&mut self) -> &mut XdgActivationState {
+        &mut self.xdg_activation_state
+    }
+
+    /// A window is focused with a token heyDM gave an app it launched, or
+    /// one a client got during the user's input in the focused window.
+    /// Anything else only flags the window urgent for the panel and status
+    /// bars, so background apps cannot steal focus.
+    fn request_activation(
+        &mut self,
+        token: XdgActivationToken,
+        token_data: XdgActivationTokenData,
+        surface: WlSurface,
+    ) {
+        let fresh = token_data.timestamp.elapsed() < LAUNCH_TIMEOUT;
+        let from_input = token_data.serial.as_ref().is_some_and(|(serial, seat)| {
+            Seat::<Self>::from_resource(seat)
+                .and_then(|seat| seat.get_keyboard())
+                .and_then(|keyboard| keyboard.last_enter())
+                .is_some_and(|last_enter| serial.is_no_older_than(&last_enter))
+        });
+        let valid = fresh && (from_input || self.launches.is_launch_token(&token));
+        // Tokens are single use
+        self.xdg_activation_state
+            .retain_tokens(|known, data| *known != token && data.timestamp.elapsed() < LAUNCH_TIMEOUT);
+
+        let id = self.window_manager.window_for_surface(&surface).map(|w| w.id());
+        match id {
+            Some(id) if valid && !self.lock.is_locked() => {
+                info!("Window {id} activated");
+                self.window_manager.activate(id, &self.output_size);
+                self.refresh_keyboard_focus();
+            }
+            _ => self.window_manager.set_urgent(&surface),
+        }
+    }
+}
+
+delegate_xdg_activation!(HeyDM);
+
+impl DmabufHandler for HeyDM {
+    fn dmabuf_state(&mut self) -> &mut DmabufState {
+        &mut self.dmabuf_state
+    }
+
+    /// Client dmabufs are only ever scanned out, and the DRM backend falls
+    /// back to compositing when a buffer does not fit the plane, so every
+    /// import is accepted
+    fn dmabuf_imported(&mut self, _global: &DmabufGlobal, _dmabuf: Dmabuf, notifier: ImportNotifier) {
+        let _ = notifier.successful::<HeyDM>();
+    }
+}
+
+delegate_dmabuf!(HeyDM);
+
+impl FractionalScaleHandler for HeyDM {
+    fn new_fractional_scale(&mut self, surface: WlSurface) {
+        let scale = self.output_scale;
+        with_states(&surface, |states| {
+            with_fractional_scale(states, |fractional| fractional.set_preferred_scale(scale));
+        });
+    }
+}
+
+delegate_fractional_scale!(HeyDM);
+delegate_viewporter!(HeyDM);
@@ -0,0 +1,182 @@
+// =============================================================================
+// heyDM — Status Bar Export
+//
+// Workspace and window state for status bars (waybar custom modules, eww,
+// shell prompts). Served over the IPC socket:
+//
+//     status            → one status object
+//     subscribe status  → the current status, then a new line every time it
+//                         changes; the connection stays open
+//
+// and from the command line, for scripts that cannot speak to a socket:
+//
+//     heydm bar-status           # stream, one JSON object per line
+//     heydm bar-status --once    # print the current status and exit
+//
+// Schema (version 1). Fields are only ever added; a change to an existing
+// field bumps `version`:
+//
+//     {
+//       "version": 1,
+//       "focused_output": "eDP-1" | null,
+//       "layout": "floating" | "master_stack" | "dwindle",  (active workspace)
+//       "focused_window": null | {
+//         "id": 4, "title": "...", "app_id": "...", "workspace": 2
+//       },
+//       "workspaces": [                   (always 1..=9, in order)
+//         {
+//           "num": 1,
+//           "output": "eDP-1" | null,     (output it lives or would open on)
+//           "active": true,               (the focused output's workspace)
+//           "visible": true,              (shown on some output)
+//           "occupied": true,             (has windows)
+//           "urgent": false,              (a window asked for attention)
+//           "layout": "floating",
+//           "windows": 3
+//         }, ...
+//       ],
+//       "outputs": [ { "name": "eDP-1", "workspace": 1 }, ... ]
+//     }
+// =============================================================================
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::ipc;
+use crate::state::HeyDM;
+use crate::workspace::WORKSPACE_COUNT;
+
+/// Current schema version
+pub const STATUS_VERSION: u32 = 1;
+
+/// Build the status object for the current state
+pub fn bar_status(state: &HeyDM) -> Value {
+    let wm = &state.window_manager;
+    let workspaces = wm.workspaces();
+    let active = workspaces.active_workspace();
+
+    let workspace_list: Vec<Value> = (1..=WORKSPACE_COUNT)
+        .map(|ws| {
+            let windows: Vec<_> = wm.windows().iter().filter(|w| w.workspace() == ws).collect();
+            json!({
+                "num": ws,
+                "output": workspaces.output_of(ws),
+                "active": ws == active,
+                "visible": workspaces.is_visible(ws),
+                "occupied": !windows.is_empty(),
+                "urgent": windows.iter().any(|w| w.is_urgent()),
+                "layout": wm.layout_name(ws),
+                "windows": windows.len(),
+            })
+        })
+        .collect();
+
+    let focused_window = wm.focused_window().map(|window| {
+        let (title, app_id) = window.title_and_app_id();
+        json!({
+            "id": window.id(),
+            "title": title,
+            "app_id": app_id,
+            "workspace": window.workspace(),
+        })
+    });
+
+    let outputs: Vec<Value> = workspaces
+        .outputs()
+        .iter()
+        .map(|name| json!({ "name": name, "workspace": workspaces.active_on(name) }))
+        .collect();
+
+    json!({
+        "version": STATUS_VERSION,
+        "focused_output": workspaces.focused_output(),
+        "layout": wm.layout_name(active),
+        "focused_window": focused_window,
+        "workspaces": workspace_list,
+        "outputs": outputs,
+    })
+}
+
+/// IPC connections subscribed to status updates
+#[derive(Debug, Default)]
+pub struct StatusSubscribers {
+    streams: Vec<UnixStream>,
+    /// Last line sent, so unchanged state is not re-sent
+    last: Option<String>,
+}
+
+impl StatusSubscribers {
+    /// Add a subscriber and send it the current status right away
+    pub fn add(&mut self, stream: UnixStream, status: &Value) {
+        if stream.set_nonblocking(true).is_err() {
+            return;
+        }
+        let line = status.to_string();
+        let mut stream = stream;
+        if writeln!(stream, "{line}").is_ok() {
+            self.streams.push(stream);
+            self.last = Some(line);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Send the status to every subscriber if it changed. Subscribers that
+    /// went away or stopped reading are dropped.
+    pub fn publish(&mut self, status: &Value) {
+        let line = status.to_string();
+        if self.last.as_deref() == Some(line.as_str()) {
+            return;
+        }
+        self.streams.retain_mut(|stream| match writeln!(stream, "{line}") {
+            Ok(()) => true,
+            Err(e) => {
+                debug!("Dropping status subscriber: {e}");
+                false
+            }
+        });
+        self.last = Some(line);
+    }
+}
+
+/// `heydm bar-status [--once]` — print the status from a running heyDM.
+/// Returns the process exit code.
+pub fn run_client(args: &[String]) -> i32 {
+    let once = args.iter().any(|a| a == "--once");
+    let Some(path) = ipc::client_socket_path() else {
+        eprintln!("heydm bar-status: neither HEYDM_SOCK nor WAYLAND_DISPLAY is set");
+        return 2;
+    };
+    let mut stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("heydm bar-status: {}: {e}", path.display());
+            return 2;
+        }
+    };
+
+    let command = if once { "status" } else { "subscribe status" };
+    if let Err(e) = writeln!(stream, "{command}") {
+        eprintln!("heydm bar-status: {e}");
+        return 2;
+    }
+
+    let stdout = std::io::stdout();
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return 1;
+        };
+        let mut out = stdout.lock();
+        // A closed pipe (e.g. `| head -1`) ends the stream quietly
+        if writeln!(out, "{line}").and_then(|()| out.flush()).is_err() || once {
+            return 0;
+        }
+    }
+    // heyDM closed the connection
+    1
+}
@@ -0,0 +1,223 @@
+// =============================================================================
+// heyDM — Window Switcher
+//
+// Super+Tab opens an overlay listing the windows of the visible workspaces,
+// most recently focused first, with the next window preselected. While Super
+// is held:
+//
+//     Tab / Shift+Tab   select the next / previous window
+//     Escape            close without changing focus
+//
+// Releasing Super focuses and raises the selected window. Hovering an
+// entry whose app id or title is cut off shows both in full as a tooltip.
+// =============================================================================
+
+use smithay::utils::{Physical, Rectangle, Size};
+
+use crate::theme::UiMetrics;
+use crate::tooltip::TooltipSource;
+use crate::window::WindowManager;
+
+/// Characters of the app id and of the title shown in a row
+pub const APP_ID_CHARS: usize = 24;
+pub const TITLE_CHARS: usize = 40;
+
+/// One window in the switcher list
+#[derive(Debug, Clone)]
+pub struct SwitcherEntry {
+    pub window_id: u32,
+    pub title: String,
+    pub app_id: String,
+}
+
+/// Where the overlay and its rows are drawn
+pub struct SwitcherLayout {
+    pub area: Rectangle<i32, Physical>,
+    pub row_h: i32,
+    /// Top of the first row
+    pub rows_y: i32,
+    /// Index of the first entry shown, so the selection stays visible
+    pub first: usize,
+    /// Rows that fit on the output
+    pub max_rows: usize,
+}
+
+/// Alt-Tab style window switcher
+#[derive(Debug)]
+pub struct WindowSwitcher {
+    entries: Vec<SwitcherEntry>,
+    selected: usize,
+    visible: bool,
+    ui: UiMetrics,
+}
+
+impl WindowSwitcher {
+    pub fn new(ui: UiMetrics) -> Self {
+        Self {
+            entries: Vec::new(),
+            selected: 0,
+            visible: false,
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed)
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    /// Open the switcher on the windows of the visible workspaces. Returns
+    /// false when there is nothing to switch to.
+    pub fn show(&mut self, wm: &WindowManager) -> bool {
+        // The stack is ordered bottom to top and focusing raises a window,
+        // so walking it top-down gives the most recently used first
+        let windows: Vec<_> = wm.visible_windows().collect();
+        self.entries = windows
+            .into_iter()
+            .rev()
+            .map(|window| {
+                let (title, app_id) = window.title_and_app_id();
+                SwitcherEntry {
+                    window_id: window.id(),
+                    title: title.unwrap_or_default(),
+                    app_id: app_id.unwrap_or_default(),
+                }
+            })
+            .collect();
+        if self.entries.len() <= 1 {
+            self.hide();
+            return false;
+        }
+        self.selected = 1;
+        self.visible = true;
+        true
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.entries.clear();
+        self.selected = 0;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Move the selection forward (Tab) or backward (Shift+Tab), wrapping
+    pub fn step(&mut self, backward: bool) {
+        let count = self.entries.len();
+        if count == 0 {
+            return;
+        }
+        self.selected = if backward {
+            (self.selected + count - 1) % count
+        } else {
+            (self.selected + 1) % count
+        };
+    }
+
+    /// Close the switcher and return the window to focus
+    pub fn commit(&mut self) -> Option<u32> {
+        let id = self.entries.get(self.selected).map(|entry| entry.window_id);
+        self.hide();
+        id
+    }
+
+    pub fn entries(&self) -> &[SwitcherEntry] {
+        &self.entries
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// The overlay centered on the output, tall enough for the rows shown
+    pub fn layout(&self, output_size: Size<i32, Physical>) -> SwitcherLayout {
+        let ui = &self.ui;
+        let row_h = ui.px(40);
+        let max_rows = ((output_size.h - ui.px(200)) / row_h).max(1) as usize;
+        let first = (self.selected + 1).saturating_sub(max_rows);
+        let shown = self.entries.len().min(max_rows);
+
+        let w = ui.px(560).min(output_size.w - ui.px(100)).max(0);
+        let h = shown as i32 * row_h + ui.px(24);
+        let (x, y) = ((output_size.w - w) / 2, (output_size.h - h) / 2);
+        SwitcherLayout {
+            area: Rectangle::new((x, y).into(), (w, h).into()),
+            row_h,
+            rows_y: y + ui.px(12),
+            first,
+            max_rows,
+        }
+    }
+
+    /// The entry in the row under `pos`
+    fn entry_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<&SwitcherEntry> {
+        let layout = self.layout(output_size);
+        let area = layout.area;
+        let inside = pos.0 >= area.loc.x as f64
+            && pos.0 < (area.loc.x + area.size.w) as f64
+            && pos.1 >= layout.rows_y as f64;
+        if !inside {
+            return None;
+        }
+        let row = ((pos.1 - layout.rows_y as f64) / layout.row_h as f64) as usize;
+        if row >= layout.max_rows {
+            return None;
+        }
+        self.entries.get(layout.first + row)
+    }
+}
+
+impl TooltipSource for WindowSwitcher {
+    /// Full title and app id of an entry that is cut off in the list
+    fn tooltip_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<String> {
+        if !self.visible {
+            return None;
+        }
+        let entry = self.entry_at(pos, output_size)?;
+        let cut_off = entry.app_id.chars().count() > APP_ID_CHARS
+            || entry.title.chars().count() > TITLE_CHARS;
+        cut_off.then(|| format!("{} — {}", entry.title, entry.app_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::ThemeConfig;
+
+    fn switcher(titles: &[&str]) -> WindowSwitcher {
+        let mut switcher = WindowSwitcher::new(UiMetrics::new(&ThemeConfig::default()));
+        switcher.entries = titles
+            .iter()
+            .enumerate()
+            .map(|(i, title)| SwitcherEntry {
+                window_id: i as u32,
+                title: title.to_string(),
+                app_id: "org.example.App".to_string(),
+            })
+            .collect();
+        switcher.selected = 1;
+        switcher.visible = true;
+        switcher
+    }
+
+    #[test]
+    fn cut_off_entries_have_tooltips() {
+        let long = "A very long document title that does not fit in the switcher";
+        let switcher = switcher(&["Short", long]);
+        let output = Size::from((1920, 1080));
+        let layout = switcher.layout(output);
+        let x = (layout.area.loc.x + 50) as f64;
+        let row = |i: i32| (layout.rows_y + i * layout.row_h + layout.row_h / 2) as f64;
+
+        assert_eq!(switcher.tooltip_at((x, row(0)), output), None);
+        assert_eq!(
+            switcher.tooltip_at((x, row(1)), output),
+            Some(format!("{long} — org.example.App"))
+        );
+        assert_eq!(switcher.tooltip_at((x, row(2)), output), None);
+        assert_eq!(switcher.tooltip_at((0.0, row(1)), output), None);
+    }
+}
@@ -0,0 +1,53 @@
+// =============================================================================
+// heyDM — Switch User
+//
+// Super+Ctrl+L (or `heyctl switch-user`) locks this session and brings up a
+// second login screen so another user can log in while this session keeps
+// running. The login screen comes from a second greetd instance,
+// hey-greeter-switch.service, whose config asks for the next free VT and
+// switches to it; the unit is started over D-Bus (a polkit rule lets active
+// local users do that without a password). Returning works through the VT
+// switch keys (Ctrl+Alt+F<n>) and this session's lock screen.
+//
+// While the second greeter (or the session started from it) runs, starting
+// the unit again does nothing.
+// =============================================================================
+
+use tracing::{info, warn};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::state::HeyDM;
+
+/// The greetd instance for additional logins
+const UNIT: &str = "hey-greeter-switch.service";
+
+/// Lock the session and start the second greeter
+pub fn switch_user(state: &mut HeyDM) {
+    if state.greeter {
+        return;
+    }
+    info!("Switching user: locking the session and starting {UNIT}");
+    state.lock_session();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-switch-user".to_string())
+        .spawn(|| {
+            if let Err(e) = start_greeter() {
+                warn!("Could not start {UNIT}: {e}");
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start switch user thread: {e}");
+    }
+}
+
+fn start_greeter() -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    let systemd = Proxy::new(
+        &connection,
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        "org.freedesktop.systemd1.Manager",
+    )?;
+    let _job: zbus::zvariant::OwnedObjectPath = systemd.call("StartUnit", &(UNIT, "replace"))?;
+    Ok(())
+}
@@ -0,0 +1,321 @@
+// =============================================================================
+// heyDM — Task Manager Overlay
+//
+// Super+Shift+Escape lists every window together with the CPU and memory use
+// of the process that owns it (pid from the Wayland client credentials,
+// usage sampled from /proc once per SAMPLE_INTERVAL while the overlay is
+// open). While visible it captures the keyboard:
+//
+//     Up / Down    select a row
+//     Tab          cycle the sort column (CPU → memory → name)
+//     Delete       force-kill the selected process (press twice to confirm)
+//     Escape       close the overlay
+// =============================================================================
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use smithay::reexports::wayland_server::DisplayHandle;
+use tracing::{info, warn};
+
+use crate::window::WindowManager;
+
+/// How often CPU and memory usage are re-sampled while the overlay is open
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Column the rows are ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Cpu,
+    Memory,
+    Name,
+}
+
+impl SortColumn {
+    fn next(self) -> Self {
+        match self {
+            Self::Cpu => Self::Memory,
+            Self::Memory => Self::Name,
+            Self::Name => Self::Cpu,
+        }
+    }
+}
+
+/// One window and the resource use of its process
+#[derive(Debug, Clone)]
+pub struct TaskRow {
+    pub window_id: u32,
+    /// Window title, falling back to the app id
+    pub name: String,
+    pub pid: Option<i32>,
+    /// CPU use since the previous sample, in percent of one core
+    pub cpu: f32,
+    /// Resident memory in KiB
+    pub memory_kb: u64,
+}
+
+/// CPU time of a process at the moment it was read
+#[derive(Debug, Clone, Copy)]
+struct CpuSample {
+    ticks: u64,
+    at: Instant,
+}
+
+/// State of the task manager overlay
+#[derive(Debug)]
+pub struct TaskManager {
+    visible: bool,
+    /// Bumped on every show, so sampling timers from earlier opens stop
+    generation: u64,
+    rows: Vec<TaskRow>,
+    sort: SortColumn,
+    /// Window id of the selected row (kept across re-sorts)
+    selected: Option<u32>,
+    /// Window whose process is waiting for a second Delete
+    confirm_kill: Option<u32>,
+    /// Result of the last kill, shown in the footer
+    status: Option<String>,
+    samples: HashMap<i32, CpuSample>,
+    /// Clock ticks per second for /proc/<pid>/stat times
+    ticks_per_second: f32,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        Self {
+            visible: false,
+            generation: 0,
+            rows: Vec::new(),
+            sort: SortColumn::Cpu,
+            selected: None,
+            confirm_kill: None,
+            status: None,
+            samples: HashMap::new(),
+            ticks_per_second: if ticks > 0 { ticks as f32 } else { 100.0 },
+        }
+    }
+
+    /// Open the overlay with a fresh sample. Returns the generation the
+    /// caller's sampling timer belongs to.
+    pub fn show(&mut self, windows: &WindowManager, display_handle: &DisplayHandle) -> u64 {
+        self.visible = true;
+        self.generation += 1;
+        self.confirm_kill = None;
+        self.status = None;
+        self.samples.clear();
+        self.refresh(windows, display_handle);
+        self.selected = self.rows.first().map(|row| row.window_id);
+        self.generation
+    }
+
+    pub fn hide(&mut self) {
+        self.visible = false;
+        self.rows.clear();
+        self.samples.clear();
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Whether a sampling timer from `generation` should keep running
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.visible && self.generation == generation
+    }
+
+    /// Rebuild the rows from the current windows and /proc
+    pub fn refresh(&mut self, windows: &WindowManager, display_handle: &DisplayHandle) {
+        let now = Instant::now();
+        let mut samples = HashMap::new();
+        let mut usage: HashMap<i32, (f32, u64)> = HashMap::new();
+
+        self.rows = windows
+            .windows()
+            .iter()
+            .map(|window| {
+                let (title, app_id) = window.title_and_app_id();
+                let name = title
+                    .filter(|t| !t.is_empty())
+                    .or(app_id)
+                    .unwrap_or_else(|| "(untitled)".to_string());
+                let pid = window.owner(display_handle).map(|(pid, _)| pid);
+
+                let (cpu, memory_kb) = match pid {
+                    Some(pid) => *usage.entry(pid).or_insert_with(|| {
+                        let cpu = match cpu_ticks(pid) {
+                            Some(ticks) => {
+                                let sample = CpuSample { ticks, at: now };
+                                samples.insert(pid, sample);
+                                self.cpu_percent(pid, sample)
+                            }
+                            None => 0.0,
+                        };
+                        (cpu, resident_kb(pid).unwrap_or(0))
+                    }),
+                    None => (0.0, 0),
+                };
+
+                TaskRow {
+                    window_id: window.id(),
+                    name,
+                    pid,
+                    cpu,
+                    memory_kb,
+                }
+            })
+            .collect();
+        self.samples = samples;
+        self.sort_rows();
+
+        if !self.rows.iter().any(|row| Some(row.window_id) == self.selected) {
+            self.selected = self.rows.first().map(|row| row.window_id);
+        }
+    }
+
+    /// CPU use between the previous sample of `pid` and `sample`
+    fn cpu_percent(&self, pid: i32, sample: CpuSample) -> f32 {
+        let Some(previous) = self.samples.get(&pid) else {
+            return 0.0;
+        };
+        let elapsed = sample.at.duration_since(previous.at).as_secs_f32();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        let ticks = sample.ticks.saturating_sub(previous.ticks) as f32;
+        ticks / self.ticks_per_second / elapsed * 100.0
+    }
+
+    fn sort_rows(&mut self) {
+        match self.sort {
+            SortColumn::Cpu => self.rows.sort_by(|a, b| b.cpu.total_cmp(&a.cpu)),
+            SortColumn::Memory => self.rows.sort_by(|a, b| b.memory_kb.cmp(&a.memory_kb)),
+            SortColumn::Name => self
+                .rows
+                .sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase())),
+        }
+    }
+
+    /// Handle a key press while the overlay is open
+    pub fn handle_key(&mut self, keysym: xkbcommon::xkb::Keysym) {
+        use xkbcommon::xkb::Keysym as K;
+
+        if keysym != K::Delete {
+            self.confirm_kill = None;
+        }
+
+        match keysym {
+            K::Escape => self.hide(),
+            K::Up => self.move_selection(-1),
+            K::Down => self.move_selection(1),
+            K::Tab | K::ISO_Left_Tab => {
+                self.sort = self.sort.next();
+                self.sort_rows();
+            }
+            K::Delete => self.kill_selected(),
+            _ => {}
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let current = self.selected_index().unwrap_or(0) as isize;
+        let next = (current + delta).clamp(0, self.rows.len() as isize - 1) as usize;
+        self.selected = Some(self.rows[next].window_id);
+    }
+
+    /// SIGKILL the selected row's process on the second Delete
+    fn kill_selected(&mut self) {
+        let Some(row) = self.selected_index().map(|idx| self.rows[idx].clone()) else {
+            return;
+        };
+        let Some(pid) = row.pid.filter(|pid| *pid > 0 && *pid as u32 != std::process::id())
+        else {
+            self.status = Some(format!("Cannot kill '{}': owner unknown", row.name));
+            return;
+        };
+
+        if self.confirm_kill != Some(row.window_id) {
+            self.confirm_kill = Some(row.window_id);
+            return;
+        }
+        self.confirm_kill = None;
+
+        match kill(Pid::from_raw(pid), Signal::SIGKILL) {
+            Ok(()) => {
+                info!("Task manager: killed pid {pid} ('{}')", row.name);
+                self.status = Some(format!("Killed {} (pid {pid})", row.name));
+            }
+            Err(e) => {
+                warn!("Task manager: failed to kill pid {pid}: {e}");
+                self.status = Some(format!("Failed to kill pid {pid}: {e}"));
+            }
+        }
+    }
+
+    pub fn rows(&self) -> &[TaskRow] {
+        &self.rows
+    }
+
+    pub fn sort_column(&self) -> SortColumn {
+        self.sort
+    }
+
+    pub fn selected_index(&self) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|row| Some(row.window_id) == self.selected)
+    }
+
+    /// Footer line: kill confirmation, last result, or the key help
+    pub fn footer_text(&self) -> String {
+        if let Some(row) = self
+            .confirm_kill
+            .and_then(|id| self.rows.iter().find(|row| row.window_id == id))
+        {
+            return format!(
+                "Press Delete again to force-kill {} (pid {})",
+                row.name,
+                row.pid.unwrap_or(0)
+            );
+        }
+        self.status.clone().unwrap_or_else(|| {
+            "Up/Down select   Tab sort   Delete kill   Esc close".to_string()
+        })
+    }
+}
+
+/// utime + stime of a process, in clock ticks
+fn cpu_ticks(pid: i32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name may contain spaces; the fixed fields follow its ')'
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+/// Resident set size of a process, in KiB
+fn resident_kb(pid: i32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|kb| kb.parse().ok())
+}
+
+/// Human-readable memory size ("512 KiB", "37.4 MiB", "1.2 GiB")
+pub fn format_memory(kb: u64) -> String {
+    if kb >= 1024 * 1024 {
+        format!("{:.1} GiB", kb as f64 / (1024.0 * 1024.0))
+    } else if kb >= 1024 {
+        format!("{:.1} MiB", kb as f64 / 1024.0)
+    } else {
+        format!("{kb} KiB")
+    }
+}
@@ -0,0 +1,127 @@
+// =============================================================================
+// heyDM — Tearing & Adaptive Sync
+//
+// Games want their frames on screen as soon as they are drawn. Two things
+// help, both only while a fullscreen window is scanned out directly (see
+// scanout.rs) — composited frames always wait for vblank:
+//   - Tearing: a client asking for async presentation through
+//     wp_tearing_control_v1 gets its new buffers flipped immediately instead
+//     of at the next vblank, at the cost of a visible tear line
+//   - Adaptive sync (VRR): the monitor refreshes when a frame arrives
+//     rather than at a fixed rate, on FreeSync/G-Sync compatible monitors
+//
+//     [output "LG 27GP850"]
+//     vrr = fullscreen         # off, on (always) or fullscreen (games only)
+//     allow_tearing = true     # honor clients that ask for tearing
+//
+//     [window "steam_app_570"]
+//     tearing = true           # tear even without the client asking
+//     vrr = false              # keep a fixed refresh for this app
+//
+// `[window "..."]` sections are matched against the app id exactly, or
+// against the sandbox label of Flatpak and Snap apps (see sandbox.rs); the
+// most specific section wins: label, then app id, then "sandboxed".
+// =============================================================================
+
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1::PresentationHint;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::wayland::compositor::with_states;
+use smithay::wayland::tearing_control::TearingControlSurfaceCachedState;
+
+use crate::sandbox::{self, SANDBOXED_RULE};
+use crate::state::HeyDM;
+use crate::window::WindowElement;
+
+/// Accepted `vrr` values of an [output] section
+pub const VRR_MODES: &[&str] = &["off", "on", "fullscreen"];
+
+/// When an output uses adaptive sync
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VrrMode {
+    #[default]
+    Off,
+    On,
+    /// Only while a fullscreen window is scanned out
+    Fullscreen,
+}
+
+impl VrrMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "off" => Ok(Self::Off),
+            "on" => Ok(Self::On),
+            "fullscreen" => Ok(Self::Fullscreen),
+            _ => Err(format!("invalid vrr mode '{value}'")),
+        }
+    }
+}
+
+/// Settings from one `[window "app id"]` section
+#[derive(Debug, Clone, Default)]
+pub struct WindowRule {
+    /// Force (true) or forbid (false) tearing, whatever the client asks for
+    pub tearing: Option<bool>,
+    /// Allow adaptive sync while the window is fullscreen; on by default
+    pub vrr: Option<bool>,
+}
+
+impl WindowRule {
+    /// Set a key from the config file
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let flag = match value {
+            "true" | "yes" | "on" => true,
+            "false" | "no" | "off" => false,
+            _ => return Err(format!("expected true or false, got '{value}'")),
+        };
+        match key {
+            "tearing" => self.tearing = Some(flag),
+            "vrr" => self.vrr = Some(flag),
+            _ => return Err(format!("unknown window setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// The `[window]` section that applies to `window`, if there is one
+pub fn window_rule(state: &HeyDM, window: &WindowElement) -> Option<WindowRule> {
+    let rules = &state.config.window_rules;
+    let sandbox = sandbox::of_window(window);
+    let (_, app_id) = window.title_and_app_id();
+    let by_label = || rules.get(&sandbox.label()).filter(|_| sandbox.is_sandboxed());
+    let by_app_id = || {
+        app_id
+            .as_deref()
+            .filter(|app_id| sandbox.may_claim(app_id))
+            .and_then(|app_id| rules.get(app_id))
+    };
+    let by_sandbox = || rules.get(SANDBOXED_RULE).filter(|_| sandbox.is_sandboxed());
+    by_label().or_else(by_app_id).or_else(by_sandbox).cloned()
+}
+
+/// The rule for the fullscreen window, if there is one
+fn fullscreen_rule(state: &HeyDM) -> Option<WindowRule> {
+    window_rule(state, state.fullscreen_window()?)
+}
+
+/// Whether a new buffer of the scanned-out `surface` may be flipped without
+/// waiting for vblank on an output that allows tearing
+pub fn wants_tearing(state: &HeyDM, surface: &WlSurface) -> bool {
+    if let Some(forced) = fullscreen_rule(state).and_then(|rule| rule.tearing) {
+        return forced;
+    }
+    with_states(surface, |states| {
+        let mut cached = states.cached_state.get::<TearingControlSurfaceCachedState>();
+        *cached.current().presentation_hint() == PresentationHint::Async
+    })
+}
+
+/// Whether adaptive sync should be on, with the output in `mode`;
+/// `scanning_out` is whether a fullscreen window is on the primary plane
+pub fn wants_vrr(state: &HeyDM, mode: VrrMode, scanning_out: bool) -> bool {
+    let allowed = || fullscreen_rule(state).and_then(|rule| rule.vrr) != Some(false);
+    match mode {
+        VrrMode::Off => false,
+        VrrMode::On => allowed(),
+        VrrMode::Fullscreen => scanning_out && allowed(),
+    }
+}
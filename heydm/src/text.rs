@@ -0,0 +1,194 @@
+// =============================================================================
+// heyDM — Text Rendering
+//
+// Rasterizes UI strings with fontdue into premultiplied ARGB8888 buffers.
+// The texture cache (texture.rs) uploads them per (text, size, color), so
+// static labels cost one upload and the clock re-uploads once a minute.
+// =============================================================================
+
+use std::path::{Path, PathBuf};
+
+use fontdue::layout::{CoordinateSystem, Layout, TextStyle};
+use fontdue::{Font, FontSettings};
+use walkdir::WalkDir;
+use tracing::{info, warn};
+
+use crate::texture::PixelBuffer;
+
+/// Directories searched for fonts given by family name
+const FONT_DIRS: &[&str] = &["/usr/share/fonts", "/usr/local/share/fonts"];
+
+/// Fonts tried in order; the first one that loads is used for all UI text
+const FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+];
+
+/// Identifies one rasterized string
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextKey {
+    pub text: String,
+    /// Pixel size of the font
+    pub size: u32,
+    /// RGBA color quantized to 8 bits per channel
+    pub color: [u8; 4],
+}
+
+impl TextKey {
+    pub fn new(text: &str, size: u32, color: [f32; 4]) -> Self {
+        Self {
+            text: text.to_string(),
+            size,
+            color: color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+        }
+    }
+}
+
+/// Font loading, measurement and rasterization
+pub struct TextRenderer {
+    font: Option<Font>,
+}
+
+#[allow(dead_code)]
+impl TextRenderer {
+    /// Load the configured font (`[theme] font`), falling back to the first
+    /// available built-in one
+    pub fn new(font: Option<&str>) -> Self {
+        let configured = font.and_then(|font| {
+            let path = resolve_font(font);
+            if path.is_none() {
+                warn!("Font '{font}' not found, using the default UI font");
+            }
+            path
+        });
+        let candidates = configured
+            .into_iter()
+            .chain(FONT_PATHS.iter().map(PathBuf::from));
+        for path in candidates {
+            let Ok(bytes) = std::fs::read(&path) else {
+                continue;
+            };
+            match Font::from_bytes(bytes, FontSettings::default()) {
+                Ok(font) => {
+                    info!("UI font loaded from {}", path.display());
+                    return Self { font: Some(font) };
+                }
+                Err(e) => warn!("Failed to parse font {}: {e}", path.display()),
+            }
+        }
+
+        warn!("No UI font found — compositor text will not be drawn");
+        Self { font: None }
+    }
+
+    /// Whether a font is available
+    pub fn is_available(&self) -> bool {
+        self.font.is_some()
+    }
+
+    /// Lay out a single line of text
+    fn layout(&self, font: &Font, text: &str, size: u32) -> Layout {
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        layout.append(&[font], &TextStyle::new(text, size as f32, 0));
+        layout
+    }
+
+    /// Measure the pixel size of a single line of text
+    pub fn measure(&self, text: &str, size: u32) -> (i32, i32) {
+        let Some(font) = &self.font else {
+            return (0, size as i32);
+        };
+
+        let layout = self.layout(font, text, size);
+        let width = layout
+            .glyphs()
+            .iter()
+            .map(|g| g.x + font.metrics(g.parent, size as f32).advance_width)
+            .fold(0.0_f32, f32::max);
+        (width.ceil() as i32, layout.height().ceil() as i32)
+    }
+
+    /// Rasterize a string into a premultiplied ARGB8888 buffer
+    pub fn rasterize(&self, key: &TextKey) -> Option<PixelBuffer> {
+        let font = self.font.as_ref()?;
+        let (width, height) = self.measure(&key.text, key.size);
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let [r, g, b, a] = key.color.map(|c| c as f32 / 255.0);
+
+        for glyph in self.layout(font, &key.text, key.size).glyphs() {
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+            let (_, coverage) = font.rasterize_config(glyph.key);
+
+            for gy in 0..glyph.height {
+                for gx in 0..glyph.width {
+                    let px = glyph.x as i32 + gx as i32;
+                    let py = glyph.y as i32 + gy as i32;
+                    if px < 0 || py < 0 || px >= width || py >= height {
+                        continue;
+                    }
+
+                    let alpha = coverage[gy * glyph.width + gx] as f32 / 255.0 * a;
+                    let offset = ((py * width + px) * 4) as usize;
+                    // Overlapping glyph edges keep the stronger coverage
+                    if alpha * 255.0 <= pixels[offset + 3] as f32 {
+                        continue;
+                    }
+                    pixels[offset] = (b * alpha * 255.0) as u8;
+                    pixels[offset + 1] = (g * alpha * 255.0) as u8;
+                    pixels[offset + 2] = (r * alpha * 255.0) as u8;
+                    pixels[offset + 3] = (alpha * 255.0) as u8;
+                }
+            }
+        }
+
+        Some(PixelBuffer {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Find a font file by path or family name. "Inter" matches Inter.ttf or
+/// Inter-Regular.ttf (case-insensitive, spaces ignored) in the system and
+/// user font directories.
+fn resolve_font(font: &str) -> Option<PathBuf> {
+    let path = Path::new(font);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let family: String = font.split_whitespace().collect::<String>().to_lowercase();
+    let user_dir = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/fonts"));
+    let mut fallback = None;
+    for dir in user_dir.into_iter().chain(FONT_DIRS.iter().map(PathBuf::from)) {
+        for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
+            let path = entry.path();
+            let is_font = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("ttf") || ext.eq_ignore_ascii_case("otf"));
+            let Some(stem) = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()) else {
+                continue;
+            };
+            if !is_font {
+                continue;
+            }
+            if stem == family || stem == format!("{family}-regular") {
+                return Some(path.to_path_buf());
+            }
+            // Any other style of the family if there is no regular one
+            if fallback.is_none() && stem.starts_with(&format!("{family}-")) {
+                fallback = Some(path.to_path_buf());
+            }
+        }
+    }
+    fallback
+}
@@ -0,0 +1,201 @@
+// =============================================================================
+// heyDM — Texture Cache
+//
+// CPU-side images (rasterized text, cursor frames, icons) are described by
+// the scene and uploaded here before the frame starts. Entries are keyed by
+// text key or image identity and evicted once they go unused for a while.
+// Pixels pass through the color filter on upload (see filters.rs); when the
+// filter changes everything is uploaded again.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::renderer::{ImportMem, Renderer as SmithayRenderer};
+use smithay::utils::{Buffer, Size};
+
+use crate::filters::ColorMatrix;
+use crate::render::{DrawCmd, Scene};
+use crate::text::{TextKey, TextRenderer};
+
+/// Frames a cached texture may go unused before it is dropped
+const CACHE_MAX_IDLE_FRAMES: u64 = 300;
+
+/// Premultiplied ARGB8888 pixels (B, G, R, A byte order in memory)
+#[derive(Debug, Clone)]
+pub struct PixelBuffer {
+    pub width: i32,
+    pub height: i32,
+    pub pixels: Vec<u8>,
+}
+
+impl PixelBuffer {
+    /// Convert straight-alpha RGBA pixels
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Self {
+        let mut pixels = Vec::with_capacity(rgba.len());
+        for p in rgba.chunks_exact(4) {
+            let [r, g, b, a] = [p[0], p[1], p[2], p[3]];
+            let premultiply = |c: u8| (c as u16 * a as u16 / 255) as u8;
+            pixels.extend_from_slice(&[premultiply(b), premultiply(g), premultiply(r), a]);
+        }
+        Self {
+            width: width as i32,
+            height: height as i32,
+            pixels,
+        }
+    }
+
+    /// Convert premultiplied RGBA pixels (e.g. a tiny-skia pixmap)
+    pub fn from_premultiplied_rgba(width: u32, height: u32, rgba: &[u8]) -> Self {
+        let pixels = rgba
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], p[3]])
+            .collect();
+        Self {
+            width: width as i32,
+            height: height as i32,
+            pixels,
+        }
+    }
+}
+
+/// Shared, immutable image that scenes can reference cheaply
+pub type Image = Arc<PixelBuffer>;
+
+/// An image uploaded to the GPU
+pub struct CachedTexture<T> {
+    pub texture: T,
+    pub size: Size<i32, Buffer>,
+    last_used: u64,
+    /// Keeps image allocations (and therefore their keys) alive
+    _source: Option<Image>,
+}
+
+/// Per-renderer cache of uploaded textures. Text and images live in
+/// separate maps so lookups borrow the scene's keys instead of building
+/// owned ones every frame.
+pub struct TextureCache<T> {
+    texts: HashMap<TextKey, CachedTexture<T>>,
+    /// Keyed by the address of the image's allocation. The cache keeps the
+    /// Arc alive, so the address cannot be reused while the entry exists.
+    images: HashMap<usize, CachedTexture<T>>,
+    frame: u64,
+    /// Color filter the textures were uploaded with
+    matrix: ColorMatrix,
+}
+
+impl<T> TextureCache<T> {
+    pub fn new() -> Self {
+        Self {
+            texts: HashMap::new(),
+            images: HashMap::new(),
+            frame: 0,
+            matrix: ColorMatrix::IDENTITY,
+        }
+    }
+
+    /// Upload every texture the scene needs that isn't cached yet, through
+    /// the color filter `matrix`, and drop textures that have gone unused
+    pub fn prepare<R>(
+        &mut self,
+        renderer: &mut R,
+        text: &TextRenderer,
+        scene: &Scene,
+        matrix: ColorMatrix,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        R: SmithayRenderer<TextureId = T> + ImportMem,
+        R::Error: 'static,
+    {
+        self.frame += 1;
+        if matrix != self.matrix {
+            self.matrix = matrix;
+            self.texts.clear();
+            self.images.clear();
+        }
+
+        let frame = self.frame;
+        for cmd in scene.commands() {
+            match cmd {
+                DrawCmd::Text { key, .. } => {
+                    if let Some(entry) = self.texts.get_mut(key) {
+                        entry.last_used = frame;
+                        continue;
+                    }
+                    let Some(buffer) = text.rasterize(key) else {
+                        continue;
+                    };
+                    if let Some(cached) = upload(renderer, &buffer, frame, None, matrix)? {
+                        self.texts.insert(key.clone(), cached);
+                    }
+                }
+                DrawCmd::Image { image, .. } => {
+                    if let Some(entry) = self.images.get_mut(&image_key(image)) {
+                        entry.last_used = frame;
+                        continue;
+                    }
+                    if let Some(cached) = upload(renderer, image, frame, Some(image.clone()), matrix)? {
+                        self.images.insert(image_key(image), cached);
+                    }
+                }
+                DrawCmd::Rect { .. } => {}
+            }
+        }
+
+        let fresh = |entry: &CachedTexture<T>| frame - entry.last_used <= CACHE_MAX_IDLE_FRAMES;
+        self.texts.retain(|_, entry| fresh(entry));
+        self.images.retain(|_, entry| fresh(entry));
+        Ok(())
+    }
+
+    /// The color filter everything is drawn with this frame
+    pub fn matrix(&self) -> &ColorMatrix {
+        &self.matrix
+    }
+
+    /// Look up an uploaded string
+    pub fn text(&self, key: &TextKey) -> Option<&CachedTexture<T>> {
+        self.texts.get(key)
+    }
+
+    /// Look up an uploaded image
+    pub fn image(&self, image: &Image) -> Option<&CachedTexture<T>> {
+        self.images.get(&image_key(image))
+    }
+}
+
+/// Upload a buffer through a color filter; None for empty buffers
+fn upload<R>(
+    renderer: &mut R,
+    buffer: &PixelBuffer,
+    frame: u64,
+    source: Option<Image>,
+    matrix: ColorMatrix,
+) -> Result<Option<CachedTexture<R::TextureId>>, R::Error>
+where
+    R: SmithayRenderer + ImportMem,
+{
+    if buffer.width <= 0 || buffer.height <= 0 {
+        return Ok(None);
+    }
+    let size = Size::from((buffer.width, buffer.height));
+    let texture = if matrix.is_identity() {
+        renderer.import_memory(&buffer.pixels, Fourcc::Argb8888, size, false)?
+    } else {
+        let mut pixels = buffer.pixels.clone();
+        matrix.apply_premultiplied_bgra(&mut pixels);
+        renderer.import_memory(&pixels, Fourcc::Argb8888, size, false)?
+    };
+    Ok(Some(CachedTexture {
+        texture,
+        size,
+        last_used: frame,
+        _source: source,
+    }))
+}
+
+/// Identity of an image allocation
+fn image_key(image: &Image) -> usize {
+    Arc::as_ptr(image) as usize
+}
@@ -0,0 +1,131 @@
+// =============================================================================
+// heyDM — UI Theme & Metrics
+//
+// Font and size of the UI heyDM draws itself (panel, launcher, overlays):
+//
+//     [theme]
+//     font = Inter              # family name or path to a .ttf/.otf file
+//     font_size = 13            # base text size in pixels at scale 1
+//     scale = 1.0               # extra factor for the UI only
+//
+// The UI follows the output's `scale` (see output.rs) on its own, so a 4K
+// display at `scale = 2` gets a 2x panel without touching [theme]; the
+// theme scale multiplies on top.
+//
+// Layout code works in pixels at scale 1 and passes every length through
+// UiMetrics::px, every text size through UiMetrics::font, so margins, item
+// sizes and text grow together and hit-testing matches what is drawn.
+// =============================================================================
+
+/// Panel bar height at scale 1
+const PANEL_HEIGHT: i32 = 44;
+/// Gap between the floating panel and the screen edges at scale 1
+const PANEL_MARGIN: i32 = 10;
+/// Window border width at scale 1
+const BORDER_WIDTH: i32 = 3;
+/// Text size the layout's font sizes are relative to
+const DEFAULT_FONT_SIZE: u32 = 13;
+
+pub const MIN_SCALE: f64 = 0.5;
+pub const MAX_SCALE: f64 = 4.0;
+
+/// `[theme]` settings from the config file
+#[derive(Debug, Clone)]
+pub struct ThemeConfig {
+    /// Font family or file; None = first of the built-in fallbacks
+    pub font: Option<String>,
+    pub font_size: u32,
+    pub scale: f64,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            font: None,
+            font_size: DEFAULT_FONT_SIZE,
+            scale: 1.0,
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Set a key from the config file. Values are expected to have passed
+    /// schema validation; anything else is rejected.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "font" => self.font = Some(value.to_string()),
+            "font_size" => {
+                self.font_size = value
+                    .parse()
+                    .map_err(|_| format!("invalid font_size '{value}'"))?
+            }
+            "scale" => {
+                let scale: f64 = value
+                    .parse()
+                    .map_err(|_| format!("invalid scale '{value}'"))?;
+                self.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+            }
+            _ => return Err(format!("unknown theme setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// Pixel sizes of compositor-drawn UI at the configured scale (`[theme]`
+/// scale times the output's scale)
+#[derive(Debug, Clone, Copy)]
+pub struct UiMetrics {
+    scale: f64,
+    font_size: u32,
+}
+
+impl Default for UiMetrics {
+    fn default() -> Self {
+        Self::new(&ThemeConfig::default())
+    }
+}
+
+impl UiMetrics {
+    pub fn new(theme: &ThemeConfig) -> Self {
+        Self {
+            scale: theme.scale,
+            font_size: theme.font_size.max(1),
+        }
+    }
+
+    /// Metrics for an output with its own scale, on top of the theme's
+    pub fn with_output_scale(theme: &ThemeConfig, output_scale: f64) -> Self {
+        Self {
+            scale: theme.scale * output_scale,
+            ..Self::new(theme)
+        }
+    }
+
+    /// A length given at scale 1
+    pub fn px(&self, value: i32) -> i32 {
+        (value as f64 * self.scale).round() as i32
+    }
+
+    /// A text size given relative to the default 13px UI font
+    pub fn font(&self, size: u32) -> u32 {
+        let size = size as f64 * self.font_size as f64 / DEFAULT_FONT_SIZE as f64;
+        ((size * self.scale).round() as u32).max(1)
+    }
+
+    pub fn panel_height(&self) -> i32 {
+        self.px(PANEL_HEIGHT)
+    }
+
+    pub fn panel_margin(&self) -> i32 {
+        self.px(PANEL_MARGIN)
+    }
+
+    /// Bottom edge of the panel; windows are placed below it
+    pub fn panel_bottom(&self) -> i32 {
+        self.panel_margin() + self.panel_height()
+    }
+
+    pub fn border_width(&self) -> i32 {
+        self.px(BORDER_WIDTH).max(1)
+    }
+}
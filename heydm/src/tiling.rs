@@ -0,0 +1,247 @@
+// =============================================================================
+// heyDM — Automatic Tiling
+//
+// Workspaces float by default. Super+T turns automatic tiling on or off for
+// the active workspace; while on, every window that is not fullscreen,
+// maximized, half-tiled or dragged out by the user is arranged by one of:
+//
+//   master_stack — the oldest window fills the master area on the left
+//                  (master_ratio of the width), the rest stack on the right
+//   dwindle      — each window takes part of the remaining space and the
+//                  next one splits what is left, along its longer side
+//
+// Super+Shift+T switches the workspace's layout, Super+Shift+H /
+// Super+Shift+L shrink and grow the master area (Super+L always locks).
+// Defaults come from the config file:
+//
+//     [tiling]
+//     enabled = false          # tile every workspace from the start
+//     layout = master_stack    # master_stack | dwindle
+//     master_ratio = 0.55      # 0.1 .. 0.9
+//     gap = 8                  # pixels around and between windows
+// =============================================================================
+
+use smithay::utils::{Logical, Point, Rectangle, Size};
+
+/// Smallest and largest share of the work area the master area may take
+pub const MIN_MASTER_RATIO: f64 = 0.1;
+pub const MAX_MASTER_RATIO: f64 = 0.9;
+
+/// How much Super+Shift+H / Super+Shift+L change the master ratio
+pub const MASTER_RATIO_STEP: f64 = 0.05;
+
+/// Accepted `layout` values
+pub const LAYOUTS: &[&str] = &["master_stack", "dwindle"];
+
+/// An automatic tiling algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    MasterStack,
+    Dwindle,
+}
+
+impl Layout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "master_stack" => Some(Self::MasterStack),
+            "dwindle" => Some(Self::Dwindle),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MasterStack => "master_stack",
+            Self::Dwindle => "dwindle",
+        }
+    }
+
+    /// The other layout (Super+Shift+T)
+    pub fn next(self) -> Self {
+        match self {
+            Self::MasterStack => Self::Dwindle,
+            Self::Dwindle => Self::MasterStack,
+        }
+    }
+}
+
+/// `[tiling]` settings from the config file
+#[derive(Debug, Clone)]
+pub struct TilingConfig {
+    /// Tile every workspace at startup
+    pub enabled: bool,
+    pub layout: Layout,
+    pub master_ratio: f64,
+    pub gap: i32,
+}
+
+impl Default for TilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            layout: Layout::MasterStack,
+            master_ratio: 0.55,
+            gap: 8,
+        }
+    }
+}
+
+impl TilingConfig {
+    /// Set a key from the config file. Values are expected to have passed
+    /// schema validation; anything else is rejected.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "enabled" => {
+                self.enabled = match value {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("expected true or false, got '{value}'")),
+                }
+            }
+            "layout" => {
+                self.layout =
+                    Layout::parse(value).ok_or_else(|| format!("invalid layout '{value}'"))?
+            }
+            "master_ratio" => {
+                let ratio: f64 = value
+                    .parse()
+                    .map_err(|_| format!("invalid master_ratio '{value}'"))?;
+                self.master_ratio = ratio.clamp(MIN_MASTER_RATIO, MAX_MASTER_RATIO);
+            }
+            "gap" => {
+                let gap: i32 = value.parse().map_err(|_| format!("invalid gap '{value}'"))?;
+                self.gap = gap.max(0);
+            }
+            _ => return Err(format!("unknown tiling setting '{key}'")),
+        }
+        Ok(())
+    }
+
+    /// Starting state for a workspace that gets tiled
+    pub fn workspace_layout(&self) -> WorkspaceLayout {
+        WorkspaceLayout {
+            layout: self.layout,
+            master_ratio: self.master_ratio,
+        }
+    }
+}
+
+/// Tiling state of one workspace
+#[derive(Debug, Clone, Copy)]
+pub struct WorkspaceLayout {
+    pub layout: Layout,
+    pub master_ratio: f64,
+}
+
+impl WorkspaceLayout {
+    /// Grow (positive) or shrink (negative) the master area
+    pub fn adjust_ratio(&mut self, delta: f64) {
+        self.master_ratio = (self.master_ratio + delta).clamp(MIN_MASTER_RATIO, MAX_MASTER_RATIO);
+    }
+
+    /// Geometry for `count` windows inside `area`, in tiling order
+    pub fn arrange(
+        &self,
+        area: Rectangle<i32, Logical>,
+        count: usize,
+        gap: i32,
+    ) -> Vec<Rectangle<i32, Logical>> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let area = Rectangle::new(
+            Point::from((area.loc.x + gap, area.loc.y + gap)),
+            Size::from(((area.size.w - 2 * gap).max(1), (area.size.h - 2 * gap).max(1))),
+        );
+        match self.layout {
+            Layout::MasterStack => master_stack(area, count, self.master_ratio, gap),
+            Layout::Dwindle => dwindle(area, count, self.master_ratio, gap),
+        }
+    }
+}
+
+/// Master on the left, the other windows stacked top to bottom on the right
+fn master_stack(
+    area: Rectangle<i32, Logical>,
+    count: usize,
+    ratio: f64,
+    gap: i32,
+) -> Vec<Rectangle<i32, Logical>> {
+    if count == 1 {
+        return vec![area];
+    }
+
+    let (master, stack) = split(area, ratio, gap, true);
+    let mut rects = vec![master];
+    let stacked = (count - 1) as i32;
+    let height = ((stack.size.h - gap * (stacked - 1)) / stacked).max(1);
+    for i in 0..stacked {
+        let y = stack.loc.y + i * (height + gap);
+        // The last window absorbs the rounding remainder
+        let h = if i == stacked - 1 {
+            stack.loc.y + stack.size.h - y
+        } else {
+            height
+        };
+        rects.push(Rectangle::new(
+            Point::from((stack.loc.x, y)),
+            Size::from((stack.size.w, h.max(1))),
+        ));
+    }
+    rects
+}
+
+/// Each window splits the space left over by the previous one, along the
+/// longer side; only the first split uses the master ratio
+fn dwindle(
+    area: Rectangle<i32, Logical>,
+    count: usize,
+    ratio: f64,
+    gap: i32,
+) -> Vec<Rectangle<i32, Logical>> {
+    let mut rects = Vec::with_capacity(count);
+    let mut remaining = area;
+    for i in 0..count {
+        if i == count - 1 {
+            rects.push(remaining);
+            break;
+        }
+        let ratio = if i == 0 { ratio } else { 0.5 };
+        let horizontal = remaining.size.w >= remaining.size.h;
+        let (first, rest) = split(remaining, ratio, gap, horizontal);
+        rects.push(first);
+        remaining = rest;
+    }
+    rects
+}
+
+/// Split a rectangle side by side (`horizontal`) or top and bottom, with
+/// the first part taking `ratio` of the space
+fn split(
+    area: Rectangle<i32, Logical>,
+    ratio: f64,
+    gap: i32,
+    horizontal: bool,
+) -> (Rectangle<i32, Logical>, Rectangle<i32, Logical>) {
+    if horizontal {
+        let first_w = (((area.size.w - gap) as f64 * ratio) as i32).max(1);
+        let rest_w = (area.size.w - gap - first_w).max(1);
+        (
+            Rectangle::new(area.loc, Size::from((first_w, area.size.h))),
+            Rectangle::new(
+                Point::from((area.loc.x + first_w + gap, area.loc.y)),
+                Size::from((rest_w, area.size.h)),
+            ),
+        )
+    } else {
+        let first_h = (((area.size.h - gap) as f64 * ratio) as i32).max(1);
+        let rest_h = (area.size.h - gap - first_h).max(1);
+        (
+            Rectangle::new(area.loc, Size::from((area.size.w, first_h))),
+            Rectangle::new(
+                Point::from((area.loc.x, area.loc.y + first_h + gap)),
+                Size::from((area.size.w, rest_h)),
+            ),
+        )
+    }
+}
@@ -0,0 +1,63 @@
+// =============================================================================
+// heyDM — Hover Tooltips
+//
+// Compositor-drawn UI (panel modules, launcher items, overlays) implements
+// TooltipSource to describe what lives under the pointer. Tooltips tracks
+// the hovered text and only reports it once the pointer has rested on the
+// same element for TOOLTIP_DELAY; the renderer then draws it near the cursor.
+// =============================================================================
+
+use std::time::{Duration, Instant};
+
+use smithay::utils::{Physical, Size};
+
+/// How long the pointer must rest before a tooltip appears
+pub const TOOLTIP_DELAY: Duration = Duration::from_millis(600);
+
+/// UI elements that can describe themselves on hover
+pub trait TooltipSource {
+    /// Tooltip text for the element under `pos`, if any
+    fn tooltip_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<String>;
+}
+
+/// Hover tracking for the tooltip currently under the pointer
+#[derive(Debug, Default)]
+pub struct Tooltips {
+    /// Text of the hovered element and when the hover started
+    hovered: Option<(String, Instant)>,
+    /// Set after a click until the pointer moves to a different element
+    dismissed: bool,
+}
+
+impl Tooltips {
+    /// Report the tooltip text under the pointer (None when over nothing)
+    pub fn hover(&mut self, text: Option<String>) {
+        let same = match (&self.hovered, &text) {
+            (Some((current, _)), Some(new)) => current == new,
+            (None, None) => true,
+            _ => false,
+        };
+        if same {
+            return;
+        }
+
+        self.dismissed = false;
+        self.hovered = text.map(|t| (t, Instant::now()));
+    }
+
+    /// Hide the tooltip until the pointer reaches another element
+    pub fn dismiss(&mut self) {
+        self.dismissed = true;
+    }
+
+    /// Text to draw this frame, once the hover delay has elapsed
+    pub fn visible_text(&self) -> Option<&str> {
+        if self.dismissed {
+            return None;
+        }
+        self.hovered
+            .as_ref()
+            .filter(|(_, since)| since.elapsed() >= TOOLTIP_DELAY)
+            .map(|(text, _)| text.as_str())
+    }
+}
@@ -0,0 +1,204 @@
+// =============================================================================
+// heyDM — Toplevel surfaces
+//
+// What the window manager needs from a client's window: its title and app
+// id, size hints, the size it committed, configures and close requests.
+// WindowManager (window.rs) only talks to windows through the Toplevel
+// trait, so its layout logic (tiling, focus, grabs) runs just as well on
+// plain fake windows as on xdg toplevels. The smithay implementation below
+// is the only one the compositor uses.
+//
+// Sizes crossing this boundary are in the client's logical units; the window
+// manager converts them to output pixels (see window.rs).
+// =============================================================================
+
+use std::fmt::Debug;
+
+use smithay::backend::renderer::buffer_dimensions;
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Logical, Size};
+use smithay::wayland::compositor::{with_states, BufferAssignment, SurfaceAttributes};
+use smithay::wayland::shell::xdg::{SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceData};
+use smithay::wayland::viewporter::ViewportCachedState;
+
+/// A client window as seen by the window manager
+pub trait Toplevel: Clone + Debug + PartialEq {
+    /// Window title and app id as set by the client
+    fn title_and_app_id(&self) -> (Option<String>, Option<String>);
+
+    /// The client's minimum and maximum size (0 = unconstrained)
+    fn size_hints(&self) -> (Size<i32, Logical>, Size<i32, Logical>);
+
+    /// Size of the last commit (None before the first sized buffer)
+    fn committed_size(&self) -> Option<Size<i32, Logical>>;
+
+    /// Send a configure with a size and the xdg_toplevel states to set or
+    /// unset
+    fn configure(&self, size: Size<i32, Logical>, states: &[(xdg_toplevel::State, bool)]);
+
+    /// Ask the client to close the window
+    fn close(&self);
+
+    /// The Wayland surface behind the window, if there is one
+    fn surface(&self) -> Option<&WlSurface>;
+}
+
+impl Toplevel for ToplevelSurface {
+    fn title_and_app_id(&self) -> (Option<String>, Option<String>) {
+        with_states(self.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .map(|data| {
+                    let data = data.lock().unwrap();
+                    (data.title.clone(), data.app_id.clone())
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    fn size_hints(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        with_states(self.wl_surface(), |states| {
+            let mut cached = states.cached_state.get::<SurfaceCachedState>();
+            let current = cached.current();
+            (current.min_size, current.max_size)
+        })
+    }
+
+    /// The xdg window geometry if set, otherwise the viewport destination
+    /// (wp_viewporter) or the attached buffer size
+    fn committed_size(&self) -> Option<Size<i32, Logical>> {
+        with_states(self.wl_surface(), |states| {
+            let geometry = states
+                .cached_state
+                .get::<SurfaceCachedState>()
+                .current()
+                .geometry;
+            if let Some(geometry) = geometry {
+                return Some(geometry.size);
+            }
+            let viewport = states.cached_state.get::<ViewportCachedState>().current().size();
+            if let Some(size) = viewport {
+                return Some(size);
+            }
+
+            let mut attributes = states.cached_state.get::<SurfaceAttributes>();
+            let attributes = attributes.current();
+            let scale = attributes.buffer_scale;
+            let transform = attributes.buffer_transform.into();
+            match &attributes.buffer {
+                Some(BufferAssignment::NewBuffer(buffer)) => {
+                    buffer_dimensions(buffer).map(|size| size.to_logical(scale, transform))
+                }
+                _ => None,
+            }
+        })
+        .filter(|size| size.w > 0 && size.h > 0)
+    }
+
+    fn configure(&self, size: Size<i32, Logical>, states: &[(xdg_toplevel::State, bool)]) {
+        self.with_pending_state(|state| {
+            state.size = Some(size);
+            for &(flag, enabled) in states {
+                if enabled {
+                    state.states.set(flag);
+                } else {
+                    state.states.unset(flag);
+                }
+            }
+        });
+        self.send_pending_configure();
+    }
+
+    fn close(&self) {
+        self.send_close();
+    }
+
+    fn surface(&self) -> Option<&WlSurface> {
+        Some(self.wl_surface())
+    }
+}
+
+/// Windows without a client, for driving the window manager in tests
+#[cfg(test)]
+pub mod fake {
+    use std::cell::{RefCell, RefMut};
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// A window that records what it was sent; clones share the record
+    #[derive(Debug, Clone, Default)]
+    pub struct FakeToplevel(Rc<RefCell<FakeState>>);
+
+    #[derive(Debug, Default)]
+    pub struct FakeState {
+        pub app_id: Option<String>,
+        pub min_size: Size<i32, Logical>,
+        pub max_size: Size<i32, Logical>,
+        /// Size and states of the last configure
+        pub configured: Option<(Size<i32, Logical>, Vec<(xdg_toplevel::State, bool)>)>,
+        pub closed: bool,
+    }
+
+    impl FakeToplevel {
+        pub fn new(app_id: &str) -> Self {
+            let fake = Self::default();
+            fake.state().app_id = Some(app_id.to_string());
+            fake
+        }
+
+        pub fn state(&self) -> RefMut<'_, FakeState> {
+            self.0.borrow_mut()
+        }
+
+        /// Size of the last configure
+        pub fn configured_size(&self) -> Option<Size<i32, Logical>> {
+            self.0.borrow().configured.as_ref().map(|(size, _)| *size)
+        }
+
+        /// Whether the last configure set `flag`
+        pub fn has_state(&self, flag: xdg_toplevel::State) -> bool {
+            self.0
+                .borrow()
+                .configured
+                .as_ref()
+                .is_some_and(|(_, states)| states.contains(&(flag, true)))
+        }
+    }
+
+    impl PartialEq for FakeToplevel {
+        fn eq(&self, other: &Self) -> bool {
+            Rc::ptr_eq(&self.0, &other.0)
+        }
+    }
+
+    impl Toplevel for FakeToplevel {
+        fn title_and_app_id(&self) -> (Option<String>, Option<String>) {
+            (None, self.0.borrow().app_id.clone())
+        }
+
+        fn size_hints(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+            let state = self.0.borrow();
+            (state.min_size, state.max_size)
+        }
+
+        /// Clients take the size they are configured with
+        fn committed_size(&self) -> Option<Size<i32, Logical>> {
+            self.configured_size()
+        }
+
+        fn configure(&self, size: Size<i32, Logical>, states: &[(xdg_toplevel::State, bool)]) {
+            self.state().configured = Some((size, states.to_vec()));
+        }
+
+        fn close(&self) {
+            self.state().closed = true;
+        }
+
+        fn surface(&self) -> Option<&WlSurface> {
+            None
+        }
+    }
+}
@@ -0,0 +1,723 @@
+// =============================================================================
+// heyDM — System Tray
+//
+// Shows StatusNotifierItems (nm-applet, Discord, Steam, ...) as icons on the
+// right side of the island panel. heyDM is both the StatusNotifierWatcher —
+// the registry apps announce their items to — and the only host:
+//
+//   - left click activates the item (or opens its menu for menu-only items)
+//   - middle click is the secondary activation
+//   - right click opens the item's DBusMenu (com.canonical.dbusmenu), drawn
+//     by heyDM below the icon; items without one get ContextMenu instead
+//
+// Only the top level of a menu is shown; entries that open a submenu are
+// drawn disabled. At most MAX_TRAY_ICONS icons fit into the panel.
+//
+// All D-Bus traffic runs on the "heydm-tray" thread, with two helper
+// threads that turn bus signals (items changing their icon, apps exiting)
+// into work for it. Items and menus reach the compositor through a calloop
+// channel; clicks go back through a std channel.
+// =============================================================================
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use image::imageops::FilterType;
+use smithay::utils::{Physical, Rectangle, Size};
+use tracing::{debug, info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::object_server::SignalContext;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::render::tray_icon_rect;
+use crate::texture::{Image, PixelBuffer};
+use crate::theme::UiMetrics;
+use crate::tooltip::TooltipSource;
+
+/// Icons that fit into the panel's tray area
+pub const MAX_TRAY_ICONS: usize = 5;
+
+/// Edge length of tray icons at scale 1
+pub const TRAY_ICON_SIZE: i32 = 20;
+
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+/// Object path of items that register with a bus name only
+const DEFAULT_ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// Icon theme contexts tray icons come from
+const ICON_CONTEXTS: &[&str] = &["status", "apps", "devices", "panel", "actions"];
+
+/// An item as shown in the panel
+#[derive(Debug, Clone)]
+pub struct TrayItem {
+    /// "<unique bus name><object path>"
+    pub key: String,
+    pub title: String,
+    pub icon: Option<Image>,
+    pub has_menu: bool,
+    /// The item only offers a menu; left click opens it too
+    pub item_is_menu: bool,
+}
+
+/// One top-level entry of an item's menu
+#[derive(Debug, Clone)]
+pub struct MenuEntry {
+    pub id: i32,
+    pub label: String,
+    pub enabled: bool,
+    pub separator: bool,
+    /// Some for checkmark and radio entries
+    pub checked: Option<bool>,
+}
+
+/// From the tray thread to the compositor
+#[derive(Debug)]
+pub enum TrayUpdate {
+    Item(TrayItem),
+    Removed(String),
+    Menu { key: String, entries: Vec<MenuEntry> },
+}
+
+/// Work for the tray thread
+#[derive(Debug)]
+enum TrayCommand {
+    /// RegisterStatusNotifierItem from `sender` with this service argument
+    Register { sender: String, service: String },
+    /// An item emitted NewIcon, NewTitle, ...
+    Changed { sender: String },
+    /// A bus name lost its owner
+    Vanished { name: String },
+    Activate { key: String, x: i32, y: i32 },
+    SecondaryActivate { key: String, x: i32, y: i32 },
+    ContextMenu { key: String, x: i32, y: i32 },
+    OpenMenu { key: String },
+    MenuClicked { key: String, id: i32 },
+}
+
+/// The open menu: whose it is, its entries and the icon it hangs from
+#[derive(Debug)]
+pub struct OpenMenu {
+    pub key: String,
+    pub entries: Vec<MenuEntry>,
+    pub icon: usize,
+}
+
+/// Tray icons and the open menu, on the compositor side
+pub struct Tray {
+    items: Vec<TrayItem>,
+    menu: Option<OpenMenu>,
+    /// Icon the menu was requested for, until it arrives
+    menu_requested: Option<(String, usize)>,
+    commands: Sender<TrayCommand>,
+    ui: UiMetrics,
+}
+
+#[allow(dead_code)]
+impl Tray {
+    /// Take over the StatusNotifierWatcher name and start hosting items
+    pub fn start(
+        updates: calloop::channel::Sender<TrayUpdate>,
+        icon_theme: Option<String>,
+        ui: UiMetrics,
+    ) -> Self {
+        let (commands, receiver) = mpsc::channel::<TrayCommand>();
+        let worker_commands = commands.clone();
+        let icon_size = ui.px(TRAY_ICON_SIZE) as u32;
+        let spawned = std::thread::Builder::new()
+            .name("heydm-tray".to_string())
+            .spawn(move || {
+                let host = match Host::connect(worker_commands, updates, icon_theme, icon_size) {
+                    Ok(host) => host,
+                    Err(e) => {
+                        warn!("System tray unavailable: {e}");
+                        return;
+                    }
+                };
+                info!("System tray serving as {WATCHER_NAME}");
+                host.run(receiver);
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start tray thread: {e}");
+        }
+        Self {
+            items: Vec::new(),
+            menu: None,
+            menu_requested: None,
+            commands,
+            ui,
+        }
+    }
+
+    /// An empty tray without the watcher (greeter mode)
+    pub fn disabled(ui: UiMetrics) -> Self {
+        Self {
+            items: Vec::new(),
+            menu: None,
+            menu_requested: None,
+            commands: mpsc::channel().0,
+            ui,
+        }
+    }
+
+    /// Take new UI metrics (scale or font size changed); icons
+    /// already loaded keep their size
+    pub fn set_ui(&mut self, ui: UiMetrics) {
+        self.ui = ui;
+    }
+
+    pub fn apply(&mut self, update: TrayUpdate) {
+        match update {
+            TrayUpdate::Item(item) => match self.items.iter_mut().find(|i| i.key == item.key) {
+                Some(existing) => *existing = item,
+                None => self.items.push(item),
+            },
+            TrayUpdate::Removed(key) => {
+                self.items.retain(|item| item.key != key);
+                if self.menu.as_ref().is_some_and(|menu| menu.key == key) {
+                    self.menu = None;
+                }
+            }
+            TrayUpdate::Menu { key, entries } => {
+                let Some((requested, icon)) = self.menu_requested.take() else {
+                    return;
+                };
+                if requested == key && !entries.is_empty() {
+                    self.menu = Some(OpenMenu { key, entries, icon });
+                }
+            }
+        }
+    }
+
+    /// Items with an icon slot in the panel
+    pub fn items(&self) -> &[TrayItem] {
+        &self.items[..self.items.len().min(MAX_TRAY_ICONS)]
+    }
+
+    pub fn open_menu(&self) -> Option<&OpenMenu> {
+        self.menu.as_ref()
+    }
+
+    /// Where the open menu is drawn: below its icon, inside the output
+    pub fn menu_rect(&self, output_size: Size<i32, Physical>) -> Option<Rectangle<i32, Physical>> {
+        self.menu.as_ref().map(|menu| self.menu_area(menu, output_size))
+    }
+
+    fn menu_area(&self, menu: &OpenMenu, output_size: Size<i32, Physical>) -> Rectangle<i32, Physical> {
+        let icon = tray_icon_rect(menu.icon, output_size, &self.ui);
+        let w = self.ui.px(220);
+        let h = self.menu_row_height() * menu.entries.len() as i32 + self.ui.px(8);
+        let x = (icon.loc.x + icon.size.w - w).clamp(0, (output_size.w - w).max(0));
+        Rectangle::new(
+            (x, self.ui.panel_bottom() + self.ui.px(4)).into(),
+            (w, h).into(),
+        )
+    }
+
+    pub fn menu_row_height(&self) -> i32 {
+        self.ui.px(28)
+    }
+
+    /// Handle a button press; returns true if it was consumed. `button` is
+    /// the evdev code (BTN_LEFT, BTN_RIGHT, BTN_MIDDLE).
+    pub fn handle_press(&mut self, pos: (f64, f64), button: u32, output_size: Size<i32, Physical>) -> bool {
+        let point = (pos.0 as i32, pos.1 as i32);
+
+        // An open menu takes the next click, wherever it lands
+        if let Some(menu) = self.menu.take() {
+            let area = self.menu_area(&menu, output_size);
+            if area.contains(point) {
+                let row = (point.1 - area.loc.y - self.ui.px(4)) / self.menu_row_height();
+                let entry = usize::try_from(row).ok().and_then(|row| menu.entries.get(row));
+                match entry {
+                    Some(entry) if entry.enabled && !entry.separator => {
+                        self.send(TrayCommand::MenuClicked {
+                            key: menu.key,
+                            id: entry.id,
+                        });
+                    }
+                    // Keep the menu open on separators and disabled entries
+                    Some(_) => self.menu = Some(menu),
+                    None => {}
+                }
+                return true;
+            }
+            // A click on the menu's own icon just closes it
+            if tray_icon_rect(menu.icon, output_size, &self.ui).contains(point) {
+                return true;
+            }
+        }
+
+        let Some(index) = (0..self.items().len()).find(|i| tray_icon_rect(*i, output_size, &self.ui).contains(point)) else {
+            return false;
+        };
+        let item = &self.items[index];
+        let key = item.key.clone();
+        let (x, y) = (point.0, self.ui.panel_bottom());
+        let wants_menu = button == BTN_RIGHT || item.item_is_menu;
+        if wants_menu && item.has_menu {
+            self.menu_requested = Some((key.clone(), index));
+            self.send(TrayCommand::OpenMenu { key });
+        } else if button == BTN_RIGHT {
+            self.send(TrayCommand::ContextMenu { key, x, y });
+        } else if button == BTN_MIDDLE {
+            self.send(TrayCommand::SecondaryActivate { key, x, y });
+        } else {
+            self.send(TrayCommand::Activate { key, x, y });
+        }
+        true
+    }
+
+    /// Close the menu without choosing anything
+    pub fn close_menu(&mut self) {
+        self.menu = None;
+        self.menu_requested = None;
+    }
+
+    fn send(&self, command: TrayCommand) {
+        let _ = self.commands.send(command);
+    }
+}
+
+impl TooltipSource for Tray {
+    /// Title of the item under the pointer
+    fn tooltip_at(&self, pos: (f64, f64), output_size: Size<i32, Physical>) -> Option<String> {
+        let point = (pos.0 as i32, pos.1 as i32);
+        self.items()
+            .iter()
+            .enumerate()
+            .find(|(i, _)| tray_icon_rect(*i, output_size, &self.ui).contains(point))
+            .map(|(_, item)| item.title.clone())
+            .filter(|title| !title.is_empty())
+    }
+}
+
+// ---- D-Bus side (tray thread) ----
+
+/// org.kde.StatusNotifierWatcher, served on the tray thread's connection
+struct Watcher {
+    commands: Mutex<Sender<TrayCommand>>,
+    /// Registered items as "<bus name><path>", for the property
+    items: Arc<Mutex<Vec<String>>>,
+}
+
+#[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    fn register_status_notifier_item(&self, service: String, #[zbus(header)] header: zbus::message::Header<'_>) {
+        let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+        let _ = self
+            .commands
+            .lock()
+            .unwrap()
+            .send(TrayCommand::Register { sender, service });
+    }
+
+    /// heyDM is the only host; other hosts are accepted but not tracked
+    fn register_status_notifier_host(&self, _service: String) {}
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.lock().unwrap().clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(ctxt: &SignalContext<'_>, service: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(ctxt: &SignalContext<'_>, service: &str) -> zbus::Result<()>;
+}
+
+/// Where a registered item lives
+#[derive(Debug, Clone)]
+struct ItemRef {
+    /// Unique name of the owning connection
+    owner: String,
+    /// Name the item registered with (unique or well-known)
+    name: String,
+    path: String,
+    menu: Option<OwnedObjectPath>,
+}
+
+impl ItemRef {
+    fn key(&self) -> String {
+        format!("{}{}", self.owner, self.path)
+    }
+}
+
+/// State of the tray thread
+struct Host {
+    connection: Connection,
+    updates: calloop::channel::Sender<TrayUpdate>,
+    items: HashMap<String, ItemRef>,
+    registered: Arc<Mutex<Vec<String>>>,
+    icon_theme: Option<String>,
+    icon_size: u32,
+}
+
+impl Host {
+    fn connect(
+        commands: Sender<TrayCommand>,
+        updates: calloop::channel::Sender<TrayUpdate>,
+        icon_theme: Option<String>,
+        icon_size: u32,
+    ) -> zbus::Result<Self> {
+        let registered = Arc::new(Mutex::new(Vec::new()));
+        let watcher = Watcher {
+            commands: Mutex::new(commands.clone()),
+            items: registered.clone(),
+        };
+        let connection = zbus::blocking::connection::Builder::session()?
+            .name(WATCHER_NAME)?
+            .serve_at(WATCHER_PATH, watcher)?
+            .build()?;
+        watch_signals(&connection, commands);
+        Ok(Self {
+            connection,
+            updates,
+            items: HashMap::new(),
+            registered,
+            icon_theme,
+            icon_size,
+        })
+    }
+
+    fn run(mut self, commands: Receiver<TrayCommand>) {
+        for command in commands {
+            if let Err(e) = self.handle(command) {
+                debug!("Tray: {e}");
+            }
+        }
+    }
+
+    fn handle(&mut self, command: TrayCommand) -> zbus::Result<()> {
+        match command {
+            TrayCommand::Register { sender, service } => {
+                // Ayatana items pass their object path, others a bus name
+                let (name, path) = if service.starts_with('/') {
+                    (sender.clone(), service)
+                } else {
+                    (service, DEFAULT_ITEM_PATH.to_string())
+                };
+                let owner = if name.starts_with(':') {
+                    name.clone()
+                } else {
+                    zbus::blocking::fdo::DBusProxy::new(&self.connection)?
+                        .get_name_owner(name.as_str().try_into()?)?
+                        .to_string()
+                };
+                let item = ItemRef {
+                    owner,
+                    name,
+                    path,
+                    menu: None,
+                };
+                let key = item.key();
+                info!("Tray item registered: {key}");
+                self.items.insert(key.clone(), item);
+                self.registered.lock().unwrap().push(key.clone());
+                self.emit_registered(&key, true);
+                self.refresh(&key)?;
+            }
+            TrayCommand::Changed { sender } => {
+                let keys: Vec<String> = self
+                    .items
+                    .values()
+                    .filter(|item| item.owner == sender)
+                    .map(ItemRef::key)
+                    .collect();
+                for key in keys {
+                    self.refresh(&key)?;
+                }
+            }
+            TrayCommand::Vanished { name } => {
+                let gone: Vec<String> = self
+                    .items
+                    .values()
+                    .filter(|item| item.owner == name || item.name == name)
+                    .map(ItemRef::key)
+                    .collect();
+                for key in gone {
+                    info!("Tray item gone: {key}");
+                    self.items.remove(&key);
+                    self.registered.lock().unwrap().retain(|k| *k != key);
+                    self.emit_registered(&key, false);
+                    let _ = self.updates.send(TrayUpdate::Removed(key));
+                }
+            }
+            TrayCommand::Activate { key, x, y } => {
+                self.item_proxy(&key)?.call::<_, _, ()>("Activate", &(x, y))?;
+            }
+            TrayCommand::SecondaryActivate { key, x, y } => {
+                self.item_proxy(&key)?.call::<_, _, ()>("SecondaryActivate", &(x, y))?;
+            }
+            TrayCommand::ContextMenu { key, x, y } => {
+                self.item_proxy(&key)?.call::<_, _, ()>("ContextMenu", &(x, y))?;
+            }
+            TrayCommand::OpenMenu { key } => {
+                let entries = self.menu_entries(&key)?;
+                let _ = self.updates.send(TrayUpdate::Menu { key, entries });
+            }
+            TrayCommand::MenuClicked { key, id } => {
+                let timestamp = 0u32;
+                self.menu_proxy(&key)?.call::<_, _, ()>(
+                    "Event",
+                    &(id, "clicked", Value::from(0i32), timestamp),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read an item's properties and send it to the compositor
+    fn refresh(&mut self, key: &str) -> zbus::Result<()> {
+        let proxy = self.item_proxy(key)?;
+        let status: String = proxy.get_property("Status").unwrap_or_default();
+        if status == "Passive" {
+            let _ = self.updates.send(TrayUpdate::Removed(key.to_string()));
+            return Ok(());
+        }
+
+        let id: String = proxy.get_property("Id").unwrap_or_default();
+        let title = proxy
+            .get_property::<String>("Title")
+            .ok()
+            .filter(|title| !title.is_empty())
+            .unwrap_or(id);
+        let icon = self.icon(&proxy, status == "NeedsAttention");
+        let menu: Option<OwnedObjectPath> = proxy
+            .get_property("Menu")
+            .ok()
+            .filter(|path: &OwnedObjectPath| path.as_str() != "/");
+        let item_is_menu: bool = proxy.get_property("ItemIsMenu").unwrap_or(false);
+
+        if let Some(item) = self.items.get_mut(key) {
+            item.menu = menu.clone();
+        }
+        let _ = self.updates.send(TrayUpdate::Item(TrayItem {
+            key: key.to_string(),
+            title,
+            icon,
+            has_menu: menu.is_some(),
+            item_is_menu,
+        }));
+        Ok(())
+    }
+
+    /// The item's icon: named from the theme, else its pixmap
+    fn icon(&self, proxy: &Proxy<'_>, attention: bool) -> Option<Image> {
+        let (name_property, pixmap_property) = if attention {
+            ("AttentionIconName", "AttentionIconPixmap")
+        } else {
+            ("IconName", "IconPixmap")
+        };
+        let name: String = proxy.get_property(name_property).unwrap_or_default();
+        if !name.is_empty() {
+            let theme_path: String = proxy.get_property("IconThemePath").unwrap_or_default();
+            let extra_dir = (!theme_path.is_empty()).then(|| Path::new(&theme_path));
+            let icon = crate::icons::load_one(
+                &name,
+                self.icon_theme.as_deref(),
+                ICON_CONTEXTS,
+                extra_dir,
+                self.icon_size,
+            );
+            if icon.is_some() {
+                return icon;
+            }
+        }
+        let pixmaps: Vec<(i32, i32, Vec<u8>)> = proxy.get_property(pixmap_property).ok()?;
+        pixmap_image(&pixmaps, self.icon_size)
+    }
+
+    /// Top level of an item's DBusMenu
+    fn menu_entries(&self, key: &str) -> zbus::Result<Vec<MenuEntry>> {
+        let menu = self.menu_proxy(key)?;
+        // Lets apps fill in the menu lazily; many do not implement it
+        let _ = menu.call::<_, _, bool>("AboutToShow", &(0i32));
+        let (_revision, (_id, _props, children)): (u32, (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>)) =
+            menu.call("GetLayout", &(0i32, 1i32, Vec::<String>::new()))?;
+        Ok(children.into_iter().filter_map(menu_entry).collect())
+    }
+
+    fn item_proxy(&self, key: &str) -> zbus::Result<Proxy<'static>> {
+        let item = self.items.get(key).ok_or_else(|| zbus::Error::Failure(format!("unknown item {key}")))?;
+        proxy(&self.connection, &item.owner, &item.path, ITEM_INTERFACE)
+    }
+
+    fn menu_proxy(&self, key: &str) -> zbus::Result<Proxy<'static>> {
+        let item = self.items.get(key).ok_or_else(|| zbus::Error::Failure(format!("unknown item {key}")))?;
+        let path = item
+            .menu
+            .as_ref()
+            .ok_or_else(|| zbus::Error::Failure(format!("{key} has no menu")))?;
+        proxy(&self.connection, &item.owner, path.as_str(), MENU_INTERFACE)
+    }
+
+    /// StatusNotifierItemRegistered / Unregistered for other hosts' sake
+    fn emit_registered(&self, key: &str, registered: bool) {
+        let iface = self
+            .connection
+            .object_server()
+            .interface::<_, Watcher>(WATCHER_PATH);
+        let result = iface.and_then(|iface| {
+            let ctxt = iface.signal_context();
+            if registered {
+                zbus::block_on(Watcher::status_notifier_item_registered(ctxt, key))
+            } else {
+                zbus::block_on(Watcher::status_notifier_item_unregistered(ctxt, key))
+            }
+        });
+        if let Err(e) = result {
+            debug!("Tray: failed to announce {key}: {e}");
+        }
+    }
+}
+
+/// A proxy that does not cache properties: items announce changes with
+/// their own signals instead of PropertiesChanged
+fn proxy(connection: &Connection, destination: &str, path: &str, interface: &'static str) -> zbus::Result<Proxy<'static>> {
+    zbus::blocking::proxy::Builder::new(connection)
+        .destination(destination.to_string())?
+        .path(path.to_string())?
+        .interface(interface)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+}
+
+/// Forward item signals and vanished bus names to the tray thread
+fn watch_signals(connection: &Connection, commands: Sender<TrayCommand>) {
+    let item_connection = connection.clone();
+    let item_commands = commands.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-tray-items".to_string())
+        .spawn(move || {
+            let rule = zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::Signal)
+                .interface(ITEM_INTERFACE)
+                .map(|builder| builder.build());
+            let messages = rule.and_then(|rule| {
+                zbus::blocking::MessageIterator::for_match_rule(rule, &item_connection, None)
+            });
+            let messages = match messages {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!("Tray: cannot follow item changes: {e}");
+                    return;
+                }
+            };
+            for message in messages.flatten() {
+                let Some(sender) = message.header().sender().map(|s| s.to_string()) else {
+                    continue;
+                };
+                if item_commands.send(TrayCommand::Changed { sender }).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start tray signal thread: {e}");
+    }
+
+    let owner_connection = connection.clone();
+    let spawned = std::thread::Builder::new()
+        .name("heydm-tray-owners".to_string())
+        .spawn(move || {
+            let changes = zbus::blocking::fdo::DBusProxy::new(&owner_connection)
+                .and_then(|dbus| dbus.receive_name_owner_changed().map_err(zbus::Error::from));
+            let changes = match changes {
+                Ok(changes) => changes,
+                Err(e) => {
+                    warn!("Tray: cannot follow exiting apps: {e}");
+                    return;
+                }
+            };
+            for change in changes {
+                let Ok(args) = change.args() else { continue };
+                if args.new_owner().is_some() {
+                    continue;
+                }
+                let name = args.name().to_string();
+                if commands.send(TrayCommand::Vanished { name }).is_err() {
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start tray owner thread: {e}");
+    }
+}
+
+/// A visible top-level menu entry
+fn menu_entry(value: OwnedValue) -> Option<MenuEntry> {
+    let (id, props, children): (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>) =
+        Value::from(value).downcast().ok()?;
+    let get_str = |name: &str| props.get(name).and_then(|v| <&str>::try_from(v).ok());
+    let get_bool = |name: &str| props.get(name).and_then(|v| bool::try_from(v).ok());
+
+    if get_bool("visible") == Some(false) {
+        return None;
+    }
+    let separator = get_str("type") == Some("separator");
+    let submenu = get_str("children-display") == Some("submenu") || !children.is_empty();
+    let checked = match get_str("toggle-type") {
+        Some("checkmark" | "radio") => Some(props.get("toggle-state").and_then(|v| i32::try_from(v).ok()) == Some(1)),
+        _ => None,
+    };
+    Some(MenuEntry {
+        id,
+        label: strip_mnemonics(get_str("label").unwrap_or("")),
+        enabled: get_bool("enabled").unwrap_or(true) && !submenu,
+        separator,
+        checked,
+    })
+}
+
+/// "_Quit" → "Quit", "Save __as" → "Save _as"
+fn strip_mnemonics(label: &str) -> String {
+    let mut result = String::with_capacity(label.len());
+    let mut chars = label.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            if chars.peek() == Some(&'_') {
+                result.push('_');
+                chars.next();
+            }
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// The pixmap closest to `size`, scaled to it; pixmaps are ARGB32 in
+/// network byte order
+fn pixmap_image(pixmaps: &[(i32, i32, Vec<u8>)], size: u32) -> Option<Image> {
+    let (w, h, data) = pixmaps
+        .iter()
+        .filter(|(w, h, data)| *w > 0 && *h > 0 && data.len() >= (*w * *h * 4) as usize)
+        .min_by_key(|(w, _, _)| ((*w as u32) < size, w.abs_diff(size as i32)))?;
+    let rgba: Vec<u8> = data[..(*w * *h * 4) as usize]
+        .chunks_exact(4)
+        .flat_map(|p| [p[1], p[2], p[3], p[0]])
+        .collect();
+    let image = image::RgbaImage::from_raw(*w as u32, *h as u32, rgba)?;
+    let scaled = image::imageops::resize(&image, size, size, FilterType::Triangle);
+    Some(Image::new(PixelBuffer::from_rgba(size, size, scaled.as_raw())))
+}
@@ -0,0 +1,1138 @@
+// =============================================================================
+// heyDM — udev/DRM Backend
+//
+// Runs heyDM directly on the hardware (no parent compositor):
+//   - libseat opens the session and device nodes without root. Switching
+//     to another VT (Ctrl+Alt+F<n>) pauses the session: input is suspended
+//     and DRM master released until the user switches back, when the
+//     devices are reactivated and rendering starts again
+//   - libinput provides input; new devices get the [input] / [device]
+//     settings from the config file (see devices.rs)
+//   - Every GPU is opened through DRM/KMS and registered with smithay's
+//     GpuManager. Frames are rendered on the primary GPU
+//     (HEYDM_DRM_DEVICE=/dev/dri/cardN picks another one); for monitors on
+//     another GPU the MultiRenderer copies the finished frame over to that
+//     GPU's buffers, as a dmabuf import where the drivers allow it and
+//     through memory otherwise. Each output renders on its own vblank.
+//   - A fullscreen window with nothing on top is scanned out directly
+//     when its dmabuf fits the mode (see scanout.rs); the client buffer is
+//     flipped onto the primary plane instead of a composited frame
+//   - GPUs come and go with udev (e.g. an eGPU dock): a new GPU is opened
+//     and its monitors lit; when one goes away its outputs are dropped, and
+//     when it was rendering, another GPU takes over
+//   - Gamma ramps from wlr-gamma-control clients (see gamma.rs) are loaded
+//     into the primary output's CRTC before the next frame, and again when
+//     the session comes back
+//   - Adaptive sync and tearing page flips for fullscreen windows scanned
+//     out directly, per the output's `vrr` and `allow_tearing` settings
+//     (see tearing.rs). Tearing uses an async atomic commit of the primary
+//     plane's framebuffer (Linux 6.8+), or an async legacy page flip; when
+//     the kernel refuses, the output falls back to vsynced flips
+//
+// Every connected connector is driven, each on its own CRTC, and monitors
+// plugged in or out are picked up. The first one lit (on the primary GPU
+// when it has a monitor) is the primary output: it shows the panel and the
+// overlays, and direct scanout, gamma ramps and the magnifier apply to it.
+// Further outputs each show their own workspace (see window.rs), and
+// clients render at the primary output's scale on all of them. Every
+// monitor is identified by its EDID, and the matching `[output "..."]`
+// section (see output.rs) sets its mode, transform and position; a further
+// output without a position is placed to the right of the others. After a
+// config reload every output's mode, transform and position are applied
+// again before the primary output's next frame.
+// =============================================================================
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
+
+use calloop::{EventLoop, LoopHandle, RegistrationToken};
+use smithay::backend::allocator::gbm::{GbmAllocator, GbmBufferFlags, GbmDevice};
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::drm::gbm::{framebuffer_from_dmabuf, GbmFramebuffer};
+use smithay::backend::drm::{
+    DrmDevice, DrmDeviceFd, DrmEvent, DrmNode, GbmBufferedSurface, NodeType, PlaneConfig, PlaneState,
+    VrrSupport,
+};
+use smithay::backend::input::InputEvent;
+use smithay::backend::libinput::{LibinputInputBackend, LibinputSessionInterface};
+use smithay::backend::renderer::glow::GlowRenderer;
+use smithay::backend::renderer::multigpu::gbm::GbmGlesBackend;
+use smithay::backend::renderer::multigpu::{GpuManager, MultiTexture};
+use smithay::backend::renderer::{Bind, Frame, ImportDma, Renderer as SmithayRenderer};
+use smithay::backend::session::libseat::LibSeatSession;
+use smithay::backend::session::{Event as SessionEvent, Session};
+use smithay::backend::udev::{self, UdevBackend, UdevEvent};
+use smithay::output::{Mode, Output, PhysicalProperties, Scale, Subpixel};
+use smithay::reexports::drm::control::atomic::AtomicModeReq;
+use smithay::reexports::drm::control::{
+    connector, crtc, framebuffer, property, AtomicCommitFlags, Device as ControlDevice,
+    Mode as DrmMode, ModeTypeFlags, PageFlipFlags,
+};
+use smithay::reexports::input::Libinput;
+use smithay::reexports::rustix::fs::OFlags;
+use smithay::reexports::wayland_server::backend::GlobalId;
+use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::Display;
+use smithay::utils::{DeviceFd, Logical, Physical, Point, Rectangle, Size, Transform};
+use tracing::{debug, error, info, warn};
+
+use crate::input::InputHandler;
+use crate::output::{self, ModeRequest, OutputIdentity};
+use crate::render::{Renderer, Scene, View};
+use crate::scanout::{self, Candidate};
+use crate::state::HeyDM;
+use crate::tearing::{self, VrrMode};
+use crate::texture::TextureCache;
+use crate::wallpaper::OutputWallpaper;
+
+/// Renderers for every GPU, rendering on one and copying to the others
+type GpuRenderer = GbmGlesBackend<GlowRenderer, DrmDeviceFd>;
+
+/// A connector heyDM renders to
+struct OutputSurface {
+    output: Output,
+    global: GlobalId,
+    surface: GbmBufferedSurface<GbmAllocator<DrmDeviceFd>, ()>,
+    connector: connector::Handle,
+    /// Modes the connector offers
+    modes: Vec<DrmMode>,
+    mode: DrmMode,
+    transform: Transform,
+    /// Size of the mode, before the transform
+    size: Size<i32, Physical>,
+    /// Where the output's part of the scene starts; (0, 0) on the primary
+    /// output
+    origin: Point<i32, Logical>,
+    /// The page flip waiting for the next vblank
+    pending: Option<PendingFlip>,
+    /// The client buffer on screen while scanning out
+    scanout: Option<ScanoutFrame>,
+    crtc: crtc::Handle,
+    /// Gamma ramps set by a client; None for the original ones
+    gamma: Option<Vec<u16>>,
+    /// Adaptive sync setting, whether the monitor supports it and whether
+    /// it is on
+    vrr_mode: VrrMode,
+    vrr_capable: bool,
+    vrr: bool,
+    /// Whether scanned-out clients may tear; false as well once the kernel
+    /// refused an async flip
+    allow_tearing: bool,
+    async_flips: bool,
+}
+
+/// What the last page flip put on screen
+enum PendingFlip {
+    /// A frame from the GbmBufferedSurface swapchain
+    Composited,
+    /// A client buffer; None when the one on screen was flipped again
+    Scanout(Option<ScanoutFrame>),
+}
+
+/// A client buffer on the primary plane
+struct ScanoutFrame {
+    surface: WlSurface,
+    buffer: WlBuffer,
+    framebuffer: GbmFramebuffer,
+}
+
+impl OutputSurface {
+    /// Size of the scene, with the transform applied
+    fn scene_size(&self) -> Size<i32, Physical> {
+        self.transform.transform_size(self.size)
+    }
+
+    /// Load the client's gamma ramps into the CRTC, or a linear ramp
+    fn load_gamma(&self, fd: &DrmDeviceFd) {
+        let result = match &self.gamma {
+            Some(ramps) => {
+                let (red, rest) = ramps.split_at(ramps.len() / 3);
+                let (green, blue) = rest.split_at(ramps.len() / 3);
+                fd.set_gamma(self.crtc, red, green, blue)
+            }
+            None => {
+                let size = fd.get_crtc(self.crtc).map(|info| info.gamma_length()).unwrap_or(0) as usize;
+                let linear: Vec<u16> = (0..size)
+                    .map(|i| (i * 0xffff / size.saturating_sub(1).max(1)) as u16)
+                    .collect();
+                fd.set_gamma(self.crtc, &linear, &linear, &linear)
+            }
+        };
+        if let Err(e) = result {
+            warn!("Output {}: setting gamma ramps failed: {e}", self.output.name());
+        }
+    }
+
+    /// Turn adaptive sync on or off, if the monitor supports it
+    fn set_vrr(&mut self, enabled: bool) {
+        if !self.vrr_capable || enabled == self.vrr {
+            return;
+        }
+        match self.surface.surface().use_vrr(enabled) {
+            Ok(()) => {
+                let state = if enabled { "on" } else { "off" };
+                info!("Output {}: adaptive sync {state}", self.output.name());
+                self.vrr = enabled;
+            }
+            Err(e) => {
+                warn!("Output {}: switching adaptive sync failed: {e}", self.output.name());
+                self.vrr_capable = false;
+            }
+        }
+    }
+
+    /// Put `fb` on the primary plane without waiting for vblank
+    fn flip_async(
+        &self,
+        fd: &DrmDeviceFd,
+        fb: framebuffer::Handle,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let drm_surface = self.surface.surface();
+        if drm_surface.is_legacy() {
+            fd.page_flip(self.crtc, fb, PageFlipFlags::EVENT | PageFlipFlags::ASYNC, None)?;
+            return Ok(());
+        }
+        // Async atomic commits may change nothing but the framebuffer
+        let plane = drm_surface.plane();
+        let properties = fd.get_properties(plane)?;
+        let (handles, _) = properties.as_props_and_values();
+        let fb_id = handles
+            .iter()
+            .copied()
+            .find(|handle| {
+                fd.get_property(*handle)
+                    .is_ok_and(|info| info.name().to_bytes() == b"FB_ID")
+            })
+            .ok_or("primary plane has no FB_ID property")?;
+        let mut request = AtomicModeReq::new();
+        request.add_property(plane, fb_id, property::Value::Framebuffer(Some(fb)));
+        let flags = AtomicCommitFlags::PAGE_FLIP_EVENT
+            | AtomicCommitFlags::PAGE_FLIP_ASYNC
+            | AtomicCommitFlags::NONBLOCK;
+        fd.atomic_commit(flags, request)?;
+        Ok(())
+    }
+
+    /// Flip a client buffer straight onto the primary plane; a new buffer
+    /// goes on screen right away with `tearing`
+    fn scan_out(
+        &mut self,
+        fd: &DrmDeviceFd,
+        gbm: &GbmDevice<DrmDeviceFd>,
+        candidate: Candidate,
+        tearing: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // A client that did not commit since the last flip shows the same
+        // buffer again
+        let shown = self
+            .scanout
+            .as_ref()
+            .filter(|shown| shown.buffer == candidate.buffer)
+            .map(|shown| *shown.framebuffer.as_ref());
+        let (fb, frame) = match shown {
+            Some(fb) => (fb, None),
+            None => {
+                let framebuffer = framebuffer_from_dmabuf(fd, gbm, &candidate.dmabuf, true, true)?;
+                let fb = *framebuffer.as_ref();
+                let frame = ScanoutFrame {
+                    surface: candidate.surface,
+                    buffer: candidate.buffer,
+                    framebuffer,
+                };
+                (fb, Some(frame))
+            }
+        };
+
+        // The same buffer again waits for vblank, which paces the loop
+        if tearing && self.async_flips && frame.is_some() {
+            match self.flip_async(fd, fb) {
+                Ok(()) => {
+                    self.pending = Some(PendingFlip::Scanout(frame));
+                    return Ok(());
+                }
+                Err(e) => {
+                    info!("Output {}: tearing page flips not supported: {e}", self.output.name());
+                    self.async_flips = false;
+                }
+            }
+        }
+
+        let drm_surface = self.surface.surface();
+        let plane = PlaneState {
+            handle: drm_surface.plane(),
+            config: Some(PlaneConfig {
+                src: Rectangle::from_size((self.size.w as f64, self.size.h as f64).into()),
+                dst: Rectangle::from_size(self.size),
+                alpha: 1.0,
+                transform: Transform::Normal,
+                damage_clips: None,
+                fb,
+                fence: None,
+            }),
+        };
+        drm_surface.page_flip([plane], true)?;
+        self.pending = Some(PendingFlip::Scanout(frame));
+        Ok(())
+    }
+
+    /// The pending flip completed: release client buffers that left the
+    /// screen and let the scanned-out client draw its next frame
+    fn flip_done(&mut self, state: &mut HeyDM, primary: bool) {
+        match self.pending.take() {
+            Some(PendingFlip::Composited) => {
+                if let Err(e) = self.surface.frame_submitted() {
+                    warn!("Frame submission failed: {e}");
+                }
+                if let Some(old) = self.scanout.take() {
+                    old.buffer.release();
+                }
+            }
+            Some(PendingFlip::Scanout(frame)) => {
+                if let Some(frame) = frame {
+                    if let Some(old) = self.scanout.replace(frame) {
+                        old.buffer.release();
+                    }
+                }
+                if let Some(frame) = self.scanout.as_ref() {
+                    scanout::send_frame(state, &frame.surface);
+                }
+            }
+            None => {}
+        }
+        if primary {
+            state.latency.frame_presented();
+        }
+    }
+}
+
+/// An open GPU and the outputs it drives
+struct Gpu {
+    path: PathBuf,
+    fd: DrmDeviceFd,
+    drm: DrmDevice,
+    gbm: GbmDevice<DrmDeviceFd>,
+    /// The node the GpuManager knows the GPU by
+    render_node: DrmNode,
+    /// Driven connectors by CRTC
+    outputs: HashMap<crtc::Handle, OutputSurface>,
+    /// The DRM event source, removed with the GPU
+    token: RegistrationToken,
+}
+
+/// Every open GPU, shared with the event sources that drive them
+struct Backend {
+    session: LibSeatSession,
+    /// The GPU preferred for rendering and for the primary output
+    primary: DrmNode,
+    /// Render node of the GPU every frame is rendered on
+    primary_render: DrmNode,
+    gpus: HashMap<DrmNode, Gpu>,
+    renderers: GpuManager<GpuRenderer>,
+    /// Textures on the rendering GPU, shared by every output
+    textures: TextureCache<MultiTexture>,
+    /// Reused for every frame
+    scene: Scene,
+    /// GPU and CRTC of the output with the panel
+    primary_output: Option<(DrmNode, crtc::Handle)>,
+}
+
+impl Backend {
+    /// Open the GPU at `path` through the session and register it for
+    /// rendering
+    fn add_gpu(
+        this: &Rc<RefCell<Self>>,
+        path: &Path,
+        handle: &LoopHandle<'static, HeyDM>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let node = DrmNode::from_path(path)?;
+        let mut backend = this.borrow_mut();
+        if backend.gpus.contains_key(&node) {
+            return Ok(());
+        }
+        let fd = backend.session.open(
+            path,
+            OFlags::RDWR | OFlags::CLOEXEC | OFlags::NOCTTY | OFlags::NONBLOCK,
+        )?;
+        let fd = DrmDeviceFd::new(DeviceFd::from(fd));
+
+        let (drm, notifier) = DrmDevice::new(fd.clone(), true)?;
+        let gbm = GbmDevice::new(fd.clone())?;
+        let render_node = node
+            .node_with_type(NodeType::Render)
+            .and_then(Result::ok)
+            .unwrap_or(node);
+        backend.renderers.as_mut().add_node(render_node, gbm.clone())?;
+        if node == backend.primary {
+            backend.primary_render = render_node;
+        }
+
+        let weak = Rc::downgrade(this);
+        let gpu_path = path.to_path_buf();
+        let token = handle.insert_source(notifier, move |event, _, state| {
+            let Some(backend) = weak.upgrade() else {
+                return;
+            };
+            let mut backend = backend.borrow_mut();
+            match event {
+                DrmEvent::VBlank(crtc) => backend.vblank(node, crtc, state),
+                DrmEvent::Error(e) => error!("DRM error on {}: {e}", gpu_path.display()),
+            }
+        })?;
+        info!("Opened GPU {}", path.display());
+
+        backend.gpus.insert(
+            node,
+            Gpu {
+                path: path.to_path_buf(),
+                fd,
+                drm,
+                gbm,
+                render_node,
+                outputs: HashMap::new(),
+                token,
+            },
+        );
+        Ok(())
+    }
+
+    /// Close a GPU that went away, with its outputs. Rendering moves to
+    /// another GPU if it was the primary one.
+    fn remove_gpu(&mut self, node: DrmNode, handle: &LoopHandle<'static, HeyDM>, state: &mut HeyDM) {
+        let Some(gpu) = self.gpus.get(&node) else {
+            return;
+        };
+        let crtcs: Vec<crtc::Handle> = gpu.outputs.keys().copied().collect();
+        for crtc in crtcs {
+            self.remove_output(node, crtc, state);
+        }
+        let Some(gpu) = self.gpus.remove(&node) else {
+            return;
+        };
+        warn!("GPU {} removed", gpu.path.display());
+        handle.remove(gpu.token);
+        self.renderers.as_mut().remove_node(&gpu.render_node);
+
+        if node == self.primary {
+            if let Some(next) = self.ordered().first().copied() {
+                self.primary = next;
+                self.primary_render = self.gpus[&next].render_node;
+                info!("Rendering on {} now", self.gpus[&next].path.display());
+            }
+            // The textures lived on the GPU that is gone
+            self.textures = TextureCache::new();
+        }
+        self.scan_outputs(state);
+        self.drive_outputs(state);
+    }
+
+    /// GPU nodes with the primary one first
+    fn ordered(&self) -> Vec<DrmNode> {
+        let mut nodes: Vec<DrmNode> = self.gpus.keys().copied().collect();
+        nodes.sort_by_key(|node| (*node != self.primary, self.gpus[node].path.clone()));
+        nodes
+    }
+
+    /// Identify the monitors connected to every GPU
+    fn scan_outputs(&mut self, state: &mut HeyDM) {
+        state.outputs = self
+            .ordered()
+            .into_iter()
+            .flat_map(|node| {
+                let gpu = &self.gpus[&node];
+                gpu.connected()
+                    .iter()
+                    .map(|info| OutputIdentity::new(&connector_name(info), read_edid(&gpu.fd, info).as_deref()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        for identity in &state.outputs {
+            info!("Detected output {}: {}", identity.connector, identity.description());
+        }
+    }
+
+    /// Drop the outputs whose monitor was unplugged and light every
+    /// connected one that is not driven yet, each starting its own render
+    /// loop. The first one lit becomes the primary output.
+    fn drive_outputs(&mut self, state: &mut HeyDM) {
+        let mut unplugged = Vec::new();
+        for (node, gpu) in &self.gpus {
+            let connected: Vec<connector::Handle> =
+                gpu.connected().iter().map(|info| info.handle()).collect();
+            for (crtc, output) in &gpu.outputs {
+                if !connected.contains(&output.connector) {
+                    unplugged.push((*node, *crtc));
+                }
+            }
+        }
+        for (node, crtc) in unplugged {
+            self.remove_output(node, crtc, state);
+        }
+        // Without the primary output start over, so that another one takes
+        // its place with the panel
+        if self.primary_output.is_none() {
+            let driven: Vec<(DrmNode, crtc::Handle)> = self
+                .gpus
+                .iter()
+                .flat_map(|(node, gpu)| gpu.outputs.keys().map(|crtc| (*node, *crtc)))
+                .collect();
+            for (node, crtc) in driven {
+                self.remove_output(node, crtc, state);
+            }
+        }
+
+        let mut lit = Vec::new();
+        for node in self.ordered() {
+            let gpu = &self.gpus[&node];
+            let driven: Vec<connector::Handle> =
+                gpu.outputs.values().map(|output| output.connector).collect();
+            let new: Vec<connector::Info> = gpu
+                .connected()
+                .into_iter()
+                .filter(|info| !driven.contains(&info.handle()))
+                .collect();
+            for info in new {
+                match self.create_output(node, &info, state) {
+                    Ok(Some(crtc)) => lit.push((node, crtc)),
+                    Ok(None) => {}
+                    Err(e) => warn!("Could not drive {}: {e}", connector_name(&info)),
+                }
+            }
+        }
+        if self.primary_output.is_none() {
+            warn!("No connected outputs found");
+        }
+        self.arrange_outputs(state);
+        for (node, crtc) in lit {
+            if let Err(e) = self.render(node, crtc, state) {
+                warn!("Rendering failed: {e}");
+            }
+        }
+    }
+
+    /// Stop driving an output and unregister it
+    fn remove_output(&mut self, node: DrmNode, crtc: crtc::Handle, state: &mut HeyDM) {
+        let Some(mut output) = self.gpus.get_mut(&node).and_then(|gpu| gpu.outputs.remove(&crtc)) else {
+            return;
+        };
+        let name = output.output.name();
+        info!("Output {name} no longer driven");
+        if let Some(frame) = output.scanout.take() {
+            frame.buffer.release();
+        }
+        state.display_handle.remove_global::<HeyDM>(output.global);
+        state.window_manager.output_removed(&name, &state.output_size);
+        state.extra_outputs.retain(|extra| extra.name() != name);
+        if self.primary_output == Some((node, crtc)) {
+            self.primary_output = None;
+            state.output = None;
+            state.gamma.set_size(0);
+        }
+    }
+
+    /// Set up a surface on a connected connector with a free CRTC. The
+    /// first output becomes the primary one; further ones start out to the
+    /// right of the outputs already lit until `arrange_outputs`.
+    fn create_output(
+        &mut self,
+        node: DrmNode,
+        info: &connector::Info,
+        state: &mut HeyDM,
+    ) -> Result<Option<crtc::Handle>, Box<dyn std::error::Error>> {
+        let primary = self.primary_output.is_none();
+        let render_node = self.gpus[&node].render_node;
+        // Buffers the GPU driving the output can render into and scan out
+        let formats = self
+            .renderers
+            .single_renderer(&render_node)?
+            .as_mut()
+            .egl_context()
+            .dmabuf_render_formats()
+            .clone();
+
+        let gpu = self.gpus.get_mut(&node).ok_or("GPU is gone")?;
+        let fd = gpu.fd.clone();
+        let resources = fd.resource_handles()?;
+        let name = connector_name(info);
+        let identity = OutputIdentity::new(&name, read_edid(&fd, info).as_deref());
+        let config = output::config_for(&state.config.outputs, &identity).map(|(section, config)| {
+            info!("Output {name}: using [output \"{section}\"]");
+            config.clone()
+        });
+        let config = config.unwrap_or_default();
+        let Some(drm_mode) = pick_mode(info.modes(), config.mode, &name) else {
+            warn!("Output {name} has no modes");
+            return Ok(None);
+        };
+
+        let crtc = info
+            .encoders()
+            .iter()
+            .filter_map(|enc| fd.get_encoder(*enc).ok())
+            .flat_map(|enc| resources.filter_crtcs(enc.possible_crtcs()))
+            .find(|crtc| !gpu.outputs.contains_key(crtc))
+            .ok_or_else(|| format!("no CRTC available for {name}"))?;
+
+        let drm_surface = gpu.drm.create_surface(crtc, drm_mode, &[info.handle()])?;
+        let vrr_capable = match drm_surface.vrr_supported(info.handle()) {
+            Ok(VrrSupport::Supported) => true,
+            Ok(VrrSupport::RequiresModeset) => {
+                debug!("Output {name}: adaptive sync needs a modeset, not switching it live");
+                config.vrr == Some(VrrMode::On)
+            }
+            _ => false,
+        };
+        let allocator =
+            GbmAllocator::new(gpu.gbm.clone(), GbmBufferFlags::RENDERING | GbmBufferFlags::SCANOUT);
+        let surface = GbmBufferedSurface::new(
+            drm_surface,
+            allocator,
+            &[Fourcc::Argb8888, Fourcc::Xrgb8888],
+            formats,
+        )?;
+
+        let (w, h) = drm_mode.size();
+        let size = Size::from((w as i32, h as i32));
+        let (mm_w, mm_h) = info.size().unwrap_or((0, 0));
+        let output = Output::new(
+            name.clone(),
+            PhysicalProperties {
+                size: (mm_w as i32, mm_h as i32).into(),
+                subpixel: Subpixel::Unknown,
+                make: if identity.make.is_empty() { "Unknown".into() } else { identity.make },
+                model: if identity.model.is_empty() { "Unknown".into() } else { identity.model },
+                serial_number: identity.serial,
+            },
+        );
+        let mode = Mode::from(drm_mode);
+        let transform = config.transform.unwrap_or(Transform::Normal);
+        let scene_size = transform.transform_size(size);
+        output.set_preferred(mode);
+
+        let origin = if primary {
+            output.change_current_state(
+                Some(mode),
+                Some(transform),
+                config.scale.map(Scale::Fractional),
+                Some(config.position.unwrap_or((0, 0)).into()),
+            );
+            let gamma_size = fd.get_crtc(crtc).map(|info| info.gamma_length()).unwrap_or(0);
+            state.gamma.set_size(gamma_size as usize);
+            state.output = Some(output.clone());
+            state.set_output_scale(config.scale.unwrap_or(1.0));
+            state.output_size = scene_size;
+            state.window_manager.output_added(&name, None, &state.output_size);
+            let own_wallpaper = OutputWallpaper {
+                path: config.wallpaper.clone(),
+                mode: config.wallpaper_mode,
+            };
+            state.wallpaper.set_output(&name, own_wallpaper, state.output_size);
+            state.wallpaper.select_output(&name, state.output_size);
+            self.primary_output = Some((node, crtc));
+            Point::default()
+        } else {
+            // Clients render at the primary output's scale everywhere
+            let right = state
+                .window_manager
+                .output_areas()
+                .map(|area| area.loc.x + area.size.w)
+                .fold(state.output_size.w, i32::max);
+            let area = Rectangle::new(Point::from((right, 0)), Size::from((scene_size.w, scene_size.h)));
+            output.change_current_state(
+                Some(mode),
+                Some(transform),
+                Some(Scale::Fractional(state.output_scale)),
+                Some(area.loc),
+            );
+            state.window_manager.output_added(&name, Some(area), &state.output_size);
+            state.extra_outputs.push(output.clone());
+            Point::from((right, 0))
+        };
+        let global = output.create_global::<HeyDM>(&state.display_handle);
+        info!("Output {name}: {}x{}@{}Hz", size.w, size.h, drm_mode.vrefresh());
+
+        gpu.outputs.insert(
+            crtc,
+            OutputSurface {
+                output,
+                global,
+                surface,
+                connector: info.handle(),
+                modes: info.modes().to_vec(),
+                mode: drm_mode,
+                transform,
+                size,
+                origin,
+                pending: None,
+                scanout: None,
+                crtc,
+                gamma: None,
+                vrr_mode: config.vrr.unwrap_or_default(),
+                vrr_capable,
+                vrr: false,
+                allow_tearing: config.allow_tearing.unwrap_or(true),
+                async_flips: true,
+            },
+        );
+        Ok(Some(crtc))
+    }
+
+    /// Place the further outputs at their `position` relative to the
+    /// primary output's, or side by side to the right of the others
+    fn arrange_outputs(&mut self, state: &mut HeyDM) {
+        let scale = state.output_scale;
+        let primary_position = state
+            .output
+            .as_ref()
+            .map(|output| output.current_location())
+            .unwrap_or_default();
+        let primary_output = self.primary_output;
+        let mut extras: Vec<&mut OutputSurface> = self
+            .gpus
+            .iter_mut()
+            .flat_map(|(node, gpu)| {
+                gpu.outputs
+                    .iter_mut()
+                    .filter(move |(crtc, _)| primary_output != Some((*node, **crtc)))
+                    .map(|(_, output)| output)
+            })
+            .collect();
+        // Configured positions first, so the others go right of them
+        extras.sort_by_key(|output| {
+            let name = output.output.name();
+            (state.output_config(&name).position.is_none(), name)
+        });
+
+        let mut right = state.output_size.w;
+        for output in extras {
+            let name = output.output.name();
+            let size = output.scene_size();
+            // Positions are logical; the scene is in the primary output's pixels
+            let origin = match state.output_config(&name).position {
+                Some((x, y)) => Point::from((
+                    ((x - primary_position.x) as f64 * scale).round() as i32,
+                    ((y - primary_position.y) as f64 * scale).round() as i32,
+                )),
+                None => Point::from((right, 0)),
+            };
+            right = right.max(origin.x + size.w);
+            let location = primary_position
+                + Point::from((
+                    (origin.x as f64 / scale).round() as i32,
+                    (origin.y as f64 / scale).round() as i32,
+                ));
+            output.origin = origin;
+            output
+                .output
+                .change_current_state(None, None, Some(Scale::Fractional(scale)), Some(location));
+            let area = Rectangle::new(origin, Size::from((size.w, size.h)));
+            state.window_manager.set_output_area(&name, area, &state.output_size);
+        }
+    }
+
+    /// Switch every output to the mode and transform of its `[output]`
+    /// section, then place them again, e.g. after the config was reloaded
+    fn modeset(&mut self, state: &mut HeyDM) {
+        for (node, gpu) in &mut self.gpus {
+            if !gpu.drm.is_active() {
+                continue;
+            }
+            let crtcs: Vec<crtc::Handle> = gpu.outputs.keys().copied().collect();
+            for crtc in crtcs {
+                gpu.modeset(crtc, state, self.primary_output == Some((*node, crtc)));
+            }
+        }
+        self.arrange_outputs(state);
+    }
+
+    /// A frame reached the screen: render the output's next one
+    fn vblank(&mut self, node: DrmNode, crtc: crtc::Handle, state: &mut HeyDM) {
+        let primary = self.primary_output == Some((node, crtc));
+        let Some(output) = self.gpus.get_mut(&node).and_then(|gpu| gpu.outputs.get_mut(&crtc)) else {
+            return;
+        };
+        output.flip_done(state, primary);
+        if let Err(e) = self.render(node, crtc, state) {
+            warn!("Rendering failed: {e}");
+        }
+    }
+
+    /// Release DRM master everywhere; nothing is rendered until `activate`
+    fn pause(&mut self) {
+        for gpu in self.gpus.values_mut() {
+            gpu.drm.pause();
+        }
+    }
+
+    /// Take DRM master back and restart every output's render loop
+    fn activate(&mut self, state: &mut HeyDM) {
+        let mut active = Vec::new();
+        for (node, gpu) in &mut self.gpus {
+            if let Err(e) = gpu.drm.activate(false) {
+                error!("Failed to reactivate GPU {}: {e}", gpu.path.display());
+                continue;
+            }
+            for (crtc, output) in &mut gpu.outputs {
+                // Frames queued before the switch never completed
+                output.surface.reset_buffers();
+                output.pending = None;
+                // Whoever had the VT may have changed the ramps
+                if output.gamma.is_some() {
+                    output.load_gamma(&gpu.fd);
+                }
+                active.push((*node, *crtc));
+            }
+        }
+        for (node, crtc) in active {
+            if let Err(e) = self.render(node, crtc, state) {
+                warn!("Rendering failed: {e}");
+            }
+        }
+    }
+
+    /// Render one frame of an output on the primary GPU, copied over to
+    /// the output's GPU when that is another one, and queue it for the
+    /// next vblank
+    fn render(
+        &mut self,
+        node: DrmNode,
+        crtc: crtc::Handle,
+        state: &mut HeyDM,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let primary = self.primary_output == Some((node, crtc));
+        let active = self.gpus.get(&node).is_some_and(|gpu| gpu.drm.is_active());
+        if primary && active && std::mem::take(&mut state.output_modeset) {
+            self.modeset(state);
+        }
+        let Self {
+            gpus,
+            renderers,
+            primary_render,
+            textures,
+            scene,
+            ..
+        } = self;
+        let Some(gpu) = gpus.get_mut(&node) else {
+            return Ok(());
+        };
+        if !gpu.drm.is_active() {
+            return Ok(());
+        }
+        let started = Instant::now();
+        let Some(output) = gpu.outputs.get_mut(&crtc) else {
+            return Ok(());
+        };
+        if primary {
+            if let Some(ramps) = state.gamma.take_pending() {
+                output.gamma = ramps;
+                output.load_gamma(&gpu.fd);
+            }
+        }
+
+        // Fullscreen windows are scanned out on the primary output only
+        let candidate = Some(output.transform)
+            .filter(|transform| primary && *transform == Transform::Normal)
+            .and_then(|_| scanout::candidate(state, output.size));
+        output.set_vrr(tearing::wants_vrr(state, output.vrr_mode, candidate.is_some()));
+        if let Some(candidate) = candidate {
+            let tearing = output.allow_tearing && tearing::wants_tearing(state, &candidate.surface);
+            match output.scan_out(&gpu.fd, &gpu.gbm, candidate, tearing) {
+                Ok(()) => {
+                    state.latency.frame_submitted(true);
+                    state.perf.frame_submitted(started.elapsed(), 0);
+                    return Ok(());
+                }
+                Err(e) => debug!("Direct scanout failed, compositing: {e}"),
+            }
+        }
+
+        let mut renderer = if gpu.render_node == *primary_render {
+            renderers.single_renderer(&gpu.render_node)?
+        } else {
+            renderers.renderer(primary_render, &gpu.render_node, output.surface.format())?
+        };
+        Renderer::build_scene(state, state.output_size, scene);
+        let matrix = state.color.matrix();
+        textures.prepare(&mut renderer, &state.text, scene, matrix)?;
+
+        let (mut dmabuf, _age) = output.surface.next_buffer()?;
+        let mut target = renderer.bind(&mut dmabuf)?;
+        let mut frame = renderer.render(&mut target, output.size, output.transform)?;
+        // Further outputs show their part of the scene; the magnifier
+        // zooms on the primary one
+        let view = if primary {
+            state
+                .zoom
+                .frame(state.window_manager.cursor_position(), output.scene_size())
+        } else {
+            View::new((output.origin.x as f64, output.origin.y as f64), 1.0)
+        };
+        Renderer::draw_scene(&mut frame, scene, textures, view)?;
+        let sync = frame.finish()?;
+        drop(target);
+
+        output.surface.queue_buffer(Some(sync), None, ())?;
+        output.pending = Some(PendingFlip::Composited);
+        if primary {
+            state.latency.frame_submitted(true);
+            let area = output.size.w as u64 * output.size.h as u64;
+            state.perf.frame_submitted(started.elapsed(), area);
+        }
+        debug!("Queued frame for {}", output.output.name());
+        Ok(())
+    }
+}
+
+impl Gpu {
+    /// The connectors with a monitor plugged in
+    fn connected(&self) -> Vec<connector::Info> {
+        let Ok(resources) = self.fd.resource_handles() else {
+            return Vec::new();
+        };
+        resources
+            .connectors()
+            .iter()
+            .filter_map(|conn| self.fd.get_connector(*conn, false).ok())
+            .filter(|info| info.state() == connector::State::Connected)
+            .collect()
+    }
+
+    /// Switch an output to the mode and transform of its `[output]`
+    /// section; the primary one resizes the scene
+    fn modeset(&mut self, crtc: crtc::Handle, state: &mut HeyDM, primary: bool) {
+        let Some(output) = self.outputs.get_mut(&crtc) else {
+            return;
+        };
+        let name = output.output.name();
+        let config = state.output_config(&name);
+        let Some(drm_mode) = pick_mode(&output.modes, config.mode, &name) else {
+            return;
+        };
+        if Mode::from(drm_mode) != Mode::from(output.mode) {
+            if let Err(e) = output.surface.use_mode(drm_mode) {
+                warn!("Output {name}: switching modes failed: {e}");
+                return;
+            }
+            let (w, h) = drm_mode.size();
+            output.mode = drm_mode;
+            output.size = Size::from((w as i32, h as i32));
+            info!("Output {name}: {w}x{h}@{}Hz", drm_mode.vrefresh());
+        }
+        output.transform = config.transform.unwrap_or(Transform::Normal);
+        output.vrr_mode = config.vrr.unwrap_or_default();
+        output.allow_tearing = config.allow_tearing.unwrap_or(true);
+        output.output.change_current_state(
+            Some(Mode::from(drm_mode)),
+            Some(output.transform),
+            None,
+            None,
+        );
+        if primary {
+            state.set_output_size(output.scene_size());
+        }
+    }
+}
+
+/// Run heyDM on the udev/DRM backend until the loop is stopped
+pub fn run(
+    event_loop: &mut EventLoop<HeyDM>,
+    display: &mut Display<HeyDM>,
+    state: &mut HeyDM,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Initializing udev/DRM backend");
+    let (session, notifier) = LibSeatSession::new()?;
+    let seat_name = session.seat();
+    info!("Session opened on seat '{seat_name}'");
+
+    state.session = Some(session.clone());
+
+    // Input devices via libinput, configured from the config file as they
+    // appear and again on reload
+    let mut libinput =
+        Libinput::new_with_udev::<LibinputSessionInterface<LibSeatSession>>(session.clone().into());
+    libinput
+        .udev_assign_seat(&seat_name)
+        .map_err(|()| "failed to assign libinput to the seat")?;
+    event_loop.handle().insert_source(
+        LibinputInputBackend::new(libinput.clone()),
+        |mut event, _, state| {
+            match &mut event {
+                InputEvent::DeviceAdded { device } => {
+                    info!("Input device added: {}", device.name());
+                    state.config.input.apply(device);
+                    state.input_devices.push(device.clone());
+                }
+                InputEvent::DeviceRemoved { device } => {
+                    state.input_devices.retain(|known| known != &*device);
+                }
+                _ => {}
+            }
+            crate::recovery::guard(state, "input handling", |state| {
+                InputHandler::handle_input(state, event)
+            });
+        },
+    )?;
+
+    // Open every GPU, the primary one first; only that one is required
+    let udev_backend = UdevBackend::new(&seat_name)?;
+    let primary_path = match std::env::var_os("HEYDM_DRM_DEVICE") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => udev::primary_gpu(&seat_name)?,
+    };
+    let primary_path = primary_path
+        .or_else(|| udev_backend.device_list().next().map(|(_, path)| path.to_path_buf()))
+        .ok_or("no GPU found")?;
+    let primary = DrmNode::from_path(&primary_path)?;
+    let backend = Rc::new(RefCell::new(Backend {
+        session,
+        primary,
+        primary_render: primary,
+        gpus: HashMap::new(),
+        renderers: GpuManager::new(GpuRenderer::default())?,
+        textures: TextureCache::new(),
+        scene: Scene::default(),
+        primary_output: None,
+    }));
+    let handle = event_loop.handle();
+    Backend::add_gpu(&backend, &primary_path, &handle)?;
+    {
+        // Clients may hand over dmabufs the primary GPU can read, for
+        // direct scanout
+        let mut backend = backend.borrow_mut();
+        let primary_render = backend.primary_render;
+        let formats: Vec<_> = backend
+            .renderers
+            .single_renderer(&primary_render)?
+            .dmabuf_formats()
+            .iter()
+            .copied()
+            .collect();
+        state
+            .dmabuf_state
+            .create_global::<HeyDM>(&state.display_handle, formats);
+    }
+    for (_, path) in udev_backend.device_list() {
+        if let Err(e) = Backend::add_gpu(&backend, path, &handle) {
+            warn!("Could not open GPU {}: {e}", path.display());
+        }
+    }
+
+    // Give up the devices while another VT is in front
+    let session_backend = backend.clone();
+    handle.insert_source(notifier, move |event, _, state| {
+        let mut backend = session_backend.borrow_mut();
+        match event {
+            SessionEvent::PauseSession => {
+                info!("Session paused");
+                libinput.suspend();
+                backend.pause();
+            }
+            SessionEvent::ActivateSession => {
+                info!("Session activated");
+                if libinput.resume().is_err() {
+                    error!("Failed to resume libinput");
+                }
+                backend.activate(state);
+            }
+        }
+    })?;
+
+    // GPU hotplug; a connector change plugs monitors in or out
+    let hotplug_backend = backend.clone();
+    let hotplug_handle = handle.clone();
+    handle.insert_source(udev_backend, move |event, _, state| match event {
+        UdevEvent::Added { path, .. } => {
+            info!("DRM device added: {}", path.display());
+            if let Err(e) = Backend::add_gpu(&hotplug_backend, &path, &hotplug_handle) {
+                warn!("Could not open GPU {}: {e}", path.display());
+                return;
+            }
+            let mut backend = hotplug_backend.borrow_mut();
+            backend.scan_outputs(state);
+            backend.drive_outputs(state);
+        }
+        UdevEvent::Changed { device_id } => {
+            debug!("DRM device {device_id} changed");
+            let mut backend = hotplug_backend.borrow_mut();
+            backend.scan_outputs(state);
+            backend.drive_outputs(state);
+        }
+        UdevEvent::Removed { device_id } => match DrmNode::from_dev_id(device_id) {
+            Ok(node) => hotplug_backend
+                .borrow_mut()
+                .remove_gpu(node, &hotplug_handle, state),
+            Err(e) => warn!("DRM device {device_id} removed: {e}"),
+        },
+    })?;
+
+    // Light every monitor and kick off their vblank-driven render loops
+    {
+        let mut backend = backend.borrow_mut();
+        backend.scan_outputs(state);
+        backend.drive_outputs(state);
+    }
+
+    event_loop.run(None, state, |state| {
+        let dispatched = crate::recovery::guard(state, "client dispatch", |state| display.dispatch_clients(state));
+        if let Some(Err(e)) = dispatched {
+            warn!("Failed to dispatch clients: {e}");
+        }
+        let _ = display.flush_clients();
+        state.publish_status();
+        state.publish_events();
+        crate::shell::publish_workspaces(state);
+    })?;
+
+    Ok(())
+}
+
+/// The connector mode for a `mode` setting: that size at the closest
+/// refresh rate, or the highest one when no rate is given. Without a
+/// setting, or when the monitor does not offer the size, the preferred mode.
+fn pick_mode(modes: &[DrmMode], wanted: Option<ModeRequest>, name: &str) -> Option<DrmMode> {
+    let preferred = modes
+        .iter()
+        .find(|m| m.mode_type().contains(ModeTypeFlags::PREFERRED))
+        .or_else(|| modes.first())
+        .copied();
+    let Some(wanted) = wanted else {
+        return preferred;
+    };
+
+    let refresh = |mode: &&DrmMode| f64::from(Mode::from(**mode).refresh) / 1000.0;
+    let sized = modes
+        .iter()
+        .filter(|m| m.size() == (wanted.width, wanted.height));
+    let picked = match wanted.refresh {
+        Some(hz) => sized.min_by(|a, b| (refresh(a) - hz).abs().total_cmp(&(refresh(b) - hz).abs())),
+        None => sized.max_by(|a, b| refresh(a).total_cmp(&refresh(b))),
+    };
+    match picked {
+        Some(mode) => Some(*mode),
+        None => {
+            warn!("Output {name} does not offer mode {wanted}, using its preferred mode");
+            preferred
+        }
+    }
+}
+
+/// Connector name in the usual "<interface>-<id>" form (e.g. "HDMI-A-1")
+fn connector_name(info: &connector::Info) -> String {
+    format!("{}-{}", info.interface().as_str(), info.interface_id())
+}
+
+/// The connector's EDID blob, if the driver exposes one
+fn read_edid(fd: &DrmDeviceFd, info: &connector::Info) -> Option<Vec<u8>> {
+    let properties = fd.get_properties(info.handle()).ok()?;
+    properties.into_iter().find_map(|(handle, value)| {
+        let property = fd.get_property(handle).ok()?;
+        if property.name().to_str() != Ok("EDID") {
+            return None;
+        }
+        let blob = property.value_type().convert_value(value).as_blob()?;
+        fd.get_property_blob(blob).ok()
+    })
+}
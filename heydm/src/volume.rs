@@ -0,0 +1,128 @@
+// =============================================================================
+// heyDM — Volume Control
+//
+// Reads and changes the default sink's volume through `pactl`, which talks
+// to PulseAudio and to PipeWire (pipewire-pulse) alike. The panel shows the
+// current level; scrolling over it changes the volume in VOLUME_STEP steps,
+// clicking it toggles mute, and the XF86AudioRaiseVolume / LowerVolume /
+// Mute keys do the same (see input.rs).
+//
+// pactl runs on the "heydm-volume" thread; after each change the new state
+// goes back through the panel's update channel, like the values the poller
+// reads (see poller.rs). The poller also re-reads the volume every few
+// seconds to follow changes made by other programs.
+// =============================================================================
+
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+
+use tracing::{debug, warn};
+
+use crate::poller::PanelUpdate;
+
+/// Percent added or removed per key press or scroll step
+pub const VOLUME_STEP: i32 = 5;
+
+/// Highest volume reachable from the panel and the volume keys
+const MAX_VOLUME: i32 = 100;
+
+const SINK: &str = "@DEFAULT_SINK@";
+
+/// Volume of the default sink; `percent` is -1 without a sound server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeState {
+    pub percent: i32,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum VolumeCommand {
+    Change(i32),
+    ToggleMute,
+}
+
+/// Handle to the volume thread
+pub struct VolumeControl {
+    commands: Sender<VolumeCommand>,
+}
+
+impl VolumeControl {
+    /// Start the volume thread; the state after every change is sent to
+    /// `updates`
+    pub fn start(updates: calloop::channel::Sender<PanelUpdate>) -> Self {
+        let (commands, receiver) = mpsc::channel::<VolumeCommand>();
+        let spawned = std::thread::Builder::new()
+            .name("heydm-volume".to_string())
+            .spawn(move || {
+                for command in receiver {
+                    let current = read();
+                    if current.percent < 0 {
+                        warn!("Cannot change the volume: no sound server");
+                        continue;
+                    }
+                    let result = match command {
+                        VolumeCommand::Change(delta) => {
+                            let percent = (current.percent + delta).clamp(0, MAX_VOLUME);
+                            pactl(&["set-sink-volume", SINK, &format!("{percent}%")])
+                        }
+                        VolumeCommand::ToggleMute => pactl(&["set-sink-mute", SINK, "toggle"]),
+                    };
+                    if let Err(e) = result {
+                        warn!("Changing the volume failed: {e}");
+                    }
+                    if updates.send(PanelUpdate::Volume(read())).is_err() {
+                        return;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start volume thread: {e}");
+        }
+        Self { commands }
+    }
+
+    /// Without the volume thread (greeter mode); changes are dropped
+    pub fn disabled() -> Self {
+        Self {
+            commands: mpsc::channel().0,
+        }
+    }
+
+    /// Raise (positive) or lower (negative) the volume by `delta` percent
+    pub fn change(&self, delta: i32) {
+        let _ = self.commands.send(VolumeCommand::Change(delta));
+    }
+
+    pub fn toggle_mute(&self) {
+        let _ = self.commands.send(VolumeCommand::ToggleMute);
+    }
+}
+
+/// Current state of the default sink
+pub fn read() -> VolumeState {
+    // "Volume: front-left: 32768 /  50% / -18.06 dB,   front-right: ..."
+    let percent = pactl(&["get-sink-volume", SINK])
+        .ok()
+        .and_then(|output| {
+            let (before, _) = output.split_once('%')?;
+            before.rsplit(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+        })
+        .unwrap_or(-1);
+    // "Mute: yes"
+    let muted = pactl(&["get-sink-mute", SINK]).is_ok_and(|output| output.trim().ends_with("yes"));
+    VolumeState { percent, muted }
+}
+
+/// Run pactl and return its output
+fn pactl(args: &[&str]) -> Result<String, String> {
+    debug!("pactl {}", args.join(" "));
+    let mut command = Command::new("pactl");
+    command.args(args);
+    let output = crate::launch::unblock_signals(&mut command)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
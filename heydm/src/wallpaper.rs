@@ -0,0 +1,371 @@
+// =============================================================================
+// heyDM — Wallpaper & Dynamic Accent
+//
+// The wallpaper is set in the config file or at runtime over IPC
+// (`wallpaper <path>`, `wallpaper mode <mode>`):
+//
+//     [appearance]
+//     wallpaper = ~/Pictures/forest.jpg
+//     wallpaper_mode = fill    # fill | fit | center | tile
+//     dynamic_accent = true    # take the accent color from the wallpaper
+//     accent = #3584e4         # fixed accent otherwise (default: crimson)
+//     color_scheme = dark      # dark | light, exported to apps
+//     icon_theme = Papirus     # launcher icons (see icons.rs)
+//
+// An output can have its own wallpaper and mode in its `[output "name"]`
+// section, or at runtime with `wallpaper output <connector> <path>`; the
+// rest use the one from [appearance].
+//
+//   fill    scale to cover the output, cropping the overflow (default)
+//   fit     scale to fit inside the output, bars around it
+//   center  unscaled in the middle, cropped when larger than the output
+//   tile    unscaled, repeated from the top-left corner
+//
+// Decoding, scaling and accent extraction run on a worker thread; the result
+// comes back through a calloop channel. With dynamic_accent the extracted
+// color replaces the configured accent (focused borders, panel, selections)
+// and is published through the Settings portal (see portal.rs).
+// =============================================================================
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use calloop::channel::Sender;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use smithay::utils::{Physical, Size};
+use tracing::{info, warn};
+
+use crate::accent;
+use crate::texture::{Image, PixelBuffer};
+
+/// Edge length of the copy the accent color is computed from
+const ACCENT_SAMPLE_SIZE: u32 = 64;
+
+/// Accepted `color_scheme` values
+pub const COLOR_SCHEMES: &[&str] = &["dark", "light"];
+
+/// Whether apps should prefer a dark or a light style
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// Accepted `wallpaper_mode` values
+pub const WALLPAPER_MODES: &[&str] = &["fill", "fit", "center", "tile"];
+
+/// How the wallpaper image is laid out on the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WallpaperMode {
+    #[default]
+    Fill,
+    Fit,
+    Center,
+    Tile,
+}
+
+impl WallpaperMode {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "fill" => Ok(Self::Fill),
+            "fit" => Ok(Self::Fit),
+            "center" => Ok(Self::Center),
+            "tile" => Ok(Self::Tile),
+            _ => Err(format!("invalid wallpaper mode '{value}'")),
+        }
+    }
+}
+
+/// `[appearance]` settings from the config file
+#[derive(Debug, Clone)]
+pub struct AppearanceConfig {
+    pub wallpaper: Option<PathBuf>,
+    pub wallpaper_mode: WallpaperMode,
+    pub dynamic_accent: bool,
+    /// Fixed accent color; used when there is no wallpaper accent
+    pub accent: Option<[f32; 4]>,
+    pub color_scheme: ColorScheme,
+    /// Icon theme for application icons (hicolor when unset)
+    pub icon_theme: Option<String>,
+}
+
+impl Default for AppearanceConfig {
+    fn default() -> Self {
+        Self {
+            wallpaper: None,
+            wallpaper_mode: WallpaperMode::Fill,
+            dynamic_accent: true,
+            accent: None,
+            color_scheme: ColorScheme::Dark,
+            icon_theme: None,
+        }
+    }
+}
+
+impl AppearanceConfig {
+    /// Set a key from the config file. Values are expected to have passed
+    /// schema validation; anything else is rejected.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "wallpaper" => self.wallpaper = Some(expand_home(value)),
+            "wallpaper_mode" => self.wallpaper_mode = WallpaperMode::parse(value)?,
+            "icon_theme" => self.icon_theme = Some(value.to_string()),
+            "accent" => {
+                self.accent = Some(
+                    parse_hex_color(value)
+                        .ok_or_else(|| format!("invalid accent '{value}' (expected #rrggbb)"))?,
+                )
+            }
+            "color_scheme" => {
+                self.color_scheme = match value {
+                    "dark" => ColorScheme::Dark,
+                    "light" => ColorScheme::Light,
+                    _ => return Err(format!("invalid color_scheme '{value}'")),
+                }
+            }
+            "dynamic_accent" => {
+                self.dynamic_accent = match value {
+                    "true" | "yes" | "on" => true,
+                    "false" | "no" | "off" => false,
+                    _ => return Err(format!("expected true or false, got '{value}'")),
+                }
+            }
+            _ => return Err(format!("unknown appearance setting '{key}'")),
+        }
+        Ok(())
+    }
+}
+
+/// An output's own wallpaper; unset fields fall back to [appearance]
+#[derive(Debug, Clone, Default)]
+pub struct OutputWallpaper {
+    pub path: Option<PathBuf>,
+    pub mode: Option<WallpaperMode>,
+}
+
+/// A decoded wallpaper, laid out for the output
+#[derive(Debug)]
+pub struct LoadedWallpaper {
+    /// Load request this belongs to (older results are dropped)
+    generation: u64,
+    path: PathBuf,
+    image: Image,
+    /// Where the image goes on the output (fit and center leave bars)
+    offset: (i32, i32),
+    accent: Option<[f32; 4]>,
+}
+
+/// Current wallpaper and its accent color
+pub struct Wallpaper {
+    path: Option<PathBuf>,
+    mode: WallpaperMode,
+    /// Per-output choices by connector name
+    outputs: HashMap<String, OutputWallpaper>,
+    /// Connector of the output being drawn
+    output: Option<String>,
+    image: Option<Image>,
+    offset: (i32, i32),
+    accent: Option<[f32; 4]>,
+    /// Bumped on every load so a slow decode cannot replace a newer one
+    generation: u64,
+    results: Sender<Result<LoadedWallpaper, String>>,
+}
+
+#[allow(dead_code)]
+impl Wallpaper {
+    /// No wallpaper yet; decoded images are reported to `results`
+    pub fn new(
+        path: Option<PathBuf>,
+        mode: WallpaperMode,
+        results: Sender<Result<LoadedWallpaper, String>>,
+    ) -> Self {
+        Self {
+            path,
+            mode,
+            outputs: HashMap::new(),
+            output: None,
+            image: None,
+            offset: (0, 0),
+            accent: None,
+            generation: 0,
+            results,
+        }
+    }
+
+    /// Switch to a new wallpaper for outputs without their own (None =
+    /// plain background)
+    pub fn set(&mut self, path: Option<PathBuf>, output_size: Size<i32, Physical>) {
+        self.path = path;
+        self.reload(output_size);
+    }
+
+    /// Change the mode for outputs without their own
+    pub fn set_mode(&mut self, mode: WallpaperMode, output_size: Size<i32, Physical>) {
+        self.mode = mode;
+        self.reload(output_size);
+    }
+
+    /// Give an output its own wallpaper, or with None fields let it use the
+    /// default again
+    pub fn set_output(&mut self, connector: &str, wallpaper: OutputWallpaper, output_size: Size<i32, Physical>) {
+        if wallpaper.path.is_none() && wallpaper.mode.is_none() {
+            self.outputs.remove(connector);
+        } else {
+            self.outputs.insert(connector.to_string(), wallpaper);
+        }
+        if self.output.as_deref() == Some(connector) {
+            self.reload(output_size);
+        }
+    }
+
+    /// The output being drawn changed
+    pub fn select_output(&mut self, connector: &str, output_size: Size<i32, Physical>) {
+        self.output = Some(connector.to_string());
+        self.reload(output_size);
+    }
+
+    /// Path and mode for the output being drawn
+    fn current(&self) -> (Option<PathBuf>, WallpaperMode) {
+        let own = self.output.as_ref().and_then(|output| self.outputs.get(output));
+        (
+            own.and_then(|own| own.path.clone()).or_else(|| self.path.clone()),
+            own.and_then(|own| own.mode).unwrap_or(self.mode),
+        )
+    }
+
+    /// Decode the current wallpaper again for a (new) output size
+    pub fn reload(&mut self, output_size: Size<i32, Physical>) {
+        let (path, mode) = self.current();
+        let Some(path) = path else {
+            self.generation += 1;
+            self.image = None;
+            self.accent = None;
+            return;
+        };
+        if output_size.w <= 0 || output_size.h <= 0 {
+            return;
+        }
+
+        self.generation += 1;
+        let generation = self.generation;
+        let results = self.results.clone();
+        let (width, height) = (output_size.w as u32, output_size.h as u32);
+        let spawned = std::thread::Builder::new()
+            .name("heydm-wallpaper".to_string())
+            .spawn(move || {
+                let result = decode(&path, width, height, mode).map(|(image, offset, accent)| LoadedWallpaper {
+                    generation,
+                    path,
+                    image,
+                    offset,
+                    accent,
+                });
+                let _ = results.send(result);
+            });
+        if let Err(e) = spawned {
+            warn!("Failed to start wallpaper worker: {e}");
+        }
+    }
+
+    /// Take a finished load. Returns whether it was applied.
+    pub fn finish(&mut self, loaded: LoadedWallpaper) -> bool {
+        if loaded.generation != self.generation {
+            return false;
+        }
+        info!(
+            "Wallpaper {} loaded, accent {:?}",
+            loaded.path.display(),
+            loaded.accent
+        );
+        self.image = Some(loaded.image);
+        self.offset = loaded.offset;
+        self.accent = loaded.accent;
+        true
+    }
+
+    /// The wallpaper laid out for the output and its top-left corner, once
+    /// decoded
+    pub fn image(&self) -> Option<(&Image, (i32, i32))> {
+        self.image.as_ref().map(|image| (image, self.offset))
+    }
+
+    /// Accent color extracted from the wallpaper
+    pub fn accent(&self) -> Option<[f32; 4]> {
+        self.accent
+    }
+}
+
+/// Decode an image file, lay it out on a width × height output and extract
+/// its accent color
+fn decode(
+    path: &Path,
+    width: u32,
+    height: u32,
+    mode: WallpaperMode,
+) -> Result<(Image, (i32, i32), Option<[f32; 4]>), String> {
+    let decoded = image::open(path).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let sample: Vec<[u8; 3]> = decoded
+        .thumbnail(ACCENT_SAMPLE_SIZE, ACCENT_SAMPLE_SIZE)
+        .to_rgb8()
+        .pixels()
+        .map(|p| p.0)
+        .collect();
+    let accent = accent::dominant_color(&sample).map(|[r, g, b]| [r, g, b, 1.0]);
+
+    let laid_out = match mode {
+        WallpaperMode::Fill => decoded.resize_to_fill(width, height, FilterType::Triangle).to_rgba8(),
+        WallpaperMode::Fit => decoded.resize(width, height, FilterType::Triangle).to_rgba8(),
+        WallpaperMode::Center => {
+            let (w, h) = (decoded.width().min(width), decoded.height().min(height));
+            decoded
+                .crop_imm((decoded.width() - w) / 2, (decoded.height() - h) / 2, w, h)
+                .to_rgba8()
+        }
+        WallpaperMode::Tile => {
+            let tile = decoded.to_rgba8();
+            let mut canvas = RgbaImage::new(width, height);
+            for y in (0..height).step_by(tile.height().max(1) as usize) {
+                for x in (0..width).step_by(tile.width().max(1) as usize) {
+                    imageops::replace(&mut canvas, &tile, x as i64, y as i64);
+                }
+            }
+            canvas
+        }
+    };
+    // Centered; fill and tile cover the whole output anyway
+    let offset = (
+        (width.saturating_sub(laid_out.width()) / 2) as i32,
+        (height.saturating_sub(laid_out.height()) / 2) as i32,
+    );
+    let image = Image::new(PixelBuffer::from_rgba(
+        laid_out.width(),
+        laid_out.height(),
+        laid_out.as_raw(),
+    ));
+    Ok((image, offset, accent))
+}
+
+/// "#rrggbb" as an opaque color
+fn parse_hex_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let [r, g, b] = [channel(0)?, channel(2)?, channel(4)?];
+    Some([r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0])
+}
+
+/// Expand a leading `~/` to $HOME
+pub fn expand_home(value: &str) -> PathBuf {
+    match value.strip_prefix("~/") {
+        Some(rest) => {
+            let home = std::env::var("HOME").unwrap_or_default();
+            PathBuf::from(home).join(rest)
+        }
+        None => PathBuf::from(value),
+    }
+}
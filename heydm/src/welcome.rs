@@ -0,0 +1,53 @@
+// =============================================================================
+// heyDM — First-Login Welcome
+//
+// The first time a user logs in — no config file and no completion marker —
+// heyDM starts `hey-welcome`, which lets them pick a keyboard layout, a dark
+// or light style and an accent color, shows the most important keybindings
+// and writes the choices into the config file (see config.rs), then has
+// heyDM reload it through heyctl. Whether they finish or skip it, the wizard
+// leaves a marker so it never comes back:
+//
+//     $XDG_STATE_HOME/heydm/welcome-done   (~/.local/state/heydm/welcome-done)
+//
+// Deleting the marker and the config file brings the wizard back.
+// =============================================================================
+
+use std::path::PathBuf;
+
+use calloop::LoopHandle;
+use tracing::info;
+
+use crate::config::Config;
+use crate::state::HeyDM;
+
+/// The wizard binary
+const WELCOME: &str = "hey-welcome";
+
+/// Where hey-welcome records that it ran
+pub fn marker_path() -> PathBuf {
+    let base = std::env::var("XDG_STATE_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = std::env::var("HOME").unwrap_or_default();
+            PathBuf::from(home).join(".local").join("state")
+        });
+    base.join("heydm").join("welcome-done")
+}
+
+/// A user without a config who has not been welcomed yet
+pub fn should_run() -> bool {
+    !Config::path().exists() && !marker_path().exists()
+}
+
+/// Start the wizard once the event loop runs, when this is a first login
+pub fn start_if_needed(loop_handle: &LoopHandle<'static, HeyDM>) {
+    if !should_run() {
+        return;
+    }
+    info!("First login — starting {WELCOME}");
+    // Idle, so clients see WAYLAND_DISPLAY set by the backend
+    loop_handle.insert_idle(|state| state.launch(WELCOME));
+}
@@ -3,47 +3,283 @@
 //
 // Manages all toplevel windows: tracking, positioning, focusing, moving,
 // resizing, tiling, and fullscreen. Maintains a stack-ordered list of
-// windows and a cursor position.
+// windows, the workspace each one lives on, and a cursor position.
+// Workspaces with automatic tiling enabled are arranged by tiling.rs.
+// Dragging a window to a screen edge snaps it to that half, to a corner
+// snaps it to a quarter and to the top edge maximizes it; the renderer
+// previews the target while the drag is in progress. One window can be
+// the scratchpad (see scratchpad.rs): it floats above every workspace and
+// slides down from the top edge when shown.
+//
+// Positions and sizes are in output pixels. Clients work in logical units
+// of the output scale (fractional-scale-v1, see state.rs): sizes in
+// configures are divided by it, committed sizes and pointer positions are
+// converted at the same boundary.
+//
+// The first output has its top-left corner at (0, 0) and holds the panel;
+// further outputs cover areas next to it (set by the backend), and the
+// windows of a workspace are laid out in the area of the output it is
+// shown on. When a workspace changes outputs or an output moves, its
+// windows move along.
+//
+// Windows are reached only through the Toplevel trait (toplevel.rs), so the
+// manager can be driven with fake windows; serialize_layout() dumps its
+// whole state for debugging (`heyctl layout`).
 // =============================================================================
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::utils::{Logical, Physical, Point, Rectangle, Size};
+use smithay::reexports::wayland_server::{DisplayHandle, Resource};
+use smithay::utils::{Logical, Physical, Point, Rectangle, Size, Transform};
+use smithay::wayland::compositor::{send_surface_state, with_surface_tree_downward, TraversalAction};
+use smithay::wayland::fractional_scale::with_fractional_scale;
 use smithay::wayland::shell::xdg::ToplevelSurface;
 
 use tracing::{debug, info};
 
+use crate::config::Config;
+use crate::theme::UiMetrics;
+use crate::toplevel::Toplevel;
+use crate::tiling::{TilingConfig, WorkspaceLayout};
+use crate::workspace::{Workspaces, WORKSPACE_COUNT};
+
+/// Distance from a screen edge at which a dragged window snaps
+const SNAP_EDGE: f64 = 16.0;
+
+/// Height of the corner zones along the left and right edges
+const SNAP_CORNER: f64 = 120.0;
+
+/// How long the scratchpad takes to slide in or out, in seconds
+const SCRATCHPAD_SLIDE: f64 = 0.18;
+/// Scratchpad width and height as fractions of the work area
+const SCRATCHPAD_SIZE: (f64, f64) = (0.8, 0.5);
+
+/// Half or quarter of the work area a tiled window occupies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileSide {
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl TileSide {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::TopLeft => "top_left",
+            Self::TopRight => "top_right",
+            Self::BottomLeft => "bottom_left",
+            Self::BottomRight => "bottom_right",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Self::Left,
+            Self::Right,
+            Self::TopLeft,
+            Self::TopRight,
+            Self::BottomLeft,
+            Self::BottomRight,
+        ]
+        .into_iter()
+        .find(|side| side.name() == name)
+    }
+
+    /// Area of the work area this side covers
+    fn rect(self, work_area: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let half_w = work_area.size.w / 2;
+        let half_h = work_area.size.h / 2;
+        let (x, y, w, h) = match self {
+            Self::Left => (0, 0, half_w, work_area.size.h),
+            Self::Right => (half_w, 0, work_area.size.w - half_w, work_area.size.h),
+            Self::TopLeft => (0, 0, half_w, half_h),
+            Self::TopRight => (half_w, 0, work_area.size.w - half_w, half_h),
+            Self::BottomLeft => (0, half_h, half_w, work_area.size.h - half_h),
+            Self::BottomRight => (half_w, half_h, work_area.size.w - half_w, work_area.size.h - half_h),
+        };
+        Rectangle::new(
+            Point::from((work_area.loc.x + x, work_area.loc.y + y)),
+            Size::from((w, h)),
+        )
+    }
+
+    /// Where Super+<arrow> moves a window tiled to this side (None = the
+    /// direction does not apply, e.g. Super+Up on a top quarter)
+    fn step(side: Option<Self>, direction: SnapDirection) -> Option<SnapTarget> {
+        use SnapDirection as D;
+        use TileSide as T;
+
+        let tile = |side| Some(SnapTarget::Tile(side));
+        match (side, direction) {
+            (Some(T::TopRight), D::Left) => tile(T::TopLeft),
+            (Some(T::BottomRight), D::Left) => tile(T::BottomLeft),
+            (_, D::Left) => tile(T::Left),
+            (Some(T::TopLeft), D::Right) => tile(T::TopRight),
+            (Some(T::BottomLeft), D::Right) => tile(T::BottomRight),
+            (_, D::Right) => tile(T::Right),
+            (Some(T::Left), D::Up) => tile(T::TopLeft),
+            (Some(T::Right), D::Up) => tile(T::TopRight),
+            (Some(T::BottomLeft), D::Up) => tile(T::Left),
+            (Some(T::BottomRight), D::Up) => tile(T::Right),
+            (None, D::Up) => Some(SnapTarget::Maximize),
+            (Some(T::Left), D::Down) => tile(T::BottomLeft),
+            (Some(T::Right), D::Down) => tile(T::BottomRight),
+            (Some(T::TopLeft), D::Down) => tile(T::Left),
+            (Some(T::TopRight), D::Down) => tile(T::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Arrow direction of a Super+<arrow> tiling keybinding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Where a window ends up when a drag is released in a snap zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapTarget {
+    Tile(TileSide),
+    Maximize,
+}
+
+/// Where a window is and how it is placed, as saved across restarts
+/// (see restore.rs)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub workspace: usize,
+    /// Floating geometry, also for windows that are not floating right now
+    pub geometry: Rectangle<i32, Logical>,
+    pub fullscreen: bool,
+    pub maximized: bool,
+    pub tiled: Option<TileSide>,
+    /// Pulled out of its workspace's automatic layout
+    pub floating: bool,
+    pub minimized: bool,
+}
+
 /// Represents a single toplevel window in the compositor
 #[derive(Debug, Clone)]
-pub struct WindowElement {
-    /// The XDG toplevel surface
-    toplevel: ToplevelSurface,
+pub struct WindowElement<T: Toplevel = ToplevelSurface> {
+    /// Stable identifier used by IPC (assigned by the window manager)
+    id: u32,
+    /// The client's window (an XDG toplevel surface)
+    toplevel: T,
     /// Position of the window in output coordinates
     position: Point<i32, Logical>,
     /// Size of the window
     size: Size<i32, Logical>,
     /// Whether the window is fullscreen
     fullscreen: bool,
-    /// Saved geometry before fullscreen (for restore)
+    /// Whether the window fills the work area
+    maximized: bool,
+    /// Half of the work area the window is tiled to
+    tiled: Option<TileSide>,
+    /// Placed by the workspace's automatic tiling layout
+    auto_tiled: bool,
+    /// Pulled out of the automatic layout by the user (drag or resize)
+    floating: bool,
+    /// Last floating geometry, restored when the window floats again
     saved_geometry: Option<Rectangle<i32, Logical>>,
+    /// Workspace the window lives on (1-based)
+    workspace: usize,
+    /// Whether the client has committed its first sized buffer
+    mapped: bool,
+    /// Whether the window has keyboard focus
+    activated: bool,
+    /// Whether an interactive resize is in progress
+    resizing: bool,
+    /// Asked for attention (xdg-activation) and not focused since
+    urgent: bool,
+    /// Hidden from the screen until restored from the taskbar
+    minimized: bool,
+    /// The scratchpad window; hidden by minimizing, but not on the taskbar
+    scratchpad: bool,
+    /// Output scale the client renders at; sizes exchanged with the client
+    /// are divided by it
+    scale: f64,
 }
 
-impl WindowElement {
+impl<T: Toplevel> WindowElement<T> {
     /// Create a new window element from an XDG toplevel surface
-    pub fn new(toplevel: ToplevelSurface) -> Self {
+    pub fn new(toplevel: T) -> Self {
         Self {
+            id: 0,
             toplevel,
             position: Point::from((100, 100)),
             size: Size::from((800, 600)),
             fullscreen: false,
+            maximized: false,
+            tiled: None,
+            auto_tiled: false,
+            floating: false,
             saved_geometry: None,
+            workspace: 1,
+            mapped: false,
+            activated: false,
+            resizing: false,
+            urgent: false,
+            minimized: false,
+            scratchpad: false,
+            scale: 1.0,
         }
     }
 
+    /// Whether the window covers the whole output
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Whether the window is minimized to the taskbar
+    pub fn is_minimized(&self) -> bool {
+        self.minimized
+    }
+
+    /// Whether the window is the scratchpad
+    pub fn is_scratchpad(&self) -> bool {
+        self.scratchpad
+    }
+
+    /// Whether the window asked for attention since it was last focused
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
     /// Get the XDG toplevel surface
-    pub fn toplevel(&self) -> &ToplevelSurface {
+    pub fn toplevel(&self) -> &T {
         &self.toplevel
     }
 
+    /// Stable window identifier
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Window title and app id as set by the client
+    pub fn title_and_app_id(&self) -> (Option<String>, Option<String>) {
+        self.toplevel.title_and_app_id()
+    }
+
+    /// pid and uid of the Wayland client owning the window
+    pub fn owner(&self, display_handle: &DisplayHandle) -> Option<(i32, u32)> {
+        let client = self.toplevel.surface()?.client()?;
+        let credentials = client.get_credentials(display_handle).ok()?;
+        Some((credentials.pid, credentials.uid))
+    }
+
     /// Get the window's bounding rectangle
     pub fn geometry(&self) -> Rectangle<i32, Logical> {
         Rectangle::new(self.position, self.size)
@@ -59,25 +295,131 @@ impl WindowElement {
         self.size = size;
     }
 
+    /// Ask the client to resize; the size is applied optimistically and
+    /// corrected by the next commit if the client picks something else.
+    /// Floating and tiled sizes respect the client's min/max size hints.
+    pub fn request_size(&mut self, size: Size<i32, Logical>) {
+        self.size = if self.fullscreen {
+            size
+        } else {
+            self.constrain_size(size)
+        };
+        self.configure();
+    }
+
+    /// Send a configure with the current size and xdg_toplevel states
+    pub fn configure(&self) {
+        use xdg_toplevel::State;
+
+        let size = self.to_client(self.size);
+        let constrained = self.maximized || self.tiled.is_some() || self.auto_tiled;
+        let states = [
+            (State::Fullscreen, self.fullscreen),
+            (State::Maximized, self.maximized && !self.fullscreen),
+            (State::Activated, self.activated),
+            (State::Resizing, self.resizing),
+            // Tiled and maximized windows are constrained on every edge
+            (State::TiledLeft, constrained),
+            (State::TiledRight, constrained),
+            (State::TiledTop, constrained),
+            (State::TiledBottom, constrained),
+        ];
+        self.toplevel.configure(size, &states);
+    }
+
+    /// The client's minimum and maximum size (0 = unconstrained)
+    pub fn size_hints(&self) -> (Size<i32, Logical>, Size<i32, Logical>) {
+        let (min, max) = self.toplevel.size_hints();
+        (self.from_client(min), self.from_client(max))
+    }
+
+    /// A size from the client (in its logical units) in output pixels
+    fn from_client(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        size.to_f64().upscale(self.scale).to_i32_round()
+    }
+
+    /// A size in output pixels in the client's logical units
+    fn to_client(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        size.to_f64().downscale(self.scale).to_i32_round()
+    }
+
+    /// Clamp a size to the client's min/max hints
+    fn constrain_size(&self, size: Size<i32, Logical>) -> Size<i32, Logical> {
+        let (min, max) = self.size_hints();
+        let clamp = |value: i32, min: i32, max: i32| {
+            let value = if min > 0 { value.max(min) } else { value };
+            if max > 0 {
+                value.min(max.max(min))
+            } else {
+                value
+            }
+        };
+        Size::from((clamp(size.w, min.w, max.w), clamp(size.h, min.h, max.h)))
+    }
+
+    /// Whether the window is placed freely (not fullscreen, maximized or tiled)
+    pub fn is_floating(&self) -> bool {
+        !self.fullscreen && !self.maximized && self.tiled.is_none() && !self.auto_tiled
+    }
+
+    /// Whether the automatic layout of its workspace should place the window
+    fn wants_auto_tiling(&self) -> bool {
+        !self.fullscreen
+            && !self.maximized
+            && self.tiled.is_none()
+            && !self.floating
+            && !self.minimized
+            && !self.scratchpad
+    }
+
+    /// Remember the floating geometry before the window leaves floating mode
+    fn save_floating_geometry(&mut self) {
+        if self.is_floating() {
+            self.saved_geometry = Some(self.geometry());
+        }
+    }
+
+    /// Window size in output pixels from the surface's committed state
+    fn committed_size(&self) -> Option<Size<i32, Logical>> {
+        self.toplevel.committed_size().map(|size| self.from_client(size))
+    }
+
+    /// Workspace the window lives on
+    pub fn workspace(&self) -> usize {
+        self.workspace
+    }
+
     /// Check if a point is inside this window
     pub fn contains_point(&self, point: (f64, f64)) -> bool {
-        let rect = self.geometry();
-        point.0 >= rect.loc.x as f64
-            && point.0 <= (rect.loc.x + rect.size.w) as f64
-            && point.1 >= rect.loc.y as f64
-            && point.1 <= (rect.loc.y + rect.size.h) as f64
+        area_contains(self.geometry(), point)
     }
 
     /// Get the WlSurface associated with this window (clones the Arc-backed handle)
     pub fn wl_surface(&self) -> Option<WlSurface> {
-        Some(self.toplevel.wl_surface().clone())
+        self.toplevel.surface().cloned()
     }
 }
 
+/// Tell a surface and its subsurfaces the scale to render at: exactly
+/// through fractional-scale-v1, rounded up as the preferred buffer scale for
+/// clients without it
+pub fn send_scale(surface: &WlSurface, scale: f64) {
+    with_surface_tree_downward(
+        surface,
+        (),
+        |_, _, _| TraversalAction::DoChildren(()),
+        |surface, states, _| {
+            with_fractional_scale(states, |fractional| fractional.set_preferred_scale(scale));
+            send_surface_state(surface, states, scale.ceil() as i32, Transform::Normal);
+        },
+        |_, _, _| true,
+    );
+}
+
 /// The window manager tracks all windows and manages focus, layout, etc.
-pub struct WindowManager {
+pub struct WindowManager<T: Toplevel = ToplevelSurface> {
     /// All managed windows, in stack order (last = topmost)
-    windows: Vec<WindowElement>,
+    windows: Vec<WindowElement<T>>,
     /// Index of the currently focused window (None if no windows)
     focused: Option<usize>,
     /// Current cursor position
@@ -86,10 +428,31 @@ pub struct WindowManager {
     grab: Option<GrabState>,
     /// Panel height (reserved space at top)
     panel_height: i32,
+    /// Space reserved by shell components below the panel, at the bottom,
+    /// left and right (see shell.rs)
+    reserved_edges: [i32; 4],
+    /// Workspace ↔ output assignment
+    workspaces: Workspaces,
+    /// Outputs besides the first one, with the area each covers
+    output_areas: HashMap<String, Rectangle<i32, Logical>>,
+    /// Snap zone under the cursor during a move grab
+    snap: Option<SnapTarget>,
+    /// Greeter mode: every window is fullscreen
+    kiosk: bool,
+    /// Identifier handed to the next new window
+    next_window_id: u32,
+    /// `[tiling]` defaults (gap, layout for newly tiled workspaces)
+    tiling: TilingConfig,
+    /// Workspaces with automatic tiling enabled
+    layouts: HashMap<usize, WorkspaceLayout>,
+    /// Scale of the output, handed to every window
+    scale: f64,
+    /// The scratchpad sliding in (true) or out, and since when
+    scratchpad_slide: Option<(Instant, bool)>,
 }
 
 /// State for an active pointer grab (move or resize)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 struct GrabState {
     /// Index of the window being grabbed
     window_index: usize,
@@ -111,31 +474,75 @@ enum GrabKind {
 }
 
 #[allow(dead_code)]
-impl WindowManager {
+impl<T: Toplevel> WindowManager<T> {
     /// Create a new empty window manager
-    pub fn new() -> Self {
+    pub fn new(config: &Config) -> Self {
+        let layouts = if config.tiling.enabled {
+            (1..=WORKSPACE_COUNT)
+                .map(|ws| (ws, config.tiling.workspace_layout()))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
         Self {
             windows: Vec::new(),
             focused: None,
             cursor_pos: (0.0, 0.0),
             grab: None,
-            panel_height: 32,
+            panel_height: UiMetrics::new(&config.theme).panel_bottom(),
+            reserved_edges: [0; 4],
+            workspaces: Workspaces::new(config.workspace_outputs.clone()),
+            output_areas: HashMap::new(),
+            snap: None,
+            kiosk: false,
+            next_window_id: 1,
+            tiling: config.tiling.clone(),
+            layouts,
+            scale: 1.0,
+            scratchpad_slide: None,
         }
     }
 
-    /// Add a new window to the manager
+    /// Add a new window to the manager. Without `focus` it opens right
+    /// below the focused window, flagged urgent, instead of taking over.
     pub fn add_window(
         &mut self,
-        mut window: WindowElement,
+        mut window: WindowElement<T>,
+        focus: bool,
         output_size: &Size<i32, Physical>,
     ) {
-        // Center the window on screen, below the panel
-        let x = (output_size.w - window.size.w) / 2;
-        let y = self.panel_height + (output_size.h - self.panel_height - window.size.h) / 2;
-        window.set_position(Point::from((x.max(0), y.max(self.panel_height))));
+        // The default size is meant in the client's units
+        window.scale = self.scale;
+        window.size = window.from_client(window.size);
 
-        self.windows.push(window);
-        self.focused = Some(self.windows.len() - 1);
+        // Center the window in the work area, below the panel
+        window.workspace = self.workspaces.active_workspace();
+        let area = self.workspace_area(window.workspace, output_size);
+        window.set_position(centered(area, window.size));
+        window.id = self.next_window_id;
+        self.next_window_id += 1;
+
+        window.fullscreen = self.kiosk;
+
+        let ws = window.workspace;
+        let idx = match self.focused {
+            Some(focused) if !focus && !self.kiosk => {
+                window.urgent = true;
+                self.windows.insert(focused, window);
+                self.focused = Some(focused + 1);
+                focused
+            }
+            _ => {
+                self.windows.push(window);
+                self.focused = Some(self.windows.len() - 1);
+                self.windows.len() - 1
+            }
+        };
+        if self.kiosk {
+            self.layout_window(idx, output_size);
+        }
+        self.arrange_workspace(ws, output_size);
 
         info!(
             "Window added (total: {}), focused: {:?}",
@@ -144,25 +551,52 @@ impl WindowManager {
         );
     }
 
+    /// The output scale or the UI metrics changed: windows are told their
+    /// size in the new client units, and the work area follows the panel
+    pub fn set_scale(&mut self, scale: f64, ui: &UiMetrics, output_size: &Size<i32, Physical>) {
+        self.panel_height = ui.panel_bottom();
+        if scale != self.scale {
+            self.scale = scale;
+            for window in &mut self.windows {
+                window.scale = scale;
+                window.configure();
+            }
+        }
+        self.arrange_all(output_size);
+    }
+
+    /// A position in output pixels in the clients' logical units (pointer
+    /// events are delivered in those)
+    pub fn client_point(&self, pos: (f64, f64)) -> Point<f64, Logical> {
+        Point::from((pos.0 / self.scale, pos.1 / self.scale))
+    }
+
+    /// Show every window fullscreen (greeter mode)
+    pub fn set_kiosk(&mut self, kiosk: bool) {
+        self.kiosk = kiosk;
+    }
+
     /// Remove a window by its toplevel surface
-    pub fn remove_window(&mut self, surface: &ToplevelSurface) {
+    pub fn remove_window(&mut self, surface: &T, output_size: &Size<i32, Physical>) {
         if let Some(idx) = self
             .windows
             .iter()
             .position(|w| &w.toplevel == surface)
         {
-            self.windows.remove(idx);
+            let window = self.windows.remove(idx);
+            // The grab refers to windows by index
+            if self.grab.as_ref().is_some_and(|g| g.window_index >= idx) {
+                self.grab = None;
+                self.snap = None;
+            }
 
             // Update focus
-            if self.windows.is_empty() {
-                self.focused = None;
-            } else if let Some(focused) = self.focused {
-                if focused >= self.windows.len() {
-                    self.focused = Some(self.windows.len() - 1);
-                } else if focused > idx {
-                    self.focused = Some(focused - 1);
-                }
+            match self.focused {
+                Some(focused) if focused == idx => self.focus_topmost_visible(),
+                Some(focused) if focused > idx => self.focused = Some(focused - 1),
+                _ => {}
             }
+            self.arrange_workspace(window.workspace, output_size);
 
             info!(
                 "Window removed (total: {}), focused: {:?}",
@@ -172,122 +606,884 @@ impl WindowManager {
         }
     }
 
-    /// Handle a surface commit (update window geometry)
-    pub fn handle_commit(&mut self, _surface: &WlSurface) {
-        // Update internal geometry tracking based on committed state
-        // In a full implementation, this would read the surface's committed size
+    /// Handle a surface commit: adopt the size the client committed. A
+    /// window's first sized commit re-centers it, since it was placed using
+    /// a guessed size. Returns true when this commit mapped the window.
+    pub fn handle_commit(
+        &mut self,
+        surface: &WlSurface,
+        output_size: &Size<i32, Physical>,
+    ) -> bool {
+        let Some(idx) = self
+            .windows
+            .iter()
+            .position(|w| w.toplevel.surface() == Some(surface))
+        else {
+            return false;
+        };
+        let area = self.workspace_area(self.windows[idx].workspace, output_size);
+        let window = &mut self.windows[idx];
+        let Some(size) = window.committed_size() else {
+            return false;
+        };
+
+        if !window.mapped {
+            window.mapped = true;
+            window.size = size;
+            if window.auto_tiled || window.fullscreen {
+                debug!("Placed window mapped at {}x{}", size.w, size.h);
+                return true;
+            }
+            window.set_position(centered(area, size));
+            debug!("Window mapped at {}x{}", size.w, size.h);
+            return true;
+        } else if window.size != size {
+            debug!(
+                "Window resized by client: {}x{} -> {}x{}",
+                window.size.w, window.size.h, size.w, size.h
+            );
+            window.size = size;
+        }
+        false
     }
 
     /// Get all windows in stack order
-    pub fn windows(&self) -> &[WindowElement] {
+    pub fn windows(&self) -> &[WindowElement<T>] {
         &self.windows
     }
 
+    /// Look up a window by its surface
+    pub fn window_for_surface(&self, surface: &WlSurface) -> Option<&WindowElement<T>> {
+        self.windows.iter().find(|w| w.toplevel.surface() == Some(surface))
+    }
+
+    /// Look up a window by its identifier
+    pub fn window_by_id(&self, id: u32) -> Option<&WindowElement<T>> {
+        self.windows.iter().find(|w| w.id == id)
+    }
+
     /// Get the currently focused window
-    pub fn focused_window(&self) -> Option<&WindowElement> {
+    pub fn focused_window(&self) -> Option<&WindowElement<T>> {
         self.focused.map(|idx| &self.windows[idx])
     }
 
+    /// Whether a window is on screen: not minimized, and its workspace is
+    /// shown on some output
+    pub fn is_window_visible(&self, window: &WindowElement<T>) -> bool {
+        !window.minimized && self.workspaces.is_visible(window.workspace)
+    }
+
+    /// Windows on visible workspaces, in stack order
+    pub fn visible_windows(&self) -> impl Iterator<Item = &WindowElement<T>> {
+        self.windows.iter().filter(|w| self.is_window_visible(w))
+    }
+
+    /// Focus the topmost window on a visible workspace (or nothing)
+    fn focus_topmost_visible(&mut self) {
+        self.focused = self
+            .windows
+            .iter()
+            .rposition(|w| !w.minimized && self.workspaces.is_visible(w.workspace));
+    }
+
+    // ---- Minimize ----
+
+    /// Minimize the focused window (Super+N)
+    pub fn minimize_focused(&mut self, output_size: &Size<i32, Physical>) {
+        if let Some(idx) = self.focused.filter(|idx| *idx < self.windows.len()) {
+            self.minimize_index(idx, output_size);
+        }
+    }
+
+    /// Minimize a window (client request)
+    pub fn minimize(&mut self, surface: &T, output_size: &Size<i32, Physical>) {
+        if let Some(idx) = self.windows.iter().position(|w| &w.toplevel == surface) {
+            self.minimize_index(idx, output_size);
+        }
+    }
+
+    fn minimize_index(&mut self, idx: usize, output_size: &Size<i32, Physical>) {
+        if self.grab.as_ref().is_some_and(|g| g.window_index == idx) {
+            self.end_grab();
+        }
+        let window = &mut self.windows[idx];
+        if window.minimized {
+            return;
+        }
+        window.minimized = true;
+        info!("Window {} minimized", window.id);
+        let ws = window.workspace;
+        if self.focused == Some(idx) {
+            self.focus_topmost_visible();
+        }
+        self.arrange_workspace(ws, output_size);
+    }
+
+    /// Taskbar click: restore a minimized window, minimize the focused one,
+    /// focus any other
+    pub fn taskbar_activate(&mut self, id: u32, output_size: &Size<i32, Physical>) {
+        let Some(idx) = self.windows.iter().position(|w| w.id == id) else {
+            return;
+        };
+        let window = &mut self.windows[idx];
+        if window.minimized {
+            window.minimized = false;
+            info!("Window {id} restored");
+            let ws = window.workspace;
+            self.focus_id(id);
+            self.arrange_workspace(ws, output_size);
+        } else if self.focused == Some(idx) {
+            self.minimize_index(idx, output_size);
+        } else {
+            self.focus_id(id);
+        }
+    }
+
+    // ---- Workspaces ----
+
+    /// Workspace bookkeeping (read-only)
+    pub fn workspaces(&self) -> &Workspaces {
+        &self.workspaces
+    }
+
+    /// Register a newly connected output; `area` places a further output
+    /// next to the first one (None for the first output itself)
+    pub fn output_added(
+        &mut self,
+        name: &str,
+        area: Option<Rectangle<i32, Logical>>,
+        output_size: &Size<i32, Physical>,
+    ) {
+        let before = self.origins();
+        if let Some(area) = area {
+            self.output_areas.insert(name.to_string(), area);
+        }
+        self.workspaces.output_added(name);
+        self.relocate(before, output_size);
+        self.focus_topmost_visible();
+    }
+
+    /// Unregister a disconnected output; its workspaces migrate elsewhere
+    pub fn output_removed(&mut self, name: &str, output_size: &Size<i32, Physical>) {
+        let before = self.origins();
+        self.output_areas.remove(name);
+        self.workspaces.output_removed(name);
+        self.relocate(before, output_size);
+        self.focus_topmost_visible();
+    }
+
+    /// Move a further output to `area` (e.g. its mode changed)
+    pub fn set_output_area(
+        &mut self,
+        name: &str,
+        area: Rectangle<i32, Logical>,
+        output_size: &Size<i32, Physical>,
+    ) {
+        let Some(current) = self.output_areas.get_mut(name) else {
+            return;
+        };
+        if *current == area {
+            return;
+        }
+        let before = self.origins();
+        *current = area;
+        self.relocate(before, output_size);
+    }
+
+    /// Areas of the outputs besides the first one
+    pub fn output_areas(&self) -> impl Iterator<Item = Rectangle<i32, Logical>> + '_ {
+        self.output_areas.values().copied()
+    }
+
+    /// Area of the output a window is shown on
+    pub fn output_area_of(
+        &self,
+        window: &WindowElement<T>,
+        output_size: &Size<i32, Physical>,
+    ) -> Rectangle<i32, Logical> {
+        self.output_area(window.workspace, output_size)
+    }
+
+    /// Area of a further output by connector name; None for the first
+    /// output and unknown ones
+    pub fn extra_output_area(&self, name: &str) -> Option<Rectangle<i32, Logical>> {
+        self.output_areas.get(name).copied()
+    }
+
+    /// Whether a window is shown on the first output, the one with the
+    /// panel and the overlays
+    pub fn is_on_first_output(&self, window: &WindowElement<T>) -> bool {
+        self.extra_area(window.workspace).is_none()
+    }
+
+    /// Area of the further output a workspace lives on; None on the first
+    fn extra_area(&self, ws: usize) -> Option<Rectangle<i32, Logical>> {
+        let output = self.workspaces.output_of(ws)?;
+        self.output_areas.get(output).copied()
+    }
+
+    /// Area of the output a workspace lives on
+    fn output_area(&self, ws: usize, output_size: &Size<i32, Physical>) -> Rectangle<i32, Logical> {
+        self.extra_area(ws)
+            .unwrap_or_else(|| Rectangle::from_size(Size::from((output_size.w, output_size.h))))
+    }
+
+    /// Area the windows of a workspace are laid out in: the work area on
+    /// the first output, the whole output on the others
+    fn workspace_area(&self, ws: usize, output_size: &Size<i32, Physical>) -> Rectangle<i32, Logical> {
+        self.extra_area(ws).unwrap_or_else(|| self.work_area(output_size))
+    }
+
+    /// Top-left corner of the output a workspace lives on
+    fn origin(&self, ws: usize) -> Point<i32, Logical> {
+        self.extra_area(ws).map_or_else(Point::default, |area| area.loc)
+    }
+
+    /// Top-left corner of every workspace's output, indexed by workspace
+    fn origins(&self) -> [Point<i32, Logical>; WORKSPACE_COUNT + 1] {
+        let mut origins = [Point::default(); WORKSPACE_COUNT + 1];
+        for (ws, origin) in origins.iter_mut().enumerate() {
+            *origin = self.origin(ws);
+        }
+        origins
+    }
+
+    /// Move windows along with their workspace's output since `before`,
+    /// and lay out the ones that fill an area again
+    fn relocate(
+        &mut self,
+        before: [Point<i32, Logical>; WORKSPACE_COUNT + 1],
+        output_size: &Size<i32, Physical>,
+    ) {
+        let after = self.origins();
+        for idx in 0..self.windows.len() {
+            let window = &mut self.windows[idx];
+            let ws = window.workspace;
+            if after[ws] != before[ws] {
+                let delta = after[ws] - before[ws];
+                window.position += delta;
+                if let Some(saved) = window.saved_geometry.as_mut() {
+                    saved.loc += delta;
+                }
+            }
+            if window.fullscreen || window.maximized || window.tiled.is_some() {
+                self.layout_window(idx, output_size);
+            }
+        }
+        self.arrange_all(output_size);
+    }
+
+    /// The output at a position in output pixels
+    fn output_at(&self, pos: (f64, f64)) -> Option<&str> {
+        self.output_areas
+            .iter()
+            .find(|(_, area)| area_contains(**area, pos))
+            .map(|(name, _)| name.as_str())
+            .or_else(|| {
+                self.workspaces
+                    .outputs()
+                    .iter()
+                    .find(|output| !self.output_areas.contains_key(*output))
+                    .map(String::as_str)
+            })
+    }
+
+    /// The workspace shown at a position
+    fn workspace_at(&self, pos: (f64, f64)) -> usize {
+        self.output_at(pos)
+            .and_then(|output| self.workspaces.active_on(output))
+            .unwrap_or_else(|| self.workspaces.active_workspace())
+    }
+
+    /// Show a workspace on its output and focus its topmost window. A
+    /// shown scratchpad comes along.
+    pub fn switch_workspace(&mut self, ws: usize) {
+        self.end_grab();
+        self.workspaces.switch_to(ws);
+        let active = self.workspaces.active_workspace();
+        if let Some(idx) = self.windows.iter().position(|w| w.scratchpad && !w.minimized) {
+            let shift = self.origin(active) - self.origin(self.windows[idx].workspace);
+            let window = &mut self.windows[idx];
+            window.position += shift;
+            window.workspace = active;
+        }
+        self.focus_topmost_visible();
+    }
+
+    /// Move the focused window to another workspace
+    pub fn move_focused_to_workspace(&mut self, ws: usize, output_size: &Size<i32, Physical>) {
+        if let Some(idx) = self.focused {
+            if idx < self.windows.len() {
+                self.move_index_to_workspace(idx, ws, output_size);
+            }
+        }
+    }
+
+    /// Move a window by id to another workspace (IPC)
+    pub fn move_to_workspace(&mut self, id: u32, ws: usize, output_size: &Size<i32, Physical>) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        self.move_index_to_workspace(idx, ws, output_size);
+        true
+    }
+
+    fn move_index_to_workspace(&mut self, idx: usize, ws: usize, output_size: &Size<i32, Physical>) {
+        if self.windows[idx].workspace == ws {
+            return;
+        }
+        self.end_grab();
+        let previous = self.windows[idx].workspace;
+        let shift = self.origin(ws) - self.origin(previous);
+        let window = &mut self.windows[idx];
+        window.workspace = ws;
+        window.floating = false;
+        // Onto the output the workspace is on
+        window.position += shift;
+        info!("Window {} moved to workspace {ws}", self.windows[idx].id);
+
+        // Leaving a tiled workspace for a floating one restores the
+        // window's floating geometry
+        if self.windows[idx].auto_tiled && !self.layouts.contains_key(&ws) {
+            self.windows[idx].auto_tiled = false;
+            self.layout_window(idx, output_size);
+        }
+        self.arrange_workspace(previous, output_size);
+        self.arrange_workspace(ws, output_size);
+        self.focus_topmost_visible();
+    }
+
+    /// Move a window to a workspace and give it a fixed floating geometry,
+    /// as x, y, width and height fractions of the work area (layout presets)
+    pub fn place_window(
+        &mut self,
+        id: u32,
+        ws: usize,
+        area: [f64; 4],
+        output_size: &Size<i32, Physical>,
+    ) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        self.end_grab();
+        let work_area = self.workspace_area(ws, output_size);
+        let scale = |fraction: f64, length: i32| (fraction * length as f64).round() as i32;
+        let position = Point::from((
+            work_area.loc.x + scale(area[0], work_area.size.w),
+            work_area.loc.y + scale(area[1], work_area.size.h),
+        ));
+        let size = Size::from((
+            scale(area[2], work_area.size.w).max(1),
+            scale(area[3], work_area.size.h).max(1),
+        ));
+
+        let window = &mut self.windows[idx];
+        let previous = window.workspace;
+        window.workspace = ws;
+        window.fullscreen = false;
+        window.maximized = false;
+        window.tiled = None;
+        window.auto_tiled = false;
+        window.floating = true;
+        window.minimized = false;
+        window.set_position(position);
+        window.request_size(size);
+        debug!("Window {id} placed at {position:?} {size:?} on workspace {ws}");
+
+        if previous != ws {
+            self.arrange_workspace(previous, output_size);
+        }
+        self.arrange_workspace(ws, output_size);
+        true
+    }
+
+    /// Show a window wherever it is: switch to its workspace, restore it
+    /// if minimized and focus it (IPC)
+    pub fn activate(&mut self, id: u32, output_size: &Size<i32, Physical>) -> bool {
+        let Some(window) = self.windows.iter_mut().find(|w| w.id == id) else {
+            return false;
+        };
+        let ws = window.workspace;
+        let restored = std::mem::replace(&mut window.minimized, false);
+        if !self.workspaces.is_visible(ws) {
+            self.switch_workspace(ws);
+        }
+        self.focus_id(id);
+        if restored {
+            info!("Window {id} restored");
+            self.arrange_workspace(ws, output_size);
+        }
+        true
+    }
+
+    /// Ask a window by id to close (IPC)
+    pub fn close(&mut self, id: u32) -> bool {
+        let Some(window) = self.windows.iter().find(|w| w.id == id) else {
+            return false;
+        };
+        window.toplevel.close();
+        true
+    }
+
     /// Close the currently focused window
     pub fn close_focused(&mut self) {
         if let Some(idx) = self.focused {
             if idx < self.windows.len() {
                 // Send a close request to the toplevel
-                self.windows[idx].toplevel.send_close();
+                self.windows[idx].toplevel.close();
             }
         }
     }
 
     /// Toggle fullscreen for the focused window
     pub fn toggle_fullscreen(&mut self, output_size: &Size<i32, Physical>) {
-        if let Some(idx) = self.focused {
-            if idx < self.windows.len() {
-                let window = &mut self.windows[idx];
-                if window.fullscreen {
-                    // Restore from fullscreen
-                    if let Some(saved) = window.saved_geometry.take() {
-                        window.set_position(saved.loc);
-                        window.set_size(saved.size);
-                    }
-                    window.fullscreen = false;
-                    info!("Window exited fullscreen");
-                } else {
-                    // Save current geometry and go fullscreen
-                    window.saved_geometry = Some(window.geometry());
-                    window.set_position(Point::from((0, 0)));
-                    window.set_size(Size::from((output_size.w, output_size.h)));
-                    window.fullscreen = true;
-                    info!("Window entered fullscreen");
+        if let Some(idx) = self.focused.filter(|idx| *idx < self.windows.len()) {
+            let window = &mut self.windows[idx];
+            window.save_floating_geometry();
+            window.fullscreen = !window.fullscreen;
+            if window.fullscreen {
+                info!("Window entered fullscreen");
+            } else {
+                info!("Window exited fullscreen");
+            }
+            self.layout_window(idx, output_size);
+            self.arrange_workspace(self.windows[idx].workspace, output_size);
+        }
+    }
+
+    /// Maximize the focused window within the work area, or restore its
+    /// saved geometry (Super+M)
+    pub fn toggle_maximized(&mut self, output_size: &Size<i32, Physical>) {
+        let Some(window) = self.focused_window() else {
+            return;
+        };
+        let surface = window.toplevel.clone();
+        let maximized = !window.maximized;
+        info!(
+            "Window {}",
+            if maximized { "maximized" } else { "restored" }
+        );
+        self.set_maximized(&surface, maximized, output_size);
+    }
+
+    /// Maximize or restore a window (client request)
+    pub fn set_maximized(
+        &mut self,
+        surface: &T,
+        maximized: bool,
+        output_size: &Size<i32, Physical>,
+    ) {
+        if let Some(idx) = self.windows.iter().position(|w| &w.toplevel == surface) {
+            let window = &mut self.windows[idx];
+            window.save_floating_geometry();
+            window.maximized = maximized;
+            window.tiled = None;
+            self.layout_window(idx, output_size);
+            self.arrange_workspace(self.windows[idx].workspace, output_size);
+        }
+    }
+
+    /// Move the focused window one step toward `direction` between halves,
+    /// quarters and maximized: Super+Left/Right tile to a half (keeping the
+    /// row of a quarter), Super+Up/Down split a half into quarters and join
+    /// them again; Super+Up maximizes a floating window, Super+Down restores
+    /// a maximized one.
+    pub fn snap_focused(&mut self, direction: SnapDirection, output_size: &Size<i32, Physical>) {
+        let Some(idx) = self.focused.filter(|idx| *idx < self.windows.len()) else {
+            return;
+        };
+        let window = &self.windows[idx];
+        if window.fullscreen {
+            return;
+        }
+
+        if window.maximized {
+            if direction == SnapDirection::Down {
+                self.windows[idx].maximized = false;
+                self.layout_window(idx, output_size);
+                self.arrange_workspace(self.windows[idx].workspace, output_size);
+                info!("Window restored");
+            }
+            return;
+        }
+        if let Some(target) = TileSide::step(window.tiled, direction) {
+            self.apply_snap(idx, target, output_size);
+        }
+    }
+
+    /// Tile or maximize a window
+    fn apply_snap(&mut self, idx: usize, target: SnapTarget, output_size: &Size<i32, Physical>) {
+        let window = &mut self.windows[idx];
+        window.save_floating_geometry();
+        window.fullscreen = false;
+        window.auto_tiled = false;
+        match target {
+            SnapTarget::Tile(side) => {
+                window.tiled = Some(side);
+                window.maximized = false;
+                info!("Window tiled to {side:?}");
+            }
+            SnapTarget::Maximize => {
+                window.tiled = None;
+                window.maximized = true;
+                info!("Window maximized");
+            }
+        }
+        self.layout_window(idx, output_size);
+        self.arrange_workspace(self.windows[idx].workspace, output_size);
+    }
+
+    /// Snap zone at a cursor position, if any
+    fn snap_zone(&self, pos: (f64, f64), output_size: &Size<i32, Physical>) -> Option<SnapTarget> {
+        let ws = self.workspace_at(pos);
+        let output = self.output_area(ws, output_size);
+        let top = self.workspace_area(ws, output_size).loc.y as f64;
+        // Relative to the output
+        let (x, y) = (pos.0 - output.loc.x as f64, pos.1 - output.loc.y as f64);
+        let top = top - output.loc.y as f64;
+        let (w, h) = (output.size.w as f64, output.size.h as f64);
+
+        let side = if x <= SNAP_EDGE {
+            Some((TileSide::TopLeft, TileSide::Left, TileSide::BottomLeft))
+        } else if x >= w - SNAP_EDGE {
+            Some((TileSide::TopRight, TileSide::Right, TileSide::BottomRight))
+        } else {
+            None
+        };
+
+        match side {
+            Some((upper, _, _)) if y <= top + SNAP_CORNER => Some(SnapTarget::Tile(upper)),
+            Some((_, _, lower)) if y >= h - SNAP_CORNER => Some(SnapTarget::Tile(lower)),
+            Some((_, half, _)) => Some(SnapTarget::Tile(half)),
+            None if y <= SNAP_EDGE => Some(SnapTarget::Maximize),
+            None => None,
+        }
+    }
+
+    /// Area the dragged window would snap to, for the renderer's preview
+    pub fn snap_preview(&self, output_size: &Size<i32, Physical>) -> Option<Rectangle<i32, Logical>> {
+        let work_area = self.workspace_area(self.workspace_at(self.cursor_pos), output_size);
+        self.snap.map(|target| match target {
+            SnapTarget::Tile(side) => side.rect(work_area),
+            SnapTarget::Maximize => work_area,
+        })
+    }
+
+    /// Screen area below the panel, minus the reserved edges
+    fn work_area(&self, output_size: &Size<i32, Physical>) -> Rectangle<i32, Logical> {
+        let [top, bottom, left, right] = self.reserved_edges;
+        let y = self.panel_height + top;
+        Rectangle::new(
+            Point::from((left, y)),
+            Size::from((
+                (output_size.w - left - right).max(1),
+                (output_size.h - y - bottom).max(1),
+            )),
+        )
+    }
+
+    /// Change the space reserved at the edges (top, bottom, left, right) and
+    /// re-place maximized, snapped and tiled windows
+    pub fn set_reserved_edges(&mut self, edges: [i32; 4], output_size: &Size<i32, Physical>) {
+        if self.reserved_edges == edges {
+            return;
+        }
+        info!("Reserved screen edges: {edges:?}");
+        self.reserved_edges = edges;
+        for idx in 0..self.windows.len() {
+            let window = &self.windows[idx];
+            if window.maximized || window.tiled.is_some() {
+                self.layout_window(idx, output_size);
+            }
+        }
+        self.arrange_all(output_size);
+    }
+
+    /// Place and size a window according to its fullscreen / maximized /
+    /// tiled state, restoring the saved geometry once it floats again.
+    /// Automatically tiled windows are placed by arrange_workspace.
+    fn layout_window(&mut self, idx: usize, output_size: &Size<i32, Physical>) {
+        let ws = self.windows[idx].workspace;
+        let work_area = self.workspace_area(ws, output_size);
+        let output_area = self.output_area(ws, output_size);
+
+        let window = &mut self.windows[idx];
+        let target = if window.fullscreen {
+            Some(output_area)
+        } else if window.maximized {
+            Some(work_area)
+        } else if let Some(side) = window.tiled {
+            Some(side.rect(work_area))
+        } else if window.auto_tiled {
+            None
+        } else {
+            window.saved_geometry.take()
+        };
+
+        match target {
+            Some(rect) => {
+                window.set_position(rect.loc);
+                window.request_size(rect.size);
+            }
+            None => window.configure(),
+        }
+    }
+
+    // ---- Scratchpad ----
+
+    /// The scratchpad window, if one was adopted
+    pub fn scratchpad(&self) -> Option<&WindowElement<T>> {
+        self.windows.iter().find(|w| w.scratchpad)
+    }
+
+    /// Make a newly mapped window the scratchpad and slide it in
+    pub fn adopt_scratchpad(&mut self, id: u32, output_size: &Size<i32, Physical>) -> bool {
+        if self.scratchpad().is_some() {
+            return false;
+        }
+        let Some(idx) = self.windows.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        let work_area = self.workspace_area(self.workspaces.active_workspace(), output_size);
+        let window = &mut self.windows[idx];
+        window.scratchpad = true;
+        window.fullscreen = false;
+        window.maximized = false;
+        window.tiled = None;
+        window.auto_tiled = false;
+        window.request_size(Size::from((
+            (work_area.size.w as f64 * SCRATCHPAD_SIZE.0).round() as i32,
+            (work_area.size.h as f64 * SCRATCHPAD_SIZE.1).round() as i32,
+        )));
+        info!("Window {id} is the scratchpad");
+        let ws = window.workspace;
+        self.arrange_workspace(ws, output_size);
+        self.show_scratchpad(output_size);
+        true
+    }
+
+    /// Super+grave: show the scratchpad over the active workspace, focus it
+    /// when it is shown but not focused, hide it when it is. Returns false
+    /// without a scratchpad; true when it starts to slide.
+    pub fn toggle_scratchpad(&mut self, output_size: &Size<i32, Physical>) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| w.scratchpad) else {
+            return false;
+        };
+        let sliding_in = matches!(self.scratchpad_slide, Some((_, true)));
+        if self.windows[idx].minimized || matches!(self.scratchpad_slide, Some((_, false))) {
+            self.show_scratchpad(output_size);
+        } else if self.focused != Some(idx) && !sliding_in {
+            self.focus_id(self.windows[idx].id);
+            return false;
+        } else {
+            info!("Scratchpad hidden");
+            self.scratchpad_slide = Some((self.slide_start(), false));
+            self.end_grab();
+        }
+        true
+    }
+
+    fn show_scratchpad(&mut self, output_size: &Size<i32, Physical>) {
+        let Some(idx) = self.windows.iter().position(|w| w.scratchpad) else {
+            return;
+        };
+        let ws = self.workspaces.active_workspace();
+        let work_area = self.workspace_area(ws, output_size);
+        let top = self.output_area(ws, output_size).loc.y;
+        let window = &mut self.windows[idx];
+        if window.minimized {
+            // Start above the top edge
+            window.set_position(Point::from((window.position.x, top - window.size.h)));
+        }
+        window.minimized = false;
+        window.workspace = ws;
+        window.position.x = work_area.loc.x + (work_area.size.w - window.size.w) / 2;
+        let id = window.id;
+        info!("Scratchpad shown");
+        self.scratchpad_slide = Some((self.slide_start(), true));
+        self.focus_id(id);
+    }
+
+    /// When a new slide starts, set back so that reversing one that is
+    /// still going continues from where it is
+    fn slide_start(&self) -> Instant {
+        let now = Instant::now();
+        match self.scratchpad_slide {
+            Some((start, _)) => {
+                let elapsed = now.duration_since(start).as_secs_f64().min(SCRATCHPAD_SLIDE);
+                now - Duration::from_secs_f64(SCRATCHPAD_SLIDE - elapsed)
+            }
+            None => now,
+        }
+    }
+
+    /// Move the sliding scratchpad for this moment. Returns whether it is
+    /// still sliding.
+    pub fn animate_scratchpad(&mut self, output_size: &Size<i32, Physical>) -> bool {
+        let Some((start, showing)) = self.scratchpad_slide else {
+            return false;
+        };
+        let Some(idx) = self.windows.iter().position(|w| w.scratchpad) else {
+            self.scratchpad_slide = None;
+            return false;
+        };
+        let t = (start.elapsed().as_secs_f64() / SCRATCHPAD_SLIDE).min(1.0);
+        let t = if showing { t } else { 1.0 - t };
+        let eased = 1.0 - (1.0 - t).powi(3);
+
+        let ws = self.windows[idx].workspace;
+        let top = self.workspace_area(ws, output_size).loc.y;
+        let edge = self.output_area(ws, output_size).loc.y;
+        let window = &mut self.windows[idx];
+        let hidden = edge - window.size.h;
+        window.position.y = hidden + ((top - hidden) as f64 * eased).round() as i32;
+
+        let done = start.elapsed().as_secs_f64() >= SCRATCHPAD_SLIDE;
+        if done {
+            self.scratchpad_slide = None;
+            if !showing {
+                self.windows[idx].minimized = true;
+                if self.focused == Some(idx) {
+                    self.focus_topmost_visible();
                 }
             }
         }
+        !done
     }
 
-    /// Tile the focused window to the left half of the screen
-    pub fn tile_left(&mut self, output_size: &Size<i32, Physical>) {
-        if let Some(idx) = self.focused {
-            if idx < self.windows.len() {
+    // ---- Automatic tiling ----
+
+    /// Whether a workspace is tiled automatically
+    pub fn is_tiling(&self, ws: usize) -> bool {
+        self.layouts.contains_key(&ws)
+    }
+
+    /// Layout of a workspace: its tiling layout, or "floating"
+    pub fn layout_name(&self, ws: usize) -> &'static str {
+        self.layouts
+            .get(&ws)
+            .map_or("floating", |layout| layout.layout.name())
+    }
+
+    /// Turn automatic tiling on or off for the active workspace
+    pub fn toggle_tiling(&mut self, output_size: &Size<i32, Physical>) {
+        let ws = self.workspaces.active_workspace();
+        if self.layouts.remove(&ws).is_some() {
+            info!("Workspace {ws}: automatic tiling off");
+            for idx in 0..self.windows.len() {
                 let window = &mut self.windows[idx];
-                window.set_position(Point::from((0, self.panel_height)));
-                window.set_size(Size::from((
-                    output_size.w / 2,
-                    output_size.h - self.panel_height,
-                )));
-                window.fullscreen = false;
-                info!("Window tiled to left half");
+                if window.workspace == ws && window.auto_tiled {
+                    window.auto_tiled = false;
+                    self.layout_window(idx, output_size);
+                }
             }
+        } else {
+            let layout = self.tiling.workspace_layout();
+            info!("Workspace {ws}: automatic tiling on ({})", layout.layout.name());
+            self.layouts.insert(ws, layout);
+            // Start over with every window taking part
+            for window in self.windows.iter_mut().filter(|w| w.workspace == ws) {
+                window.floating = false;
+            }
+            self.arrange_workspace(ws, output_size);
         }
     }
 
-    /// Tile the focused window to the right half of the screen
-    pub fn tile_right(&mut self, output_size: &Size<i32, Physical>) {
-        if let Some(idx) = self.focused {
-            if idx < self.windows.len() {
-                let window = &mut self.windows[idx];
-                window.set_position(Point::from((
-                    output_size.w / 2,
-                    self.panel_height,
-                )));
-                window.set_size(Size::from((
-                    output_size.w / 2,
-                    output_size.h - self.panel_height,
-                )));
-                window.fullscreen = false;
-                info!("Window tiled to right half");
+    /// Switch the active workspace to the next tiling layout
+    pub fn cycle_layout(&mut self, output_size: &Size<i32, Physical>) {
+        let ws = self.workspaces.active_workspace();
+        if let Some(layout) = self.layouts.get_mut(&ws) {
+            layout.layout = layout.layout.next();
+            info!("Workspace {ws}: layout {}", layout.layout.name());
+            self.arrange_workspace(ws, output_size);
+        }
+    }
+
+    /// Grow or shrink the master area of the active workspace
+    pub fn adjust_master_ratio(&mut self, delta: f64, output_size: &Size<i32, Physical>) {
+        let ws = self.workspaces.active_workspace();
+        if let Some(layout) = self.layouts.get_mut(&ws) {
+            layout.adjust_ratio(delta);
+            debug!("Workspace {ws}: master ratio {:.2}", layout.master_ratio);
+            self.arrange_workspace(ws, output_size);
+        }
+    }
+
+    /// Take new `[tiling]` settings. The gap applies right away, the layout
+    /// to workspaces tiled from now on; turning `enabled` on tiles the
+    /// workspaces that are floating.
+    pub fn set_tiling(&mut self, tiling: &TilingConfig, output_size: &Size<i32, Physical>) {
+        if tiling.enabled && !self.tiling.enabled {
+            for ws in 1..=WORKSPACE_COUNT {
+                self.layouts.entry(ws).or_insert_with(|| tiling.workspace_layout());
             }
         }
+        self.tiling = tiling.clone();
+        self.arrange_all(output_size);
     }
 
-    /// Cycle focus to the next window
-    pub fn cycle_focus(&mut self) {
-        if self.windows.len() <= 1 {
-            return;
+    /// Re-arrange every tiled workspace (e.g. after the output was resized)
+    pub fn arrange_all(&mut self, output_size: &Size<i32, Physical>) {
+        let tiled: Vec<usize> = self.layouts.keys().copied().collect();
+        for ws in tiled {
+            self.arrange_workspace(ws, output_size);
         }
+    }
 
-        self.focused = Some(match self.focused {
-            Some(idx) => (idx + 1) % self.windows.len(),
-            None => 0,
-        });
+    /// Place the windows of a tiled workspace; oldest first, so the first
+    /// window stays in the master area
+    fn arrange_workspace(&mut self, ws: usize, output_size: &Size<i32, Physical>) {
+        let Some(layout) = self.layouts.get(&ws).copied() else {
+            return;
+        };
 
-        // Raise the focused window to the top of the stack
-        if let Some(idx) = self.focused {
-            let window = self.windows.remove(idx);
-            self.windows.push(window);
-            self.focused = Some(self.windows.len() - 1);
+        let mut tiled: Vec<usize> = self
+            .windows
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.workspace == ws && w.wants_auto_tiling())
+            .map(|(idx, _)| idx)
+            .collect();
+        tiled.sort_by_key(|idx| self.windows[*idx].id);
+
+        let rects = layout.arrange(self.workspace_area(ws, output_size), tiled.len(), self.tiling.gap);
+        for (idx, rect) in tiled.into_iter().zip(rects) {
+            let window = &mut self.windows[idx];
+            window.save_floating_geometry();
+            window.auto_tiled = true;
+            window.set_position(rect.loc);
+            window.request_size(rect.size);
         }
+    }
 
-        debug!("Focus cycled to window {:?}", self.focused);
+    /// Mark only the focused window as activated (none while `active` is
+    /// false, e.g. when the session is locked)
+    pub fn sync_activation(&mut self, active: bool) {
+        let focused = self.focused.filter(|_| active);
+        for (idx, window) in self.windows.iter_mut().enumerate() {
+            let activated = focused == Some(idx);
+            if activated {
+                window.urgent = false;
+            }
+            if window.activated != activated {
+                window.activated = activated;
+                window.configure();
+            }
+        }
     }
 
-    /// Focus the window at the given screen position
+    /// Focus the output and the window at the given screen position
     pub fn focus_at(&mut self, pos: (f64, f64)) {
+        if let Some(output) = self.output_at(pos).map(str::to_string) {
+            self.workspaces.focus_output(&output);
+        }
         // Search from top of stack (last) to bottom (first)
         let found = self
             .windows
             .iter()
             .enumerate()
             .rev()
-            .find(|(_, w)| w.contains_point(pos))
+            .find(|(_, w)| self.is_window_visible(w) && w.contains_point(pos))
             .map(|(idx, _)| idx);
 
         if let Some(idx) = found {
@@ -300,16 +1496,14 @@ impl WindowManager {
         }
     }
 
-    /// Find the Wayland surface under the given screen position (returns owned WlSurface)
+    /// Find the Wayland surface under the given screen position, with the
+    /// surface's origin in client units (see client_point)
     pub fn surface_under(&self, pos: (f64, f64)) -> Option<(WlSurface, (f64, f64))> {
         for window in self.windows.iter().rev() {
-            if window.contains_point(pos) {
+            if self.is_window_visible(window) && window.contains_point(pos) {
                 if let Some(surface) = window.wl_surface() {
-                    let relative_pos = (
-                        pos.0 - window.position.x as f64,
-                        pos.1 - window.position.y as f64,
-                    );
-                    return Some((surface, relative_pos));
+                    let origin = self.client_point((window.position.x as f64, window.position.y as f64));
+                    return Some((surface, (origin.x, origin.y)));
                 }
             }
         }
@@ -328,25 +1522,43 @@ impl WindowManager {
         self.cursor_pos = (x, y);
     }
 
-    /// Update cursor position by a relative delta, clamped to output bounds
+    /// Update cursor position by a relative delta. It crosses into any
+    /// output it lands on and is clamped to the one it was on otherwise.
     pub fn update_cursor_relative(
         &mut self,
         dx: f64,
         dy: f64,
         output_size: Size<i32, Physical>,
     ) -> (f64, f64) {
-        self.cursor_pos.0 = (self.cursor_pos.0 + dx).clamp(0.0, output_size.w as f64);
-        self.cursor_pos.1 = (self.cursor_pos.1 + dy).clamp(0.0, output_size.h as f64);
+        let moved = (self.cursor_pos.0 + dx, self.cursor_pos.1 + dy);
+        let first = Rectangle::from_size(Size::from((output_size.w, output_size.h)));
+        let on_output =
+            area_contains(first, moved) || self.output_areas().any(|area| area_contains(area, moved));
+        if !on_output {
+            let area = self
+                .output_areas()
+                .find(|area| area_contains(*area, self.cursor_pos))
+                .unwrap_or(first);
+            self.cursor_pos = (
+                moved.0.clamp(area.loc.x as f64, (area.loc.x + area.size.w) as f64),
+                moved.1.clamp(area.loc.y as f64, (area.loc.y + area.size.h) as f64),
+            );
+        } else {
+            self.cursor_pos = moved;
+        }
         self.cursor_pos
     }
 
     // ---- Pointer grab (move/resize) ----
 
     /// Handle pointer motion during an active grab
-    pub fn handle_pointer_motion(&mut self, pos: (f64, f64)) -> bool {
-        let grab = match &self.grab {
-            Some(g) => g.clone(),
-            None => return false,
+    pub fn handle_pointer_motion(
+        &mut self,
+        pos: (f64, f64),
+        output_size: &Size<i32, Physical>,
+    ) -> bool {
+        let Some(grab) = self.grab else {
+            return false;
         };
 
         let dx = pos.0 - grab.initial_cursor.0;
@@ -357,15 +1569,17 @@ impl WindowManager {
                 if grab.window_index < self.windows.len() {
                     let new_x = grab.initial_window_pos.x + dx as i32;
                     let new_y = grab.initial_window_pos.y + dy as i32;
-                    self.windows[grab.window_index]
-                        .set_position(Point::from((new_x, new_y.max(self.panel_height))));
+                    // Kept below the panel, or the top edge of another output
+                    let top = self.workspace_area(self.workspace_at(pos), output_size).loc.y;
+                    self.windows[grab.window_index].set_position(Point::from((new_x, new_y.max(top))));
+                    self.snap = self.snap_zone(pos, output_size);
                 }
             }
             GrabKind::Resize => {
                 if grab.window_index < self.windows.len() {
                     let new_w = (grab.initial_window_size.w + dx as i32).max(200);
                     let new_h = (grab.initial_window_size.h + dy as i32).max(150);
-                    self.windows[grab.window_index].set_size(Size::from((new_w, new_h)));
+                    self.windows[grab.window_index].request_size(Size::from((new_w, new_h)));
                 }
             }
         }
@@ -374,9 +1588,31 @@ impl WindowManager {
     }
 
     /// Start a move grab on the focused window
-    pub fn begin_move(&mut self) {
+    pub fn begin_move(&mut self, output_size: &Size<i32, Physical>) {
         if let Some(idx) = self.focused {
             if idx < self.windows.len() {
+                // Dragging a tiled or maximized window makes it float again
+                let window = &mut self.windows[idx];
+                if window.tiled.is_some() || window.maximized || window.auto_tiled {
+                    window.tiled = None;
+                    window.maximized = false;
+                    window.floating = window.auto_tiled;
+                    window.auto_tiled = false;
+                    // Back to the floating size, keeping the grabbed spot
+                    // under the cursor horizontally
+                    match window.saved_geometry.take() {
+                        Some(saved) => {
+                            let grabbed = self.cursor_pos.0 as i32 - window.position.x;
+                            let x = self.cursor_pos.0 as i32
+                                - grabbed * saved.size.w / window.size.w.max(1);
+                            window.set_position(Point::from((x, window.position.y)));
+                            window.request_size(saved.size);
+                        }
+                        None => window.configure(),
+                    }
+                    let ws = window.workspace;
+                    self.arrange_workspace(ws, output_size);
+                }
                 self.grab = Some(GrabState {
                     window_index: idx,
                     kind: GrabKind::Move,
@@ -390,9 +1626,18 @@ impl WindowManager {
     }
 
     /// Start a resize grab on the focused window
-    pub fn begin_resize(&mut self) {
+    pub fn begin_resize(&mut self, output_size: &Size<i32, Physical>) {
         if let Some(idx) = self.focused {
             if idx < self.windows.len() {
+                let window = &mut self.windows[idx];
+                window.tiled = None;
+                window.maximized = false;
+                if window.auto_tiled {
+                    window.auto_tiled = false;
+                    window.floating = true;
+                    let ws = window.workspace;
+                    self.arrange_workspace(ws, output_size);
+                }
                 self.grab = Some(GrabState {
                     window_index: idx,
                     kind: GrabKind::Resize,
@@ -400,16 +1645,668 @@ impl WindowManager {
                     initial_window_pos: self.windows[idx].position,
                     initial_window_size: self.windows[idx].size,
                 });
+                self.windows[idx].resizing = true;
+                self.windows[idx].configure();
                 debug!("Resize grab started on window {idx}");
             }
         }
     }
 
+    /// Focus and raise the window of a toplevel (e.g. before a client
+    /// initiated move)
+    pub fn focus_toplevel(&mut self, surface: &T) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| &w.toplevel == surface) else {
+            return false;
+        };
+        let window = self.windows.remove(idx);
+        self.windows.push(window);
+        self.focused = Some(self.windows.len() - 1);
+        true
+    }
+
+    /// Focus and raise a window by id (window switcher)
+    pub fn focus_id(&mut self, id: u32) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| w.id == id) else {
+            return false;
+        };
+        let window = self.windows.remove(idx);
+        self.windows.push(window);
+        self.focused = Some(self.windows.len() - 1);
+        true
+    }
+
+    /// Mark the window of a surface as wanting attention, unless it is
+    /// already focused
+    pub fn set_urgent(&mut self, surface: &WlSurface) {
+        let focused = self.focused;
+        if let Some((idx, window)) = self
+            .windows
+            .iter_mut()
+            .enumerate()
+            .find(|(_, w)| w.toplevel.surface() == Some(surface))
+        {
+            if focused != Some(idx) && !window.urgent {
+                info!("Window {} wants attention", window.id);
+                window.urgent = true;
+            }
+        }
+    }
+
+    /// Whether a pointer grab is in progress
+    pub fn has_grab(&self) -> bool {
+        self.grab.is_some()
+    }
+
+    /// End the grab when the button is released, snapping a dragged window
+    /// if it was dropped in a snap zone
+    pub fn finish_grab(&mut self, output_size: &Size<i32, Physical>) {
+        let snap = self.snap.take();
+        let moved = self
+            .grab
+            .as_ref()
+            .filter(|g| matches!(g.kind, GrabKind::Move) && g.window_index < self.windows.len())
+            .map(|g| g.window_index);
+        let index = self.grab.as_ref().map(|g| g.window_index);
+        self.end_grab();
+        // A window dropped on another output joins the workspace shown there
+        if let Some(idx) = moved {
+            let ws = self.workspace_at(self.cursor_pos);
+            let previous = self.windows[idx].workspace;
+            if ws != previous {
+                self.windows[idx].workspace = ws;
+                info!("Window {} moved to workspace {ws}", self.windows[idx].id);
+                self.arrange_workspace(previous, output_size);
+                self.arrange_workspace(ws, output_size);
+            }
+        }
+        if let (Some(target), Some(idx)) = (snap, index) {
+            if idx < self.windows.len() {
+                self.apply_snap(idx, target, output_size);
+            }
+        }
+    }
+
     /// End any active grab
     pub fn end_grab(&mut self) {
-        if self.grab.is_some() {
+        self.snap = None;
+        if let Some(grab) = self.grab.take() {
+            if let Some(window) = self.windows.get_mut(grab.window_index) {
+                if window.resizing {
+                    window.resizing = false;
+                    window.configure();
+                }
+            }
             debug!("Grab ended");
-            self.grab = None;
         }
     }
+
+    // ---- Restoring across restarts ----
+
+    /// A window's placement, for saving
+    pub fn placement(&self, window: &WindowElement<T>) -> Placement {
+        let geometry = match window.saved_geometry {
+            Some(saved) if !window.is_floating() => saved,
+            _ => window.geometry(),
+        };
+        Placement {
+            workspace: window.workspace,
+            geometry,
+            fullscreen: window.fullscreen,
+            maximized: window.maximized,
+            tiled: window.tiled,
+            floating: window.floating,
+            minimized: window.minimized,
+        }
+    }
+
+    /// Put a window back on the workspace and in the place it had. The
+    /// geometry is pulled back onto the output if that got smaller.
+    pub fn restore_placement(
+        &mut self,
+        id: u32,
+        placement: &Placement,
+        output_size: &Size<i32, Physical>,
+    ) -> bool {
+        let Some(idx) = self.windows.iter().position(|w| w.id == id && !w.scratchpad) else {
+            return false;
+        };
+        if self.grab.as_ref().is_some_and(|g| g.window_index == idx) {
+            self.end_grab();
+        }
+        let ws = placement.workspace.clamp(1, WORKSPACE_COUNT);
+        let output = self.output_area(ws, output_size);
+        let top = self.workspace_area(ws, output_size).loc.y;
+        let saved = placement.geometry;
+        let size = Size::from((
+            saved.size.w.clamp(1, output.size.w.max(1)),
+            saved.size.h.clamp(1, output.size.h.max(1)),
+        ));
+        let (right, bottom) = (output.loc.x + output.size.w, output.loc.y + output.size.h);
+        let position = Point::from((
+            saved.loc.x.clamp(output.loc.x, (right - size.w).max(output.loc.x)),
+            saved.loc.y.clamp(top, (bottom - size.h).max(top)),
+        ));
+
+        let window = &mut self.windows[idx];
+        let previous = window.workspace;
+        window.workspace = ws;
+        window.fullscreen = placement.fullscreen || self.kiosk;
+        window.maximized = placement.maximized;
+        window.tiled = placement.tiled;
+        window.auto_tiled = false;
+        window.floating = placement.floating;
+        if window.fullscreen || window.maximized || window.tiled.is_some() {
+            window.saved_geometry = Some(Rectangle::new(position, size));
+            self.layout_window(idx, output_size);
+        } else {
+            window.saved_geometry = None;
+            window.set_position(position);
+            window.request_size(size);
+        }
+        debug!("Window {id} restored to workspace {ws}: {placement:?}");
+
+        if previous != ws {
+            self.arrange_workspace(previous, output_size);
+        }
+        self.arrange_workspace(ws, output_size);
+        if placement.minimized {
+            self.minimize_index(idx, output_size);
+        } else if self.focused == Some(idx) && !self.workspaces.is_visible(ws) {
+            self.focus_topmost_visible();
+        }
+        true
+    }
+
+    /// Layouts of the automatically tiled workspaces, for saving
+    pub fn tiling_layouts(&self) -> Vec<(usize, WorkspaceLayout)> {
+        let mut layouts: Vec<_> = self.layouts.iter().map(|(ws, layout)| (*ws, *layout)).collect();
+        layouts.sort_by_key(|(ws, _)| *ws);
+        layouts
+    }
+
+    /// Replace which workspaces are tiled automatically, and how (at
+    /// startup, before any window maps)
+    pub fn restore_tiling_layouts(&mut self, layouts: HashMap<usize, WorkspaceLayout>) {
+        self.layouts = layouts;
+    }
+
+    // ---- Debugging ----
+
+    /// The whole layout state as JSON, windows in stack order (`heyctl
+    /// layout`). Meant for reading and for comparing snapshots, not as a
+    /// stable interface.
+    pub fn serialize_layout(&self) -> Value {
+        let [top, bottom, left, right] = self.reserved_edges;
+        let workspaces: Vec<Value> = (1..=WORKSPACE_COUNT)
+            .map(|ws| {
+                json!({
+                    "number": ws,
+                    "active": self.workspaces.active_workspace() == ws,
+                    "output": self.workspaces.output_of(ws),
+                    "layout": self.layout_name(ws),
+                    "master_ratio": self.layouts.get(&ws).map(|layout| layout.master_ratio),
+                })
+            })
+            .collect();
+        let windows: Vec<Value> = self
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(idx, window)| {
+                let (title, app_id) = window.title_and_app_id();
+                json!({
+                    "id": window.id,
+                    "app_id": app_id,
+                    "title": title,
+                    "workspace": window.workspace,
+                    "geometry": rect_json(window.geometry()),
+                    "saved_geometry": window.saved_geometry.map(rect_json),
+                    "focused": self.focused == Some(idx),
+                    "visible": self.is_window_visible(window),
+                    "mapped": window.mapped,
+                    "activated": window.activated,
+                    "fullscreen": window.fullscreen,
+                    "maximized": window.maximized,
+                    "tiled": window.tiled.map(|side| format!("{side:?}")),
+                    "auto_tiled": window.auto_tiled,
+                    "floating": window.floating,
+                    "minimized": window.minimized,
+                    "scratchpad": window.scratchpad,
+                    "urgent": window.urgent,
+                    "resizing": window.resizing,
+                })
+            })
+            .collect();
+        let grab = self.grab.map(|grab| {
+            json!({
+                "window": self.windows.get(grab.window_index).map(|w| w.id),
+                "kind": format!("{:?}", grab.kind),
+            })
+        });
+        let sliding = self
+            .scratchpad_slide
+            .map(|(_, showing)| if showing { "in" } else { "out" });
+        let snap = self.snap.map(|target| match target {
+            SnapTarget::Tile(side) => format!("{side:?}"),
+            SnapTarget::Maximize => "Maximize".to_string(),
+        });
+
+        json!({
+            "scale": self.scale,
+            "kiosk": self.kiosk,
+            "panel_height": self.panel_height,
+            "reserved_edges": { "top": top, "bottom": bottom, "left": left, "right": right },
+            "cursor": [self.cursor_pos.0, self.cursor_pos.1],
+            "gap": self.tiling.gap,
+            "grab": grab,
+            "snap": snap,
+            "scratchpad_sliding": sliding,
+            "workspaces": workspaces,
+            "windows": windows,
+        })
+    }
+}
+
+/// A rectangle as {x, y, width, height}
+fn rect_json(rect: Rectangle<i32, Logical>) -> Value {
+    json!({ "x": rect.loc.x, "y": rect.loc.y, "width": rect.size.w, "height": rect.size.h })
+}
+
+/// Where a window of `size` is centered in `area`, kept inside its top-left
+fn centered(area: Rectangle<i32, Logical>, size: Size<i32, Logical>) -> Point<i32, Logical> {
+    let x = area.loc.x + (area.size.w - size.w) / 2;
+    let y = area.loc.y + (area.size.h - size.h) / 2;
+    Point::from((x.max(area.loc.x), y.max(area.loc.y)))
+}
+
+/// Whether a point lies in an area, edges included
+fn area_contains(area: Rectangle<i32, Logical>, pos: (f64, f64)) -> bool {
+    pos.0 >= area.loc.x as f64
+        && pos.0 <= (area.loc.x + area.size.w) as f64
+        && pos.1 >= area.loc.y as f64
+        && pos.1 <= (area.loc.y + area.size.h) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::xdg_toplevel::State;
+    use super::*;
+    use crate::alloc_count::allocations;
+    use crate::toplevel::fake::FakeToplevel;
+    use crate::workspace::OutputTarget;
+
+    fn output() -> Size<i32, Physical> {
+        Size::from((1920, 1080))
+    }
+
+    /// A window manager with one output, like a single-monitor session
+    fn manager() -> WindowManager<FakeToplevel> {
+        let mut wm = WindowManager::new(&Config::default());
+        wm.output_added("TEST-1", None, &output());
+        wm
+    }
+
+    /// The manager with a 1280x1024 output to the right of the first one
+    fn two_outputs() -> (WindowManager<FakeToplevel>, Rectangle<i32, Logical>) {
+        let mut wm = manager();
+        let area = Rectangle::new(Point::from((1920, 0)), Size::from((1280, 1024)));
+        wm.output_added("TEST-2", Some(area), &output());
+        (wm, area)
+    }
+
+    fn open(wm: &mut WindowManager<FakeToplevel>, app_id: &str) -> (u32, FakeToplevel) {
+        let toplevel = FakeToplevel::new(app_id);
+        wm.add_window(WindowElement::new(toplevel.clone()), true, &output());
+        (wm.windows().last().unwrap().id(), toplevel)
+    }
+
+    fn geometry(wm: &WindowManager<FakeToplevel>, id: u32) -> Rectangle<i32, Logical> {
+        wm.window_by_id(id).unwrap().geometry()
+    }
+
+    fn focused_id(wm: &WindowManager<FakeToplevel>) -> Option<u32> {
+        wm.focused_window().map(WindowElement::id)
+    }
+
+    #[test]
+    fn new_windows_are_centered_below_the_panel_and_focused() {
+        let mut wm = manager();
+        let (id, _) = open(&mut wm, "a");
+        let panel = wm.panel_height;
+        let expected = Rectangle::new(
+            Point::from(((1920 - 800) / 2, panel + (1080 - panel - 600) / 2)),
+            Size::from((800, 600)),
+        );
+        assert_eq!(geometry(&wm, id), expected);
+        assert_eq!(wm.window_by_id(id).unwrap().workspace(), 1);
+        assert_eq!(focused_id(&wm), Some(id));
+    }
+
+    #[test]
+    fn unfocused_windows_open_below_the_focused_one() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        wm.add_window(WindowElement::new(FakeToplevel::new("b")), false, &output());
+        assert_eq!(focused_id(&wm), Some(first));
+        assert_eq!(wm.windows().last().map(WindowElement::id), Some(first));
+        assert!(wm.windows()[0].is_urgent());
+    }
+
+    #[test]
+    fn keyboard_snapping_steps_between_halves_and_quarters() {
+        let mut wm = manager();
+        let (id, toplevel) = open(&mut wm, "a");
+        let floating = geometry(&wm, id);
+        let work_area = wm.work_area(&output());
+
+        wm.snap_focused(SnapDirection::Left, &output());
+        assert_eq!(geometry(&wm, id), TileSide::Left.rect(work_area));
+        assert_eq!(toplevel.configured_size(), Some(TileSide::Left.rect(work_area).size));
+        assert!(toplevel.has_state(State::TiledLeft));
+
+        wm.snap_focused(SnapDirection::Up, &output());
+        assert_eq!(geometry(&wm, id), TileSide::TopLeft.rect(work_area));
+        wm.snap_focused(SnapDirection::Right, &output());
+        assert_eq!(geometry(&wm, id), TileSide::TopRight.rect(work_area));
+        wm.snap_focused(SnapDirection::Down, &output());
+        assert_eq!(geometry(&wm, id), TileSide::Right.rect(work_area));
+
+        // Dragging it away brings back the floating size
+        wm.set_cursor_position(1500.0, 500.0);
+        wm.begin_move(&output());
+        wm.end_grab();
+        assert_eq!(geometry(&wm, id).size, floating.size);
+        assert!(wm.window_by_id(id).unwrap().is_floating());
+    }
+
+    #[test]
+    fn super_up_maximizes_and_super_down_restores() {
+        let mut wm = manager();
+        let (id, toplevel) = open(&mut wm, "a");
+        let floating = geometry(&wm, id);
+
+        wm.snap_focused(SnapDirection::Up, &output());
+        assert_eq!(geometry(&wm, id), wm.work_area(&output()));
+        assert!(toplevel.has_state(State::Maximized));
+
+        wm.snap_focused(SnapDirection::Down, &output());
+        assert_eq!(geometry(&wm, id), floating);
+        assert!(!toplevel.has_state(State::Maximized));
+    }
+
+    #[test]
+    fn dropping_a_window_at_an_edge_snaps_it() {
+        let mut wm = manager();
+        let (id, _) = open(&mut wm, "a");
+        let start = geometry(&wm, id).loc.to_f64();
+        wm.set_cursor_position(start.x + 10.0, start.y + 10.0);
+        wm.begin_move(&output());
+
+        wm.handle_pointer_motion((0.0, 540.0), &output());
+        assert_eq!(wm.snap_preview(&output()), Some(TileSide::Left.rect(wm.work_area(&output()))));
+        wm.finish_grab(&output());
+        assert!(!wm.has_grab());
+        assert_eq!(geometry(&wm, id), TileSide::Left.rect(wm.work_area(&output())));
+
+        // The top edge away from the sides maximizes
+        wm.set_cursor_position(100.0, 540.0);
+        wm.begin_move(&output());
+        wm.handle_pointer_motion((960.0, 0.0), &output());
+        wm.finish_grab(&output());
+        assert_eq!(geometry(&wm, id), wm.work_area(&output()));
+    }
+
+    #[test]
+    fn dragging_a_window_does_not_allocate() {
+        let mut wm = manager();
+        let (id, _) = open(&mut wm, "a");
+        wm.set_cursor_position(500.0, 500.0);
+        wm.begin_move(&output());
+
+        let motion = allocations(|| {
+            for step in 0..100 {
+                wm.handle_pointer_motion((500.0 + step as f64, 500.0), &output());
+            }
+        });
+        assert_eq!(motion, 0);
+        assert_eq!(geometry(&wm, id).loc.x, (1920 - 800) / 2 + 99);
+    }
+
+    #[test]
+    fn clients_cannot_be_sized_past_their_hints() {
+        let mut wm = manager();
+        let (id, toplevel) = open(&mut wm, "a");
+        toplevel.state().min_size = Size::from((1200, 0));
+        wm.snap_focused(SnapDirection::Left, &output());
+        assert_eq!(geometry(&wm, id).size.w, 1200);
+    }
+
+    #[test]
+    fn tiling_arranges_windows_in_opening_order() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        let (second, _) = open(&mut wm, "b");
+        let (third, _) = open(&mut wm, "c");
+        let floating = geometry(&wm, first);
+
+        wm.toggle_tiling(&output());
+        assert!(wm.is_tiling(1));
+        let layout = wm.tiling_layouts()[0].1;
+        let expected = layout.arrange(wm.work_area(&output()), 3, wm.tiling.gap);
+        assert_eq!(geometry(&wm, first), expected[0]);
+        assert_eq!(geometry(&wm, second), expected[1]);
+        assert_eq!(geometry(&wm, third), expected[2]);
+
+        // A closed window's space goes to the others
+        let closing = wm.window_by_id(second).unwrap().toplevel().clone();
+        wm.remove_window(&closing, &output());
+        let expected = layout.arrange(wm.work_area(&output()), 2, wm.tiling.gap);
+        assert_eq!(geometry(&wm, first), expected[0]);
+        assert_eq!(geometry(&wm, third), expected[1]);
+
+        // Minimized windows leave the layout too
+        wm.focus_id(third);
+        wm.minimize_focused(&output());
+        assert_eq!(geometry(&wm, first), layout.arrange(wm.work_area(&output()), 1, wm.tiling.gap)[0]);
+
+        wm.toggle_tiling(&output());
+        assert!(!wm.is_tiling(1));
+        assert_eq!(geometry(&wm, first), floating);
+    }
+
+    #[test]
+    fn master_ratio_changes_the_master_width() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        open(&mut wm, "b");
+        wm.toggle_tiling(&output());
+        let before = geometry(&wm, first).size.w;
+        wm.adjust_master_ratio(0.1, &output());
+        assert!(geometry(&wm, first).size.w > before);
+        wm.adjust_master_ratio(-0.2, &output());
+        assert!(geometry(&wm, first).size.w < before);
+    }
+
+    #[test]
+    fn minimizing_moves_focus_and_the_taskbar_restores() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        let (second, _) = open(&mut wm, "b");
+
+        wm.minimize_focused(&output());
+        assert!(wm.window_by_id(second).unwrap().is_minimized());
+        assert_eq!(focused_id(&wm), Some(first));
+        assert!(!wm.visible_windows().any(|w| w.id() == second));
+
+        wm.taskbar_activate(second, &output());
+        assert!(!wm.window_by_id(second).unwrap().is_minimized());
+        assert_eq!(focused_id(&wm), Some(second));
+
+        // Clicking the focused window's entry minimizes it
+        wm.taskbar_activate(second, &output());
+        assert!(wm.window_by_id(second).unwrap().is_minimized());
+        assert_eq!(focused_id(&wm), Some(first));
+
+        wm.minimize_focused(&output());
+        assert_eq!(focused_id(&wm), None);
+    }
+
+    #[test]
+    fn focus_follows_workspaces_and_activation() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        let (second, second_toplevel) = open(&mut wm, "b");
+
+        wm.move_focused_to_workspace(2, &output());
+        assert_eq!(wm.window_by_id(second).unwrap().workspace(), 2);
+        assert_eq!(focused_id(&wm), Some(first));
+
+        wm.sync_activation(true);
+        assert!(!second_toplevel.has_state(State::Activated));
+
+        // Activating a window on a hidden workspace switches there
+        assert!(wm.activate(second, &output()));
+        assert_eq!(wm.workspaces().active_workspace(), 2);
+        assert_eq!(focused_id(&wm), Some(second));
+        wm.sync_activation(true);
+        assert!(second_toplevel.has_state(State::Activated));
+
+        wm.switch_workspace(1);
+        assert_eq!(focused_id(&wm), Some(first));
+    }
+
+    #[test]
+    fn clicking_focuses_and_raises_the_topmost_window() {
+        let mut wm = manager();
+        let (first, _) = open(&mut wm, "a");
+        let (second, _) = open(&mut wm, "b");
+        wm.snap_focused(SnapDirection::Right, &output());
+        wm.focus_id(first);
+        wm.snap_focused(SnapDirection::Left, &output());
+
+        wm.focus_at((1500.0, 500.0));
+        assert_eq!(focused_id(&wm), Some(second));
+        assert_eq!(wm.windows().last().map(WindowElement::id), Some(second));
+    }
+
+    #[test]
+    fn close_requests_reach_the_client() {
+        let mut wm = manager();
+        let (id, toplevel) = open(&mut wm, "a");
+        assert!(wm.close(id));
+        assert!(toplevel.state().closed);
+        assert!(!wm.close(id + 1));
+    }
+
+    #[test]
+    fn placements_round_trip() {
+        let mut wm = manager();
+        let (id, _) = open(&mut wm, "a");
+        wm.snap_focused(SnapDirection::Left, &output());
+        wm.move_focused_to_workspace(3, &output());
+        let saved = wm.placement(wm.window_by_id(id).unwrap());
+        assert_eq!(saved.tiled, Some(TileSide::Left));
+        assert_eq!(saved.workspace, 3);
+
+        let (other, _) = open(&mut wm, "a");
+        assert!(wm.restore_placement(other, &saved, &output()));
+        assert_eq!(wm.placement(wm.window_by_id(other).unwrap()), saved);
+    }
+
+    #[test]
+    fn windows_open_and_move_on_further_outputs() {
+        let (mut wm, area) = two_outputs();
+        assert_eq!(wm.workspaces().active_on("TEST-2"), Some(2));
+        assert_eq!(wm.extra_output_area("TEST-2"), Some(area));
+        assert_eq!(wm.extra_output_area("TEST-1"), None);
+
+        wm.switch_workspace(2);
+        let (id, _) = open(&mut wm, "a");
+        let expected = Rectangle::new(
+            Point::from((1920 + (1280 - 800) / 2, (1024 - 600) / 2)),
+            Size::from((800, 600)),
+        );
+        assert_eq!(geometry(&wm, id), expected);
+        assert!(!wm.is_on_first_output(wm.window_by_id(id).unwrap()));
+
+        // Maximized windows fill the whole output: it has no panel
+        wm.toggle_maximized(&output());
+        assert_eq!(geometry(&wm, id), area);
+
+        // Moving the output takes its windows along
+        let moved = Rectangle::new(Point::from((1920, 56)), area.size);
+        wm.set_output_area("TEST-2", moved, &output());
+        assert_eq!(geometry(&wm, id), moved);
+
+        // Unplugging it brings the workspace and its windows back
+        wm.output_removed("TEST-2", &output());
+        assert_eq!(geometry(&wm, id), wm.work_area(&output()));
+        assert!(wm.is_on_first_output(wm.window_by_id(id).unwrap()));
+    }
+
+    #[test]
+    fn the_pointer_crosses_between_outputs() {
+        let (mut wm, _) = two_outputs();
+        wm.set_cursor_position(1900.0, 500.0);
+        assert_eq!(wm.update_cursor_relative(50.0, 0.0, output()), (1950.0, 500.0));
+        // Past the edges of the shorter output it stops at them
+        assert_eq!(wm.update_cursor_relative(0.0, 600.0, output()), (1950.0, 1024.0));
+        assert_eq!(wm.update_cursor_relative(2000.0, 0.0, output()), (3200.0, 1024.0));
+        // and crosses back where the first output continues
+        wm.set_cursor_position(1950.0, 1024.0);
+        assert_eq!(wm.update_cursor_relative(-100.0, 40.0, output()), (1850.0, 1064.0));
+    }
+
+    #[test]
+    fn dropping_a_window_on_another_output_moves_it_there() {
+        let (mut wm, _) = two_outputs();
+        let (id, _) = open(&mut wm, "a");
+        assert_eq!(wm.window_by_id(id).unwrap().workspace(), 1);
+        let before = geometry(&wm, id).loc;
+
+        let start = (960.0, 540.0);
+        wm.set_cursor_position(start.0, start.1);
+        wm.begin_move(&output());
+        wm.set_cursor_position(2500.0, 500.0);
+        wm.handle_pointer_motion((2500.0, 500.0), &output());
+        wm.finish_grab(&output());
+        assert_eq!(wm.window_by_id(id).unwrap().workspace(), 2);
+        assert_eq!(geometry(&wm, id).loc, before + Point::from((1540, -40)));
+    }
+
+    #[test]
+    fn pinned_workspaces_follow_their_output() {
+        let mut config = Config::default();
+        for ws in 6..=9 {
+            config.workspace_outputs.insert(ws, OutputTarget::Secondary);
+        }
+        let mut wm = WindowManager::new(&config);
+        wm.output_added("TEST-1", None, &output());
+        let area = Rectangle::new(Point::from((1920, 0)), Size::from((1280, 1024)));
+        wm.output_added("TEST-2", Some(area), &output());
+        assert_eq!(wm.workspaces().active_on("TEST-2"), Some(6));
+
+        // Switching to a pinned workspace shows it on its output and
+        // leaves the first one as it was
+        wm.switch_workspace(7);
+        assert_eq!(wm.workspaces().active_on("TEST-2"), Some(7));
+        assert_eq!(wm.workspaces().active_on("TEST-1"), Some(1));
+        let (id, _) = open(&mut wm, "a");
+        assert_eq!(wm.window_by_id(id).unwrap().workspace(), 7);
+        assert!(area.contains(geometry(&wm, id).loc));
+
+        // Clicking the first output sends new windows there
+        wm.focus_at((100.0, 500.0));
+        let (other, _) = open(&mut wm, "b");
+        assert_eq!(wm.window_by_id(other).unwrap().workspace(), 1);
+
+        // Unplugged, the workspace migrates; plugged back in, it returns
+        let before = geometry(&wm, id).loc;
+        wm.output_removed("TEST-2", &output());
+        assert_eq!(wm.workspaces().output_of(7), Some("TEST-1"));
+        assert!(!area.contains(geometry(&wm, id).loc));
+        wm.output_added("TEST-2", Some(area), &output());
+        assert_eq!(wm.workspaces().output_of(7), Some("TEST-2"));
+        assert_eq!(geometry(&wm, id).loc, before);
+    }
 }
@@ -0,0 +1,243 @@
+// =============================================================================
+// heyDM — Workspaces
+//
+// Tracks which workspace lives on which output, following the sway model:
+//   - Every output shows exactly one active workspace
+//   - Workspaces can be pinned to an output in the config
+//   - When an output disappears its workspaces migrate to a remaining output
+//     and return to their pinned output when it is reconnected
+// =============================================================================
+
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::{debug, info};
+
+/// Number of workspaces reachable via Super+1..9
+pub const WORKSPACE_COUNT: usize = 9;
+
+/// The output a workspace is pinned to in the config
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    /// The first connected output
+    Primary,
+    /// The second connected output (falls back to primary when absent)
+    Secondary,
+    /// A specific output by connector name (e.g. "HDMI-A-1")
+    Named(String),
+}
+
+impl OutputTarget {
+    /// Parse a config value: "primary", "secondary", or an output name
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "primary" => Self::Primary,
+            "secondary" => Self::Secondary,
+            _ => Self::Named(value.to_string()),
+        }
+    }
+}
+
+/// Workspace ↔ output bookkeeping
+#[derive(Debug, Default)]
+pub struct Workspaces {
+    /// Connected outputs in connection order (first = primary)
+    outputs: Vec<String>,
+    /// Configured pins (workspace → desired output)
+    pins: BTreeMap<usize, OutputTarget>,
+    /// Output each workspace currently lives on
+    assignment: HashMap<usize, String>,
+    /// Visible workspace of every connected output
+    active: HashMap<String, usize>,
+    /// Output that receives new windows and workspace switches
+    focused_output: Option<String>,
+}
+
+#[allow(dead_code)]
+impl Workspaces {
+    /// Create the workspace set with the configured output pins
+    pub fn new(pins: BTreeMap<usize, OutputTarget>) -> Self {
+        Self {
+            pins,
+            ..Self::default()
+        }
+    }
+
+    /// Resolve a pin target against the currently connected outputs
+    fn resolve(&self, target: &OutputTarget) -> Option<&str> {
+        match target {
+            OutputTarget::Primary => self.outputs.first(),
+            OutputTarget::Secondary => self.outputs.get(1).or(self.outputs.first()),
+            OutputTarget::Named(name) => self.outputs.iter().find(|o| *o == name),
+        }
+        .map(String::as_str)
+    }
+
+    /// The output a workspace is pinned to, if that output is connected
+    fn pinned_output(&self, ws: usize) -> Option<&str> {
+        self.pins.get(&ws).and_then(|target| self.resolve(target))
+    }
+
+    /// Whether a workspace is pinned to this very output rather than to a
+    /// missing one that falls back to it
+    fn pinned_here(&self, ws: usize, output: &str) -> bool {
+        match self.pins.get(&ws) {
+            Some(OutputTarget::Secondary) => self.outputs.get(1).is_some_and(|o| o == output),
+            Some(target) => self.resolve(target) == Some(output),
+            None => false,
+        }
+    }
+
+    /// Register a newly connected output
+    pub fn output_added(&mut self, name: &str) {
+        if self.outputs.iter().any(|o| o == name) {
+            return;
+        }
+        self.outputs.push(name.to_string());
+        info!("Output '{name}' connected ({} total)", self.outputs.len());
+
+        // Workspaces pinned here come back from wherever they migrated to
+        for ws in 1..=WORKSPACE_COUNT {
+            if self.pinned_here(ws, name) {
+                if let Some(previous) = self.assignment.insert(ws, name.to_string()) {
+                    if previous != name {
+                        debug!("Workspace {ws} returns from '{previous}' to '{name}'");
+                        if self.active.get(&previous) == Some(&ws) {
+                            self.active.remove(&previous);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Outputs that lost their visible workspace pick another one
+        let outputs = self.outputs.clone();
+        for output in &outputs {
+            if !self.active.contains_key(output) {
+                if let Some(ws) = self.pick_workspace_for(output) {
+                    self.assignment.insert(ws, output.clone());
+                    self.active.insert(output.clone(), ws);
+                }
+            }
+        }
+
+        if self.focused_output.is_none() {
+            self.focused_output = Some(name.to_string());
+        }
+    }
+
+    /// Unregister a disconnected output and migrate its workspaces
+    pub fn output_removed(&mut self, name: &str) {
+        let Some(idx) = self.outputs.iter().position(|o| o == name) else {
+            return;
+        };
+        self.outputs.remove(idx);
+        self.active.remove(name);
+        info!("Output '{name}' disconnected ({} remaining)", self.outputs.len());
+
+        let orphaned: Vec<usize> = self
+            .assignment
+            .iter()
+            .filter(|(_, output)| *output == name)
+            .map(|(ws, _)| *ws)
+            .collect();
+
+        for ws in orphaned {
+            let fallback = self
+                .pinned_output(ws)
+                .or(self.outputs.first().map(String::as_str))
+                .map(str::to_string);
+            match fallback {
+                Some(output) => {
+                    debug!("Workspace {ws} migrates from '{name}' to '{output}'");
+                    self.assignment.insert(ws, output);
+                }
+                None => {
+                    self.assignment.remove(&ws);
+                }
+            }
+        }
+
+        if self.focused_output.as_deref() == Some(name) {
+            self.focused_output = self.outputs.first().cloned();
+        }
+    }
+
+    /// Choose the workspace an output shows when it has none yet
+    fn pick_workspace_for(&self, output: &str) -> Option<usize> {
+        let visible = |ws: &usize| self.active.values().any(|a| a == ws);
+
+        // Prefer workspaces already living on (or pinned to) this output
+        (1..=WORKSPACE_COUNT)
+            .filter(|ws| !visible(ws))
+            .find(|ws| {
+                self.assignment.get(ws).map(String::as_str) == Some(output)
+                    || (self.assignment.get(ws).is_none() && self.pinned_here(*ws, output))
+            })
+            .or_else(|| {
+                (1..=WORKSPACE_COUNT).filter(|ws| !visible(ws)).find(|ws| {
+                    self.assignment.get(ws).is_none() && self.pinned_output(*ws).is_none()
+                })
+            })
+    }
+
+    /// Output the given workspace lives on (or would be opened on)
+    pub fn output_of(&self, ws: usize) -> Option<&str> {
+        self.assignment
+            .get(&ws)
+            .map(String::as_str)
+            .or_else(|| self.pinned_output(ws))
+            .or(self.focused_output.as_deref())
+    }
+
+    /// Make a workspace visible on its output and focus that output
+    pub fn switch_to(&mut self, ws: usize) {
+        if !(1..=WORKSPACE_COUNT).contains(&ws) {
+            return;
+        }
+        let Some(output) = self.output_of(ws).map(str::to_string) else {
+            return;
+        };
+
+        self.assignment.insert(ws, output.clone());
+        self.active.insert(output.clone(), ws);
+        info!("Workspace {ws} now active on '{output}'");
+        self.focused_output = Some(output);
+    }
+
+    /// Send new windows and workspace switches to a connected output
+    pub fn focus_output(&mut self, name: &str) {
+        if self.focused_output.as_deref() != Some(name) && self.outputs.iter().any(|o| o == name) {
+            debug!("Output '{name}' focused");
+            self.focused_output = Some(name.to_string());
+        }
+    }
+
+    /// Workspace visible on the focused output
+    pub fn active_workspace(&self) -> usize {
+        self.focused_output
+            .as_ref()
+            .and_then(|o| self.active.get(o))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Workspace visible on a specific output
+    pub fn active_on(&self, output: &str) -> Option<usize> {
+        self.active.get(output).copied()
+    }
+
+    /// Whether the workspace is currently shown on any output
+    pub fn is_visible(&self, ws: usize) -> bool {
+        self.active.values().any(|a| *a == ws)
+    }
+
+    /// Name of the focused output
+    pub fn focused_output(&self) -> Option<&str> {
+        self.focused_output.as_deref()
+    }
+
+    /// Connected outputs in connection order
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+}
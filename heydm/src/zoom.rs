@@ -0,0 +1,106 @@
+// =============================================================================
+// heyDM — Screen Magnifier
+//
+// Super+scroll zooms the whole output in and out around the pointer, for
+// low-vision users. Works everywhere, including the lock and login screens.
+// The magnified area follows the pointer: its center glides after it and is
+// kept inside the output, so the pointer never leaves the view. Zoom level
+// changes are eased too. Nothing is re-laid out — the renderer scales the
+// finished scene (see render::View), so input keeps working in unzoomed
+// coordinates.
+// =============================================================================
+
+use std::time::Instant;
+
+use smithay::utils::{Physical, Size};
+use tracing::info;
+
+use crate::render::View;
+
+/// Scroll distance for one zoom step (one wheel notch)
+const ZOOM_SCROLL_DISTANCE: f64 = 15.0;
+/// Magnification change per step
+const ZOOM_STEP: f64 = 1.25;
+const MAX_ZOOM: f64 = 16.0;
+/// Seconds for the view to cover most of the way to the pointer or the
+/// new level (exponential ease)
+const EASE_TIME: f64 = 0.08;
+
+#[derive(Debug)]
+pub struct Zoom {
+    /// Magnification being shown; 1.0 is off
+    level: f64,
+    /// Magnification the user scrolled to
+    target: f64,
+    /// Scene point in the middle of the view
+    center: (f64, f64),
+    /// Scroll not yet turned into a step
+    scroll: f64,
+    last_frame: Instant,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            target: 1.0,
+            center: (0.0, 0.0),
+            scroll: 0.0,
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl Zoom {
+    /// Whether the output is magnified (or easing back from it)
+    pub fn is_active(&self) -> bool {
+        self.level > 1.0 || self.target > 1.0
+    }
+
+    /// Super+scroll: positive amounts (scrolling down) zoom out
+    pub fn scroll(&mut self, amount: f64) {
+        self.scroll -= amount;
+        let steps = (self.scroll / ZOOM_SCROLL_DISTANCE).trunc();
+        self.scroll -= steps * ZOOM_SCROLL_DISTANCE;
+        if steps == 0.0 {
+            return;
+        }
+        let target = (self.target * ZOOM_STEP.powf(steps)).clamp(1.0, MAX_ZOOM);
+        if target != self.target {
+            info!("Zoom {target:.2}x");
+            self.target = target;
+        }
+    }
+
+    /// Advance the easing for a new frame and return how to draw it
+    pub fn frame(&mut self, cursor: (f64, f64), size: Size<i32, Physical>) -> View {
+        let now = Instant::now();
+        let dt = now.duration_since(self.last_frame).as_secs_f64();
+        self.last_frame = now;
+
+        if !self.is_active() {
+            self.center = cursor;
+            return View::IDENTITY;
+        }
+        let t = 1.0 - (-dt / EASE_TIME).exp();
+        self.level += (self.target - self.level) * t;
+        if (self.level - self.target).abs() < 0.005 {
+            self.level = self.target;
+        }
+        self.center.0 += (cursor.0 - self.center.0) * t;
+        self.center.1 += (cursor.1 - self.center.1) * t;
+
+        // The view is 1/level of the output; keep it on the output and the
+        // pointer inside it
+        let view = (size.w as f64 / self.level, size.h as f64 / self.level);
+        let place = |center: f64, cursor: f64, len: f64, total: f64| {
+            let origin = (center - len / 2.0).clamp(cursor - len, cursor);
+            origin.clamp(0.0, (total - len).max(0.0))
+        };
+        let origin = (
+            place(self.center.0, cursor.0, view.0, size.w as f64),
+            place(self.center.1, cursor.1, view.1, size.h as f64),
+        );
+        View::new(origin, self.level)
+    }
+}
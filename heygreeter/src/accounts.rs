@@ -0,0 +1,223 @@
+//! Users offered on the login screen.
+//!
+//! Accounts from /etc/passwd whose UID lies within UID_MIN..=UID_MAX from
+//! /etc/login.defs (1000..=60000 when unset), minus those AccountsService
+//! marks as system accounts (`SystemAccount`, asked over D-Bus, else read
+//! from /var/lib/AccountsService/users) and those whose shell refuses logins.
+//! /etc/hey-greeter/config adjusts this:
+//!
+//! ```text
+//! # UID range, overriding login.defs
+//! min_uid = 1000
+//! max_uid = 60000
+//! # Always shown (when they exist), whatever their UID
+//! allow_users = hey
+//! # Never shown
+//! deny_users = guest, backup
+//! # Hide accounts with a nologin or false shell
+//! hide_nologin = true
+//! ```
+
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader};
+
+use tracing::{debug, info};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedObjectPath;
+
+const LOGIN_DEFS: &str = "/etc/login.defs";
+const ACCOUNTS_DIR: &str = "/var/lib/AccountsService/users";
+
+/// The live session user, allowed unless the config says otherwise
+const LIVE_USER: &str = "hey";
+
+const DEFAULT_UID_MIN: u32 = 1000;
+const DEFAULT_UID_MAX: u32 = 60000;
+
+/// Which accounts are offered, from /etc/hey-greeter/config
+#[derive(Debug, Clone)]
+pub struct UserFilter {
+    /// None: UID_MIN / UID_MAX from login.defs
+    pub min_uid: Option<u32>,
+    pub max_uid: Option<u32>,
+    pub allow: HashSet<String>,
+    pub deny: HashSet<String>,
+    pub hide_nologin: bool,
+}
+
+impl Default for UserFilter {
+    fn default() -> Self {
+        Self {
+            min_uid: None,
+            max_uid: None,
+            allow: HashSet::from([LIVE_USER.to_string()]),
+            deny: HashSet::new(),
+            hide_nologin: true,
+        }
+    }
+}
+
+impl UserFilter {
+    /// Apply a config key; Ok(false) when the key is not a user filter key
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        let names = |value: &str| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(String::from)
+                .collect::<HashSet<_>>()
+        };
+        match key {
+            "min_uid" => self.min_uid = Some(parse_uid(value)?),
+            "max_uid" => self.max_uid = Some(parse_uid(value)?),
+            "allow_users" => self.allow = names(value),
+            "deny_users" => self.deny = names(value),
+            "hide_nologin" => match value {
+                "on" | "true" => self.hide_nologin = true,
+                "off" | "false" => self.hide_nologin = false,
+                other => return Err(format!("invalid hide_nologin '{other}'")),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+fn parse_uid(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("invalid uid '{value}'"))
+}
+
+/// An account shown as a tile
+#[derive(Debug, Clone)]
+pub struct User {
+    pub name: String,
+    /// Full name from GECOS, else the login name
+    pub display_name: String,
+}
+
+impl User {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            display_name: display_name(name),
+        }
+    }
+
+    /// First letter of the display name for the tile's avatar
+    pub fn initial(&self) -> String {
+        self.display_name.chars().next().map(|c| c.to_uppercase().collect()).unwrap_or_default()
+    }
+}
+
+/// Users that can log in, in /etc/passwd order; never empty
+pub fn detect(filter: &UserFilter) -> Vec<User> {
+    let (defs_min, defs_max) = uid_range();
+    let uid_min = filter.min_uid.unwrap_or(defs_min);
+    let uid_max = filter.max_uid.unwrap_or(defs_max);
+    let accounts = Connection::system().ok();
+    let mut users = Vec::new();
+
+    if let Ok(file) = std::fs::File::open("/etc/passwd") {
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            let Ok(uid) = parts[2].parse::<u32>() else {
+                continue;
+            };
+            let name = parts[0];
+            if filter.deny.contains(name) {
+                continue;
+            }
+            let shell = parts.get(6).copied().unwrap_or("");
+            if filter.hide_nologin && refuses_login(shell) {
+                debug!("Hiding {name}: shell {shell}");
+                continue;
+            }
+            if filter.allow.contains(name) {
+                users.push(User::new(name));
+                continue;
+            }
+            if !(uid_min..=uid_max).contains(&uid) {
+                continue;
+            }
+            if is_system_account(accounts.as_ref(), name) {
+                debug!("Hiding {name}: AccountsService system account");
+                continue;
+            }
+            users.push(User::new(name));
+        }
+    }
+
+    if users.is_empty() {
+        users.push(User {
+            name: LIVE_USER.to_string(),
+            display_name: LIVE_USER.to_string(),
+        });
+    }
+    info!("Offering {} user(s), UID range {uid_min}..={uid_max}", users.len());
+    users
+}
+
+/// UID_MIN and UID_MAX from /etc/login.defs
+fn uid_range() -> (u32, u32) {
+    let content = std::fs::read_to_string(LOGIN_DEFS).unwrap_or_default();
+    let value = |key: &str| {
+        content.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some(key)).then(|| fields.next()?.parse::<u32>().ok())?
+        })
+    };
+    (
+        value("UID_MIN").unwrap_or(DEFAULT_UID_MIN),
+        value("UID_MAX").unwrap_or(DEFAULT_UID_MAX),
+    )
+}
+
+/// nologin and false shells exist to refuse interactive logins
+fn refuses_login(shell: &str) -> bool {
+    matches!(shell.rsplit('/').next(), Some("nologin" | "false"))
+}
+
+/// Whether AccountsService flags `name` as a system account
+fn is_system_account(connection: Option<&Connection>, name: &str) -> bool {
+    if let Some(flag) = connection.and_then(|c| system_account_flag(c, name).ok()) {
+        return flag;
+    }
+    // No accountsd running: its cache files hold the same flag
+    std::fs::read_to_string(format!("{ACCOUNTS_DIR}/{name}"))
+        .map(|content| content.lines().any(|line| line.trim() == "SystemAccount=true"))
+        .unwrap_or(false)
+}
+
+fn system_account_flag(connection: &Connection, name: &str) -> zbus::Result<bool> {
+    let accounts = Proxy::new(
+        connection,
+        "org.freedesktop.Accounts",
+        "/org/freedesktop/Accounts",
+        "org.freedesktop.Accounts",
+    )?;
+    let path: OwnedObjectPath = accounts.call("FindUserByName", &(name,))?;
+    let user = Proxy::new(
+        connection,
+        "org.freedesktop.Accounts",
+        path,
+        "org.freedesktop.Accounts.User",
+    )?;
+    user.get_property("SystemAccount")
+}
+
+/// Name to greet a user with: the full name from the GECOS field, falling
+/// back to the login name
+fn display_name(user: &str) -> String {
+    use users::os::unix::UserExt;
+    users::get_user_by_name(user)
+        .and_then(|u| {
+            let gecos = u.gecos().to_string_lossy().into_owned();
+            let full_name = gecos.split(',').next().unwrap_or("").trim().to_string();
+            (!full_name.is_empty()).then_some(full_name)
+        })
+        .unwrap_or_else(|| user.to_string())
+}
@@ -0,0 +1,304 @@
+//! Display backend selection for the greeter.
+//!
+//! greetd starts `hey-greeter --launch`, which probes the machine and runs the
+//! real UI under the first usable backend from /etc/hey-greeter/config:
+//!
+//! ```text
+//! # Tried in order until one starts successfully
+//! backends = heydm, kms, weston-kiosk
+//! # auto = use the GPU when a DRM render node exists, on / off to force
+//! acceleration = auto
+//! # off, audit (report leftovers) or strict (see isolation.rs)
+//! isolation = strict
+//! # Hide the session picker when only one session is installed
+//! auto_session = true
+//! ```
+//!
+//! - `heydm`        — the greeter under `heydm --greeter` (kiosk mode of the desktop's
+//!                    own compositor)
+//! - `cage`         — the greeter as a Wayland client of cage (kiosk compositor)
+//! - `kms`          — the greeter drawing straight to KMS via Slint's linuxkms backend
+//! - `weston-kiosk` — the greeter under weston's kiosk shell
+
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::accounts::UserFilter;
+use crate::crashes::SafeMode;
+use crate::isolation::{self, Isolation};
+use crate::notify;
+use crate::session::SessionSettings;
+use crate::throttle::ThrottleConfig;
+
+const CONFIG_PATH: &str = "/etc/hey-greeter/config";
+
+/// A backend that exits faster than this is assumed to have failed to start
+const STARTUP_GRACE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Heydm,
+    Cage,
+    Kms,
+    WestonKiosk,
+}
+
+impl Backend {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "heydm" => Some(Self::Heydm),
+            "cage" => Some(Self::Cage),
+            "kms" => Some(Self::Kms),
+            "weston-kiosk" | "weston" => Some(Self::WestonKiosk),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Heydm => "heydm",
+            Self::Cage => "cage",
+            Self::Kms => "kms",
+            Self::WestonKiosk => "weston-kiosk",
+        }
+    }
+
+    /// Whether everything the backend needs is present
+    fn is_available(self) -> bool {
+        match self {
+            Self::Heydm => find_in_path("heydm"),
+            Self::Cage => find_in_path("cage"),
+            Self::Kms => has_device("/dev/dri", "card"),
+            Self::WestonKiosk => find_in_path("weston"),
+        }
+    }
+
+    /// Command that runs `greeter` (this binary, in UI mode) on this backend
+    fn command(self, greeter: &Path, accelerated: bool) -> Command {
+        match self {
+            // heyDM drives KMS itself, always through the GPU
+            Self::Heydm => {
+                let mut cmd = Command::new("heydm");
+                cmd.arg("--greeter").arg(greeter);
+                cmd
+            }
+            Self::Cage => {
+                let mut cmd = Command::new("cage");
+                cmd.arg("-s").arg("--").arg(greeter);
+                if !accelerated {
+                    cmd.env("WLR_RENDERER", "pixman");
+                    cmd.env("WLR_NO_HARDWARE_CURSORS", "1");
+                }
+                cmd
+            }
+            Self::Kms => {
+                let mut cmd = Command::new(greeter);
+                let renderer = if accelerated { "linuxkms-femtovg" } else { "linuxkms-software" };
+                cmd.env("SLINT_BACKEND", renderer);
+                cmd
+            }
+            Self::WestonKiosk => {
+                let mut cmd = Command::new("weston");
+                cmd.arg("--shell=kiosk-shell.so");
+                if !accelerated {
+                    cmd.arg("--renderer=pixman");
+                }
+                cmd.arg("--").arg(greeter);
+                cmd
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Acceleration {
+    Auto,
+    On,
+    Off,
+}
+
+#[derive(Debug)]
+pub struct LaunchConfig {
+    pub backends: Vec<Backend>,
+    acceleration: Acceleration,
+    isolation: Isolation,
+    /// Skip the session picker when exactly one session is installed
+    pub auto_session: bool,
+    /// Accounts offered on the login screen (see accounts.rs)
+    pub users: UserFilter,
+    /// Delays after failed logins (see throttle.rs)
+    pub throttle: ThrottleConfig,
+    /// Command X11 sessions run under; None picks startx or xinit
+    pub x_wrapper: Option<Vec<String>>,
+    /// session_* keys, applied when the session starts (see session.rs)
+    pub session: SessionSettings,
+    /// Fallback after repeated session crashes (see crashes.rs)
+    pub safe_mode: SafeMode,
+}
+
+impl Default for LaunchConfig {
+    fn default() -> Self {
+        Self {
+            backends: vec![Backend::Heydm, Backend::Kms, Backend::WestonKiosk],
+            acceleration: Acceleration::Auto,
+            isolation: Isolation::Off,
+            auto_session: false,
+            users: UserFilter::default(),
+            throttle: ThrottleConfig::default(),
+            x_wrapper: None,
+            session: SessionSettings::default(),
+            safe_mode: SafeMode::default(),
+        }
+    }
+}
+
+impl LaunchConfig {
+    /// Read /etc/hey-greeter/config, keeping defaults for anything missing
+    pub fn load() -> Self {
+        let mut config = Self::default();
+        let Ok(content) = std::fs::read_to_string(CONFIG_PATH) else {
+            return config;
+        };
+
+        for (num, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                warn!("{}:{}: expected 'key = value'", CONFIG_PATH, num + 1);
+                continue;
+            };
+
+            match key.trim() {
+                "backends" => {
+                    let mut backends = Vec::new();
+                    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                        match Backend::parse(name) {
+                            Some(backend) => backends.push(backend),
+                            None => warn!("{}:{}: unknown backend '{}'", CONFIG_PATH, num + 1, name),
+                        }
+                    }
+                    if !backends.is_empty() {
+                        config.backends = backends;
+                    }
+                }
+                "acceleration" => match value.trim() {
+                    "auto" => config.acceleration = Acceleration::Auto,
+                    "on" | "true" => config.acceleration = Acceleration::On,
+                    "off" | "false" => config.acceleration = Acceleration::Off,
+                    other => warn!("{}:{}: invalid acceleration '{}'", CONFIG_PATH, num + 1, other),
+                },
+                "isolation" => match Isolation::parse(value.trim()) {
+                    Some(mode) => config.isolation = mode,
+                    None => warn!("{}:{}: invalid isolation '{}'", CONFIG_PATH, num + 1, value.trim()),
+                },
+                "auto_session" => match value.trim() {
+                    "on" | "true" => config.auto_session = true,
+                    "off" | "false" => config.auto_session = false,
+                    other => warn!("{}:{}: invalid auto_session '{}'", CONFIG_PATH, num + 1, other),
+                },
+                "x_wrapper" => match shlex::split(value.trim()) {
+                    Some(wrapper) if !wrapper.is_empty() => config.x_wrapper = Some(wrapper),
+                    _ => warn!("{}:{}: invalid x_wrapper '{}'", CONFIG_PATH, num + 1, value.trim()),
+                },
+                other if other.starts_with("session_") => {
+                    match config.session.set(&other["session_".len()..], value.trim()) {
+                        Ok(true) => {}
+                        Ok(false) => warn!("{}:{}: unknown key '{}'", CONFIG_PATH, num + 1, other),
+                        Err(e) => warn!("{}:{}: {}", CONFIG_PATH, num + 1, e),
+                    }
+                }
+                other => match config
+                    .users
+                    .set(other, value.trim())
+                    .and_then(|known| Ok(known || config.throttle.set(other, value.trim())?))
+                    .and_then(|known| Ok(known || config.safe_mode.set(other, value.trim())?))
+                {
+                    Ok(true) => {}
+                    Ok(false) => warn!("{}:{}: unknown key '{}'", CONFIG_PATH, num + 1, other),
+                    Err(e) => warn!("{}:{}: {}", CONFIG_PATH, num + 1, e),
+                },
+            }
+        }
+        config
+    }
+
+    /// Whether the GPU should be used for rendering
+    fn accelerated(&self) -> bool {
+        match self.acceleration {
+            Acceleration::On => true,
+            Acceleration::Off => false,
+            Acceleration::Auto => has_device("/dev/dri", "renderD"),
+        }
+    }
+}
+
+/// Run the greeter UI under the first backend that starts.
+/// Returns only when every configured backend has failed.
+pub fn launch() -> Result<(), Box<dyn std::error::Error>> {
+    let config = LaunchConfig::load();
+    let greeter = std::env::current_exe()?;
+    let accelerated = config.accelerated();
+    info!(
+        "Greeter backends: {:?}, acceleration: {}, isolation: {}",
+        config.backends.iter().map(|b| b.name()).collect::<Vec<_>>(),
+        if accelerated { "on" } else { "off (software rendering)" },
+        config.isolation.name()
+    );
+
+    for backend in &config.backends {
+        if !backend.is_available() {
+            info!("Backend '{}' not available, skipping", backend.name());
+            continue;
+        }
+
+        // Kept alive until the backend exits; removed again on drop
+        let private_runtime = match isolation::prepare(config.isolation) {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Isolation setup failed, not starting '{}': {}", backend.name(), e);
+                continue;
+            }
+        };
+        let mut command = backend.command(&greeter, accelerated);
+        if let Some(runtime) = &private_runtime {
+            runtime.confine(&mut command);
+        }
+        notify::hand_over(&mut command);
+
+        info!("Starting greeter on '{}'", backend.name());
+        let started = Instant::now();
+        match command.status() {
+            Ok(status) if status.success() => return Ok(()),
+            // A backend that ran for a while worked; its exit is the greeter's
+            Ok(status) if started.elapsed() >= STARTUP_GRACE => {
+                warn!("Backend '{}' exited with {}", backend.name(), status);
+                return Ok(());
+            }
+            Ok(status) => error!("Backend '{}' failed to start ({})", backend.name(), status),
+            Err(e) => error!("Backend '{}' could not be run: {}", backend.name(), e),
+        }
+    }
+
+    Err("no greeter backend could be started".into())
+}
+
+/// Whether an executable with this name exists in $PATH
+pub fn find_in_path(program: &str) -> bool {
+    let path = std::env::var("PATH").unwrap_or_else(|_| "/usr/local/bin:/usr/bin:/bin".to_string());
+    path.split(':').any(|dir| Path::new(dir).join(program).is_file())
+}
+
+/// Whether `dir` contains an entry whose name starts with `prefix`
+fn has_device(dir: &str, prefix: &str) -> bool {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        })
+        .unwrap_or(false)
+}
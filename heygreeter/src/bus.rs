@@ -0,0 +1,86 @@
+//! The D-Bus session bus for a new session.
+//!
+//! With systemd's user instance running, dbus.socket (dbus-daemon or
+//! dbus-broker) already listens at $XDG_RUNTIME_DIR/bus and starts the bus
+//! on first use; the session only needs DBUS_SESSION_BUS_ADDRESS pointing
+//! there, and systemd stops it with the user's last session. Without it, a
+//! private dbus-daemon is started for the session and stopped when the
+//! session ends.
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use tracing::{info, warn};
+
+pub enum SessionBus {
+    /// The environment already names a bus
+    Inherited,
+    /// systemd's socket-activated user bus
+    Activated(String),
+    /// A dbus-daemon of our own, stopped on drop
+    Private { daemon: Child, address: String },
+}
+
+impl SessionBus {
+    /// Find or start the session bus; None when there is none to be had
+    pub fn start(env: &[(String, String)]) -> Option<Self> {
+        let inherited = env.iter().any(|(name, _)| name == "DBUS_SESSION_BUS_ADDRESS")
+            || std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some();
+        if inherited {
+            return Some(Self::Inherited);
+        }
+
+        if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+            let socket = PathBuf::from(runtime_dir).join("bus");
+            if socket.exists() {
+                info!("Using the user bus at {}", socket.display());
+                return Some(Self::Activated(format!("unix:path={}", socket.display())));
+            }
+        }
+
+        let spawned = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--nopidfile", "--print-address=1"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn();
+        let mut daemon = match spawned {
+            Ok(daemon) => daemon,
+            Err(e) => {
+                warn!("No session bus: cannot start dbus-daemon: {e}");
+                return None;
+            }
+        };
+        let mut address = String::new();
+        if let Some(stdout) = daemon.stdout.take() {
+            let _ = BufReader::new(stdout).read_line(&mut address);
+        }
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            warn!("No session bus: dbus-daemon did not report its address");
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+            return None;
+        }
+        info!("Started a session bus at {address}");
+        Some(Self::Private { daemon, address })
+    }
+
+    /// Value for DBUS_SESSION_BUS_ADDRESS, unless already set
+    pub fn address(&self) -> Option<&str> {
+        match self {
+            Self::Inherited => None,
+            Self::Activated(address) | Self::Private { address, .. } => Some(address),
+        }
+    }
+}
+
+impl Drop for SessionBus {
+    fn drop(&mut self) {
+        if let Self::Private { daemon, .. } = self {
+            info!("Stopping the session bus");
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+        }
+    }
+}
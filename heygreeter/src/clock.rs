@@ -0,0 +1,80 @@
+//! Clock, date and hostname on the login screen.
+//!
+//! Updated every second and formatted for the system locale (LC_ALL,
+//! LC_TIME or LANG, else /etc/locale.conf), so weekday and month names come
+//! out in the machine's language. The formats, whether the hostname shows
+//! and where the block sits are theme keys (see theme.rs):
+//!
+//! ```toml
+//! clock_position = "center"   # center, top-right, bottom-left or hidden
+//! time_format = "%H:%M"       # strftime, see chrono::format::strftime
+//! date_format = "%A, %B %e"
+//! show_hostname = true
+//! ```
+
+use std::time::Duration;
+
+use chrono::Locale;
+use tracing::info;
+
+use crate::theme::Theme;
+use crate::AppWindow;
+
+const DEFAULT_TIME_FORMAT: &str = "%H:%M";
+const DEFAULT_DATE_FORMAT: &str = "%A, %B %e";
+
+/// Start updating the clock; it runs as long as the returned timer lives
+pub fn start(app: &AppWindow, theme: &Theme) -> slint::Timer {
+    let locale = system_locale();
+    let time_format = theme.time_format.clone().unwrap_or_else(|| DEFAULT_TIME_FORMAT.to_string());
+    let date_format = theme.date_format.clone().unwrap_or_else(|| DEFAULT_DATE_FORMAT.to_string());
+    if theme.show_hostname.unwrap_or(true) {
+        if let Some(hostname) = hostname() {
+            app.set_hostname(hostname.into());
+        }
+    }
+
+    let update = {
+        let handle = app.as_weak();
+        move || {
+            let Some(app) = handle.upgrade() else { return };
+            let now = chrono::Local::now();
+            app.set_current_time(now.format_localized(&time_format, locale).to_string().into());
+            app.set_current_date(now.format_localized(&date_format, locale).to_string().into());
+        }
+    };
+    update();
+    let timer = slint::Timer::default();
+    timer.start(slint::TimerMode::Repeated, Duration::from_secs(1), update);
+    timer
+}
+
+/// The locale dates are formatted in; POSIX when unset or unknown
+fn system_locale() -> Locale {
+    let from_env = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    let name = from_env.or_else(|| {
+        let conf = std::fs::read_to_string("/etc/locale.conf").ok()?;
+        ["LC_TIME=", "LANG="].iter().find_map(|key| {
+            conf.lines()
+                .find_map(|line| line.trim().strip_prefix(key))
+                .map(|value| value.trim_matches('"').to_string())
+        })
+    });
+    // "de_DE.UTF-8@euro" -> "de_DE"
+    let name = name.unwrap_or_default();
+    let name = name.split(['.', '@']).next().unwrap_or("");
+    let locale = Locale::try_from(name).unwrap_or(Locale::POSIX);
+    info!("Formatting dates for locale {locale:?}");
+    locale
+}
+
+fn hostname() -> Option<String> {
+    let name = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
@@ -0,0 +1,311 @@
+//! Session crash detection and the safe-mode fallback.
+//!
+//! The session wrapper (see session.rs) stays the session's parent, keeps
+//! the last STDERR_LINES lines of its stderr (still passing them on to the
+//! journal) and, when the session exits, writes a record to
+//! /run/hey-greeter/session-<uid>.<random>.json and removes its older ones.
+//! A session that fails within
+//! RAPID_EXIT counts as a crash; crashes in a row are counted, anything
+//! else resets the count.
+//!
+//! A session that could not be started at all (a missing binary, say) is
+//! recorded as a crash too. For a crash the record also keeps the user's
+//! warnings and errors from the journal since the session started, which
+//! catch what the session did not print itself (a D-Bus activated service
+//! failing, a unit of the user manager, ...).
+//!
+//! The next login screen shows a notice for a crash that ended within
+//! NOTICE_WINDOW, with the stderr tail and the journal lines under Details
+//! as a diagnostics panel. From `safe_mode_after`
+//! crashes in a row on, it offers a fallback session, `safe_session` from
+//! /etc/hey-greeter/config (default: a terminal in cage).
+//!
+//! The directory is world-writable (sticky, see tmpfiles.d/hey-greeter.conf).
+//! Records are created exclusively under a name nobody can guess, so another
+//! user cannot plant a file or symlink in their place, and are only trusted
+//! when they are regular files owned by the uid in their name, so users
+//! cannot fake crash reports for each other.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+const RECORD_DIR: &str = "/run/hey-greeter";
+
+/// A session ending with an error sooner than this crashed
+const RAPID_EXIT: Duration = Duration::from_secs(15);
+
+/// Lines of stderr kept for the notice
+const STDERR_LINES: usize = 40;
+
+/// Larger files in the record directory are not records
+const MAX_RECORD_BYTES: u64 = 1 << 20;
+
+/// Journal lines kept for the notice
+const JOURNAL_LINES: usize = 20;
+
+/// journalctl is given this long before the notice does without it
+const JOURNAL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Crashes older than this are not shown any more
+const NOTICE_WINDOW: Duration = Duration::from_secs(600);
+
+/// Session id the picker uses for the fallback session
+pub const SAFE_MODE_ID: &str = "Safe mode";
+
+/// How one session run ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub user: String,
+    pub command: Vec<String>,
+    /// "exit code 1", "signal 11", ...
+    pub status: String,
+    /// Seconds since the epoch
+    pub ended: u64,
+    /// Crashes in a row, including this run; 0 when it did not crash
+    pub crashes: u32,
+    pub stderr: Vec<String>,
+    /// The user's warnings and errors in the journal during the session
+    #[serde(default)]
+    pub journal: Vec<String>,
+}
+
+/// Fallback session settings from /etc/hey-greeter/config
+#[derive(Debug, Clone)]
+pub struct SafeMode {
+    /// Crashes in a row before the fallback is offered; 0 never offers it
+    pub after: u32,
+    pub command: Vec<String>,
+}
+
+impl Default for SafeMode {
+    fn default() -> Self {
+        Self {
+            after: 2,
+            command: ["cage", "-s", "--", "foot"].map(String::from).to_vec(),
+        }
+    }
+}
+
+impl SafeMode {
+    /// Apply a config key; Ok(false) when the key is not a safe mode key
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        match key {
+            "safe_mode_after" => {
+                self.after = value
+                    .parse()
+                    .map_err(|_| format!("invalid safe_mode_after '{value}'"))?;
+            }
+            "safe_session" => match shlex::split(value) {
+                Some(command) if !command.is_empty() => self.command = command,
+                _ => return Err(format!("invalid safe_session '{value}'")),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Run the session, keep its stderr tail and record how it ended
+pub fn supervise(mut command: Command, argv: &[String]) -> std::io::Result<ExitStatus> {
+    let started = Instant::now();
+    let since = SystemTime::now();
+    let mut child = match command.stderr(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            write_record(format!("could not start: {e}"), true, argv, Vec::new(), Vec::new());
+            return Err(e);
+        }
+    };
+
+    let tail = Arc::new(Mutex::new(VecDeque::with_capacity(STDERR_LINES)));
+    let reader = child.stderr.take().map(|stderr| {
+        let tail = tail.clone();
+        std::thread::spawn(move || {
+            let mut journal = std::io::stderr();
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let _ = writeln!(journal, "{line}");
+                let mut tail = tail.lock().unwrap_or_else(|e| e.into_inner());
+                if tail.len() == STDERR_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(line);
+            }
+        })
+    });
+
+    let status = child.wait()?;
+    if let Some(reader) = reader {
+        let _ = reader.join();
+    }
+    let crashed = !status.success() && started.elapsed() < RAPID_EXIT;
+    let stderr = tail.lock().unwrap_or_else(|e| e.into_inner()).drain(..).collect();
+    let journal = if crashed { journal_since(since) } else { Vec::new() };
+    write_record(describe(status), crashed, argv, stderr, journal);
+    Ok(status)
+}
+
+/// The user's journal entries of priority warning or worse since `since`
+fn journal_since(since: SystemTime) -> Vec<String> {
+    let Ok(since) = since.duration_since(UNIX_EPOCH) else {
+        return Vec::new();
+    };
+    let child = Command::new("journalctl")
+        .args(["--quiet", "--no-pager", "--output=short", "--priority=warning"])
+        .arg(format!("--since=@{}", since.as_secs()))
+        .arg(format!("--lines={JOURNAL_LINES}"))
+        .arg(format!("_UID={}", users::get_current_uid()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            info!("No journal lines for the crash report: {e}");
+            return Vec::new();
+        }
+    };
+    // Read on a thread so a hanging journalctl cannot keep the session's
+    // exit from reaching greetd
+    let stdout = child.stdout.take();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let lines: Vec<String> = stdout
+            .map(|out| BufReader::new(out).lines().map_while(Result::ok).collect())
+            .unwrap_or_default();
+        let _ = sender.send(lines);
+    });
+    let lines = receiver.recv_timeout(JOURNAL_TIMEOUT).unwrap_or_else(|_| {
+        warn!("journalctl did not answer within {JOURNAL_TIMEOUT:?}");
+        Vec::new()
+    });
+    let _ = child.kill();
+    let _ = child.wait();
+    lines
+}
+
+fn write_record(status: String, crashed: bool, argv: &[String], stderr: Vec<String>, journal: Vec<String>) {
+    let uid = users::get_current_uid();
+    let own: Vec<(PathBuf, SessionRecord)> = records()
+        .into_iter()
+        .filter(|(_, owner, _)| *owner == uid)
+        .map(|(path, _, record)| (path, record))
+        .collect();
+    let previous = own
+        .iter()
+        .map(|(_, record)| record)
+        .max_by_key(|record| record.ended)
+        .map_or(0, |record| record.crashes);
+    let record = SessionRecord {
+        user: users::get_current_username()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| uid.to_string()),
+        command: argv.to_vec(),
+        status,
+        ended: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        crashes: if crashed { previous + 1 } else { 0 },
+        stderr,
+        journal,
+    };
+    if crashed {
+        warn!("Session crashed ({}), {} time(s) in a row", record.status, record.crashes);
+    }
+    let written = serde_json::to_vec(&record)
+        .map_err(std::io::Error::other)
+        .and_then(|json| create_record(uid)?.write_all(&json));
+    if let Err(e) = written {
+        warn!("Could not write a session record to {RECORD_DIR}: {e}");
+        return;
+    }
+    for (path, _) in own {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn describe(status: ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exit code {code}"),
+        (None, Some(signal)) => format!("signal {signal}"),
+        _ => status.to_string(),
+    }
+}
+
+/// A new record file under an unguessable name. O_EXCL and O_NOFOLLOW
+/// refuse anything already there, a symlink included.
+fn create_record(uid: u32) -> std::io::Result<File> {
+    let mut token = [0u8; 8];
+    File::open("/dev/urandom")?.read_exact(&mut token)?;
+    let name = format!("session-{uid}.{:016x}.json", u64::from_ne_bytes(token));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o644)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(Path::new(RECORD_DIR).join(name))
+}
+
+/// Every trusted record with its path and uid
+fn records() -> Vec<(PathBuf, u32, SessionRecord)> {
+    let Ok(entries) = std::fs::read_dir(RECORD_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (uid, _) = name.strip_prefix("session-")?.strip_suffix(".json")?.split_once('.')?;
+            let uid = uid.parse().ok()?;
+            let path = entry.path();
+            let record = read_record(&path, uid)?;
+            Some((path, uid, record))
+        })
+        .collect()
+}
+
+/// The record at `path`, if it is a regular file `uid` owns. O_NONBLOCK
+/// keeps a planted FIFO from hanging the greeter.
+fn read_record(path: &Path, uid: u32) -> Option<SessionRecord> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path)
+        .ok()?;
+    let metadata = file.metadata().ok()?;
+    if !metadata.is_file() || metadata.uid() != uid {
+        warn!("Ignoring {}: not a file owned by uid {uid}", path.display());
+        return None;
+    }
+    let mut json = Vec::new();
+    file.take(MAX_RECORD_BYTES).read_to_end(&mut json).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// The most recent crash still worth a notice on the login screen
+pub fn recent() -> Option<SessionRecord> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    // Only each user's newest record counts, in case removing an older one failed
+    let mut latest: HashMap<u32, SessionRecord> = HashMap::new();
+    for (_, uid, record) in records() {
+        if !latest.get(&uid).is_some_and(|newest| newest.ended >= record.ended) {
+            latest.insert(uid, record);
+        }
+    }
+    let crash = latest
+        .into_values()
+        .filter(|record| record.crashes > 0 && now.saturating_sub(record.ended) < NOTICE_WINDOW.as_secs())
+        .max_by_key(|record| record.ended)?;
+    info!("Session of {} crashed ({}) {} time(s) in a row", crash.user, crash.status, crash.crashes);
+    Some(crash)
+}
@@ -0,0 +1,221 @@
+//! Detection of the greetd daemon and recovery when it is unreachable.
+//!
+//! greetd passes its socket in `GREETD_SOCK`. When the variable is missing or
+//! points at a socket nobody listens on (greetd crashed or was restarted
+//! under a new pid), the greeter looks for a live `/run/greetd-<pid>.sock`
+//! instead. While no socket answers, the UI shows a recovery panel that
+//! retries every RETRY_INTERVAL seconds with a countdown, lists the sockets
+//! tried and the state of greetd.service from systemd (over D-Bus), and, when
+//! the greeter runs as root, offers to restart greetd.service.
+
+use std::cell::RefCell;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Duration;
+
+use tracing::{info, warn};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::AppWindow;
+
+/// Seconds between reconnection attempts
+const RETRY_INTERVAL: i32 = 5;
+
+/// Directory greetd creates its sockets in
+const RUNTIME_DIR: &str = "/run";
+
+const UNIT: &str = "greetd.service";
+
+/// The greetd socket that answered last, shared by the login handler and the
+/// watchdog
+pub type SharedSocket = Rc<RefCell<Option<PathBuf>>>;
+
+/// Outcome of looking for greetd
+struct Status {
+    /// Socket that accepted a connection
+    socket: Option<PathBuf>,
+    /// One-line reason greetd is unreachable
+    problem: String,
+    /// Report for the diagnostics panel, one fact per line
+    details: String,
+}
+
+/// Find a socket greetd is listening on
+fn probe() -> Status {
+    let mut details = Vec::new();
+    let configured = std::env::var("GREETD_SOCK")
+        .ok()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    match &configured {
+        Some(path) => details.push(format!("GREETD_SOCK = {}", path.display())),
+        None => details.push("GREETD_SOCK is not set".to_string()),
+    }
+
+    let candidates: Vec<PathBuf> = configured
+        .iter()
+        .cloned()
+        .chain(runtime_sockets().into_iter().filter(|p| Some(p) != configured.as_ref()))
+        .collect();
+    let mut problem = None;
+    for path in &candidates {
+        match UnixStream::connect(path) {
+            Ok(_) => {
+                details.push(format!("{}: accepting connections", path.display()));
+                return Status {
+                    socket: Some(path.clone()),
+                    problem: String::new(),
+                    details: details.join("\n"),
+                };
+            }
+            Err(e) => {
+                details.push(format!("{}: {e}", path.display()));
+                problem.get_or_insert_with(|| format!("cannot connect to greetd ({e})"));
+            }
+        }
+    }
+    if candidates.is_empty() {
+        details.push(format!("No greetd-*.sock in {RUNTIME_DIR}"));
+    }
+
+    let unit = unit_state().unwrap_or_else(|e| format!("unknown ({e})"));
+    details.push(format!("{UNIT}: {unit}"));
+    Status {
+        socket: None,
+        problem: problem.unwrap_or_else(|| "greetd is not running".to_string()),
+        details: details.join("\n"),
+    }
+}
+
+/// greetd sockets in the runtime dir, newest first
+fn runtime_sockets() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(RUNTIME_DIR) else {
+        return Vec::new();
+    };
+    let mut sockets: Vec<(std::time::SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with("greetd-") && name.ends_with(".sock")
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    sockets.sort_by(|a, b| b.0.cmp(&a.0));
+    sockets.into_iter().map(|(_, path)| path).collect()
+}
+
+/// systemd manager proxy on the system bus
+fn systemd(connection: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        connection,
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        "org.freedesktop.systemd1.Manager",
+    )
+}
+
+/// "active (running)", "failed (failed)", ... of greetd.service
+fn unit_state() -> zbus::Result<String> {
+    let connection = Connection::system()?;
+    let path: OwnedObjectPath = systemd(&connection)?.call("LoadUnit", &(UNIT,))?;
+    let unit = Proxy::new(
+        &connection,
+        "org.freedesktop.systemd1",
+        path,
+        "org.freedesktop.systemd1.Unit",
+    )?;
+    let active: String = unit.get_property("ActiveState")?;
+    let sub: String = unit.get_property("SubState")?;
+    Ok(format!("{active} ({sub})"))
+}
+
+/// Whether restarting greetd.service is allowed without asking polkit
+fn can_restart() -> bool {
+    users::get_effective_uid() == 0
+}
+
+/// Ask systemd to restart greetd.service
+fn restart() -> zbus::Result<()> {
+    let connection = Connection::system()?;
+    let _job: OwnedObjectPath = systemd(&connection)?.call("RestartUnit", &(UNIT, "replace"))?;
+    Ok(())
+}
+
+/// Probe greetd now, remember the socket and show or hide the recovery panel
+pub fn refresh(app: &AppWindow, socket: &SharedSocket) {
+    let status = probe();
+    match &status.socket {
+        Some(path) => info!("greetd reachable at {}", path.display()),
+        None => warn!("greetd unreachable: {}", status.problem),
+    }
+    app.set_daemon_unavailable(status.socket.is_none());
+    app.set_daemon_problem(status.problem.into());
+    app.set_daemon_details(status.details.into());
+    app.set_can_restart_daemon(can_restart());
+    app.set_reconnect_countdown(RETRY_INTERVAL);
+    *socket.borrow_mut() = status.socket;
+}
+
+/// Probe greetd once and keep retrying every RETRY_INTERVAL seconds while it
+/// is unreachable. The returned timer must be kept alive.
+pub fn watch(app: &AppWindow, socket: SharedSocket) -> slint::Timer {
+    refresh(app, &socket);
+
+    let retry_handle = app.as_weak();
+    let retry_socket = socket.clone();
+    app.on_retry_daemon(move || {
+        if let Some(app) = retry_handle.upgrade() {
+            refresh(&app, &retry_socket);
+        }
+    });
+
+    let restart_handle = app.as_weak();
+    app.on_restart_daemon(move || {
+        let Some(app) = restart_handle.upgrade() else { return };
+        info!("Restarting {UNIT}");
+        match restart() {
+            // greetd starts a fresh greeter; this one must not hold the VT
+            Ok(()) => std::process::exit(0),
+            Err(e) => {
+                warn!("Failed to restart {UNIT}: {e}");
+                app.set_daemon_problem(format!("restarting {UNIT} failed: {e}").into());
+            }
+        }
+    });
+
+    let timer = slint::Timer::default();
+    let handle = app.as_weak();
+    timer.start(slint::TimerMode::Repeated, Duration::from_secs(1), move || {
+        let Some(app) = handle.upgrade() else { return };
+        if !app.get_daemon_unavailable() {
+            return;
+        }
+        let left = app.get_reconnect_countdown() - 1;
+        if left > 0 {
+            app.set_reconnect_countdown(left);
+        } else {
+            refresh(&app, &socket);
+        }
+    });
+    timer
+}
+
+/// Connect to greetd, looking for it again if the remembered socket stopped
+/// answering. On failure the recovery panel is shown and the error returned
+/// for the login form.
+pub fn connect(app: &AppWindow, socket: &SharedSocket) -> Result<UnixStream, String> {
+    let remembered = socket.borrow().clone();
+    if let Some(stream) = remembered.and_then(|path| UnixStream::connect(path).ok()) {
+        return Ok(stream);
+    }
+    refresh(app, socket);
+    let path = socket.borrow().clone();
+    match path {
+        Some(path) => UnixStream::connect(&path).map_err(|e| {
+            format!("Failed to connect to login manager at {}: {e}", path.display())
+        }),
+        None => Err(format!("Login manager unavailable: {}", app.get_daemon_problem())),
+    }
+}
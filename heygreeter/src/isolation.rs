@@ -0,0 +1,206 @@
+//! Greeter isolation from previous sessions.
+//!
+//! Selected with `isolation = off | audit | strict` in /etc/hey-greeter/config.
+//! Before every backend spawn:
+//!
+//! - `audit`  reports leftovers from earlier sessions and greeter runs
+//!   (Wayland sockets and locks in XDG_RUNTIME_DIR, clipboard and compositor
+//!   temp files in /tmp, inherited display environment) without touching them
+//! - `strict` removes those leftovers and runs the backend with a fresh,
+//!   private XDG_RUNTIME_DIR and a scrubbed environment, so the greeter gets
+//!   its own Wayland socket, cannot reach a prior session's sockets and
+//!   starts with an empty clipboard
+//!
+//! Only known artifact names owned by the greeter's own user are removed,
+//! and nothing at all while that user still has another logind session
+//! (e.g. a greeter started to switch users from a running session). Sockets
+//! that still accept connections and their locks are always left alone.
+
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+/// Runtime dir entries left behind by compositors and earlier greeter runs
+const RUNTIME_ARTIFACTS: &[&str] = &["wayland-", "heydm-", "hey-greeter-", "wl-copy-"];
+
+/// /tmp entries left behind by the backends and clipboard tools
+const TMP_ARTIFACTS: &[&str] = &["hey-greeter", "cage", "weston", "wl-copy-buffer-", "clipman"];
+
+/// Variables that would point the greeter at another session's display,
+/// clipboard or bus
+const SESSION_ENV: &[&str] = &[
+    "WAYLAND_DISPLAY",
+    "WAYLAND_SOCKET",
+    "DISPLAY",
+    "XAUTHORITY",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "HEYDM_SOCK",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Isolation {
+    Off,
+    Audit,
+    Strict,
+}
+
+impl Isolation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" | "false" => Some(Self::Off),
+            "audit" => Some(Self::Audit),
+            "strict" | "on" | "true" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Audit => "audit",
+            Self::Strict => "strict",
+        }
+    }
+}
+
+/// A private runtime directory for one backend run; removed on drop
+pub struct PrivateRuntime {
+    path: PathBuf,
+}
+
+impl PrivateRuntime {
+    /// Point the backend at the private runtime dir and drop inherited
+    /// session variables
+    pub fn confine(&self, cmd: &mut Command) {
+        for var in SESSION_ENV {
+            cmd.env_remove(var);
+        }
+        cmd.env("XDG_RUNTIME_DIR", &self.path);
+    }
+}
+
+impl Drop for PrivateRuntime {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!("Could not remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Check (and in strict mode clean) the environment before a backend spawn.
+/// Returns the private runtime dir the backend must run in, if any.
+pub fn prepare(mode: Isolation) -> Result<Option<PrivateRuntime>, Box<dyn std::error::Error>> {
+    if mode == Isolation::Off {
+        return Ok(None);
+    }
+    let strict = mode == Isolation::Strict;
+    let uid = users::get_current_uid();
+    let remove = strict && !has_other_session(uid);
+
+    for var in SESSION_ENV {
+        if std::env::var_os(var).is_some() {
+            report(strict, &format!("inherited ${var}"));
+        }
+    }
+
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(format!("/run/user/{uid}")));
+    let mut found = sweep(&runtime_dir, RUNTIME_ARTIFACTS, uid, remove);
+    found += sweep(Path::new("/tmp"), TMP_ARTIFACTS, uid, remove);
+    if found == 0 {
+        info!("Isolation: no leftovers from previous sessions");
+    }
+
+    if !strict {
+        return Ok(None);
+    }
+
+    let path = runtime_dir.join(format!("hey-greeter-{}", std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&path)?;
+    info!("Isolation: backend runtime dir {}", path.display());
+    Ok(Some(PrivateRuntime { path }))
+}
+
+/// Whether `uid` has a logind session besides the greeter's own. When
+/// logind cannot be asked, assume it does.
+fn has_other_session(uid: u32) -> bool {
+    let output = Command::new("loginctl")
+        .args(["show-user", &uid.to_string(), "--property=Sessions", "--value"])
+        .output();
+    let sessions = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        }
+        // No logind user record: nobody is logged in as us
+        Ok(_) => return false,
+        Err(e) => {
+            warn!("Isolation: could not ask logind for sessions ({e}), not removing anything");
+            return true;
+        }
+    };
+    let own = std::env::var("XDG_SESSION_ID").unwrap_or_default();
+    let other = sessions.split_whitespace().any(|id| id != own);
+    if other {
+        info!("Isolation: uid {uid} has a running session, leaving its files alone");
+    }
+    other
+}
+
+/// Whether a socket still has a listener, or a lock belongs to such a socket
+fn in_use(path: &Path, meta: &std::fs::Metadata) -> bool {
+    let socket = match path.to_str().and_then(|path| path.strip_suffix(".lock")) {
+        Some(socket) => PathBuf::from(socket),
+        None if meta.file_type().is_socket() => path.to_path_buf(),
+        None => return false,
+    };
+    UnixStream::connect(socket).is_ok()
+}
+
+/// Report (and in strict mode remove) entries of `dir` owned by `uid` whose
+/// names start with one of `prefixes`. Returns how many were found.
+fn sweep(dir: &Path, prefixes: &[&str], uid: u32, remove: bool) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+
+    let mut found = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !prefixes.iter().any(|prefix| name.starts_with(prefix)) {
+            continue;
+        }
+        let path = entry.path();
+        let Ok(meta) = std::fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if meta.uid() != uid || in_use(&path, &meta) {
+            continue;
+        }
+
+        found += 1;
+        report(remove, &path.display().to_string());
+        if remove {
+            let result = if meta.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            if let Err(e) = result {
+                warn!("Isolation: could not remove {}: {}", path.display(), e);
+            }
+        }
+    }
+    found
+}
+
+fn report(removing: bool, what: &str) {
+    if removing {
+        info!("Isolation: removing leftover {}", what);
+    } else {
+        warn!("Isolation audit: leftover {}", what);
+    }
+}
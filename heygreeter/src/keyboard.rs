@@ -0,0 +1,103 @@
+//! Caps Lock warning and keyboard layout next to the password field.
+//!
+//! Caps Lock is read from the keyboard LEDs in /sys/class/leds
+//! (`input*::capslock`), which the kernel console and compositors keep in
+//! sync with the lock state, so it works on every backend. The layout comes
+//! from systemd-localed (the X11 layout, else the console keymap), or from
+//! XKB_DEFAULT_LAYOUT and /etc/vconsole.conf when localed is not running.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tracing::{debug, info};
+use zbus::blocking::{Connection, Proxy};
+
+use crate::AppWindow;
+
+const LEDS_DIR: &str = "/sys/class/leds";
+
+/// How often the Caps Lock LED is read
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Show the layout and follow Caps Lock while the returned timer lives
+pub fn start(app: &AppWindow) -> slint::Timer {
+    if let Some(layout) = layout() {
+        info!("Keyboard layout: {layout}");
+        app.set_keyboard_layout(layout.into());
+    }
+
+    let leds = capslock_leds();
+    let timer = slint::Timer::default();
+    if leds.is_empty() {
+        debug!("No Caps Lock LED found, not showing the warning");
+        return timer;
+    }
+    let handle = app.as_weak();
+    timer.start(slint::TimerMode::Repeated, POLL_INTERVAL, move || {
+        let Some(app) = handle.upgrade() else { return };
+        // Any keyboard with Caps Lock on counts
+        let on = leds.iter().any(|led| {
+            std::fs::read_to_string(led)
+                .map(|value| value.trim() != "0")
+                .unwrap_or(false)
+        });
+        if app.get_caps_lock() != on {
+            app.set_caps_lock(on);
+        }
+    });
+    timer
+}
+
+/// brightness files of every keyboard's Caps Lock LED
+fn capslock_leds() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(LEDS_DIR) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with("::capslock"))
+        .map(|entry| entry.path().join("brightness"))
+        .collect()
+}
+
+/// Short name of the active layout, e.g. "us" or "de"
+fn layout() -> Option<String> {
+    let from_localed = localed_layout().unwrap_or_else(|e| {
+        debug!("localed unavailable: {e}");
+        None
+    });
+    from_localed
+        .or_else(|| std::env::var("XKB_DEFAULT_LAYOUT").ok())
+        .or_else(vconsole_keymap)
+        .map(|layout| first_layout(&layout))
+        .filter(|layout| !layout.is_empty())
+}
+
+fn localed_layout() -> zbus::Result<Option<String>> {
+    let connection = Connection::system()?;
+    let localed = Proxy::new(
+        &connection,
+        "org.freedesktop.locale1",
+        "/org/freedesktop/locale1",
+        "org.freedesktop.locale1",
+    )?;
+    let x11: String = localed.get_property("X11Layout")?;
+    if !x11.is_empty() {
+        return Ok(Some(x11));
+    }
+    let keymap: String = localed.get_property("VConsoleKeymap")?;
+    Ok((!keymap.is_empty()).then_some(keymap))
+}
+
+fn vconsole_keymap() -> Option<String> {
+    let conf = std::fs::read_to_string("/etc/vconsole.conf").ok()?;
+    conf.lines()
+        .find_map(|line| line.trim().strip_prefix("KEYMAP="))
+        .map(|keymap| keymap.trim_matches('"').to_string())
+}
+
+/// "de,us" -> "de"; console keymaps like "de-latin1" -> "de"
+fn first_layout(layout: &str) -> String {
+    let first = layout.split(',').next().unwrap_or("").trim();
+    first.split(['-', '_']).next().unwrap_or(first).to_string()
+}
@@ -0,0 +1,284 @@
+//! The greetd conversation, off the UI thread.
+//!
+//! PAM stacks can take seconds (fingerprint readers, network accounts,
+//! pam_faillock's delays), so each login attempt talks to greetd on its own
+//! "hey-greeter-login" thread and reports back over a channel that the UI
+//! polls while the attempt runs. The UI shows the reported progress with a
+//! spinner and a Cancel button.
+//!
+//! PAM may send any number of messages: the first secret prompt is answered
+//! with the password from the login card, info and error messages are shown
+//! as progress, and further prompts (one-time codes, a second factor) are
+//! put to the user through the password field. Whenever the attempt ends
+//! without a session (failure, cancel, or greetd or the user not answering
+//! within the timeouts) greetd gets a CancelSession, so the next attempt
+//! starts from a clean state. Cancelling waits (up to CANCEL_TIMEOUT per
+//! step) for the login thread to get greetd's answer to it, so the next
+//! attempt's CreateSession cannot reach greetd first.
+
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use greetd_ipc::codec::SyncCodec;
+use greetd_ipc::{AuthMessageType, ErrorType, Request, Response};
+use tracing::{info, warn};
+
+/// How long greetd (that is, PAM) may take to answer
+const GREETD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long a PAM prompt waits for the user
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long a cancel waits for the login thread to finish
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the login thread reports to the UI
+#[derive(Debug)]
+pub enum Event {
+    /// A step of the attempt or a PAM info message, for the progress text
+    Progress(String),
+    /// PAM asks something beyond the password; answer with `Attempt::answer`
+    Prompt { message: String, secret: bool },
+    /// The password was accepted; the session is being started
+    Authenticated,
+    /// The attempt ended without a session; `denied` when authentication
+    /// itself failed (wrong password, locked account), for throttling
+    Failed { reason: String, denied: bool },
+    /// greetd started the session; the greeter should exit
+    Started,
+}
+
+/// What to log in as and what to run
+pub struct LoginRequest {
+    pub user: String,
+    pub password: String,
+    pub cmd: Vec<String>,
+    pub env: Vec<String>,
+}
+
+/// A login attempt running on its thread
+pub struct Attempt {
+    events: Receiver<Event>,
+    answers: Sender<String>,
+    stream: UnixStream,
+    cancelled: Arc<AtomicBool>,
+    /// Disconnected once the login thread is done
+    finished: Receiver<()>,
+}
+
+impl Attempt {
+    /// Start talking to greetd over `stream`
+    pub fn start(stream: UnixStream, request: LoginRequest) -> std::io::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let (answers, answer_receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        stream.set_read_timeout(Some(GREETD_TIMEOUT))?;
+        let worker_stream = stream.try_clone()?;
+        let worker_cancelled = cancelled.clone();
+        let (done, finished) = mpsc::channel::<()>();
+        std::thread::Builder::new()
+            .name("hey-greeter-login".to_string())
+            .spawn(move || {
+                let _done = done;
+                let mut conversation = Conversation {
+                    stream: worker_stream,
+                    events: sender,
+                    answers: answer_receiver,
+                    cancelled: worker_cancelled.clone(),
+                };
+                let result = conversation.run(request);
+                if let Err(failure) = &result {
+                    conversation.cancel_session();
+                    if let Event::Failed { reason, .. } = failure {
+                        info!("Login attempt ended: {reason}");
+                    }
+                }
+                // Nobody listens to a cancelled attempt
+                if worker_cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                let event = match result {
+                    Ok(()) => Event::Started,
+                    Err(failure) => failure,
+                };
+                let _ = conversation.events.send(event);
+            })?;
+        Ok(Self {
+            events,
+            answers,
+            stream,
+            cancelled,
+            finished,
+        })
+    }
+
+    /// Answer the last `Event::Prompt`
+    pub fn answer(&self, response: String) {
+        let _ = self.answers.send(response);
+    }
+
+    /// Abandon the attempt and wait for the login thread's CancelSession.
+    /// Only the login thread writes to greetd: dropping `answers` wakes it
+    /// from waiting on the user, and it cancels once greetd answers. When
+    /// PAM keeps greetd busy past CANCEL_TIMEOUT, shutting down the read side
+    /// wakes it from waiting on greetd instead.
+    pub fn cancel(self) {
+        info!("Login cancelled");
+        let Self {
+            answers,
+            stream,
+            cancelled,
+            finished,
+            ..
+        } = self;
+        cancelled.store(true, Ordering::SeqCst);
+        drop(answers);
+        if finished.recv_timeout(CANCEL_TIMEOUT) != Err(RecvTimeoutError::Disconnected) {
+            warn!("greetd is busy, not waiting for it to cancel the login");
+            let _ = stream.shutdown(Shutdown::Read);
+            if finished.recv_timeout(CANCEL_TIMEOUT) != Err(RecvTimeoutError::Disconnected) {
+                warn!("Login thread did not finish in time");
+            }
+        }
+    }
+
+    /// The next event, if one arrived
+    pub fn poll(&self) -> Option<Event> {
+        match self.events.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(failed("Login stopped unexpectedly")),
+        }
+    }
+}
+
+fn failed(reason: impl Into<String>) -> Event {
+    Event::Failed {
+        reason: reason.into(),
+        denied: false,
+    }
+}
+
+fn denied(reason: impl Into<String>) -> Event {
+    Event::Failed {
+        reason: reason.into(),
+        denied: true,
+    }
+}
+
+/// The login thread's side of an attempt
+struct Conversation {
+    stream: UnixStream,
+    events: Sender<Event>,
+    answers: Receiver<String>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Conversation {
+    /// Create a session, answer every PAM message and start the session
+    fn run(&mut self, request: LoginRequest) -> Result<(), Event> {
+        self.progress("Authenticating…");
+        let mut response = self.send(Request::CreateSession {
+            username: request.user.clone(),
+        })?;
+        let mut password = Some(request.password);
+        // Error messages before the failure say why (pam_faillock's lock)
+        let mut last_error = None;
+
+        loop {
+            match response {
+                Response::Success => break,
+                Response::Error {
+                    error_type,
+                    description,
+                } => {
+                    let reason = last_error.unwrap_or(description);
+                    return Err(match error_type {
+                        ErrorType::AuthError => denied(reason),
+                        ErrorType::Error => failed(reason),
+                    });
+                }
+                Response::AuthMessage {
+                    auth_message_type,
+                    auth_message,
+                } => {
+                    let answer = match auth_message_type {
+                        AuthMessageType::Secret if password.is_some() => password.take(),
+                        AuthMessageType::Secret | AuthMessageType::Visible => {
+                            let secret = matches!(auth_message_type, AuthMessageType::Secret);
+                            Some(self.ask(auth_message, secret)?)
+                        }
+                        AuthMessageType::Info => {
+                            self.progress(&auth_message);
+                            None
+                        }
+                        AuthMessageType::Error => {
+                            warn!("PAM: {auth_message}");
+                            last_error = Some(auth_message);
+                            None
+                        }
+                    };
+                    response = self.send(Request::PostAuthMessageResponse { response: answer })?;
+                }
+            }
+        }
+
+        info!("Authentication successful! Starting session...");
+        let _ = self.events.send(Event::Authenticated);
+        info!("Executing session command: {:?} with {:?}", request.cmd, request.env);
+        match self.send(Request::StartSession {
+            cmd: request.cmd,
+            env: request.env,
+        })? {
+            Response::Success => Ok(()),
+            Response::Error { description, .. } => {
+                warn!("greetd could not start the session: {description}");
+                Err(failed(description))
+            }
+            Response::AuthMessage { .. } => Err(failed("Unexpected session response")),
+        }
+    }
+
+    fn progress(&self, message: &str) {
+        let _ = self.events.send(Event::Progress(message.to_string()));
+    }
+
+    /// Put a PAM prompt to the user and wait for the answer
+    fn ask(&self, message: String, secret: bool) -> Result<String, Event> {
+        let _ = self.events.send(Event::Prompt { message, secret });
+        match self.answers.recv_timeout(PROMPT_TIMEOUT) {
+            Ok(answer) => {
+                self.progress("Authenticating…");
+                Ok(answer)
+            }
+            Err(RecvTimeoutError::Timeout) => Err(failed("Login timed out")),
+            Err(RecvTimeoutError::Disconnected) => Err(failed("Login cancelled")),
+        }
+    }
+
+    /// Send a request and read greetd's response
+    fn send(&mut self, request: Request) -> Result<Response, Event> {
+        request
+            .write_to(&mut self.stream)
+            .map_err(|e| failed(format!("Login manager communication failed: {e}")))?;
+        // A read timeout (GREETD_TIMEOUT) surfaces as an i/o error here too
+        let response = Response::read_from(&mut self.stream)
+            .map_err(|e| failed(format!("Login manager communication failed: {e}")))?;
+        // Go no further once the user gave up on the attempt
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(failed("Login cancelled"));
+        }
+        Ok(response)
+    }
+
+    /// Leave greetd without a half-open session
+    fn cancel_session(&mut self) {
+        if Request::CancelSession.write_to(&mut self.stream).is_ok() {
+            let _ = Response::read_from(&mut self.stream);
+        }
+    }
+}
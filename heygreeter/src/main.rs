@@ -1,171 +1,230 @@
-use greetd_ipc::codec::SyncCodec;
-use greetd_ipc::{Request, Response, AuthMessageType};
-use std::os::unix::net::UnixStream;
 use slint::{SharedString, VecModel};
+use std::cell::RefCell;
 use std::rc::Rc;
-use tracing::{info, error};
-use std::path::PathBuf;
+use tracing::info;
 
-slint::include_modules!();
+mod accounts;
+mod backend;
+mod bus;
+mod clock;
+mod crashes;
+mod daemon;
+mod isolation;
+mod keyboard;
+mod login;
+mod notify;
+mod session;
+mod sessions;
+mod theme;
+mod throttle;
 
-/// Detect "real" users (UID >= 1000) from /etc/passwd
-fn detect_users() -> Vec<String> {
-    use std::io::{BufRead, BufReader};
-    let mut users = Vec::new();
-    
-    if let Ok(file) = std::fs::File::open("/etc/passwd") {
-        let reader = BufReader::new(file);
-        for line in reader.lines().flatten() {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 3 {
-                if let Ok(uid) = parts[2].parse::<u32>() {
-                    // Filter for normal users (Arch standard is 1000+)
-                    // Also include 'hey' live user specifically if detected
-                    if uid >= 1000 || parts[0] == "hey" {
-                        users.push(parts[0].to_string());
-                    }
-                }
-            }
-        }
-    }
-    
-    if users.is_empty() {
-        users.push("hey".to_string());
-    }
-    users
-}
-
-/// Parse a .desktop file to find the Exec command
-fn get_session_command(session_name: &str) -> Vec<String> {
-    let session_dirs = ["/usr/share/wayland-sessions", "/usr/share/xsessions"];
-    for dir in session_dirs {
-        let path = PathBuf::from(dir).join(format!("{}.desktop", session_name));
-        if let Ok(content) = std::fs::read_to_string(path) {
-            for line in content.lines() {
-                if line.starts_with("Exec=") {
-                    let exec = line.trim_start_matches("Exec=").trim();
-                    if let Some(cmd) = shlex::split(exec) {
-                        return cmd;
-                    }
-                }
-            }
-        }
-    }
-    // Fallback
-    vec![session_name.to_string()]
-}
+slint::include_modules!();
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
 
+    // greetd runs us with --launch; pick a display backend and re-run as the UI
+    if std::env::args().any(|arg| arg == "--launch") {
+        return backend::launch();
+    }
+    // greetd runs the session through us as the user (see session.rs)
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("--session") {
+        let safe_mode = args.get(2).map(String::as_str) == Some("--safe-mode");
+        let rest = &args[if safe_mode { 3 } else { 2 }..];
+        let command = rest.strip_prefix(&["--".to_string()]).unwrap_or(rest);
+        return session::run(command, safe_mode);
+    }
+
     let app = AppWindow::new()?;
+    let theme = theme::Theme::load();
+    theme.apply(&app);
 
-    let users = detect_users();
-    let user_models: Vec<SharedString> = users.into_iter().map(SharedString::from).collect();
+    let config = backend::LaunchConfig::load();
+    let users = accounts::detect(&config.users);
+    // A single eligible user gets a personalized layout without the user list
+    if let [user] = users.as_slice() {
+        app.set_single_user(true);
+        app.set_welcome_name(user.display_name.clone().into());
+    }
+    let user_names: Vec<SharedString> = users.iter().map(|u| u.name.as_str().into()).collect();
+    let display_names: Vec<SharedString> =
+        users.iter().map(|u| u.display_name.as_str().into()).collect();
+    let initials: Vec<SharedString> = users.iter().map(|u| u.initial().into()).collect();
     
-    let mut sessions: Vec<SharedString> = Vec::new();
-    let session_dirs = ["/usr/share/wayland-sessions", "/usr/share/xsessions"];
-    for dir in session_dirs {
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                if let Some(name) = entry.path().file_stem().and_then(|s| s.to_str()) {
-                    let name_str = name.to_string();
-                    if !sessions.iter().any(|s| s.as_str() == name_str) {
-                        sessions.push(name_str.into());
-                    }
-                }
-            }
+    let mut sessions: Vec<SharedString> = sessions::list().into_iter().map(SharedString::from).collect();
+
+    // Tell about a session that crashed right away; after repeated crashes
+    // offer the fallback session as well
+    if let Some(crash) = crashes::recent() {
+        app.set_crash_notice(
+            format!(
+                "{}'s session failed right after login ({}), {} time(s) in a row.",
+                crash.user, crash.status, crash.crashes
+            )
+            .into(),
+        );
+        app.set_crash_output(crash.stderr.join("\n").into());
+        app.set_crash_journal(crash.journal.join("\n").into());
+        if config.safe_mode.after > 0 && crash.crashes >= config.safe_mode.after {
+            app.set_offer_safe_mode(true);
+            sessions.push(crashes::SAFE_MODE_ID.into());
         }
     }
+    app.set_safe_mode_session(crashes::SAFE_MODE_ID.into());
 
-    if sessions.is_empty() {
-        sessions.push("heydm".into());
+    app.set_users(Rc::new(VecModel::from(user_names)).into());
+    app.set_user_display_names(Rc::new(VecModel::from(display_names)).into());
+    app.set_user_initials(Rc::new(VecModel::from(initials)).into());
+    // Nothing to choose from: log straight into the only session
+    if config.auto_session && sessions.len() == 1 {
+        info!("Single session '{}' installed, hiding session picker", sessions[0]);
+        app.set_show_session_picker(false);
     }
-
-    app.set_users(Rc::new(VecModel::from(user_models)).into());
     app.set_sessions(Rc::new(VecModel::from(sessions)).into());
 
-    // Update clock every second
-    let clock_handle = app.as_weak();
-    let timer = slint::Timer::default();
-    timer.start(slint::TimerMode::Repeated, std::time::Duration::from_secs(1), move || {
-        if let Some(app) = clock_handle.upgrade() {
-            let now = chrono::Local::now();
-            app.set_current_time(now.format("%H:%M").to_string().into());
-            app.set_current_date(now.format("%A, %B %e").to_string().into());
-        }
-    });
+    let _clock = clock::start(&app, &theme);
+    let _keyboard = keyboard::start(&app);
+
+    // Find greetd and keep retrying with a recovery panel while it is down
+    let greetd: daemon::SharedSocket = Rc::new(RefCell::new(None));
+    let _daemon_watch = daemon::watch(&app, greetd.clone());
+
+    // Slow down repeated failures
+    let throttle = throttle::Throttle::new(config.throttle);
+    let _cooldown = throttle::watch(&app, throttle.clone());
+
+    // The running login attempt, polled while it talks to greetd
+    let attempt: Rc<RefCell<Option<login::Attempt>>> = Rc::new(RefCell::new(None));
+    let attempt_timer = Rc::new(slint::Timer::default());
 
     let app_handle = app.as_weak();
+    let login_attempt = attempt.clone();
+    let login_timer = attempt_timer.clone();
+    let x_wrapper = config.x_wrapper.clone();
+    let safe_session = config.safe_mode.command.clone();
     app.on_login(move |user, password, session| {
         let Some(app) = app_handle.upgrade() else { return; };
+        // The password field answers PAM's follow-up prompts of a running attempt
+        if let Some(running) = login_attempt.borrow().as_ref() {
+            if app.get_auth_prompt() != "" {
+                app.set_auth_prompt("".into());
+                app.set_password("".into());
+                running.answer(password.to_string());
+            }
+            return;
+        }
+        if throttle.borrow().remaining().is_some() {
+            return;
+        }
         app.set_error_message("".into());
-        info!("Attempting login for user: {}", user);
-        
-        let socket_path = match std::env::var("GREETD_SOCK") {
-            Ok(path) => path,
-            Err(_) => {
-                error!("GREETD_SOCK not found");
-                app.set_error_message("System error: greetd not found".into());
+        throttle.borrow().attempt(&user);
+
+        let stream = match daemon::connect(&app, &greetd) {
+            Ok(stream) => stream,
+            Err(e) => {
+                app.set_error_message(e.into());
                 return;
             }
         };
+        let picked = sessions::Session::find(session.as_str());
+        let safe_mode = session == crashes::SAFE_MODE_ID;
+        let command = if safe_mode {
+            info!("Starting the safe mode session {safe_session:?}");
+            safe_session.clone()
+        } else {
+            picked.command(x_wrapper.as_deref())
+        };
+        let request = login::LoginRequest {
+            user: user.to_string(),
+            password: password.to_string(),
+            cmd: session::wrap(command, safe_mode),
+            env: picked.environment(&app.get_keyboard_layout()),
+        };
+        match login::Attempt::start(stream, request) {
+            Ok(started) => *login_attempt.borrow_mut() = Some(started),
+            Err(e) => {
+                app.set_error_message(format!("Failed to start login: {e}").into());
+                return;
+            }
+        }
+        app.set_authenticating(true);
 
-        match UnixStream::connect(socket_path) {
-            Ok(mut stream) => {
-                let req = Request::CreateSession { username: user.to_string() };
-                if let Err(e) = req.write_to(&mut stream) {
-                    app.set_error_message(format!("IPC Error: {}", e).into());
-                    return;
+        let poll_handle = app.as_weak();
+        let poll_attempt = login_attempt.clone();
+        let poll_timer = Rc::downgrade(&login_timer);
+        let throttle = throttle.clone();
+        let user = user.to_string();
+        login_timer.start(slint::TimerMode::Repeated, std::time::Duration::from_millis(50), move || {
+            let Some(app) = poll_handle.upgrade() else { return; };
+            let event = match poll_attempt.borrow().as_ref() {
+                Some(running) => running.poll(),
+                None => return,
+            };
+            match event {
+                None => {}
+                Some(login::Event::Progress(step)) => app.set_login_progress(step.into()),
+                Some(login::Event::Prompt { message, secret }) => {
+                    app.set_password("".into());
+                    app.set_auth_prompt_secret(secret);
+                    app.set_auth_prompt(message.into());
                 }
-                
-                match Response::read_from(&mut stream) {
-                    Ok(Response::AuthMessage { auth_message_type, .. }) => {
-                        if matches!(auth_message_type, AuthMessageType::Visible | AuthMessageType::Secret) {
-                            let req = Request::PostAuthMessageResponse { response: Some(password.to_string()) };
-                            if let Err(e) = req.write_to(&mut stream) {
-                                app.set_error_message(format!("Auth communication failed: {}", e).into());
-                                return;
-                            }
-                            
-                            match Response::read_from(&mut stream) {
-                                Ok(Response::Success) => {
-                                    info!("Authentication successful! Starting session...");
-                                    
-                                    let cmd = get_session_command(session.as_str());
-                                    info!("Executing session command: {:?}", cmd);
-                                    let req = Request::StartSession { cmd, env: vec![] };
-                                    
-                                    if let Err(e) = req.write_to(&mut stream) {
-                                        app.set_error_message(format!("Failed to start session: {}", e).into());
-                                    } else {
-                                        match Response::read_from(&mut stream) {
-                                            Ok(Response::Success) => {
-                                                info!("Session started! Exiting greeter...");
-                                                std::process::exit(0);
-                                            },
-                                            Ok(Response::Error { description, .. }) => {
-                                                app.set_error_message(description.into());
-                                            },
-                                            _ => app.set_error_message("Unexpected session response".into()),
-                                        }
-                                    }
-                                },
-                                Ok(Response::Error { description, .. }) => {
-                                    app.set_error_message(description.into());
-                                },
-                                _ => app.set_error_message("Unexpected auth response".into()),
-                            }
-                        }
-                    },
-                    Ok(Response::Error { description, .. }) => app.set_error_message(description.into()),
-                    _ => app.set_error_message("Unexpected greetd response".into()),
+                Some(login::Event::Authenticated) => {
+                    throttle.borrow_mut().succeeded(&user);
+                    app.set_login_progress("Starting session…".into());
                 }
-            },
-            Err(e) => app.set_error_message(format!("Failed to connect to login manager: {}", e).into()),
+                Some(login::Event::Started) => {
+                    info!("Session started! Exiting greeter...");
+                    notify::stopping();
+                    std::process::exit(0);
+                }
+                Some(login::Event::Failed { reason, denied }) => {
+                    if denied {
+                        throttle.borrow_mut().failed(&user, &reason);
+                    }
+                    app.set_error_message(reason.into());
+                    app.set_authenticating(false);
+                    app.set_auth_prompt("".into());
+                    poll_attempt.borrow_mut().take();
+                    if let Some(timer) = poll_timer.upgrade() {
+                        timer.stop();
+                    }
+                }
+            }
+        });
+    });
+
+    // On-screen keyboard from the accessibility menu types into the password field
+    let app_handle = app.as_weak();
+    app.on_keyboard_key(move |key| {
+        let Some(app) = app_handle.upgrade() else { return; };
+        let mut password = app.get_password().to_string();
+        match key.as_str() {
+            "⌫" => {
+                password.pop();
+            }
+            "⏎" => {
+                app.invoke_login(app.get_selected_user(), password.into(), app.get_selected_session());
+                return;
+            }
+            text => password.push_str(text),
+        }
+        app.set_password(password.into());
+    });
+
+    let app_handle = app.as_weak();
+    app.on_cancel_login(move || {
+        let Some(app) = app_handle.upgrade() else { return; };
+        if let Some(running) = attempt.borrow_mut().take() {
+            running.cancel();
         }
+        attempt_timer.stop();
+        app.set_authenticating(false);
+        app.set_auth_prompt("".into());
     });
 
+    let _watchdog = notify::start();
     app.run()?;
+    notify::stopping();
     Ok(())
 }
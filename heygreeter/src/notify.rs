@@ -0,0 +1,84 @@
+//! systemd service notifications (sd_notify).
+//!
+//! When the greeter runs under a unit with `Type=notify` (and
+//! `NotifyAccess=all`, as the UI is a child of the launcher), it reports
+//! READY=1 once the login screen's event loop runs and STOPPING=1 before it
+//! exits. With `WatchdogSec=` it also sends WATCHDOG=1 at half that interval
+//! from the UI thread, so a hung login screen stops the pings and systemd
+//! restarts the unit.
+//!
+//! greetd passes no $NOTIFY_SOCKET to its greeter, and the shipped units
+//! start greetd as `Type=simple`, so on heyOS nothing is sent and systemd
+//! does not watch the login screen; this is for units of your own.
+
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::process::Command;
+use std::time::Duration;
+
+use tracing::debug;
+
+/// Send a state line like "READY=1"; false when there is no one to tell
+pub fn notify(state: &str) -> bool {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return false;
+    };
+    let path = path.to_string_lossy();
+    let address = match path.strip_prefix('@') {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            SocketAddr::from_abstract_name(name)
+        }
+        None => SocketAddr::from_pathname(path.as_ref()),
+    };
+    let sent = address.and_then(|address| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address));
+    if let Err(e) = &sent {
+        debug!("sd_notify {state}: {e}");
+    }
+    sent.is_ok()
+}
+
+/// Half the watchdog timeout, when the watchdog is meant for this process
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+/// Hand the watchdog of the launcher on to the backend it starts, which
+/// runs the UI that pings it
+pub fn hand_over(command: &mut Command) {
+    if watchdog_interval().is_some() {
+        command.env_remove("WATCHDOG_PID");
+    }
+}
+
+/// Keep the service manager out of a user session started from the greeter
+pub fn detach(command: &mut Command) {
+    for name in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+        command.env_remove(name);
+    }
+}
+
+/// Report READY=1 once the event loop runs and feed the watchdog from it.
+/// Keep the returned timer alive.
+pub fn start() -> slint::Timer {
+    slint::Timer::single_shot(Duration::ZERO, || {
+        notify("READY=1");
+    });
+    let watchdog = slint::Timer::default();
+    if let Some(interval) = watchdog_interval() {
+        watchdog.start(slint::TimerMode::Repeated, interval, || {
+            notify("WATCHDOG=1");
+        });
+    }
+    watchdog
+}
+
+/// Report STOPPING=1 before exiting
+pub fn stopping() {
+    notify("STOPPING=1");
+}
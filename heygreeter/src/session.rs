@@ -0,0 +1,413 @@
+//! Session startup as the logged-in user.
+//!
+//! greetd runs the session command as the user, so the greeter hands it
+//! `hey-greeter --session -- <command>` and this wrapper, already running
+//! as the user, applies the session settings before exec'ing the command:
+//!
+//! ```text
+//! # /etc/hey-greeter/config: for everyone
+//! session_command = sway --unsupported-gpu   # replaces the picked session
+//! session_args = --debug                     # appended to the command
+//! session_env = MOZ_ENABLE_WAYLAND=1         # repeatable
+//!
+//! # ~/.config/hey-greeter/session: per user, same keys without "session_",
+//! # taking precedence over the system settings
+//! command = river
+//! env = XKB_DEFAULT_OPTIONS=caps:escape
+//! ```
+//!
+//! The user's file lives in a home directory the greeter usually cannot
+//! read, which is why it is applied here rather than in the greeter.
+//!
+//! Before the settings' own `env`, the session environment is built like a
+//! login shell or systemd user session would: /etc/environment, then the
+//! environment.d directories (/usr/lib, /etc, ~/.config; later files win),
+//! then whatever the user's login shell profile exports. `profile = false`
+//! (`session_profile` system-wide) skips the profile, e.g. for a profile that
+//! prints or prompts.
+//!
+//! The session also gets DBUS_SESSION_BUS_ADDRESS (see bus.rs). The wrapper
+//! stays the session's parent to stop a private bus after the session and
+//! to notice crashes (see crashes.rs). The safe mode session offered after
+//! repeated crashes (`--session --safe-mode`) ignores the session settings
+//! and the shell profile, which may be what made the session crash.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::backend::LaunchConfig;
+use crate::bus::SessionBus;
+use crate::crashes;
+use crate::notify;
+
+/// Command, arguments and environment changes for the session
+#[derive(Debug, Clone, Default)]
+pub struct SessionSettings {
+    /// Replaces the session picked on the login screen
+    pub command: Option<Vec<String>>,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Import the environment of the user's login shell profile
+    pub profile: Option<bool>,
+}
+
+impl SessionSettings {
+    /// Apply a key (without the "session_" prefix); Ok(false) when unknown
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        match key {
+            "command" => match shlex::split(value) {
+                Some(command) if !command.is_empty() => self.command = Some(command),
+                _ => return Err(format!("invalid command '{value}'")),
+            },
+            "args" => match shlex::split(value) {
+                Some(args) => self.args.extend(args),
+                None => return Err(format!("invalid args '{value}'")),
+            },
+            "profile" => match value {
+                "on" | "true" => self.profile = Some(true),
+                "off" | "false" => self.profile = Some(false),
+                other => return Err(format!("invalid profile '{other}'")),
+            },
+            "env" => match value.split_once('=') {
+                Some((name, val)) if !name.trim().is_empty() => {
+                    self.env.push((name.trim().to_string(), val.trim().to_string()));
+                }
+                _ => return Err(format!("invalid env '{value}', expected NAME=value")),
+            },
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// `other` on top of these settings
+    fn merge(&mut self, other: SessionSettings) {
+        if other.command.is_some() {
+            self.command = other.command;
+        }
+        self.args.extend(other.args);
+        self.env.extend(other.env);
+        if other.profile.is_some() {
+            self.profile = other.profile;
+        }
+    }
+}
+
+/// The greeter-side half: the command greetd should run for `command`
+pub fn wrap(command: Vec<String>, safe_mode: bool) -> Vec<String> {
+    let exe = std::env::current_exe()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| "hey-greeter".to_string());
+    let mut argv = vec![exe, "--session".to_string()];
+    if safe_mode {
+        argv.push("--safe-mode".to_string());
+    }
+    argv.push("--".to_string());
+    argv.extend(command);
+    argv
+}
+
+/// `hey-greeter --session -- <command>`: apply the settings and run the
+/// session. Exits with the session's exit code.
+pub fn run(picked: &[String], safe_mode: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut settings = SessionSettings::default();
+    if safe_mode {
+        info!("Safe mode: ignoring session settings and shell profile");
+        settings.profile = Some(false);
+    } else {
+        settings = LaunchConfig::load().session;
+        if let Some(user) = user_settings() {
+            settings.merge(user);
+        }
+    }
+
+    let mut argv = settings.command.unwrap_or_else(|| picked.to_vec());
+    argv.extend(settings.args);
+    let (program, args) = argv.split_first().ok_or("no session command")?;
+
+    let mut env = base_environment();
+    if settings.profile.unwrap_or(true) {
+        env.extend(profile_environment());
+    }
+    env.extend(settings.env);
+
+    let mut command = Command::new(program);
+    command.args(args);
+    notify::detach(&mut command);
+    for (name, value) in &env {
+        command.env(name, value);
+    }
+    let bus = SessionBus::start(&env);
+    if let Some(address) = bus.as_ref().and_then(SessionBus::address) {
+        command.env("DBUS_SESSION_BUS_ADDRESS", address);
+    }
+
+    info!("Starting session {argv:?}");
+    let status = crashes::supervise(command, &argv)?;
+    info!("Session exited ({status})");
+    drop(bus);
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// ~/.config/hey-greeter/session (honoring XDG_CONFIG_HOME)
+fn user_settings() -> Option<SessionSettings> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+    let path = base.join("hey-greeter/session");
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    let mut settings = SessionSettings::default();
+    for (num, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            warn!("{}:{}: expected 'key = value'", path.display(), num + 1);
+            continue;
+        };
+        match settings.set(key.trim(), value.trim()) {
+            Ok(true) => {}
+            Ok(false) => warn!("{}:{}: unknown key '{}'", path.display(), num + 1, key.trim()),
+            Err(e) => warn!("{}:{}: {}", path.display(), num + 1, e),
+        }
+    }
+    info!("Using session settings from {}", path.display());
+    Some(settings)
+}
+
+/// How long the login shell may take to print its environment
+const PROFILE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Variables the profile must not override: they describe the session
+/// greetd and PAM set up, not the user's preferences
+const SESSION_OWNED: &[&str] = &["XDG_SESSION_ID", "XDG_SESSION_TYPE", "XDG_SEAT", "XDG_VTNR", "SHLVL", "_", "PWD", "OLDPWD"];
+
+/// /etc/environment, then the environment.d files
+fn base_environment() -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = std::fs::read_to_string("/etc/environment")
+        .map(|content| content.lines().filter_map(parse_assignment).collect())
+        .unwrap_or_default();
+
+    // environment.d: files with the same name override earlier directories
+    let mut files: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in environment_d_dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".conf") {
+                files.insert(name, entry.path());
+            }
+        }
+    }
+    for path in files.values() {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for (name, value) in content.lines().filter_map(parse_assignment) {
+            let value = expand(&value, &env);
+            env.push((name, value));
+        }
+    }
+    env
+}
+
+fn environment_d_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/usr/lib/environment.d"),
+        PathBuf::from("/etc/environment.d"),
+    ];
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")));
+    if let Some(config) = config {
+        dirs.push(config.join("environment.d"));
+    }
+    dirs
+}
+
+/// `NAME=value`, `NAME="value"` or `export NAME=value`; comments skipped
+fn parse_assignment(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value);
+    Some((name.to_string(), value.to_string()))
+}
+
+/// environment.d expansion: $NAME, ${NAME}, ${NAME:-default} and
+/// ${NAME:+alternate}, from earlier assignments or the inherited environment
+fn expand(value: &str, env: &[(String, String)]) -> String {
+    let lookup = |name: &str| {
+        env.iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .or_else(|| std::env::var(name).ok())
+            .filter(|v| !v.is_empty())
+    };
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(braced) = rest.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                out.push_str("${");
+                rest = braced;
+                continue;
+            };
+            let expr = &braced[..end];
+            rest = &braced[end + 1..];
+            let expanded = if let Some((name, default)) = expr.split_once(":-") {
+                lookup(name).unwrap_or_else(|| default.to_string())
+            } else if let Some((name, alternate)) = expr.split_once(":+") {
+                lookup(name).map(|_| alternate.to_string()).unwrap_or_default()
+            } else {
+                lookup(expr).unwrap_or_default()
+            };
+            out.push_str(&expanded);
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            if end == 0 {
+                out.push('$');
+            } else {
+                out.push_str(&lookup(&rest[..end]).unwrap_or_default());
+            }
+            rest = &rest[end..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// What the user's login shell exports after reading its profile
+fn profile_environment() -> Vec<(String, String)> {
+    let shell = std::env::var("SHELL")
+        .ok()
+        .filter(|shell| Path::new(shell).is_file())
+        .unwrap_or_else(|| "/bin/sh".to_string());
+    let child = Command::new(&shell)
+        .args(["-l", "-c", "exec env -0"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Cannot run {shell} for the profile environment: {e}");
+            return Vec::new();
+        }
+    };
+
+    // Read on the side so a large environment cannot fill the pipe
+    let reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = std::io::Read::read_to_end(&mut stdout, &mut output);
+            output
+        })
+    });
+
+    // A profile waiting for input must not keep the session from starting
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < PROFILE_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            _ => {
+                warn!("{shell} -l took too long, starting without the profile environment");
+                let _ = child.kill();
+                let _ = child.wait();
+                return Vec::new();
+            }
+        }
+    }
+    let Some(output) = reader.and_then(|reader| reader.join().ok()) else {
+        return Vec::new();
+    };
+    output
+        .split(|&b| b == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (name, value) = entry.split_once('=')?;
+            if name.is_empty() || SESSION_OWNED.contains(&name) {
+                return None;
+            }
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand, parse_assignment};
+
+    fn assignment(name: &str, value: &str) -> Option<(String, String)> {
+        Some((name.to_string(), value.to_string()))
+    }
+
+    #[test]
+    fn parses_assignments() {
+        assert_eq!(parse_assignment("FOO=bar"), assignment("FOO", "bar"));
+        assert_eq!(parse_assignment("export FOO=\"a b\""), assignment("FOO", "a b"));
+        assert_eq!(parse_assignment("  BAR='x'  "), assignment("BAR", "x"));
+        assert_eq!(parse_assignment("A=b=c"), assignment("A", "b=c"));
+        assert_eq!(parse_assignment("EMPTY="), assignment("EMPTY", ""));
+    }
+
+    #[test]
+    fn skips_everything_else() {
+        for line in ["", "   ", "# FOO=bar", "not an assignment", "BAD-NAME=1", "=1"] {
+            assert_eq!(parse_assignment(line), None, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn expands_variables() {
+        let env = [("A", "1"), ("B", ""), ("C", "x"), ("C", "y")]
+            .map(|(name, value)| (name.to_string(), value.to_string()));
+        let unset = "HEY_GREETER_TEST_UNSET";
+        assert_eq!(expand("$A/bin", &env), "1/bin");
+        assert_eq!(expand("${A}b", &env), "1b");
+        assert_eq!(expand("$C", &env), "y");
+        assert_eq!(expand(&format!("${{{unset}:-default}}"), &env), "default");
+        assert_eq!(expand("${A:-default}", &env), "1");
+        assert_eq!(expand("${B:-default}", &env), "default");
+        assert_eq!(expand("${A:+alternate}", &env), "alternate");
+        assert_eq!(expand(&format!("${{{unset}:+alternate}}"), &env), "");
+        assert_eq!(expand(&format!("[${unset}]"), &env), "[]");
+    }
+
+    #[test]
+    fn keeps_stray_dollars() {
+        assert_eq!(expand("price $", &[]), "price $");
+        assert_eq!(expand("$ sign", &[]), "$ sign");
+        assert_eq!(expand("${unterminated", &[]), "${unterminated");
+    }
+}
@@ -0,0 +1,181 @@
+//! Installed sessions and the environment they start with.
+//!
+//! Sessions come from the .desktop files in /usr/share/wayland-sessions and
+//! /usr/share/xsessions; the file name without .desktop is the session id
+//! shown in the picker. Wayland sessions run their Exec line directly; X11
+//! sessions need an X server first and run under `x_wrapper` from
+//! /etc/hey-greeter/config, by default `startx /usr/bin/env` (or `xinit`
+//! when startx is missing), which starts X and then the Exec line as its
+//! client. greetd starts the session with:
+//!
+//! - `XDG_SESSION_TYPE`    — `wayland` or `x11`, from the directory
+//! - `XDG_SESSION_DESKTOP` — the session id
+//! - `XDG_CURRENT_DESKTOP` — `DesktopNames` joined with `:`, else the id
+//! - `LANG`                — the system locale
+//! - `XKB_DEFAULT_LAYOUT`  — the layout shown on the login screen
+
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::backend::find_in_path;
+
+const WAYLAND_SESSIONS: &str = "/usr/share/wayland-sessions";
+const X11_SESSIONS: &str = "/usr/share/xsessions";
+
+/// Started when no session is installed
+const FALLBACK_SESSION: &str = "heydm";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionType {
+    Wayland,
+    X11,
+}
+
+impl SessionType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Wayland => "wayland",
+            Self::X11 => "x11",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub kind: SessionType,
+    pub exec: Vec<String>,
+    pub desktop_names: Vec<String>,
+}
+
+impl Session {
+    /// The session with the given id; unknown ids run as a command
+    pub fn find(id: &str) -> Self {
+        for (dir, kind) in dirs() {
+            let path = dir.join(format!("{id}.desktop"));
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                match Self::parse(id, kind, &content) {
+                    Some(session) => return session,
+                    None => warn!("{}: no usable Exec line", path.display()),
+                }
+            }
+        }
+        Self {
+            id: id.to_string(),
+            kind: SessionType::Wayland,
+            exec: vec![id.to_string()],
+            desktop_names: Vec::new(),
+        }
+    }
+
+    fn parse(id: &str, kind: SessionType, content: &str) -> Option<Self> {
+        let mut exec = None;
+        let mut desktop_names = Vec::new();
+        let mut in_entry = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_entry {
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("Exec=") {
+                exec = shlex::split(value.trim());
+            } else if let Some(value) = line.strip_prefix("DesktopNames=") {
+                desktop_names = value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(String::from)
+                    .collect();
+            }
+        }
+        Some(Self {
+            id: id.to_string(),
+            kind,
+            exec: exec.filter(|cmd| !cmd.is_empty())?,
+            desktop_names,
+        })
+    }
+
+    /// Command for greetd's StartSession, wrapped for X11 sessions
+    pub fn command(&self, x_wrapper: Option<&[String]>) -> Vec<String> {
+        if self.kind == SessionType::Wayland {
+            return self.exec.clone();
+        }
+        let wrapper: Vec<String> = match x_wrapper {
+            Some(wrapper) => wrapper.to_vec(),
+            None if find_in_path("startx") => vec!["startx".into(), "/usr/bin/env".into()],
+            None if find_in_path("xinit") => vec!["xinit".into(), "/usr/bin/env".into()],
+            None => {
+                warn!("Neither startx nor xinit found, starting X11 session {} without X", self.id);
+                Vec::new()
+            }
+        };
+        wrapper.into_iter().chain(self.exec.iter().cloned()).collect()
+    }
+
+    /// Environment for greetd's StartSession
+    pub fn environment(&self, keyboard_layout: &str) -> Vec<String> {
+        let current_desktop = if self.desktop_names.is_empty() {
+            self.id.clone()
+        } else {
+            self.desktop_names.join(":")
+        };
+        let mut env = vec![
+            format!("XDG_SESSION_TYPE={}", self.kind.name()),
+            format!("XDG_SESSION_DESKTOP={}", self.id),
+            format!("XDG_CURRENT_DESKTOP={current_desktop}"),
+        ];
+        if let Some(lang) = system_lang() {
+            env.push(format!("LANG={lang}"));
+        }
+        if !keyboard_layout.is_empty() {
+            env.push(format!("XKB_DEFAULT_LAYOUT={keyboard_layout}"));
+        }
+        env
+    }
+}
+
+/// Ids of the installed sessions, Wayland first; never empty
+pub fn list() -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for (dir, _) in dirs() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .filter(|id| !ids.contains(id))
+            .collect();
+        found.sort();
+        ids.extend(found);
+    }
+    if ids.is_empty() {
+        ids.push(FALLBACK_SESSION.to_string());
+    }
+    ids
+}
+
+fn dirs() -> [(PathBuf, SessionType); 2] {
+    [
+        (Path::new(WAYLAND_SESSIONS).to_path_buf(), SessionType::Wayland),
+        (Path::new(X11_SESSIONS).to_path_buf(), SessionType::X11),
+    ]
+}
+
+/// LANG of the greeter, else from /etc/locale.conf
+fn system_lang() -> Option<String> {
+    if let Some(lang) = std::env::var("LANG").ok().filter(|lang| !lang.is_empty()) {
+        return Some(lang);
+    }
+    let conf = std::fs::read_to_string("/etc/locale.conf").ok()?;
+    conf.lines()
+        .find_map(|line| line.trim().strip_prefix("LANG="))
+        .map(|lang| lang.trim_matches('"').to_string())
+}
@@ -0,0 +1,222 @@
+//! Login screen theming.
+//!
+//! Distributions and administrators brand the greeter without recompiling
+//! through /etc/hey-greeter/theme.toml; the greeter user's own
+//! `$XDG_CONFIG_HOME/hey-greeter/theme.toml` (e.g. for a shared kiosk
+//! account) overrides single keys of it:
+//!
+//! ```toml
+//! background = "#1e4552"                      # color behind everything
+//! background_image = "/usr/share/backgrounds/heyos.png"
+//! background_blur = 12                        # Gaussian blur radius in px
+//! background_darken = 0.35                    # black overlay, 0.0 – 1.0
+//! accent = "#0078d7"                          # buttons, selection, focus
+//! logo_text = "heyOS"
+//! logo_image = "/usr/share/pixmaps/heyos.png" # shown instead of the text
+//! font = "Inter"
+//! corner_radius = 8                           # px, cards and fields
+//! ```
+//!
+//! The clock keys are described in clock.rs.
+//!
+//! Missing keys keep the built-in look; an unreadable image or a bad color
+//! is logged and ignored. The wallpaper is decoded (and blurred) once at
+//! startup.
+
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use tracing::{info, warn};
+
+use crate::AppWindow;
+
+const THEME_PATH: &str = "/etc/hey-greeter/theme.toml";
+
+/// Largest accepted corner radius in px
+const MAX_CORNER_RADIUS: f32 = 48.0;
+
+/// Largest accepted blur radius in px
+const MAX_BLUR: f32 = 64.0;
+
+/// Blurred wallpapers are scaled down to this width first; the blur hides
+/// the lost detail and keeps startup fast on 4K screens
+const BLUR_WIDTH: u32 = 1280;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub background: Option<String>,
+    pub background_image: Option<PathBuf>,
+    pub background_blur: Option<f32>,
+    pub background_darken: Option<f32>,
+    pub accent: Option<String>,
+    pub logo_text: Option<String>,
+    pub logo_image: Option<PathBuf>,
+    pub font: Option<String>,
+    pub corner_radius: Option<f32>,
+    pub clock_position: Option<String>,
+    pub time_format: Option<String>,
+    pub date_format: Option<String>,
+    pub show_hostname: Option<bool>,
+}
+
+impl Theme {
+    /// The system theme with the greeter user's overrides on top
+    pub fn load() -> Self {
+        let mut theme = Self::read(Path::new(THEME_PATH)).unwrap_or_default();
+        if let Some(user) = user_theme_path().and_then(|path| Self::read(&path)) {
+            theme.merge(user);
+        }
+        theme
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        match toml::from_str(&content) {
+            Ok(theme) => {
+                info!("Using greeter theme {}", path.display());
+                Some(theme)
+            }
+            Err(e) => {
+                warn!("{}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        macro_rules! take {
+            ($($field:ident),*) => { $( if other.$field.is_some() { self.$field = other.$field; } )* };
+        }
+        take!(
+            background,
+            background_image,
+            background_blur,
+            background_darken,
+            accent,
+            logo_text,
+            logo_image,
+            font,
+            corner_radius,
+            clock_position,
+            time_format,
+            date_format,
+            show_hostname
+        );
+    }
+
+    /// Hand the theme to the UI
+    pub fn apply(&self, app: &AppWindow) {
+        if let Some(color) = self.background.as_deref().and_then(|c| parse_color("background", c)) {
+            app.set_theme_background(color);
+            app.set_has_theme_background(true);
+        }
+        if let Some(path) = self.background_image.as_deref() {
+            let blur = self.background_blur.unwrap_or(0.0).clamp(0.0, MAX_BLUR);
+            let image = if blur > 0.0 {
+                load_blurred(path, blur)
+            } else {
+                load_image(path)
+            };
+            if let Some(image) = image {
+                app.set_background_image(image);
+                app.set_has_background_image(true);
+            }
+        }
+        if let Some(darken) = self.background_darken {
+            app.set_background_darken(darken.clamp(0.0, 1.0));
+        }
+        if let Some(color) = self.accent.as_deref().and_then(|c| parse_color("accent", c)) {
+            app.set_accent(color);
+        }
+        if let Some(text) = &self.logo_text {
+            app.set_logo_text(text.into());
+        }
+        if let Some(image) = self.logo_image.as_deref().and_then(load_image) {
+            app.set_logo_image(image);
+            app.set_has_logo_image(true);
+        }
+        if let Some(font) = &self.font {
+            app.set_font_family(font.into());
+        }
+        if let Some(radius) = self.corner_radius {
+            app.set_corner_radius(radius.clamp(0.0, MAX_CORNER_RADIUS));
+        }
+        match self.clock_position.as_deref() {
+            None => {}
+            Some(position @ ("center" | "top-right" | "bottom-left" | "hidden")) => {
+                app.set_clock_position(position.into());
+            }
+            Some(other) => warn!("theme: unknown clock_position '{other}'"),
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/hey-greeter/theme.toml` of the user running the greeter
+fn user_theme_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+    Some(base.join("hey-greeter/theme.toml"))
+}
+
+/// "#rgb", "#rrggbb" or "#rrggbbaa"
+fn parse_color(key: &str, value: &str) -> Option<slint::Color> {
+    let hex = value.trim().strip_prefix('#').unwrap_or(value.trim());
+    let digits: Option<Vec<u8>> = match hex.len() {
+        3 => hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| (d * 17) as u8))
+            .collect(),
+        6 | 8 => (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect(),
+        _ => None,
+    };
+    match digits.as_deref() {
+        Some([r, g, b]) => Some(slint::Color::from_rgb_u8(*r, *g, *b)),
+        Some([r, g, b, a]) => Some(slint::Color::from_argb_u8(*a, *r, *g, *b)),
+        _ => {
+            warn!("theme: invalid {key} color '{value}'");
+            None
+        }
+    }
+}
+
+fn load_image(path: &Path) -> Option<slint::Image> {
+    match slint::Image::load_from_path(path) {
+        Ok(image) => Some(image),
+        Err(_) => {
+            warn!("theme: cannot load image {}", path.display());
+            None
+        }
+    }
+}
+
+/// The wallpaper with a Gaussian blur of `radius` px (at screen scale)
+fn load_blurred(path: &Path, radius: f32) -> Option<slint::Image> {
+    let started = std::time::Instant::now();
+    let mut image = match image::open(path) {
+        Ok(image) => image,
+        Err(e) => {
+            warn!("theme: cannot load image {}: {e}", path.display());
+            return None;
+        }
+    };
+    let mut sigma = radius;
+    if image.width() > BLUR_WIDTH {
+        sigma *= BLUR_WIDTH as f32 / image.width() as f32;
+        image = image.resize(BLUR_WIDTH, u32::MAX, image::imageops::FilterType::Triangle);
+    }
+    let blurred = image::imageops::blur(&image.to_rgba8(), sigma.max(0.5));
+    let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+        blurred.as_raw(),
+        blurred.width(),
+        blurred.height(),
+    );
+    info!("Blurred wallpaper {} in {:?}", path.display(), started.elapsed());
+    Some(slint::Image::from_rgba8(buffer))
+}
@@ -0,0 +1,203 @@
+//! Throttling of failed logins.
+//!
+//! After `lockout_attempts` consecutive failures the greeter refuses new
+//! attempts for `lockout_delay` seconds, doubling with every further failure
+//! up to `lockout_max_delay`; the login card shows the remaining time. A
+//! successful login resets the count. Set in /etc/hey-greeter/config:
+//!
+//! ```text
+//! # 0 disables throttling
+//! lockout_attempts = 3
+//! lockout_delay = 5
+//! lockout_max_delay = 300
+//! ```
+//!
+//! This only slows down guessing at the greeter itself; pam_faillock (when
+//! configured) locks the account for every login path, and its messages are
+//! shown as they come from greetd. Every attempt is logged with the tty the
+//! greeter runs on, which ends up in the journal through greetd.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::AppWindow;
+
+/// Throttling settings from /etc/hey-greeter/config
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleConfig {
+    /// Failures before the first delay; 0 disables throttling
+    pub attempts: u32,
+    pub delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(300),
+        }
+    }
+}
+
+impl ThrottleConfig {
+    /// Apply a config key; Ok(false) when the key is not a throttling key
+    pub fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        let number = |value: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|_| format!("invalid {key} '{value}'"))
+        };
+        match key {
+            "lockout_attempts" => self.attempts = number(value)?,
+            "lockout_delay" => self.delay = Duration::from_secs(number(value)?.into()),
+            "lockout_max_delay" => self.max_delay = Duration::from_secs(number(value)?.into()),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Failure count and cooldown, shared by the login handler and the countdown
+pub type SharedThrottle = Rc<RefCell<Throttle>>;
+
+pub struct Throttle {
+    config: ThrottleConfig,
+    failures: u32,
+    until: Option<Instant>,
+    tty: String,
+}
+
+impl Throttle {
+    pub fn new(config: ThrottleConfig) -> SharedThrottle {
+        Rc::new(RefCell::new(Self {
+            config,
+            failures: 0,
+            until: None,
+            tty: tty(),
+        }))
+    }
+
+    /// Time left before the next attempt is allowed
+    pub fn remaining(&self) -> Option<Duration> {
+        let left = self.until?.saturating_duration_since(Instant::now());
+        (!left.is_zero()).then_some(left)
+    }
+
+    pub fn attempt(&self, user: &str) {
+        info!("Login attempt for {user} on {}", self.tty);
+    }
+
+    /// Record a failed attempt; returns the cooldown it starts, if any
+    pub fn failed(&mut self, user: &str, reason: &str) -> Option<Duration> {
+        self.failures += 1;
+        warn!(
+            "Login failed for {user} on {} ({} in a row): {reason}",
+            self.tty, self.failures
+        );
+        if self.config.attempts == 0 || self.failures < self.config.attempts {
+            return None;
+        }
+        let doublings = (self.failures - self.config.attempts).min(16);
+        let delay = self.config.delay.saturating_mul(1 << doublings).min(self.config.max_delay);
+        warn!("Refusing logins on {} for {}s", self.tty, delay.as_secs());
+        self.until = Some(Instant::now() + delay);
+        Some(delay)
+    }
+
+    pub fn succeeded(&mut self, user: &str) {
+        info!("Login succeeded for {user} on {}", self.tty);
+        self.failures = 0;
+        self.until = None;
+    }
+}
+
+/// Keep the login card's countdown current while the returned timer lives
+pub fn watch(app: &AppWindow, throttle: SharedThrottle) -> slint::Timer {
+    let handle = app.as_weak();
+    let timer = slint::Timer::default();
+    timer.start(slint::TimerMode::Repeated, Duration::from_millis(500), move || {
+        let Some(app) = handle.upgrade() else { return };
+        let seconds = throttle
+            .borrow()
+            .remaining()
+            .map_or(0, |left| left.as_secs_f32().ceil() as i32);
+        if app.get_login_cooldown() != seconds {
+            app.set_login_cooldown(seconds);
+        }
+    });
+    timer
+}
+
+/// The terminal the greeter runs on, for the log
+fn tty() -> String {
+    if let Ok(vt) = std::env::var("XDG_VTNR") {
+        return format!("tty{vt}");
+    }
+    std::fs::read_link("/proc/self/fd/0")
+        .ok()
+        .and_then(|path| path.to_str().and_then(|p| p.strip_prefix("/dev/")).map(String::from))
+        .unwrap_or_else(|| "unknown tty".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(attempts: u32) -> ThrottleConfig {
+        ThrottleConfig {
+            attempts,
+            delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    #[test]
+    fn delays_double_up_to_the_maximum() {
+        let throttle = Throttle::new(config(3));
+        let mut throttle = throttle.borrow_mut();
+        let delays: Vec<Option<u64>> = (0..7)
+            .map(|_| throttle.failed("hey", "wrong password").map(|d| d.as_secs()))
+            .collect();
+        assert_eq!(delays, [None, None, Some(5), Some(10), Some(20), Some(30), Some(30)]);
+        assert!(throttle.remaining().is_some());
+    }
+
+    #[test]
+    fn success_resets_the_count() {
+        let throttle = Throttle::new(config(2));
+        let mut throttle = throttle.borrow_mut();
+        assert_eq!(throttle.failed("hey", "wrong password"), None);
+        assert!(throttle.failed("hey", "wrong password").is_some());
+        throttle.succeeded("hey");
+        assert_eq!(throttle.remaining(), None);
+        assert_eq!(throttle.failed("hey", "wrong password"), None);
+    }
+
+    #[test]
+    fn zero_attempts_disables_throttling() {
+        let throttle = Throttle::new(config(0));
+        let mut throttle = throttle.borrow_mut();
+        for _ in 0..10 {
+            assert_eq!(throttle.failed("hey", "wrong password"), None);
+        }
+        assert_eq!(throttle.remaining(), None);
+    }
+
+    #[test]
+    fn reads_config_keys() {
+        let mut config = ThrottleConfig::default();
+        assert_eq!(config.set("lockout_attempts", "5"), Ok(true));
+        assert_eq!(config.set("lockout_delay", "2"), Ok(true));
+        assert_eq!(config.set("lockout_max_delay", "60"), Ok(true));
+        assert_eq!(config.set("theme", "dark"), Ok(false));
+        assert!(config.set("lockout_delay", "soon").is_err());
+        assert_eq!(config.attempts, 5);
+        assert_eq!(config.delay, Duration::from_secs(2));
+        assert_eq!(config.max_delay, Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,81 @@
+//! Writing the choices into heyDM's config file and the completion marker.
+//!
+//! heyDM only starts the wizard when there is no config file, but the user
+//! may have created one meanwhile; the new sections are then appended, and
+//! heyDM's parser lets later keys override earlier ones.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+
+/// What the user picked
+#[derive(Debug, Clone)]
+pub struct Choices {
+    pub layout: String,
+    pub dark: bool,
+    /// "#rrggbb"
+    pub accent: String,
+}
+
+/// $XDG_CONFIG_HOME/heydm/config, like heyDM's Config::path
+pub fn config_path() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+        .join("heydm")
+        .join("config")
+}
+
+/// $XDG_STATE_HOME/heydm/welcome-done, like heyDM's welcome::marker_path
+pub fn marker_path() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+        .join("heydm")
+        .join("welcome-done")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    std::env::var(var)
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let home = std::env::var("HOME").unwrap_or_default();
+            PathBuf::from(home).join(fallback)
+        })
+}
+
+/// Append the choices to the config file, creating it if needed
+pub fn save(choices: &Choices) -> std::io::Result<()> {
+    let path = config_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let existing = std::fs::read_to_string(&path).unwrap_or_default();
+
+    let mut text = String::new();
+    if existing.is_empty() {
+        text.push_str("# heyDM configuration, started by hey-welcome\n");
+    } else if !existing.ends_with('\n') {
+        text.push('\n');
+    }
+    let _ = write!(
+        text,
+        "\n[keyboard]\nlayout = {}\n\n[appearance]\ncolor_scheme = {}\naccent = {}\n",
+        choices.layout,
+        if choices.dark { "dark" } else { "light" },
+        choices.accent,
+    );
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    file.write_all(text.as_bytes())
+}
+
+/// Record that the wizard ran, so heyDM does not start it again
+pub fn mark_done() -> std::io::Result<()> {
+    let path = marker_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, "")
+}
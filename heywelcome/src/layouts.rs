@@ -0,0 +1,80 @@
+//! Keyboard layouts offered by the wizard.
+//!
+//! The list comes from the `! layout` section of xkeyboard-config's
+//! evdev.lst ("  us              English (US)"). When the file is missing a
+//! short built-in list is used instead.
+
+use std::path::Path;
+
+const EVDEV_LST: &str = "/usr/share/X11/xkb/rules/evdev.lst";
+
+/// Used when xkeyboard-config is not installed
+const FALLBACK: &[(&str, &str)] = &[
+    ("us", "English (US)"),
+    ("gb", "English (UK)"),
+    ("de", "German"),
+    ("fr", "French"),
+    ("es", "Spanish"),
+    ("it", "Italian"),
+    ("pt", "Portuguese"),
+    ("br", "Portuguese (Brazil)"),
+    ("se", "Swedish"),
+    ("pl", "Polish"),
+    ("ru", "Russian"),
+    ("tr", "Turkish"),
+    ("jp", "Japanese"),
+];
+
+/// An XKB layout and its human-readable name
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub code: String,
+    pub name: String,
+}
+
+/// All layouts, sorted by name
+pub fn available() -> Vec<Layout> {
+    let mut layouts = std::fs::read_to_string(Path::new(EVDEV_LST))
+        .map(|content| parse_lst(&content))
+        .unwrap_or_default();
+    if layouts.is_empty() {
+        layouts = FALLBACK
+            .iter()
+            .map(|(code, name)| Layout {
+                code: code.to_string(),
+                name: name.to_string(),
+            })
+            .collect();
+    }
+    layouts.sort_by(|a, b| a.name.cmp(&b.name));
+    layouts
+}
+
+/// The entries of the `! layout` section
+fn parse_lst(content: &str) -> Vec<Layout> {
+    content
+        .lines()
+        .skip_while(|line| line.trim() != "! layout")
+        .skip(1)
+        .take_while(|line| !line.starts_with('!'))
+        .filter_map(|line| {
+            let (code, name) = line.trim().split_once(char::is_whitespace)?;
+            Some(Layout {
+                code: code.to_string(),
+                name: name.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The system layout from the X11 keyboard config that localectl writes,
+/// if one is set
+pub fn current() -> Option<String> {
+    let content = std::fs::read_to_string("/etc/X11/xorg.conf.d/00-keyboard.conf").ok()?;
+    content.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("Option")?.trim();
+        let value = rest.strip_prefix("\"XkbLayout\"")?.trim().trim_matches('"');
+        let first = value.split(',').next()?.trim();
+        (!first.is_empty()).then(|| first.to_string())
+    })
+}
@@ -0,0 +1,119 @@
+//! hey-welcome — the first-login wizard of heyOS.
+//!
+//! heyDM starts it when a user has neither a config file nor the completion
+//! marker (see heydm's welcome.rs). It asks for a keyboard layout, a dark or
+//! light style and an accent color, walks through the main keybindings and
+//! writes the result into heyDM's config, then has heyDM read it again with
+//! `heyctl reload`. Finishing and skipping both leave the marker behind.
+
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+
+use slint::{StandardListViewItem, VecModel};
+use tracing::{info, warn};
+
+mod config;
+mod layouts;
+
+slint::include_modules!();
+
+/// Accent presets offered on the appearance page; the first is heyDM's
+/// default crimson
+const ACCENTS: &[&str] = &[
+    "#d43b47", "#e66100", "#f5c211", "#2ec27e", "#1c71d8", "#813d9c", "#865e3c", "#77767b",
+];
+
+/// The keybinding tour: keys and what they do
+const KEYBINDINGS: &[(&str, &str)] = &[
+    ("Super + D", "Open the app launcher"),
+    ("Super + Enter", "Open a terminal"),
+    ("Super + Q  /  Alt + F4", "Close the window"),
+    ("Super + Tab", "Switch between windows"),
+    ("Super + ← → ↑ ↓", "Snap the window to a side, maximize or restore"),
+    ("Super + F", "Fullscreen"),
+    ("Super + M  /  Super + N", "Maximize / minimize"),
+    ("Super + 1 … 9", "Switch workspace (add Shift to move the window)"),
+    ("Super + T", "Tile the workspace automatically"),
+    ("Super + Shift + H / L", "Shrink or grow the main tiled window"),
+    ("Super + Shift + Esc", "Task manager"),
+    ("Super + L", "Lock the screen"),
+];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt::init();
+
+    let app = WelcomeWindow::new()?;
+
+    let layouts = layouts::available();
+    let current = layouts::current().unwrap_or_else(|| "us".to_string());
+    let selected = layouts.iter().position(|l| l.code == current).unwrap_or(0);
+    let names: Vec<StandardListViewItem> = layouts
+        .iter()
+        .map(|l| StandardListViewItem::from(format!("{} ({})", l.name, l.code).as_str()))
+        .collect();
+    app.set_layouts(Rc::new(VecModel::from(names)).into());
+    app.set_layout_index(selected as i32);
+
+    let accents: Vec<slint::Color> = ACCENTS.iter().filter_map(|hex| parse_color(hex)).collect();
+    app.set_accents(Rc::new(VecModel::from(accents)).into());
+
+    let bindings: Vec<Keybinding> = KEYBINDINGS
+        .iter()
+        .map(|(keys, action)| Keybinding {
+            keys: (*keys).into(),
+            action: (*action).into(),
+        })
+        .collect();
+    app.set_keybindings(Rc::new(VecModel::from(bindings)).into());
+
+    let app_handle = app.as_weak();
+    app.on_finish(move || {
+        let Some(app) = app_handle.upgrade() else { return };
+        let layout_index = app.get_layout_index().max(0) as usize;
+        let accent_index = app.get_accent_index().max(0) as usize;
+        let choices = config::Choices {
+            layout: layouts
+                .get(layout_index)
+                .map_or_else(|| "us".to_string(), |l| l.code.clone()),
+            dark: app.get_dark(),
+            accent: ACCENTS.get(accent_index).unwrap_or(&ACCENTS[0]).to_string(),
+        };
+        info!("Saving {choices:?} to {}", config::config_path().display());
+        if let Err(e) = config::save(&choices) {
+            warn!("Failed to write the heyDM config: {e}");
+            app.set_error_message(format!("Could not save your settings: {e}").into());
+            return;
+        }
+        reload_heydm();
+        done();
+    });
+    app.on_skip(|| {
+        info!("Welcome skipped");
+        done();
+    });
+
+    app.run()?;
+    Ok(())
+}
+
+/// Have the running heyDM apply the new settings
+fn reload_heydm() {
+    match Command::new("heyctl").arg("reload").stdout(Stdio::null()).status() {
+        Ok(status) if status.success() => info!("heyDM reloaded its config"),
+        Ok(status) => warn!("heyctl reload failed ({status}); the settings apply at the next login"),
+        Err(e) => warn!("Could not run heyctl: {e}; the settings apply at the next login"),
+    }
+}
+
+/// Leave the marker and quit
+fn done() {
+    if let Err(e) = config::mark_done() {
+        warn!("Failed to write {}: {e}", config::marker_path().display());
+    }
+    let _ = slint::quit_event_loop();
+}
+
+fn parse_color(hex: &str) -> Option<slint::Color> {
+    let value = u32::from_str_radix(hex.strip_prefix('#')?, 16).ok()?;
+    Some(slint::Color::from_argb_encoded(0xff00_0000 | value))
+}